@@ -0,0 +1,614 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::ast_owned;
+use crate::diagnostics::{CompileError, CompileWarning, Diagnostic};
+use crate::driver::{self, Emit, Options};
+use crate::host::Host;
+use crate::host_binding::IntoHostFunction;
+use crate::interp::RuntimeError;
+use crate::render::DiagnosticRenderer;
+use crate::scanner::Span;
+use crate::snapshot::Snapshot;
+use crate::source_map::SourceMap;
+use crate::structure;
+use crate::timing::PassTiming;
+use crate::vm::{self, ExecutionLimit, ExecutionLimits, HostCall, HostFunction, Progress};
+
+/// Source files of a program, and how to compile them.
+///
+/// ```
+/// let mut session = sophia::Session::new().with_emit(sophia::Emit::C);
+/// session.add_file("main.sph", "main :: () -> i32 { answer() }");
+/// session.add_file("answer.sph", "answer :: () -> i32 { 42 }");
+///
+/// let compiled_program = session.compile().unwrap();
+/// assert!(compiled_program.code.contains("answer"));
+/// ```
+#[derive(Default)]
+pub struct Session {
+    source_map: SourceMap,
+    options: Options,
+    /// Functions registered with `Session::register_fn`, which are shared
+    /// with every VM that runs the program.
+    host_functions: Vec<(String, Rc<RefCell<HostFunction>>)>,
+}
+
+/// Code generated for a program, along with the warnings compiling it gave.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CompiledProgram {
+    /// Generated code, in the format given by `Session::with_emit`.
+    pub code: String,
+    pub warnings: Vec<Message>,
+}
+
+/// Outcome of running a program with the interpreter.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Execution {
+    /// Value returned by `main`, or zero if it returns `()`, unless the
+    /// program failed or was stopped while running.
+    pub exit_code: Result<i32, ExecutionError>,
+    pub warnings: Vec<Message>,
+}
+
+/// Why a program didn't run until `main` returned.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ExecutionError {
+    /// The program took more steps than `Session::with_fuel` allows.
+    OutOfFuel,
+    /// The program nested more calls than `Session::with_max_call_depth`
+    /// allows.
+    CallDepthExceeded,
+    /// The program needed more room for its values than the VM has, or
+    /// nested calls and expressions deeper than the interpreter's stack
+    /// holds.
+    StackOverflow,
+    /// The program ran for longer than `Session::with_timeout` allows.
+    TimedOut,
+    /// The program failed, e.g., by dividing by zero.
+    Failed(Message),
+}
+
+/// Program compiled to bytecode and loaded into the VM, which embedders call
+/// into function by function, rather than only running `main`, and whose
+/// executions they can pause and resume.
+///
+/// ```
+/// let mut session = sophia::Session::new();
+/// session.add_file("main.sph", "double :: (n: i32) -> i32 { n * 2 }\nmain :: () {}");
+///
+/// let mut vm = session.load_vm().unwrap();
+/// assert_eq!(vm.call("double", &[21]), Ok(Some(42)));
+///
+/// let mut snapshot = vm.start("double", &[4]).unwrap();
+/// assert_eq!(vm.resume(&mut snapshot, 1), Ok(sophia::Progress::Paused));
+/// assert_eq!(
+///     vm.resume(&mut snapshot, u64::MAX),
+///     Ok(sophia::Progress::Finished(Some(8)))
+/// );
+/// ```
+pub struct Vm {
+    vm: vm::Vm,
+    /// Files the program was compiled from, which runtime errors are located
+    /// in.
+    source_map: SourceMap,
+}
+
+/// Errors and warnings that compiling a program gave. They're displayed the
+/// way the `sophia` binary shows them, with the lines of code they're about.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostics {
+    pub errors: Vec<Message>,
+    pub warnings: Vec<Message>,
+    rendered: String,
+}
+
+/// Error, warning or runtime failure, as text.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Message {
+    pub text: String,
+    /// Code the message is about, if it's not about the whole program.
+    pub location: Option<Location>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Location {
+    /// Name the file was added to the session with.
+    pub file: String,
+    /// Line of the code's start, counting from one.
+    pub line: usize,
+    /// Column of the code's start, counting characters from one.
+    pub col: usize,
+    /// Byte offsets of the code in its file.
+    pub range: Range<usize>,
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    /// Adds a file of the program. Files are compiled as one program, in the
+    /// order they're added.
+    pub fn add_file(&mut self, name: &str, source_code: &str) {
+        self.source_map.add_file(name, source_code);
+    }
+
+    /// What `Session::compile` generates, which is x86-64 assembly unless
+    /// told otherwise.
+    pub fn with_emit(mut self, emit: Emit) -> Session {
+        self.options.emit = emit;
+        self
+    }
+
+    /// Whether the files are a script, whose top-level statements make up an
+    /// implicit `main` function.
+    pub fn with_script_mode(mut self, script_mode: bool) -> Session {
+        self.options.script_mode = script_mode;
+        self
+    }
+
+    /// How many steps `Session::run` lets the program take, where a step is
+    /// an instruction of the VM, or an expression that the interpreter
+    /// evaluates. Programs may take any number of steps unless told
+    /// otherwise.
+    ///
+    /// ```
+    /// let mut session = sophia::Session::new().with_fuel(1000);
+    /// session.add_file("main.sph", "main :: () { loop { } }");
+    ///
+    /// let exit_code = session.run().unwrap().exit_code;
+    /// assert_eq!(exit_code, Err(sophia::ExecutionError::OutOfFuel));
+    /// ```
+    pub fn with_fuel(mut self, fuel: u64) -> Session {
+        self.options.execution_limits.max_instructions = fuel;
+        self
+    }
+
+    /// How deep `Session::run` lets calls nest, which is 10000 unless told
+    /// otherwise.
+    pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Session {
+        self.options.execution_limits.max_call_depth = max_call_depth;
+        self
+    }
+
+    /// How long `Session::run` lets the program run for. Programs may run for
+    /// any time unless told otherwise.
+    pub fn with_timeout(mut self, timeout: Duration) -> Session {
+        self.options.execution_limits.timeout = Some(timeout);
+        self
+    }
+
+    /// What the compiler may learn from the system it runs on. Unless told
+    /// otherwise, randomness comes from a fixed seed, so that compiling the
+    /// same files always gives the same results.
+    ///
+    /// ```
+    /// struct SeededHost(u64);
+    ///
+    /// impl sophia::Host for SeededHost {
+    ///     fn random_u64(&self) -> u64 {
+    ///         self.0
+    ///     }
+    /// }
+    ///
+    /// let mut session = sophia::Session::new().with_host(SeededHost(7));
+    /// session.add_file("main.sph", "main :: () -> i32 { 42 }");
+    ///
+    /// assert_eq!(session.run().unwrap().exit_code, Ok(42));
+    /// ```
+    pub fn with_host(mut self, host: impl Host + 'static) -> Session {
+        self.options.host = Some(Arc::new(host));
+        self
+    }
+
+    /// Whether to time the passes that compile the program, which
+    /// `Session::pass_timings` gives.
+    ///
+    /// ```
+    /// let mut session = sophia::Session::new().with_time_passes(true);
+    /// session.add_file("main.sph", "main :: () -> i32 { 42 }");
+    /// session.compile().unwrap();
+    ///
+    /// let pass_timings = session.pass_timings();
+    /// assert_eq!(pass_timings[0].pass, sophia::Pass::Lex);
+    /// assert_eq!(pass_timings.last().unwrap().pass, sophia::Pass::Codegen);
+    /// ```
+    pub fn with_time_passes(mut self, time_passes: bool) -> Session {
+        self.options.pass_timings = time_passes.then(Rc::default);
+        self
+    }
+
+    /// Timings of the passes run since they were last asked for, in the order
+    /// they were run, if `Session::with_time_passes` is set. Passes after one
+    /// that gave errors aren't run.
+    pub fn pass_timings(&self) -> Vec<PassTiming> {
+        self.options
+            .pass_timings
+            .as_ref()
+            .map_or(vec![], |pass_timings| pass_timings.take())
+    }
+
+    /// Registers the Rust function as one that the program can call without
+    /// declaring it, replacing the one of the same name registered before, if
+    /// any. Its arguments and value are converted from and to integers, and
+    /// `bool`s are true unless zero, just like conditions.
+    ///
+    /// Programs are run on the VM instead of the interpreter once they have
+    /// functions registered, and can't be compiled to native code.
+    ///
+    /// ```
+    /// let mut session = sophia::Session::new();
+    /// session.add_file("main.sph", "main :: () -> i32 { if is_odd(roll()) { 1 } else { 2 } }");
+    /// session.register_fn("roll", || 3);
+    /// session.register_fn("is_odd", |n: i32| n % 2 != 0);
+    ///
+    /// assert_eq!(session.run().unwrap().exit_code, Ok(1));
+    /// ```
+    pub fn register_fn<Params>(&mut self, name: &str, function: impl IntoHostFunction<Params>) {
+        let binding = function.bind(name);
+
+        self.options.host_functions.retain(|decl| decl.name != name);
+        self.options.host_functions.push(binding.decl);
+
+        self.host_functions
+            .retain(|(other_name, _)| other_name != name);
+        self.host_functions
+            .push((name.to_owned(), Rc::new(RefCell::new(binding.function))));
+    }
+
+    /// Compiles the program to bytecode and loads it into a VM, along with the
+    /// functions registered with `Session::register_fn` and the limits the
+    /// session sets.
+    pub fn load_vm(&self) -> Result<Vm, Diagnostics> {
+        self.compile_to_vm()
+            .map(|(vm, _)| vm)
+            .map_err(|diagnostic| self.diagnostics(&diagnostic))
+    }
+
+    /// Parses the program without analyzing it, into a syntax tree that
+    /// tools can keep. Only code that can't be parsed is an error.
+    pub fn parse(&self) -> Result<ast_owned::Program, Diagnostics> {
+        driver::parse_owned(self.source_map.clone(), &self.options)
+            .map_err(|diagnostic| self.diagnostics(&diagnostic))
+    }
+
+    /// Parses and analyzes the program without generating code for it.
+    pub fn check(&self) -> Diagnostics {
+        let diagnostic = driver::check(self.source_map.clone(), &self.options);
+
+        self.diagnostics(&diagnostic)
+    }
+
+    pub fn compile(&self) -> Result<CompiledProgram, Diagnostics> {
+        match driver::compile(self.source_map.clone(), &self.options) {
+            Ok(compiled_program) => Ok(CompiledProgram {
+                code: compiled_program.code,
+                warnings: self.warning_messages(&compiled_program.warnings),
+            }),
+            Err(diagnostic) => Err(self.diagnostics(&diagnostic)),
+        }
+    }
+
+    /// Runs the program with the interpreter, or with the VM if it has
+    /// functions registered.
+    pub fn run(&self) -> Result<Execution, Diagnostics> {
+        if !self.host_functions.is_empty() {
+            return self.run_on_vm();
+        }
+
+        match driver::run(self.source_map.clone(), &self.options) {
+            Ok(execution) => Ok(Execution {
+                exit_code: execution
+                    .exit_code
+                    .map_err(|error| self.execution_error(&error)),
+                warnings: self.warning_messages(&execution.warnings),
+            }),
+            Err(diagnostic) => Err(self.diagnostics(&diagnostic)),
+        }
+    }
+
+    fn run_on_vm(&self) -> Result<Execution, Diagnostics> {
+        let (mut vm, warnings) = self
+            .compile_to_vm()
+            .map_err(|diagnostic| self.diagnostics(&diagnostic))?;
+
+        Ok(Execution {
+            exit_code: vm.run_main(),
+            warnings: self.warning_messages(&warnings),
+        })
+    }
+
+    fn compile_to_vm(&self) -> Result<(Vm, Vec<CompileWarning>), Diagnostic> {
+        let compiled_bytecode = driver::compile_bytecode(self.source_map.clone(), &self.options)?;
+
+        let mut vm = vm::Vm::new(compiled_bytecode.module);
+        vm.set_limits(self.options.execution_limits);
+
+        for (name, function) in &self.host_functions {
+            let function = Rc::clone(function);
+            vm.register_host_function(name.as_str(), move |args| (function.borrow_mut())(args));
+        }
+
+        let vm = Vm {
+            vm,
+            source_map: self.source_map.clone(),
+        };
+
+        Ok((vm, compiled_bytecode.warnings))
+    }
+
+    fn diagnostics(&self, diagnostic: &Diagnostic) -> Diagnostics {
+        Diagnostics {
+            errors: diagnostic
+                .errors
+                .iter()
+                .map(|error| self.error_message(error))
+                .collect(),
+            warnings: self.warning_messages(&diagnostic.warnings),
+            rendered: DiagnosticRenderer::new(&self.source_map).render(diagnostic),
+        }
+    }
+
+    fn error_message(&self, error: &CompileError) -> Message {
+        let span = (!error.is_about_program()).then(|| error.span());
+
+        message(&self.source_map, error.to_string(), span)
+    }
+
+    fn warning_messages(&self, warnings: &[CompileWarning]) -> Vec<Message> {
+        warnings
+            .iter()
+            .map(|warning| message(&self.source_map, warning.to_string(), Some(warning.span())))
+            .collect()
+    }
+
+    fn execution_error(&self, error: &RuntimeError) -> ExecutionError {
+        execution_error(&self.source_map, error)
+    }
+}
+
+impl Vm {
+    /// Bounds every call into the VM from now on, each on its own.
+    pub fn set_limits(&mut self, limits: ExecutionLimits) {
+        self.vm.set_limits(limits);
+    }
+
+    /// Makes runs deterministic, or not.
+    ///
+    /// Programs only ever observe the world through host functions, so in
+    /// deterministic mode, the VM records every call to them, along with what
+    /// they returned. Replaying those calls with `Vm::replay_host_calls` runs
+    /// the program exactly the same way again, down to the number of
+    /// instructions it executes.
+    ///
+    /// ```
+    /// let mut session = sophia::Session::new();
+    /// session.add_file("main.sph", "main :: () -> i32 { roll() + roll() }");
+    /// session.register_fn("roll", || 4);
+    ///
+    /// let mut vm = session.load_vm().unwrap();
+    /// vm.set_deterministic(true);
+    /// assert_eq!(vm.run_main(), Ok(8));
+    ///
+    /// session.register_fn("roll", || 6);
+    ///
+    /// let mut replaying_vm = session.load_vm().unwrap();
+    /// replaying_vm.replay_host_calls(vm.host_calls().to_vec());
+    /// assert_eq!(replaying_vm.run_main(), Ok(8));
+    /// assert_eq!(replaying_vm.executed_instructions(), vm.executed_instructions());
+    /// ```
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.vm.set_deterministic(deterministic);
+    }
+
+    /// Instructions executed by every run so far, in any mode, which is how
+    /// long they took regardless of the machine.
+    pub fn executed_instructions(&self) -> u64 {
+        self.vm.executed_instructions()
+    }
+
+    /// Calls to host functions recorded in deterministic mode, in the order
+    /// they were made.
+    pub fn host_calls(&self) -> &[HostCall] {
+        self.vm.host_calls()
+    }
+
+    /// Answers calls to host functions with the recorded ones instead of
+    /// calling them. A call that doesn't match the next recorded one stops
+    /// the program.
+    pub fn replay_host_calls(&mut self, host_calls: Vec<HostCall>) {
+        self.vm.replay_host_calls(host_calls);
+    }
+
+    /// Runs the program's `main` function, returning its value as an exit
+    /// code, or zero if it returns `()`.
+    pub fn run_main(&mut self) -> Result<i32, ExecutionError> {
+        self.vm
+            .run_main()
+            .map_err(|error| execution_error(&self.source_map, &error))
+    }
+
+    /// Runs the function of the name, passing it the arguments for its
+    /// parameters of type `i32`. Returns the function's value, or `None` if it
+    /// returns `()`.
+    pub fn call(&mut self, name: &str, args: &[i32]) -> Result<Option<i32>, ExecutionError> {
+        self.vm
+            .call(name, args)
+            .map_err(|error| execution_error(&self.source_map, &error))
+    }
+
+    /// Prepares to run the function of the name, just like `Vm::call` does,
+    /// but without running any of it until the execution is resumed.
+    pub fn start(&mut self, name: &str, args: &[i32]) -> Result<Snapshot, ExecutionError> {
+        self.vm
+            .start(name, args)
+            .map_err(|error| execution_error(&self.source_map, &error))
+    }
+
+    /// Runs the paused execution for at most the given number of
+    /// instructions, updating the snapshot to where it stopped, even if it
+    /// stopped on an error.
+    pub fn resume(
+        &mut self,
+        snapshot: &mut Snapshot,
+        max_instructions: u64,
+    ) -> Result<Progress, ExecutionError> {
+        self.vm
+            .resume(snapshot, max_instructions)
+            .map_err(|error| execution_error(&self.source_map, &error))
+    }
+}
+
+impl Diagnostics {
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.rendered)
+    }
+}
+
+impl Error for Diagnostics {}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutionError::OutOfFuel => write!(f, "program ran out of fuel"),
+            ExecutionError::CallDepthExceeded => {
+                write!(f, "program exceeded the limit of nested calls")
+            }
+            ExecutionError::StackOverflow => write!(f, "program overflowed the stack"),
+            ExecutionError::TimedOut => write!(f, "program ran out of time"),
+            ExecutionError::Failed(message) => f.write_str(&message.text),
+        }
+    }
+}
+
+impl Error for ExecutionError {}
+
+fn execution_error(source_map: &SourceMap, error: &RuntimeError) -> ExecutionError {
+    match error {
+        RuntimeError::ExecutionLimitExceeded { limit } => match limit {
+            ExecutionLimit::Instructions(_) => ExecutionError::OutOfFuel,
+            ExecutionLimit::CallDepth(_) => ExecutionError::CallDepthExceeded,
+            ExecutionLimit::StackSize(_) | ExecutionLimit::NativeStack(_) => {
+                ExecutionError::StackOverflow
+            }
+            ExecutionLimit::Timeout(_) => ExecutionError::TimedOut,
+        },
+        _ => ExecutionError::Failed(message(source_map, error.to_string(), error.span())),
+    }
+}
+
+fn message(source_map: &SourceMap, text: String, span: Option<Span>) -> Message {
+    let location = span.map(|span| {
+        let (file_id, line, col) = source_map.lookup_line_col(span.start);
+        let (_, range) = source_map.file_range(span);
+
+        Location {
+            file: source_map.file_name(file_id).to_owned(),
+            line: line.0,
+            col: col.0,
+            range,
+        }
+    });
+
+    Message { text, location }
+}
+
+/// Compiles a program of a single file to x86-64 assembly.
+///
+/// ```
+/// let compiled_program = sophia::compile_str("main :: () -> i32 { 42 }").unwrap();
+/// assert!(compiled_program.code.contains("main"));
+///
+/// let diagnostics = sophia::compile_str("main :: () -> i32 { x }").unwrap_err();
+/// assert_eq!(diagnostics.errors[0].text, "cannot find `x` in this scope");
+/// ```
+pub fn compile_str(source_code: &str) -> Result<CompiledProgram, Diagnostics> {
+    let mut session = Session::new();
+    session.add_file("<input>", source_code);
+
+    session.compile()
+}
+
+/// Parses a single expression, rather than a program, e.g., one typed into a
+/// REPL, or evaluated by a debugger. The expression may be followed by a `;`,
+/// but by no other code.
+///
+/// ```
+/// use sophia::ast_owned::{BinaryOp, Expr};
+///
+/// let expr = sophia::parse_expr_fragment("x * 2").unwrap();
+/// assert!(matches!(expr, Expr::Binary(binary_expr) if binary_expr.op == BinaryOp::Mul));
+///
+/// let diagnostics = sophia::parse_expr_fragment("x * 2 }").unwrap_err();
+/// assert_eq!(
+///     diagnostics.errors[0].text,
+///     "expected one of `.`, `[`, `as`, `+`, `-`, `*`, `/`, `%`, `;`, found `}`"
+/// );
+/// ```
+pub fn parse_expr_fragment(source_code: &str) -> Result<ast_owned::Expr, Diagnostics> {
+    let session = fragment_session(source_code);
+
+    driver::parse_owned_expr(session.source_map.clone(), &session.options)
+        .map_err(|diagnostic| session.diagnostics(&diagnostic))
+}
+
+/// Parses a single declaration, rather than a program, e.g., one in an
+/// example of the documentation.
+pub fn parse_decl_fragment(source_code: &str) -> Result<ast_owned::Decl, Diagnostics> {
+    let session = fragment_session(source_code);
+
+    driver::parse_owned_decl(session.source_map.clone(), &session.options)
+        .map_err(|diagnostic| session.diagnostics(&diagnostic))
+}
+
+/// Parses both programs, and diffs their syntax trees with
+/// [`diff_structures`], so that tools that rewrite programs, e.g.,
+/// formatters, can check that they kept their meaning. It's `None` if they're
+/// the same parse, however their source code is laid out.
+///
+/// ```
+/// let diff = sophia::diff_parses("main :: () -> i32 { (1 + 2) }", "main::()->i32{1+2}");
+/// assert_eq!(diff.unwrap(), None);
+///
+/// let diff = sophia::diff_parses("main :: () -> i32 { 1 }", "main :: () -> i32 { 2 }");
+/// assert!(diff.unwrap().unwrap().contains("-       const 1\n+       const 2"));
+/// ```
+pub fn diff_parses(
+    expected_source: &str,
+    found_source: &str,
+) -> Result<Option<String>, Diagnostics> {
+    let render = |file_name: &str, source_code: &str| {
+        let mut session = Session::new().with_emit(Emit::Ast);
+        session.add_file(file_name, source_code);
+
+        session
+            .compile()
+            .map(|compiled_program| compiled_program.code)
+    };
+
+    let expected = render("<expected>", expected_source)?;
+    let found = render("<found>", found_source)?;
+
+    Ok(structure::diff_structures(&expected, &found))
+}
+
+/// Session whose only file is the fragment, so that diagnostics about the
+/// fragment are located in it.
+fn fragment_session(source_code: &str) -> Session {
+    let mut session = Session::new();
+    session.add_file("<fragment>", source_code);
+
+    session
+}
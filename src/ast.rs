@@ -1,4 +1,7 @@
+use std::fmt;
+
 use crate::interner::Symbol;
+use crate::scanner::Span;
 
 #[derive(Clone, Copy)]
 pub(crate) struct Program<'ctx> {
@@ -8,38 +11,109 @@ pub(crate) struct Program<'ctx> {
 #[derive(Clone, Copy)]
 pub(crate) struct Decl<'ctx> {
     pub(crate) identifier: Symbol,
+    pub(crate) identifier_span: Span,
     pub(crate) value: &'ctx Expr<'ctx>,
 }
 
 #[derive(Clone, Copy)]
 pub(crate) enum Expr<'ctx> {
-    Const(Const),
+    Const(ConstExpr),
     BindRef(BindRef),
     BindDef(BindDef<'ctx>),
     Function(Function<'ctx>),
+    Extern(ExternFn<'ctx>),
+    Struct(StructDef<'ctx>),
+    StructLit(StructLitExpr<'ctx>),
+    Field(FieldExpr<'ctx>),
+    Enum(EnumDef<'ctx>),
+    Variant(VariantExpr<'ctx>),
+    Tuple(TupleExpr<'ctx>),
+    TupleField(TupleFieldExpr<'ctx>),
+    TupleBindDef(TupleBindDef<'ctx>),
+    Array(ArrayExpr<'ctx>),
+    Index(IndexExpr<'ctx>),
+    IndexAssign(IndexAssignExpr<'ctx>),
     If(IfExpr<'ctx>),
+    Match(MatchExpr<'ctx>),
     For(ForExpr<'ctx>),
-    Break,
-    Continue,
+    Break(BreakExpr<'ctx>),
+    Continue(ContinueExpr),
     Compound(CompoundExpr<'ctx>),
     Semi(&'ctx Expr<'ctx>),
-    FnCall(FnCallExpr),
+    FnCall(FnCallExpr<'ctx>),
+    Binary(BinaryExpr<'ctx>),
+    Cast(CastExpr<'ctx>),
+    Paren(ParenExpr<'ctx>),
+}
+
+impl Expr<'_> {
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            Expr::Const(const_expr) => const_expr.span,
+            Expr::BindRef(bind_ref) => bind_ref.span,
+            Expr::BindDef(bind_def) => bind_def.span,
+            Expr::Function(function) => function.span,
+            Expr::Extern(extern_fn) => extern_fn.span,
+            Expr::Struct(struct_def) => struct_def.span,
+            Expr::StructLit(struct_lit_expr) => struct_lit_expr.span,
+            Expr::Field(field_expr) => field_expr.span,
+            Expr::Enum(enum_def) => enum_def.span,
+            Expr::Variant(variant_expr) => variant_expr.span,
+            Expr::Tuple(tuple_expr) => tuple_expr.span,
+            Expr::TupleField(tuple_field_expr) => tuple_field_expr.span,
+            Expr::TupleBindDef(tuple_bind_def) => tuple_bind_def.span,
+            Expr::Array(array_expr) => array_expr.span,
+            Expr::Index(index_expr) => index_expr.span,
+            Expr::IndexAssign(index_assign_expr) => index_assign_expr.span,
+            Expr::If(if_expr) => if_expr.span,
+            Expr::Match(match_expr) => match_expr.span,
+            Expr::For(for_expr) => for_expr.span,
+            Expr::Break(break_expr) => break_expr.span,
+            Expr::Continue(continue_expr) => continue_expr.span,
+            Expr::Compound(compound_expr) => compound_expr.span,
+            Expr::Semi(expr) => expr.span(),
+            Expr::FnCall(fn_call_expr) => fn_call_expr.span,
+            Expr::Binary(binary_expr) => binary_expr.span,
+            Expr::Cast(cast_expr) => cast_expr.span,
+            Expr::Paren(paren_expr) => paren_expr.span,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct ConstExpr {
+    pub(crate) value: Const,
+    pub(crate) span: Span,
 }
 
 #[derive(Clone, Copy)]
 pub(crate) enum Const {
-    IntegerConstant { value: i32 },
+    IntegerConstant {
+        value: i32,
+    },
+    /// Integer constant suffixed with a type other than `i32`, as in `42u8`.
+    SuffixedIntegerConstant {
+        value: u64,
+        ty: Type,
+    },
 }
 
 #[derive(Clone, Copy)]
 pub(crate) struct BindRef {
     pub(crate) identifier: Symbol,
+    pub(crate) span: Span,
 }
 
+/// Identifier that, when bound to, discards the value instead.
+pub(crate) const DISCARD_IDENTIFIER: &str = "_";
+
 #[derive(Clone, Copy)]
 pub(crate) struct BindDef<'ctx> {
     pub(crate) identifier: Symbol,
+    /// Type that the value is annotated with, as in `x : i32 = 0`, if any.
+    pub(crate) ty: Option<Type>,
     pub(crate) value: &'ctx Expr<'ctx>,
+    pub(crate) span: Span,
 }
 
 #[derive(Clone, Copy)]
@@ -47,18 +121,315 @@ pub(crate) struct Function<'ctx> {
     pub(crate) return_type: Type,
     pub(crate) parameters: &'ctx [Param],
     pub(crate) body: CompoundExpr<'ctx>,
+    pub(crate) span: Span,
 }
 
+/// Function that's defined outside of the program, in code that follows the
+/// platform's C calling convention, as in `extern putchar :: (c: i32) -> i32;`.
+/// Like structs and enums, it's only declared at the top level.
 #[derive(Clone, Copy)]
-pub(crate) struct Param {
-    identifier: Symbol,
-    ty: Type,
+pub(crate) struct ExternFn<'ctx> {
+    pub(crate) return_type: Type,
+    pub(crate) parameters: &'ctx [Param],
+    /// Span from the `extern` keyword to the `;`.
+    pub(crate) span: Span,
 }
 
 #[derive(Clone, Copy)]
+pub(crate) struct Param {
+    pub(crate) identifier: Symbol,
+    pub(crate) ty: Type,
+    pub(crate) span: Span,
+}
+
+/// Function that programs can call without declaring it, as the embedder of
+/// the VM provides it instead.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct HostFnDecl {
+    pub(crate) name: String,
+    pub(crate) param_types: Vec<Type>,
+    pub(crate) return_type: Type,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub(crate) enum Type {
     Unit,
+    I8,
+    I16,
     I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    /// Unsigned integer as wide as an address, which is 64 bits.
+    Usize,
+    /// Struct or enum declared at the top level with this name.
+    Named(&'static str),
+    /// Types of a tuple's elements, in order. Tuples have at least one
+    /// element, as `()` is the unit type.
+    Tuple(&'static [Type]),
+    /// Fixed number of elements of the same type, as in `[i32; 4]`.
+    Array {
+        element: &'static Type,
+        len: usize,
+    },
+    /// Function that takes arguments of these types, in order, as in
+    /// `(i32, i32) -> i32`.
+    Function {
+        params: &'static [Type],
+        return_type: &'static Type,
+    },
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Unit => write!(f, "()"),
+            Type::I8 => write!(f, "i8"),
+            Type::I16 => write!(f, "i16"),
+            Type::I32 => write!(f, "i32"),
+            Type::I64 => write!(f, "i64"),
+            Type::U8 => write!(f, "u8"),
+            Type::U16 => write!(f, "u16"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::Usize => write!(f, "usize"),
+            Type::Named(name) => write!(f, "{}", name),
+            Type::Tuple([element_ty]) => write!(f, "({},)", element_ty),
+            Type::Tuple(element_types) => {
+                write!(f, "(")?;
+
+                for (idx, element_ty) in element_types.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{}", element_ty)?;
+                }
+
+                write!(f, ")")
+            }
+            Type::Array { element, len } => write!(f, "[{}; {}]", element, len),
+            Type::Function {
+                params,
+                return_type,
+            } => {
+                write!(f, "(")?;
+
+                for (idx, param_ty) in params.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{}", param_ty)?;
+                }
+
+                write!(f, ") -> {}", return_type)
+            }
+        }
+    }
+}
+
+impl Type {
+    /// Integer types, in the order their keywords are listed in.
+    pub(crate) const INTS: [Type; 9] = [
+        Type::I8,
+        Type::I16,
+        Type::I32,
+        Type::I64,
+        Type::U8,
+        Type::U16,
+        Type::U32,
+        Type::U64,
+        Type::Usize,
+    ];
+
+    pub(crate) fn is_int(self) -> bool {
+        self.int_range().is_some()
+    }
+
+    /// Smallest and largest values of the integer type, or `None` if the type
+    /// isn't of integers. `i32` is 32 bits wide here, whatever the integer
+    /// profile makes of it.
+    pub(crate) fn int_range(self) -> Option<(i128, i128)> {
+        let range = match self {
+            Type::I8 => (i8::MIN.into(), i8::MAX.into()),
+            Type::I16 => (i16::MIN.into(), i16::MAX.into()),
+            Type::I32 => (i32::MIN.into(), i32::MAX.into()),
+            Type::I64 => (i64::MIN.into(), i64::MAX.into()),
+            Type::U8 => (0, u8::MAX.into()),
+            Type::U16 => (0, u16::MAX.into()),
+            Type::U32 => (0, u32::MAX.into()),
+            Type::U64 | Type::Usize => (0, u64::MAX.into()),
+            Type::Unit
+            | Type::Named(_)
+            | Type::Tuple(_)
+            | Type::Array { .. }
+            | Type::Function { .. } => return None,
+        };
+
+        Some(range)
+    }
+
+    /// Whether values of this type can be used where values of the other
+    /// type are expected, with no conversion but widening, which is when
+    /// both are integers and every value of this one is one of the other.
+    /// `usize` is only as wide as `u32` is known to be, so that programs work
+    /// the same wherever addresses are narrower, and `usize` only widens into
+    /// itself.
+    pub(crate) fn widens_to(self, other: Type) -> bool {
+        if self == other {
+            return true;
+        }
+
+        if self == Type::Usize {
+            return false;
+        }
+
+        let other = if other == Type::Usize {
+            Type::U32
+        } else {
+            other
+        };
+
+        match (self.int_range(), other.int_range()) {
+            (Some((min, max)), Some((other_min, other_max))) => {
+                other_min <= min && max <= other_max
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Declaration of a struct's fields, which can only be the value of a
+/// top-level declaration, as in `Point :: struct { x: i32, y: i32 }`.
+#[derive(Clone, Copy)]
+pub(crate) struct StructDef<'ctx> {
+    pub(crate) fields: &'ctx [FieldDef],
+    pub(crate) span: Span,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct FieldDef {
+    pub(crate) identifier: Symbol,
+    pub(crate) ty: Type,
+    pub(crate) span: Span,
+}
+
+/// Value of a struct made of the values of its fields, as in
+/// `Point { x: 1, y: 2 }`.
+#[derive(Clone, Copy)]
+pub(crate) struct StructLitExpr<'ctx> {
+    pub(crate) identifier: Symbol,
+    pub(crate) identifier_span: Span,
+    pub(crate) fields: &'ctx [FieldInit<'ctx>],
+    pub(crate) span: Span,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct FieldInit<'ctx> {
+    pub(crate) identifier: Symbol,
+    pub(crate) identifier_span: Span,
+    pub(crate) value: &'ctx Expr<'ctx>,
+}
+
+/// Access to a field of a struct value, as in `point.x`.
+#[derive(Clone, Copy)]
+pub(crate) struct FieldExpr<'ctx> {
+    pub(crate) base: &'ctx Expr<'ctx>,
+    pub(crate) identifier: Symbol,
+    pub(crate) identifier_span: Span,
+    pub(crate) span: Span,
+}
+
+/// Declaration of an enum's variants, which can only be the value of a
+/// top-level declaration, as in `Shape :: enum { Circle(i32), Square(i32) }`.
+#[derive(Clone, Copy)]
+pub(crate) struct EnumDef<'ctx> {
+    pub(crate) variants: &'ctx [VariantDef],
+    pub(crate) span: Span,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct VariantDef {
+    pub(crate) identifier: Symbol,
+    /// Type of the value that the variant holds, if it holds one.
+    pub(crate) payload: Option<Type>,
+    pub(crate) span: Span,
+}
+
+/// Value of an enum's variant, along with the value it holds, if any, as in
+/// `Shape::Circle(2)`.
+#[derive(Clone, Copy)]
+pub(crate) struct VariantExpr<'ctx> {
+    pub(crate) enum_identifier: Symbol,
+    pub(crate) enum_identifier_span: Span,
+    pub(crate) variant_identifier: Symbol,
+    pub(crate) variant_identifier_span: Span,
+    pub(crate) payload: Option<&'ctx Expr<'ctx>>,
+    pub(crate) span: Span,
+}
+
+/// Value made of the values of its elements, as in `(1, 2)`. Tuples of one
+/// element are written with a trailing comma, as in `(1,)`, as `(1)` is just
+/// a parenthesized `1`.
+#[derive(Clone, Copy)]
+pub(crate) struct TupleExpr<'ctx> {
+    pub(crate) elements: &'ctx [Expr<'ctx>],
+    pub(crate) span: Span,
+}
+
+/// Access to an element of a tuple value by its position, as in `pair.0`.
+#[derive(Clone, Copy)]
+pub(crate) struct TupleFieldExpr<'ctx> {
+    pub(crate) base: &'ctx Expr<'ctx>,
+    pub(crate) index: usize,
+    pub(crate) index_span: Span,
+    pub(crate) span: Span,
+}
+
+/// Definition of a binding for each element of a tuple value, in order, as
+/// in `a, b := pair`.
+#[derive(Clone, Copy)]
+pub(crate) struct TupleBindDef<'ctx> {
+    pub(crate) bindings: &'ctx [Binding],
+    pub(crate) value: &'ctx Expr<'ctx>,
+    pub(crate) span: Span,
+}
+
+/// Name that a tuple's element is bound to, which may be `_` to discard it.
+#[derive(Clone, Copy)]
+pub(crate) struct Binding {
+    pub(crate) identifier: Symbol,
+    pub(crate) span: Span,
+}
+
+/// Value made of the values of its elements, which have the same type, as in
+/// `[1, 2, 3]`. Array literals have at least one element, which tells the
+/// type of the others.
+#[derive(Clone, Copy)]
+pub(crate) struct ArrayExpr<'ctx> {
+    pub(crate) elements: &'ctx [Expr<'ctx>],
+    pub(crate) span: Span,
+}
+
+/// Access to an element of an array value by an index computed at run time,
+/// as in `values[i]`.
+#[derive(Clone, Copy)]
+pub(crate) struct IndexExpr<'ctx> {
+    pub(crate) base: &'ctx Expr<'ctx>,
+    pub(crate) index: &'ctx Expr<'ctx>,
+    pub(crate) span: Span,
+}
+
+/// Replacement of an element of an array that's bound to a name, possibly
+/// nested in other arrays, as in `grid[i][j] = 0`.
+#[derive(Clone, Copy)]
+pub(crate) struct IndexAssignExpr<'ctx> {
+    pub(crate) target: IndexExpr<'ctx>,
+    pub(crate) value: &'ctx Expr<'ctx>,
+    pub(crate) span: Span,
 }
 
 #[derive(Clone, Copy)]
@@ -67,6 +438,7 @@ pub(crate) struct IfExpr<'ctx> {
     pub(crate) true_branch: CompoundExpr<'ctx>,
     pub(crate) else_if_branches: &'ctx [ElseIfBranch<'ctx>],
     pub(crate) final_branch: Option<CompoundExpr<'ctx>>,
+    pub(crate) span: Span,
 }
 
 #[derive(Clone, Copy)]
@@ -75,10 +447,164 @@ pub(crate) struct ElseIfBranch<'ctx> {
     pub(crate) true_branch: CompoundExpr<'ctx>,
 }
 
+#[derive(Clone, Copy)]
+pub(crate) struct MatchExpr<'ctx> {
+    pub(crate) scrutinee: &'ctx Expr<'ctx>,
+    /// Arms in the order they're tried, the first one whose pattern matches
+    /// the scrutinee giving the match-expr its value.
+    pub(crate) arms: &'ctx [MatchArm<'ctx>],
+    pub(crate) span: Span,
+}
+
+impl<'ctx> MatchExpr<'ctx> {
+    /// Arms that may be tried, i.e., up to the first one that matches every
+    /// value, if any. Match-expressions are exhaustive, so the last of them
+    /// runs whenever the others don't match, and backends don't test it.
+    pub(crate) fn tried_arms(&self) -> &'ctx [MatchArm<'ctx>] {
+        let tried_len = self
+            .arms
+            .iter()
+            .position(|arm| arm.pattern.kind == PatternKind::Wildcard)
+            .map_or(self.arms.len(), |idx| idx + 1);
+
+        &self.arms[..tried_len]
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct MatchArm<'ctx> {
+    pub(crate) pattern: Pattern,
+    pub(crate) expr: &'ctx Expr<'ctx>,
+}
+
+/// Values that a match arm matches, which are either integers or an enum's
+/// variant. Constants are kept as they're spelled, so that the type checker
+/// tells whether they fit in an integer.
+#[derive(Clone, Copy)]
+pub(crate) struct Pattern {
+    pub(crate) kind: PatternKind,
+    pub(crate) span: Span,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum PatternKind {
+    /// `_`, which matches every value.
+    Wildcard,
+    Integer(i128),
+    Range {
+        start: i128,
+        end: i128,
+        range_kind: RangeKind,
+    },
+    Variant(VariantPattern),
+}
+
+/// Variant of an enum that a pattern matches, binding the value it holds, as
+/// in `Shape::Circle(radius)`. Names are kept as strings, like a
+/// `Type::Named`'s, so that patterns can be compared and printed on their own.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct VariantPattern {
+    pub(crate) enum_name: &'static str,
+    pub(crate) enum_name_span: Span,
+    pub(crate) variant_name: &'static str,
+    pub(crate) variant_name_span: Span,
+    /// Name that the variant's value is bound to, and its span, if the
+    /// pattern has one, which may be `_` to discard the value.
+    pub(crate) binding: Option<(&'static str, Span)>,
+}
+
+impl PatternKind {
+    /// Least and greatest integers that the pattern matches, or `None` if it
+    /// matches all of them. Ranges may be empty, e.g., `1..1`.
+    ///
+    /// Variant patterns don't match integers, and programs whose matches have
+    /// them are run by the interpreter only, which doesn't ask for bounds.
+    pub(crate) fn bounds(self) -> Option<(i128, i128)> {
+        match self {
+            PatternKind::Wildcard => None,
+            PatternKind::Variant(_) => unreachable!("variant patterns have no integer bounds"),
+            PatternKind::Integer(value) => Some((value, value)),
+            PatternKind::Range {
+                start,
+                end,
+                range_kind: RangeKind::Inclusive,
+            } => Some((start, end)),
+            PatternKind::Range {
+                start,
+                end,
+                range_kind: RangeKind::Exclusive,
+            } => Some((start, end.saturating_sub(1))),
+        }
+    }
+
+    pub(crate) fn matches(self, value: i32) -> bool {
+        self.bounds()
+            .is_none_or(|(start, end)| (start..=end).contains(&value.into()))
+    }
+}
+
+impl fmt::Display for PatternKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternKind::Wildcard => write!(f, "{}", DISCARD_IDENTIFIER),
+            PatternKind::Integer(value) => write!(f, "{}", value),
+            PatternKind::Range {
+                start,
+                end,
+                range_kind,
+            } => write!(f, "{}{}{}", start, range_kind, end),
+            PatternKind::Variant(variant_pattern) => {
+                write!(
+                    f,
+                    "{}::{}",
+                    variant_pattern.enum_name, variant_pattern.variant_name
+                )?;
+
+                match variant_pattern.binding {
+                    Some((name, _)) => write!(f, "({})", name),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct ForExpr<'ctx> {
+    pub(crate) keyword: LoopKeyword,
+    /// Span of the loop's keyword, which is `loop` for loops written with it.
+    pub(crate) for_kw_span: Span,
     pub(crate) iteration: Option<ForIteration<'ctx>>,
     pub(crate) body: CompoundExpr<'ctx>,
+    /// Branch executed only when the loop runs to completion, i.e., when it's
+    /// not exited by a `break`.
+    pub(crate) else_branch: Option<CompoundExpr<'ctx>>,
+    /// Whether the loop is marked with `#[main_loop]`, meaning it's intended to
+    /// never exit.
+    pub(crate) is_main_loop: bool,
+    pub(crate) span: Span,
+}
+
+/// Keyword that a loop is written with, which tells apart a loop meant to run
+/// until a `break` from a `for` whose iteration is missing.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum LoopKeyword {
+    For,
+    /// `loop { ... }`, which has no iteration nor else-branch.
+    Loop,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct BreakExpr<'ctx> {
+    /// Value of the loop that the `break` exits, which only loops without an
+    /// iteration, as in `loop { ... }`, can have.
+    pub(crate) value: Option<&'ctx Expr<'ctx>>,
+    pub(crate) span: Span,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct ContinueExpr {
+    pub(crate) span: Span,
 }
 
 #[derive(Clone, Copy)]
@@ -88,24 +614,126 @@ pub(crate) enum ForIteration<'ctx> {
     },
     Iterative {
         identifier: Symbol,
+        identifier_span: Span,
         start_expr: &'ctx Expr<'ctx>,
         end_expr: &'ctx Expr<'ctx>,
         range_kind: RangeKind,
+        /// What the variable is stepped by after each iteration, as in
+        /// `for i : 0..100 step 2 { ... }`, which is one if there's none.
+        step_expr: Option<&'ctx Expr<'ctx>>,
+        /// Whether the variable counts down from the last value of the range
+        /// to its start, as in `for i : rev 0..10 { ... }`, which goes from 9
+        /// down to 0.
+        is_reversed: bool,
+    },
+    /// Iteration over the elements of an array, in order, as in
+    /// `for x : values { ... }`.
+    InCollection {
+        identifier: Symbol,
+        identifier_span: Span,
+        collection_expr: &'ctx Expr<'ctx>,
     },
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub(crate) enum RangeKind {
     Inclusive,
     Exclusive,
 }
 
+impl fmt::Display for RangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RangeKind::Inclusive => write!(f, "..="),
+            RangeKind::Exclusive => write!(f, ".."),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct CompoundExpr<'ctx> {
     pub(crate) exprs: &'ctx [Expr<'ctx>],
+    pub(crate) span: Span,
 }
 
 #[derive(Clone, Copy)]
-pub(crate) struct FnCallExpr {
+pub(crate) struct FnCallExpr<'ctx> {
     pub(crate) identifier: Symbol,
+    pub(crate) args: &'ctx [Expr<'ctx>],
+    pub(crate) span: Span,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct BinaryExpr<'ctx> {
+    pub(crate) op: BinaryOp,
+    pub(crate) lhs: &'ctx Expr<'ctx>,
+    pub(crate) rhs: &'ctx Expr<'ctx>,
+    pub(crate) span: Span,
+}
+
+impl<'ctx> BinaryExpr<'ctx> {
+    /// Leftmost operand of the chain of operations that this one ends, e.g.,
+    /// `a` of `a + b - c`, and the operations of the chain, from the innermost
+    /// one, `a + b`, to this one. Chains of left-associative operators aren't
+    /// bounded by the nesting limit, so passes walk them with this rather than
+    /// by recursing into `lhs`.
+    pub(crate) fn chain(&self) -> (&'ctx Expr<'ctx>, Vec<BinaryExpr<'ctx>>) {
+        let mut operations = vec![*self];
+        let mut operand = self.lhs;
+
+        while let Expr::Binary(binary_expr) = operand {
+            operations.push(*binary_expr);
+            operand = binary_expr.lhs;
+        }
+
+        operations.reverse();
+
+        (operand, operations)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    /// How tightly the operator binds its operands: the higher, the tighter.
+    pub(crate) fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Add | BinaryOp::Sub => 1,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 2,
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryOp::Add => write!(f, "+"),
+            BinaryOp::Sub => write!(f, "-"),
+            BinaryOp::Mul => write!(f, "*"),
+            BinaryOp::Div => write!(f, "/"),
+            BinaryOp::Rem => write!(f, "%"),
+        }
+    }
+}
+
+/// Conversion of an integer to another integer type, which truncates or
+/// extends it as needed.
+#[derive(Clone, Copy)]
+pub(crate) struct CastExpr<'ctx> {
+    pub(crate) expr: &'ctx Expr<'ctx>,
+    pub(crate) ty: Type,
+    pub(crate) span: Span,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct ParenExpr<'ctx> {
+    pub(crate) expr: &'ctx Expr<'ctx>,
+    pub(crate) span: Span,
 }
@@ -0,0 +1,957 @@
+//! Syntax tree of a program that owns everything in it, for tools that keep
+//! or send parse results, e.g., to another thread or process.
+//!
+//! It mirrors the compiler's own syntax tree, whose nodes are borrowed from
+//! the arenas of a compilation and name identifiers by interned symbols, so
+//! that they can't outlive it. Here, nodes are boxed and identifiers are
+//! strings instead. Parse a program into it with [`Session::parse`], or a
+//! snippet of one with [`parse_expr_fragment`] or [`parse_decl_fragment`].
+//!
+//! ```
+//! use sophia::ast_owned::{Expr, Function};
+//!
+//! let mut session = sophia::Session::new();
+//! session.add_file("main.sph", "main :: () -> i32 { 42 }");
+//!
+//! let program = session.parse().unwrap();
+//! let Expr::Function(Function { body, .. }) = &program.decls[0].value else {
+//!     panic!("`main` isn't a function");
+//! };
+//! assert_eq!(program.decls[0].identifier, "main");
+//! assert_eq!(body.span.range, 18..24);
+//! ```
+//!
+//! [`Session::parse`]: crate::Session::parse
+//! [`parse_expr_fragment`]: crate::parse_expr_fragment
+//! [`parse_decl_fragment`]: crate::parse_decl_fragment
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::ast;
+use crate::compiler_context::CompilerContext;
+use crate::interner::Symbol;
+use crate::scanner;
+use crate::source_map::SourceMap;
+
+/// Code that a node was parsed from.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Span {
+    /// Index of the file the code starts in, in the order files were added to
+    /// the session.
+    pub file: usize,
+    /// Byte offsets of the code in its file.
+    pub range: Range<usize>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Program {
+    pub decls: Vec<Decl>,
+}
+
+/// Top-level declaration, e.g., `main :: () -> i32 { 0 }`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Decl {
+    pub identifier: String,
+    pub identifier_span: Span,
+    pub value: Expr,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Expr {
+    Const(ConstExpr),
+    /// Use of a binding, e.g., `x`.
+    BindRef(BindRef),
+    /// Definition of a binding, e.g., `x := 1`.
+    BindDef(BindDef),
+    Function(Function),
+    /// Declaration of an extern function, e.g.,
+    /// `extern putchar :: (c: i32) -> i32;`.
+    Extern(ExternFn),
+    /// Declaration of a struct, e.g., `struct { x: i32, y: i32 }`.
+    Struct(StructDef),
+    /// Value of a struct, e.g., `Point { x: 1, y: 2 }`.
+    StructLit(StructLitExpr),
+    /// Access to a field, e.g., `point.x`.
+    Field(FieldExpr),
+    /// Declaration of an enum, e.g., `enum { Circle(i32), Empty }`.
+    Enum(EnumDef),
+    /// Value of an enum, e.g., `Shape::Circle(2)`.
+    Variant(VariantExpr),
+    /// Value of a tuple, e.g., `(1, 2)`.
+    Tuple(TupleExpr),
+    /// Access to an element of a tuple, e.g., `pair.0`.
+    TupleField(TupleFieldExpr),
+    /// Definition of a binding for each element of a tuple, e.g.,
+    /// `a, b := pair`.
+    TupleBindDef(TupleBindDef),
+    /// Value of an array, e.g., `[1, 2, 3]`.
+    Array(ArrayExpr),
+    /// Access to an element of an array, e.g., `values[i]`.
+    Index(IndexExpr),
+    /// Assignment to an element of an array, e.g., `values[i] = 0`.
+    IndexAssign(IndexAssignExpr),
+    If(IfExpr),
+    Match(MatchExpr),
+    For(ForExpr),
+    Break(BreakExpr),
+    Continue(ContinueExpr),
+    /// Block, e.g., `{ x := 1; x }`.
+    Compound(CompoundExpr),
+    /// Expression followed by a `;`, whose value is `()`.
+    Semi(Box<Expr>),
+    FnCall(FnCallExpr),
+    Binary(BinaryExpr),
+    Cast(CastExpr),
+    Paren(ParenExpr),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ConstExpr {
+    pub value: Const,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Const {
+    IntegerConstant {
+        value: i32,
+    },
+    /// Integer constant suffixed with a type other than `i32`, e.g., `42u8`.
+    SuffixedIntegerConstant {
+        value: u64,
+        ty: Type,
+    },
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BindRef {
+    pub identifier: String,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BindDef {
+    /// Name of the binding, which is `_` if the value is discarded.
+    pub identifier: String,
+    /// Type that the value is annotated with, e.g., `x : i32 = 0`, if any.
+    pub ty: Option<Type>,
+    pub value: Box<Expr>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Function {
+    pub return_type: Type,
+    pub parameters: Vec<Param>,
+    pub body: CompoundExpr,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ExternFn {
+    pub return_type: Type,
+    pub parameters: Vec<Param>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Param {
+    pub identifier: String,
+    pub ty: Type,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Type {
+    Unit,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    Usize,
+    /// Struct or enum declared with this name.
+    Named(String),
+    /// Tuple of elements of these types, e.g., `(i32, i32)`.
+    Tuple(Vec<Type>),
+    /// Array of elements of a type, e.g., `[i32; 4]`.
+    Array {
+        element: Box<Type>,
+        len: usize,
+    },
+    /// Function of parameters of these types, e.g., `(i32, i32) -> i32`.
+    Function {
+        params: Vec<Type>,
+        return_type: Box<Type>,
+    },
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Unit => write!(f, "()"),
+            Type::I8 => write!(f, "i8"),
+            Type::I16 => write!(f, "i16"),
+            Type::I32 => write!(f, "i32"),
+            Type::I64 => write!(f, "i64"),
+            Type::U8 => write!(f, "u8"),
+            Type::U16 => write!(f, "u16"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::Usize => write!(f, "usize"),
+            Type::Named(name) => write!(f, "{}", name),
+            Type::Tuple(element_types) if element_types.len() == 1 => {
+                write!(f, "({},)", element_types[0])
+            }
+            Type::Tuple(element_types) => write!(f, "({})", comma_separated(element_types)),
+            Type::Array { element, len } => write!(f, "[{}; {}]", element, len),
+            Type::Function {
+                params,
+                return_type,
+            } => write!(f, "({}) -> {}", comma_separated(params), return_type),
+        }
+    }
+}
+
+/// Types of the compiler's own syntax tree point into the arenas of their
+/// compilation, so they're converted to owned ones to outlive it, e.g., in
+/// diagnostics.
+impl From<ast::Type> for Type {
+    fn from(ty: ast::Type) -> Type {
+        convert_type(ty)
+    }
+}
+
+fn comma_separated(types: &[Type]) -> String {
+    types
+        .iter()
+        .map(|ty| ty.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StructDef {
+    pub fields: Vec<FieldDef>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FieldDef {
+    pub identifier: String,
+    pub ty: Type,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StructLitExpr {
+    /// Name of the struct.
+    pub identifier: String,
+    pub identifier_span: Span,
+    pub fields: Vec<FieldInit>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FieldInit {
+    pub identifier: String,
+    pub identifier_span: Span,
+    pub value: Box<Expr>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FieldExpr {
+    pub base: Box<Expr>,
+    /// Name of the field.
+    pub identifier: String,
+    pub identifier_span: Span,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EnumDef {
+    pub variants: Vec<VariantDef>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VariantDef {
+    pub identifier: String,
+    /// Type of the value that the variant holds, if it holds one.
+    pub payload: Option<Type>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VariantExpr {
+    /// Name of the enum.
+    pub enum_identifier: String,
+    pub enum_identifier_span: Span,
+    /// Name of the variant.
+    pub variant_identifier: String,
+    pub variant_identifier_span: Span,
+    pub payload: Option<Box<Expr>>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TupleExpr {
+    pub elements: Vec<Expr>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TupleFieldExpr {
+    pub base: Box<Expr>,
+    /// Position of the element, from zero.
+    pub index: usize,
+    pub index_span: Span,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TupleBindDef {
+    pub bindings: Vec<Binding>,
+    pub value: Box<Expr>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Binding {
+    /// Name of the binding, which is `_` if the element is discarded.
+    pub identifier: String,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ArrayExpr {
+    pub elements: Vec<Expr>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct IndexExpr {
+    pub base: Box<Expr>,
+    pub index: Box<Expr>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct IndexAssignExpr {
+    pub target: IndexExpr,
+    pub value: Box<Expr>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct IfExpr {
+    pub cond_expr: Box<Expr>,
+    pub true_branch: CompoundExpr,
+    pub else_if_branches: Vec<ElseIfBranch>,
+    pub final_branch: Option<CompoundExpr>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ElseIfBranch {
+    pub cond_expr: Box<Expr>,
+    pub true_branch: CompoundExpr,
+}
+
+/// Match on an integer or an enum, e.g., `match x { 0 => 1, _ => x }`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MatchExpr {
+    pub scrutinee: Box<Expr>,
+    pub arms: Vec<MatchArm>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub expr: Box<Expr>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PatternKind {
+    /// `_`, which matches every value.
+    Wildcard,
+    Integer(i128),
+    /// Range of integers, e.g., `1..=9`.
+    Range {
+        start: i128,
+        end: i128,
+        range_kind: RangeKind,
+    },
+    /// Variant of an enum, e.g., `Shape::Circle(radius)`.
+    Variant(VariantPattern),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VariantPattern {
+    pub enum_identifier: String,
+    pub enum_identifier_span: Span,
+    pub variant_identifier: String,
+    pub variant_identifier_span: Span,
+    /// Name that the variant's value is bound to, and its span, which is `_`
+    /// if the value is discarded, or `None` if the pattern doesn't name it.
+    pub binding: Option<(String, Span)>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ForExpr {
+    pub keyword: LoopKeyword,
+    /// Span of the loop's keyword, which is `loop` for loops written with it.
+    pub for_kw_span: Span,
+    /// How the loop iterates, or `None` if it loops until a `break`.
+    pub iteration: Option<ForIteration>,
+    pub body: CompoundExpr,
+    /// Branch executed only when the loop runs to completion, i.e., when it's
+    /// not exited by a `break`.
+    pub else_branch: Option<CompoundExpr>,
+    /// Whether the loop is marked with `#[main_loop]`, meaning it's intended to
+    /// never exit.
+    pub is_main_loop: bool,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ForIteration {
+    /// Loop that runs while the condition is non-zero, e.g., `for x { ... }`.
+    Conditional { cond_expr: Box<Expr> },
+    /// Loop over a range, e.g., `for i : 0..10 { ... }`,
+    /// `for i : 0..10 step 2 { ... }`, or `for i : rev 0..10 { ... }`.
+    Iterative {
+        identifier: String,
+        identifier_span: Span,
+        start_expr: Box<Expr>,
+        end_expr: Box<Expr>,
+        range_kind: RangeKind,
+        step_expr: Option<Box<Expr>>,
+        is_reversed: bool,
+    },
+    /// Loop over the elements of an array, e.g., `for x : values { ... }`.
+    InCollection {
+        identifier: String,
+        identifier_span: Span,
+        collection_expr: Box<Expr>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LoopKeyword {
+    /// `for`, which may also loop until a `break` if it has no iteration.
+    For,
+    /// `loop`, which always loops until a `break`.
+    Loop,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RangeKind {
+    /// `a..=b`
+    Inclusive,
+    /// `a..b`
+    Exclusive,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BreakExpr {
+    pub value: Option<Box<Expr>>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ContinueExpr {
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CompoundExpr {
+    pub exprs: Vec<Expr>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FnCallExpr {
+    /// Name of the function called.
+    pub identifier: String,
+    pub args: Vec<Expr>,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BinaryExpr {
+    pub op: BinaryOp,
+    pub lhs: Box<Expr>,
+    pub rhs: Box<Expr>,
+    pub span: Span,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+/// Conversion of an integer to another integer type.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CastExpr {
+    pub expr: Box<Expr>,
+    pub ty: Type,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParenExpr {
+    pub expr: Box<Expr>,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn span(&self) -> &Span {
+        match self {
+            Expr::Const(const_expr) => &const_expr.span,
+            Expr::BindRef(bind_ref) => &bind_ref.span,
+            Expr::BindDef(bind_def) => &bind_def.span,
+            Expr::Function(function) => &function.span,
+            Expr::Extern(extern_fn) => &extern_fn.span,
+            Expr::Struct(struct_def) => &struct_def.span,
+            Expr::StructLit(struct_lit_expr) => &struct_lit_expr.span,
+            Expr::Field(field_expr) => &field_expr.span,
+            Expr::Enum(enum_def) => &enum_def.span,
+            Expr::Variant(variant_expr) => &variant_expr.span,
+            Expr::Tuple(tuple_expr) => &tuple_expr.span,
+            Expr::TupleField(tuple_field_expr) => &tuple_field_expr.span,
+            Expr::TupleBindDef(tuple_bind_def) => &tuple_bind_def.span,
+            Expr::Array(array_expr) => &array_expr.span,
+            Expr::Index(index_expr) => &index_expr.span,
+            Expr::IndexAssign(index_assign_expr) => &index_assign_expr.span,
+            Expr::If(if_expr) => &if_expr.span,
+            Expr::Match(match_expr) => &match_expr.span,
+            Expr::For(for_expr) => &for_expr.span,
+            Expr::Break(break_expr) => &break_expr.span,
+            Expr::Continue(continue_expr) => &continue_expr.span,
+            Expr::Compound(compound_expr) => &compound_expr.span,
+            Expr::Semi(expr) => expr.span(),
+            Expr::FnCall(fn_call_expr) => &fn_call_expr.span,
+            Expr::Binary(binary_expr) => &binary_expr.span,
+            Expr::Cast(cast_expr) => &cast_expr.span,
+            Expr::Paren(paren_expr) => &paren_expr.span,
+        }
+    }
+}
+
+/// Converts the compiler's syntax tree of a program to an owned one, whose
+/// spans are in the files of the source map.
+pub(crate) struct AstConverter<'a, 'ctx> {
+    ctx: &'ctx CompilerContext,
+    source_map: &'a SourceMap,
+}
+
+impl<'a, 'ctx> AstConverter<'a, 'ctx> {
+    pub(crate) fn new(
+        ctx: &'ctx CompilerContext,
+        source_map: &'a SourceMap,
+    ) -> AstConverter<'a, 'ctx> {
+        AstConverter { ctx, source_map }
+    }
+
+    pub(crate) fn convert_program(&self, program: ast::Program<'ctx>) -> Program {
+        Program {
+            decls: program
+                .decls
+                .iter()
+                .map(|decl| self.convert_decl(decl))
+                .collect(),
+        }
+    }
+
+    pub(crate) fn convert_decl(&self, decl: &ast::Decl<'ctx>) -> Decl {
+        Decl {
+            identifier: self.identifier(decl.identifier),
+            identifier_span: self.span(decl.identifier_span),
+            value: self.convert_expr(decl.value),
+        }
+    }
+
+    pub(crate) fn convert_expr(&self, expr: &ast::Expr<'ctx>) -> Expr {
+        match *expr {
+            ast::Expr::Const(const_expr) => Expr::Const(ConstExpr {
+                value: match const_expr.value {
+                    ast::Const::IntegerConstant { value } => Const::IntegerConstant { value },
+                    ast::Const::SuffixedIntegerConstant { value, ty } => {
+                        Const::SuffixedIntegerConstant {
+                            value,
+                            ty: convert_type(ty),
+                        }
+                    }
+                },
+                span: self.span(const_expr.span),
+            }),
+            ast::Expr::BindRef(bind_ref) => Expr::BindRef(BindRef {
+                identifier: self.identifier(bind_ref.identifier),
+                span: self.span(bind_ref.span),
+            }),
+            ast::Expr::BindDef(bind_def) => Expr::BindDef(BindDef {
+                identifier: self.identifier(bind_def.identifier),
+                ty: bind_def.ty.map(convert_type),
+                value: self.convert_boxed_expr(bind_def.value),
+                span: self.span(bind_def.span),
+            }),
+            ast::Expr::Function(function) => Expr::Function(Function {
+                return_type: convert_type(function.return_type),
+                parameters: self.convert_params(function.parameters),
+                body: self.convert_compound_expr(function.body),
+                span: self.span(function.span),
+            }),
+            ast::Expr::Extern(extern_fn) => Expr::Extern(ExternFn {
+                return_type: convert_type(extern_fn.return_type),
+                parameters: self.convert_params(extern_fn.parameters),
+                span: self.span(extern_fn.span),
+            }),
+            ast::Expr::Struct(struct_def) => Expr::Struct(StructDef {
+                fields: struct_def
+                    .fields
+                    .iter()
+                    .map(|field| FieldDef {
+                        identifier: self.identifier(field.identifier),
+                        ty: convert_type(field.ty),
+                        span: self.span(field.span),
+                    })
+                    .collect(),
+                span: self.span(struct_def.span),
+            }),
+            ast::Expr::StructLit(struct_lit_expr) => Expr::StructLit(StructLitExpr {
+                identifier: self.identifier(struct_lit_expr.identifier),
+                identifier_span: self.span(struct_lit_expr.identifier_span),
+                fields: struct_lit_expr
+                    .fields
+                    .iter()
+                    .map(|field| FieldInit {
+                        identifier: self.identifier(field.identifier),
+                        identifier_span: self.span(field.identifier_span),
+                        value: self.convert_boxed_expr(field.value),
+                    })
+                    .collect(),
+                span: self.span(struct_lit_expr.span),
+            }),
+            ast::Expr::Field(field_expr) => Expr::Field(FieldExpr {
+                base: self.convert_boxed_expr(field_expr.base),
+                identifier: self.identifier(field_expr.identifier),
+                identifier_span: self.span(field_expr.identifier_span),
+                span: self.span(field_expr.span),
+            }),
+            ast::Expr::Enum(enum_def) => Expr::Enum(EnumDef {
+                variants: enum_def
+                    .variants
+                    .iter()
+                    .map(|variant| VariantDef {
+                        identifier: self.identifier(variant.identifier),
+                        payload: variant.payload.map(convert_type),
+                        span: self.span(variant.span),
+                    })
+                    .collect(),
+                span: self.span(enum_def.span),
+            }),
+            ast::Expr::Variant(variant_expr) => Expr::Variant(VariantExpr {
+                enum_identifier: self.identifier(variant_expr.enum_identifier),
+                enum_identifier_span: self.span(variant_expr.enum_identifier_span),
+                variant_identifier: self.identifier(variant_expr.variant_identifier),
+                variant_identifier_span: self.span(variant_expr.variant_identifier_span),
+                payload: variant_expr
+                    .payload
+                    .map(|payload| self.convert_boxed_expr(payload)),
+                span: self.span(variant_expr.span),
+            }),
+            ast::Expr::Tuple(tuple_expr) => Expr::Tuple(TupleExpr {
+                elements: self.convert_exprs(tuple_expr.elements),
+                span: self.span(tuple_expr.span),
+            }),
+            ast::Expr::TupleField(tuple_field_expr) => Expr::TupleField(TupleFieldExpr {
+                base: self.convert_boxed_expr(tuple_field_expr.base),
+                index: tuple_field_expr.index,
+                index_span: self.span(tuple_field_expr.index_span),
+                span: self.span(tuple_field_expr.span),
+            }),
+            ast::Expr::TupleBindDef(tuple_bind_def) => Expr::TupleBindDef(TupleBindDef {
+                bindings: tuple_bind_def
+                    .bindings
+                    .iter()
+                    .map(|binding| Binding {
+                        identifier: self.identifier(binding.identifier),
+                        span: self.span(binding.span),
+                    })
+                    .collect(),
+                value: self.convert_boxed_expr(tuple_bind_def.value),
+                span: self.span(tuple_bind_def.span),
+            }),
+            ast::Expr::Array(array_expr) => Expr::Array(ArrayExpr {
+                elements: self.convert_exprs(array_expr.elements),
+                span: self.span(array_expr.span),
+            }),
+            ast::Expr::Index(index_expr) => Expr::Index(self.convert_index_expr(index_expr)),
+            ast::Expr::IndexAssign(index_assign_expr) => Expr::IndexAssign(IndexAssignExpr {
+                target: self.convert_index_expr(index_assign_expr.target),
+                value: self.convert_boxed_expr(index_assign_expr.value),
+                span: self.span(index_assign_expr.span),
+            }),
+            ast::Expr::If(if_expr) => Expr::If(IfExpr {
+                cond_expr: self.convert_boxed_expr(if_expr.cond_expr),
+                true_branch: self.convert_compound_expr(if_expr.true_branch),
+                else_if_branches: if_expr
+                    .else_if_branches
+                    .iter()
+                    .map(|branch| ElseIfBranch {
+                        cond_expr: self.convert_boxed_expr(branch.cond_expr),
+                        true_branch: self.convert_compound_expr(branch.true_branch),
+                    })
+                    .collect(),
+                final_branch: if_expr
+                    .final_branch
+                    .map(|branch| self.convert_compound_expr(branch)),
+                span: self.span(if_expr.span),
+            }),
+            ast::Expr::Match(match_expr) => Expr::Match(MatchExpr {
+                scrutinee: self.convert_boxed_expr(match_expr.scrutinee),
+                arms: match_expr
+                    .arms
+                    .iter()
+                    .map(|arm| MatchArm {
+                        pattern: Pattern {
+                            kind: self.convert_pattern_kind(arm.pattern.kind),
+                            span: self.span(arm.pattern.span),
+                        },
+                        expr: self.convert_boxed_expr(arm.expr),
+                    })
+                    .collect(),
+                span: self.span(match_expr.span),
+            }),
+            ast::Expr::For(for_expr) => Expr::For(self.convert_for_expr(for_expr)),
+            ast::Expr::Break(break_expr) => Expr::Break(BreakExpr {
+                value: break_expr.value.map(|value| self.convert_boxed_expr(value)),
+                span: self.span(break_expr.span),
+            }),
+            ast::Expr::Continue(continue_expr) => Expr::Continue(ContinueExpr {
+                span: self.span(continue_expr.span),
+            }),
+            ast::Expr::Compound(compound_expr) => {
+                Expr::Compound(self.convert_compound_expr(compound_expr))
+            }
+            ast::Expr::Semi(expr) => Expr::Semi(self.convert_boxed_expr(expr)),
+            ast::Expr::FnCall(fn_call_expr) => Expr::FnCall(FnCallExpr {
+                identifier: self.identifier(fn_call_expr.identifier),
+                args: self.convert_exprs(fn_call_expr.args),
+                span: self.span(fn_call_expr.span),
+            }),
+            ast::Expr::Binary(binary_expr) => {
+                let (operand, operations) = binary_expr.chain();
+                let mut lhs = self.convert_expr(operand);
+
+                for binary_expr in operations {
+                    lhs = Expr::Binary(BinaryExpr {
+                        op: convert_binary_op(binary_expr.op),
+                        lhs: Box::new(lhs),
+                        rhs: self.convert_boxed_expr(binary_expr.rhs),
+                        span: self.span(binary_expr.span),
+                    });
+                }
+
+                lhs
+            }
+            ast::Expr::Cast(cast_expr) => Expr::Cast(CastExpr {
+                expr: self.convert_boxed_expr(cast_expr.expr),
+                ty: convert_type(cast_expr.ty),
+                span: self.span(cast_expr.span),
+            }),
+            ast::Expr::Paren(paren_expr) => Expr::Paren(ParenExpr {
+                expr: self.convert_boxed_expr(paren_expr.expr),
+                span: self.span(paren_expr.span),
+            }),
+        }
+    }
+
+    fn convert_boxed_expr(&self, expr: &ast::Expr<'ctx>) -> Box<Expr> {
+        Box::new(self.convert_expr(expr))
+    }
+
+    fn convert_exprs(&self, exprs: &[ast::Expr<'ctx>]) -> Vec<Expr> {
+        exprs.iter().map(|expr| self.convert_expr(expr)).collect()
+    }
+
+    fn convert_for_expr(&self, for_expr: ast::ForExpr<'ctx>) -> ForExpr {
+        let iteration = for_expr.iteration.map(|iteration| match iteration {
+            ast::ForIteration::Conditional { cond_expr } => ForIteration::Conditional {
+                cond_expr: self.convert_boxed_expr(cond_expr),
+            },
+            ast::ForIteration::Iterative {
+                identifier,
+                identifier_span,
+                start_expr,
+                end_expr,
+                range_kind,
+                step_expr,
+                is_reversed,
+            } => ForIteration::Iterative {
+                identifier: self.identifier(identifier),
+                identifier_span: self.span(identifier_span),
+                start_expr: self.convert_boxed_expr(start_expr),
+                end_expr: self.convert_boxed_expr(end_expr),
+                range_kind: convert_range_kind(range_kind),
+                step_expr: step_expr.map(|step_expr| self.convert_boxed_expr(step_expr)),
+                is_reversed,
+            },
+            ast::ForIteration::InCollection {
+                identifier,
+                identifier_span,
+                collection_expr,
+            } => ForIteration::InCollection {
+                identifier: self.identifier(identifier),
+                identifier_span: self.span(identifier_span),
+                collection_expr: self.convert_boxed_expr(collection_expr),
+            },
+        });
+
+        ForExpr {
+            keyword: convert_loop_keyword(for_expr.keyword),
+            for_kw_span: self.span(for_expr.for_kw_span),
+            iteration,
+            body: self.convert_compound_expr(for_expr.body),
+            else_branch: for_expr
+                .else_branch
+                .map(|branch| self.convert_compound_expr(branch)),
+            is_main_loop: for_expr.is_main_loop,
+            span: self.span(for_expr.span),
+        }
+    }
+
+    fn convert_compound_expr(&self, compound_expr: ast::CompoundExpr<'ctx>) -> CompoundExpr {
+        CompoundExpr {
+            exprs: self.convert_exprs(compound_expr.exprs),
+            span: self.span(compound_expr.span),
+        }
+    }
+
+    fn convert_params(&self, params: &[ast::Param]) -> Vec<Param> {
+        params
+            .iter()
+            .map(|param| Param {
+                identifier: self.identifier(param.identifier),
+                ty: convert_type(param.ty),
+                span: self.span(param.span),
+            })
+            .collect()
+    }
+
+    fn convert_index_expr(&self, index_expr: ast::IndexExpr<'ctx>) -> IndexExpr {
+        IndexExpr {
+            base: self.convert_boxed_expr(index_expr.base),
+            index: self.convert_boxed_expr(index_expr.index),
+            span: self.span(index_expr.span),
+        }
+    }
+
+    fn convert_pattern_kind(&self, kind: ast::PatternKind) -> PatternKind {
+        match kind {
+            ast::PatternKind::Wildcard => PatternKind::Wildcard,
+            ast::PatternKind::Integer(value) => PatternKind::Integer(value),
+            ast::PatternKind::Range {
+                start,
+                end,
+                range_kind,
+            } => PatternKind::Range {
+                start,
+                end,
+                range_kind: convert_range_kind(range_kind),
+            },
+            ast::PatternKind::Variant(variant_pattern) => PatternKind::Variant(VariantPattern {
+                enum_identifier: variant_pattern.enum_name.to_owned(),
+                enum_identifier_span: self.span(variant_pattern.enum_name_span),
+                variant_identifier: variant_pattern.variant_name.to_owned(),
+                variant_identifier_span: self.span(variant_pattern.variant_name_span),
+                binding: variant_pattern
+                    .binding
+                    .map(|(name, span)| (name.to_owned(), self.span(span))),
+            }),
+        }
+    }
+
+    fn identifier(&self, symbol: Symbol) -> String {
+        self.ctx.resolve_symbol(symbol).to_owned()
+    }
+
+    fn span(&self, span: scanner::Span) -> Span {
+        let (file_id, range) = self.source_map.file_range(span);
+
+        Span {
+            file: file_id.0,
+            range,
+        }
+    }
+}
+
+fn convert_loop_keyword(keyword: ast::LoopKeyword) -> LoopKeyword {
+    match keyword {
+        ast::LoopKeyword::For => LoopKeyword::For,
+        ast::LoopKeyword::Loop => LoopKeyword::Loop,
+    }
+}
+
+fn convert_range_kind(range_kind: ast::RangeKind) -> RangeKind {
+    match range_kind {
+        ast::RangeKind::Inclusive => RangeKind::Inclusive,
+        ast::RangeKind::Exclusive => RangeKind::Exclusive,
+    }
+}
+
+fn convert_type(ty: ast::Type) -> Type {
+    match ty {
+        ast::Type::Unit => Type::Unit,
+        ast::Type::I8 => Type::I8,
+        ast::Type::I16 => Type::I16,
+        ast::Type::I32 => Type::I32,
+        ast::Type::I64 => Type::I64,
+        ast::Type::U8 => Type::U8,
+        ast::Type::U16 => Type::U16,
+        ast::Type::U32 => Type::U32,
+        ast::Type::U64 => Type::U64,
+        ast::Type::Usize => Type::Usize,
+        ast::Type::Named(name) => Type::Named(name.to_owned()),
+        ast::Type::Tuple(element_types) => {
+            Type::Tuple(element_types.iter().copied().map(convert_type).collect())
+        }
+        ast::Type::Array { element, len } => Type::Array {
+            element: Box::new(convert_type(*element)),
+            len,
+        },
+        ast::Type::Function {
+            params,
+            return_type,
+        } => Type::Function {
+            params: params.iter().copied().map(convert_type).collect(),
+            return_type: Box::new(convert_type(*return_type)),
+        },
+    }
+}
+
+fn convert_binary_op(op: ast::BinaryOp) -> BinaryOp {
+    match op {
+        ast::BinaryOp::Add => BinaryOp::Add,
+        ast::BinaryOp::Sub => BinaryOp::Sub,
+        ast::BinaryOp::Mul => BinaryOp::Mul,
+        ast::BinaryOp::Div => BinaryOp::Div,
+        ast::BinaryOp::Rem => BinaryOp::Rem,
+    }
+}
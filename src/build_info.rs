@@ -0,0 +1,42 @@
+/// What this build of the compiler is and what it can do, for tools that
+/// check that they can work with it, e.g., that it has a backend they need.
+///
+/// ```
+/// let info = sophia::build_info();
+///
+/// assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+/// assert!(info.backends.contains(&"interpreter"));
+/// assert_eq!(info.backends.contains(&"jit"), info.features.contains(&"jit"));
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BuildInfo {
+    /// Version of the compiler, e.g., `0.1.0`.
+    pub version: &'static str,
+    /// Ways to run or compile programs, e.g., `interpreter` or `C`. Compiled
+    /// backends are named the way their errors name them.
+    pub backends: Vec<&'static str>,
+    /// Optional features that the compiler was built with, e.g., `jit`.
+    pub features: Vec<&'static str>,
+    /// Machines that the compiler generates code for itself. The C and LLVM IR
+    /// it generates are compiled further for whichever machines their
+    /// compilers target.
+    pub targets: Vec<&'static str>,
+}
+
+/// Describes this build of the compiler.
+pub fn build_info() -> BuildInfo {
+    let jit = cfg!(feature = "jit");
+
+    let mut backends = vec!["interpreter", "bytecode"];
+    if jit {
+        backends.push("jit");
+    }
+    backends.extend(["x86-64", "LLVM IR", "WebAssembly", "C"]);
+
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        backends,
+        features: if jit { vec!["jit"] } else { vec![] },
+        targets: vec!["x86_64", "wasm32"],
+    }
+}
@@ -0,0 +1,48 @@
+/// Function that the compiler knows of, which programs can call without
+/// declaring it, unless they declare one of the same name.
+///
+/// Builtins print what programs give them. They take an integer of any type,
+/// and will take strings too once Sophia has them. The interpreter prints
+/// through its output, the VM through host functions of the same names, and
+/// native code through functions of the runtime library.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum Builtin {
+    /// Prints the value.
+    Print,
+    /// Prints the value, then a newline.
+    Println,
+}
+
+impl Builtin {
+    pub(crate) const ALL: [Builtin; 2] = [Builtin::Print, Builtin::Println];
+
+    pub(crate) fn from_name(name: &str) -> Option<Builtin> {
+        Builtin::ALL
+            .into_iter()
+            .find(|builtin| builtin.name() == name)
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Builtin::Print => "print",
+            Builtin::Println => "println",
+        }
+    }
+
+    /// Name of the runtime library's function that native code calls to print
+    /// an `i32`.
+    pub(crate) fn runtime_symbol(self) -> &'static str {
+        match self {
+            Builtin::Print => "sophia_print_i32",
+            Builtin::Println => "sophia_println_i32",
+        }
+    }
+
+    /// Text that the builtin prints for the integer.
+    pub(crate) fn format(self, value: i128) -> String {
+        match self {
+            Builtin::Print => value.to_string(),
+            Builtin::Println => format!("{}\n", value),
+        }
+    }
+}
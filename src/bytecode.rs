@@ -0,0 +1,493 @@
+use std::fmt;
+
+use crate::scanner::{BytePos, Span};
+
+/// Bytes that every bytecode file starts with.
+pub(crate) const MAGIC: &[u8; 4] = b"SBC\0";
+
+/// Version of the bytecode format, bumped whenever it changes in a way that
+/// older loaders can't read.
+pub(crate) const FORMAT_VERSION: u16 = 3;
+
+/// Precompiled program, which can be saved to disk and loaded back to be run
+/// later, without compiling the source code again.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub(crate) struct Module {
+    /// Integer constants that instructions refer to by index.
+    pub(crate) constants: Vec<i32>,
+    /// Functions that the embedder of the VM has to provide for the module to
+    /// run.
+    pub(crate) host_functions: Vec<HostFunctionImport>,
+    pub(crate) functions: Vec<FunctionCode>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct HostFunctionImport {
+    pub(crate) name: String,
+    /// How many values a call passes, which are the parameters of type `i32`.
+    pub(crate) arity: u32,
+    pub(crate) returns_value: bool,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct FunctionCode {
+    pub(crate) name: String,
+    /// How many values a call takes from the caller's stack, which are the
+    /// parameters of type `i32`, as values of type `()` have no representation.
+    pub(crate) arity: u32,
+    pub(crate) slot_count: u32,
+    /// Whether the function returns a value, i.e., it returns an `i32`.
+    pub(crate) returns_value: bool,
+    pub(crate) code: Vec<Instr>,
+    pub(crate) debug: DebugInfo,
+}
+
+/// Source locations of a function, for reporting runtime errors.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub(crate) struct DebugInfo {
+    /// Span of every parameter, including those of type `()`.
+    pub(crate) param_spans: Vec<Span>,
+    /// Span of the expression each instruction that can fail comes from, in
+    /// increasing order of instruction index.
+    pub(crate) instr_spans: Vec<(u32, Span)>,
+}
+
+impl DebugInfo {
+    pub(crate) fn span_of(&self, instr_idx: usize) -> Option<Span> {
+        let idx = self
+            .instr_spans
+            .binary_search_by_key(&instr_idx, |&(idx, _)| idx as usize)
+            .ok()?;
+
+        Some(self.instr_spans[idx].1)
+    }
+}
+
+/// Instruction of a stack machine, where every value is an `i32`. Each frame
+/// has its own slots, and jump targets are indices into the function's code.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Instr {
+    /// Pushes a constant of the module's pool.
+    Const(u32),
+    /// Pushes the value of a slot.
+    Load(u32),
+    /// Pops a value into a slot.
+    Store(u32),
+    Pop,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    /// Pops the right-hand side, then the left-hand side, pushing one if the
+    /// latter is less than the former, or zero otherwise.
+    Less,
+    LessEqual,
+    /// Pops the right-hand side, then the left-hand side, pushing one if
+    /// adding them overflows, or zero otherwise.
+    AddOverflows,
+    SubOverflows,
+    /// Pops the step of a range, failing unless it's positive.
+    CheckStep,
+    Jump(u32),
+    /// Pops a value, jumping if it's zero.
+    JumpIfZero(u32),
+    /// Calls a function of the module, passing it as many values off the
+    /// stack as its arity, the last one on top.
+    Call(u32),
+    /// Calls a host function, just like `Call` does, pushing the value it
+    /// returns if it returns one.
+    CallHost(u32),
+    /// Returns from the function, along with the value on top of the stack if
+    /// it returns one.
+    Return,
+}
+
+impl Instr {
+    fn opcode(self) -> u8 {
+        match self {
+            Instr::Const(_) => 0x01,
+            Instr::Load(_) => 0x02,
+            Instr::Store(_) => 0x03,
+            Instr::Pop => 0x04,
+            Instr::Add => 0x10,
+            Instr::Sub => 0x11,
+            Instr::Mul => 0x12,
+            Instr::Div => 0x13,
+            Instr::Rem => 0x14,
+            Instr::Less => 0x15,
+            Instr::LessEqual => 0x16,
+            Instr::AddOverflows => 0x17,
+            Instr::SubOverflows => 0x18,
+            Instr::CheckStep => 0x19,
+            Instr::Jump(_) => 0x20,
+            Instr::JumpIfZero(_) => 0x21,
+            Instr::Call(_) => 0x30,
+            Instr::Return => 0x31,
+            Instr::CallHost(_) => 0x32,
+        }
+    }
+
+    fn operand(self) -> Option<u32> {
+        match self {
+            Instr::Const(operand)
+            | Instr::Load(operand)
+            | Instr::Store(operand)
+            | Instr::Jump(operand)
+            | Instr::JumpIfZero(operand)
+            | Instr::Call(operand)
+            | Instr::CallHost(operand) => Some(operand),
+            _ => None,
+        }
+    }
+}
+
+/// Reason why bytes couldn't be loaded as a module.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum LoadError {
+    NotBytecode,
+    UnsupportedVersion { version: u16 },
+    Malformed { reason: &'static str },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::NotBytecode => write!(f, "not a sophia bytecode file"),
+            LoadError::UnsupportedVersion { version } => write!(
+                f,
+                "bytecode format version {} is not supported, only version {} is",
+                version, FORMAT_VERSION
+            ),
+            LoadError::Malformed { reason } => write!(f, "malformed bytecode: {}", reason),
+        }
+    }
+}
+
+/// Whether the bytes look like a bytecode file, rather than source code.
+pub(crate) fn is_bytecode(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+impl Module {
+    /// Serializes the module, in little-endian byte order:
+    ///
+    /// ```text
+    /// magic, version: u16,
+    /// constant count: u32, constants: i32...,
+    /// host function count: u32, for each host function:
+    ///     name, arity: u32, returns value: u8,
+    /// function count: u32, for each function:
+    ///     name, arity: u32, slot count: u32, returns value: u8,
+    ///     instruction count: u32, instructions: opcode: u8 [operand: u32]...,
+    ///     parameter count: u32, parameter spans...,
+    ///     instruction span count: u32, (instruction index: u32, span)...
+    /// ```
+    ///
+    /// Names are a byte length followed by UTF-8 bytes, and spans are a start
+    /// and end byte offset, each an u32.
+    pub(crate) fn save(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.u16(FORMAT_VERSION);
+
+        writer.u32(self.constants.len() as u32);
+
+        for &constant in &self.constants {
+            writer.u32(constant as u32);
+        }
+
+        writer.u32(self.host_functions.len() as u32);
+
+        for host_function in &self.host_functions {
+            writer.name(&host_function.name);
+            writer.u32(host_function.arity);
+            writer.bytes.push(host_function.returns_value.into());
+        }
+
+        writer.u32(self.functions.len() as u32);
+
+        for function in &self.functions {
+            writer.name(&function.name);
+            writer.u32(function.arity);
+            writer.u32(function.slot_count);
+            writer.bytes.push(function.returns_value.into());
+
+            writer.u32(function.code.len() as u32);
+
+            for &instr in &function.code {
+                writer.bytes.push(instr.opcode());
+
+                if let Some(operand) = instr.operand() {
+                    writer.u32(operand);
+                }
+            }
+
+            writer.u32(function.debug.param_spans.len() as u32);
+
+            for &span in &function.debug.param_spans {
+                writer.span(span);
+            }
+
+            writer.u32(function.debug.instr_spans.len() as u32);
+
+            for &(instr_idx, span) in &function.debug.instr_spans {
+                writer.u32(instr_idx);
+                writer.span(span);
+            }
+        }
+
+        writer.bytes
+    }
+
+    /// Deserializes a module saved by `save`, checking that every operand
+    /// refers to something that exists, so that running it can't go out of
+    /// bounds.
+    pub(crate) fn load(bytes: &[u8]) -> Result<Module, LoadError> {
+        if !is_bytecode(bytes) {
+            return Err(LoadError::NotBytecode);
+        }
+
+        let mut reader = Reader {
+            bytes,
+            pos: MAGIC.len(),
+        };
+
+        let version = reader.u16()?;
+
+        if version != FORMAT_VERSION {
+            return Err(LoadError::UnsupportedVersion { version });
+        }
+
+        let constant_count = reader.u32()?;
+        let mut constants = vec![];
+
+        for _ in 0..constant_count {
+            constants.push(reader.u32()? as i32);
+        }
+
+        let host_function_count = reader.u32()?;
+        let mut host_functions = vec![];
+
+        for _ in 0..host_function_count {
+            host_functions.push(HostFunctionImport {
+                name: reader.name()?,
+                arity: reader.u32()?,
+                returns_value: reader.bool()?,
+            });
+        }
+
+        let function_count = reader.u32()?;
+        let mut functions = vec![];
+
+        for _ in 0..function_count {
+            functions.push(reader.function()?);
+        }
+
+        if reader.pos != bytes.len() {
+            return Err(malformed("trailing bytes after the last function"));
+        }
+
+        let module = Module {
+            constants,
+            host_functions,
+            functions,
+        };
+
+        module.validate()?;
+
+        Ok(module)
+    }
+
+    fn validate(&self) -> Result<(), LoadError> {
+        for function in &self.functions {
+            for &instr in &function.code {
+                let is_valid = match instr {
+                    Instr::Const(idx) => (idx as usize) < self.constants.len(),
+                    Instr::Load(slot) | Instr::Store(slot) => slot < function.slot_count,
+                    Instr::Jump(target) | Instr::JumpIfZero(target) => {
+                        (target as usize) < function.code.len()
+                    }
+                    Instr::Call(idx) => (idx as usize) < self.functions.len(),
+                    Instr::CallHost(idx) => (idx as usize) < self.host_functions.len(),
+                    _ => true,
+                };
+
+                if !is_valid {
+                    return Err(malformed("instruction operand out of bounds"));
+                }
+            }
+
+            // Running off the end of the code would go out of bounds too.
+            if function.code.last() != Some(&Instr::Return) {
+                return Err(malformed("function doesn't end with a return"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn malformed(reason: &'static str) -> LoadError {
+    LoadError::Malformed { reason }
+}
+
+/// Writes values in the byte order of saved modules, which snapshots of the
+/// VM share.
+#[derive(Default)]
+pub(crate) struct Writer {
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl Writer {
+    pub(crate) fn u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn span(&mut self, span: Span) {
+        self.u32(span.start.0 as u32);
+        self.u32(span.end.0 as u32);
+    }
+
+    fn name(&mut self, name: &str) {
+        self.u32(name.len() as u32);
+        self.bytes.extend_from_slice(name.as_bytes());
+    }
+}
+
+pub(crate) struct Reader<'a> {
+    pub(crate) bytes: &'a [u8],
+    pub(crate) pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], LoadError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or(malformed("unexpected end of file"))?;
+
+        self.pos += len;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, LoadError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, LoadError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(malformed("invalid boolean")),
+        }
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, LoadError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, LoadError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, LoadError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn span(&mut self) -> Result<Span, LoadError> {
+        Ok(Span {
+            start: BytePos(self.u32()? as usize),
+            end: BytePos(self.u32()? as usize),
+        })
+    }
+
+    fn name(&mut self) -> Result<String, LoadError> {
+        let len = self.u32()? as usize;
+
+        let name = std::str::from_utf8(self.take(len)?)
+            .map_err(|_| malformed("function name is not UTF-8"))?;
+
+        Ok(name.to_owned())
+    }
+
+    fn function(&mut self) -> Result<FunctionCode, LoadError> {
+        let name = self.name()?;
+        let arity = self.u32()?;
+        let slot_count = self.u32()?;
+        let returns_value = self.bool()?;
+
+        let instr_count = self.u32()?;
+        let mut code = vec![];
+
+        for _ in 0..instr_count {
+            code.push(self.instr()?);
+        }
+
+        let param_count = self.u32()?;
+        let mut param_spans = vec![];
+
+        for _ in 0..param_count {
+            param_spans.push(self.span()?);
+        }
+
+        let instr_span_count = self.u32()?;
+        let mut instr_spans = vec![];
+
+        for _ in 0..instr_span_count {
+            instr_spans.push((self.u32()?, self.span()?));
+        }
+
+        if !instr_spans.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+            return Err(malformed("instruction spans are out of order"));
+        }
+
+        Ok(FunctionCode {
+            name,
+            arity,
+            slot_count,
+            returns_value,
+            code,
+            debug: DebugInfo {
+                param_spans,
+                instr_spans,
+            },
+        })
+    }
+
+    fn instr(&mut self) -> Result<Instr, LoadError> {
+        let instr = match self.u8()? {
+            0x01 => Instr::Const(self.u32()?),
+            0x02 => Instr::Load(self.u32()?),
+            0x03 => Instr::Store(self.u32()?),
+            0x04 => Instr::Pop,
+            0x10 => Instr::Add,
+            0x11 => Instr::Sub,
+            0x12 => Instr::Mul,
+            0x13 => Instr::Div,
+            0x14 => Instr::Rem,
+            0x15 => Instr::Less,
+            0x16 => Instr::LessEqual,
+            0x17 => Instr::AddOverflows,
+            0x18 => Instr::SubOverflows,
+            0x19 => Instr::CheckStep,
+            0x20 => Instr::Jump(self.u32()?),
+            0x21 => Instr::JumpIfZero(self.u32()?),
+            0x30 => Instr::Call(self.u32()?),
+            0x31 => Instr::Return,
+            0x32 => Instr::CallHost(self.u32()?),
+            _ => return Err(malformed("unknown opcode")),
+        };
+
+        Ok(instr)
+    }
+}
@@ -0,0 +1,440 @@
+use crate::ast::{BinaryOp, Expr, ExternFn, HostFnDecl, Program, Type};
+use crate::builtin::Builtin;
+use crate::bytecode::{DebugInfo, FunctionCode, HostFunctionImport, Instr, Module};
+use crate::compiler_context::CompilerContext;
+use crate::host::HashMap;
+use crate::ir::{self, BlockCall, BlockId, Callee, Inst, InstKind, Terminator, Value};
+use crate::ir_lower::IrLowering;
+use crate::resolve::Resolutions;
+use crate::scanner::Span;
+
+/// Compiles a program to bytecode, which the `Vm` runs.
+pub(crate) struct BytecodeGen<'ctx> {
+    ctx: &'ctx CompilerContext,
+    resolutions: &'ctx Resolutions,
+    host_fns: &'ctx [HostFnDecl],
+    /// Builtins and extern functions that the program calls, in the order
+    /// they're first called, which the module imports as host functions after
+    /// the embedder's.
+    imports: Vec<HostFunctionImport>,
+    extern_fns: HashMap<&'static str, ExternFn<'ctx>>,
+    /// Index of every function in the module, which is the order they're
+    /// declared in.
+    function_indices: HashMap<&'static str, u32>,
+    constants: Vec<i32>,
+    constant_indices: HashMap<i32, u32>,
+}
+
+impl<'ctx> BytecodeGen<'ctx> {
+    pub(crate) fn new(
+        ctx: &'ctx CompilerContext,
+        resolutions: &'ctx Resolutions,
+        host_fns: &'ctx [HostFnDecl],
+    ) -> BytecodeGen<'ctx> {
+        BytecodeGen {
+            ctx,
+            resolutions,
+            host_fns,
+            imports: vec![],
+            extern_fns: Default::default(),
+            function_indices: Default::default(),
+            constants: vec![],
+            constant_indices: Default::default(),
+        }
+    }
+
+    pub(crate) fn gen_module(mut self, program: Program<'ctx>) -> Module {
+        // The IR leaves out parameters of type `()`, but runtime errors point
+        // at any of them.
+        let mut param_spans = HashMap::default();
+
+        for decl in program.decls {
+            let name = self.ctx.resolve_symbol(decl.identifier);
+
+            match decl.value {
+                Expr::Function(function) => {
+                    let spans: Vec<_> =
+                        function.parameters.iter().map(|param| param.span).collect();
+                    param_spans.insert(name, spans);
+                }
+                Expr::Extern(extern_fn) => {
+                    self.extern_fns.insert(name, *extern_fn);
+                }
+                _ => {}
+            }
+        }
+
+        let ir_module =
+            IrLowering::new(self.ctx, self.resolutions, self.host_fns).lower_program(program);
+
+        for (idx, function) in ir_module.functions.iter().enumerate() {
+            self.function_indices.insert(function.name, idx as u32);
+        }
+
+        let functions = ir_module
+            .functions
+            .iter()
+            .map(|function| {
+                let param_spans = param_spans.remove(function.name).unwrap_or_default();
+
+                FunctionGen::new(&mut self, function).gen_function(param_spans)
+            })
+            .collect();
+
+        let host_functions = self
+            .host_fns
+            .iter()
+            .map(|host_fn| HostFunctionImport {
+                name: host_fn.name.clone(),
+                arity: host_fn
+                    .param_types
+                    .iter()
+                    .filter(|&&ty| ty == Type::I32)
+                    .count() as u32,
+                returns_value: host_fn.return_type == Type::I32,
+            })
+            .chain(self.imports)
+            .collect();
+
+        Module {
+            constants: self.constants,
+            host_functions,
+            functions,
+        }
+    }
+
+    /// Index of the builtin's import among the module's host functions.
+    fn builtin_import(&mut self, builtin: Builtin) -> u32 {
+        self.import(HostFunctionImport {
+            name: builtin.name().to_owned(),
+            arity: 1,
+            returns_value: false,
+        })
+    }
+
+    /// Index of the extern function's import among the module's host
+    /// functions, which the VM provides for functions of the C library that it
+    /// knows of, e.g., `putchar`.
+    fn extern_import(&mut self, name: &str) -> u32 {
+        let extern_fn = self.extern_fns[name];
+
+        self.import(HostFunctionImport {
+            name: name.to_owned(),
+            arity: extern_fn.parameters.len() as u32,
+            returns_value: extern_fn.return_type != Type::Unit,
+        })
+    }
+
+    fn import(&mut self, import: HostFunctionImport) -> u32 {
+        let idx = match self
+            .imports
+            .iter()
+            .position(|other| other.name == import.name)
+        {
+            Some(idx) => idx,
+            None => {
+                self.imports.push(import);
+
+                self.imports.len() - 1
+            }
+        };
+
+        (self.host_fns.len() + idx) as u32
+    }
+
+    fn constant(&mut self, value: i32) -> u32 {
+        *self.constant_indices.entry(value).or_insert_with(|| {
+            self.constants.push(value);
+
+            self.constants.len() as u32 - 1
+        })
+    }
+}
+
+/// Generates a function's blocks one after the other, in the order of the IR.
+///
+/// Values that are used right away, by the next instruction that pops them,
+/// are left on the stack. Constants are pushed wherever they're used, and the
+/// other values are stored in slots of their own.
+struct FunctionGen<'gen, 'ctx> {
+    codegen: &'gen mut BytecodeGen<'ctx>,
+    function: &'gen ir::Function,
+    use_counts: Vec<usize>,
+    is_stacked: Vec<bool>,
+    /// Value of every constant, by its number.
+    constants: Vec<Option<i32>>,
+    /// Slot of every stored value, by its number.
+    slots: Vec<Option<u32>>,
+    slot_count: u32,
+    code: Vec<Instr>,
+    instr_spans: Vec<(u32, Span)>,
+    /// Index of every block's first instruction, by its number.
+    block_starts: Vec<u32>,
+    /// Jumps to blocks, whose targets are patched in once every block's start
+    /// is known.
+    block_jumps: Vec<(usize, BlockId)>,
+}
+
+impl<'gen, 'ctx> FunctionGen<'gen, 'ctx> {
+    fn new(
+        codegen: &'gen mut BytecodeGen<'ctx>,
+        function: &'gen ir::Function,
+    ) -> FunctionGen<'gen, 'ctx> {
+        let use_counts = function.use_counts();
+        let is_stacked = function.stacked_values();
+        let mut constants = vec![None; use_counts.len()];
+
+        for inst in function.blocks.iter().flat_map(|block| &block.insts) {
+            if let (Some(result), InstKind::Const(value)) = (inst.result, &inst.kind) {
+                constants[result.0] = Some(*value);
+            }
+        }
+
+        let mut slots = vec![None; use_counts.len()];
+        let mut slot_count = 0;
+
+        for (value, slot) in slots.iter_mut().enumerate() {
+            if use_counts[value] > 0 && !is_stacked[value] && constants[value].is_none() {
+                *slot = Some(slot_count);
+                slot_count += 1;
+            }
+        }
+
+        FunctionGen {
+            codegen,
+            function,
+            use_counts,
+            is_stacked,
+            constants,
+            slots,
+            slot_count,
+            code: vec![],
+            instr_spans: vec![],
+            block_starts: vec![0; function.blocks.len()],
+            block_jumps: vec![],
+        }
+    }
+
+    fn gen_function(mut self, param_spans: Vec<Span>) -> FunctionCode {
+        let function = self.function;
+
+        // Arguments are passed on the stack, the last one on top.
+        self.gen_param_stores(function.params());
+
+        for (idx, block) in function.blocks.iter().enumerate() {
+            self.block_starts[idx] = self.code.len() as u32;
+
+            for inst in &block.insts {
+                self.gen_inst(inst);
+            }
+
+            self.gen_terminator(&block.terminator, BlockId(idx + 1));
+        }
+
+        // The last block loops forever, but running off the end of the code
+        // has to be ruled out anyway.
+        if self.code.last() != Some(&Instr::Return) {
+            if self.function.returns_value {
+                self.gen_constant(0);
+            }
+
+            self.emit(Instr::Return);
+        }
+
+        for &(jump_idx, block) in &self.block_jumps {
+            let target = self.block_starts[block.0];
+
+            match &mut self.code[jump_idx] {
+                Instr::Jump(jump_target) | Instr::JumpIfZero(jump_target) => *jump_target = target,
+                instr => unreachable!("{:?} is not a jump", instr),
+            }
+        }
+
+        FunctionCode {
+            name: self.function.name.to_owned(),
+            arity: self.function.params().len() as u32,
+            slot_count: self.slot_count,
+            returns_value: self.function.returns_value,
+            code: self.code,
+            debug: DebugInfo {
+                param_spans,
+                instr_spans: self.instr_spans,
+            },
+        }
+    }
+
+    fn gen_inst(&mut self, inst: &Inst) {
+        if let InstKind::Const(value) = inst.kind {
+            // Constants that aren't left on the stack are pushed by their uses.
+            if inst.result.is_some_and(|result| self.is_stacked[result.0]) {
+                self.gen_constant(value);
+            }
+
+            return;
+        }
+
+        self.gen_operands(&inst.kind.operands());
+
+        match &inst.kind {
+            InstKind::Const(_) => unreachable!("constants are pushed by their uses"),
+            InstKind::Binary { op, span, .. } => {
+                let instr = match op {
+                    BinaryOp::Add => Instr::Add,
+                    BinaryOp::Sub => Instr::Sub,
+                    BinaryOp::Mul => Instr::Mul,
+                    BinaryOp::Div => Instr::Div,
+                    BinaryOp::Rem => Instr::Rem,
+                };
+
+                let instr_idx = self.emit(instr);
+
+                if matches!(op, BinaryOp::Div | BinaryOp::Rem) {
+                    self.instr_spans.push((instr_idx as u32, *span));
+                }
+            }
+            InstKind::Less { .. } => {
+                self.emit(Instr::Less);
+            }
+            InstKind::LessEqual { .. } => {
+                self.emit(Instr::LessEqual);
+            }
+            InstKind::Overflows { op, .. } => {
+                let instr = match op {
+                    BinaryOp::Add => Instr::AddOverflows,
+                    BinaryOp::Sub => Instr::SubOverflows,
+                    _ => unreachable!("only steps of ranges are checked for overflows"),
+                };
+
+                self.emit(instr);
+            }
+            InstKind::CheckStep { span, .. } => {
+                let instr_idx = self.emit(Instr::CheckStep);
+                self.instr_spans.push((instr_idx as u32, *span));
+            }
+            InstKind::Call { callee, .. } => {
+                let instr = match *callee {
+                    Callee::Function(name) => Instr::Call(self.codegen.function_indices[name]),
+                    Callee::Builtin(builtin) => {
+                        Instr::CallHost(self.codegen.builtin_import(builtin))
+                    }
+                    Callee::Extern(name) => Instr::CallHost(self.codegen.extern_import(name)),
+                    Callee::Host(host_fn_idx) => Instr::CallHost(host_fn_idx as u32),
+                };
+
+                self.emit(instr);
+            }
+        }
+
+        match inst.result {
+            Some(result) if self.is_stacked[result.0] => {}
+            Some(result) => self.gen_store(result),
+            None => {}
+        }
+    }
+
+    fn gen_terminator(&mut self, terminator: &Terminator, next_block: BlockId) {
+        match terminator {
+            Terminator::Jump(call) => self.gen_block_call(call, Some(next_block)),
+            Terminator::Branch {
+                cond,
+                then_call,
+                else_call,
+            } => {
+                self.gen_operands(&[*cond]);
+
+                if then_call == else_call {
+                    self.emit(Instr::Pop);
+                    self.gen_block_call(then_call, Some(next_block));
+                } else if else_call.args.is_empty() {
+                    self.gen_jump(Instr::JumpIfZero(0), else_call.block);
+                    self.gen_block_call(then_call, Some(next_block));
+                } else {
+                    // The else-branch's arguments are passed right after the
+                    // then-branch's jump.
+                    let else_jump = self.emit(Instr::JumpIfZero(0));
+                    self.gen_block_call(then_call, None);
+
+                    let else_start = self.code.len() as u32;
+                    self.code[else_jump] = Instr::JumpIfZero(else_start);
+                    self.gen_block_call(else_call, Some(next_block));
+                }
+            }
+            Terminator::Return(value) => {
+                match value {
+                    Some(value) => self.gen_operands(&[*value]),
+                    // The function never returns, e.g., it ends in an infinite
+                    // loop, but the return still has to be well-formed.
+                    None if self.function.returns_value => self.gen_constant(0),
+                    None => {}
+                }
+
+                self.emit(Instr::Return);
+            }
+        }
+    }
+
+    /// Passes the arguments to the block's parameters that are used, then jumps
+    /// to it, unless it's the next block.
+    fn gen_block_call(&mut self, call: &BlockCall, next_block: Option<BlockId>) {
+        let function = self.function;
+        let (params, args): (Vec<_>, Vec<_>) = function.used_args(call, &self.use_counts).unzip();
+
+        self.gen_operands(&args);
+        self.gen_param_stores(&params);
+
+        if Some(call.block) != next_block {
+            self.gen_jump(Instr::Jump(0), call.block);
+        }
+    }
+
+    /// Pops the values on top of the stack into the parameters, the last one
+    /// on top.
+    fn gen_param_stores(&mut self, params: &[Value]) {
+        for &param in params.iter().rev() {
+            self.gen_store(param);
+        }
+    }
+
+    /// Pushes the operands that aren't on the stack already, which come after
+    /// the ones that are.
+    fn gen_operands(&mut self, operands: &[Value]) {
+        for &operand in operands {
+            if self.is_stacked[operand.0] {
+                continue;
+            }
+
+            match (self.slots[operand.0], self.constants[operand.0]) {
+                (Some(slot), _) => {
+                    self.emit(Instr::Load(slot));
+                }
+                (None, Some(value)) => self.gen_constant(value),
+                (None, None) => unreachable!("{} is used, so it's stored", operand),
+            }
+        }
+    }
+
+    /// Pops the value into its slot, or drops it if it's never used.
+    fn gen_store(&mut self, value: Value) {
+        match self.slots[value.0] {
+            Some(slot) => self.emit(Instr::Store(slot)),
+            None => self.emit(Instr::Pop),
+        };
+    }
+
+    fn gen_constant(&mut self, value: i32) {
+        let idx = self.codegen.constant(value);
+        self.emit(Instr::Const(idx));
+    }
+
+    fn gen_jump(&mut self, jump: Instr, block: BlockId) {
+        let jump_idx = self.emit(jump);
+        self.block_jumps.push((jump_idx, block));
+    }
+
+    /// Emits the instruction, returning its index.
+    fn emit(&mut self, instr: Instr) -> usize {
+        self.code.push(instr);
+
+        self.code.len() - 1
+    }
+}
@@ -0,0 +1,661 @@
+use std::fmt::Write;
+
+use crate::ast::{BinaryOp, Expr, ExternFn, Program, Type};
+use crate::builtin::Builtin;
+use crate::compiler_context::CompilerContext;
+use crate::ir::{self, BlockCall, BlockId, Callee, Inst, InstKind, Terminator, Value};
+use crate::ir_lower::IrLowering;
+use crate::profile::{IntProfile, Overflow};
+use crate::resolve::Resolutions;
+
+/// Arithmetic that wraps around. It's done on unsigned integers, as signed
+/// overflow is undefined behavior in C, and on `unsigned long`, as smaller
+/// integers would be promoted to `int`, which could overflow again.
+const WRAPPING_ARITHMETIC: &str = "\
+static inline sophia_int sophia_add(sophia_int lhs, sophia_int rhs) {
+    return (sophia_int)(sophia_uint)((unsigned long)lhs + (unsigned long)rhs);
+}
+
+static inline sophia_int sophia_sub(sophia_int lhs, sophia_int rhs) {
+    return (sophia_int)(sophia_uint)((unsigned long)lhs - (unsigned long)rhs);
+}
+
+static inline sophia_int sophia_mul(sophia_int lhs, sophia_int rhs) {
+    return (sophia_int)(sophia_uint)((unsigned long)lhs * (unsigned long)rhs);
+}
+";
+
+/// Arithmetic that aborts on overflow, which it checks for on `long long`, as
+/// that's wide enough to hold any result.
+const TRAPPING_ARITHMETIC: &str = "\
+static inline sophia_int sophia_checked(long long value) {
+    if (value < SOPHIA_INT_MIN || value > SOPHIA_INT_MAX) {
+        abort();
+    }
+    return (sophia_int)value;
+}
+
+static inline sophia_int sophia_add(sophia_int lhs, sophia_int rhs) {
+    return sophia_checked((long long)lhs + rhs);
+}
+
+static inline sophia_int sophia_sub(sophia_int lhs, sophia_int rhs) {
+    return sophia_checked((long long)lhs - rhs);
+}
+
+static inline sophia_int sophia_mul(sophia_int lhs, sophia_int rhs) {
+    return sophia_checked((long long)lhs * rhs);
+}
+";
+
+/// Checks of whether stepping a range overflows, which stops the loop rather
+/// than wrapping around or aborting, whatever the profile says about
+/// overflows.
+const STEP_OVERFLOWS: &str = "\
+static inline int sophia_add_overflows(sophia_int lhs, sophia_int rhs) {
+    long long value = (long long)lhs + rhs;
+    return value < SOPHIA_INT_MIN || value > SOPHIA_INT_MAX;
+}
+
+static inline int sophia_sub_overflows(sophia_int lhs, sophia_int rhs) {
+    long long value = (long long)lhs - rhs;
+    return value < SOPHIA_INT_MIN || value > SOPHIA_INT_MAX;
+}
+";
+
+/// Divisions with C's own operators, which are undefined behavior on the
+/// cases that trap on x86, so they abort the program instead.
+const HARDWARE_DIVISION: &str = "\
+static inline sophia_int sophia_div(sophia_int lhs, sophia_int rhs) {
+    if (rhs == 0 || (lhs == SOPHIA_INT_MIN && rhs == -1)) {
+        abort();
+    }
+    return lhs / rhs;
+}
+
+static inline sophia_int sophia_rem(sophia_int lhs, sophia_int rhs) {
+    if (rhs == 0 || (lhs == SOPHIA_INT_MIN && rhs == -1)) {
+        abort();
+    }
+    return lhs % rhs;
+}
+";
+
+/// Divisions by shifting and subtracting, for targets without a division
+/// instruction, where C's own operators would pull in a large runtime, if
+/// any. The quotient is truncated and the remainder takes the sign of the
+/// dividend, just like C's operators do.
+const SOFTWARE_DIVISION: &str = "\
+static inline sophia_uint sophia_udivmod(sophia_uint dividend, sophia_uint divisor, sophia_uint *remainder) {
+    sophia_uint quotient = 0;
+    sophia_uint partial = 0;
+    int bit;
+    for (bit = SOPHIA_INT_BITS - 1; bit >= 0; bit--) {
+        partial = (sophia_uint)((sophia_uint)(partial << 1) | ((dividend >> bit) & 1u));
+        if (partial >= divisor) {
+            partial = (sophia_uint)(partial - divisor);
+            quotient = (sophia_uint)(quotient | ((sophia_uint)1 << bit));
+        }
+    }
+    *remainder = partial;
+    return quotient;
+}
+
+static inline sophia_uint sophia_magnitude(sophia_int value) {
+    return value < 0 ? (sophia_uint)(0u - (sophia_uint)value) : (sophia_uint)value;
+}
+
+static inline sophia_int sophia_negate_if(int negate, sophia_uint magnitude) {
+    return negate ? (sophia_int)(sophia_uint)(0u - magnitude) : (sophia_int)magnitude;
+}
+
+static inline sophia_int sophia_divmod(sophia_int lhs, sophia_int rhs, sophia_int *remainder) {
+    sophia_uint quotient;
+    sophia_uint remainder_magnitude;
+    if (rhs == 0 || (lhs == SOPHIA_INT_MIN && rhs == -1)) {
+        abort();
+    }
+    quotient = sophia_udivmod(sophia_magnitude(lhs), sophia_magnitude(rhs), &remainder_magnitude);
+    *remainder = sophia_negate_if(lhs < 0, remainder_magnitude);
+    return sophia_negate_if((lhs < 0) != (rhs < 0), quotient);
+}
+
+static inline sophia_int sophia_div(sophia_int lhs, sophia_int rhs) {
+    sophia_int remainder;
+    return sophia_divmod(lhs, rhs, &remainder);
+}
+
+static inline sophia_int sophia_rem(sophia_int lhs, sophia_int rhs) {
+    sophia_int remainder;
+    sophia_divmod(lhs, rhs, &remainder);
+    return remainder;
+}
+";
+
+/// Definitions that the generated code relies on, which follow the integer
+/// profile: `sophia_int` is the type of sophia's integers, and its operations
+/// are done by helpers, as C's own operators are undefined behavior on the
+/// cases that matter.
+pub(crate) fn prelude(int_profile: IntProfile) -> String {
+    let bits = int_profile.width.bits();
+
+    let mut prelude = String::new();
+    writeln!(prelude, "#include <stdint.h>").unwrap();
+    writeln!(prelude, "#include <stdlib.h>").unwrap();
+    writeln!(prelude).unwrap();
+    writeln!(prelude, "typedef int{}_t sophia_int;", bits).unwrap();
+    writeln!(prelude, "typedef uint{}_t sophia_uint;", bits).unwrap();
+    writeln!(prelude, "#define SOPHIA_INT_MIN INT{}_MIN", bits).unwrap();
+    writeln!(prelude, "#define SOPHIA_INT_MAX INT{}_MAX", bits).unwrap();
+    writeln!(prelude, "#define SOPHIA_INT_BITS {}", bits).unwrap();
+    writeln!(prelude).unwrap();
+
+    prelude.push_str(match int_profile.overflow {
+        Overflow::Wrap => WRAPPING_ARITHMETIC,
+        Overflow::Trap => TRAPPING_ARITHMETIC,
+    });
+
+    writeln!(prelude).unwrap();
+    prelude.push_str(STEP_OVERFLOWS);
+    writeln!(prelude).unwrap();
+
+    prelude.push_str(if int_profile.has_hardware_division {
+        HARDWARE_DIVISION
+    } else {
+        SOFTWARE_DIVISION
+    });
+
+    prelude
+}
+
+/// Functions of the runtime library that builtins call, which are defined in
+/// the source file itself, so that it doesn't have to be linked with anything.
+fn runtime(builtins: &[Builtin]) -> String {
+    let mut runtime = String::new();
+    writeln!(runtime, "#include <stdio.h>").unwrap();
+
+    for &builtin in builtins {
+        let newline = match builtin {
+            Builtin::Print => "",
+            Builtin::Println => "\\n",
+        };
+
+        writeln!(runtime).unwrap();
+        writeln!(
+            runtime,
+            "static void {}(sophia_int value) {{",
+            builtin.runtime_symbol()
+        )
+        .unwrap();
+        writeln!(
+            runtime,
+            "    printf(\"%lld{}\", (long long) value);",
+            newline
+        )
+        .unwrap();
+        writeln!(runtime, "}}").unwrap();
+    }
+
+    runtime
+}
+
+/// Transpiles a program to portable C99, so that any platform with a C
+/// compiler can build it natively, following the integer profile of the
+/// target.
+///
+/// The program is lowered to the IR first. Every function `f` becomes a C
+/// function `fn_f`, so that its name can't clash with C's keywords or its
+/// standard library, and the C `main` calls `fn_main`. Every value becomes a
+/// local, which jumps assign to for the parameters of blocks, and every block
+/// that's jumped to gets a label. Constants are written where they're used
+/// instead.
+pub(crate) struct CCodeGen<'ctx> {
+    ctx: &'ctx CompilerContext,
+    resolutions: &'ctx Resolutions,
+    int_profile: IntProfile,
+}
+
+impl<'ctx> CCodeGen<'ctx> {
+    pub(crate) fn new(
+        ctx: &'ctx CompilerContext,
+        resolutions: &'ctx Resolutions,
+        int_profile: IntProfile,
+    ) -> CCodeGen<'ctx> {
+        CCodeGen {
+            ctx,
+            resolutions,
+            int_profile,
+        }
+    }
+
+    pub(crate) fn gen_source_file(self, program: Program<'ctx>) -> String {
+        let mut source_file = prelude(self.int_profile);
+
+        let builtins = self.resolutions.builtins();
+
+        if !builtins.is_empty() {
+            writeln!(source_file).unwrap();
+            source_file.push_str(&runtime(&builtins));
+        }
+
+        // Functions are declared upfront, so that they can call each other in
+        // any order.
+        writeln!(source_file).unwrap();
+
+        // Extern functions keep their names, as they're defined in C.
+        for decl in program.decls {
+            if let Expr::Extern(extern_fn) = decl.value {
+                let name = self.ctx.resolve_symbol(decl.identifier);
+                writeln!(source_file, "{};", extern_prototype(name, *extern_fn)).unwrap();
+            }
+        }
+
+        let module = IrLowering::new(self.ctx, self.resolutions, &[]).lower_program(program);
+
+        for function in &module.functions {
+            writeln!(source_file, "{};", prototype(function)).unwrap();
+        }
+
+        for function in &module.functions {
+            writeln!(source_file).unwrap();
+            source_file.push_str(&FunctionGen::new(function).gen_function());
+        }
+
+        if let Some(main_function) = module.functions.iter().find(|f| f.name == "main") {
+            writeln!(source_file).unwrap();
+            writeln!(source_file, "int main(void) {{").unwrap();
+
+            if main_function.returns_value {
+                writeln!(source_file, "    return fn_main();").unwrap();
+            } else {
+                writeln!(source_file, "    fn_main();").unwrap();
+                writeln!(source_file, "    return 0;").unwrap();
+            }
+
+            writeln!(source_file, "}}").unwrap();
+        }
+
+        source_file
+    }
+}
+
+/// Condition of a branch, which is written along with its negation, so that
+/// the branch can go to whichever of its blocks doesn't come next.
+#[derive(Clone)]
+struct Cond {
+    is_true: String,
+    is_false: String,
+}
+
+struct FunctionGen<'f> {
+    function: &'f ir::Function,
+    use_counts: Vec<usize>,
+    is_needed: Vec<bool>,
+    /// Constant of every value that is one, by the value's number.
+    constants: Vec<Option<i32>>,
+    /// Comparisons, by the number of their value, that are used only by the
+    /// branch of their block, which are written in the branch's condition.
+    branch_conds: Vec<Option<Cond>>,
+    /// Blocks that are jumped to, rather than only come next after the block
+    /// before them, which need a label.
+    is_jumped_to: Vec<bool>,
+    /// Code of the block being generated.
+    code: String,
+}
+
+impl<'f> FunctionGen<'f> {
+    fn new(function: &'f ir::Function) -> FunctionGen<'f> {
+        let use_counts = function.use_counts();
+        let value_count = use_counts.len();
+
+        let mut function_gen = FunctionGen {
+            function,
+            use_counts,
+            is_needed: function.needed_values(),
+            constants: vec![None; value_count],
+            branch_conds: (0..value_count).map(|_| None).collect(),
+            is_jumped_to: vec![false; function.blocks.len()],
+            code: String::new(),
+        };
+
+        for inst in function.blocks.iter().flat_map(|block| &block.insts) {
+            if let (Some(result), InstKind::Const(value)) = (inst.result, &inst.kind) {
+                function_gen.constants[result.0] = Some(*value);
+            }
+        }
+
+        for block in &function.blocks {
+            let Terminator::Branch { cond, .. } = block.terminator else {
+                continue;
+            };
+
+            if function_gen.use_counts[cond.0] != 1 {
+                continue;
+            }
+
+            let cond_inst = block.insts.iter().find(|inst| inst.result == Some(cond));
+
+            let (lhs, rhs, is_true, is_false) = match cond_inst.map(|inst| &inst.kind) {
+                Some(InstKind::Less { lhs, rhs }) => (lhs, rhs, "<", ">="),
+                Some(InstKind::LessEqual { lhs, rhs }) => (lhs, rhs, "<=", ">"),
+                Some(InstKind::Overflows { op, lhs, rhs }) => {
+                    let check = format!(
+                        "{}({}, {})",
+                        overflow_helper(*op),
+                        function_gen.operand(*lhs),
+                        function_gen.operand(*rhs)
+                    );
+
+                    function_gen.branch_conds[cond.0] = Some(Cond {
+                        is_false: format!("!{}", check),
+                        is_true: check,
+                    });
+
+                    continue;
+                }
+                _ => continue,
+            };
+
+            let lhs = function_gen.operand(*lhs);
+            let rhs = function_gen.operand(*rhs);
+
+            function_gen.branch_conds[cond.0] = Some(Cond {
+                is_true: format!("{} {} {}", lhs, is_true, rhs),
+                is_false: format!("{} {} {}", lhs, is_false, rhs),
+            });
+        }
+
+        function_gen
+    }
+
+    fn gen_function(mut self) -> String {
+        let mut locals = String::new();
+        let params = self.function.params();
+
+        for idx in 0..self.use_counts.len() {
+            let value = Value(idx);
+            let is_stored = !params.contains(&value)
+                && self.is_needed[idx]
+                && self.constants[idx].is_none()
+                && self.branch_conds[idx].is_none();
+
+            if is_stored {
+                writeln!(locals, "    sophia_int {};", value).unwrap();
+            }
+        }
+
+        let mut block_codes = vec![];
+
+        for idx in 0..self.function.blocks.len() {
+            self.gen_block(BlockId(idx));
+            block_codes.push(std::mem::take(&mut self.code));
+        }
+
+        let mut body = String::new();
+
+        for (idx, block_code) in block_codes.into_iter().enumerate() {
+            if self.is_jumped_to[idx] {
+                writeln!(body, "{}:;", BlockId(idx)).unwrap();
+            }
+
+            body.push_str(&block_code);
+        }
+
+        format!("{} {{\n{}{}}}\n", prototype(self.function), locals, body)
+    }
+
+    fn gen_block(&mut self, block_id: BlockId) {
+        let block = &self.function.blocks[block_id.0];
+
+        for inst in &block.insts {
+            self.gen_inst(inst);
+        }
+
+        let next_block = BlockId(block_id.0 + 1);
+
+        match &block.terminator {
+            Terminator::Jump(call) => self.gen_block_call(call, next_block),
+            Terminator::Branch {
+                cond,
+                then_call,
+                else_call,
+            } => {
+                let cond = match &self.branch_conds[cond.0] {
+                    Some(cond) => cond.clone(),
+                    None => Cond {
+                        is_true: format!("{} != 0", self.operand(*cond)),
+                        is_false: format!("{} == 0", self.operand(*cond)),
+                    },
+                };
+
+                self.gen_branch(cond, then_call, else_call, next_block);
+            }
+            Terminator::Return(Some(value)) => {
+                let value = self.operand(*value);
+                self.emit(format!("return {};", value));
+            }
+            // The function never returns, e.g., it ends in an infinite loop, but
+            // C compilers can't tell.
+            Terminator::Return(None) if self.function.returns_value => self.emit("abort();"),
+            // Void functions return at their end anyway.
+            Terminator::Return(None) if next_block.0 == self.function.blocks.len() => {}
+            Terminator::Return(None) => self.emit("return;"),
+        }
+    }
+
+    fn gen_inst(&mut self, inst: &Inst) {
+        let expr = match &inst.kind {
+            InstKind::Const(_) => return,
+            InstKind::Binary { op, lhs, rhs, .. } => {
+                let helper = match op {
+                    BinaryOp::Add => "sophia_add",
+                    BinaryOp::Sub => "sophia_sub",
+                    BinaryOp::Mul => "sophia_mul",
+                    BinaryOp::Div => "sophia_div",
+                    BinaryOp::Rem => "sophia_rem",
+                };
+
+                format!("{}({}, {})", helper, self.operand(*lhs), self.operand(*rhs))
+            }
+            InstKind::Less { lhs, rhs } => {
+                format!("{} < {}", self.operand(*lhs), self.operand(*rhs))
+            }
+            InstKind::LessEqual { lhs, rhs } => {
+                format!("{} <= {}", self.operand(*lhs), self.operand(*rhs))
+            }
+            InstKind::Overflows { op, lhs, rhs } => format!(
+                "{}({}, {})",
+                overflow_helper(*op),
+                self.operand(*lhs),
+                self.operand(*rhs)
+            ),
+            InstKind::CheckStep { step, .. } => {
+                let step = self.operand(*step);
+                self.emit(format!("if ({} <= 0) abort();", step));
+
+                return;
+            }
+            InstKind::Call { callee, args } => {
+                let callee = match callee {
+                    Callee::Function(name) => format!("fn_{}", name),
+                    Callee::Builtin(builtin) => builtin.runtime_symbol().to_owned(),
+                    Callee::Extern(name) => name.to_string(),
+                    Callee::Host(_) => unreachable!(
+                        "programs that call host functions are rejected before code generation"
+                    ),
+                };
+                let args: Vec<_> = args.iter().map(|&arg| self.operand(arg)).collect();
+
+                format!("{}({})", callee, args.join(", "))
+            }
+        };
+
+        match inst.result {
+            Some(result) if self.branch_conds[result.0].is_some() => {}
+            Some(result) if self.is_needed[result.0] => {
+                self.emit(format!("{} = {};", result, expr))
+            }
+            // Comparisons that aren't needed can be left out, but divisions may
+            // still abort the program.
+            Some(_)
+                if matches!(
+                    inst.kind,
+                    InstKind::Less { .. } | InstKind::LessEqual { .. } | InstKind::Overflows { .. }
+                ) => {}
+            Some(_) if matches!(inst.kind, InstKind::Binary { .. }) => {
+                self.emit(format!("(void){};", expr))
+            }
+            _ => self.emit(format!("{};", expr)),
+        }
+    }
+
+    /// Goes to one block or the other depending on the condition. The block
+    /// that comes next is gone to by not jumping at all, unless arguments
+    /// have to be passed to it.
+    fn gen_branch(
+        &mut self,
+        cond: Cond,
+        then_call: &BlockCall,
+        else_call: &BlockCall,
+        next_block: BlockId,
+    ) {
+        if then_call == else_call {
+            self.gen_block_call(then_call, next_block);
+        } else if then_call.args.is_empty() && then_call.block != next_block {
+            self.emit(format!("if ({}) goto {};", cond.is_true, then_call.block));
+            self.is_jumped_to[then_call.block.0] = true;
+            self.gen_block_call(else_call, next_block);
+        } else if else_call.args.is_empty() {
+            self.emit(format!("if ({}) goto {};", cond.is_false, else_call.block));
+            self.is_jumped_to[else_call.block.0] = true;
+            self.gen_block_call(then_call, next_block);
+        } else {
+            self.emit(format!("if ({}) {{", cond.is_true));
+            self.gen_nested_block_call(then_call);
+            self.emit("}");
+            self.gen_block_call(else_call, next_block);
+        }
+    }
+
+    /// Passes the arguments to the block, then goes to it, unless it's the next
+    /// block.
+    fn gen_block_call(&mut self, call: &BlockCall, next_block: BlockId) {
+        self.gen_args(call, "    ");
+
+        if call.block != next_block {
+            self.emit(format!("goto {};", call.block));
+            self.is_jumped_to[call.block.0] = true;
+        }
+    }
+
+    fn gen_nested_block_call(&mut self, call: &BlockCall) {
+        self.gen_args(call, "        ");
+        writeln!(self.code, "        goto {};", call.block).unwrap();
+        self.is_jumped_to[call.block.0] = true;
+    }
+
+    /// Assigns the arguments to the block's parameters, all at once, through
+    /// temporaries if some argument is a parameter that's assigned first.
+    /// Parameters that aren't needed are left out.
+    fn gen_args(&mut self, call: &BlockCall, indentation: &str) {
+        let params = &self.function.blocks[call.block.0].params;
+
+        let moves: Vec<_> = params
+            .iter()
+            .zip(&call.args)
+            .filter(|&(&param, &arg)| self.is_needed[param.0] && param != arg)
+            .map(|(&param, &arg)| (param, arg))
+            .collect();
+
+        let is_clobbered = moves
+            .iter()
+            .any(|&(_, arg)| moves.iter().any(|&(param, _)| param == arg));
+
+        if is_clobbered {
+            writeln!(self.code, "{}{{", indentation).unwrap();
+
+            for (idx, &(_, arg)) in moves.iter().enumerate() {
+                let arg = self.operand(arg);
+                writeln!(
+                    self.code,
+                    "{}    sophia_int arg{} = {};",
+                    indentation, idx, arg
+                )
+                .unwrap();
+            }
+
+            for (idx, &(param, _)) in moves.iter().enumerate() {
+                writeln!(self.code, "{}    {} = arg{};", indentation, param, idx).unwrap();
+            }
+
+            writeln!(self.code, "{}}}", indentation).unwrap();
+        } else {
+            for &(param, arg) in &moves {
+                let arg = self.operand(arg);
+                writeln!(self.code, "{}{} = {};", indentation, param, arg).unwrap();
+            }
+        }
+    }
+
+    /// C expression for the value, which is either a constant or a local.
+    fn operand(&self, value: Value) -> String {
+        match self.constants[value.0] {
+            Some(constant) => constant.to_string(),
+            None => value.to_string(),
+        }
+    }
+
+    fn emit(&mut self, statement: impl AsRef<str>) {
+        writeln!(self.code, "    {}", statement.as_ref()).unwrap();
+    }
+}
+
+/// C declaration of the function, without the trailing semicolon.
+fn prototype(function: &ir::Function) -> String {
+    let return_type = if function.returns_value {
+        "sophia_int"
+    } else {
+        "void"
+    };
+
+    let params: Vec<_> = function
+        .params()
+        .iter()
+        .map(|param| format!("sophia_int {}", param))
+        .collect();
+
+    let params = if params.is_empty() {
+        "void".to_owned()
+    } else {
+        params.join(", ")
+    };
+
+    format!("{} fn_{}({})", return_type, function.name, params)
+}
+
+/// C declaration of the extern function, without the trailing semicolon, which
+/// takes and returns `int`s, as programs with integers of other types are
+/// rejected before code generation.
+fn extern_prototype(name: &str, extern_fn: ExternFn) -> String {
+    let return_type = match extern_fn.return_type {
+        Type::Unit => "void",
+        _ => "int",
+    };
+
+    let params = if extern_fn.parameters.is_empty() {
+        "void".to_owned()
+    } else {
+        vec!["int"; extern_fn.parameters.len()].join(", ")
+    };
+
+    format!("{} {}({})", return_type, name, params)
+}
+
+/// Helper of the prelude that checks whether stepping a range overflows.
+fn overflow_helper(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "sophia_add_overflows",
+        BinaryOp::Sub => "sophia_sub_overflows",
+        _ => unreachable!("only steps of ranges are checked for overflows"),
+    }
+}
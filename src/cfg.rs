@@ -0,0 +1,167 @@
+use std::fmt::Write;
+
+use crate::ir::{BlockId, Function, Module, Terminator};
+
+/// Control-flow graph of a function, i.e., which blocks each block can go to
+/// next, and which ones can come before it, for analyses to walk in either
+/// direction.
+pub(crate) struct Cfg {
+    successors: Vec<Vec<BlockId>>,
+    predecessors: Vec<Vec<BlockId>>,
+}
+
+impl Cfg {
+    pub(crate) fn of_function(function: &Function) -> Cfg {
+        let block_count = function.blocks.len();
+        let mut successors = Vec::with_capacity(block_count);
+        let mut predecessors = vec![vec![]; block_count];
+
+        for (idx, block) in function.blocks.iter().enumerate() {
+            let block_successors = block.terminator.successors();
+
+            for &successor in &block_successors {
+                // A branch to the same block either way is a single edge.
+                if !predecessors[successor.0].contains(&BlockId(idx)) {
+                    predecessors[successor.0].push(BlockId(idx));
+                }
+            }
+
+            successors.push(block_successors);
+        }
+
+        Cfg {
+            successors,
+            predecessors,
+        }
+    }
+
+    pub(crate) fn block_count(&self) -> usize {
+        self.successors.len()
+    }
+
+    /// Blocks the block goes to, in the order its terminator lists them, so
+    /// that a branch's block for a true condition comes first.
+    pub(crate) fn successors(&self, block: BlockId) -> &[BlockId] {
+        &self.successors[block.0]
+    }
+
+    pub(crate) fn predecessors(&self, block: BlockId) -> &[BlockId] {
+        &self.predecessors[block.0]
+    }
+
+    /// Immediate dominator of every block, by its number, which is the closest
+    /// block that every path from the entry block to it goes through. The
+    /// entry block is its own.
+    ///
+    /// This is the algorithm of Cooper, Harvey and Kennedy, which relies on the
+    /// blocks being numbered in reverse postorder, as they are.
+    pub(crate) fn immediate_dominators(&self) -> Vec<BlockId> {
+        let mut idoms = vec![None; self.block_count()];
+        idoms[0] = Some(BlockId(0));
+
+        let mut has_changed = true;
+
+        while has_changed {
+            has_changed = false;
+
+            for idx in 1..self.block_count() {
+                let mut new_idom = None;
+
+                // Predecessors that haven't been visited yet are skipped, as
+                // they come later, through a back edge.
+                for &pred in self.predecessors(BlockId(idx)) {
+                    if idoms[pred.0].is_some() {
+                        new_idom = Some(match new_idom {
+                            Some(other) => intersect(&idoms, pred, other),
+                            None => pred,
+                        });
+                    }
+                }
+
+                if new_idom != idoms[idx] {
+                    idoms[idx] = new_idom;
+                    has_changed = true;
+                }
+            }
+        }
+
+        idoms
+            .into_iter()
+            .map(|idom| idom.expect("every block is reachable from the entry block"))
+            .collect()
+    }
+}
+
+/// Closest block that dominates both blocks, walking up the dominator tree
+/// from whichever one comes later.
+fn intersect(idoms: &[Option<BlockId>], mut a: BlockId, mut b: BlockId) -> BlockId {
+    while a != b {
+        while a.0 > b.0 {
+            a = idoms[a.0].unwrap();
+        }
+
+        while b.0 > a.0 {
+            b = idoms[b.0].unwrap();
+        }
+    }
+
+    a
+}
+
+/// Renders the control-flow graph of every function in the Graphviz dot
+/// language, each function in its own cluster, and each block labeled with its
+/// code.
+pub(crate) fn to_dot(module: &Module) -> String {
+    let mut dot = String::new();
+    writeln!(dot, "digraph cfg {{").unwrap();
+    writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+
+    for function in &module.functions {
+        let cfg = Cfg::of_function(function);
+        let node = |block: BlockId| format!("\"{}.{}\"", function.name, block);
+
+        writeln!(dot, "    subgraph \"cluster_{}\" {{", function.name).unwrap();
+        writeln!(dot, "        label=\"{}\";", escape(&function.header())).unwrap();
+
+        for (idx, block) in function.blocks.iter().enumerate() {
+            let mut lines = vec![format!("{}:", block.label(BlockId(idx)))];
+            lines.extend(block.insts.iter().map(|inst| format!("    {}", inst)));
+            lines.push(format!("    {}", block.terminator));
+
+            // Lines end in `\l` to be left-justified.
+            let label: String = lines
+                .iter()
+                .map(|line| format!("{}\\l", escape(line)))
+                .collect();
+
+            writeln!(dot, "        {} [label=\"{}\"];", node(BlockId(idx)), label).unwrap();
+        }
+
+        for idx in 0..cfg.block_count() {
+            let block = BlockId(idx);
+            let is_branch = matches!(function.blocks[idx].terminator, Terminator::Branch { .. });
+
+            for (successor_idx, &successor) in cfg.successors(block).iter().enumerate() {
+                write!(dot, "        {} -> {}", node(block), node(successor)).unwrap();
+
+                if is_branch {
+                    let label = if successor_idx == 0 { "true" } else { "false" };
+                    write!(dot, " [label=\"{}\"]", label).unwrap();
+                }
+
+                writeln!(dot, ";").unwrap();
+            }
+        }
+
+        writeln!(dot, "    }}").unwrap();
+    }
+
+    writeln!(dot, "}}").unwrap();
+
+    dot
+}
+
+/// Escapes the text for a quoted string in the dot language.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,1311 @@
+use std::cell::Cell;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+use crate::build_info::build_info;
+use crate::bytecode;
+use crate::daemon::Daemon;
+use crate::diagnostics::{CompileWarning, Diagnostic, Lint, LintLevel, Remark, LINT_GROUPS};
+use crate::doctest::{
+    check_doc_example, extract_doc_examples, with_doc_example, DocExampleFailure,
+};
+use crate::driver::{
+    check, compile, compile_bytecode, compile_object, expand, report_features, run, run_vm, Emit,
+    Options, Stage,
+};
+use crate::error_codes;
+use crate::fixer;
+use crate::formatter;
+use crate::linker::{self, LinkOutput};
+use crate::lint_config::{self, LintConfig};
+use crate::lsp::LanguageServer;
+use crate::manifest::{ArtifactKind, Manifest};
+use crate::profile::{IntWidth, Overflow};
+use crate::query::QueryDatabase;
+use crate::render::{count_of, DiagnosticCounts, DiagnosticRenderer, ErrorFormat};
+use crate::repl::{self, Repl};
+use crate::source_map::SourceMap;
+use crate::timing;
+use crate::vm::Vm;
+use crate::watch::FileWatcher;
+
+const USAGE: &str = "usage: sophia [build | run [--engine=interp|vm|jit | --jit | --profile [--profile-folded <path>]]]
+              [--script] [--check [--watch] | --report-features]
+              [--emit=asm|obj|llvm-ir|wat|c|ir|cfg|source|tokens|ast|cst|bytecode | --no-link]
+              [--int-width=16|32] [--overflow=wrap|trap] [--soft-div]
+              [-W <lint>] [-A <lint>] [-D <lint>] [--remarks] [--color=auto|always|never]
+              [--error-format=human|json] [--quiet | --summary]
+              [--max-nesting-depth=<depth>] [-Ztime-passes]
+              [-o <output> [--manifest <path>]] <file>...
+       sophia run <file.sbc>
+       sophia expand [--stage=parse|dce] [--script] <file>...
+       sophia test [--int-width=16|32] [--overflow=wrap|trap] [--soft-div] <file>...
+       sophia fmt [--check] <file>...
+       sophia fix <file>
+       sophia repl
+       sophia lsp
+       sophia daemon [--socket <path>]
+       sophia --explain <code>
+       sophia --version [--verbose]";
+
+/// How often the files of `--watch` are read for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+enum Command {
+    Compile,
+    Check,
+    /// Prints the language features the program uses, as JSON.
+    ReportFeatures,
+    Run {
+        engine: Engine,
+    },
+    /// Prints the program back to source code after each stage that
+    /// transforms it, or only after the given one.
+    Expand {
+        stage: Option<Stage>,
+    },
+    /// Compiles and runs the examples in the program's doc comments.
+    Test,
+}
+
+/// What runs programs for `sophia run`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Engine {
+    /// Tree-walking interpreter, which runs the program as it's checked.
+    Interpreter,
+    /// Stack VM, which runs the program compiled to bytecode.
+    Vm,
+    /// Machine code compiled in-process, if built with the `jit` feature.
+    Jit,
+}
+
+/// Kinds of code that aren't text, which `Emit` doesn't cover.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BinaryEmit {
+    Bytecode,
+    /// Relocatable object file of the program's machine code, see
+    /// `ObjectFile`.
+    Object,
+}
+
+/// Which diagnostics are reported, see `Reporter`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ReportMode {
+    All,
+    /// Only errors, which include warnings of denied lints.
+    Quiet,
+    /// Only how many diagnostics there were of each severity, once the
+    /// command is done.
+    Summary,
+}
+
+/// Runs the `sophia` command line tool with the arguments of the process.
+///
+/// It exits with a failure if the program has errors, or warnings of lints
+/// denied with `-D`, but not if it only has warnings. Running a program exits
+/// with the program's exit code instead, once it compiled.
+///
+/// The lint levels of each source file come from the `sophia-lints.toml` files
+/// in its directory and in the ones above, see `LintConfig`, and then from the
+/// `-W`, `-A` and `-D` flags, which override them.
+pub fn main() -> ExitCode {
+    let mut args = env::args().skip(1).peekable();
+
+    if args.next_if_eq("daemon").is_some() {
+        return serve_daemon(args);
+    }
+
+    if args.next_if_eq("fmt").is_some() {
+        return format_files(args);
+    }
+
+    if args.next_if_eq("fix").is_some() {
+        return fix_file(args);
+    }
+
+    if args.next_if_eq("lsp").is_some() {
+        return serve_language_server(args);
+    }
+
+    if args.next_if_eq("repl").is_some() {
+        return run_repl(args);
+    }
+
+    if args.next_if_eq("--explain").is_some() {
+        return explain(args);
+    }
+
+    if args.next_if_eq("--version").is_some() {
+        return version(args);
+    }
+
+    let mut command = if args.next_if_eq("run").is_some() {
+        Command::Run {
+            engine: Engine::Interpreter,
+        }
+    } else if args.next_if_eq("expand").is_some() {
+        Command::Expand { stage: None }
+    } else if args.next_if_eq("test").is_some() {
+        Command::Test
+    } else {
+        // Building is what happens without a command anyway.
+        args.next_if_eq("build");
+
+        Command::Compile
+    };
+    let mut options = Options::default();
+    // Bytecode and object files aren't text, so they're generated apart from
+    // the other kinds of code.
+    let mut emit_binary = None;
+    // Without `--emit`, the assembly is linked into an executable.
+    let mut emit_given = false;
+    let mut no_link = false;
+    let mut input_paths = vec![];
+    let mut output_path = None;
+    let mut manifest_path = None;
+    let mut folded_stacks_path = None;
+    let mut colors = io::stderr().is_terminal();
+    let mut error_format = ErrorFormat::Human;
+    let mut report_mode = ReportMode::All;
+    let mut watch = false;
+    // Levels given on the command line, which override the ones of the lint
+    // configuration files.
+    let mut lint_level_overrides = vec![];
+
+    while let Some(arg) = args.next() {
+        if arg.starts_with("--emit=") {
+            emit_given = true;
+        }
+
+        match arg.as_str() {
+            "--script" => options.script_mode = true,
+            "--check" => command = Command::Check,
+            "--watch" => watch = true,
+            "--report-features" => command = Command::ReportFeatures,
+            "--emit=bytecode" => emit_binary = Some(BinaryEmit::Bytecode),
+            "--emit=obj" => emit_binary = Some(BinaryEmit::Object),
+            "--emit=asm" => (options.emit, emit_binary) = (Emit::Assembly, None),
+            "--emit=llvm-ir" => (options.emit, emit_binary) = (Emit::LlvmIr, None),
+            "--emit=wat" => (options.emit, emit_binary) = (Emit::Wat, None),
+            "--emit=c" => (options.emit, emit_binary) = (Emit::C, None),
+            "--emit=ir" => (options.emit, emit_binary) = (Emit::Ir, None),
+            "--emit=cfg" => (options.emit, emit_binary) = (Emit::Cfg, None),
+            "--emit=source" => (options.emit, emit_binary) = (Emit::Source, None),
+            "--emit=tokens" => (options.emit, emit_binary) = (Emit::Tokens, None),
+            "--emit=ast" => (options.emit, emit_binary) = (Emit::Ast, None),
+            "--emit=cst" => (options.emit, emit_binary) = (Emit::Cst, None),
+            "--no-link" => no_link = true,
+            "--int-width=16" => options.int_profile.width = IntWidth::Bits16,
+            "--int-width=32" => options.int_profile.width = IntWidth::Bits32,
+            "--overflow=wrap" => options.int_profile.overflow = Overflow::Wrap,
+            "--overflow=trap" => options.int_profile.overflow = Overflow::Trap,
+            "--soft-div" => options.int_profile.has_hardware_division = false,
+            "--remarks" => options.remarks = true,
+            "--color=auto" => colors = io::stderr().is_terminal(),
+            "--color=always" => colors = true,
+            "--color=never" => colors = false,
+            "--error-format=human" => error_format = ErrorFormat::Human,
+            "--error-format=json" => error_format = ErrorFormat::Json,
+            "--quiet" => report_mode = ReportMode::Quiet,
+            "--summary" => report_mode = ReportMode::Summary,
+            "-Ztime-passes" => options.pass_timings = Some(Rc::default()),
+            "--engine=interp" if matches!(command, Command::Run { .. }) => {
+                command = Command::Run {
+                    engine: Engine::Interpreter,
+                }
+            }
+            "--engine=vm" if matches!(command, Command::Run { .. }) => {
+                command = Command::Run { engine: Engine::Vm }
+            }
+            "--engine=jit" | "--jit" if matches!(command, Command::Run { .. }) => {
+                if !cfg!(feature = "jit") {
+                    eprintln!(
+                        "error: `{}` requires sophia to be built with the `jit` feature",
+                        arg
+                    );
+                    return ExitCode::FAILURE;
+                }
+
+                command = Command::Run {
+                    engine: Engine::Jit,
+                }
+            }
+            _ if arg.starts_with("--stage=") && matches!(command, Command::Expand { .. }) => {
+                let name = &arg["--stage=".len()..];
+
+                match Stage::from_name(name) {
+                    Some(stage) => command = Command::Expand { stage: Some(stage) },
+                    None => {
+                        let names: Vec<_> = Stage::ALL.iter().map(|stage| stage.name()).collect();
+                        eprintln!(
+                            "error: unknown stage `{}`, expected one of: {}",
+                            name,
+                            names.join(", ")
+                        );
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            _ if arg.starts_with("--max-nesting-depth=") => {
+                let depth = &arg["--max-nesting-depth=".len()..];
+
+                match depth.parse() {
+                    Ok(depth) => options.limits.max_nesting_depth = depth,
+                    Err(_) => {
+                        eprintln!("error: invalid nesting depth `{}`\n{}", depth, USAGE);
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            "--profile" if matches!(command, Command::Run { .. }) => options.profiling = true,
+            "--profile-folded" if matches!(command, Command::Run { .. }) => match args.next() {
+                Some(path) => {
+                    options.profiling = true;
+                    folded_stacks_path = Some(PathBuf::from(path));
+                }
+                None => {
+                    eprintln!("error: expected a path after `--profile-folded`\n{}", USAGE);
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-W" | "-A" | "-D" => {
+                let lints = match args.next() {
+                    Some(name) => match Lint::lints_named(&name) {
+                        Some(lints) => lints,
+                        None => {
+                            let names: Vec<_> = LINT_GROUPS
+                                .iter()
+                                .map(|&(group, _)| group)
+                                .chain(Lint::ALL.iter().map(|lint| lint.name()))
+                                .collect();
+                            eprintln!(
+                                "error: unknown lint `{}`, expected one of: {}",
+                                name,
+                                names.join(", ")
+                            );
+                            return ExitCode::FAILURE;
+                        }
+                    },
+                    None => {
+                        eprintln!("error: expected a lint after `{}`\n{}", arg, USAGE);
+                        return ExitCode::FAILURE;
+                    }
+                };
+
+                let level = match arg.as_str() {
+                    "-W" => LintLevel::Warn,
+                    "-A" => LintLevel::Allow,
+                    _ => LintLevel::Deny,
+                };
+
+                for &lint in lints {
+                    options.lint_levels.set(lint, level);
+                    lint_level_overrides.push((lint, level));
+                }
+            }
+            "-o" if output_path.is_none() => match args.next() {
+                Some(path) => output_path = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("error: expected an output path after `-o`\n{}", USAGE);
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--manifest" if manifest_path.is_none() => match args.next() {
+                Some(path) => manifest_path = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!(
+                        "error: expected a manifest path after `--manifest`\n{}",
+                        USAGE
+                    );
+                    return ExitCode::FAILURE;
+                }
+            },
+            _ if !arg.starts_with('-') => input_paths.push(arg),
+            _ => {
+                eprintln!("error: unexpected argument `{}`\n{}", arg, USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if input_paths.is_empty() {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    }
+
+    if watch && !matches!(command, Command::Check) {
+        eprintln!(
+            "error: `--watch` can only be used with `--check`\n{}",
+            USAGE
+        );
+        return ExitCode::FAILURE;
+    }
+
+    // Only the interpreter keeps track of the functions it runs.
+    if options.profiling
+        && matches!(command, Command::Run { engine } if engine != Engine::Interpreter)
+    {
+        eprintln!("error: `--profile` can only be used with the interpreter");
+        return ExitCode::FAILURE;
+    }
+
+    let link = match (emit_given, no_link) {
+        (false, false) => Some(LinkOutput::Executable),
+        (false, true) => Some(LinkOutput::Object),
+        (true, false) => None,
+        (true, true) => {
+            eprintln!("error: `--no-link` can't be used with `--emit`\n{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Executables start at `main`, and so do the programs that are run.
+    options.requires_main = match command {
+        Command::Compile => link == Some(LinkOutput::Executable),
+        Command::Run { .. } => true,
+        _ => false,
+    };
+
+    // The manifest lists files by path, so the generated code has to go to one.
+    if manifest_path.is_some() && output_path.is_none() {
+        eprintln!(
+            "error: `--manifest` requires an output path given with `-o`\n{}",
+            USAGE
+        );
+        return ExitCode::FAILURE;
+    }
+
+    // A program may be split across files, which are compiled as one program.
+    let mut source_map = SourceMap::new();
+
+    for input_path in &input_paths {
+        let input = match fs::read(input_path) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("error: couldn't read `{}`: {}", input_path, err);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        if bytecode::is_bytecode(&input) {
+            return match command {
+                Command::Run { engine }
+                    if engine != Engine::Jit && input_paths.len() == 1 && !options.profiling =>
+                {
+                    run_bytecode(input_path, &input)
+                }
+                _ => {
+                    eprintln!(
+                        "error: `{}` is bytecode, which can only be run on its own, by the VM",
+                        input_path
+                    );
+
+                    ExitCode::FAILURE
+                }
+            };
+        }
+
+        let file_id = match String::from_utf8(input) {
+            Ok(source_code) => source_map.add_file(input_path.as_str(), &source_code),
+            Err(err) => {
+                eprintln!("error: couldn't read `{}`: {}", input_path, err);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        match LintConfig::discover(Path::new(input_path)) {
+            Ok(configs) if configs.is_empty() => {}
+            Ok(configs) => options.file_lint_levels.push((
+                file_id,
+                lint_config::lint_levels(&configs, &lint_level_overrides),
+            )),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if watch {
+        let reporting = Reporting {
+            colors,
+            error_format,
+            mode: report_mode,
+        };
+
+        return watch_source(&input_paths, &options, reporting);
+    }
+
+    let renderer = DiagnosticRenderer::new(&source_map)
+        .with_colors(colors)
+        .with_format(error_format);
+    let reporter = Reporter {
+        renderer,
+        mode: report_mode,
+        source_map: &source_map,
+        options: &options,
+        counts: Cell::default(),
+    };
+
+    let exit_code = match command {
+        Command::Compile => {
+            let paths = OutputPaths {
+                output: output_path.as_deref(),
+                manifest: manifest_path.as_deref(),
+            };
+
+            compile_source(&source_map, &options, emit_binary, link, paths, &reporter)
+        }
+        Command::Check => check_source(&source_map, &options, &reporter),
+        Command::ReportFeatures => report_source_features(&source_map, &options, &reporter),
+        Command::Run { engine } => run_source(
+            &source_map,
+            &options,
+            engine,
+            folded_stacks_path.as_deref(),
+            &reporter,
+        ),
+        Command::Expand { stage } => expand_source(&source_map, &options, stage, &reporter),
+        Command::Test => test_doc_examples(&source_map, &options, &reporter),
+    };
+
+    reporter.report_summary();
+
+    if let Some(pass_timings) = &options.pass_timings {
+        eprint!("{}", timing::render_pass_timings(&pass_timings.borrow()));
+    }
+
+    exit_code
+}
+
+fn explain(mut args: impl Iterator<Item = String>) -> ExitCode {
+    let (Some(code), None) = (args.next(), args.next()) else {
+        eprintln!("error: expected an error code after `--explain`\n{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    match error_codes::explain(&code) {
+        Some(explanation) => {
+            print!("{}", explanation);
+            ExitCode::SUCCESS
+        }
+        None => {
+            eprintln!("error: `{}` is not an error code", code);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Prints the compiler's version, along with what the build supports if
+/// `--verbose` is given, one `<key>: <values>` line each.
+fn version(mut args: impl Iterator<Item = String>) -> ExitCode {
+    let verbose = match (args.next().as_deref(), args.next()) {
+        (None, _) => false,
+        (Some("--verbose" | "-v"), None) => true,
+        (Some(arg), _) => {
+            eprintln!("error: unexpected argument `{}`\n{}", arg, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let info = build_info();
+
+    println!("sophia {}", info.version);
+
+    if verbose {
+        println!("version: {}", info.version);
+        println!("backends: {}", info.backends.join(", "));
+        println!("features: {}", info.features.join(", "));
+        println!("targets: {}", info.targets.join(", "));
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Formats the files in place, see `formatter::format_source`, or, with
+/// `--check`, fails if any of them isn't formatted, naming each one. Files
+/// that don't parse are reported and left as they are.
+fn format_files(args: impl Iterator<Item = String>) -> ExitCode {
+    let mut check = false;
+    let mut paths = vec![];
+
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ if !arg.starts_with('-') => paths.push(arg),
+            _ => {
+                eprintln!("error: unexpected argument `{}`\n{}", arg, USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if paths.is_empty() {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    }
+
+    let mut exit_code = ExitCode::SUCCESS;
+
+    for path in &paths {
+        let source_code = match fs::read_to_string(path) {
+            Ok(source_code) => source_code,
+            Err(err) => {
+                eprintln!("error: couldn't read `{}`: {}", path, err);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let formatted = match formatter::format_source(&source_code) {
+            Ok(formatted) => formatted,
+            Err(diagnostic) => {
+                let mut source_map = SourceMap::new();
+                source_map.add_file(path.as_str(), &source_code);
+
+                eprint!(
+                    "{}",
+                    DiagnosticRenderer::new(&source_map).render(&diagnostic)
+                );
+                eprintln!("error: `{}` can't be formatted, as it doesn't parse", path);
+                exit_code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        if formatted == source_code {
+            continue;
+        }
+
+        if check {
+            eprintln!("error: `{}` isn't formatted", path);
+            exit_code = ExitCode::FAILURE;
+        } else if let Err(err) = fs::write(path, formatted) {
+            eprintln!("error: couldn't write `{}`: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    exit_code
+}
+
+/// Fixes the diagnostics of the program of a single file in place, see
+/// `fixer::fix_source`, and reports the ones that are left, failing if any of
+/// them is an error.
+fn fix_file(args: impl Iterator<Item = String>) -> ExitCode {
+    let mut paths = vec![];
+
+    for arg in args {
+        if arg.starts_with('-') {
+            eprintln!("error: unexpected argument `{}`\n{}", arg, USAGE);
+            return ExitCode::FAILURE;
+        }
+
+        paths.push(arg);
+    }
+
+    let [path] = &paths[..] else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let source_code = match fs::read_to_string(path) {
+        Ok(source_code) => source_code,
+        Err(err) => {
+            eprintln!("error: couldn't read `{}`: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let fixed = fixer::fix_source(&source_code, &Options::default());
+
+    if fixed.fix_count > 0 {
+        if let Err(err) = fs::write(path, &fixed.source_code) {
+            eprintln!("error: couldn't write `{}`: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+
+        eprintln!(
+            "fixed {} in `{}`",
+            count_of(fixed.fix_count, "problem"),
+            path
+        );
+    }
+
+    let mut source_map = SourceMap::new();
+    source_map.add_file(path.as_str(), &fixed.source_code);
+
+    eprint!(
+        "{}",
+        DiagnosticRenderer::new(&source_map).render(&fixed.diagnostic)
+    );
+
+    if fixed.diagnostic.has_errors() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Evaluates what's typed into the standard input, one input at a time, see
+/// `Repl`. An input is evaluated at the end of a line that closes all of its
+/// delimiters, and the value of the expression it ends with is printed. The
+/// prompts are only printed if the standard input is a terminal.
+fn run_repl(mut args: impl Iterator<Item = String>) -> ExitCode {
+    if let Some(arg) = args.next() {
+        eprintln!("error: unexpected argument `{}`\n{}", arg, USAGE);
+        return ExitCode::FAILURE;
+    }
+
+    let interactive = io::stdin().is_terminal();
+    let colors = io::stderr().is_terminal();
+    let mut repl = Repl::new(Options::default());
+    let mut input = String::new();
+
+    loop {
+        if interactive {
+            print!("{}", if input.is_empty() { ">> " } else { ".. " });
+            let _ = io::stdout().flush();
+        }
+
+        let mut line = String::new();
+
+        match io::stdin().read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => input.push_str(&line),
+            Err(err) => {
+                eprintln!("error: couldn't read the standard input: {}", err);
+                return ExitCode::FAILURE;
+            }
+        }
+
+        if input.trim().is_empty() {
+            input.clear();
+        } else if !repl::is_incomplete(&input) {
+            eval_repl_input(&mut repl, &input, colors);
+            input.clear();
+        }
+    }
+
+    // Whatever was left open is evaluated all the same, to tell what's missing.
+    if !input.trim().is_empty() {
+        eval_repl_input(&mut repl, &input, colors);
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn eval_repl_input(repl: &mut Repl, input: &str, colors: bool) {
+    let outcome = repl.eval(input);
+    let renderer = DiagnosticRenderer::new(&outcome.source_map).with_colors(colors);
+
+    match outcome.evaluation {
+        Ok(evaluation) => {
+            for warning in &evaluation.warnings {
+                eprint!("{}", renderer.render_warning(warning));
+            }
+
+            match evaluation.value {
+                Ok(Some(value)) => println!("{}", value),
+                Ok(None) => {}
+                Err(error) => eprintln!("error: {}", error),
+            }
+        }
+        Err(diagnostic) => eprint!("{}", renderer.render(&diagnostic)),
+    }
+}
+
+fn serve_language_server(mut args: impl Iterator<Item = String>) -> ExitCode {
+    if let Some(arg) = args.next() {
+        eprintln!("error: unexpected argument `{}`\n{}", arg, USAGE);
+        return ExitCode::FAILURE;
+    }
+
+    let server = LanguageServer::new(Options::default());
+
+    // Exiting without being asked to shut down first is an error, as the
+    // protocol has it.
+    match server.serve(io::stdin().lock(), io::stdout().lock()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: language server failed: {}", err);
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn serve_daemon(mut args: impl Iterator<Item = String>) -> ExitCode {
+    // The runtime directory is only accessible to its user, unlike the
+    // temporary one, where anyone could take the socket's path first.
+    let mut socket_path = env::var_os("XDG_RUNTIME_DIR")
+        .map(|runtime_dir| PathBuf::from(runtime_dir).join("sophia.sock"));
+
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--socket", Some(path)) => socket_path = Some(PathBuf::from(path)),
+            _ => {
+                eprintln!("error: unexpected argument `{}`\n{}", arg, USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let Some(socket_path) = socket_path else {
+        eprintln!("error: `XDG_RUNTIME_DIR` isn't set, so the socket's path has to be given with `--socket`");
+        return ExitCode::FAILURE;
+    };
+
+    match Daemon::new().serve(&socket_path) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: daemon failed: {}", err);
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+struct OutputPaths<'a> {
+    output: Option<&'a Path>,
+    manifest: Option<&'a Path>,
+}
+
+/// Compiles the source files, writing the generated code to the output path, or
+/// to the standard output if there's none. Then, if asked for, writes the
+/// manifest of the build.
+///
+/// If `link` is given, the generated assembly is linked instead, see
+/// `linker::link`, and the output goes to a file named after the first source
+/// file if there's no output path.
+fn compile_source(
+    source_map: &SourceMap,
+    options: &Options,
+    emit_binary: Option<BinaryEmit>,
+    link: Option<LinkOutput>,
+    paths: OutputPaths,
+    reporter: &Reporter,
+) -> ExitCode {
+    let mut links_c_library = false;
+
+    let compilation = match emit_binary {
+        Some(BinaryEmit::Bytecode) => {
+            compile_bytecode(source_map.clone(), options).map(|compiled_bytecode| {
+                let code = compiled_bytecode.module.save();
+
+                (
+                    code,
+                    ArtifactKind::Bytecode,
+                    compiled_bytecode.warnings,
+                    compiled_bytecode.remarks,
+                )
+            })
+        }
+        Some(BinaryEmit::Object) => {
+            compile_object(source_map.clone(), options).map(|compiled_object| {
+                let code = compiled_object.object.to_elf();
+
+                (
+                    code,
+                    ArtifactKind::Object,
+                    compiled_object.warnings,
+                    compiled_object.remarks,
+                )
+            })
+        }
+        None => compile(source_map.clone(), options).map(|compiled_program| {
+            let artifact_kind = match options.emit {
+                Emit::Assembly => ArtifactKind::Assembly,
+                Emit::LlvmIr => ArtifactKind::LlvmIr,
+                Emit::Wat => ArtifactKind::Wat,
+                Emit::C => ArtifactKind::C,
+                Emit::Ir => ArtifactKind::Ir,
+                Emit::Cfg => ArtifactKind::Cfg,
+                Emit::Source => ArtifactKind::Source,
+                Emit::Tokens => ArtifactKind::Tokens,
+                Emit::Ast => ArtifactKind::Ast,
+                Emit::Cst => ArtifactKind::Cst,
+            };
+
+            let code = compiled_program.code.into_bytes();
+            links_c_library = compiled_program.links_c_library;
+
+            (
+                code,
+                artifact_kind,
+                compiled_program.warnings,
+                compiled_program.remarks,
+            )
+        }),
+    };
+
+    let (code, artifact_kind, warnings, remarks) = match compilation {
+        Ok(compilation) => compilation,
+        Err(diagnostic) => {
+            reporter.report(&diagnostic);
+
+            return ExitCode::FAILURE;
+        }
+    };
+
+    reporter.report_warnings(&warnings);
+    reporter.report_remarks(&remarks);
+
+    if let Some(link_output) = link {
+        return link_program(source_map, &code, link_output, links_c_library, paths);
+    }
+
+    let Some(output_path) = paths.output else {
+        if let Err(err) = io::stdout().write_all(&code) {
+            eprintln!("error: couldn't write to the standard output: {}", err);
+
+            return ExitCode::FAILURE;
+        }
+
+        return ExitCode::SUCCESS;
+    };
+
+    if let Err(err) = fs::write(output_path, &code) {
+        eprintln!("error: couldn't write `{}`: {}", output_path.display(), err);
+
+        return ExitCode::FAILURE;
+    }
+
+    match paths.manifest {
+        Some(manifest_path) => {
+            write_manifest(source_map, artifact_kind, output_path, &code, manifest_path)
+        }
+        None => ExitCode::SUCCESS,
+    }
+}
+
+/// Links the program's assembly, then writes the manifest of the build if
+/// asked for, which lists the linked file.
+fn link_program(
+    source_map: &SourceMap,
+    assembly: &[u8],
+    link_output: LinkOutput,
+    links_c_library: bool,
+    paths: OutputPaths,
+) -> ExitCode {
+    let output_path = match paths.output {
+        Some(output_path) => output_path.to_owned(),
+        None => {
+            let input_path = source_map.file_name(source_map.file_ids().next().unwrap());
+
+            link_output.default_path(Path::new(input_path))
+        }
+    };
+
+    // The assembly comes from a `String`, so it's always valid UTF-8.
+    let assembly = String::from_utf8_lossy(assembly);
+
+    if let Err(err) = linker::link(&assembly, link_output, links_c_library, &output_path) {
+        eprintln!("error: couldn't link `{}`: {}", output_path.display(), err);
+
+        return ExitCode::FAILURE;
+    }
+
+    let Some(manifest_path) = paths.manifest else {
+        return ExitCode::SUCCESS;
+    };
+
+    let code = match fs::read(&output_path) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: couldn't read `{}`: {}", output_path.display(), err);
+
+            return ExitCode::FAILURE;
+        }
+    };
+    let artifact_kind = match link_output {
+        LinkOutput::Executable => ArtifactKind::Executable,
+        LinkOutput::Object => ArtifactKind::Object,
+    };
+
+    write_manifest(
+        source_map,
+        artifact_kind,
+        &output_path,
+        &code,
+        manifest_path,
+    )
+}
+
+fn write_manifest(
+    source_map: &SourceMap,
+    artifact_kind: ArtifactKind,
+    output_path: &Path,
+    code: &[u8],
+    manifest_path: &Path,
+) -> ExitCode {
+    let mut manifest = Manifest::default();
+    for file_id in source_map.file_ids() {
+        manifest.add_source(
+            Path::new(source_map.file_name(file_id)),
+            source_map.file_source_code(file_id).as_bytes(),
+        );
+    }
+
+    manifest.add_artifact(artifact_kind, output_path, code);
+
+    match fs::write(manifest_path, format!("{}\n", manifest.to_json())) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!(
+                "error: couldn't write `{}`: {}",
+                manifest_path.display(),
+                err
+            );
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn check_source(source_map: &SourceMap, options: &Options, reporter: &Reporter) -> ExitCode {
+    reporter.report(&check(source_map.clone(), options));
+
+    // Warnings of denied lints don't count as errors of the diagnostic.
+    if reporter.has_reported_errors() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Checks the program whenever any of its files changes, until interrupted,
+/// clearing the terminal before reporting the diagnostics of each version.
+/// Only the declarations that changed are checked again, see
+/// `QueryDatabase`.
+fn watch_source(input_paths: &[String], options: &Options, reporting: Reporting) -> ExitCode {
+    let mut watcher = FileWatcher::new(input_paths);
+    let mut queries = QueryDatabase::default();
+    let clears_screen = io::stderr().is_terminal();
+
+    loop {
+        if let Some(contents) = watcher.poll() {
+            if clears_screen {
+                eprint!("\x1b[2J\x1b[H");
+            }
+
+            let mut source_map = SourceMap::new();
+            let mut can_read_files = true;
+
+            for (input_path, contents) in input_paths.iter().zip(contents) {
+                match contents {
+                    Ok(source_code) => {
+                        source_map.add_file(input_path.as_str(), &source_code);
+                    }
+                    Err(err) => {
+                        eprintln!("error: couldn't read `{}`: {}", input_path, err);
+                        can_read_files = false;
+                    }
+                }
+            }
+
+            if can_read_files {
+                let diagnostic = if options.script_mode {
+                    check(source_map.clone(), options)
+                } else {
+                    queries.check(source_map.clone(), options)
+                };
+
+                let reporter = Reporter {
+                    renderer: DiagnosticRenderer::new(&source_map)
+                        .with_colors(reporting.colors)
+                        .with_format(reporting.error_format),
+                    mode: reporting.mode,
+                    source_map: &source_map,
+                    options,
+                    counts: Cell::default(),
+                };
+                reporter.report(&diagnostic);
+                reporter.report_summary();
+            }
+
+            eprintln!("watching {} file(s) for changes", input_paths.len());
+        }
+
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+fn report_source_features(
+    source_map: &SourceMap,
+    options: &Options,
+    reporter: &Reporter,
+) -> ExitCode {
+    match report_features(source_map.clone(), options) {
+        Ok((report, warnings)) => {
+            reporter.report_warnings(&warnings);
+            println!("{}", report.to_json());
+
+            ExitCode::SUCCESS
+        }
+        Err(diagnostic) => {
+            reporter.report(&diagnostic);
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Prints the source code after the stage, or after every stage, each under a
+/// header naming it.
+fn expand_source(
+    source_map: &SourceMap,
+    options: &Options,
+    stage: Option<Stage>,
+    reporter: &Reporter,
+) -> ExitCode {
+    let (expansions, warnings) = match expand(source_map.clone(), options) {
+        Ok(expansion) => expansion,
+        Err(diagnostic) => {
+            reporter.report(&diagnostic);
+
+            return ExitCode::FAILURE;
+        }
+    };
+
+    reporter.report_warnings(&warnings);
+
+    match stage {
+        // Only the source code is printed, so that it can be compiled as is.
+        Some(stage) => {
+            let expansion = expansions
+                .iter()
+                .find(|expansion| expansion.stage == stage)
+                .expect("every stage is expanded");
+
+            print!("{}", expansion.source_code);
+        }
+        None => {
+            for (idx, expansion) in expansions.iter().enumerate() {
+                if idx > 0 {
+                    println!();
+                }
+
+                println!("==> {} <==", expansion.stage.name());
+                print!("{}", expansion.source_code);
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Checks every example in the doc comments of the source files, printing
+/// whether each one passed under the file and line of its fence, along with
+/// what made it fail.
+fn test_doc_examples(source_map: &SourceMap, options: &Options, reporter: &Reporter) -> ExitCode {
+    let (mut passed, mut failed) = (0, 0);
+
+    for file_id in source_map.file_ids() {
+        let file_name = source_map.file_name(file_id);
+
+        for example in extract_doc_examples(source_map.file_source_code(file_id)) {
+            let name = format!("{}:{}", file_name, example.line);
+            let example_file_name = format!("doc example at {}", name);
+
+            let example_source_map = with_doc_example(source_map, &example, &example_file_name);
+
+            let Err(failure) = check_doc_example(example_source_map.clone(), &example, options)
+            else {
+                println!("test {} ... ok", name);
+                passed += 1;
+                continue;
+            };
+
+            println!("test {} ... FAILED", name);
+            failed += 1;
+
+            match failure {
+                DocExampleFailure::Compile(diagnostic) => eprint!(
+                    "{}",
+                    reporter
+                        .renderer
+                        .with_source_map(&example_source_map)
+                        .render(&diagnostic)
+                ),
+                DocExampleFailure::Runtime(error) => {
+                    eprintln!("error: doc example at {} failed: {}", name, error)
+                }
+            }
+        }
+    }
+
+    let result = if failed == 0 { "ok" } else { "FAILED" };
+    println!(
+        "test result: {}. {} passed; {} failed",
+        result, passed, failed
+    );
+
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Runs the program, printing where it spent its time if it's profiled, and
+/// writing the folded stacks of the profile to `folded_stacks_path` if given.
+fn run_source(
+    source_map: &SourceMap,
+    options: &Options,
+    engine: Engine,
+    folded_stacks_path: Option<&Path>,
+    reporter: &Reporter,
+) -> ExitCode {
+    let execution = match engine {
+        Engine::Vm => run_vm(source_map.clone(), options),
+        #[cfg(feature = "jit")]
+        Engine::Jit => crate::driver::run_jit(source_map.clone(), options),
+        _ => run(source_map.clone(), options),
+    };
+
+    match execution {
+        Ok(execution) => {
+            reporter.report_warnings(&execution.warnings);
+            reporter.report_remarks(&execution.remarks);
+
+            // A program that fails at runtime still ran, so it's profiled all the same.
+            if let Some(profile) = &execution.profile {
+                eprint!("{}", profile.to_flat_profile());
+
+                if let Some(path) = folded_stacks_path {
+                    if let Err(err) = fs::write(path, profile.to_folded_stacks()) {
+                        eprintln!("error: couldn't write `{}`: {}", path.display(), err);
+
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+
+            match execution.exit_code {
+                // Only the lowest byte of an exit code makes it to the parent process.
+                Ok(exit_code) => ExitCode::from(exit_code as u8),
+                Err(error) => {
+                    eprintln!("error: {}", error);
+
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Err(diagnostic) => {
+            reporter.report(&diagnostic);
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs a module that was compiled to bytecode earlier.
+fn run_bytecode(input_path: &str, bytes: &[u8]) -> ExitCode {
+    let mut vm = match Vm::load(bytes) {
+        Ok(vm) => vm,
+        Err(err) => {
+            eprintln!("error: couldn't load `{}`: {}", input_path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match vm.run_main() {
+        // Only the lowest byte of an exit code makes it to the parent process.
+        Ok(exit_code) => ExitCode::from(exit_code as u8),
+        Err(error) => {
+            eprintln!("error: {}", error);
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// How diagnostics are reported, which `Reporter` is made from anew for every
+/// version of a watched program.
+#[derive(Clone, Copy)]
+struct Reporting {
+    colors: bool,
+    error_format: ErrorFormat,
+    mode: ReportMode,
+}
+
+/// Reports diagnostics to the standard error the way the report mode asks
+/// for, keeping count of them by severity. Warnings of denied lints are
+/// reported as errors, as they fail the command just like errors do.
+struct Reporter<'a> {
+    renderer: DiagnosticRenderer<'a>,
+    mode: ReportMode,
+    source_map: &'a SourceMap,
+    options: &'a Options,
+    counts: Cell<DiagnosticCounts>,
+}
+
+impl Reporter<'_> {
+    /// Reports the warnings first, and then the errors, as the errors are what
+    /// stopped the compiler.
+    fn report(&self, diagnostic: &Diagnostic) {
+        self.report_warnings(&diagnostic.warnings);
+
+        for error in &diagnostic.errors {
+            self.count(|counts| counts.errors += 1);
+
+            if self.mode != ReportMode::Summary {
+                eprint!("{}", self.renderer.render_error(error));
+            }
+        }
+    }
+
+    fn report_warnings(&self, warnings: &[CompileWarning]) {
+        for warning in warnings {
+            let lint_levels = self.options.lint_levels_of(warning, self.source_map);
+
+            if lint_levels.denies(warning) {
+                self.count(|counts| counts.errors += 1);
+
+                if self.mode != ReportMode::Summary {
+                    eprint!("{}", self.renderer.render_denied_warning(warning));
+                }
+            } else {
+                self.count(|counts| counts.warnings += 1);
+
+                if self.mode == ReportMode::All {
+                    eprint!("{}", self.renderer.render_warning(warning));
+                }
+            }
+        }
+    }
+
+    fn report_remarks(&self, remarks: &[Remark]) {
+        for remark in remarks {
+            self.count(|counts| counts.remarks += 1);
+
+            if self.mode == ReportMode::All {
+                eprint!("{}", self.renderer.render_remark(remark));
+            }
+        }
+    }
+
+    fn has_reported_errors(&self) -> bool {
+        self.counts.get().errors > 0
+    }
+
+    /// Reports how many diagnostics there were of each severity, if that's
+    /// what's reported.
+    fn report_summary(&self) {
+        if self.mode == ReportMode::Summary {
+            eprint!("{}", self.renderer.render_summary(self.counts.get()));
+        }
+    }
+
+    fn count(&self, update: impl FnOnce(&mut DiagnosticCounts)) {
+        let mut counts = self.counts.get();
+        update(&mut counts);
+        self.counts.set(counts);
+    }
+}
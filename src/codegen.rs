@@ -1,71 +1,223 @@
-use std::collections::HashMap;
 use std::fmt;
 
-use crate::ast::{
-    BindDef, BindRef, CompoundExpr, Const, Decl, Expr, FnCallExpr, ForExpr, ForIteration, Function,
-    IfExpr, Program, RangeKind,
-};
+use crate::ast::{BinaryOp, Program};
 use crate::compiler_context::CompilerContext;
+use crate::host::HashMap;
 use crate::interner::Symbol;
-
+use crate::ir::{self, BlockCall, BlockId, Callee, Inst as IrInst, InstKind, Terminator, Value};
+use crate::ir_lower::IrLowering;
+use crate::object::{FunctionSymbol, ObjectFile, Relocation};
+use crate::resolve::Resolutions;
+
+/// Generates x86-64 assembly for a program, which is lowered to the IR first.
+///
+/// Every value gets a slot of its function's frame, except for constants,
+/// which are written where they're used, values that are used right away by
+/// the next instruction, which are left in `eax`, and comparisons that only
+/// decide the branch of their block, which leave their result in the flags.
+/// Jumps store their arguments in the slots of the blocks' parameters.
 pub(crate) struct CodeGen<'ctx> {
     ctx: &'ctx CompilerContext,
+    resolutions: &'ctx Resolutions,
     label_counter: u64,
-    allocated_stack_bytes: usize,
-    scope_stack: Vec<Scope>,
-}
-
-#[derive(Default)]
-pub(crate) struct Scope {
-    memory_offset_by_symbol: HashMap<Symbol, usize>,
-    innermost_start_label: Option<Symbol>,
-    innermost_exit_label: Option<Symbol>,
 }
 
 impl<'ctx> CodeGen<'ctx> {
-    pub(crate) fn new(ctx: &'ctx CompilerContext) -> CodeGen<'ctx> {
+    pub(crate) fn new(ctx: &'ctx CompilerContext, resolutions: &'ctx Resolutions) -> CodeGen<'ctx> {
         CodeGen {
             ctx,
+            resolutions,
             label_counter: 0,
-            allocated_stack_bytes: 0,
-            scope_stack: vec![],
         }
     }
 
-    pub(crate) fn gen_program(&mut self, program: Program) -> X86Program {
+    pub(crate) fn gen_program(&mut self, program: Program<'ctx>) -> X86Program<'ctx> {
+        let module = IrLowering::new(self.ctx, self.resolutions, &[]).lower_program(program);
+
         let mut generated_insts = vec![];
+        let mut globals = vec![];
 
-        for decl in program.decls {
-            generated_insts.extend(self.gen_decl(decl));
+        for function in &module.functions {
+            let name = self.ctx.get_or_intern_str(function.name);
+
+            globals.push(name);
+            generated_insts.push(Inst::Label { name });
+            generated_insts.extend(FunctionGen::new(self, function).gen_function());
         }
 
         X86Program {
             ctx: self.ctx,
+            globals,
             instructions: generated_insts,
         }
     }
 
-    fn gen_decl(&mut self, decl: &Decl) -> Vec<Inst> {
-        let mut decl_insts = vec![Inst::Label {
-            name: decl.identifier,
-        }];
-
-        let value_insts = self.parse_top_level_expr(decl.value);
-        decl_insts.extend(value_insts);
+    fn make_label(&mut self) -> Symbol {
+        let label_count = self.label_counter;
+        self.label_counter += 1;
 
-        decl_insts
+        self.ctx.get_or_intern_str(&format!(".L{}", label_count))
     }
+}
 
-    fn parse_top_level_expr(&mut self, expr: &Expr) -> Vec<Inst> {
-        match expr {
-            Expr::Function(Function { body, .. }) => self.gen_function(*body),
-            _ => todo!("other top-level exprs"),
+/// Condition that a jump tests the flags for.
+#[derive(Clone, Copy)]
+enum Cond {
+    NotZero,
+    Less,
+    LessEqual,
+    Overflow,
+}
+
+impl Cond {
+    /// Jump to the label if the condition holds, or if it doesn't when it's
+    /// negated.
+    fn jump(self, is_negated: bool, label: Symbol) -> Inst {
+        match (self, is_negated) {
+            (Cond::NotZero, false) => Inst::Jne { label },
+            (Cond::NotZero, true) => Inst::Je { label },
+            (Cond::Less, false) => Inst::Jl { label },
+            (Cond::Less, true) => Inst::Jge { label },
+            (Cond::LessEqual, false) => Inst::Jle { label },
+            (Cond::LessEqual, true) => Inst::Jg { label },
+            (Cond::Overflow, false) => Inst::Jo { label },
+            (Cond::Overflow, true) => Inst::Jno { label },
         }
     }
+}
+
+struct FunctionGen<'gen, 'ctx> {
+    codegen: &'gen mut CodeGen<'ctx>,
+    function: &'gen ir::Function,
+    is_needed: Vec<bool>,
+    /// Constant of every value that is one, by the value's number.
+    constants: Vec<Option<i32>>,
+    /// Comparisons, by the number of their value, that are the last
+    /// instruction of their block and are used only by its branch, which tests
+    /// the flags they set.
+    branch_conds: Vec<Option<Cond>>,
+    /// Values, by their number, that are left in `eax` for the instruction or
+    /// terminator right after the one that defines them.
+    is_in_eax: Vec<bool>,
+    /// Slot of every other value that's needed, by the value's number.
+    slots: Vec<Option<Arg>>,
+    frame_bytes: i32,
+    /// Labels of the blocks that are jumped to, rather than only come next
+    /// after the block before them, which are made on the first jump.
+    block_labels: Vec<Option<Symbol>>,
+    /// Instructions of the block being generated.
+    insts: Vec<Inst>,
+}
+
+impl<'gen, 'ctx> FunctionGen<'gen, 'ctx> {
+    fn new(
+        codegen: &'gen mut CodeGen<'ctx>,
+        function: &'gen ir::Function,
+    ) -> FunctionGen<'gen, 'ctx> {
+        let use_counts = function.use_counts();
+        let is_needed = function.needed_values();
+        let value_count = use_counts.len();
+
+        let mut constants = vec![None; value_count];
+        let mut branch_conds = vec![None; value_count];
+        let mut is_in_eax = vec![false; value_count];
+
+        for block in &function.blocks {
+            for inst in &block.insts {
+                if let (Some(result), InstKind::Const(value)) = (inst.result, &inst.kind) {
+                    constants[result.0] = Some(*value);
+                }
+            }
+
+            if let (
+                Some(last_inst),
+                Terminator::Branch {
+                    cond,
+                    then_call,
+                    else_call,
+                },
+            ) = (block.insts.last(), &block.terminator)
+            {
+                let cond_code = match last_inst.kind {
+                    InstKind::Less { .. } => Some(Cond::Less),
+                    InstKind::LessEqual { .. } => Some(Cond::LessEqual),
+                    InstKind::Overflows { .. } => Some(Cond::Overflow),
+                    _ => None,
+                };
+
+                if last_inst.result == Some(*cond)
+                    && use_counts[cond.0] == 1
+                    && then_call != else_call
+                {
+                    branch_conds[cond.0] = cond_code;
+                }
+            }
+        }
+
+        // Constants generate no code, so the instruction after one that leaves
+        // its result in `eax` is the next one that isn't a constant.
+        for block in &function.blocks {
+            let insts: Vec<_> = (block.insts.iter())
+                .filter(|inst| !matches!(inst.kind, InstKind::Const(_)))
+                .collect();
+
+            let uses = (insts.iter().skip(1))
+                .map(|inst| eax_operands(&inst.kind))
+                .chain([eax_terminator_operand(
+                    function,
+                    &block.terminator,
+                    &is_needed,
+                )]);
+
+            for (inst, operands) in insts.iter().zip(uses) {
+                let Some(result) = inst.result else {
+                    continue;
+                };
+
+                if branch_conds[result.0].is_none()
+                    && use_counts[result.0] == 1
+                    && operands.contains(&result)
+                {
+                    is_in_eax[result.0] = true;
+                }
+            }
+        }
 
-    fn gen_function(&mut self, body: CompoundExpr) -> Vec<Inst> {
-        self.enter_scope();
+        let mut slots = vec![None; value_count];
+        let mut slot_count = 0;
+
+        for idx in 0..value_count {
+            let is_stored = is_needed[idx]
+                && constants[idx].is_none()
+                && branch_conds[idx].is_none()
+                && !is_in_eax[idx];
+
+            if is_stored {
+                slot_count += 1;
+                slots[idx] = Some(Arg::MemOffset {
+                    base: Reg::Rbp,
+                    // FIXME: Should not cast the offset to i32.
+                    offset: -((slot_count * SLOT_BYTES) as i32),
+                });
+            }
+        }
 
+        FunctionGen {
+            codegen,
+            function,
+            is_needed,
+            constants,
+            branch_conds,
+            is_in_eax,
+            slots,
+            // FIXME: Should not cast the frame's size to i32.
+            frame_bytes: (slot_count * SLOT_BYTES) as i32,
+            block_labels: vec![None; function.blocks.len()],
+            insts: vec![],
+        }
+    }
+
+    fn gen_function(mut self) -> Vec<Inst> {
         let mut insts = vec![
             Inst::Push { source: Reg::Rbp },
             Inst::Mov {
@@ -74,349 +226,451 @@ impl<'ctx> CodeGen<'ctx> {
             },
         ];
 
-        let mut body_insts = self.gen_compound_expr(body);
-
-        if self.allocated_stack_bytes != 0 {
-            // FIXME: Should not cast allocated_stack_bytes to i32.
+        if self.frame_bytes != 0 {
             insts.push(Inst::Sub {
                 target: Arg::Reg(Reg::Rsp),
-                source: Arg::Imm(self.allocated_stack_bytes as i32),
-            });
-
-            // FIXME: Should not cast allocated_stack_bytes to i32.
-            body_insts.push(Inst::Add {
-                target: Arg::Reg(Reg::Rsp),
-                source: Arg::Imm(self.allocated_stack_bytes as i32),
+                source: Arg::Imm(self.frame_bytes),
             });
         }
 
-        body_insts.push(Inst::Pop { target: Reg::Rbp });
-        body_insts.push(Inst::Ret);
-
-        insts.extend(body_insts);
+        let params = self.function.params();
 
-        self.exit_scope();
+        assert!(
+            params.len() <= ARG_REGS.len(),
+            "FIXME: pass arguments on the stack"
+        );
 
-        insts
-    }
-
-    fn gen_expr(&mut self, expr: &Expr) -> Vec<Inst> {
-        match expr {
-            Expr::Semi(expr) => self.gen_expr(expr),
-            Expr::Const(constant) => self.gen_constant_expr(*constant),
-            Expr::If(if_expr) => self.gen_if_expr(*if_expr),
-            Expr::For(for_expr) => self.gen_for_expr(*for_expr),
-            Expr::Break => self.gen_break_expr(),
-            Expr::Continue => self.gen_continue_expr(),
-            Expr::BindDef(bind_def) => self.gen_bind_def_expr(*bind_def),
-            Expr::BindRef(bind_ref) => self.gen_bind_ref_expr(*bind_ref),
-            Expr::Compound(compound_expr) => self.gen_compound_expr(*compound_expr),
-            Expr::FnCall(fn_call_expr) => self.gen_fn_call_expr(*fn_call_expr),
-            Expr::Function(_) => unimplemented!(),
-        }
-    }
-
-    fn gen_constant_expr(&self, constant: Const) -> Vec<Inst> {
-        match constant {
-            Const::IntegerConstant { value } => {
-                vec![Inst::Mov {
-                    target: Arg::Reg(Reg::Eax),
-                    source: Arg::Imm(value),
-                }]
+        // Arguments are passed in registers, so they're spilled into the stack frame
+        // like any other value.
+        for (param, &arg_reg) in params.iter().zip(ARG_REGS.iter()) {
+            if let Some(slot) = self.slots[param.0] {
+                insts.push(Inst::Mov {
+                    target: slot,
+                    source: Arg::Reg(arg_reg.dword()),
+                });
             }
         }
-    }
-
-    fn gen_if_expr(&mut self, if_expr: IfExpr) -> Vec<Inst> {
-        let (first_branch_insts, mut next_label) =
-            self.gen_cond_and_branch(if_expr.cond_expr, if_expr.true_branch);
-
-        let mut branches_insts = vec![];
-
-        for branch in if_expr.else_if_branches {
-            let mut branch_insts = vec![];
-
-            branch_insts.push(Inst::Label { name: next_label });
-
-            let (cond_insts, je_label) =
-                self.gen_cond_and_branch(branch.cond_expr, branch.true_branch);
 
-            branch_insts.extend(cond_insts);
-            branches_insts.push(branch_insts);
+        let mut block_insts = vec![];
 
-            next_label = je_label;
+        for idx in 0..self.function.blocks.len() {
+            self.gen_block(BlockId(idx));
+            block_insts.push(std::mem::take(&mut self.insts));
         }
 
-        let mut final_branch_insts = vec![];
+        for (idx, block_insts) in block_insts.into_iter().enumerate() {
+            if let Some(name) = self.block_labels[idx] {
+                insts.push(Inst::Label { name });
+            }
 
-        if let Some(final_branch) = if_expr.final_branch {
-            final_branch_insts.push(Inst::Label { name: next_label });
-            next_label = self.make_label();
-            final_branch_insts.extend(self.gen_compound_expr(final_branch));
+            insts.extend(block_insts);
         }
 
-        let mut if_insts = vec![];
-        let exit_label = next_label;
-
-        if_insts.extend(first_branch_insts);
-
-        if !branches_insts.is_empty() || !final_branch_insts.is_empty() {
-            if_insts.push(Inst::Jmp { label: exit_label });
-        }
+        insts
+    }
 
-        for branch_insts in branches_insts {
-            if_insts.extend(branch_insts);
-            if_insts.push(Inst::Jmp { label: exit_label });
-        }
+    fn gen_block(&mut self, block_id: BlockId) {
+        let block = &self.function.blocks[block_id.0];
 
-        if !final_branch_insts.is_empty() {
-            if_insts.extend(final_branch_insts);
+        for inst in &block.insts {
+            self.gen_inst(inst);
         }
 
-        if_insts.push(Inst::Label { name: exit_label });
-
-        if_insts
-    }
-
-    fn gen_cond_and_branch(
-        &mut self,
-        cond_expr: &Expr,
-        branch: CompoundExpr,
-    ) -> (Vec<Inst>, Symbol) {
-        let mut insts = self.gen_expr(cond_expr);
-
-        insts.push(Inst::Cmp {
-            reg: Reg::Eax,
-            value: 0,
-        });
+        let next_block = BlockId(block_id.0 + 1);
+
+        match &block.terminator {
+            Terminator::Jump(call) => self.gen_block_call(call, next_block),
+            Terminator::Branch {
+                then_call,
+                else_call,
+                ..
+            } if then_call == else_call => self.gen_block_call(then_call, next_block),
+            Terminator::Branch {
+                cond,
+                then_call,
+                else_call,
+            } => {
+                let cond = match self.branch_conds[cond.0] {
+                    Some(cond) => cond,
+                    None => {
+                        self.load_eax(*cond);
+                        self.emit(Inst::Cmp {
+                            target: Reg::Eax,
+                            source: Arg::Imm(0),
+                        });
+
+                        Cond::NotZero
+                    }
+                };
 
-        let next_branch_label = self.make_label();
-        insts.push(Inst::Je {
-            label: next_branch_label,
-        });
+                self.gen_branch(cond, then_call, else_call, next_block);
+            }
+            Terminator::Return(value) => {
+                if let Some(value) = value {
+                    self.load_eax(*value);
+                }
 
-        insts.extend(self.gen_compound_expr(branch));
+                if self.frame_bytes != 0 {
+                    self.emit(Inst::Add {
+                        target: Arg::Reg(Reg::Rsp),
+                        source: Arg::Imm(self.frame_bytes),
+                    });
+                }
 
-        (insts, next_branch_label)
+                self.emit(Inst::Pop { target: Reg::Rbp });
+                self.emit(Inst::Ret);
+            }
+        }
     }
 
-    fn gen_for_expr(&mut self, for_expr: ForExpr) -> Vec<Inst> {
-        let mut insts = vec![];
-
-        self.enter_scope();
-
-        let start_label = self.make_label();
-        let exit_label = self.make_label();
-
-        self.set_innermost_start_label(start_label);
-        self.set_innermost_exit_label(exit_label);
-
-        match for_expr.iteration {
-            Some(ForIteration::Conditional { cond_expr }) => {
-                insts.push(Inst::Label { name: start_label });
-
-                insts.extend(self.gen_expr(cond_expr));
-                insts.push(Inst::Cmp {
-                    reg: Reg::Eax,
-                    value: 0,
+    fn gen_inst(&mut self, inst: &IrInst) {
+        let is_needed = inst.result.is_some_and(|result| self.is_needed[result.0]);
+
+        match &inst.kind {
+            InstKind::Const(_) => return,
+            InstKind::Binary { op, lhs, rhs, .. } => {
+                self.load_eax(*lhs);
+
+                match op {
+                    BinaryOp::Add => self.emit(Inst::Add {
+                        target: Arg::Reg(Reg::Eax),
+                        source: self.operand(*rhs),
+                    }),
+                    BinaryOp::Sub => self.emit(Inst::Sub {
+                        target: Arg::Reg(Reg::Eax),
+                        source: self.operand(*rhs),
+                    }),
+                    BinaryOp::Mul => {
+                        self.load(Reg::Ecx, *rhs);
+                        self.emit(Inst::Imul {
+                            target: Reg::Eax,
+                            source: Reg::Ecx,
+                        });
+                    }
+                    BinaryOp::Div | BinaryOp::Rem => {
+                        // The dividend is sign-extended into edx:eax, and the quotient is left in
+                        // eax, whereas the remainder is left in edx.
+                        self.load(Reg::Ecx, *rhs);
+                        self.emit(Inst::Cdq);
+                        self.emit(Inst::Idiv { source: Reg::Ecx });
+
+                        if *op == BinaryOp::Rem {
+                            self.emit(Inst::Mov {
+                                target: Arg::Reg(Reg::Eax),
+                                source: Arg::Reg(Reg::Edx),
+                            });
+                        }
+                    }
+                }
+            }
+            // Comparisons that aren't needed can be left out, as they have no
+            // effect.
+            InstKind::Less { .. } | InstKind::LessEqual { .. } | InstKind::Overflows { .. }
+                if !is_needed => {}
+            InstKind::Less { lhs, rhs } | InstKind::LessEqual { lhs, rhs } => {
+                self.load_eax(*lhs);
+                self.emit(Inst::Cmp {
+                    target: Reg::Eax,
+                    source: self.operand(*rhs),
                 });
 
-                insts.push(Inst::Je { label: exit_label });
-                insts.extend(self.gen_compound_expr(for_expr.body));
-            }
-            Some(ForIteration::Iterative {
-                identifier,
-                start_expr,
-                end_expr,
-                range_kind,
-            }) => {
-                insts.extend(self.gen_bind_def_expr(BindDef {
-                    identifier,
-                    value: start_expr,
-                }));
-
-                insts.push(Inst::Label { name: start_label });
-
-                let bind_ref = BindRef { identifier };
-
-                insts.extend(self.gen_bind_ref_expr(bind_ref));
-                // FIXME: This is specialized because I can't allocate registers at will.
-                let value = match end_expr {
-                    Expr::Const(Const::IntegerConstant { value }) => *value,
-                    _ => unimplemented!(),
+                let cond = match inst.kind {
+                    InstKind::Less { .. } => Cond::Less,
+                    _ => Cond::LessEqual,
                 };
 
-                insts.push(Inst::Cmp {
-                    reg: Reg::Eax,
-                    value,
-                });
-                match range_kind {
-                    RangeKind::Inclusive => insts.push(Inst::Jg { label: exit_label }),
-                    RangeKind::Exclusive => insts.push(Inst::Jge { label: exit_label }),
+                self.gen_flag(inst.result.unwrap(), cond);
+            }
+            InstKind::Overflows { op, lhs, rhs } => {
+                self.load_eax(*lhs);
+
+                match op {
+                    BinaryOp::Add => self.emit(Inst::Add {
+                        target: Arg::Reg(Reg::Eax),
+                        source: self.operand(*rhs),
+                    }),
+                    BinaryOp::Sub => self.emit(Inst::Sub {
+                        target: Arg::Reg(Reg::Eax),
+                        source: self.operand(*rhs),
+                    }),
+                    _ => unreachable!("only steps of ranges are checked for overflows"),
                 }
 
-                insts.extend(self.gen_compound_expr(for_expr.body));
+                self.gen_flag(inst.result.unwrap(), Cond::Overflow);
+            }
+            InstKind::CheckStep { step, .. } => {
+                let positive_label = self.codegen.make_label();
 
-                insts.extend(self.gen_bind_ref_expr(bind_ref));
-                insts.push(Inst::Add {
-                    target: Arg::Reg(Reg::Eax),
-                    source: Arg::Imm(1),
+                self.load_eax(*step);
+                self.emit(Inst::Cmp {
+                    target: Reg::Eax,
+                    source: Arg::Imm(0),
                 });
-                let bind_offset = self.get_in_scope(bind_ref);
-                insts.push(Inst::Mov {
-                    target: Arg::MemOffset {
-                        base: Reg::Rbp,
-                        offset: -(bind_offset as i32),
-                    },
-                    source: Arg::Reg(Reg::Eax),
+                self.emit(Inst::Jg {
+                    label: positive_label,
+                });
+                self.emit(Inst::Call {
+                    label: self.codegen.ctx.get_or_intern_str("sophia_abort"),
+                });
+                self.emit(Inst::Label {
+                    name: positive_label,
                 });
             }
-            None => {
-                insts.push(Inst::Label { name: start_label });
-                insts.extend(self.gen_compound_expr(for_expr.body));
-            }
+            InstKind::Call { callee, args } => self.gen_call(*callee, args),
         }
 
-        insts.push(Inst::Jmp { label: start_label });
-        insts.push(Inst::Label { name: exit_label });
-
-        self.exit_scope();
-
-        insts
+        if let Some(slot) = inst.result.and_then(|result| self.slots[result.0]) {
+            self.emit(Inst::Mov {
+                target: slot,
+                source: Arg::Reg(Reg::Eax),
+            });
+        }
     }
 
-    fn gen_break_expr(&mut self) -> Vec<Inst> {
-        let exit_label = self.get_innermost_exit_label();
-
-        vec![Inst::Jmp { label: exit_label }]
-    }
+    /// Leaves one in `eax` if the flags hold the condition, or zero otherwise,
+    /// unless the comparison's branch tests the flags itself.
+    fn gen_flag(&mut self, result: Value, cond: Cond) {
+        if self.branch_conds[result.0].is_some() {
+            return;
+        }
 
-    fn gen_continue_expr(&mut self) -> Vec<Inst> {
-        let start_label = self.get_innermost_start_label();
+        let done_label = self.codegen.make_label();
 
-        vec![Inst::Jmp { label: start_label }]
+        // Moves leave the flags as they are.
+        self.emit(Inst::Mov {
+            target: Arg::Reg(Reg::Eax),
+            source: Arg::Imm(1),
+        });
+        self.emit(cond.jump(false, done_label));
+        self.emit(Inst::Mov {
+            target: Arg::Reg(Reg::Eax),
+            source: Arg::Imm(0),
+        });
+        self.emit(Inst::Label { name: done_label });
     }
 
-    fn gen_bind_def_expr(&mut self, bind_def: BindDef) -> Vec<Inst> {
-        let mut insts = self.gen_expr(bind_def.value);
+    fn gen_call(&mut self, callee: Callee, args: &[Value]) {
+        assert!(
+            args.len() <= ARG_REGS.len(),
+            "FIXME: pass arguments on the stack"
+        );
+
+        // None of the registers of arguments is `eax`, so an argument that's
+        // left there isn't overwritten by the ones before it.
+        for (&arg, &arg_reg) in args.iter().zip(ARG_REGS.iter()) {
+            self.emit(Inst::Mov {
+                target: Arg::Reg(arg_reg.dword()),
+                source: self.operand(arg),
+            });
+        }
 
-        let offset = self.insert_in_scope(bind_def);
+        let ctx = self.codegen.ctx;
 
-        insts.push(Inst::Mov {
-            target: Arg::MemOffset {
-                base: Reg::Rbp,
-                // FIXME: Should not cast allocated_stack_bytes to i32.
-                offset: -(offset as i32),
-            },
-            source: Arg::Reg(Reg::Eax),
-        });
+        // Builtins are functions of the runtime library that's linked in.
+        let (label, is_extern) = match callee {
+            Callee::Function(name) => (ctx.get_or_intern_str(name), false),
+            Callee::Builtin(builtin) => (ctx.get_or_intern_str(builtin.runtime_symbol()), false),
+            Callee::Extern(name) => (ctx.get_or_intern_str(name), true),
+            Callee::Host(_) => unreachable!(
+                "programs that call host functions are rejected before code generation"
+            ),
+        };
 
-        insts
-    }
+        if !is_extern {
+            self.emit(Inst::Call { label });
 
-    fn gen_bind_ref_expr(&mut self, bind_ref: BindRef) -> Vec<Inst> {
-        let bind_offset = self.get_in_scope(bind_ref);
+            return;
+        }
 
-        vec![Inst::Mov {
-            target: Arg::Reg(Reg::Eax),
-            source: Arg::MemOffset {
-                base: Reg::Rbp,
-                offset: -(bind_offset as i32),
+        // The generated code doesn't keep the stack aligned, but C functions
+        // expect it to be aligned to 16 bytes. `rbx` is preserved across the
+        // call, so it keeps where the stack was, but it has to be preserved for
+        // the caller too.
+        for inst in [
+            Inst::Push { source: Reg::Rbx },
+            Inst::Mov {
+                target: Arg::Reg(Reg::Rbx),
+                source: Arg::Reg(Reg::Rsp),
             },
-        }]
-    }
-
-    fn gen_compound_expr(&mut self, compound_expr: CompoundExpr) -> Vec<Inst> {
-        self.enter_scope();
-        let insts = compound_expr
-            .exprs
-            .iter()
-            .flat_map(|e| self.gen_expr(e))
-            .collect();
-        self.exit_scope();
-
-        insts
+            Inst::And {
+                target: Arg::Reg(Reg::Rsp),
+                source: Arg::Imm(-16),
+            },
+            Inst::Call { label },
+            Inst::Mov {
+                target: Arg::Reg(Reg::Rsp),
+                source: Arg::Reg(Reg::Rbx),
+            },
+            Inst::Pop { target: Reg::Rbx },
+        ] {
+            self.emit(inst);
+        }
     }
 
-    fn gen_fn_call_expr(&mut self, fn_call_expr: FnCallExpr) -> Vec<Inst> {
-        vec![Inst::Call {
-            label: fn_call_expr.identifier,
-        }]
+    /// Goes to one block or the other depending on the condition that the
+    /// flags hold. The block that comes next is gone to by not jumping at all,
+    /// and moves in between leave the flags as they are.
+    fn gen_branch(
+        &mut self,
+        cond: Cond,
+        then_call: &BlockCall,
+        else_call: &BlockCall,
+        next_block: BlockId,
+    ) {
+        if self.moves(then_call).is_empty() && then_call.block != next_block {
+            let then_label = self.block_label(then_call.block);
+            self.emit(cond.jump(false, then_label));
+            self.gen_block_call(else_call, next_block);
+        } else if self.moves(else_call).is_empty() {
+            let else_label = self.block_label(else_call.block);
+            self.emit(cond.jump(true, else_label));
+            self.gen_block_call(then_call, next_block);
+        } else {
+            let else_label = self.codegen.make_label();
+
+            self.emit(cond.jump(true, else_label));
+            self.gen_block_call(then_call, BlockId(usize::MAX));
+            self.emit(Inst::Label { name: else_label });
+            self.gen_block_call(else_call, next_block);
+        }
     }
 
-    fn make_label(&mut self) -> Symbol {
-        let label_count = self.label_counter;
-        self.label_counter += 1;
+    /// Stores the arguments in the slots of the block's parameters, then goes
+    /// to it, unless it's the next block.
+    fn gen_block_call(&mut self, call: &BlockCall, next_block: BlockId) {
+        let moves = self.moves(call);
 
-        self.ctx.get_or_intern_str(&format!(".L{}", label_count))
-    }
+        let is_clobbered = moves
+            .iter()
+            .any(|&(_, arg)| moves.iter().any(|&(param, _)| param == arg));
+
+        // Arguments that are parameters assigned first are saved on the stack
+        // along with the others, so that all of them are assigned at once.
+        if is_clobbered {
+            for &(_, arg) in &moves {
+                self.load_eax(arg);
+                self.emit(Inst::Push { source: Reg::Rax });
+            }
 
-    fn enter_scope(&mut self) {
-        self.scope_stack.push(Scope::default());
-    }
+            for &(param, _) in moves.iter().rev() {
+                self.emit(Inst::Pop { target: Reg::Rax });
+                self.emit(Inst::Mov {
+                    target: self.slots[param.0].unwrap(),
+                    source: Arg::Reg(Reg::Eax),
+                });
+            }
+        } else {
+            for &(param, arg) in &moves {
+                let source = match self.constants[arg.0] {
+                    Some(constant) => Arg::Imm(constant),
+                    None => {
+                        self.load_eax(arg);
+                        Arg::Reg(Reg::Eax)
+                    }
+                };
 
-    fn exit_scope(&mut self) {
-        self.scope_stack.pop();
+                self.emit(Inst::Mov {
+                    target: self.slots[param.0].unwrap(),
+                    source,
+                });
+            }
+        }
 
-        if self.scope_stack.is_empty() {
-            self.allocated_stack_bytes = 0;
+        if call.block != next_block {
+            let label = self.block_label(call.block);
+            self.emit(Inst::Jmp { label });
         }
     }
 
-    fn get_this_scope_mut(&mut self) -> &mut Scope {
-        self.scope_stack.last_mut().unwrap()
+    fn block_label(&mut self, block: BlockId) -> Symbol {
+        match self.block_labels[block.0] {
+            Some(label) => label,
+            None => *self.block_labels[block.0].insert(self.codegen.make_label()),
+        }
     }
 
-    fn insert_in_scope(&mut self, bind_def: BindDef) -> usize {
-        let bumped_allocated_stack_bytes = self.allocated_stack_bytes + 4;
-
-        self.get_this_scope_mut()
-            .memory_offset_by_symbol
-            .insert(bind_def.identifier, bumped_allocated_stack_bytes);
-
-        self.allocated_stack_bytes = bumped_allocated_stack_bytes;
-
-        self.allocated_stack_bytes
+    /// Parameters of the block that are needed, each along with its argument,
+    /// leaving out the ones that are passed themselves.
+    fn moves(&self, call: &BlockCall) -> Vec<(Value, Value)> {
+        moves(self.function, call, &self.is_needed)
     }
 
-    fn get_in_scope(&self, bind_ref: BindRef) -> usize {
-        self.find_in_scope(|scope| {
-            scope
-                .memory_offset_by_symbol
-                .get(&bind_ref.identifier)
-                .cloned()
-        })
+    fn load_eax(&mut self, value: Value) {
+        self.load(Reg::Eax, value);
     }
 
-    fn find_in_scope<R: Clone, F: Fn(&Scope) -> Option<R>>(&self, f: F) -> R {
-        for scope in self.scope_stack.iter().rev() {
-            if let Some(r) = f(scope) {
-                return r;
-            }
+    fn load(&mut self, reg: Reg, value: Value) {
+        if !(self.is_in_eax[value.0] && matches!(reg, Reg::Eax)) {
+            self.emit(Inst::Mov {
+                target: Arg::Reg(reg),
+                source: self.operand(value),
+            });
         }
-
-        unreachable!("scope does not exist")
     }
 
-    fn get_innermost_start_label(&self) -> Symbol {
-        self.find_in_scope(|scope| scope.innermost_start_label)
+    /// Operand for the value, which is either a constant, `eax` or a slot.
+    fn operand(&self, value: Value) -> Arg {
+        if let Some(constant) = self.constants[value.0] {
+            Arg::Imm(constant)
+        } else if self.is_in_eax[value.0] {
+            Arg::Reg(Reg::Eax)
+        } else {
+            self.slots[value.0].expect("values that are used are needed")
+        }
     }
 
-    fn set_innermost_start_label(&mut self, start_label: Symbol) {
-        self.get_this_scope_mut().innermost_start_label = Some(start_label)
+    fn emit(&mut self, inst: Inst) {
+        self.insts.push(inst);
     }
+}
 
-    fn get_innermost_exit_label(&self) -> Symbol {
-        self.find_in_scope(|scope| scope.innermost_exit_label)
+/// Operands of the instruction that it can take from `eax`, which are the ones
+/// it uses before it overwrites `eax`.
+fn eax_operands(kind: &InstKind) -> Vec<Value> {
+    match kind {
+        InstKind::Const(_) => vec![],
+        InstKind::Binary { lhs, .. }
+        | InstKind::Less { lhs, .. }
+        | InstKind::LessEqual { lhs, .. }
+        | InstKind::Overflows { lhs, .. } => vec![*lhs],
+        InstKind::CheckStep { step, .. } => vec![*step],
+        InstKind::Call { args, .. } => args.clone(),
     }
+}
 
-    fn set_innermost_exit_label(&mut self, exit_label: Symbol) {
-        self.get_this_scope_mut().innermost_exit_label = Some(exit_label)
+/// Operand of the terminator that it can take from `eax`, which is the first
+/// argument stored by a jump.
+fn eax_terminator_operand(
+    function: &ir::Function,
+    terminator: &Terminator,
+    is_needed: &[bool],
+) -> Vec<Value> {
+    match terminator {
+        Terminator::Jump(call) => moves(function, call, is_needed)
+            .first()
+            .map(|&(_, arg)| arg)
+            .into_iter()
+            .collect(),
+        Terminator::Branch { cond, .. } => vec![*cond],
+        Terminator::Return(value) => value.iter().copied().collect(),
     }
 }
 
+fn moves(function: &ir::Function, call: &BlockCall, is_needed: &[bool]) -> Vec<(Value, Value)> {
+    let params = &function.blocks[call.block.0].params;
+
+    params
+        .iter()
+        .zip(&call.args)
+        .filter(|&(&param, &arg)| is_needed[param.0] && param != arg)
+        .map(|(&param, &arg)| (param, arg))
+        .collect()
+}
+
 pub(crate) struct X86Program<'ctx> {
     ctx: &'ctx CompilerContext,
+    /// Symbols visible to the linker.
+    globals: Vec<Symbol>,
     instructions: Vec<Inst>,
 }
 
@@ -424,16 +678,25 @@ pub(crate) struct X86Program<'ctx> {
 enum Inst {
     Label { name: Symbol },
     Mov { target: Arg, source: Arg },
-    Cmp { reg: Reg, value: i32 },
+    Cmp { target: Reg, source: Arg },
     Je { label: Symbol },
+    Jne { label: Symbol },
     Jg { label: Symbol },
     Jge { label: Symbol },
+    Jl { label: Symbol },
+    Jle { label: Symbol },
+    Jo { label: Symbol },
+    Jno { label: Symbol },
     Jmp { label: Symbol },
     Ret,
     Push { source: Reg },
     Pop { target: Reg },
     Sub { target: Arg, source: Arg },
     Add { target: Arg, source: Arg },
+    And { target: Arg, source: Arg },
+    Imul { target: Reg, source: Reg },
+    Cdq,
+    Idiv { source: Reg },
     Call { label: Symbol },
 }
 
@@ -447,12 +710,56 @@ enum Arg {
 #[derive(Clone, Copy)]
 enum Reg {
     Eax,
+    Edi,
+    Esi,
+    Edx,
+    Ecx,
+    R8d,
+    R9d,
+    Rax,
+    Rdi,
+    Rsi,
+    Rdx,
+    Rcx,
+    R8,
+    R9,
+    Rbx,
     Rbp,
     Rsp,
 }
 
+/// Size of each slot of a frame, which only holds 32-bit integers for now.
+const SLOT_BYTES: usize = 4;
+
+/// Registers used to pass integer arguments, in order, as per the System V
+/// AMD64 ABI.
+const ARG_REGS: [Reg; 6] = [Reg::Rdi, Reg::Rsi, Reg::Rdx, Reg::Rcx, Reg::R8, Reg::R9];
+
+impl Reg {
+    /// The lower 32 bits of this register.
+    fn dword(self) -> Reg {
+        match self {
+            Reg::Rax => Reg::Eax,
+            Reg::Rdi => Reg::Edi,
+            Reg::Rsi => Reg::Esi,
+            Reg::Rdx => Reg::Edx,
+            Reg::Rcx => Reg::Ecx,
+            Reg::R8 => Reg::R8d,
+            Reg::R9 => Reg::R9d,
+            _ => unreachable!("register has no lower 32 bits"),
+        }
+    }
+}
+
 impl fmt::Display for X86Program<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "    .intel_syntax noprefix")?;
+        writeln!(f, "    .text")?;
+
+        for &global in &self.globals {
+            writeln!(f, "    .globl {}", self.ctx.resolve_symbol(global))?;
+        }
+
         for inst in &self.instructions {
             let ctx_inst = CtxInst {
                 ctx: self.ctx,
@@ -461,6 +768,9 @@ impl fmt::Display for X86Program<'_> {
             writeln!(f, "{}", ctx_inst)?;
         }
 
+        // Tells the linker that the stack doesn't need to be executable.
+        writeln!(f, "    .section .note.GNU-stack,\"\",@progbits")?;
+
         Ok(())
     }
 }
@@ -479,16 +789,25 @@ impl fmt::Display for CtxInst<'_> {
         match self.inst {
             Inst::Label { name } => write!(f, "{}:", self.ctx.resolve_symbol(name)),
             Inst::Mov { target, source } => write!(f, "mov {}, {}", target, source),
-            Inst::Cmp { reg, value } => write!(f, "cmp {}, {}", reg, value),
+            Inst::Cmp { target, source } => write!(f, "cmp {}, {}", target, source),
             Inst::Je { label } => write!(f, "je {}", self.ctx.resolve_symbol(label)),
+            Inst::Jne { label } => write!(f, "jne {}", self.ctx.resolve_symbol(label)),
             Inst::Jg { label } => write!(f, "jg {}", self.ctx.resolve_symbol(label)),
             Inst::Jge { label } => write!(f, "jge {}", self.ctx.resolve_symbol(label)),
+            Inst::Jl { label } => write!(f, "jl {}", self.ctx.resolve_symbol(label)),
+            Inst::Jle { label } => write!(f, "jle {}", self.ctx.resolve_symbol(label)),
+            Inst::Jo { label } => write!(f, "jo {}", self.ctx.resolve_symbol(label)),
+            Inst::Jno { label } => write!(f, "jno {}", self.ctx.resolve_symbol(label)),
             Inst::Jmp { label } => write!(f, "jmp {}", self.ctx.resolve_symbol(label)),
             Inst::Ret => write!(f, "ret"),
             Inst::Push { source } => write!(f, "push {}", source),
             Inst::Pop { target } => write!(f, "pop {}", target),
             Inst::Sub { target, source } => write!(f, "sub {}, {}", target, source),
             Inst::Add { target, source } => write!(f, "add {}, {}", target, source),
+            Inst::And { target, source } => write!(f, "and {}, {}", target, source),
+            Inst::Imul { target, source } => write!(f, "imul {}, {}", target, source),
+            Inst::Cdq => write!(f, "cdq"),
+            Inst::Idiv { source } => write!(f, "idiv {}", source),
             Inst::Call { label } => write!(f, "call {}", self.ctx.resolve_symbol(label)),
         }
     }
@@ -516,8 +835,271 @@ impl fmt::Display for Reg {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Reg::Eax => write!(f, "eax"),
+            Reg::Edi => write!(f, "edi"),
+            Reg::Esi => write!(f, "esi"),
+            Reg::Edx => write!(f, "edx"),
+            Reg::Ecx => write!(f, "ecx"),
+            Reg::R8d => write!(f, "r8d"),
+            Reg::R9d => write!(f, "r9d"),
+            Reg::Rax => write!(f, "rax"),
+            Reg::Rdi => write!(f, "rdi"),
+            Reg::Rsi => write!(f, "rsi"),
+            Reg::Rdx => write!(f, "rdx"),
+            Reg::Rcx => write!(f, "rcx"),
+            Reg::R8 => write!(f, "r8"),
+            Reg::R9 => write!(f, "r9"),
+            Reg::Rbx => write!(f, "rbx"),
             Reg::Rbp => write!(f, "rbp"),
             Reg::Rsp => write!(f, "rsp"),
         }
     }
 }
+
+impl X86Program<'_> {
+    /// Encodes the instructions to machine code, in an object file with a
+    /// symbol for each top-level function.
+    ///
+    /// Jumps and calls always take 32-bit displacements, as the assembler would
+    /// have to pick between sizes otherwise.
+    pub(crate) fn encode(&self) -> ObjectFile {
+        let mut text = vec![];
+        let mut label_offsets = HashMap::default();
+        // Offsets of the displacements to the labels, which aren't all known
+        // until every instruction is encoded.
+        let mut fixups = vec![];
+
+        for inst in &self.instructions {
+            match *inst {
+                Inst::Label { name } => {
+                    label_offsets.insert(name, text.len());
+                }
+                Inst::Mov {
+                    target: Arg::Reg(target),
+                    source: Arg::Imm(value),
+                } if !target.is_qword() => {
+                    encode_rex(&mut text, false, 0, target.number());
+                    text.push(0xb8 + (target.number() & 7));
+                    text.extend_from_slice(&value.to_le_bytes());
+                }
+                Inst::Mov { target, source } => {
+                    encode_binary(&mut text, [0x89, 0x8b, 0xc7], 0, target, source)
+                }
+                Inst::Add { target, source } => {
+                    encode_binary(&mut text, [0x01, 0x03, 0x81], 0, target, source)
+                }
+                Inst::Sub { target, source } => {
+                    encode_binary(&mut text, [0x29, 0x2b, 0x81], 5, target, source)
+                }
+                Inst::And { target, source } => {
+                    encode_binary(&mut text, [0x21, 0x23, 0x81], 4, target, source)
+                }
+                Inst::Cmp { target, source } => {
+                    encode_binary(&mut text, [0x39, 0x3b, 0x81], 7, Arg::Reg(target), source)
+                }
+                Inst::Imul { target, source } => {
+                    encode_rex(
+                        &mut text,
+                        target.is_qword(),
+                        target.number(),
+                        source.number(),
+                    );
+                    text.extend_from_slice(&[0x0f, 0xaf]);
+                    encode_modrm(&mut text, target.number(), Arg::Reg(source));
+                }
+                Inst::Idiv { source } => {
+                    encode_rex(&mut text, source.is_qword(), 0, source.number());
+                    text.push(0xf7);
+                    encode_modrm(&mut text, 7, Arg::Reg(source));
+                }
+                Inst::Cdq => text.push(0x99),
+                Inst::Push { source } => {
+                    encode_rex(&mut text, false, 0, source.number());
+                    text.push(0x50 + (source.number() & 7));
+                }
+                Inst::Pop { target } => {
+                    encode_rex(&mut text, false, 0, target.number());
+                    text.push(0x58 + (target.number() & 7));
+                }
+                Inst::Ret => text.push(0xc3),
+                Inst::Jmp { label } => encode_jump(&mut text, &mut fixups, &[0xe9], label),
+                Inst::Jo { label } => encode_jump(&mut text, &mut fixups, &[0x0f, 0x80], label),
+                Inst::Jno { label } => encode_jump(&mut text, &mut fixups, &[0x0f, 0x81], label),
+                Inst::Je { label } => encode_jump(&mut text, &mut fixups, &[0x0f, 0x84], label),
+                Inst::Jne { label } => encode_jump(&mut text, &mut fixups, &[0x0f, 0x85], label),
+                Inst::Jl { label } => encode_jump(&mut text, &mut fixups, &[0x0f, 0x8c], label),
+                Inst::Jge { label } => encode_jump(&mut text, &mut fixups, &[0x0f, 0x8d], label),
+                Inst::Jle { label } => encode_jump(&mut text, &mut fixups, &[0x0f, 0x8e], label),
+                Inst::Jg { label } => encode_jump(&mut text, &mut fixups, &[0x0f, 0x8f], label),
+                Inst::Call { label } => encode_jump(&mut text, &mut fixups, &[0xe8], label),
+            }
+        }
+
+        let mut relocations = vec![];
+
+        for (offset, label) in fixups {
+            match label_offsets.get(&label) {
+                Some(&target) => {
+                    let displacement = target as i32 - (offset + 4) as i32;
+                    text[offset..offset + 4].copy_from_slice(&displacement.to_le_bytes());
+                }
+                // Only calls go to labels that the program doesn't define,
+                // which are functions of the runtime library.
+                None => relocations.push(Relocation {
+                    offset,
+                    symbol: self.ctx.resolve_symbol(label).to_owned(),
+                }),
+            }
+        }
+
+        // Each function's code goes on until the next one's.
+        let mut function_offsets: Vec<_> = self
+            .globals
+            .iter()
+            .map(|global| (label_offsets[global], *global))
+            .collect();
+        function_offsets.sort_by_key(|&(offset, _)| offset);
+
+        let ends = function_offsets
+            .iter()
+            .skip(1)
+            .map(|&(offset, _)| offset)
+            .chain([text.len()]);
+
+        let functions = function_offsets
+            .iter()
+            .zip(ends)
+            .map(|(&(offset, global), end)| FunctionSymbol {
+                name: self.ctx.resolve_symbol(global).to_owned(),
+                offset,
+                size: end - offset,
+            })
+            .collect();
+
+        ObjectFile {
+            text,
+            functions,
+            relocations,
+        }
+    }
+}
+
+impl Reg {
+    /// Number of the register in the encoding of instructions, whose fourth
+    /// bit goes in the REX prefix.
+    fn number(self) -> u8 {
+        match self {
+            Reg::Eax | Reg::Rax => 0,
+            Reg::Ecx | Reg::Rcx => 1,
+            Reg::Edx | Reg::Rdx => 2,
+            Reg::Rbx => 3,
+            Reg::Rsp => 4,
+            Reg::Rbp => 5,
+            Reg::Esi | Reg::Rsi => 6,
+            Reg::Edi | Reg::Rdi => 7,
+            Reg::R8d | Reg::R8 => 8,
+            Reg::R9d | Reg::R9 => 9,
+        }
+    }
+
+    fn is_qword(self) -> bool {
+        matches!(
+            self,
+            Reg::Rax
+                | Reg::Rdi
+                | Reg::Rsi
+                | Reg::Rdx
+                | Reg::Rcx
+                | Reg::R8
+                | Reg::R9
+                | Reg::Rbx
+                | Reg::Rbp
+                | Reg::Rsp
+        )
+    }
+}
+
+/// Encodes an instruction of two operands, given its opcodes of the forms
+/// `r/m, reg`, `reg, r/m` and `r/m, imm32`, the last of which takes the
+/// extension of the opcode in the ModRM byte.
+fn encode_binary(text: &mut Vec<u8>, opcodes: [u8; 3], extension: u8, target: Arg, source: Arg) {
+    let [rm_reg, reg_rm, rm_imm] = opcodes;
+
+    match (target, source) {
+        (_, Arg::Imm(value)) => {
+            let is_qword = matches!(target, Arg::Reg(reg) if reg.is_qword());
+            encode_rex(text, is_qword, 0, rm_number(target));
+            text.push(rm_imm);
+            encode_modrm(text, extension, target);
+            text.extend_from_slice(&value.to_le_bytes());
+        }
+        (_, Arg::Reg(source)) => {
+            encode_rex(text, source.is_qword(), source.number(), rm_number(target));
+            text.push(rm_reg);
+            encode_modrm(text, source.number(), target);
+        }
+        (Arg::Reg(target), source) => {
+            encode_rex(text, target.is_qword(), target.number(), rm_number(source));
+            text.push(reg_rm);
+            encode_modrm(text, target.number(), source);
+        }
+        _ => unreachable!("instructions can't have two memory operands"),
+    }
+}
+
+/// Encodes a jump or call to the label, whose displacement is filled in later.
+fn encode_jump(
+    text: &mut Vec<u8>,
+    fixups: &mut Vec<(usize, Symbol)>,
+    opcode: &[u8],
+    label: Symbol,
+) {
+    text.extend_from_slice(opcode);
+    fixups.push((text.len(), label));
+    text.extend_from_slice(&[0; 4]);
+}
+
+/// Encodes the REX prefix, if the instruction needs one, for a 64-bit operand
+/// or registers past the eighth in the `reg` or `r/m` fields.
+fn encode_rex(text: &mut Vec<u8>, is_qword: bool, reg: u8, rm: u8) {
+    let rex = 0x40 | (u8::from(is_qword) << 3) | ((reg >> 3) << 2) | (rm >> 3);
+
+    if rex != 0x40 {
+        text.push(rex);
+    }
+}
+
+/// Number of the register in the `r/m` field of the operand, which is the base
+/// register of a memory operand.
+fn rm_number(arg: Arg) -> u8 {
+    match arg {
+        Arg::Reg(reg) | Arg::MemOffset { base: reg, .. } => reg.number(),
+        Arg::Imm(_) => unreachable!("immediates aren't in the ModRM byte"),
+    }
+}
+
+/// Encodes the ModRM byte of the `reg` field and the `r/m` operand, followed by
+/// the operand's displacement, if it's in memory.
+fn encode_modrm(text: &mut Vec<u8>, reg: u8, rm: Arg) {
+    let reg = (reg & 7) << 3;
+
+    match rm {
+        Arg::Reg(rm) => text.push(0xc0 | reg | (rm.number() & 7)),
+        Arg::MemOffset { base, offset } => {
+            let base = base.number() & 7;
+            let is_byte = i8::try_from(offset).is_ok();
+            text.push(if is_byte { 0x40 } else { 0x80 } | reg | base);
+
+            // A base of `rsp` needs a SIB byte of no index.
+            if base == 4 {
+                text.push(0x24);
+            }
+
+            if is_byte {
+                text.push(offset as i8 as u8);
+            } else {
+                text.extend_from_slice(&offset.to_le_bytes());
+            }
+        }
+        Arg::Imm(_) => unreachable!("immediates aren't in the ModRM byte"),
+    }
+}
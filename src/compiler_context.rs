@@ -1,41 +1,237 @@
-use std::cell::RefCell;
+use std::sync::{Mutex, MutexGuard, PoisonError, RwLock};
 
 use bumpalo::Bump;
 
-use crate::ast::{Decl, ElseIfBranch, Expr, Param};
-use crate::interner::{StringInterner, Symbol};
+use crate::ast::{
+    Binding, Decl, ElseIfBranch, Expr, FieldDef, FieldInit, MatchArm, Param, Type, VariantDef,
+};
+use crate::host::{HermeticHost, Host};
+use crate::interner::{StringInterner, Symbol, Symbols};
+use crate::line_index::{Col, Line};
+use crate::scanner::BytePos;
+use crate::source_map::SourceMap;
 
+/// Everything a compilation allocates, which lives for as long as the
+/// compilation does.
+///
+/// The context is `Send` and `Sync`, so that phases running on different
+/// threads, e.g., the language server's, can share it rather than copy the
+/// source code into contexts of their own. Strings are interned behind a
+/// read-write lock, as they're mostly looked up once the program is parsed,
+/// and every kind of node has an arena of its own, so that threads building
+/// different kinds of nodes don't wait on each other.
 pub(crate) struct CompilerContext {
-    source_code: String,
-    string_interner: RefCell<StringInterner>,
-    exprs: Bump,
-    else_if_branches: Bump,
-    params: Bump,
-    decls: Bump,
+    source_map: SourceMap,
+    string_interner: RwLock<StringInterner>,
+    symbols: Symbols,
+    exprs: SyncArena,
+    else_if_branches: SyncArena,
+    match_arms: SyncArena,
+    params: SyncArena,
+    field_defs: SyncArena,
+    field_inits: SyncArena,
+    variant_defs: SyncArena,
+    bindings: SyncArena,
+    decls: SyncArena,
+    /// Types of tuples' elements, which live as long as interned strings do,
+    /// so that types can be copied around as freely as their names. Neither
+    /// outlives the context: anything kept past it, such as diagnostics, must
+    /// hold an `ast_owned::Type` instead.
+    type_lists: SyncArena,
+    /// How many times the context was reset.
+    generation: u64,
+}
+
+/// Memory taken up by each arena of the context, in bytes.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub(crate) struct ArenaStats {
+    pub(crate) exprs: usize,
+    pub(crate) else_if_branches: usize,
+    pub(crate) match_arms: usize,
+    pub(crate) params: usize,
+    pub(crate) field_defs: usize,
+    pub(crate) field_inits: usize,
+    pub(crate) variant_defs: usize,
+    pub(crate) bindings: usize,
+    pub(crate) decls: usize,
+    pub(crate) type_lists: usize,
+}
+
+/// Arena that any thread can allocate in, one at a time.
+#[derive(Default)]
+struct SyncArena(Mutex<Bump>);
+
+impl SyncArena {
+    fn alloc<T>(&self, value: T) -> &T {
+        let allocated: *const T = self.bump().alloc(value);
+
+        unsafe {
+            // SAFETY: Allocations stay where they are until the arena is dropped or reset,
+            // which takes `&mut self`, so they outlive the lock.
+            &*allocated
+        }
+    }
+
+    fn alloc_slice_copy<T: Copy>(&self, values: &[T]) -> &[T] {
+        let allocated: *const [T] = self.bump().alloc_slice_copy(values);
+
+        unsafe {
+            // SAFETY: As for `alloc`.
+            &*allocated
+        }
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.bump().allocated_bytes()
+    }
+
+    fn reset(&mut self) {
+        self.0
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .reset();
+    }
+
+    fn bump(&self) -> MutexGuard<'_, Bump> {
+        // Allocating never leaves the arena half-updated, even if it panics.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
 }
 
 impl<'ctx> CompilerContext {
     pub(crate) fn new(source_code: String) -> CompilerContext {
+        CompilerContext::with_source_map(SourceMap::from(source_code), &HermeticHost::default())
+    }
+
+    /// Context of a program whose source code may be split across files.
+    pub(crate) fn with_source_map(source_map: SourceMap, host: &dyn Host) -> CompilerContext {
+        let mut string_interner = StringInterner::with_seed(host.random_u64());
+        let symbols = Symbols::intern(&mut string_interner);
+
         CompilerContext {
-            source_code,
-            string_interner: Default::default(),
+            source_map,
+            string_interner: RwLock::new(string_interner),
+            symbols,
             exprs: Default::default(),
             else_if_branches: Default::default(),
+            match_arms: Default::default(),
             params: Default::default(),
+            field_defs: Default::default(),
+            field_inits: Default::default(),
+            variant_defs: Default::default(),
+            bindings: Default::default(),
             decls: Default::default(),
+            type_lists: Default::default(),
+            generation: 0,
+        }
+    }
+
+    /// Replaces the source code, e.g., with an edited version of it, freeing
+    /// the syntax trees parsed from the old one, so that long-lived embedders
+    /// like the language server reuse the memory of a context rather than
+    /// allocating one anew for every parse. Interned strings and the types of
+    /// tuples are kept, as they're handed out for as long as the context
+    /// lives, and names mostly stay the same across edits anyway.
+    pub(crate) fn reset(&mut self, source_code: impl Into<SourceMap>) {
+        self.source_map = source_code.into();
+
+        for arena in [
+            &mut self.exprs,
+            &mut self.else_if_branches,
+            &mut self.match_arms,
+            &mut self.params,
+            &mut self.field_defs,
+            &mut self.field_inits,
+            &mut self.variant_defs,
+            &mut self.bindings,
+            &mut self.decls,
+        ] {
+            arena.reset();
+        }
+
+        self.generation += 1;
+    }
+
+    /// How many times the context was reset, which tells apart what was
+    /// computed from each of the source codes it had, e.g., spans.
+    // Only tests tell generations apart yet.
+    #[allow(dead_code)]
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Memory taken up by the AST so far, in bytes.
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.arena_stats().total()
+    }
+
+    /// Memory taken up by each kind of node of the AST so far, in bytes.
+    pub(crate) fn arena_stats(&self) -> ArenaStats {
+        ArenaStats {
+            exprs: self.exprs.allocated_bytes(),
+            else_if_branches: self.else_if_branches.allocated_bytes(),
+            match_arms: self.match_arms.allocated_bytes(),
+            params: self.params.allocated_bytes(),
+            field_defs: self.field_defs.allocated_bytes(),
+            field_inits: self.field_inits.allocated_bytes(),
+            variant_defs: self.variant_defs.allocated_bytes(),
+            bindings: self.bindings.allocated_bytes(),
+            decls: self.decls.allocated_bytes(),
+            type_lists: self.type_lists.allocated_bytes(),
         }
     }
 
     pub(crate) fn get_source_code(&'ctx self) -> &str {
-        &self.source_code
+        self.source_map.source_code()
+    }
+
+    pub(crate) fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// Line and column of the position in the file it's in, for showing it to
+    /// people.
+    // Diagnostics are rendered once the context is gone, so nothing in the
+    // compile path looks positions up here yet.
+    #[allow(dead_code)]
+    pub(crate) fn lookup_line_col(&self, pos: BytePos) -> (Line, Col) {
+        let (_, line, col) = self.source_map.lookup_line_col(pos);
+
+        (line, col)
     }
 
     pub(crate) fn get_or_intern_str(&'ctx self, string: &str) -> Symbol {
-        self.string_interner.borrow_mut().get_or_intern(string)
+        let mut string_interner = self
+            .string_interner
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        string_interner.get_or_intern(string)
+    }
+
+    /// How many strings were interned, and the memory they take up, in
+    /// bytes.
+    pub(crate) fn interner_size(&self) -> (usize, usize) {
+        let string_interner = self
+            .string_interner
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        (string_interner.len(), string_interner.allocated_bytes())
+    }
+
+    /// Symbols of the names that the compiler looks for, e.g., `main`.
+    pub(crate) fn symbols(&self) -> &Symbols {
+        &self.symbols
     }
 
     pub(crate) fn resolve_symbol(&'ctx self, symbol: Symbol) -> &'static str {
-        self.string_interner.borrow().resolve(symbol)
+        let string_interner = self
+            .string_interner
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        string_interner.resolve(symbol)
     }
 
     pub(crate) fn alloc_slice_of_decl<'a>(
@@ -63,10 +259,70 @@ impl<'ctx> CompilerContext {
         self.else_if_branches.alloc_slice_copy(else_if_branches)
     }
 
+    pub(crate) fn alloc_slice_of_match_arm<'a>(
+        &'ctx self,
+        match_arms: &'a [MatchArm<'ctx>],
+    ) -> &'ctx [MatchArm<'ctx>] {
+        self.match_arms.alloc_slice_copy(match_arms)
+    }
+
     pub(crate) fn alloc_slice_of_param<'a>(
         &'ctx self,
         params: &'a [Param],
     ) -> &'ctx [Param] {
         self.params.alloc_slice_copy(params)
     }
+
+    pub(crate) fn alloc_slice_of_field_def<'a>(
+        &'ctx self,
+        field_defs: &'a [FieldDef],
+    ) -> &'ctx [FieldDef] {
+        self.field_defs.alloc_slice_copy(field_defs)
+    }
+
+    pub(crate) fn alloc_slice_of_field_init<'a>(
+        &'ctx self,
+        field_inits: &'a [FieldInit<'ctx>],
+    ) -> &'ctx [FieldInit<'ctx>] {
+        self.field_inits.alloc_slice_copy(field_inits)
+    }
+
+    pub(crate) fn alloc_slice_of_variant_def<'a>(
+        &'ctx self,
+        variant_defs: &'a [VariantDef],
+    ) -> &'ctx [VariantDef] {
+        self.variant_defs.alloc_slice_copy(variant_defs)
+    }
+
+    pub(crate) fn alloc_slice_of_binding<'a>(
+        &'ctx self,
+        bindings: &'a [Binding],
+    ) -> &'ctx [Binding] {
+        self.bindings.alloc_slice_copy(bindings)
+    }
+
+    pub(crate) fn alloc_type_list(&self, types: &[Type]) -> &'static [Type] {
+        let allocated_types = self.type_lists.alloc_slice_copy(types);
+
+        unsafe {
+            // SAFETY: `type_lists` is only dropped or reset along with the context, and
+            // types borrowing from it are converted to owned ones before they escape it.
+            &*(allocated_types as *const [Type])
+        }
+    }
+}
+
+impl ArenaStats {
+    pub(crate) fn total(&self) -> usize {
+        self.exprs
+            + self.else_if_branches
+            + self.match_arms
+            + self.params
+            + self.field_defs
+            + self.field_inits
+            + self.variant_defs
+            + self.bindings
+            + self.decls
+            + self.type_lists
+    }
 }
@@ -0,0 +1,318 @@
+use std::fmt;
+
+use crate::scanner::{BytePos, Delim, Keyword, Scanner, Span, Token, TokenKind};
+
+/// Token of a file along with the text before it, i.e., the whitespace and
+/// the comments since the token before it. In order, the tokens make up all of
+/// the file's source code, so nothing of it is lost when it's formatted.
+#[derive(Clone, Copy)]
+pub(crate) struct LosslessToken<'src> {
+    pub(crate) token: Token,
+    pub(crate) leading_trivia: &'src str,
+    pub(crate) text: &'src str,
+}
+
+/// Tokens of the source code, along with the text after the last one.
+pub(crate) fn lossless_tokens(source_code: &str) -> (Vec<LosslessToken<'_>>, &str) {
+    let mut tokens = vec![];
+    let mut trivia_start = 0;
+
+    for token in Scanner::for_text(source_code, BytePos(0)) {
+        tokens.push(LosslessToken {
+            token,
+            leading_trivia: &source_code[trivia_start..token.span.start.0],
+            text: &source_code[token.span.start.0..token.span.end.0],
+        });
+        trivia_start = token.span.end.0;
+    }
+
+    (tokens, &source_code[trivia_start..])
+}
+
+/// Kind of a node of the concrete syntax tree.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum NodeKind {
+    /// The whole file, the only root.
+    File,
+    /// Top-level declaration, along with its attributes, or top-level
+    /// statement of a script.
+    Item,
+    /// Delimiters along with what's between them, e.g., a function's body.
+    Delimited(Delim),
+    /// Code that doesn't nest, i.e., a closing delimiter without an opening
+    /// one, or an opening delimiter along with the rest of the file, as it's
+    /// never closed.
+    Error,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum SyntaxTokenKind {
+    Token(TokenKind),
+    Whitespace,
+    /// Comment from `//` to the end of the line, without the newline.
+    Comment,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct SyntaxToken<'src> {
+    pub(crate) kind: SyntaxTokenKind,
+    pub(crate) text: &'src str,
+    pub(crate) span: Span,
+}
+
+impl SyntaxToken<'_> {
+    pub(crate) fn is_trivia(&self) -> bool {
+        matches!(
+            self.kind,
+            SyntaxTokenKind::Whitespace | SyntaxTokenKind::Comment
+        )
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum SyntaxElement<'src> {
+    Node(SyntaxNode<'src>),
+    Token(SyntaxToken<'src>),
+}
+
+/// Node of the concrete syntax tree, whose tokens are all of the source code
+/// it covers, whitespace and comments included, so that writing them back in
+/// order gives that source code exactly.
+///
+/// The tree only tells how the tokens nest and where the top-level items
+/// start, which is what the formatter and the editor features need to edit
+/// code without losing any of it. What the code means is left to the AST.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct SyntaxNode<'src> {
+    pub(crate) kind: NodeKind,
+    pub(crate) children: Vec<SyntaxElement<'src>>,
+}
+
+impl<'src> SyntaxNode<'src> {
+    /// Tokens of the node in source order, at any depth.
+    pub(crate) fn tokens(&self) -> Vec<SyntaxToken<'src>> {
+        let mut tokens = vec![];
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens(&self, tokens: &mut Vec<SyntaxToken<'src>>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(*token),
+            }
+        }
+    }
+
+    /// Span of the node's tokens, which is empty if it has none.
+    pub(crate) fn span(&self) -> Option<Span> {
+        let tokens = self.tokens();
+        let first = tokens.first()?;
+        let last = tokens.last()?;
+
+        Some(first.span.to(last.span))
+    }
+}
+
+impl fmt::Display for SyntaxNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => write!(f, "{}", node)?,
+                SyntaxElement::Token(token) => f.write_str(token.text)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds the concrete syntax tree of the source code, which never fails:
+/// code that doesn't nest ends up in error nodes instead.
+pub(crate) fn parse_file(source_code: &str) -> SyntaxNode<'_> {
+    let (tokens, trailing_trivia) = lossless_tokens(source_code);
+    let mut builder = TreeBuilder {
+        tokens: &tokens,
+        // The file is always at the bottom.
+        stack: vec![SyntaxNode {
+            kind: NodeKind::File,
+            children: vec![],
+        }],
+        open_delims: 0,
+    };
+
+    for idx in 0..tokens.len() {
+        builder.add_token(idx);
+    }
+
+    // Delimiters left open take the rest of the file with them.
+    while builder.stack.len() > 1 {
+        let kind = builder.top().kind;
+
+        if let NodeKind::Delimited(_) = kind {
+            builder.top().kind = NodeKind::Error;
+        }
+
+        builder.finish_node();
+    }
+
+    let end = BytePos(source_code.len() - trailing_trivia.len());
+    builder.add_trivia(trailing_trivia, end);
+
+    builder.stack.pop().unwrap()
+}
+
+struct TreeBuilder<'a, 'src> {
+    tokens: &'a [LosslessToken<'src>],
+    /// Nodes that are being built, innermost last.
+    stack: Vec<SyntaxNode<'src>>,
+    /// Number of delimited nodes on the stack.
+    open_delims: usize,
+}
+
+impl<'src> TreeBuilder<'_, 'src> {
+    fn add_token(&mut self, idx: usize) {
+        let token = self.tokens[idx];
+
+        if self.open_delims == 0 {
+            if self.top().kind == NodeKind::Item && self.starts_item(idx) && !self.is_item_prefix()
+            {
+                self.finish_node();
+            }
+
+            // The text before an item is outside of it, as it's usually what
+            // separates it from the item before.
+            let trivia_start = BytePos(token.token.span.start.0 - token.leading_trivia.len());
+            self.add_trivia(token.leading_trivia, trivia_start);
+
+            if self.top().kind != NodeKind::Item {
+                self.start_node(NodeKind::Item);
+            }
+        } else {
+            let trivia_start = BytePos(token.token.span.start.0 - token.leading_trivia.len());
+            self.add_trivia(token.leading_trivia, trivia_start);
+        }
+
+        let syntax_token = SyntaxToken {
+            kind: SyntaxTokenKind::Token(token.token.kind),
+            text: token.text,
+            span: token.token.span,
+        };
+
+        match token.token.kind {
+            TokenKind::Open(delim) => {
+                self.start_node(NodeKind::Delimited(delim));
+                self.open_delims += 1;
+                self.add_element(SyntaxElement::Token(syntax_token));
+            }
+            TokenKind::Closed(delim) if self.top().kind == NodeKind::Delimited(delim) => {
+                self.add_element(SyntaxElement::Token(syntax_token));
+                self.open_delims -= 1;
+                self.finish_node();
+            }
+            TokenKind::Closed(_) => {
+                self.add_element(SyntaxElement::Node(SyntaxNode {
+                    kind: NodeKind::Error,
+                    children: vec![SyntaxElement::Token(syntax_token)],
+                }));
+            }
+            _ => self.add_element(SyntaxElement::Token(syntax_token)),
+        }
+
+        // Statements of scripts end at their semicolons.
+        if self.open_delims == 0 && token.token.kind == TokenKind::Semi {
+            self.finish_node();
+        }
+    }
+
+    /// Whether the token at the index starts a declaration, i.e., it's the
+    /// start of an attribute, an `extern`, or a name at the start of its line
+    /// that's followed by `::`, as in `main :: () {}` rather than
+    /// `Shape::Square`.
+    fn starts_item(&self, idx: usize) -> bool {
+        let token = self.tokens[idx];
+
+        match token.token.kind {
+            TokenKind::Hash | TokenKind::Keyword(Keyword::Extern) => true,
+            TokenKind::Identifier => {
+                (idx == 0 || token.leading_trivia.contains('\n'))
+                    && self
+                        .tokens
+                        .get(idx + 1)
+                        .is_some_and(|next| next.token.kind == TokenKind::ColonColon)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the item being built only has what comes before a declaration,
+    /// i.e., attributes and `extern`, so that the declaration is part of it.
+    fn is_item_prefix(&mut self) -> bool {
+        self.top().children.iter().all(|child| match child {
+            SyntaxElement::Token(token) => {
+                token.is_trivia()
+                    || matches!(
+                        token.kind,
+                        SyntaxTokenKind::Token(
+                            TokenKind::Hash | TokenKind::Keyword(Keyword::Extern)
+                        )
+                    )
+            }
+            SyntaxElement::Node(node) => node.kind == NodeKind::Delimited(Delim::Bracket),
+        })
+    }
+
+    /// Adds the whitespace and the comments in the text, which starts at the
+    /// position, as tokens of their own.
+    fn add_trivia(&mut self, trivia: &'src str, start: BytePos) {
+        let mut rest = trivia;
+        let mut pos = start.0;
+
+        while !rest.is_empty() {
+            let (kind, len) = if rest.starts_with("//") {
+                (
+                    SyntaxTokenKind::Comment,
+                    rest.find('\n').unwrap_or(rest.len()),
+                )
+            } else {
+                (
+                    SyntaxTokenKind::Whitespace,
+                    rest.find("//").unwrap_or(rest.len()),
+                )
+            };
+
+            self.add_element(SyntaxElement::Token(SyntaxToken {
+                kind,
+                text: &rest[..len],
+                span: Span {
+                    start: BytePos(pos),
+                    end: BytePos(pos + len),
+                },
+            }));
+
+            rest = &rest[len..];
+            pos += len;
+        }
+    }
+
+    fn top(&mut self) -> &mut SyntaxNode<'src> {
+        self.stack.last_mut().unwrap()
+    }
+
+    fn start_node(&mut self, kind: NodeKind) {
+        self.stack.push(SyntaxNode {
+            kind,
+            children: vec![],
+        });
+    }
+
+    fn finish_node(&mut self) {
+        let node = self.stack.pop().unwrap();
+        self.add_element(SyntaxElement::Node(node));
+    }
+
+    fn add_element(&mut self, element: SyntaxElement<'src>) {
+        self.top().children.push(element);
+    }
+}
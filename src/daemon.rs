@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::diagnostics::Diagnostic;
+use crate::driver::{self, Options};
+use crate::json::Json;
+use crate::line_index::LineIndex;
+use crate::scanner::Span;
+
+/// JSON-RPC error codes, as defined by the JSON-RPC 2.0 specification.
+pub(crate) const PARSE_ERROR: i64 = -32700;
+pub(crate) const INVALID_REQUEST: i64 = -32600;
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+pub(crate) const INVALID_PARAMS: i64 = -32602;
+
+/// Long-lived compiler process that serves requests over a Unix socket, so
+/// that tools invoking the compiler repeatedly don't pay for its startup every
+/// time, nor for compiling files again that haven't changed since.
+///
+/// Requests and responses are JSON-RPC 2.0 messages, one per line. The
+/// supported methods are:
+///
+/// - `check`, which runs every analysis on the source code and responds with
+///   its errors and warnings;
+/// - `compile`, which also responds with the generated assembly, if any;
+/// - `shutdown`, which stops the daemon once it has responded.
+///
+/// Both `check` and `compile` take the source code either as a `source`
+/// string, or as the `path` to a file to read it from. Passing `script: true`
+/// compiles it in script mode.
+///
+/// Each connection is served on a thread of its own, so that a client that's
+/// slow to send its requests, or to read the responses, doesn't hold up the
+/// others.
+pub(crate) struct Daemon {
+    /// Result of the last request for each file, reused for as long as the
+    /// file's source code doesn't change. Results are kept rather than
+    /// compiler contexts, as a context is made for the one source code it
+    /// holds, and the result of that source code is all there is left to
+    /// compute with it. It's only locked while looking up or storing a result,
+    /// so that requests are compiled in parallel.
+    cache: Mutex<HashMap<CacheKey, CacheEntry>>,
+    is_shutting_down: AtomicBool,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct CacheKey {
+    method: String,
+    path: String,
+    script_mode: bool,
+}
+
+struct CacheEntry {
+    source_code: String,
+    result: Json,
+}
+
+pub(crate) struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    pub(crate) fn new(code: i64, message: impl Into<String>) -> RpcError {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl Daemon {
+    pub(crate) fn new() -> Daemon {
+        Daemon {
+            cache: Default::default(),
+            is_shutting_down: AtomicBool::new(false),
+        }
+    }
+
+    /// Serves connections on the socket at `socket_path` until a `shutdown`
+    /// request arrives. A connection that fails is only logged, as it's no
+    /// reason to stop serving the others.
+    ///
+    /// Fails if another daemon is serving on the socket already.
+    pub(crate) fn serve(self, socket_path: &Path) -> io::Result<()> {
+        // A socket left behind by a daemon that's gone would make binding fail,
+        // so it's removed, unlike anything else that's at the path.
+        if let Ok(metadata) = fs::symlink_metadata(socket_path) {
+            if UnixStream::connect(socket_path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("a daemon is serving on `{}` already", socket_path.display()),
+                ));
+            }
+
+            if metadata.file_type().is_socket() {
+                fs::remove_file(socket_path)?;
+            }
+        }
+
+        let listener = UnixListener::bind(socket_path)?;
+        let daemon = Arc::new(self);
+
+        for stream in listener.incoming() {
+            if daemon.is_shutting_down() {
+                break;
+            }
+
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    eprintln!("error: couldn't accept a connection: {}", err);
+                    continue;
+                }
+            };
+
+            let daemon = Arc::clone(&daemon);
+            let socket_path = socket_path.to_owned();
+
+            thread::spawn(move || {
+                if let Err(err) = daemon.serve_connection(stream, socket_path) {
+                    eprintln!("error: connection failed: {}", err);
+                }
+            });
+        }
+
+        fs::remove_file(socket_path)
+    }
+
+    fn serve_connection(&self, stream: UnixStream, socket_path: PathBuf) -> io::Result<()> {
+        let mut writer = stream.try_clone()?;
+
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if let Some(response) = self.handle_message(&line) {
+                writeln!(writer, "{}", response)?;
+            }
+
+            if self.is_shutting_down() {
+                // The listener only finds out once it accepts another
+                // connection, which this one is.
+                UnixStream::connect(socket_path)?;
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_shutting_down(&self) -> bool {
+        self.is_shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// Handles a single JSON-RPC message, returning the response to it. There's
+    /// no response to notifications, i.e., requests without an `id`.
+    pub(crate) fn handle_message(&self, message: &str) -> Option<Json> {
+        let request = match Json::parse(message) {
+            Ok(request) => request,
+            Err(error) => {
+                return Some(error_response(
+                    Json::Null,
+                    RpcError::new(PARSE_ERROR, error.to_string()),
+                ));
+            }
+        };
+
+        let id = request.get("id").cloned();
+
+        let result = match request.get("method").and_then(Json::as_str) {
+            Some(method) => self.handle_request(method, request.get("params")),
+            None => Err(RpcError::new(INVALID_REQUEST, "missing `method`")),
+        };
+
+        let id = id?;
+
+        Some(match result {
+            Ok(result) => Json::object([
+                ("jsonrpc", Json::from("2.0")),
+                ("id", id),
+                ("result", result),
+            ]),
+            Err(error) => error_response(id, error),
+        })
+    }
+
+    fn handle_request(&self, method: &str, params: Option<&Json>) -> Result<Json, RpcError> {
+        match method {
+            "check" | "compile" => self.handle_compile_request(method, params),
+            "shutdown" => {
+                self.is_shutting_down.store(true, Ordering::SeqCst);
+
+                Ok(Json::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method `{}`", method),
+            )),
+        }
+    }
+
+    fn handle_compile_request(
+        &self,
+        method: &str,
+        params: Option<&Json>,
+    ) -> Result<Json, RpcError> {
+        let params = params.unwrap_or(&Json::Null);
+        let path = params.get("path").and_then(Json::as_str);
+
+        let options = Options {
+            script_mode: params
+                .get("script")
+                .and_then(Json::as_bool)
+                .unwrap_or(false),
+            ..Default::default()
+        };
+
+        let source_code = match (params.get("source").and_then(Json::as_str), path) {
+            (Some(source_code), _) => source_code.to_owned(),
+            (None, Some(path)) => fs::read_to_string(path).map_err(|error| {
+                RpcError::new(
+                    INVALID_PARAMS,
+                    format!("couldn't read `{}`: {}", path, error),
+                )
+            })?,
+            (None, None) => {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    "expected either `source` or `path`",
+                ))
+            }
+        };
+
+        // Only requests naming a file are cached, as there's nothing else to tell
+        // apart the programs being worked on.
+        let cache_key = path.map(|path| CacheKey {
+            method: method.to_owned(),
+            path: path.to_owned(),
+            script_mode: options.script_mode,
+        });
+
+        if let Some(cache_key) = &cache_key {
+            let cache = self.cache.lock().unwrap();
+
+            if let Some(entry) = cache.get(cache_key) {
+                if entry.source_code == source_code {
+                    return Ok(entry.result.clone());
+                }
+            }
+        }
+
+        let result = if method == "check" {
+            Json::object(diagnostic_members(
+                &driver::check(&source_code, &options),
+                &source_code,
+            ))
+        } else {
+            let (assembly, diagnostic) = match driver::compile(&source_code, &options) {
+                Ok(compiled_program) => (
+                    Some(compiled_program.code),
+                    Diagnostic {
+                        errors: vec![],
+                        warnings: compiled_program.warnings,
+                    },
+                ),
+                Err(diagnostic) => (None, diagnostic),
+            };
+
+            let mut members = vec![("assembly", Json::from(assembly))];
+            members.extend(diagnostic_members(&diagnostic, &source_code));
+
+            Json::object(members)
+        };
+
+        if let Some(cache_key) = cache_key {
+            self.cache.lock().unwrap().insert(
+                cache_key,
+                CacheEntry {
+                    source_code,
+                    result: result.clone(),
+                },
+            );
+        }
+
+        Ok(result)
+    }
+}
+
+pub(crate) fn error_response(id: Json, error: RpcError) -> Json {
+    Json::object([
+        ("jsonrpc", Json::from("2.0")),
+        ("id", id),
+        (
+            "error",
+            Json::object([
+                ("code", Json::from(error.code)),
+                ("message", Json::from(error.message)),
+            ]),
+        ),
+    ])
+}
+
+fn diagnostic_members(diagnostic: &Diagnostic, source_code: &str) -> [(&'static str, Json); 2] {
+    let line_index = LineIndex::new(source_code);
+    let to_json = |message: String, span| message_to_json(message, span, &line_index, source_code);
+
+    [
+        (
+            "errors",
+            Json::Array(
+                diagnostic
+                    .errors
+                    .iter()
+                    .map(|error| to_json(error.to_string(), error.span()))
+                    .collect(),
+            ),
+        ),
+        (
+            "warnings",
+            Json::Array(
+                diagnostic
+                    .warnings
+                    .iter()
+                    .map(|warning| to_json(warning.to_string(), warning.span()))
+                    .collect(),
+            ),
+        ),
+    ]
+}
+
+/// Message along with its span, whose start is given as a line and a column
+/// too, as editors show positions that way.
+fn message_to_json(message: String, span: Span, line_index: &LineIndex, source_code: &str) -> Json {
+    let (line, col) = line_index.line_col(source_code, span.start);
+
+    Json::object([
+        ("message", Json::from(message)),
+        (
+            "span",
+            Json::object([
+                ("start", Json::from(span.start.0)),
+                ("end", Json::from(span.end.0)),
+                ("line", Json::from(line.0)),
+                ("col", Json::from(col.0)),
+            ]),
+        ),
+    ])
+}
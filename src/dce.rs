@@ -0,0 +1,313 @@
+use std::cell::RefCell;
+
+use crate::ast::{
+    ArrayExpr, BinaryExpr, BindDef, BreakExpr, CastExpr, CompoundExpr, Decl, ElseIfBranch, Expr,
+    FieldExpr, FieldInit, FnCallExpr, ForExpr, ForIteration, Function, IfExpr, IndexAssignExpr,
+    IndexExpr, MatchArm, MatchExpr, ParenExpr, Program, StructLitExpr, TupleBindDef, TupleExpr,
+    TupleFieldExpr, VariantExpr,
+};
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::Remark;
+use crate::scanner::Span;
+
+/// Removes code that control never reaches, i.e., whatever follows a `break`
+/// or a `continue` in a block, before code is generated for the program.
+///
+/// A block that ends in a value keeps it, even if it's unreachable, as the type
+/// checker gave the block that value's type, and backends generate code for
+/// the block accordingly.
+pub(crate) struct DeadCodeEliminator<'ctx> {
+    ctx: &'ctx CompilerContext,
+    /// What was eliminated and what wasn't, in the order it was found.
+    remarks: RefCell<Vec<Remark>>,
+}
+
+impl<'ctx> DeadCodeEliminator<'ctx> {
+    pub(crate) fn new(ctx: &'ctx CompilerContext) -> DeadCodeEliminator<'ctx> {
+        DeadCodeEliminator {
+            ctx,
+            remarks: Default::default(),
+        }
+    }
+
+    pub(crate) fn into_remarks(self) -> Vec<Remark> {
+        self.remarks.into_inner()
+    }
+
+    pub(crate) fn eliminate_program(&self, program: Program<'ctx>) -> Program<'ctx> {
+        let decls: Vec<_> = program
+            .decls
+            .iter()
+            .map(|decl| Decl {
+                value: self.eliminate_expr_ref(decl.value),
+                ..*decl
+            })
+            .collect();
+
+        Program {
+            decls: self.ctx.alloc_slice_of_decl(&decls),
+        }
+    }
+
+    fn eliminate_expr(&self, expr: Expr<'ctx>) -> Expr<'ctx> {
+        match expr {
+            Expr::Const(_)
+            | Expr::BindRef(_)
+            | Expr::Continue(_)
+            | Expr::Extern(_)
+            | Expr::Struct(_)
+            | Expr::Enum(_) => expr,
+            Expr::Break(break_expr) => Expr::Break(BreakExpr {
+                value: break_expr.value.map(|value| self.eliminate_expr_ref(value)),
+                ..break_expr
+            }),
+            Expr::BindDef(bind_def) => Expr::BindDef(BindDef {
+                value: self.eliminate_expr_ref(bind_def.value),
+                ..bind_def
+            }),
+            Expr::Function(function) => Expr::Function(Function {
+                body: self.eliminate_compound_expr(function.body),
+                ..function
+            }),
+            Expr::StructLit(struct_lit_expr) => {
+                let fields: Vec<_> = struct_lit_expr
+                    .fields
+                    .iter()
+                    .map(|field| FieldInit {
+                        value: self.eliminate_expr_ref(field.value),
+                        ..*field
+                    })
+                    .collect();
+
+                Expr::StructLit(StructLitExpr {
+                    fields: self.ctx.alloc_slice_of_field_init(&fields),
+                    ..struct_lit_expr
+                })
+            }
+            Expr::Field(field_expr) => Expr::Field(FieldExpr {
+                base: self.eliminate_expr_ref(field_expr.base),
+                ..field_expr
+            }),
+            Expr::Variant(variant_expr) => Expr::Variant(VariantExpr {
+                payload: variant_expr
+                    .payload
+                    .map(|payload| self.eliminate_expr_ref(payload)),
+                ..variant_expr
+            }),
+            Expr::Tuple(tuple_expr) => Expr::Tuple(TupleExpr {
+                elements: self.eliminate_exprs(tuple_expr.elements),
+                ..tuple_expr
+            }),
+            Expr::TupleField(tuple_field_expr) => Expr::TupleField(TupleFieldExpr {
+                base: self.eliminate_expr_ref(tuple_field_expr.base),
+                ..tuple_field_expr
+            }),
+            Expr::TupleBindDef(tuple_bind_def) => Expr::TupleBindDef(TupleBindDef {
+                value: self.eliminate_expr_ref(tuple_bind_def.value),
+                ..tuple_bind_def
+            }),
+            Expr::Array(array_expr) => Expr::Array(ArrayExpr {
+                elements: self.eliminate_exprs(array_expr.elements),
+                ..array_expr
+            }),
+            Expr::Index(index_expr) => Expr::Index(self.eliminate_index_expr(index_expr)),
+            Expr::IndexAssign(index_assign_expr) => Expr::IndexAssign(IndexAssignExpr {
+                target: self.eliminate_index_expr(index_assign_expr.target),
+                value: self.eliminate_expr_ref(index_assign_expr.value),
+                ..index_assign_expr
+            }),
+            Expr::If(if_expr) => Expr::If(self.eliminate_if_expr(if_expr)),
+            Expr::Match(match_expr) => Expr::Match(self.eliminate_match_expr(match_expr)),
+            Expr::For(for_expr) => Expr::For(self.eliminate_for_expr(for_expr)),
+            Expr::Compound(compound_expr) => {
+                Expr::Compound(self.eliminate_compound_expr(compound_expr))
+            }
+            Expr::Semi(expr) => Expr::Semi(self.eliminate_expr_ref(expr)),
+            Expr::FnCall(fn_call_expr) => Expr::FnCall(FnCallExpr {
+                args: self.eliminate_exprs(fn_call_expr.args),
+                ..fn_call_expr
+            }),
+            Expr::Binary(binary_expr) => {
+                let (operand, operations) = binary_expr.chain();
+                let mut lhs = self.eliminate_expr(*operand);
+
+                for binary_expr in operations {
+                    lhs = Expr::Binary(BinaryExpr {
+                        lhs: self.ctx.alloc_expr(lhs),
+                        rhs: self.eliminate_expr_ref(binary_expr.rhs),
+                        ..binary_expr
+                    });
+                }
+
+                lhs
+            }
+            Expr::Cast(cast_expr) => Expr::Cast(CastExpr {
+                expr: self.eliminate_expr_ref(cast_expr.expr),
+                ..cast_expr
+            }),
+            Expr::Paren(paren_expr) => Expr::Paren(ParenExpr {
+                expr: self.eliminate_expr_ref(paren_expr.expr),
+                ..paren_expr
+            }),
+        }
+    }
+
+    fn eliminate_expr_ref(&self, expr: &Expr<'ctx>) -> &'ctx Expr<'ctx> {
+        self.ctx.alloc_expr(self.eliminate_expr(*expr))
+    }
+
+    fn eliminate_exprs(&self, exprs: &[Expr<'ctx>]) -> &'ctx [Expr<'ctx>] {
+        let exprs: Vec<_> = exprs
+            .iter()
+            .map(|&expr| self.eliminate_expr(expr))
+            .collect();
+
+        self.ctx.alloc_slice_of_expr(&exprs)
+    }
+
+    fn eliminate_index_expr(&self, index_expr: IndexExpr<'ctx>) -> IndexExpr<'ctx> {
+        IndexExpr {
+            base: self.eliminate_expr_ref(index_expr.base),
+            index: self.eliminate_expr_ref(index_expr.index),
+            ..index_expr
+        }
+    }
+
+    fn eliminate_if_expr(&self, if_expr: IfExpr<'ctx>) -> IfExpr<'ctx> {
+        let else_if_branches: Vec<_> = if_expr
+            .else_if_branches
+            .iter()
+            .map(|branch| ElseIfBranch {
+                cond_expr: self.eliminate_expr_ref(branch.cond_expr),
+                true_branch: self.eliminate_compound_expr(branch.true_branch),
+            })
+            .collect();
+
+        IfExpr {
+            cond_expr: self.eliminate_expr_ref(if_expr.cond_expr),
+            true_branch: self.eliminate_compound_expr(if_expr.true_branch),
+            else_if_branches: self.ctx.alloc_slice_of_else_if_branch(&else_if_branches),
+            final_branch: if_expr
+                .final_branch
+                .map(|branch| self.eliminate_compound_expr(branch)),
+            ..if_expr
+        }
+    }
+
+    fn eliminate_match_expr(&self, match_expr: MatchExpr<'ctx>) -> MatchExpr<'ctx> {
+        let arms: Vec<_> = match_expr
+            .arms
+            .iter()
+            .map(|arm| MatchArm {
+                expr: self.eliminate_expr_ref(arm.expr),
+                ..*arm
+            })
+            .collect();
+
+        MatchExpr {
+            scrutinee: self.eliminate_expr_ref(match_expr.scrutinee),
+            arms: self.ctx.alloc_slice_of_match_arm(&arms),
+            ..match_expr
+        }
+    }
+
+    fn eliminate_for_expr(&self, for_expr: ForExpr<'ctx>) -> ForExpr<'ctx> {
+        let iteration = for_expr.iteration.map(|iteration| match iteration {
+            ForIteration::Conditional { cond_expr } => ForIteration::Conditional {
+                cond_expr: self.eliminate_expr_ref(cond_expr),
+            },
+            ForIteration::Iterative {
+                identifier,
+                identifier_span,
+                start_expr,
+                end_expr,
+                range_kind,
+                step_expr,
+                is_reversed,
+            } => ForIteration::Iterative {
+                identifier,
+                identifier_span,
+                start_expr: self.eliminate_expr_ref(start_expr),
+                end_expr: self.eliminate_expr_ref(end_expr),
+                range_kind,
+                step_expr: step_expr.map(|step_expr| self.eliminate_expr_ref(step_expr)),
+                is_reversed,
+            },
+            ForIteration::InCollection {
+                identifier,
+                identifier_span,
+                collection_expr,
+            } => ForIteration::InCollection {
+                identifier,
+                identifier_span,
+                collection_expr: self.eliminate_expr_ref(collection_expr),
+            },
+        });
+
+        ForExpr {
+            iteration,
+            body: self.eliminate_compound_expr(for_expr.body),
+            else_branch: for_expr
+                .else_branch
+                .map(|branch| self.eliminate_compound_expr(branch)),
+            ..for_expr
+        }
+    }
+
+    fn eliminate_compound_expr(&self, compound_expr: CompoundExpr<'ctx>) -> CompoundExpr<'ctx> {
+        let reachable_len = reachable_len(compound_expr);
+        let mut exprs = compound_expr.exprs[..reachable_len].to_vec();
+        let mut unreachable_exprs = &compound_expr.exprs[reachable_len..];
+        let kept_value = unreachable_exprs
+            .last()
+            .filter(|expr| !matches!(expr, Expr::Semi(_)))
+            .copied();
+
+        if kept_value.is_some() {
+            unreachable_exprs = &unreachable_exprs[..unreachable_exprs.len() - 1];
+        }
+
+        if let (Some(first), Some(last)) = (unreachable_exprs.first(), unreachable_exprs.last()) {
+            self.remarks
+                .borrow_mut()
+                .push(Remark::RemovedUnreachableCode {
+                    count: unreachable_exprs.len(),
+                    span: Span {
+                        start: first.span().start,
+                        end: last.span().end,
+                    },
+                });
+        }
+
+        if let Some(value) = kept_value {
+            exprs.push(value);
+            self.remarks
+                .borrow_mut()
+                .push(Remark::KeptUnreachableValue { span: value.span() });
+        }
+
+        CompoundExpr {
+            exprs: self.eliminate_exprs(&exprs),
+            ..compound_expr
+        }
+    }
+}
+
+/// How many of the block's expressions control can reach, i.e., up to and
+/// including the first one that diverges.
+pub(crate) fn reachable_len(compound_expr: CompoundExpr) -> usize {
+    match compound_expr.exprs.iter().position(diverges) {
+        Some(idx) => idx + 1,
+        None => compound_expr.exprs.len(),
+    }
+}
+
+/// Whether `expr` unconditionally transfers control elsewhere, making whatever
+/// follows it unreachable.
+pub(crate) fn diverges(expr: &Expr) -> bool {
+    match expr {
+        Expr::Break(_) | Expr::Continue(_) => true,
+        Expr::Semi(expr) | Expr::Paren(ParenExpr { expr, .. }) => diverges(expr),
+        _ => false,
+    }
+}
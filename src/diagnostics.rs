@@ -0,0 +1,1442 @@
+use std::fmt;
+
+use crate::ast::RangeKind;
+use crate::ast_owned::Type;
+use crate::profile::IntWidth;
+use crate::scanner::{BytePos, Delim, Keyword, Span, TokenKind};
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum CompileError {
+    UnclosedDelimiter {
+        delim: Delim,
+        open_span: Span,
+        eof_span: Span,
+    },
+    UndefinedName {
+        name: String,
+        span: Span,
+        /// Name that's spelled almost the same, which is likely what was meant.
+        suggestion: Option<String>,
+    },
+    DiscardUsedAsValue {
+        span: Span,
+    },
+    MismatchedTypes {
+        expected: Type,
+        found: Type,
+        span: Span,
+    },
+    MismatchedCallArguments {
+        expected: Vec<Type>,
+        found: Vec<Type>,
+        span: Span,
+    },
+    CallToNonFunction {
+        ty: Type,
+        span: Span,
+    },
+    ScriptStatementsWithMain {
+        main_span: Span,
+        stmt_span: Span,
+    },
+    SourceTooLarge {
+        size: usize,
+        limit: usize,
+    },
+    AstTooLarge {
+        limit: usize,
+        span: Span,
+    },
+    NestingTooDeep {
+        limit: usize,
+        span: Span,
+    },
+    IntegerConstantOutOfRange {
+        width: IntWidth,
+        span: Span,
+    },
+    /// The integer profile asks for semantics that the backend doesn't have.
+    IntProfileUnsupported {
+        backend: &'static str,
+    },
+    /// Host functions were declared, but only the VM can call them.
+    HostFunctionsUnsupported {
+        backend: &'static str,
+    },
+    /// A `break` or a `continue` that isn't in the body of a loop, which
+    /// includes being in a loop's else-branch, or in a function declared in a
+    /// loop's body.
+    BreakOutsideLoop {
+        keyword: Keyword,
+        span: Span,
+    },
+    /// The parser expected one of some kinds of tokens, in the order it
+    /// checked for them, but found another one, or the end of the code.
+    ExpectedButFound {
+        expected: Vec<TokenKind>,
+        found: Option<TokenKind>,
+        span: Span,
+    },
+    /// The arms of a match-expr don't cover every integer, which are left as
+    /// the smallest set of inclusive ranges, in order.
+    NonExhaustiveMatch {
+        uncovered: Vec<(i128, i128)>,
+        width: IntWidth,
+        span: Span,
+    },
+    /// A field access, or a field in a struct literal, names a field that the
+    /// type doesn't have, which includes every field of types that aren't
+    /// structs.
+    NoSuchField {
+        ty: Type,
+        field: String,
+        span: Span,
+    },
+    /// A struct literal leaves out fields, in the order they're declared.
+    MissingFields {
+        ty: Type,
+        fields: Vec<String>,
+        span: Span,
+    },
+    /// A struct declares a field twice, or a struct literal initializes it
+    /// twice.
+    DuplicateField {
+        field: String,
+        span: Span,
+    },
+    /// A struct or an enum contains itself, even if through other types, so
+    /// its values would be infinitely large.
+    RecursiveType {
+        name: String,
+        span: Span,
+    },
+    /// A struct literal names a declaration that isn't a struct.
+    NotAStruct {
+        name: String,
+        span: Span,
+    },
+    /// Structs were declared, but only the interpreter can run them so far.
+    StructsUnsupported {
+        backend: &'static str,
+    },
+    /// A variant's value, or a pattern, names a variant that the enum doesn't
+    /// have.
+    NoSuchVariant {
+        ty: Type,
+        variant: String,
+        span: Span,
+    },
+    /// An enum declares a variant twice.
+    DuplicateVariant {
+        variant: String,
+        span: Span,
+    },
+    /// A variant's value, or a pattern, gives the variant a value when it
+    /// holds none, or doesn't when it holds one, whose type is `expected`.
+    MismatchedVariantPayload {
+        variant: String,
+        expected: Option<Type>,
+        span: Span,
+    },
+    /// A variant's value, or a pattern, names a declaration that isn't an
+    /// enum.
+    NotAnEnum {
+        name: String,
+        span: Span,
+    },
+    /// The arms of a match-expr on an enum don't cover all of its variants,
+    /// which are left in the order they're declared.
+    NonExhaustiveVariants {
+        uncovered: Vec<String>,
+        span: Span,
+    },
+    /// Enums were declared, but only the interpreter can run them so far.
+    EnumsUnsupported {
+        backend: &'static str,
+    },
+    /// A type names a declaration that's neither a struct nor an enum.
+    NotAType {
+        name: String,
+        span: Span,
+    },
+    /// Name that's spelled almost like a keyword, where the code only parses
+    /// with the keyword, which the parser takes it for to go on.
+    MisspelledKeyword {
+        found: String,
+        keyword: Keyword,
+        span: Span,
+    },
+    /// A value is destructured into as many bindings as `bindings`, but it's
+    /// not a tuple of as many elements.
+    MismatchedTupleBindings {
+        bindings: usize,
+        found: Type,
+        span: Span,
+    },
+    /// Tuples were used, but only the interpreter can run them so far.
+    TuplesUnsupported {
+        backend: &'static str,
+    },
+    /// An array is indexed by a constant that's past its end.
+    IndexOutOfBounds {
+        index: i32,
+        len: usize,
+        span: Span,
+    },
+    /// A value that's not an array is indexed.
+    CannotIndex {
+        ty: Type,
+        span: Span,
+    },
+    /// An element is assigned to, but it's not of an array bound to a name.
+    InvalidAssignTarget {
+        span: Span,
+    },
+    /// Arrays were used, but only the interpreter can run them so far.
+    ArraysUnsupported {
+        backend: &'static str,
+    },
+    /// A loop iterates over the elements of a value that's not an array.
+    CannotIterate {
+        ty: Type,
+        span: Span,
+    },
+    /// An integer constant is suffixed with something other than an integer
+    /// type, as in `42f32`.
+    InvalidIntegerSuffix {
+        suffix: String,
+        span: Span,
+    },
+    /// An integer constant is larger than its type's largest value.
+    IntegerConstantTooLarge {
+        ty: Type,
+        span: Span,
+    },
+    /// Integers of types other than `i32` were used, but only the interpreter
+    /// can run them so far.
+    IntTypesUnsupported {
+        backend: &'static str,
+    },
+    /// A value is cast to a type, but either isn't an integer.
+    InvalidCast {
+        from: Type,
+        to: Type,
+        span: Span,
+    },
+    /// Functions were used as values, but only the interpreter can run them
+    /// so far.
+    FunctionValuesUnsupported {
+        backend: &'static str,
+    },
+    /// A `break` carries a value out of a loop that can complete without one,
+    /// i.e., a loop with an iteration.
+    BreakValueInIterativeLoop {
+        span: Span,
+    },
+    /// Loops were broken out of with values, but only the interpreter can run
+    /// them so far.
+    LoopValuesUnsupported {
+        backend: &'static str,
+    },
+    /// An if-expression without an `else` branch is used as a value, but a
+    /// branch of it has one.
+    MissingElseBranch {
+        ty: Type,
+        span: Span,
+    },
+    /// Extern functions follow the C calling convention, which Sophia only
+    /// passes integers with.
+    InvalidExternType {
+        ty: Type,
+        span: Span,
+    },
+    /// An executable has no `main` to start at. Names the functions that the
+    /// program has instead, if any.
+    MissingMain {
+        functions: Vec<String>,
+    },
+    /// An executable has more than one `main` to start at.
+    DuplicateMain {
+        first_span: Span,
+        span: Span,
+    },
+    /// An executable's `main` isn't a function that takes nothing and returns
+    /// `i32` or `()`. Says what it is instead, e.g., "a struct".
+    InvalidMain {
+        found: String,
+        span: Span,
+    },
+    /// Condition of an `if` or of a `for` that isn't an integer, which is what
+    /// conditions are, holding when they aren't zero.
+    InvalidCondition {
+        found: Type,
+        span: Span,
+    },
+    /// Step of a range loop that's zero, so that the loop would never end.
+    ZeroStep {
+        span: Span,
+    },
+    /// Char that starts no token, which the parser skips to go on.
+    UnknownChar {
+        ch: char,
+        span: Span,
+    },
+    /// Value of a top-level declaration that isn't a function, e.g., the `5`
+    /// in `five :: 5`, which only functions, structs and enums can be.
+    InvalidDeclValue {
+        span: Span,
+    },
+    /// Top-level declaration of a name that an earlier one declares, at
+    /// `first_span`.
+    DuplicateDecl {
+        name: String,
+        first_span: Span,
+        span: Span,
+    },
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum CompileWarning {
+    InfiniteLoop {
+        for_span: Span,
+    },
+    UnusedValue {
+        span: Span,
+        ty: Type,
+    },
+    LintsSkipped {
+        decl_count: usize,
+        limit: usize,
+        span: Span,
+    },
+    UnreachableCode {
+        span: Span,
+    },
+    /// A binding defined with `x := ...` that's never read.
+    UnusedBinding {
+        name: String,
+        span: Span,
+    },
+    /// A top-level declaration that `main` never refers to, not even through
+    /// other declarations.
+    UnusedDecl {
+        name: String,
+        span: Span,
+    },
+    /// A binding defined with `x := ...` in a nested scope, whose name is
+    /// bound in an enclosing one already, e.g., by a parameter.
+    ShadowedBinding {
+        name: String,
+        span: Span,
+        shadowed_span: Span,
+        shadows_param: bool,
+    },
+    /// Condition of an `if` or of a `for` that's made up of constants only,
+    /// so that it's always true or always false, and a branch is dead.
+    ConstantCondition {
+        value: bool,
+        is_loop: bool,
+        span: Span,
+        /// Code that never runs because of the condition, if there's any.
+        dead_span: Option<Span>,
+    },
+    /// Range of a `for` whose bounds are constants, with the start past the
+    /// end, so that the loop never runs.
+    ReversedRange {
+        start: i128,
+        end: i128,
+        range_kind: RangeKind,
+        span: Span,
+    },
+    /// A `for` without an iteration, which loops until a `break` just like
+    /// `loop` does, but reads as if its iteration was left out by mistake.
+    /// Loops without a reachable `break` are warned about as infinite instead.
+    BareFor {
+        for_span: Span,
+    },
+}
+
+/// Note about what an optimization did to the program, or why it didn't, for
+/// people who want to know what code the backends get.
+///
+/// The IR has no optimization passes of its own, so the remarks so far are all
+/// about dead code elimination.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Remark {
+    /// Expressions after a `break` or a `continue` were removed.
+    RemovedUnreachableCode { count: usize, span: Span },
+    /// A block's unreachable value was kept, as the block has its type.
+    KeptUnreachableValue { span: Span },
+}
+
+/// Kind of warning, which can be allowed, i.e., turned off, by its name.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Lint {
+    InfiniteLoop,
+    UnusedValue,
+    UnreachableCode,
+    UnusedBinding,
+    UnusedDecl,
+    ShadowedBinding,
+    ConstantCondition,
+    ReversedRange,
+    BareFor,
+}
+
+impl Lint {
+    pub(crate) const ALL: [Lint; 9] = [
+        Lint::InfiniteLoop,
+        Lint::UnusedValue,
+        Lint::UnreachableCode,
+        Lint::UnusedBinding,
+        Lint::UnusedDecl,
+        Lint::ShadowedBinding,
+        Lint::ConstantCondition,
+        Lint::ReversedRange,
+        Lint::BareFor,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Lint::InfiniteLoop => "infinite-loop",
+            Lint::UnusedValue => "unused-value",
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::UnusedBinding => "unused-binding",
+            Lint::UnusedDecl => "unused-decl",
+            Lint::ShadowedBinding => "shadowed-binding",
+            Lint::ConstantCondition => "constant-condition",
+            Lint::ReversedRange => "reversed-range",
+            Lint::BareFor => "bare-for",
+        }
+    }
+
+    /// Lints that the name stands for, which is either a lint's or a group's,
+    /// as it's given on the command line or in a lint configuration file.
+    /// Underscores stand for dashes, e.g., `dead_code` for `dead-code`.
+    pub(crate) fn lints_named(name: &str) -> Option<&'static [Lint]> {
+        let name = name.replace('_', "-");
+
+        LINT_GROUPS
+            .iter()
+            .find(|(group, _)| *group == name)
+            .map(|&(_, lints)| lints)
+            .or_else(|| {
+                let idx = Lint::ALL.iter().position(|lint| lint.name() == name)?;
+
+                Some(&Lint::ALL[idx..=idx])
+            })
+    }
+}
+
+/// Groups of lints, by name, whose lints are given a level all at once.
+pub(crate) const LINT_GROUPS: [(&str, &[Lint]); 4] = [
+    ("all", &Lint::ALL),
+    // Every lint warns unless it's allowed, so `-D warnings` denies them all.
+    ("warnings", &Lint::ALL),
+    (
+        "unused",
+        &[Lint::UnusedValue, Lint::UnusedBinding, Lint::UnusedDecl],
+    ),
+    ("dead-code", &[Lint::UnreachableCode, Lint::UnusedDecl]),
+];
+
+/// What a lint's warnings do: be left out, be reported, or fail the
+/// compilation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl LintLevel {
+    pub(crate) fn from_name(name: &str) -> Option<LintLevel> {
+        match name {
+            "allow" => Some(LintLevel::Allow),
+            "warn" => Some(LintLevel::Warn),
+            "deny" => Some(LintLevel::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// Which lints are allowed or denied, as every lint warns unless it's either.
+/// Warnings of denied lints fail the compilation, like errors do.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub(crate) struct LintLevels {
+    allowed: Vec<Lint>,
+    denied: Vec<Lint>,
+}
+
+impl LintLevels {
+    pub(crate) fn allow(&mut self, lint: Lint) {
+        self.warn(lint);
+        self.allowed.push(lint);
+    }
+
+    pub(crate) fn warn(&mut self, lint: Lint) {
+        self.allowed.retain(|&allowed| allowed != lint);
+        self.denied.retain(|&denied| denied != lint);
+    }
+
+    pub(crate) fn deny(&mut self, lint: Lint) {
+        self.warn(lint);
+        self.denied.push(lint);
+    }
+
+    pub(crate) fn set(&mut self, lint: Lint, level: LintLevel) {
+        match level {
+            LintLevel::Allow => self.allow(lint),
+            LintLevel::Warn => self.warn(lint),
+            LintLevel::Deny => self.deny(lint),
+        }
+    }
+
+    pub(crate) fn is_allowed(&self, lint: Lint) -> bool {
+        self.allowed.contains(&lint)
+    }
+
+    pub(crate) fn is_denied(&self, lint: Lint) -> bool {
+        self.denied.contains(&lint)
+    }
+
+    /// Whether the warning's lint is denied. Warnings without a lint can't be
+    /// denied.
+    pub(crate) fn denies(&self, warning: &CompileWarning) -> bool {
+        warning.lint().is_some_and(|lint| self.is_denied(lint))
+    }
+}
+
+/// Edit of the tokens of a program that fixes what a diagnostic is about,
+/// which `sophia fix` makes, see `fixer::fix_source`.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Fix {
+    /// Replaces the token at the span with the text.
+    Replace { span: Span, text: String },
+    /// Deletes the token at the span.
+    Delete { span: Span },
+    /// Inserts the text after the last token before the position, which
+    /// there must be.
+    Insert { pos: BytePos, text: String },
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub(crate) struct Diagnostic {
+    pub(crate) errors: Vec<CompileError>,
+    pub(crate) warnings: Vec<CompileWarning>,
+}
+
+impl Diagnostic {
+    pub(crate) fn from_errors(errors: Vec<CompileError>) -> Diagnostic {
+        Diagnostic {
+            errors,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    /// Moves the spans of the errors and the warnings by `delta` bytes, as when
+    /// the code they're about moves.
+    pub(crate) fn move_spans(&mut self, delta: isize) {
+        let spans = self
+            .errors
+            .iter_mut()
+            .flat_map(CompileError::spans_mut)
+            .chain(self.warnings.iter_mut().flat_map(CompileWarning::spans_mut));
+
+        for span in spans {
+            span.start = BytePos(span.start.0.wrapping_add_signed(delta));
+            span.end = BytePos(span.end.0.wrapping_add_signed(delta));
+        }
+    }
+}
+
+impl CompileError {
+    /// Code that names the kind of error, which stays the same across
+    /// releases, unlike the message, so tools can tell errors apart by it.
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            CompileError::UnclosedDelimiter { .. } => "E0001",
+            CompileError::UndefinedName { .. } => "E0002",
+            CompileError::DiscardUsedAsValue { .. } => "E0003",
+            CompileError::MismatchedTypes { .. } => "E0004",
+            CompileError::MismatchedCallArguments { .. } => "E0005",
+            CompileError::CallToNonFunction { .. } => "E0006",
+            CompileError::ScriptStatementsWithMain { .. } => "E0007",
+            CompileError::SourceTooLarge { .. } => "E0008",
+            CompileError::AstTooLarge { .. } => "E0009",
+            CompileError::NestingTooDeep { .. } => "E0010",
+            CompileError::IntegerConstantOutOfRange { .. } => "E0011",
+            CompileError::IntProfileUnsupported { .. } => "E0012",
+            CompileError::HostFunctionsUnsupported { .. } => "E0013",
+            CompileError::BreakOutsideLoop { .. } => "E0014",
+            CompileError::ExpectedButFound { .. } => "E0015",
+            CompileError::NonExhaustiveMatch { .. } => "E0016",
+            CompileError::NoSuchField { .. } => "E0017",
+            CompileError::MissingFields { .. } => "E0018",
+            CompileError::DuplicateField { .. } => "E0019",
+            CompileError::RecursiveType { .. } => "E0020",
+            CompileError::NotAStruct { .. } => "E0021",
+            CompileError::StructsUnsupported { .. } => "E0022",
+            CompileError::NoSuchVariant { .. } => "E0023",
+            CompileError::DuplicateVariant { .. } => "E0024",
+            CompileError::MismatchedVariantPayload { .. } => "E0025",
+            CompileError::NotAnEnum { .. } => "E0026",
+            CompileError::NonExhaustiveVariants { .. } => "E0027",
+            CompileError::EnumsUnsupported { .. } => "E0028",
+            CompileError::NotAType { .. } => "E0029",
+            CompileError::MisspelledKeyword { .. } => "E0030",
+            CompileError::MismatchedTupleBindings { .. } => "E0031",
+            CompileError::TuplesUnsupported { .. } => "E0032",
+            CompileError::IndexOutOfBounds { .. } => "E0033",
+            CompileError::CannotIndex { .. } => "E0034",
+            CompileError::InvalidAssignTarget { .. } => "E0035",
+            CompileError::ArraysUnsupported { .. } => "E0036",
+            CompileError::CannotIterate { .. } => "E0037",
+            CompileError::InvalidIntegerSuffix { .. } => "E0038",
+            CompileError::IntegerConstantTooLarge { .. } => "E0039",
+            CompileError::IntTypesUnsupported { .. } => "E0040",
+            CompileError::InvalidCast { .. } => "E0041",
+            CompileError::FunctionValuesUnsupported { .. } => "E0042",
+            CompileError::BreakValueInIterativeLoop { .. } => "E0043",
+            CompileError::LoopValuesUnsupported { .. } => "E0044",
+            CompileError::MissingElseBranch { .. } => "E0045",
+            CompileError::InvalidExternType { .. } => "E0046",
+            CompileError::MissingMain { .. } => "E0047",
+            CompileError::DuplicateMain { .. } => "E0048",
+            CompileError::InvalidMain { .. } => "E0049",
+            CompileError::InvalidCondition { .. } => "E0050",
+            CompileError::ZeroStep { .. } => "E0051",
+            CompileError::UnknownChar { .. } => "E0052",
+            CompileError::InvalidDeclValue { .. } => "E0053",
+            CompileError::DuplicateDecl { .. } => "E0054",
+        }
+    }
+
+    /// Whether the error is about the whole program rather than some code in
+    /// it, so its span is empty and at the start of the source code.
+    pub(crate) fn is_about_program(&self) -> bool {
+        matches!(
+            self,
+            CompileError::SourceTooLarge { .. }
+                | CompileError::IntProfileUnsupported { .. }
+                | CompileError::HostFunctionsUnsupported { .. }
+                | CompileError::StructsUnsupported { .. }
+                | CompileError::EnumsUnsupported { .. }
+                | CompileError::TuplesUnsupported { .. }
+                | CompileError::ArraysUnsupported { .. }
+                | CompileError::IntTypesUnsupported { .. }
+                | CompileError::FunctionValuesUnsupported { .. }
+                | CompileError::LoopValuesUnsupported { .. }
+                | CompileError::MissingMain { .. }
+        )
+    }
+
+    /// Span of the code this error is primarily about.
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            CompileError::UnclosedDelimiter { open_span, .. } => *open_span,
+            CompileError::UndefinedName { span, .. }
+            | CompileError::DiscardUsedAsValue { span }
+            | CompileError::MismatchedTypes { span, .. }
+            | CompileError::MismatchedCallArguments { span, .. }
+            | CompileError::CallToNonFunction { span, .. }
+            | CompileError::BreakOutsideLoop { span, .. }
+            | CompileError::ExpectedButFound { span, .. }
+            | CompileError::NonExhaustiveMatch { span, .. }
+            | CompileError::NoSuchField { span, .. }
+            | CompileError::MissingFields { span, .. }
+            | CompileError::DuplicateField { span, .. }
+            | CompileError::RecursiveType { span, .. }
+            | CompileError::NotAStruct { span, .. }
+            | CompileError::NoSuchVariant { span, .. }
+            | CompileError::DuplicateVariant { span, .. }
+            | CompileError::MismatchedVariantPayload { span, .. }
+            | CompileError::NotAnEnum { span, .. }
+            | CompileError::NonExhaustiveVariants { span, .. }
+            | CompileError::NotAType { span, .. }
+            | CompileError::MisspelledKeyword { span, .. }
+            | CompileError::MismatchedTupleBindings { span, .. }
+            | CompileError::IndexOutOfBounds { span, .. }
+            | CompileError::CannotIndex { span, .. }
+            | CompileError::CannotIterate { span, .. }
+            | CompileError::InvalidIntegerSuffix { span, .. }
+            | CompileError::IntegerConstantTooLarge { span, .. }
+            | CompileError::InvalidCast { span, .. }
+            | CompileError::BreakValueInIterativeLoop { span }
+            | CompileError::MissingElseBranch { span, .. }
+            | CompileError::InvalidExternType { span, .. }
+            | CompileError::DuplicateMain { span, .. }
+            | CompileError::InvalidMain { span, .. }
+            | CompileError::InvalidCondition { span, .. }
+            | CompileError::ZeroStep { span }
+            | CompileError::UnknownChar { span, .. }
+            | CompileError::InvalidDeclValue { span }
+            | CompileError::DuplicateDecl { span, .. }
+            | CompileError::InvalidAssignTarget { span } => *span,
+            CompileError::ScriptStatementsWithMain { stmt_span, .. } => *stmt_span,
+            // They're about the whole program rather than some code in it.
+            CompileError::SourceTooLarge { .. }
+            | CompileError::IntProfileUnsupported { .. }
+            | CompileError::HostFunctionsUnsupported { .. }
+            | CompileError::StructsUnsupported { .. }
+            | CompileError::EnumsUnsupported { .. }
+            | CompileError::TuplesUnsupported { .. }
+            | CompileError::ArraysUnsupported { .. }
+            | CompileError::IntTypesUnsupported { .. }
+            | CompileError::FunctionValuesUnsupported { .. }
+            | CompileError::LoopValuesUnsupported { .. }
+            | CompileError::MissingMain { .. } => Span {
+                start: BytePos(0),
+                end: BytePos(0),
+            },
+            CompileError::AstTooLarge { span, .. }
+            | CompileError::NestingTooDeep { span, .. }
+            | CompileError::IntegerConstantOutOfRange { span, .. } => *span,
+        }
+    }
+
+    /// Spans of the code this error is about, so that they can be moved along
+    /// with the code.
+    pub(crate) fn spans_mut(&mut self) -> Vec<&mut Span> {
+        match self {
+            CompileError::UnclosedDelimiter {
+                open_span,
+                eof_span,
+                ..
+            } => vec![open_span, eof_span],
+            CompileError::ScriptStatementsWithMain {
+                main_span,
+                stmt_span,
+            } => vec![main_span, stmt_span],
+            CompileError::DuplicateMain { first_span, span }
+            | CompileError::DuplicateDecl {
+                first_span, span, ..
+            } => vec![first_span, span],
+            CompileError::UndefinedName { span, .. }
+            | CompileError::DiscardUsedAsValue { span, .. }
+            | CompileError::MismatchedTypes { span, .. }
+            | CompileError::MismatchedCallArguments { span, .. }
+            | CompileError::CallToNonFunction { span, .. }
+            | CompileError::BreakOutsideLoop { span, .. }
+            | CompileError::ExpectedButFound { span, .. }
+            | CompileError::NonExhaustiveMatch { span, .. }
+            | CompileError::NoSuchField { span, .. }
+            | CompileError::MissingFields { span, .. }
+            | CompileError::DuplicateField { span, .. }
+            | CompileError::RecursiveType { span, .. }
+            | CompileError::NotAStruct { span, .. }
+            | CompileError::NoSuchVariant { span, .. }
+            | CompileError::DuplicateVariant { span, .. }
+            | CompileError::MismatchedVariantPayload { span, .. }
+            | CompileError::NotAnEnum { span, .. }
+            | CompileError::NonExhaustiveVariants { span, .. }
+            | CompileError::NotAType { span, .. }
+            | CompileError::MisspelledKeyword { span, .. }
+            | CompileError::MismatchedTupleBindings { span, .. }
+            | CompileError::IndexOutOfBounds { span, .. }
+            | CompileError::CannotIndex { span, .. }
+            | CompileError::CannotIterate { span, .. }
+            | CompileError::InvalidIntegerSuffix { span, .. }
+            | CompileError::IntegerConstantTooLarge { span, .. }
+            | CompileError::InvalidCast { span, .. }
+            | CompileError::BreakValueInIterativeLoop { span, .. }
+            | CompileError::MissingElseBranch { span, .. }
+            | CompileError::InvalidExternType { span, .. }
+            | CompileError::InvalidAssignTarget { span, .. }
+            | CompileError::InvalidMain { span, .. }
+            | CompileError::InvalidCondition { span, .. }
+            | CompileError::ZeroStep { span }
+            | CompileError::UnknownChar { span, .. }
+            | CompileError::InvalidDeclValue { span }
+            | CompileError::AstTooLarge { span, .. }
+            | CompileError::NestingTooDeep { span, .. }
+            | CompileError::IntegerConstantOutOfRange { span, .. } => vec![span],
+            CompileError::SourceTooLarge { .. }
+            | CompileError::IntProfileUnsupported { .. }
+            | CompileError::HostFunctionsUnsupported { .. }
+            | CompileError::StructsUnsupported { .. }
+            | CompileError::EnumsUnsupported { .. }
+            | CompileError::TuplesUnsupported { .. }
+            | CompileError::ArraysUnsupported { .. }
+            | CompileError::IntTypesUnsupported { .. }
+            | CompileError::FunctionValuesUnsupported { .. }
+            | CompileError::LoopValuesUnsupported { .. }
+            | CompileError::MissingMain { .. } => vec![],
+        }
+    }
+
+    /// Other code that this error points at, along with what it is, e.g., the
+    /// first declaration of a name that's declared again.
+    pub(crate) fn note(&self) -> Option<(Span, String)> {
+        match self {
+            CompileError::DuplicateDecl {
+                name, first_span, ..
+            } => Some((*first_span, format!("`{}` is first declared here", name))),
+            _ => None,
+        }
+    }
+
+    /// Edit that fixes the error, if it's clear what the code was meant to
+    /// be, e.g., the keyword that a name is a typo of.
+    pub(crate) fn fix(&self) -> Option<Fix> {
+        match self {
+            // The span of a call is of the whole call, which starts with the
+            // name, as references do.
+            CompileError::UndefinedName {
+                name,
+                span,
+                suggestion: Some(suggestion),
+            } => Some(Fix::Replace {
+                span: Span {
+                    start: span.start,
+                    end: BytePos(span.start.0 + name.len()),
+                },
+                text: suggestion.clone(),
+            }),
+            CompileError::MisspelledKeyword { keyword, span, .. } => Some(Fix::Replace {
+                span: *span,
+                text: keyword.as_str().to_owned(),
+            }),
+            CompileError::UnknownChar { span, .. } => Some(Fix::Delete { span: *span }),
+            CompileError::UnclosedDelimiter {
+                delim, eof_span, ..
+            } => Some(Fix::Insert {
+                pos: eof_span.start,
+                text: delim.closed_char().to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl CompileWarning {
+    /// Code that names the kind of warning, which is the name of its lint, so
+    /// it's what allows it too.
+    pub(crate) fn code(&self) -> &'static str {
+        self.lint().map_or("lints-skipped", Lint::name)
+    }
+
+    /// Span of the code this warning is primarily about.
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            CompileWarning::InfiniteLoop { for_span } | CompileWarning::BareFor { for_span } => {
+                *for_span
+            }
+            CompileWarning::UnusedValue { span, .. }
+            | CompileWarning::LintsSkipped { span, .. }
+            | CompileWarning::UnreachableCode { span }
+            | CompileWarning::UnusedBinding { span, .. }
+            | CompileWarning::UnusedDecl { span, .. }
+            | CompileWarning::ShadowedBinding { span, .. }
+            | CompileWarning::ConstantCondition { span, .. }
+            | CompileWarning::ReversedRange { span, .. } => *span,
+        }
+    }
+
+    /// Spans of the code this warning is about, so that they can be moved
+    /// along with the code.
+    pub(crate) fn spans_mut(&mut self) -> Vec<&mut Span> {
+        match self {
+            CompileWarning::InfiniteLoop { for_span } | CompileWarning::BareFor { for_span } => {
+                vec![for_span]
+            }
+            CompileWarning::UnusedValue { span, .. }
+            | CompileWarning::LintsSkipped { span, .. }
+            | CompileWarning::UnreachableCode { span }
+            | CompileWarning::UnusedBinding { span, .. }
+            | CompileWarning::UnusedDecl { span, .. }
+            | CompileWarning::ReversedRange { span, .. } => vec![span],
+            CompileWarning::ShadowedBinding {
+                span,
+                shadowed_span,
+                ..
+            } => vec![span, shadowed_span],
+            CompileWarning::ConstantCondition {
+                span, dead_span, ..
+            } => [span].into_iter().chain(dead_span).collect(),
+        }
+    }
+
+    /// Other code that this warning points at, along with what it is, e.g.,
+    /// the binding that another one shadows.
+    pub(crate) fn note(&self) -> Option<(Span, String)> {
+        match self {
+            CompileWarning::ShadowedBinding {
+                name,
+                shadowed_span,
+                shadows_param,
+                ..
+            } => {
+                let binding = if *shadows_param {
+                    "parameter"
+                } else {
+                    "binding"
+                };
+
+                Some((
+                    *shadowed_span,
+                    format!("shadowed {} `{}` is defined here", binding, name),
+                ))
+            }
+            CompileWarning::ConstantCondition {
+                value,
+                is_loop,
+                dead_span: Some(dead_span),
+                ..
+            } => {
+                let note = match (is_loop, value) {
+                    (false, _) => "this branch is never taken",
+                    (true, false) => "the loop's body never runs",
+                    (true, true) => "the loop's `else` branch never runs",
+                };
+
+                Some((*dead_span, note.to_owned()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Edit that fixes the warning, if it's clear what the code was meant to
+    /// be, e.g., `loop` for a `for` without an iteration.
+    pub(crate) fn fix(&self) -> Option<Fix> {
+        match self {
+            CompileWarning::BareFor { for_span } => Some(Fix::Replace {
+                span: *for_span,
+                text: "loop".to_owned(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Lint this warning is of, or `None` if it's about the compiler itself,
+    /// which can't be allowed.
+    pub(crate) fn lint(&self) -> Option<Lint> {
+        match self {
+            CompileWarning::InfiniteLoop { .. } => Some(Lint::InfiniteLoop),
+            CompileWarning::UnusedValue { .. } => Some(Lint::UnusedValue),
+            CompileWarning::LintsSkipped { .. } => None,
+            CompileWarning::UnreachableCode { .. } => Some(Lint::UnreachableCode),
+            CompileWarning::UnusedBinding { .. } => Some(Lint::UnusedBinding),
+            CompileWarning::UnusedDecl { .. } => Some(Lint::UnusedDecl),
+            CompileWarning::ShadowedBinding { .. } => Some(Lint::ShadowedBinding),
+            CompileWarning::ConstantCondition { .. } => Some(Lint::ConstantCondition),
+            CompileWarning::ReversedRange { .. } => Some(Lint::ReversedRange),
+            CompileWarning::BareFor { .. } => Some(Lint::BareFor),
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::UnclosedDelimiter { delim, .. } => {
+                write!(f, "unclosed `{}` opened here", delim.open_char())
+            }
+            CompileError::UndefinedName {
+                name, suggestion, ..
+            } => {
+                write!(f, "cannot find `{}` in this scope", name)?;
+
+                if let Some(suggestion) = suggestion {
+                    write!(f, "; did you mean `{}`?", suggestion)?;
+                }
+
+                Ok(())
+            }
+            CompileError::DiscardUsedAsValue { .. } => {
+                write!(f, "`_` can only be used to discard a value, not as a value")
+            }
+            CompileError::MismatchedTypes {
+                expected, found, ..
+            } => {
+                write!(
+                    f,
+                    "mismatched types: expected `{}`, found `{}`",
+                    expected, found
+                )
+            }
+            CompileError::MismatchedCallArguments {
+                expected, found, ..
+            } => {
+                write!(
+                    f,
+                    "arguments to this function are incorrect: expected `({})`, found `({})`",
+                    comma_separated(expected),
+                    comma_separated(found)
+                )
+            }
+            CompileError::CallToNonFunction { ty, .. } => {
+                write!(f, "expected function, found `{}`", ty)
+            }
+            CompileError::ScriptStatementsWithMain { .. } => {
+                write!(
+                    f,
+                    "script has top-level statements, so it cannot also declare `main`"
+                )
+            }
+            CompileError::SourceTooLarge { size, limit } => {
+                write!(
+                    f,
+                    "source code is {} bytes long, over the limit of {} bytes",
+                    size, limit
+                )
+            }
+            CompileError::AstTooLarge { limit, .. } => {
+                write!(
+                    f,
+                    "program takes up over {} bytes of memory, stopped parsing here",
+                    limit
+                )
+            }
+            CompileError::NestingTooDeep { limit, .. } => {
+                write!(f, "expression nests deeper than {} levels", limit)
+            }
+            CompileError::IntegerConstantOutOfRange { width, .. } => {
+                write!(f, "integer constant doesn't fit in a {} integer", width)
+            }
+            CompileError::IntProfileUnsupported { backend } => {
+                write!(
+                    f,
+                    "the {} backend only supports 32-bit integers that wrap around, \
+                     divided by hardware; use the C backend for other integer profiles",
+                    backend
+                )
+            }
+            CompileError::HostFunctionsUnsupported { backend } => {
+                write!(
+                    f,
+                    "the {} backend can't call host functions; compile to bytecode to run \
+                     them in the VM",
+                    backend
+                )
+            }
+            CompileError::BreakOutsideLoop { keyword, .. } => {
+                write!(f, "`{}` outside of a loop", keyword.as_str())
+            }
+            CompileError::ExpectedButFound {
+                expected, found, ..
+            } => {
+                let expected: Vec<_> = expected.iter().map(|kind| kind.to_string()).collect();
+                let found = found.map_or("end of file".to_owned(), |kind| kind.to_string());
+
+                if let [expected] = expected.as_slice() {
+                    write!(f, "expected {}, found {}", expected, found)
+                } else {
+                    write!(
+                        f,
+                        "expected one of {}, found {}",
+                        expected.join(", "),
+                        found
+                    )
+                }
+            }
+            CompileError::NonExhaustiveMatch {
+                uncovered, width, ..
+            } => {
+                let uncovered: Vec<_> = uncovered
+                    .iter()
+                    .map(|&range| uncovered_range(range, *width))
+                    .collect();
+
+                write_non_exhaustive_patterns(f, &uncovered)
+            }
+            CompileError::NoSuchField { ty, field, .. } => {
+                write!(f, "no field `{}` on type `{}`", field, ty)
+            }
+            CompileError::MissingFields { ty, fields, .. } => {
+                let fields: Vec<_> = fields.iter().map(|field| format!("`{}`", field)).collect();
+
+                match fields.split_last() {
+                    Some((last, [])) => write!(f, "missing field {}", last)?,
+                    Some((last, rest)) => {
+                        write!(f, "missing fields {} and {}", rest.join(", "), last)?
+                    }
+                    None => write!(f, "missing fields")?,
+                }
+
+                write!(f, " in initializer of `{}`", ty)
+            }
+            CompileError::DuplicateField { field, .. } => {
+                write!(f, "field `{}` is already declared", field)
+            }
+            CompileError::RecursiveType { name, .. } => {
+                write!(
+                    f,
+                    "type `{}` contains itself, so it has infinite size",
+                    name
+                )
+            }
+            CompileError::NotAStruct { name, .. } => {
+                write!(f, "expected struct, found `{}`", name)
+            }
+            CompileError::StructsUnsupported { backend } => {
+                write!(
+                    f,
+                    "the {} backend doesn't support structs yet; run the program with the \
+                     interpreter instead",
+                    backend
+                )
+            }
+            CompileError::NoSuchVariant { ty, variant, .. } => {
+                write!(f, "no variant `{}` on enum `{}`", variant, ty)
+            }
+            CompileError::DuplicateVariant { variant, .. } => {
+                write!(f, "variant `{}` is already declared", variant)
+            }
+            CompileError::MismatchedVariantPayload {
+                variant, expected, ..
+            } => match expected {
+                Some(expected) => write!(
+                    f,
+                    "variant `{}` holds a value of type `{}`",
+                    variant, expected
+                ),
+                None => write!(f, "variant `{}` holds no value", variant),
+            },
+            CompileError::NotAnEnum { name, .. } => {
+                write!(f, "expected enum, found `{}`", name)
+            }
+            CompileError::NonExhaustiveVariants { uncovered, .. } => {
+                write_non_exhaustive_patterns(f, uncovered)
+            }
+            CompileError::EnumsUnsupported { backend } => {
+                write!(
+                    f,
+                    "the {} backend doesn't support enums yet; run the program with the \
+                     interpreter instead",
+                    backend
+                )
+            }
+            CompileError::NotAType { name, .. } => {
+                write!(f, "expected type, found `{}`", name)
+            }
+            CompileError::MisspelledKeyword { found, keyword, .. } => {
+                write!(
+                    f,
+                    "expected keyword, found `{}`; did you mean `{}`?",
+                    found,
+                    keyword.as_str()
+                )
+            }
+            CompileError::MismatchedTupleBindings {
+                bindings, found, ..
+            } => {
+                write!(
+                    f,
+                    "expected a tuple of {} elements, found `{}`",
+                    bindings, found
+                )
+            }
+            CompileError::TuplesUnsupported { backend } => {
+                write!(
+                    f,
+                    "the {} backend doesn't support tuples yet; run the program with the \
+                     interpreter instead",
+                    backend
+                )
+            }
+            CompileError::IndexOutOfBounds { index, len, .. } => {
+                write!(
+                    f,
+                    "index out of bounds: the length is {} but the index is {}",
+                    len, index
+                )
+            }
+            CompileError::CannotIndex { ty, .. } => {
+                write!(f, "cannot index into a value of type `{}`", ty)
+            }
+            CompileError::CannotIterate { ty, .. } => {
+                write!(f, "cannot iterate over a value of type `{}`", ty)
+            }
+            CompileError::InvalidIntegerSuffix { suffix, .. } => {
+                write!(f, "invalid suffix `{}` for an integer constant", suffix)
+            }
+            CompileError::IntegerConstantTooLarge { ty, .. } => {
+                write!(f, "integer constant doesn't fit in `{}`", ty)
+            }
+            CompileError::InvalidAssignTarget { .. } => {
+                write!(
+                    f,
+                    "only elements of arrays bound to names can be assigned to"
+                )
+            }
+            CompileError::ArraysUnsupported { backend } => {
+                write!(
+                    f,
+                    "the {} backend doesn't support arrays yet; run the program with the \
+                     interpreter instead",
+                    backend
+                )
+            }
+            CompileError::IntTypesUnsupported { backend } => {
+                write!(
+                    f,
+                    "the {} backend only supports integers of type `i32` yet; run the program \
+                     with the interpreter instead",
+                    backend
+                )
+            }
+            CompileError::InvalidCast { from, to, .. } => {
+                write!(f, "cannot cast a value of type `{}` to `{}`", from, to)
+            }
+            CompileError::FunctionValuesUnsupported { backend } => {
+                write!(
+                    f,
+                    "the {} backend doesn't support function values yet; run the program with \
+                     the interpreter instead",
+                    backend
+                )
+            }
+            CompileError::BreakValueInIterativeLoop { .. } => {
+                write!(
+                    f,
+                    "`break` with a value out of a loop that can complete without one"
+                )
+            }
+            CompileError::LoopValuesUnsupported { backend } => {
+                write!(
+                    f,
+                    "the {} backend doesn't support breaking out of loops with values yet; run \
+                     the program with the interpreter instead",
+                    backend
+                )
+            }
+            CompileError::MissingElseBranch { ty, .. } => {
+                write!(
+                    f,
+                    "`if` without an `else` branch can't have a value of type `{}`",
+                    ty
+                )
+            }
+            CompileError::InvalidExternType { ty, .. } => {
+                write!(
+                    f,
+                    "extern functions can only take and return integers, not `{}`",
+                    ty
+                )
+            }
+            CompileError::MissingMain { functions } if functions.is_empty() => {
+                write!(f, "program has no `main` function to start at")
+            }
+            CompileError::MissingMain { functions } => {
+                let functions: Vec<_> = functions
+                    .iter()
+                    .map(|function| format!("`{}`", function))
+                    .collect();
+
+                write!(
+                    f,
+                    "program has no `main` function to start at; found only {}",
+                    functions.join(", ")
+                )
+            }
+            CompileError::DuplicateMain { .. } => {
+                write!(
+                    f,
+                    "`main` is declared more than once, but a program starts at only one"
+                )
+            }
+            CompileError::InvalidMain { found, .. } => {
+                write!(
+                    f,
+                    "`main` must be a function of type `() -> i32` or `() -> ()`, found {}",
+                    found
+                )
+            }
+            CompileError::InvalidCondition { found, .. } => {
+                write!(
+                    f,
+                    "expected integer condition, found `{}`; conditions hold when they aren't zero",
+                    found
+                )
+            }
+            CompileError::ZeroStep { .. } => {
+                write!(f, "range loop steps by zero, so it never ends")
+            }
+            CompileError::UnknownChar { ch, .. } => {
+                write!(f, "unknown character {:?}", ch)
+            }
+            CompileError::InvalidDeclValue { .. } => {
+                write!(
+                    f,
+                    "expected function, struct or enum; only those can be declared at the top level"
+                )
+            }
+            CompileError::DuplicateDecl { name, .. } => {
+                write!(f, "`{}` is declared more than once", name)
+            }
+        }
+    }
+}
+
+/// Tells what patterns a match-expr is missing, which, like a list of missing
+/// cases, is cut short if it's long.
+fn write_non_exhaustive_patterns(f: &mut fmt::Formatter<'_>, uncovered: &[String]) -> fmt::Result {
+    let shown: Vec<_> = uncovered
+        .iter()
+        .take(3)
+        .map(|pattern| format!("`{}`", pattern))
+        .collect();
+
+    write!(f, "non-exhaustive patterns: ")?;
+
+    match shown.split_last() {
+        Some((last, [])) => write!(f, "{}", last)?,
+        Some((last, rest)) if uncovered.len() <= 3 => {
+            write!(f, "{} and {}", rest.join(", "), last)?
+        }
+        _ => write!(
+            f,
+            "{} and {} more",
+            shown.join(", "),
+            uncovered.len() - shown.len()
+        )?,
+    }
+
+    write!(f, " not covered")
+}
+
+/// Range of integers as a pattern that matches it, naming the bounds of the
+/// integers' width instead of spelling them out.
+fn uncovered_range((start, end): (i128, i128), width: IntWidth) -> String {
+    let bound = |value: i128| {
+        if value == i128::from(width.min()) {
+            format!("i{}::MIN", width.bits())
+        } else if value == i128::from(width.max()) {
+            format!("i{}::MAX", width.bits())
+        } else {
+            value.to_string()
+        }
+    };
+
+    if start == end {
+        bound(start)
+    } else {
+        format!("{}..={}", bound(start), bound(end))
+    }
+}
+
+impl Remark {
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            Remark::RemovedUnreachableCode { span, .. } | Remark::KeptUnreachableValue { span } => {
+                *span
+            }
+        }
+    }
+
+    /// Name of the pass that made the remark.
+    pub(crate) fn pass(&self) -> &'static str {
+        match self {
+            Remark::RemovedUnreachableCode { .. } | Remark::KeptUnreachableValue { .. } => "dce",
+        }
+    }
+}
+
+impl fmt::Display for Remark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Remark::RemovedUnreachableCode { count: 1, .. } => {
+                write!(f, "removed an unreachable expression")
+            }
+            Remark::RemovedUnreachableCode { count, .. } => {
+                write!(f, "removed {} unreachable expressions", count)
+            }
+            Remark::KeptUnreachableValue { .. } => {
+                write!(
+                    f,
+                    "kept an unreachable value, as the type of its block is the value's"
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for CompileWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileWarning::InfiniteLoop { .. } => {
+                write!(f, "loop never exits, as it has no reachable `break`")
+            }
+            CompileWarning::UnusedValue { ty, .. } => {
+                write!(
+                    f,
+                    "unused value of type `{}`; bind it with `x := ...` or discard it explicitly \
+                     with `_ = ...`",
+                    ty
+                )
+            }
+            CompileWarning::LintsSkipped {
+                decl_count, limit, ..
+            } => {
+                write!(
+                    f,
+                    "lints skipped, as the program has {} declarations, over the limit of {}",
+                    decl_count, limit
+                )
+            }
+            CompileWarning::UnreachableCode { .. } => {
+                write!(f, "unreachable code after `break` or `continue`")
+            }
+            CompileWarning::UnusedBinding { name, .. } => {
+                write!(
+                    f,
+                    "binding `{}` is never read; discard the value with `_ = ...` instead",
+                    name
+                )
+            }
+            CompileWarning::UnusedDecl { name, .. } => {
+                write!(f, "`{}` is never used by `main`", name)
+            }
+            CompileWarning::ShadowedBinding {
+                name,
+                shadows_param: true,
+                ..
+            } => {
+                write!(f, "binding `{}` shadows a parameter of the same name", name)
+            }
+            CompileWarning::ShadowedBinding { name, .. } => {
+                write!(
+                    f,
+                    "binding `{}` shadows an outer binding of the same name",
+                    name
+                )
+            }
+            CompileWarning::ConstantCondition { value, is_loop, .. } => {
+                let construct = if *is_loop { "loop" } else { "`if`" };
+
+                write!(f, "{} condition is always {}", construct, value)
+            }
+            CompileWarning::ReversedRange {
+                start,
+                end,
+                range_kind,
+                ..
+            } => {
+                write!(
+                    f,
+                    "range `{}{}{}` starts past its end, so the loop never runs",
+                    start, range_kind, end
+                )
+            }
+            CompileWarning::BareFor { .. } => {
+                write!(
+                    f,
+                    "`for` without an iteration loops until a `break`; write `loop` if that's meant"
+                )
+            }
+        }
+    }
+}
+
+fn comma_separated(types: &[Type]) -> String {
+    types
+        .iter()
+        .map(|ty| ty.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,134 @@
+use crate::diagnostics::Diagnostic;
+use crate::driver::{self, Options};
+use crate::interp::RuntimeError;
+use crate::source_map::SourceMap;
+
+/// Example in a fenced code block of a doc comment, i.e., of the comment lines
+/// that start with `///` above a declaration.
+///
+/// Examples are scripts that can use the declarations of the program they're
+/// in, so that they can show a function in use without declaring a `main` for
+/// it. Fences may say what the example is for:
+/// `no_run` examples are only compiled, and `ignore` examples, or examples of
+/// code that isn't Sophia, e.g., `text`, are left alone.
+#[derive(PartialEq, Debug)]
+pub(crate) struct DocExample {
+    /// Line of the fence that opens the example, counting from one.
+    pub(crate) line: usize,
+    pub(crate) source_code: String,
+    pub(crate) no_run: bool,
+}
+
+pub(crate) enum DocExampleFailure {
+    Compile(Diagnostic),
+    Runtime(RuntimeError),
+}
+
+/// Examples in the doc comments of the source code, in order. An example
+/// whose fence isn't closed ends with its doc comment.
+pub(crate) fn extract_doc_examples(source_code: &str) -> Vec<DocExample> {
+    let mut examples = vec![];
+    // Example that the fence on the line opened, unless it's left alone.
+    let mut open_example: Option<Option<DocExample>> = None;
+
+    for (line_idx, line) in source_code.lines().enumerate() {
+        let Some(text) = doc_comment_text(line) else {
+            examples.extend(open_example.take().flatten());
+            continue;
+        };
+
+        match (text.trim_start().strip_prefix("```"), &mut open_example) {
+            (Some(_), Some(_)) => examples.extend(open_example.take().flatten()),
+            (Some(info), None) => open_example = Some(new_example(line_idx + 1, info)),
+            (None, Some(Some(example))) => {
+                example.source_code.push_str(text);
+                example.source_code.push('\n');
+            }
+            (None, _) => {}
+        }
+    }
+
+    examples.extend(open_example.flatten());
+
+    examples
+}
+
+/// Source code of the program with the example as its last file, of the given
+/// name, so that it can use the program's declarations.
+pub(crate) fn with_doc_example(
+    source_map: &SourceMap,
+    example: &DocExample,
+    file_name: &str,
+) -> SourceMap {
+    let mut source_map = source_map.clone();
+    source_map.add_file(file_name, &example.source_code);
+
+    source_map
+}
+
+/// Compiles the example, the last file of the source code, and runs it unless
+/// it's `no_run`.
+pub(crate) fn check_doc_example(
+    source_map: SourceMap,
+    example: &DocExample,
+    options: &Options,
+) -> Result<(), DocExampleFailure> {
+    // Warnings don't fail examples, so only what changes the program's
+    // meaning carries over.
+    let options = Options {
+        limits: options.limits,
+        int_profile: options.int_profile,
+        ..Options::default()
+    };
+
+    if example.no_run {
+        let diagnostic = driver::check_doc_example(source_map, &options);
+
+        if !diagnostic.errors.is_empty() {
+            return Err(DocExampleFailure::Compile(diagnostic));
+        }
+
+        return Ok(());
+    }
+
+    match driver::run_doc_example(source_map, &options) {
+        Ok(execution) => execution
+            .exit_code
+            .map(|_| ())
+            .map_err(DocExampleFailure::Runtime),
+        Err(diagnostic) => Err(DocExampleFailure::Compile(diagnostic)),
+    }
+}
+
+/// Text of the doc comment on the line, without the `///` and the space after
+/// it, if the line is one.
+fn doc_comment_text(line: &str) -> Option<&str> {
+    let text = line.trim_start().strip_prefix("///")?;
+
+    // Comments that start with more slashes are regular ones, e.g., rulers.
+    if text.starts_with('/') {
+        return None;
+    }
+
+    Some(text.strip_prefix(' ').unwrap_or(text))
+}
+
+/// Example that a fence with the given info string opens, or `None` if it's
+/// left alone.
+fn new_example(line: usize, info: &str) -> Option<DocExample> {
+    let mut no_run = false;
+
+    for attribute in info.split(',').map(str::trim) {
+        match attribute {
+            "" | "sophia" => {}
+            "no_run" => no_run = true,
+            _ => return None,
+        }
+    }
+
+    Some(DocExample {
+        line,
+        source_code: String::new(),
+        no_run,
+    })
+}
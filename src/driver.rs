@@ -1,22 +1,1248 @@
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::ast::{
+    BindDef, CompoundExpr, Const, Decl, Expr, FieldExpr, ForIteration, Function, HostFnDecl,
+    ParenExpr, Program, TupleBindDef, TupleFieldExpr, Type,
+};
+use crate::ast_owned::{self, AstConverter};
+use crate::bytecode::Module;
+use crate::bytecode_gen::BytecodeGen;
+use crate::c::CCodeGen;
+use crate::cfg;
 use crate::codegen::CodeGen;
 use crate::compiler_context::CompilerContext;
+use crate::cst;
+use crate::dce::DeadCodeEliminator;
+use crate::diagnostics::{CompileError, CompileWarning, Diagnostic, LintLevels, Remark};
+use crate::features::{FeatureCollector, FeatureReport};
+use crate::host::{HermeticHost, Host};
+use crate::interp::{Interpreter, ReplBindings, RuntimeError};
+use crate::ir_lower::IrLowering;
+#[cfg(feature = "jit")]
+use crate::jit::Jit;
+use crate::layout::Layouts;
+use crate::limits::Limits;
+use crate::lint::Linter;
+use crate::llvm::LlvmCodeGen;
+use crate::object::ObjectFile;
 use crate::parser::Parser;
-use crate::scanner::Scanner;
+use crate::printer::SourcePrinter;
+use crate::profile::IntProfile;
+use crate::profiler::ExecutionProfile;
+use crate::resolve::{Resolution, Resolutions, Resolver};
+use crate::scanner::{BytePos, Scanner};
+use crate::source_map::{FileId, SourceMap};
+use crate::structure;
+use crate::timing::{self, Pass, PassTiming};
+use crate::typeck::TypeChecker;
+use crate::unused::UnusedChecker;
+use crate::vm::{ExecutionLimits, Vm};
+use crate::wasm::WasmCodeGen;
+
+#[derive(Default)]
+pub(crate) struct Options {
+    /// Whether the source code is a script, whose top-level statements make up
+    /// an implicit `main` function.
+    pub(crate) script_mode: bool,
+    /// What the compiler learns about the system from, which is a
+    /// `HermeticHost` if there's none.
+    pub(crate) host: Option<Arc<dyn Host>>,
+    pub(crate) emit: Emit,
+    pub(crate) limits: Limits,
+    /// Bounds on running the program, which the JIT doesn't enforce.
+    pub(crate) execution_limits: ExecutionLimits,
+    /// How integers behave, which only the C backend and the interpreter can
+    /// change.
+    pub(crate) int_profile: IntProfile,
+    /// Functions that the embedder of the VM provides, which only bytecode can
+    /// call.
+    pub(crate) host_functions: Vec<HostFnDecl>,
+    /// Lints whose warnings are left out, or fail the compilation.
+    pub(crate) lint_levels: LintLevels,
+    /// Lint levels of the files that have their own, e.g., from the
+    /// `sophia-lints.toml` files around them, which the warnings in those
+    /// files follow instead of `lint_levels`.
+    pub(crate) file_lint_levels: Vec<(FileId, LintLevels)>,
+    /// Where the interpreter prints what programs print, which is the
+    /// standard output if there's none.
+    pub(crate) output: Option<Rc<RefCell<dyn io::Write>>>,
+    /// Whether the interpreter records where the program spends its time.
+    pub(crate) profiling: bool,
+    /// Whether to make remarks about what the optimizations did.
+    pub(crate) remarks: bool,
+    /// Where the passes record how long they took, for `-Ztime-passes`, if
+    /// anywhere.
+    pub(crate) pass_timings: Option<Rc<RefCell<Vec<PassTiming>>>>,
+    /// Whether the program is built into an executable, or run, so that it
+    /// must have a `main` to start at. Checks, and code emitted to be linked
+    /// with other code, do without one.
+    pub(crate) requires_main: bool,
+}
+
+impl Options {
+    /// Lint levels that the warning follows, which are the ones of the file
+    /// it's in.
+    pub(crate) fn lint_levels_of(
+        &self,
+        warning: &CompileWarning,
+        source_map: &SourceMap,
+    ) -> &LintLevels {
+        let file_id = source_map.lookup_file(warning.span().start);
+
+        self.file_lint_levels
+            .iter()
+            .find(|(id, _)| *id == file_id)
+            .map_or(&self.lint_levels, |(_, lint_levels)| lint_levels)
+    }
+}
+
+/// What compiling a program generates.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum Emit {
+    /// x86-64 assembly, in Intel syntax.
+    #[default]
+    Assembly,
+    /// Textual LLVM IR.
+    LlvmIr,
+    /// WebAssembly module, in the text format.
+    Wat,
+    /// C99 source file.
+    C,
+    /// Sophia's own mid-level IR, for inspecting how programs are lowered.
+    Ir,
+    /// Control-flow graph of every function's IR, in the Graphviz dot
+    /// language.
+    Cfg,
+    /// Sophia source code of the program as the backends get it, i.e., after
+    /// it's transformed, e.g., by dead code elimination.
+    Source,
+    /// Tokens that the scanner produced, with their spans.
+    Tokens,
+    /// AST that the parser produced, as an indented tree.
+    Ast,
+    /// Concrete syntax tree of the source code, as an indented tree, which
+    /// keeps the whitespace and the comments that the AST leaves out.
+    Cst,
+}
+
+pub(crate) struct CompiledProgram {
+    /// Generated code, in the format given by `Options::emit`.
+    pub(crate) code: String,
+    pub(crate) warnings: Vec<CompileWarning>,
+    /// Remarks about the optimizations, if `Options::remarks` is set.
+    pub(crate) remarks: Vec<Remark>,
+    /// Whether the program calls functions of the C library, which native
+    /// executables are then linked with.
+    pub(crate) links_c_library: bool,
+}
+
+pub(crate) fn compile(
+    source_code: impl Into<SourceMap>,
+    options: &Options,
+) -> Result<CompiledProgram, Diagnostic> {
+    let backend = match options.emit {
+        Emit::Assembly => "x86-64",
+        Emit::LlvmIr => "LLVM IR",
+        Emit::Wat => "WebAssembly",
+        Emit::C => "C",
+        Emit::Ir => "IR",
+        Emit::Cfg => "CFG",
+        Emit::Source => "Sophia",
+        Emit::Tokens => "tokens",
+        Emit::Ast => "AST",
+        Emit::Cst => "CST",
+    };
+
+    // Source code can call host functions just fine, it's running it that can't.
+    if !matches!(
+        options.emit,
+        Emit::Source | Emit::Tokens | Emit::Ast | Emit::Cst
+    ) {
+        require_no_host_functions(options, backend)?;
+    }
+
+    if matches!(options.emit, Emit::Assembly | Emit::LlvmIr | Emit::Wat) {
+        require_default_int_profile(options, backend)?;
+    }
+
+    let context = new_context(source_code, options);
+
+    // Dumps of the front end are of whatever it produced, so that they can be
+    // made for programs that the analyses reject.
+    if options.emit == Emit::Tokens {
+        return Ok(CompiledProgram::of_front_end(structure::render_tokens(
+            &context,
+        )));
+    }
+
+    if options.emit == Emit::Cst {
+        return Ok(CompiledProgram::of_front_end(structure::render_cst(
+            &cst::parse_file(context.get_source_code()),
+        )));
+    }
+
+    let program = parse(&context, options)?;
+
+    if options.emit == Emit::Ast {
+        return Ok(CompiledProgram::of_front_end(structure::render_ast(
+            &context, program,
+        )));
+    }
+
+    let analysis = analyze(&context, program, options)?;
+
+    if options.emit != Emit::Source {
+        require_supported_values(program, &analysis.resolutions, backend)?;
+    }
+
+    let (program, remarks) = eliminate_dead_code(&context, program, options);
+
+    let links_c_library = program
+        .decls
+        .iter()
+        .any(|decl| matches!(decl.value, Expr::Extern(_)));
+
+    let code = time_pass(&context, options, Pass::Codegen, || match options.emit {
+        Emit::Assembly => {
+            let mut codegen = CodeGen::new(&context, &analysis.resolutions);
+            let x86_program = codegen.gen_program(program);
+
+            format!("{}", x86_program)
+        }
+        Emit::LlvmIr => LlvmCodeGen::new(&context, &analysis.resolutions).gen_module(program),
+        Emit::Wat => WasmCodeGen::new(&context, &analysis.resolutions).gen_module(program),
+        Emit::C => CCodeGen::new(&context, &analysis.resolutions, options.int_profile)
+            .gen_source_file(program),
+        Emit::Ir => IrLowering::new(&context, &analysis.resolutions, &options.host_functions)
+            .lower_program(program)
+            .to_string(),
+        Emit::Cfg => cfg::to_dot(
+            &IrLowering::new(&context, &analysis.resolutions, &options.host_functions)
+                .lower_program(program),
+        ),
+        Emit::Source => SourcePrinter::new(&context).print_program(program),
+        Emit::Tokens | Emit::Ast | Emit::Cst => unreachable!("the front end was dumped already"),
+    });
+
+    Ok(CompiledProgram {
+        code,
+        warnings: analysis.warnings,
+        remarks,
+        links_c_library,
+    })
+}
+
+impl CompiledProgram {
+    /// Dump of what the front end produced, which nothing was analyzed for.
+    fn of_front_end(code: String) -> CompiledProgram {
+        CompiledProgram {
+            code,
+            warnings: vec![],
+            remarks: vec![],
+            links_c_library: false,
+        }
+    }
+}
+
+pub(crate) struct CompiledBytecode {
+    pub(crate) module: Module,
+    pub(crate) warnings: Vec<CompileWarning>,
+    /// Remarks about the optimizations, if `Options::remarks` is set.
+    pub(crate) remarks: Vec<Remark>,
+}
+
+/// Compiles the program to a bytecode module, which is saved to disk to be run
+/// later by the `Vm`, without compiling the source code again.
+pub(crate) fn compile_bytecode(
+    source_code: impl Into<SourceMap>,
+    options: &Options,
+) -> Result<CompiledBytecode, Diagnostic> {
+    require_default_int_profile(options, "bytecode")?;
+
+    let context = new_context(source_code, options);
+
+    let program = parse(&context, options)?;
+
+    let analysis = analyze(&context, program, options)?;
+
+    require_supported_values(program, &analysis.resolutions, "bytecode")?;
+
+    let (program, remarks) = eliminate_dead_code(&context, program, options);
+
+    let module = time_pass(&context, options, Pass::Codegen, || {
+        BytecodeGen::new(&context, &analysis.resolutions, &options.host_functions)
+            .gen_module(program)
+    });
+
+    Ok(CompiledBytecode {
+        module,
+        warnings: analysis.warnings,
+        remarks,
+    })
+}
+
+pub(crate) struct CompiledObject {
+    pub(crate) object: ObjectFile,
+    pub(crate) warnings: Vec<CompileWarning>,
+    /// Remarks about the optimizations, if `Options::remarks` is set.
+    pub(crate) remarks: Vec<Remark>,
+}
+
+/// Compiles the program to x86-64 machine code, in an object file that's linked
+/// with the runtime library into an executable.
+pub(crate) fn compile_object(
+    source_code: impl Into<SourceMap>,
+    options: &Options,
+) -> Result<CompiledObject, Diagnostic> {
+    require_no_host_functions(options, "x86-64")?;
+    require_default_int_profile(options, "x86-64")?;
+
+    let context = new_context(source_code, options);
+
+    let program = parse(&context, options)?;
+
+    let analysis = analyze(&context, program, options)?;
+
+    require_supported_values(program, &analysis.resolutions, "x86-64")?;
+
+    let (program, remarks) = eliminate_dead_code(&context, program, options);
+
+    let object = time_pass(&context, options, Pass::Codegen, || {
+        CodeGen::new(&context, &analysis.resolutions)
+            .gen_program(program)
+            .encode()
+    });
+
+    Ok(CompiledObject {
+        object,
+        warnings: analysis.warnings,
+        remarks,
+    })
+}
+
+pub(crate) struct Execution {
+    /// Value returned by `main`, or zero if it returns `()`.
+    pub(crate) exit_code: Result<i32, RuntimeError>,
+    /// Where the program spent its time, if `Options::profiling` is set and it
+    /// was interpreted.
+    pub(crate) profile: Option<ExecutionProfile>,
+    pub(crate) warnings: Vec<CompileWarning>,
+    /// Remarks about the optimizations, if `Options::remarks` is set.
+    pub(crate) remarks: Vec<Remark>,
+}
+
+/// Interprets the program instead of compiling it to native code.
+pub(crate) fn run(
+    source_code: impl Into<SourceMap>,
+    options: &Options,
+) -> Result<Execution, Diagnostic> {
+    require_no_host_functions(options, "interpreter")?;
+
+    let context = new_context(source_code, options);
+
+    let program = parse(&context, options)?;
+
+    interpret(&context, program, options)
+}
+
+fn interpret(
+    context: &CompilerContext,
+    program: Program,
+    options: &Options,
+) -> Result<Execution, Diagnostic> {
+    let analysis = analyze(context, program, options)?;
+
+    let (program, remarks) = eliminate_dead_code(context, program, options);
+
+    let output = options
+        .output
+        .clone()
+        .unwrap_or_else(|| Rc::new(RefCell::new(io::stdout())));
+    let interpreter = Interpreter::new(context, &analysis.resolutions, options.int_profile, output)
+        .with_limits(options.execution_limits);
+    let (exit_code, profile) = if options.profiling {
+        let (exit_code, profile) = interpreter.run_program_profiled(program);
+
+        (exit_code, Some(profile))
+    } else {
+        (interpreter.run_program(program), None)
+    };
+
+    Ok(Execution {
+        exit_code,
+        profile,
+        warnings: analysis.warnings,
+        remarks,
+    })
+}
+
+/// Compiles the program to bytecode, then runs it on the `Vm`, which is faster
+/// than interpreting it, as the analyses and the lookups of bindings are done
+/// once, ahead of time, rather than every time the code runs.
+pub(crate) fn run_vm(
+    source_code: impl Into<SourceMap>,
+    options: &Options,
+) -> Result<Execution, Diagnostic> {
+    require_no_host_functions(options, "VM")?;
+
+    let compiled_bytecode = compile_bytecode(source_code, options)?;
+
+    let mut vm = Vm::new(compiled_bytecode.module);
+    vm.set_limits(options.execution_limits);
+
+    if let Some(output) = &options.output {
+        vm.print_to(output.clone());
+    }
+
+    Ok(Execution {
+        exit_code: vm.run_main(),
+        profile: None,
+        warnings: compiled_bytecode.warnings,
+        remarks: compiled_bytecode.remarks,
+    })
+}
+
+/// Compiles the program to machine code in-process, then runs it.
+#[cfg(feature = "jit")]
+pub(crate) fn run_jit(
+    source_code: impl Into<SourceMap>,
+    options: &Options,
+) -> Result<Execution, Diagnostic> {
+    require_no_host_functions(options, "JIT")?;
+    require_default_int_profile(options, "JIT")?;
+
+    let context = new_context(source_code, options);
+
+    let program = parse(&context, options)?;
+
+    let analysis = analyze(&context, program, options)?;
+
+    require_supported_values(program, &analysis.resolutions, "JIT")?;
+
+    let (program, remarks) = eliminate_dead_code(&context, program, options);
+
+    let exit_code = match Jit::new(&context, &analysis.resolutions) {
+        Ok(jit) => jit.run_program(program),
+        Err(error) => Err(error),
+    };
+
+    Ok(Execution {
+        exit_code,
+        profile: None,
+        warnings: analysis.warnings,
+        remarks,
+    })
+}
+
+/// Runs every analysis on the program without generating code, collecting all
+/// errors and warnings found.
+pub(crate) fn check(source_code: impl Into<SourceMap>, options: &Options) -> Diagnostic {
+    let context = new_context(source_code, options);
+
+    match parse(&context, options) {
+        Ok(program) => check_program(&context, program, options),
+        Err(diagnostic) => diagnostic,
+    }
+}
+
+/// Checks a doc example, which is the last file of the source code, like
+/// `check` does, see `parse_doc_example`.
+pub(crate) fn check_doc_example(
+    source_code: impl Into<SourceMap>,
+    options: &Options,
+) -> Diagnostic {
+    let source_map = source_code.into();
+    let example_start = last_file_start(&source_map);
+    let context = new_context(source_map, options);
+
+    match parse_doc_example(&context, example_start, options) {
+        Ok(program) => check_program(&context, program, options),
+        Err(diagnostic) => diagnostic,
+    }
+}
+
+/// Interprets a doc example, which is the last file of the source code, like
+/// `run` does, see `parse_doc_example`.
+pub(crate) fn run_doc_example(
+    source_code: impl Into<SourceMap>,
+    options: &Options,
+) -> Result<Execution, Diagnostic> {
+    let source_map = source_code.into();
+    let example_start = last_file_start(&source_map);
+    let context = new_context(source_map, options);
+
+    let program = parse_doc_example(&context, example_start, options)?;
+
+    interpret(&context, program, options)
+}
+
+/// What evaluating an input of the REPL produced.
+pub(crate) struct ReplEvaluation {
+    /// Value of the expression that the input ends with, unless it's `()`,
+    /// rendered as text.
+    pub(crate) value: Result<Option<String>, RuntimeError>,
+    pub(crate) warnings: Vec<CompileWarning>,
+}
+
+/// Evaluates the input of a REPL, which is the last file of the context's
+/// source code, after every input before it. The inputs make up a script,
+/// which is analyzed as a whole, but only the input's statements are run,
+/// starting with the bindings that the ones before it defined.
+pub(crate) fn eval_repl_input(
+    context: &CompilerContext,
+    bindings: &mut ReplBindings,
+    options: &Options,
+) -> Result<ReplEvaluation, Diagnostic> {
+    require_no_host_functions(options, "REPL")?;
+
+    let input_start = last_file_start(context.source_map());
+
+    let source_code = context.get_source_code();
+    check_source_size(source_code, &options.limits)?;
+
+    // Inputs are parsed apart, so that the statement an input ends with
+    // doesn't run into the next input, e.g., into a call of its value.
+    let mut decls = vec![];
+    let mut parsed_stmts = vec![];
+
+    for file_id in context.source_map().file_ids() {
+        let span = context.source_map().file_span(file_id);
+        let scanner = Scanner::for_text(&source_code[span.start.0..span.end.0], span.start);
+        let (input, input_stmts) =
+            Parser::new(scanner, context, options.limits).parse_repl_input()?;
+
+        decls.extend_from_slice(input.decls);
+
+        // Bindings ended with a `;` are bindings of the session all the same.
+        parsed_stmts.extend(input_stmts.iter().map(|&stmt| match stmt {
+            Expr::Semi(binding @ (Expr::BindDef(_) | Expr::TupleBindDef(_))) => *binding,
+            _ => stmt,
+        }));
+    }
+
+    let main_identifier = context.symbols().main;
+
+    if let (Some(main_decl), Some(first_stmt)) = (
+        decls.iter().find(|decl| decl.identifier == main_identifier),
+        parsed_stmts.first(),
+    ) {
+        return Err(Diagnostic::from_errors(vec![
+            CompileError::ScriptStatementsWithMain {
+                main_span: main_decl.identifier_span,
+                stmt_span: first_stmt.span(),
+            },
+        ]));
+    }
+
+    // Statements that aren't bindings are bound to `_`, so that `main` type
+    // checks whatever their types are, as the value of the one that the input
+    // ends with is shown.
+    let discard_identifier = context.symbols().discard;
+    let stmts: Vec<_> = parsed_stmts
+        .iter()
+        .map(|&stmt| match stmt {
+            Expr::BindDef(_) | Expr::TupleBindDef(_) => stmt,
+            _ => Expr::BindDef(BindDef {
+                identifier: discard_identifier,
+                ty: None,
+                value: context.alloc_expr(stmt),
+                span: stmt.span(),
+            }),
+        })
+        .collect();
+    let shows_value = parsed_stmts.last().is_some_and(|last_stmt| {
+        last_stmt.span().start.0 >= input_start.0
+            && !matches!(last_stmt, Expr::BindDef(_) | Expr::TupleBindDef(_))
+    });
+    let stmts = context.alloc_slice_of_expr(&stmts);
+
+    if let (Some(first_stmt), Some(last_stmt)) = (stmts.first(), stmts.last()) {
+        let stmts_span = first_stmt.span().to(last_stmt.span());
 
-pub(crate) fn compile(source_code: &str) -> String {
-    // FIXME: don't copy source code, move it.
-    let context = CompilerContext::new(source_code.into());
+        decls.push(Decl {
+            identifier: main_identifier,
+            identifier_span: first_stmt.span(),
+            value: context.alloc_expr(Expr::Function(Function {
+                return_type: Type::Unit,
+                parameters: context.alloc_slice_of_param(&[]),
+                body: CompoundExpr {
+                    exprs: stmts,
+                    span: stmts_span,
+                },
+                span: stmts_span,
+            })),
+        });
+    }
 
-    let tokens = {
-        let mut scanner = Scanner::new(&context);
-        scanner.scan_all_tokens()
+    let program = Program {
+        decls: context.alloc_slice_of_decl(&decls),
     };
 
-    let mut parser = Parser::new(tokens, &context);
-    let program = parser.parse_program().unwrap();
+    let mut analysis = analyze(context, program, options)?;
+
+    // Warnings about earlier inputs were shown when they were evaluated.
+    analysis
+        .warnings
+        .retain(|warning| warning.span().start.0 >= input_start.0);
+
+    let earlier_stmt_count = stmts
+        .iter()
+        .take_while(|stmt| stmt.span().start.0 < input_start.0)
+        .count();
+    let input_stmts = &stmts[earlier_stmt_count..];
+    let value = if input_stmts.is_empty() {
+        // Declarations are only checked.
+        Ok(None)
+    } else {
+        let output = options
+            .output
+            .clone()
+            .unwrap_or_else(|| Rc::new(RefCell::new(io::stdout())));
+
+        Interpreter::new(context, &analysis.resolutions, options.int_profile, output)
+            .with_limits(options.execution_limits)
+            .run_repl_input(program, input_stmts, bindings, shows_value)
+    };
+
+    Ok(ReplEvaluation {
+        value,
+        warnings: analysis.warnings,
+    })
+}
+
+fn last_file_start(source_map: &SourceMap) -> BytePos {
+    let file_id = source_map
+        .file_ids()
+        .last()
+        .expect("the source map has a file");
+
+    source_map.file_span(file_id).start
+}
+
+/// Parses the source code from the example's start as a script that can use
+/// the declarations of the program before it. The example's `main`, if it has
+/// statements or declares one, replaces the program's.
+fn parse_doc_example<'ctx>(
+    context: &'ctx CompilerContext,
+    example_start: BytePos,
+    options: &Options,
+) -> Result<Program<'ctx>, Diagnostic> {
+    let source_code = context.get_source_code();
+    check_source_size(source_code, &options.limits)?;
+
+    let program_scanner = Scanner::for_text(&source_code[..example_start.0], BytePos(0));
+    let example_scanner = Scanner::for_text(&source_code[example_start.0..], example_start);
+
+    let program = Parser::new(program_scanner, context, options.limits).parse_program()?;
+    let example = Parser::new(example_scanner, context, options.limits).parse_script()?;
+
+    let main_identifier = context.symbols().main;
+    let example_has_main = example
+        .decls
+        .iter()
+        .any(|decl| decl.identifier == main_identifier);
+    let decls: Vec<_> = program
+        .decls
+        .iter()
+        .filter(|decl| !(example_has_main && decl.identifier == main_identifier))
+        .chain(example.decls)
+        .copied()
+        .collect();
+
+    Ok(Program {
+        decls: context.alloc_slice_of_decl(&decls),
+    })
+}
+
+/// Analyzes a program that's parsed already, e.g., by an `IncrementalParse`,
+/// the way `check` does.
+pub(crate) fn check_program(
+    context: &CompilerContext,
+    program: Program,
+    options: &Options,
+) -> Diagnostic {
+    match analyze(context, program, options) {
+        Ok(analysis) => Diagnostic {
+            errors: vec![],
+            warnings: analysis.warnings,
+        },
+        Err(diagnostic) => diagnostic,
+    }
+}
+
+/// Lists the language features the program uses, for reviewers to see what a
+/// program relies on without reading all of it.
+pub(crate) fn report_features(
+    source_code: impl Into<SourceMap>,
+    options: &Options,
+) -> Result<(FeatureReport, Vec<CompileWarning>), Diagnostic> {
+    let context = new_context(source_code, options);
+
+    let program = parse(&context, options)?;
+    let analysis = analyze(&context, program, options)?;
+    let report = FeatureCollector::new(&context, &analysis.resolutions).collect_program(program);
+
+    Ok((report, analysis.warnings))
+}
+
+/// Stage of the compiler that transforms the program before the backends get
+/// it, after which it can be printed back to source code.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Stage {
+    /// The program as parsed, which is already free of sugar, as Sophia has
+    /// no syntax that the parser doesn't build the AST for directly.
+    Parse,
+    DeadCodeElimination,
+}
+
+impl Stage {
+    pub(crate) const ALL: [Stage; 2] = [Stage::Parse, Stage::DeadCodeElimination];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Stage::Parse => "parse",
+            Stage::DeadCodeElimination => "dce",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Stage> {
+        Stage::ALL.into_iter().find(|stage| stage.name() == name)
+    }
+}
+
+/// Source code of the program after a stage of the compiler.
+pub(crate) struct Expansion {
+    pub(crate) stage: Stage,
+    pub(crate) source_code: String,
+}
+
+/// Prints the program back to source code after each stage that transforms
+/// it, in the order of `Stage::ALL`, for people to see what the compiler makes
+/// of their program.
+pub(crate) fn expand(
+    source_code: impl Into<SourceMap>,
+    options: &Options,
+) -> Result<(Vec<Expansion>, Vec<CompileWarning>), Diagnostic> {
+    let context = new_context(source_code, options);
+
+    let mut program = parse(&context, options)?;
+    let analysis = analyze(&context, program, options)?;
+    let mut expansions = vec![];
+
+    // Every stage transforms the program as the stage before it left it.
+    for stage in Stage::ALL {
+        program = match stage {
+            Stage::Parse => program,
+            Stage::DeadCodeElimination => {
+                DeadCodeEliminator::new(&context).eliminate_program(program)
+            }
+        };
+
+        expansions.push(Expansion {
+            stage,
+            source_code: SourcePrinter::new(&context).print_program(program),
+        });
+    }
+
+    Ok((expansions, analysis.warnings))
+}
+
+fn require_default_int_profile(options: &Options, backend: &'static str) -> Result<(), Diagnostic> {
+    if options.int_profile == IntProfile::default() {
+        Ok(())
+    } else {
+        Err(Diagnostic::from_errors(vec![
+            CompileError::IntProfileUnsupported { backend },
+        ]))
+    }
+}
+
+/// Requires the program to have the one `main` that executables start at,
+/// which is a function that takes nothing and returns `i32` or `()`.
+fn require_main(context: &CompilerContext, program: Program) -> Result<(), Diagnostic> {
+    let main_identifier = context.symbols().main;
+    let main_decls: Vec<_> = program
+        .decls
+        .iter()
+        .filter(|decl| decl.identifier == main_identifier)
+        .collect();
+
+    let error = match main_decls[..] {
+        [] => CompileError::MissingMain {
+            functions: program
+                .decls
+                .iter()
+                .filter(|decl| matches!(decl.value, Expr::Function(_)))
+                .map(|decl| context.resolve_symbol(decl.identifier).to_owned())
+                .collect(),
+        },
+        [main_decl] => {
+            let found = match main_decl.value {
+                Expr::Function(function)
+                    if function.parameters.is_empty()
+                        && matches!(function.return_type, Type::I32 | Type::Unit) =>
+                {
+                    return Ok(());
+                }
+                Expr::Function(function) => {
+                    let param_types: Vec<_> =
+                        function.parameters.iter().map(|param| param.ty).collect();
+                    let ty = Type::Function {
+                        params: context.alloc_type_list(&param_types),
+                        return_type: &context.alloc_type_list(&[function.return_type])[0],
+                    };
+
+                    format!("a function of type `{}`", ty)
+                }
+                Expr::Extern(_) => "an extern function".to_owned(),
+                Expr::Struct(_) => "a struct".to_owned(),
+                Expr::Enum(_) => "an enum".to_owned(),
+                _ => "a value that isn't a function".to_owned(),
+            };
+
+            CompileError::InvalidMain {
+                found,
+                span: main_decl.identifier_span,
+            }
+        }
+        [first_decl, second_decl, ..] => CompileError::DuplicateMain {
+            first_span: first_decl.identifier_span,
+            span: second_decl.identifier_span,
+        },
+    };
+
+    Err(Diagnostic::from_errors(vec![error]))
+}
+
+fn require_no_host_functions(options: &Options, backend: &'static str) -> Result<(), Diagnostic> {
+    if options.host_functions.is_empty() {
+        Ok(())
+    } else {
+        Err(Diagnostic::from_errors(vec![
+            CompileError::HostFunctionsUnsupported { backend },
+        ]))
+    }
+}
+
+/// Fails if the program declares structs or enums, or uses tuples, arrays,
+/// integers of types other than `i32`, function values, or values of loops,
+/// which only the interpreter runs so far.
+/// It's checked after the program is analyzed, so that its errors are reported
+/// first.
+fn require_supported_values(
+    program: Program,
+    resolutions: &Resolutions,
+    backend: &'static str,
+) -> Result<(), Diagnostic> {
+    for decl in program.decls {
+        let decl_uses = |kind| match decl.value {
+            Expr::Function(function) => function_uses(kind, resolutions, function),
+            expr => uses(kind, resolutions, expr),
+        };
+
+        let error = match decl.value {
+            Expr::Struct(_) => CompileError::StructsUnsupported { backend },
+            Expr::Enum(_) => CompileError::EnumsUnsupported { backend },
+            _ if decl_uses(ValueKind::Tuple) => CompileError::TuplesUnsupported { backend },
+            _ if decl_uses(ValueKind::Array) => CompileError::ArraysUnsupported { backend },
+            _ if decl_uses(ValueKind::OtherInt) => CompileError::IntTypesUnsupported { backend },
+            _ if decl_uses(ValueKind::Function) => {
+                CompileError::FunctionValuesUnsupported { backend }
+            }
+            _ if decl_uses(ValueKind::Loop) => CompileError::LoopValuesUnsupported { backend },
+            _ => continue,
+        };
+
+        return Err(Diagnostic::from_errors(vec![error]));
+    }
+
+    Ok(())
+}
+
+/// Values that aren't declared, unlike structs and enums, but come about
+/// wherever they're made.
+#[derive(Clone, Copy, PartialEq)]
+enum ValueKind {
+    Tuple,
+    Array,
+    /// Integers of types other than `i32`.
+    OtherInt,
+    /// Functions used as values rather than called by name.
+    Function,
+    /// Values that `break`s carry out of loops.
+    Loop,
+}
+
+/// Whether the function takes or returns values of the kind, or makes them.
+fn function_uses(kind: ValueKind, resolutions: &Resolutions, function: &Function) -> bool {
+    type_has(kind, function.return_type)
+        || function
+            .parameters
+            .iter()
+            .any(|param| type_has(kind, param.ty))
+        || function
+            .body
+            .exprs
+            .iter()
+            .any(|expr| uses(kind, resolutions, expr))
+}
+
+/// Whether the expression makes values of the kind, or is a function that
+/// takes or returns them, which are the only ways that they come about.
+fn uses(kind: ValueKind, resolutions: &Resolutions, expr: &Expr) -> bool {
+    let uses_any = |exprs: &[Expr]| exprs.iter().any(|expr| uses(kind, resolutions, expr));
+
+    match expr {
+        Expr::Tuple(tuple_expr) => kind == ValueKind::Tuple || uses_any(tuple_expr.elements),
+        Expr::Array(array_expr) => kind == ValueKind::Array || uses_any(array_expr.elements),
+        Expr::Const(const_expr) => {
+            kind == ValueKind::OtherInt
+                && matches!(const_expr.value, Const::SuffixedIntegerConstant { .. })
+        }
+        // Names of functions are the only names that refer to declarations
+        // where values are expected.
+        Expr::BindRef(bind_ref) => {
+            kind == ValueKind::Function
+                && matches!(
+                    resolutions.get(bind_ref.span),
+                    Some(Resolution::Decl(_) | Resolution::Host(_) | Resolution::Builtin(_))
+                )
+        }
+        Expr::Break(break_expr) => break_expr
+            .value
+            .is_some_and(|value| kind == ValueKind::Loop || uses(kind, resolutions, value)),
+        Expr::Continue(_) | Expr::Struct(_) | Expr::Enum(_) => false,
+        Expr::Extern(extern_fn) => {
+            type_has(kind, extern_fn.return_type)
+                || extern_fn
+                    .parameters
+                    .iter()
+                    .any(|param| type_has(kind, param.ty))
+        }
+        // Functions within functions are closures, which are function values.
+        Expr::Function(function) => {
+            kind == ValueKind::Function || function_uses(kind, resolutions, function)
+        }
+        Expr::BindDef(bind_def) => {
+            bind_def.ty.is_some_and(|ty| type_has(kind, ty))
+                || uses(kind, resolutions, bind_def.value)
+        }
+        Expr::TupleBindDef(TupleBindDef { value, .. })
+        | Expr::Field(FieldExpr { base: value, .. })
+        | Expr::TupleField(TupleFieldExpr { base: value, .. })
+        | Expr::Semi(value)
+        | Expr::Paren(ParenExpr { expr: value, .. }) => uses(kind, resolutions, value),
+        Expr::Index(index_expr) => {
+            uses(kind, resolutions, index_expr.base) || uses(kind, resolutions, index_expr.index)
+        }
+        Expr::IndexAssign(index_assign_expr) => {
+            uses(kind, resolutions, index_assign_expr.target.base)
+                || uses(kind, resolutions, index_assign_expr.target.index)
+                || uses(kind, resolutions, index_assign_expr.value)
+        }
+        Expr::StructLit(struct_lit_expr) => struct_lit_expr
+            .fields
+            .iter()
+            .any(|field| uses(kind, resolutions, field.value)),
+        Expr::Variant(variant_expr) => variant_expr
+            .payload
+            .is_some_and(|payload| uses(kind, resolutions, payload)),
+        Expr::If(if_expr) => {
+            uses(kind, resolutions, if_expr.cond_expr)
+                || uses_any(if_expr.true_branch.exprs)
+                || if_expr.else_if_branches.iter().any(|branch| {
+                    uses(kind, resolutions, branch.cond_expr) || uses_any(branch.true_branch.exprs)
+                })
+                || if_expr
+                    .final_branch
+                    .is_some_and(|branch| uses_any(branch.exprs))
+        }
+        Expr::Match(match_expr) => {
+            uses(kind, resolutions, match_expr.scrutinee)
+                || match_expr
+                    .arms
+                    .iter()
+                    .any(|arm| uses(kind, resolutions, arm.expr))
+        }
+        Expr::For(for_expr) => {
+            let iteration_uses = match for_expr.iteration {
+                Some(ForIteration::Conditional { cond_expr }) => uses(kind, resolutions, cond_expr),
+                Some(ForIteration::Iterative {
+                    start_expr,
+                    end_expr,
+                    step_expr,
+                    ..
+                }) => {
+                    uses(kind, resolutions, start_expr)
+                        || uses(kind, resolutions, end_expr)
+                        || step_expr.is_some_and(|step_expr| uses(kind, resolutions, step_expr))
+                }
+                Some(ForIteration::InCollection {
+                    collection_expr, ..
+                }) => uses(kind, resolutions, collection_expr),
+                None => false,
+            };
+
+            iteration_uses
+                || uses_any(for_expr.body.exprs)
+                || for_expr
+                    .else_branch
+                    .is_some_and(|branch| uses_any(branch.exprs))
+        }
+        Expr::Compound(compound_expr) => uses_any(compound_expr.exprs),
+        Expr::FnCall(fn_call_expr) => uses_any(fn_call_expr.args),
+        Expr::Binary(binary_expr) => {
+            let (operand, operations) = binary_expr.chain();
+
+            uses(kind, resolutions, operand)
+                || operations
+                    .iter()
+                    .any(|binary_expr| uses(kind, resolutions, binary_expr.rhs))
+        }
+        Expr::Cast(cast_expr) => {
+            type_has(kind, cast_expr.ty) || uses(kind, resolutions, cast_expr.expr)
+        }
+    }
+}
+
+/// Whether values of the type are, or have elements that are, of the kind.
+fn type_has(kind: ValueKind, ty: Type) -> bool {
+    match ty {
+        Type::Unit | Type::I32 | Type::Named(_) => false,
+        Type::I8
+        | Type::I16
+        | Type::I64
+        | Type::U8
+        | Type::U16
+        | Type::U32
+        | Type::U64
+        | Type::Usize => kind == ValueKind::OtherInt,
+        Type::Tuple(element_types) => {
+            kind == ValueKind::Tuple
+                || element_types
+                    .iter()
+                    .any(|&element_ty| type_has(kind, element_ty))
+        }
+        Type::Array { element, .. } => kind == ValueKind::Array || type_has(kind, *element),
+        Type::Function {
+            params,
+            return_type,
+        } => {
+            kind == ValueKind::Function
+                || params.iter().any(|&param_ty| type_has(kind, param_ty))
+                || type_has(kind, *return_type)
+        }
+    }
+}
+
+pub(crate) fn new_context(source_code: impl Into<SourceMap>, options: &Options) -> CompilerContext {
+    match &options.host {
+        Some(host) => CompilerContext::with_source_map(source_code.into(), &**host),
+        None => CompilerContext::with_source_map(source_code.into(), &HermeticHost::default()),
+    }
+}
+
+/// Parses a program into a syntax tree that outlives the compilation.
+pub(crate) fn parse_owned(
+    source_code: impl Into<SourceMap>,
+    options: &Options,
+) -> Result<ast_owned::Program, Diagnostic> {
+    let source_map = source_code.into();
+    let context = new_context(source_map.clone(), options);
+    let program = parse(&context, options)?;
+
+    Ok(AstConverter::new(&context, &source_map).convert_program(program))
+}
+
+/// Parses a single expression, rather than a program, into a syntax tree that
+/// outlives the compilation.
+pub(crate) fn parse_owned_expr(
+    source_code: impl Into<SourceMap>,
+    options: &Options,
+) -> Result<ast_owned::Expr, Diagnostic> {
+    let source_map = source_code.into();
+    let context = new_context(source_map.clone(), options);
+    check_source_size(context.get_source_code(), &options.limits)?;
+
+    let expr =
+        Parser::new(Scanner::new(&context), &context, options.limits).parse_expr_fragment()?;
+
+    Ok(AstConverter::new(&context, &source_map).convert_expr(&expr))
+}
+
+/// Parses a single declaration, rather than a program, into a syntax tree
+/// that outlives the compilation.
+pub(crate) fn parse_owned_decl(
+    source_code: impl Into<SourceMap>,
+    options: &Options,
+) -> Result<ast_owned::Decl, Diagnostic> {
+    let source_map = source_code.into();
+    let context = new_context(source_map.clone(), options);
+    check_source_size(context.get_source_code(), &options.limits)?;
+
+    let decl =
+        Parser::new(Scanner::new(&context), &context, options.limits).parse_decl_fragment()?;
+
+    Ok(AstConverter::new(&context, &source_map).convert_decl(&decl))
+}
+
+pub(crate) fn parse<'ctx>(
+    context: &'ctx CompilerContext,
+    options: &Options,
+) -> Result<Program<'ctx>, Diagnostic> {
+    check_source_size(context.get_source_code(), &options.limits)?;
+
+    if options.pass_timings.is_some() {
+        time_pass(context, options, Pass::Lex, || {
+            Scanner::new(context).count()
+        });
+    }
+
+    let mut parser = Parser::new(Scanner::new(context), context, options.limits);
+
+    time_pass(context, options, Pass::Parse, || {
+        if options.script_mode {
+            parser.parse_script()
+        } else {
+            parser.parse_program()
+        }
+    })
+}
+
+pub(crate) fn check_source_size(source_code: &str, limits: &Limits) -> Result<(), Diagnostic> {
+    if source_code.len() > limits.max_source_bytes {
+        return Err(Diagnostic::from_errors(vec![
+            CompileError::SourceTooLarge {
+                size: source_code.len(),
+                limit: limits.max_source_bytes,
+            },
+        ]));
+    }
+
+    Ok(())
+}
+
+/// Eliminates dead code, which is the only optimization so far, making
+/// remarks about it if `Options::remarks` is set.
+fn eliminate_dead_code<'ctx>(
+    context: &'ctx CompilerContext,
+    program: Program<'ctx>,
+    options: &Options,
+) -> (Program<'ctx>, Vec<Remark>) {
+    let eliminator = DeadCodeEliminator::new(context);
+    let program = time_pass(context, options, Pass::Optimize, || {
+        eliminator.eliminate_program(program)
+    });
+    let remarks = if options.remarks {
+        eliminator.into_remarks()
+    } else {
+        vec![]
+    };
+
+    (program, remarks)
+}
+
+/// Runs the pass, recording how long it took if `Options::pass_timings` is
+/// set.
+fn time_pass<T>(
+    context: &CompilerContext,
+    options: &Options,
+    pass: Pass,
+    run: impl FnOnce() -> T,
+) -> T {
+    timing::time_pass(options.pass_timings.as_deref(), context, pass, run)
+}
+
+/// Leaves out the warnings of allowed lints, returning whether any of the
+/// others are of denied lints.
+pub(crate) fn apply_lint_levels(
+    warnings: &mut Vec<CompileWarning>,
+    source_map: &SourceMap,
+    options: &Options,
+) -> bool {
+    warnings.retain(|warning| {
+        let lint_levels = options.lint_levels_of(warning, source_map);
+
+        warning
+            .lint()
+            .is_none_or(|lint| !lint_levels.is_allowed(lint))
+    });
+
+    // Warnings of denied lints stop the compilation too, even though they're
+    // kept among the warnings rather than the errors.
+    warnings
+        .iter()
+        .any(|warning| options.lint_levels_of(warning, source_map).denies(warning))
+}
+
+/// Results of analyzing a program free of errors.
+struct Analysis {
+    resolutions: Resolutions,
+    warnings: Vec<CompileWarning>,
+}
+
+fn analyze(
+    context: &CompilerContext,
+    program: Program,
+    options: &Options,
+) -> Result<Analysis, Diagnostic> {
+    if options.requires_main {
+        require_main(context, program)?;
+    }
+
+    let resolutions = time_pass(context, options, Pass::Resolve, || {
+        Resolver::new(context, &options.host_functions).resolve_program(program)
+    })
+    .map_err(Diagnostic::from_errors)?;
+
+    let layouts = time_pass(context, options, Pass::Layout, || {
+        Layouts::of_program(context, program, options.int_profile.width)
+    })
+    .map_err(Diagnostic::from_errors)?;
+
+    let mut diagnostic = time_pass(context, options, Pass::Typecheck, || {
+        TypeChecker::new(
+            context,
+            &resolutions,
+            &layouts,
+            options.int_profile.width,
+            &options.host_functions,
+        )
+        .check_program(program)
+    });
+
+    let limit = options.limits.max_decls_to_lint;
+
+    let lint_warnings = time_pass(context, options, Pass::Lint, || {
+        match program.decls.get(limit) {
+            Some(first_unlinted_decl) => vec![CompileWarning::LintsSkipped {
+                decl_count: program.decls.len(),
+                limit,
+                span: first_unlinted_decl.identifier_span,
+            }],
+            None => {
+                let mut lint_warnings = Linter::new(context, &resolutions).lint_program(program);
+                lint_warnings
+                    .extend(UnusedChecker::new(context, &resolutions).check_program(program));
+
+                lint_warnings
+            }
+        }
+    });
+
+    diagnostic.warnings.splice(0..0, lint_warnings);
+    let has_denied_warnings =
+        apply_lint_levels(&mut diagnostic.warnings, context.source_map(), options);
 
-    let mut codegen = CodeGen::new(&context);
-    let x86_program = codegen.gen_program(program);
+    if diagnostic.has_errors() || has_denied_warnings {
+        return Err(diagnostic);
+    }
 
-    format!("{}", x86_program)
+    Ok(Analysis {
+        resolutions,
+        warnings: diagnostic.warnings,
+    })
 }
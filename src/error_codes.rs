@@ -0,0 +1,1008 @@
+/// Long-form explanation of each error, by its code, for `sophia --explain`.
+/// Codes never change meaning, and the codes of errors that are gone stay
+/// taken, so that what people found about an error keeps being about it.
+pub(crate) const EXPLANATIONS: [(&str, &str); 54] = [
+    (
+        "E0001",
+        "A delimiter was opened but never closed.
+
+Erroneous code example:
+
+    main :: () -> i32 {
+        1 + 2
+
+Every `(` needs a `)` and every `{` a `}` to close it. The error points at the
+delimiter that's left open, which is usually not where the closing one is
+missing, so look for where the code it opened should end:
+
+    main :: () -> i32 {
+        1 + 2
+    }
+",
+    ),
+    (
+        "E0002",
+        "A name was used that isn't defined where it's used.
+
+Erroneous code example:
+
+    main :: () -> i32 {
+        x + 1
+    }
+
+Names are bindings defined with `x := ...` before they're used in the same
+block or an enclosing one, parameters of the enclosing function, or top-level
+declarations:
+
+    main :: () -> i32 {
+        x := 41;
+        x + 1
+    }
+",
+    ),
+    (
+        "E0003",
+        "`_` was used as a value.
+
+Erroneous code example:
+
+    main :: () -> i32 {
+        _ := 42;
+        _
+    }
+
+Binding a value to `_` discards it, so there's no value to read back. Bind the
+value to a name to use it later:
+
+    main :: () -> i32 {
+        answer := 42;
+        answer
+    }
+",
+    ),
+    (
+        "E0004",
+        "An expression's type isn't the one its context expects.
+
+Erroneous code example:
+
+    main :: () -> i32 {
+        x := 1;
+    }
+
+The body of `main` ends with a statement, so its value is `()`, but `main` is
+declared to return an `i32`. Blocks take the value of their last expression,
+if it isn't followed by a `;`:
+
+    main :: () -> i32 {
+        x := 1;
+        x
+    }
+",
+    ),
+    (
+        "E0005",
+        "A function was called with the wrong number of arguments.
+
+Erroneous code example:
+
+    add :: (a: i32, b: i32) -> i32 {
+        a + b
+    }
+    main :: () -> i32 {
+        add(1)
+    }
+
+A call passes an argument for each parameter, in order, of the parameter's
+type:
+
+    main :: () -> i32 {
+        add(1, 2)
+    }
+
+Once there's an argument for each parameter, each one that isn't of its
+parameter's type is reported on its own, as E0004.
+",
+    ),
+    (
+        "E0006",
+        "Something that isn't a function was called.
+
+Erroneous code example:
+
+    main :: () {
+        x := 1;
+        x()
+    }
+
+Only functions can be called, whether they're declared at the top level or
+bound to a name. Leave out the parentheses to use the value itself:
+
+    main :: () -> i32 {
+        x := 1;
+        x
+    }
+",
+    ),
+    (
+        "E0007",
+        "A script has top-level statements and also declares `main`.
+
+Erroneous code example, compiled with `--script`:
+
+    main :: () {}
+    foo();
+
+The top-level statements of a script become the body of its `main`, so there
+can't be another one. Move the statements into `main`, or remove `main` to keep
+the statements where they are.
+",
+    ),
+    (
+        "E0008",
+        "The source code is larger than the compiler accepts.
+
+Source code past the limit, 64 MiB by default, isn't read at all, so that
+pathological inputs fail quickly instead of running out of memory. Split the
+program, or raise the limit when embedding the compiler.
+",
+    ),
+    (
+        "E0009",
+        "The program's syntax tree takes up more memory than the compiler allows.
+
+Parsing stops at the code where the limit was reached, which is 1 GiB by
+default. Programs that large are usually generated, so generate less code, or
+raise the limit when embedding the compiler.
+",
+    ),
+    (
+        "E0010",
+        "Expressions nest deeper than the compiler allows.
+
+Erroneous code example, compiled with `--max-nesting-depth=4`:
+
+    main :: () -> i32 {
+        (1 + (2 * (3 + 4)))
+    }
+
+Every pass of the compiler recurses into nested expressions, so nesting is
+limited to keep them from overflowing the stack, to 256 levels by default.
+Chains of operators, as in `1 + 2 + 3`, don't count as nesting.
+Bind nested expressions to names to flatten them:
+
+    main :: () -> i32 {
+        x := 2 * (3 + 4);
+        (1 + x)
+    }
+
+Or raise the limit with `--max-nesting-depth`, if the compiler runs on a stack
+large enough for it.
+",
+    ),
+    (
+        "E0011",
+        "An integer constant doesn't fit in the integers of the program.
+
+Erroneous code example, compiled with `--int-width=16`:
+
+    main :: () -> i32 {
+        40000
+    }
+
+Integers are 32 bits wide unless they're made narrower, e.g., with
+`--int-width=16`, in which case constants have to fit in 16 bits too. Use a
+smaller constant, or wider integers.
+",
+    ),
+    (
+        "E0012",
+        "The integer profile asks for integers that the backend can't generate.
+
+Erroneous command example:
+
+    sophia --int-width=16 --emit=asm main.sph
+
+Only the C backend supports integers that aren't 32 bits wide, that trap on
+overflow, or that are divided without hardware division. Emit C with
+`--emit=c` for those, or use the default integer profile.
+",
+    ),
+    (
+        "E0013",
+        "Host functions were declared, but the backend can't call them.
+
+Host functions are Rust functions that a program embedding the compiler lets
+programs call. Only the VM can call back into the embedding program, so
+programs with host functions have to be compiled to bytecode and run in it.
+",
+    ),
+    (
+        "E0014",
+        "`break` or `continue` was used outside of a loop.
+
+Erroneous code example:
+
+    main :: () {
+        break;
+    }
+
+`break` and `continue` apply to the innermost loop whose body they're in, so
+there has to be one. The else-branch of a loop isn't its body, and neither is
+the body of a function declared in a loop, so they can't be used there either:
+
+    main :: () {
+        loop {
+            break;
+        }
+    }
+",
+    ),
+    (
+        "E0015",
+        "The code isn't what the parser expected next.
+
+Erroneous code example:
+
+    main :: () -> i32 {
+        1 +
+    }
+
+The error lists every kind of token that could have come next, in the order
+the parser looked for them. Here, `+` is followed by `}` instead of the
+expression to add:
+
+    main :: () -> i32 {
+        1 + 2
+    }
+",
+    ),
+    (
+        "E0016",
+        "A match-expression doesn't have an arm for every integer.
+
+Erroneous code example:
+
+    main :: () -> i32 {
+        x := 3;
+        match x {
+            0 => 1,
+            1..=9 => 2,
+        }
+    }
+
+Match-expressions have to give a value whatever the integer is, so their arms
+have to cover every integer of the profile's width. Cover the rest with a
+range, or with `_`, which matches every integer:
+
+    main :: () -> i32 {
+        x := 3;
+        match x {
+            0 => 1,
+            1..=9 => 2,
+            _ => 3,
+        }
+    }
+",
+    ),
+    (
+        "E0017",
+        "A field was used that the type doesn't have.
+
+Erroneous code example:
+
+    Point :: struct { x: i32, y: i32 }
+    main :: () {
+        p := Point { x: 1, y: 2 };
+        z := p.z;
+    }
+
+Only structs have fields, and only the ones their declaration lists, both to
+access and to initialize in a struct literal. Check the declaration for the
+field's name:
+
+    Point :: struct { x: i32, y: i32 }
+    main :: () {
+        p := Point { x: 1, y: 2 };
+        y := p.y;
+    }
+",
+    ),
+    (
+        "E0018",
+        "A struct literal doesn't initialize every field of the struct.
+
+Erroneous code example:
+
+    Point :: struct { x: i32, y: i32 }
+    main :: () -> i32 {
+        p := Point { x: 1 };
+        p.x
+    }
+
+Fields have no default values, so struct literals have to give every field
+one, in whichever order:
+
+    Point :: struct { x: i32, y: i32 }
+    main :: () -> i32 {
+        p := Point { y: 2, x: 1 };
+        p.x
+    }
+",
+    ),
+    (
+        "E0019",
+        "A field was declared or initialized more than once.
+
+Erroneous code example:
+
+    Point :: struct { x: i32, x: i32 }
+    main :: () {}
+
+Each field of a struct has its own name, so that accessing it by name isn't
+ambiguous, and struct literals initialize each field once:
+
+    Point :: struct { x: i32, y: i32 }
+    main :: () {}
+",
+    ),
+    (
+        "E0020",
+        "A struct or an enum contains itself.
+
+Erroneous code example:
+
+    Node :: struct { value: i32, next: Node }
+    main :: () {}
+
+Fields are stored in the struct's values themselves, so a struct with a field
+of its own type, or of a struct that has a field of its type, and so on, would
+be infinitely large. The same goes for the values that an enum's variants
+hold. Structs and enums can only contain other types.
+",
+    ),
+    (
+        "E0021",
+        "A struct literal was used with a name that isn't of a struct.
+
+Erroneous code example:
+
+    origin :: () -> i32 { 0 }
+    main :: () -> i32 {
+        p := origin { x: 0 };
+        0
+    }
+
+Only structs can be initialized with a struct literal. Call functions instead:
+
+    origin :: () -> i32 { 0 }
+    main :: () -> i32 {
+        p := origin();
+        0
+    }
+",
+    ),
+    (
+        "E0022",
+        "Structs were declared, but the backend can't compile them.
+
+Erroneous command example:
+
+    sophia --emit=asm main.sph
+
+Only the interpreter runs programs with structs so far, as the compiled
+backends don't lay their values out in memory yet. Run the program with
+`sophia run` instead.
+",
+    ),
+    (
+        "E0023",
+        "A variant was used that the enum doesn't have.
+
+Erroneous code example:
+
+    Shape :: enum { Circle(i32), Empty }
+    main :: () {
+        s := Shape::Square(2);
+    }
+
+Check the enum's declaration for the variant's name:
+
+    Shape :: enum { Circle(i32), Empty }
+    main :: () {
+        s := Shape::Circle(2);
+    }
+",
+    ),
+    (
+        "E0024",
+        "A variant was declared more than once.
+
+Erroneous code example:
+
+    Shape :: enum { Circle(i32), Circle }
+    main :: () {}
+
+Each variant of an enum has its own name, so that naming it isn't ambiguous:
+
+    Shape :: enum { Circle(i32), Empty }
+    main :: () {}
+",
+    ),
+    (
+        "E0025",
+        "A variant was given a value that its declaration doesn't say it holds.
+
+Erroneous code example:
+
+    Shape :: enum { Circle(i32), Empty }
+    main :: () {
+        s := Shape::Circle;
+    }
+
+Variants that are declared to hold a value are always given one, and the ones
+that aren't never are, both when making values of them and in patterns:
+
+    Shape :: enum { Circle(i32), Empty }
+    main :: () {
+        s := Shape::Circle(2);
+        e := Shape::Empty;
+    }
+",
+    ),
+    (
+        "E0026",
+        "A variant was used with a name that isn't of an enum.
+
+Erroneous code example:
+
+    main :: () {
+        x := main::Circle;
+    }
+
+Only enums have variants. Check the name of the enum:
+
+    Shape :: enum { Circle(i32), Empty }
+    main :: () {
+        x := Shape::Circle(2);
+    }
+",
+    ),
+    (
+        "E0027",
+        "A match-expression on an enum doesn't cover every variant.
+
+Erroneous code example:
+
+    Shape :: enum { Circle(i32), Empty }
+    area :: (s: Shape) -> i32 {
+        match s {
+            Shape::Circle(r) => 3 * r * r,
+        }
+    }
+    main :: () {}
+
+Match-expressions always give a value, so some arm has to match each variant.
+Add arms for the variants not covered, or a `_` arm to match all of them:
+
+    Shape :: enum { Circle(i32), Empty }
+    area :: (s: Shape) -> i32 {
+        match s {
+            Shape::Circle(r) => 3 * r * r,
+            Shape::Empty => 0,
+        }
+    }
+    main :: () {}
+",
+    ),
+    (
+        "E0028",
+        "Enums were declared, but the backend can't compile them.
+
+Erroneous command example:
+
+    sophia --emit=asm main.sph
+
+Only the interpreter runs programs with enums so far, as the compiled backends
+don't lay their values out in memory yet. Run the program with `sophia run`
+instead.
+",
+    ),
+    (
+        "E0029",
+        "A name was used as a type, but it isn't of a struct nor of an enum.
+
+Erroneous code example:
+
+    main :: () {}
+    f :: (x: main) {}
+
+Types are either `i32`, `()`, tuples of types, as in `(i32, i32)`, arrays of a
+type, as in `[i32; 4]`, or the names of structs and enums:
+
+    Point :: struct { x: i32 }
+    main :: () {}
+    f :: (p: Point) {}
+",
+    ),
+    (
+        "E0030",
+        "A keyword was misspelled.
+
+Erroneous code example:
+
+    main :: () -> i32 {
+        fro i : 0..3 {}
+        0
+    }
+
+The name is spelled almost like a keyword, where only the keyword makes sense,
+so the compiler takes it for the keyword to go on checking the code. Spell
+the keyword right:
+
+    main :: () -> i32 {
+        for i : 0..3 {}
+        0
+    }
+",
+    ),
+    (
+        "E0031",
+        "A value was destructured into bindings, but it isn't a tuple of as many
+elements.
+
+Erroneous code example:
+
+    main :: () {
+        a, b := (1, 2, 3);
+    }
+
+Each binding is given the element at its position, so there must be a binding
+for every element. Bind the elements that aren't needed to `_`:
+
+    main :: () {
+        a, b, _ := (1, 2, 3);
+    }
+",
+    ),
+    (
+        "E0032",
+        "Tuples were used, but the backend can't compile them.
+
+Erroneous command example:
+
+    sophia --emit=asm main.sph
+
+Only the interpreter runs programs with tuples so far, as the compiled
+backends don't lay their values out in memory yet. Run the program with
+`sophia run` instead.
+",
+    ),
+    (
+        "E0033",
+        "An array was indexed by a constant that's past its end.
+
+Erroneous code example:
+
+    main :: () -> i32 {
+        values := [1, 2, 3];
+        values[3]
+    }
+
+Arrays are indexed from zero, so the last element of an array of length `n`
+is at index `n - 1`:
+
+    main :: () -> i32 {
+        values := [1, 2, 3];
+        values[2]
+    }
+
+Indices that aren't constants are checked when the program runs instead.
+",
+    ),
+    (
+        "E0034",
+        "A value that isn't an array was indexed.
+
+Erroneous code example:
+
+    main :: () {
+        x := 1;
+        x[0];
+    }
+
+Only arrays have elements that can be accessed by index. Elements of tuples
+are accessed by their position instead, as in `pair.0`.
+",
+    ),
+    (
+        "E0035",
+        "An element was assigned to, but it isn't of an array bound to a name.
+
+Erroneous code example:
+
+    values :: () -> [i32; 2] {
+        [1, 2]
+    }
+    main :: () {
+        values()[0] = 3;
+    }
+
+Assigning to an element changes the array that a binding holds, so there must
+be one. Bind the array to a name first:
+
+    values :: () -> [i32; 2] {
+        [1, 2]
+    }
+
+    main :: () {
+        copy := values();
+        copy[0] = 3;
+    }
+",
+    ),
+    (
+        "E0036",
+        "Arrays were used, but the backend can't compile them.
+
+Erroneous command example:
+
+    sophia --emit=asm main.sph
+
+Only the interpreter runs programs with arrays so far, as the compiled
+backends don't lay their values out in memory yet. Run the program with
+`sophia run` instead.
+",
+    ),
+    (
+        "E0037",
+        "A loop iterated over the elements of a value that isn't an array.
+
+Erroneous code example:
+
+    main :: () {
+        count := 3;
+        for x : count {}
+    }
+
+Only arrays have elements to iterate over. To count up to a number, iterate
+over a range instead:
+
+    main :: () {
+        count := 3;
+        for x : 0..count {}
+    }
+",
+    ),
+    (
+        "E0038",
+        "An integer constant was suffixed with something other than an integer type.
+
+Erroneous code example:
+
+    main :: () {
+        x := 42f32;
+    }
+
+Suffixes give integer constants a type other than `i32`, so they can only be
+one of the integer types: `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64`
+or `usize`.
+",
+    ),
+    (
+        "E0039",
+        "An integer constant is too large for its type.
+
+Erroneous code example:
+
+    main :: () {
+        x := 256u8;
+    }
+
+Constants without a suffix are of type `i32`. Suffix the constant with a type
+that's wide enough for it instead:
+
+    main :: () {
+        x := 256u16;
+    }
+",
+    ),
+    (
+        "E0040",
+        "Integers of types other than `i32` were used, but the backend can't compile
+them.
+
+Erroneous command example:
+
+    sophia --emit=asm main.sph
+
+Only the interpreter runs programs with integers of other types so far. Run
+the program with `sophia run` instead.
+",
+    ),
+    (
+        "E0041",
+        "A value is cast to a type, but either isn't an integer.
+
+Erroneous code example:
+
+    main :: () -> i32 {
+        ({}) as i32
+    }
+
+Only integers can be cast, and only to other integer types, which truncates or
+extends them. Other values have no integer to convert to, so build the value
+of the other type yourself:
+
+    main :: () -> i32 {
+        0
+    }
+",
+    ),
+    (
+        "E0042",
+        "Functions were used as values, but the backend can't compile them.
+
+Erroneous command example:
+
+    sophia --emit=asm main.sph
+
+Only the interpreter runs programs that make closures, bind functions to
+names, pass them to other functions or return them so far. Run the program
+with `sophia run` instead.
+",
+    ),
+    (
+        "E0043",
+        "A `break` carries a value out of a loop that can complete without one.
+
+Erroneous code example:
+
+    main :: () {
+        for i : 0..10 {
+            break i;
+        }
+    }
+
+Loops with a condition, a range or a collection to iterate over complete when
+their iteration ends, so they'd have no value then. Only loops without an
+iteration, as in `loop { ... }`, which nothing but a `break` ends, can break
+with a value:
+
+    main :: () {
+        x := loop {
+            break 7;
+        };
+    }
+",
+    ),
+    (
+        "E0044",
+        "Loops were broken out of with values, but the backend can't compile them.
+
+Erroneous command example:
+
+    sophia --emit=asm main.sph
+
+Only the interpreter runs programs whose loops have values so far. Run the
+program with `sophia run` instead.
+",
+    ),
+    (
+        "E0045",
+        "An if-expression without an `else` branch is used as a value, but a branch
+of it has one.
+
+Erroneous code example:
+
+    main :: () {
+        x := if 1 { 2 };
+    }
+
+When no condition holds, there'd be no branch to give the if-expression its
+value. Add an `else` branch with a value of the same type:
+
+    main :: () {
+        x := if 1 { 2 } else { 3 };
+    }
+",
+    ),
+    (
+        "E0046",
+        "An extern function takes or returns a value that isn't an integer.
+
+Erroneous code example:
+
+    extern first :: (pair: (i32, i32)) -> i32;
+
+Extern functions are called with the platform's C calling convention, which
+Sophia only passes integers with, and they return an integer or nothing. Pass
+the values one by one instead:
+
+    extern first :: (a: i32, b: i32) -> i32;
+",
+    ),
+    (
+        "E0047",
+        "A program was built into an executable, or run, but it has no `main`.
+
+Erroneous command example:
+
+    sophia helpers.sph
+
+Executables start at the `main` function, which the error lists the functions
+found instead of. Declare `main` in one of the files, or compile the file
+along with the one that declares it:
+
+    sophia main.sph helpers.sph
+
+Checking a program with `sophia --check`, or compiling it with `--emit` or
+`--no-link`, doesn't need a `main`.
+",
+    ),
+    (
+        "E0048",
+        "A program was built into an executable, or run, but it has more than one
+`main`.
+
+Erroneous command example:
+
+    sophia main.sph other_main.sph
+
+Where `main.sph` and `other_main.sph` both declare `main`. Executables start
+at the one `main` function, so declare it in only one of the files.
+",
+    ),
+    (
+        "E0049",
+        "A program was built into an executable, or run, but its `main` isn't a
+function that takes nothing and returns `i32` or `()`.
+
+Erroneous command example:
+
+    sophia main.sph
+
+Where `main.sph` declares `main` with a parameter:
+
+    main :: (n: i32) -> i32 {
+        n * 2
+    }
+
+Nothing passes values to `main` when the program starts, and its value is the
+program's exit code. Take the values from elsewhere instead:
+
+    main :: () -> i32 {
+        n := 21;
+        n * 2
+    }
+",
+    ),
+    (
+        "E0050",
+        "The condition of an `if` or of a `for` isn't an integer.
+
+Erroneous code example:
+
+    main :: () -> i32 {
+        if {} { 1 } else { 2 }
+    }
+
+Conditions are integers, of any type, that hold when they aren't zero. There's
+no other kind of value they could be compared against zero as, so compute an
+integer instead:
+
+    main :: () -> i32 {
+        n := 1;
+        if n { 1 } else { 2 }
+    }
+",
+    ),
+    (
+        "E0051",
+        "A range loop steps by zero.
+
+Erroneous code example:
+
+    main :: () -> i32 {
+        evens := [0, 0, 0, 0, 0];
+        for i : 0..10 step 0 {
+            evens[i / 2] = i;
+        }
+        evens[4]
+    }
+
+The step is added to the loop's variable after each iteration, so stepping by
+zero would leave it at the start of the range forever. Step by a positive
+amount instead, or leave the step out to step by one:
+
+    main :: () -> i32 {
+        evens := [0, 0, 0, 0, 0];
+        for i : 0..10 step 2 {
+            evens[i / 2] = i;
+        }
+        evens[4]
+    }
+",
+    ),
+    (
+        "E0052",
+        "The code has a character that no token starts with.
+
+Erroneous code example:
+
+    main :: () -> i32 {
+        answer := 42;
+        $answer
+    }
+
+Only ASCII letters, digits, `_` and the punctuation of the language's operators
+and delimiters make up tokens. Other characters, such as `$`, `@` and quotes,
+may only appear in comments:
+
+    main :: () -> i32 {
+        answer := 42;
+        answer // $answer
+    }
+",
+    ),
+    (
+        "E0053",
+        "A top-level declaration's value isn't a function, a struct or an enum.
+
+Erroneous code example:
+
+    five :: 5
+
+    main :: () -> i32 {
+        five() * 2
+    }
+
+Only functions, structs and enums can be declared at the top level. Declare a
+function that returns the value instead:
+
+    five :: () -> i32 { 5 }
+
+    main :: () -> i32 {
+        five() * 2
+    }
+",
+    ),
+    (
+        "E0054",
+        "A name is declared more than once at the top level.
+
+Erroneous code example:
+
+    area :: (side: i32) -> i32 {
+        side * side
+    }
+    area :: (width: i32, height: i32) -> i32 {
+        width * height
+    }
+    main :: () -> i32 {
+        area(3)
+    }
+
+Names refer to the one declaration of the name, so each declaration needs a
+name of its own:
+
+    square_area :: (side: i32) -> i32 {
+        side * side
+    }
+    area :: (width: i32, height: i32) -> i32 {
+        width * height
+    }
+    main :: () -> i32 {
+        square_area(3)
+    }
+",
+    ),
+];
+
+/// Long-form explanation of the error with the code, e.g., `E0002`.
+pub(crate) fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(explained_code, _)| *explained_code == code)
+        .map(|(_, explanation)| *explanation)
+}
@@ -0,0 +1,340 @@
+use crate::ast::{CastExpr, CompoundExpr, Expr, ForExpr, ForIteration, IfExpr, ParenExpr, Program};
+use crate::compiler_context::CompilerContext;
+use crate::json::Json;
+use crate::resolve::{Resolution, Resolutions};
+use crate::scanner::Span;
+
+/// Language feature that reviewers of programs, e.g., course instructors and
+/// auditors, may want to know about.
+///
+/// Sophia has no unsafe code, so there's nothing to report about it. Host
+/// functions and extern functions are the only ways programs reach outside of
+/// themselves.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) enum Feature {
+    Loops,
+    Recursion,
+    /// Functions declared in other functions, which capture their bindings.
+    Closures,
+    HostFunctions,
+    /// Functions of the C library, which are declared with `extern`, or
+    /// called.
+    ExternFunctions,
+}
+
+impl Feature {
+    pub(crate) const ALL: [Feature; 5] = [
+        Feature::Loops,
+        Feature::Recursion,
+        Feature::Closures,
+        Feature::HostFunctions,
+        Feature::ExternFunctions,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Feature::Loops => "loops",
+            Feature::Recursion => "recursion",
+            Feature::Closures => "closures",
+            Feature::HostFunctions => "host-functions",
+            Feature::ExternFunctions => "extern-functions",
+        }
+    }
+}
+
+/// Features that a program's declarations use, in the order they're declared.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct FeatureReport {
+    pub(crate) decls: Vec<DeclFeatures>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct DeclFeatures {
+    pub(crate) name: String,
+    /// Features the declaration uses, in the order of `Feature::ALL`.
+    pub(crate) features: Vec<Feature>,
+}
+
+impl FeatureReport {
+    /// Features that any declaration uses, in the order of `Feature::ALL`.
+    pub(crate) fn features(&self) -> Vec<Feature> {
+        Feature::ALL
+            .into_iter()
+            .filter(|feature| {
+                self.decls
+                    .iter()
+                    .any(|decl| decl.features.contains(feature))
+            })
+            .collect()
+    }
+
+    pub(crate) fn to_json(&self) -> Json {
+        let decls = self
+            .decls
+            .iter()
+            .map(|decl| {
+                Json::object([
+                    ("name", Json::from(decl.name.as_str())),
+                    ("features", features_to_json(&decl.features)),
+                ])
+            })
+            .collect();
+
+        Json::object([
+            ("features", features_to_json(&self.features())),
+            ("decls", Json::Array(decls)),
+        ])
+    }
+}
+
+fn features_to_json(features: &[Feature]) -> Json {
+    Json::Array(
+        features
+            .iter()
+            .map(|feature| Json::from(feature.name()))
+            .collect(),
+    )
+}
+
+/// Finds which features every declaration of a program uses. A declaration
+/// is recursive if it calls itself, either directly or through other
+/// declarations.
+pub(crate) struct FeatureCollector<'a> {
+    ctx: &'a CompilerContext,
+    resolutions: &'a Resolutions,
+    uses_loops: bool,
+    uses_closures: bool,
+    uses_host_functions: bool,
+    uses_extern_functions: bool,
+    /// Whether each declaration of the program is of an extern function.
+    is_extern_decl: Vec<bool>,
+    /// Indices of the declarations the current declaration refers to.
+    decl_refs: Vec<usize>,
+}
+
+impl<'a> FeatureCollector<'a> {
+    pub(crate) fn new(
+        ctx: &'a CompilerContext,
+        resolutions: &'a Resolutions,
+    ) -> FeatureCollector<'a> {
+        FeatureCollector {
+            ctx,
+            resolutions,
+            uses_loops: false,
+            uses_closures: false,
+            uses_host_functions: false,
+            uses_extern_functions: false,
+            is_extern_decl: vec![],
+            decl_refs: vec![],
+        }
+    }
+
+    pub(crate) fn collect_program(mut self, program: Program) -> FeatureReport {
+        let mut decls = vec![];
+        let mut decl_refs = vec![];
+        self.is_extern_decl = (program.decls.iter())
+            .map(|decl| matches!(decl.value, Expr::Extern(_)))
+            .collect();
+
+        for decl in program.decls {
+            // Functions in the declared function are closures, but it's not.
+            match decl.value {
+                Expr::Function(function) => self.collect_compound_expr(function.body),
+                value => self.collect_expr(value),
+            }
+
+            let features = [
+                (Feature::Loops, std::mem::take(&mut self.uses_loops)),
+                (Feature::Closures, std::mem::take(&mut self.uses_closures)),
+                (
+                    Feature::HostFunctions,
+                    std::mem::take(&mut self.uses_host_functions),
+                ),
+                (
+                    Feature::ExternFunctions,
+                    std::mem::take(&mut self.uses_extern_functions),
+                ),
+            ];
+
+            decls.push(DeclFeatures {
+                name: self.ctx.resolve_symbol(decl.identifier).to_owned(),
+                features: features
+                    .into_iter()
+                    .filter_map(|(feature, is_used)| is_used.then_some(feature))
+                    .collect(),
+            });
+            decl_refs.push(std::mem::take(&mut self.decl_refs));
+        }
+
+        // Recursion is only known once every declaration's references are.
+        for (decl_idx, decl) in decls.iter_mut().enumerate() {
+            if refers_to_itself(&decl_refs, decl_idx) {
+                decl.features.push(Feature::Recursion);
+                decl.features.sort();
+            }
+        }
+
+        FeatureReport { decls }
+    }
+
+    fn collect_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Const(_) | Expr::Continue(_) | Expr::Struct(_) | Expr::Enum(_) => {}
+            Expr::Extern(_) => self.uses_extern_functions = true,
+            Expr::Break(break_expr) => {
+                if let Some(value) = break_expr.value {
+                    self.collect_expr(value);
+                }
+            }
+            Expr::BindRef(bind_ref) => self.collect_ref(bind_ref.span),
+            Expr::BindDef(bind_def) => self.collect_expr(bind_def.value),
+            Expr::Function(function) => {
+                self.uses_closures = true;
+                self.collect_compound_expr(function.body);
+            }
+            // Struct literals don't call anything, so they can't recurse.
+            Expr::StructLit(struct_lit_expr) => {
+                for field in struct_lit_expr.fields {
+                    self.collect_expr(field.value);
+                }
+            }
+            Expr::Field(field_expr) => self.collect_expr(field_expr.base),
+            Expr::Variant(variant_expr) => {
+                if let Some(payload) = variant_expr.payload {
+                    self.collect_expr(payload);
+                }
+            }
+            Expr::Tuple(tuple_expr) => {
+                for element in tuple_expr.elements {
+                    self.collect_expr(element);
+                }
+            }
+            Expr::TupleField(tuple_field_expr) => self.collect_expr(tuple_field_expr.base),
+            Expr::TupleBindDef(tuple_bind_def) => self.collect_expr(tuple_bind_def.value),
+            Expr::Array(array_expr) => {
+                for element in array_expr.elements {
+                    self.collect_expr(element);
+                }
+            }
+            Expr::Index(index_expr) => {
+                self.collect_expr(index_expr.base);
+                self.collect_expr(index_expr.index);
+            }
+            Expr::IndexAssign(index_assign_expr) => {
+                self.collect_expr(index_assign_expr.target.base);
+                self.collect_expr(index_assign_expr.target.index);
+                self.collect_expr(index_assign_expr.value);
+            }
+            Expr::If(if_expr) => self.collect_if_expr(*if_expr),
+            Expr::Match(match_expr) => {
+                self.collect_expr(match_expr.scrutinee);
+
+                for arm in match_expr.arms {
+                    self.collect_expr(arm.expr);
+                }
+            }
+            Expr::For(for_expr) => self.collect_for_expr(*for_expr),
+            Expr::Compound(compound_expr) => self.collect_compound_expr(*compound_expr),
+            Expr::Semi(expr)
+            | Expr::Cast(CastExpr { expr, .. })
+            | Expr::Paren(ParenExpr { expr, .. }) => self.collect_expr(expr),
+            Expr::FnCall(fn_call_expr) => {
+                for arg in fn_call_expr.args {
+                    self.collect_expr(arg);
+                }
+
+                self.collect_ref(fn_call_expr.span);
+            }
+            Expr::Binary(binary_expr) => {
+                let (operand, operations) = binary_expr.chain();
+                self.collect_expr(operand);
+
+                for binary_expr in operations {
+                    self.collect_expr(binary_expr.rhs);
+                }
+            }
+        }
+    }
+
+    fn collect_if_expr(&mut self, if_expr: IfExpr) {
+        self.collect_expr(if_expr.cond_expr);
+        self.collect_compound_expr(if_expr.true_branch);
+
+        for branch in if_expr.else_if_branches {
+            self.collect_expr(branch.cond_expr);
+            self.collect_compound_expr(branch.true_branch);
+        }
+
+        if let Some(final_branch) = if_expr.final_branch {
+            self.collect_compound_expr(final_branch);
+        }
+    }
+
+    fn collect_for_expr(&mut self, for_expr: ForExpr) {
+        self.uses_loops = true;
+
+        match for_expr.iteration {
+            Some(ForIteration::Conditional { cond_expr }) => self.collect_expr(cond_expr),
+            Some(ForIteration::Iterative {
+                start_expr,
+                end_expr,
+                step_expr,
+                ..
+            }) => {
+                self.collect_expr(start_expr);
+                self.collect_expr(end_expr);
+
+                if let Some(step_expr) = step_expr {
+                    self.collect_expr(step_expr);
+                }
+            }
+            Some(ForIteration::InCollection {
+                collection_expr, ..
+            }) => self.collect_expr(collection_expr),
+            None => {}
+        }
+
+        self.collect_compound_expr(for_expr.body);
+
+        if let Some(else_branch) = for_expr.else_branch {
+            self.collect_compound_expr(else_branch);
+        }
+    }
+
+    fn collect_compound_expr(&mut self, compound_expr: CompoundExpr) {
+        for expr in compound_expr.exprs {
+            self.collect_expr(expr);
+        }
+    }
+
+    fn collect_ref(&mut self, ref_span: Span) {
+        match self.resolutions.get(ref_span) {
+            Some(Resolution::Decl(decl_idx)) => {
+                self.uses_extern_functions |= self.is_extern_decl[decl_idx];
+                self.decl_refs.push(decl_idx);
+            }
+            Some(Resolution::Host(_)) => self.uses_host_functions = true,
+            Some(Resolution::Builtin(_) | Resolution::Local(_)) | None => {}
+        }
+    }
+}
+
+/// Whether the declaration at `decl_idx` refers to itself through any chain of
+/// references.
+fn refers_to_itself(decl_refs: &[Vec<usize>], decl_idx: usize) -> bool {
+    let mut is_visited = vec![false; decl_refs.len()];
+    let mut worklist = decl_refs[decl_idx].clone();
+
+    while let Some(ref_idx) = worklist.pop() {
+        if ref_idx == decl_idx {
+            return true;
+        }
+
+        if !is_visited[ref_idx] {
+            is_visited[ref_idx] = true;
+            worklist.extend(&decl_refs[ref_idx]);
+        }
+    }
+
+    false
+}
@@ -0,0 +1,143 @@
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::{CompileError, CompileWarning, Diagnostic, Fix};
+use crate::driver::{self, Options};
+use crate::rewrite::{Rewrite, TokenRewriter};
+use crate::scanner::Span;
+
+/// Source code with the fixes of its diagnostics made, see `fix_source`.
+pub(crate) struct Fixed {
+    pub(crate) source_code: String,
+    pub(crate) fix_count: usize,
+    /// What's left to report about the fixed source code.
+    pub(crate) diagnostic: Diagnostic,
+}
+
+/// Most times the source code is fixed again, as fixing code that doesn't
+/// parse may only then tell what else to fix, e.g., names misspelled after a
+/// misspelled keyword.
+const MAX_PASSES: usize = 8;
+
+/// Makes the fixes of the diagnostics of the source code, see
+/// `CompileError::fix` and `CompileWarning::fix`, which leaves the code around
+/// the fixed tokens as it is. The fixed source code is fixed again, until
+/// nothing more can be.
+///
+/// A fix is only made if it leaves no error in the code it rewrote, e.g.,
+/// replacing a name with a similar one that's of another type isn't, so that
+/// fixes never trade an error for another one. The fixed source code is
+/// checked again for that, and errors in it are located in the original
+/// source code, to tell which fixes they're about.
+pub(crate) fn fix_source(source_code: &str, options: &Options) -> Fixed {
+    let mut fixed = fix_pass(source_code, options);
+
+    for _ in 1..MAX_PASSES {
+        let next_fixed = fix_pass(&fixed.source_code, options);
+
+        if next_fixed.fix_count == 0 {
+            break;
+        }
+
+        fixed = Fixed {
+            fix_count: fixed.fix_count + next_fixed.fix_count,
+            ..next_fixed
+        };
+    }
+
+    fixed
+}
+
+fn fix_pass(source_code: &str, options: &Options) -> Fixed {
+    let context = driver::new_context(source_code, options);
+    let diagnostic = driver::check(source_code, options);
+    let mut fixes: Vec<Fix> = vec![];
+
+    for fix in (diagnostic.errors.iter().filter_map(CompileError::fix))
+        .chain(diagnostic.warnings.iter().filter_map(CompileWarning::fix))
+    {
+        if !fixes.contains(&fix) {
+            fixes.push(fix);
+        }
+    }
+
+    // Dropping fixes may only make the errors of the others go away, so this
+    // ends once no error is in the code of a fix, at the latest once there
+    // are no fixes.
+    loop {
+        let (rewrite, fixed_spans) = rewrite_fixes(&context, &fixes);
+        let diagnostic = driver::check(rewrite.source_code.as_str(), options);
+        let error_spans: Vec<Span> = diagnostic
+            .errors
+            .iter()
+            .map(|error| rewrite.original_span(error.span()))
+            .collect();
+        let fix_count = fixes.len();
+
+        let mut fixed_spans = fixed_spans.into_iter();
+        fixes.retain(|_| {
+            let fixed_span = fixed_spans.next().unwrap();
+
+            !error_spans
+                .iter()
+                .any(|&error_span| overlaps(error_span, fixed_span))
+        });
+
+        if fixes.len() == fix_count {
+            return Fixed {
+                source_code: rewrite.source_code,
+                fix_count,
+                diagnostic,
+            };
+        }
+    }
+}
+
+/// Rewrites the tokens of the fixes, along with the span of the original
+/// source code that each fix rewrote, which is empty for insertions.
+fn rewrite_fixes(context: &CompilerContext, fixes: &[Fix]) -> (Rewrite, Vec<Span>) {
+    let mut rewriter = TokenRewriter::new(context);
+    let mut fixed_spans = vec![];
+
+    for fix in fixes {
+        let fixed_span = match fix {
+            Fix::Replace { span, text } => {
+                rewriter.replace(token_at(&rewriter, *span), text.as_str());
+                *span
+            }
+            Fix::Delete { span } => {
+                rewriter.delete(token_at(&rewriter, *span));
+                *span
+            }
+            Fix::Insert { pos, text } => {
+                let token_idx = (rewriter.tokens().iter())
+                    .rposition(|token| token.span.end.0 <= pos.0)
+                    .unwrap();
+                let end = rewriter.tokens()[token_idx].span.end;
+
+                rewriter.insert_after(token_idx, text.as_str());
+                Span { start: end, end }
+            }
+        };
+
+        fixed_spans.push(fixed_span);
+    }
+
+    (rewriter.finish(), fixed_spans)
+}
+
+fn token_at(rewriter: &TokenRewriter, span: Span) -> usize {
+    (rewriter.tokens().iter())
+        .position(|token| token.span == span)
+        .unwrap()
+}
+
+/// Whether the spans share code, or, if either is empty, whether it's
+/// between two chars of the other one, e.g., code inserted into it.
+fn overlaps(a: Span, b: Span) -> bool {
+    if a.start == a.end {
+        a == b || (b.start.0 < a.start.0 && a.start.0 < b.end.0)
+    } else if b.start == b.end {
+        overlaps(b, a)
+    } else {
+        a.start.0 < b.end.0 && b.start.0 < a.end.0
+    }
+}
@@ -0,0 +1,296 @@
+use crate::cst::{lossless_tokens, LosslessToken};
+use crate::diagnostics::Diagnostic;
+use crate::driver::{self, Options};
+use crate::scanner::{Delim, Keyword, TokenKind};
+
+const INDENT: &str = "    ";
+
+/// Formats the source code the canonical way, which only changes the
+/// whitespace between its tokens: lines are indented by four spaces for every
+/// delimiter left open on the lines above, and tokens on the same line are
+/// spaced as in `x := f(a, b)`, `main :: () -> i32 { 0 }` or `for i : 0..3`.
+///
+/// Where the lines break is left as it is, except that blank lines are never
+/// more than one in a row, nor at the start or at the end of a block.
+/// Comments are kept where they are, with a single space before the ones that
+/// follow code.
+///
+/// The source code must parse, either as a program or as a script, otherwise
+/// why it doesn't is returned instead: respacing tokens that aren't meant to
+/// be there may well change what the code means, e.g., `a <= b` would become
+/// `a < = b`, as `<=` isn't a token.
+pub(crate) fn format_source(source_code: &str) -> Result<String, Diagnostic> {
+    let options = Options {
+        script_mode: true,
+        ..Options::default()
+    };
+    let context = driver::new_context(source_code, &options);
+    driver::parse(&context, &options)?;
+
+    let (tokens, trailing_trivia) = lossless_tokens(source_code);
+    let mut formatter = Formatter {
+        tokens: &tokens,
+        text: String::new(),
+        open_delims: vec![],
+        line_indent: 0,
+        follows_open_delim: false,
+    };
+
+    for idx in 0..tokens.len() {
+        formatter.format_token(idx);
+    }
+
+    formatter.format_trivia(trailing_trivia, None);
+
+    let mut text = formatter.text;
+    trim_trailing_spaces(&mut text);
+
+    if !text.is_empty() {
+        text.push('\n');
+    }
+
+    Ok(text)
+}
+
+struct Formatter<'a, 'src> {
+    tokens: &'a [LosslessToken<'src>],
+    text: String,
+    /// Indentation of the lines that the open delimiters are on, innermost
+    /// last, as what's inside them is indented one level further.
+    open_delims: Vec<usize>,
+    /// Indentation of the line being formatted.
+    line_indent: usize,
+    /// Whether the last line written ends with an open delimiter.
+    follows_open_delim: bool,
+}
+
+impl Formatter<'_, '_> {
+    fn format_token(&mut self, idx: usize) {
+        let token = self.tokens[idx];
+        let starts_line = self.format_trivia(token.leading_trivia, Some(idx));
+
+        if starts_line {
+            self.line_indent = match token.token.kind {
+                TokenKind::Closed(_) => self.open_delims.last().copied().unwrap_or(0),
+                _ => self.inner_indent() + usize::from(self.continues_line(idx)),
+            };
+            self.push_indent();
+        } else if idx > 0 && self.is_spaced(idx - 1, idx) {
+            self.text.push(' ');
+        }
+
+        self.text.push_str(token.text);
+
+        self.follows_open_delim = matches!(token.token.kind, TokenKind::Open(_));
+
+        match token.token.kind {
+            TokenKind::Open(_) => self.open_delims.push(self.line_indent),
+            TokenKind::Closed(_) => {
+                self.open_delims.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /// Formats the comments in the text before the token at the index, or
+    /// before the end of the file, returning whether the token starts a line.
+    fn format_trivia(&mut self, trivia: &str, next_idx: Option<usize>) -> bool {
+        let mut lines = trivia.split('\n');
+
+        // A comment after code stays on the code's line.
+        let first_line = lines.next().unwrap_or_default().trim();
+
+        if !first_line.is_empty() {
+            if !self.text.is_empty() {
+                self.text.push(' ');
+            }
+
+            self.text.push_str(first_line);
+        }
+
+        let lines: Vec<_> = lines.collect();
+        // The last line is the one the token is on, so the lines before it are
+        // either blank or comments of their own.
+        let own_lines = match next_idx {
+            Some(_) => &lines[..lines.len().saturating_sub(1)],
+            None => &lines[..],
+        };
+        let mut follows_blank_line = false;
+
+        for line in own_lines {
+            let line = line.trim();
+
+            if line.is_empty() {
+                follows_blank_line = true;
+                continue;
+            }
+
+            self.start_line(follows_blank_line);
+            follows_blank_line = false;
+
+            self.line_indent = self.inner_indent();
+            self.push_indent();
+            self.text.push_str(line);
+        }
+
+        let Some(idx) = next_idx else {
+            return false;
+        };
+
+        if self.text.is_empty() {
+            return true;
+        }
+
+        if lines.is_empty() {
+            return false;
+        }
+
+        let is_closed_delim = matches!(self.tokens[idx].token.kind, TokenKind::Closed(_));
+        self.start_line(follows_blank_line && !is_closed_delim);
+
+        true
+    }
+
+    /// Starts a new line, after a blank one if asked for, unless it'd be the
+    /// first line of the file or of a block.
+    fn start_line(&mut self, after_blank_line: bool) {
+        if self.text.is_empty() {
+            return;
+        }
+
+        self.newline();
+
+        if after_blank_line && !self.follows_open_delim {
+            self.newline();
+        }
+
+        self.follows_open_delim = false;
+    }
+
+    fn inner_indent(&self) -> usize {
+        self.open_delims.last().map_or(0, |indent| indent + 1)
+    }
+
+    fn newline(&mut self) {
+        trim_trailing_spaces(&mut self.text);
+        self.text.push('\n');
+    }
+
+    fn push_indent(&mut self) {
+        for _ in 0..self.line_indent {
+            self.text.push_str(INDENT);
+        }
+    }
+
+    /// Whether the line that the token at the index starts continues the
+    /// expression on the line above, e.g., after a `+` or before one, so that
+    /// it's indented one level further.
+    fn continues_line(&self, idx: usize) -> bool {
+        let ends_expecting_operand = |kind| {
+            is_binary_operator(kind)
+                || matches!(
+                    kind,
+                    TokenKind::ColonEqual
+                        | TokenKind::Equal
+                        | TokenKind::EqualGreater
+                        | TokenKind::DashGreater
+                        | TokenKind::ColonColon
+                        | TokenKind::Colon
+                        | TokenKind::Period
+                        | TokenKind::Keyword(Keyword::As)
+                )
+        };
+        let starts_with_operator = |kind| {
+            is_binary_operator(kind)
+                || matches!(kind, TokenKind::Period | TokenKind::Keyword(Keyword::As))
+        };
+
+        idx > 0
+            && (ends_expecting_operand(self.tokens[idx - 1].token.kind)
+                || starts_with_operator(self.tokens[idx].token.kind))
+    }
+
+    /// Whether there's a space between the tokens at the indices, which are
+    /// on the same line, one right after the other.
+    fn is_spaced(&self, prev_idx: usize, next_idx: usize) -> bool {
+        let prev = self.tokens[prev_idx].token.kind;
+        let next = self.tokens[next_idx].token.kind;
+
+        match (prev, next) {
+            (TokenKind::Open(Delim::Curly), TokenKind::Closed(Delim::Curly)) => false,
+            (TokenKind::Open(Delim::Curly), _) | (_, TokenKind::Closed(Delim::Curly)) => true,
+            (TokenKind::Open(_), _) | (_, TokenKind::Closed(_)) => false,
+            (_, TokenKind::Comma | TokenKind::Semi) => false,
+            (TokenKind::Period, _) | (_, TokenKind::Period) => false,
+            (TokenKind::PeriodPeriod | TokenKind::PeriodPeriodEqual, _)
+            | (_, TokenKind::PeriodPeriod | TokenKind::PeriodPeriodEqual) => false,
+            (TokenKind::Hash, _) => false,
+            (
+                TokenKind::Identifier | TokenKind::Closed(_),
+                TokenKind::Open(Delim::Paren | Delim::Bracket),
+            ) => false,
+            (_, TokenKind::ColonColon) | (TokenKind::ColonColon, _) => {
+                self.is_decl_colon_colon(if next == TokenKind::ColonColon {
+                    next_idx
+                } else {
+                    prev_idx
+                })
+            }
+            (_, TokenKind::Colon) => self.is_binding_colon(next_idx),
+            _ => true,
+        }
+    }
+
+    /// Whether the `::` at the index separates the name of a declaration from
+    /// its value, e.g., `main :: () {}`, rather than an enum from its variant,
+    /// e.g., `Shape::Square`. Declarations are at the top level, at the start
+    /// of their lines.
+    fn is_decl_colon_colon(&self, idx: usize) -> bool {
+        if !self.open_delims.is_empty() || idx == 0 {
+            return false;
+        }
+
+        let name_idx = idx - 1;
+
+        name_idx == 0
+            || self.tokens[name_idx].leading_trivia.contains('\n')
+            || self.tokens[name_idx - 1].token.kind == TokenKind::Keyword(Keyword::Extern)
+    }
+
+    /// Whether the `:` at the index comes after a binding's name, as in
+    /// `for i : 0..3` and `x : i32 = 0`, rather than after a parameter's or a
+    /// field's, as in `(x: i32)` and `Point { x: 0 }`.
+    fn is_binding_colon(&self, idx: usize) -> bool {
+        if idx >= 2 && self.tokens[idx - 2].token.kind == TokenKind::Keyword(Keyword::For) {
+            return true;
+        }
+
+        // Only a binding's type is followed by an `=`.
+        let mut depth = 0usize;
+
+        for token in &self.tokens[idx + 1..] {
+            match token.token.kind {
+                TokenKind::Open(_) => depth += 1,
+                TokenKind::Closed(_) if depth == 0 => return false,
+                TokenKind::Closed(_) => depth -= 1,
+                TokenKind::Equal if depth == 0 => return true,
+                TokenKind::Comma | TokenKind::Semi if depth == 0 => return false,
+                _ => {}
+            }
+        }
+
+        false
+    }
+}
+
+fn is_binary_operator(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Plus | TokenKind::Dash | TokenKind::Star | TokenKind::Slash | TokenKind::Percent
+    )
+}
+
+fn trim_trailing_spaces(text: &mut String) {
+    let len = text.trim_end_matches([' ', '\t']).len();
+    text.truncate(len);
+}
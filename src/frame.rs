@@ -0,0 +1,227 @@
+use crate::ast::{CompoundExpr, Expr, ForIteration, Function};
+use crate::compiler_context::CompilerContext;
+use crate::host::HashMap;
+use crate::lower::constant_value;
+use crate::scanner::Span;
+
+/// Value that occupies a slot of a function's frame.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum SlotOwner {
+    /// Binding defined at this span, be it a parameter, a binding definition or
+    /// the variable of an iterative for-loop.
+    Binding(Span),
+    /// End of the range of an iterative for-loop, evaluated once before the
+    /// loop starts. Ranges ending in a constant don't need a slot.
+    RangeEnd(Span),
+    /// Start of the range of a reversed for-loop, which its variable counts
+    /// down to, and which it needs a slot for just like ends do otherwise.
+    RangeStart(Span),
+    /// Step of an iterative for-loop, evaluated once after the range's end.
+    /// Constant steps don't need a slot either.
+    RangeStep(Span),
+    /// Scrutinee of a match-expression at this span, tested against the
+    /// patterns of its arms one after the other.
+    Scrutinee(Span),
+}
+
+/// Slots of a function's frame, i.e., its local storage, numbered in the order
+/// their values are first computed. The JIT backend assigns each of them a
+/// variable, as it walks the AST instead of lowering it to the IR.
+pub(crate) struct FrameLayout {
+    slot_by_owner: HashMap<SlotOwner, usize>,
+}
+
+impl FrameLayout {
+    pub(crate) fn of_function(ctx: &CompilerContext, function: Function) -> FrameLayout {
+        let mut layout = FrameLayout {
+            slot_by_owner: HashMap::default(),
+        };
+
+        for param in function.parameters {
+            layout.alloc_slot(SlotOwner::Binding(param.span));
+        }
+
+        layout.lay_out_compound_expr(ctx, function.body);
+
+        layout
+    }
+
+    pub(crate) fn slot(&self, owner: SlotOwner) -> usize {
+        self.slot_by_owner[&owner]
+    }
+
+    pub(crate) fn slot_count(&self) -> usize {
+        self.slot_by_owner.len()
+    }
+
+    fn alloc_slot(&mut self, owner: SlotOwner) {
+        let slot = self.slot_by_owner.len();
+        self.slot_by_owner.insert(owner, slot);
+    }
+
+    fn lay_out_expr(&mut self, ctx: &CompilerContext, expr: &Expr) {
+        match expr {
+            Expr::Const(_)
+            | Expr::BindRef(_)
+            | Expr::Break(_)
+            | Expr::Continue(_)
+            | Expr::Function(_)
+            | Expr::Extern(_)
+            | Expr::Struct(_)
+            | Expr::Enum(_) => {}
+            Expr::BindDef(bind_def) => {
+                self.lay_out_expr(ctx, bind_def.value);
+
+                if bind_def.identifier != ctx.symbols().discard {
+                    self.alloc_slot(SlotOwner::Binding(bind_def.span));
+                }
+            }
+            Expr::StructLit(struct_lit_expr) => {
+                for field in struct_lit_expr.fields {
+                    self.lay_out_expr(ctx, field.value);
+                }
+            }
+            Expr::Field(field_expr) => self.lay_out_expr(ctx, field_expr.base),
+            Expr::Variant(variant_expr) => {
+                if let Some(payload) = variant_expr.payload {
+                    self.lay_out_expr(ctx, payload);
+                }
+            }
+            Expr::Tuple(tuple_expr) => {
+                for element in tuple_expr.elements {
+                    self.lay_out_expr(ctx, element);
+                }
+            }
+            Expr::TupleField(tuple_field_expr) => self.lay_out_expr(ctx, tuple_field_expr.base),
+            Expr::Array(array_expr) => {
+                for element in array_expr.elements {
+                    self.lay_out_expr(ctx, element);
+                }
+            }
+            Expr::Index(index_expr) => {
+                self.lay_out_expr(ctx, index_expr.base);
+                self.lay_out_expr(ctx, index_expr.index);
+            }
+            Expr::IndexAssign(index_assign_expr) => {
+                self.lay_out_expr(ctx, index_assign_expr.target.base);
+                self.lay_out_expr(ctx, index_assign_expr.target.index);
+                self.lay_out_expr(ctx, index_assign_expr.value);
+            }
+            Expr::TupleBindDef(tuple_bind_def) => {
+                self.lay_out_expr(ctx, tuple_bind_def.value);
+
+                for binding in tuple_bind_def.bindings {
+                    if binding.identifier != ctx.symbols().discard {
+                        self.alloc_slot(SlotOwner::Binding(binding.span));
+                    }
+                }
+            }
+            Expr::If(if_expr) => {
+                self.lay_out_expr(ctx, if_expr.cond_expr);
+                self.lay_out_compound_expr(ctx, if_expr.true_branch);
+
+                for branch in if_expr.else_if_branches {
+                    self.lay_out_expr(ctx, branch.cond_expr);
+                    self.lay_out_compound_expr(ctx, branch.true_branch);
+                }
+
+                if let Some(final_branch) = if_expr.final_branch {
+                    self.lay_out_compound_expr(ctx, final_branch);
+                }
+            }
+            Expr::Match(match_expr) => {
+                self.lay_out_expr(ctx, match_expr.scrutinee);
+                self.alloc_slot(SlotOwner::Scrutinee(match_expr.scrutinee.span()));
+
+                for arm in match_expr.arms {
+                    self.lay_out_expr(ctx, arm.expr);
+                }
+            }
+            Expr::For(for_expr) => {
+                match for_expr.iteration {
+                    Some(ForIteration::Conditional { cond_expr }) => {
+                        self.lay_out_expr(ctx, cond_expr)
+                    }
+                    Some(ForIteration::Iterative {
+                        identifier_span,
+                        start_expr,
+                        end_expr,
+                        step_expr,
+                        is_reversed,
+                        ..
+                    }) => {
+                        // Reversed loops start their variable at the end of the range
+                        // instead.
+                        if is_reversed {
+                            self.lay_out_expr(ctx, start_expr);
+
+                            if !is_constant(start_expr) {
+                                self.alloc_slot(SlotOwner::RangeStart(start_expr.span()));
+                            }
+
+                            self.lay_out_expr(ctx, end_expr);
+                            self.alloc_slot(SlotOwner::Binding(identifier_span));
+                        } else {
+                            self.lay_out_expr(ctx, start_expr);
+                            self.alloc_slot(SlotOwner::Binding(identifier_span));
+
+                            if !is_constant(end_expr) {
+                                self.lay_out_expr(ctx, end_expr);
+                                self.alloc_slot(SlotOwner::RangeEnd(end_expr.span()));
+                            }
+                        }
+
+                        if let Some(step_expr) =
+                            step_expr.filter(|step_expr| !is_constant(step_expr))
+                        {
+                            self.lay_out_expr(ctx, step_expr);
+                            self.alloc_slot(SlotOwner::RangeStep(step_expr.span()));
+                        }
+                    }
+                    Some(ForIteration::InCollection {
+                        identifier_span,
+                        collection_expr,
+                        ..
+                    }) => {
+                        self.lay_out_expr(ctx, collection_expr);
+                        self.alloc_slot(SlotOwner::Binding(identifier_span));
+                    }
+                    None => {}
+                }
+
+                self.lay_out_compound_expr(ctx, for_expr.body);
+
+                if let Some(else_branch) = for_expr.else_branch {
+                    self.lay_out_compound_expr(ctx, else_branch);
+                }
+            }
+            Expr::Compound(compound_expr) => self.lay_out_compound_expr(ctx, *compound_expr),
+            Expr::Semi(expr) => self.lay_out_expr(ctx, expr),
+            Expr::FnCall(fn_call_expr) => {
+                for arg in fn_call_expr.args {
+                    self.lay_out_expr(ctx, arg);
+                }
+            }
+            Expr::Binary(binary_expr) => {
+                let (operand, operations) = binary_expr.chain();
+                self.lay_out_expr(ctx, operand);
+
+                for binary_expr in operations {
+                    self.lay_out_expr(ctx, binary_expr.rhs);
+                }
+            }
+            Expr::Cast(cast_expr) => self.lay_out_expr(ctx, cast_expr.expr),
+            Expr::Paren(paren_expr) => self.lay_out_expr(ctx, paren_expr.expr),
+        }
+    }
+
+    fn lay_out_compound_expr(&mut self, ctx: &CompilerContext, compound_expr: CompoundExpr) {
+        for expr in compound_expr.exprs {
+            self.lay_out_expr(ctx, expr);
+        }
+    }
+}
+
+fn is_constant(expr: &Expr) -> bool {
+    constant_value(expr).is_some()
+}
@@ -0,0 +1,61 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{BuildHasher, Hasher};
+
+/// Hash map for the compile path, which, unlike the standard library's default,
+/// doesn't key its hashes with the OS's random source.
+pub(crate) type HashMap<K, V> = std::collections::HashMap<K, V, SeededState>;
+
+/// Everything the compiler may learn from the system it runs on, which is
+/// only randomness so far.
+///
+/// The compile path asks the context's host instead of reaching for the OS's
+/// random source directly, so that embedders, e.g., sandboxed build systems,
+/// control every input of a compilation and get results that are safe to
+/// cache. Linking and finding lint configurations aren't part of it, as only
+/// the command-line tool does them, which reads the `CC` environment variable
+/// and the file system just like any other tool.
+///
+/// Compilations get one through `Session::with_host`.
+pub trait Host: Send + Sync {
+    /// Seeds the hashing of the compile path.
+    fn random_u64(&self) -> u64;
+}
+
+/// Host that gives the same answers every time, as randomness comes from a
+/// fixed seed.
+///
+/// This is the host the compiler runs with unless told otherwise.
+#[derive(Default)]
+pub(crate) struct HermeticHost {
+    pub(crate) seed: u64,
+}
+
+impl Host for HermeticHost {
+    fn random_u64(&self) -> u64 {
+        self.seed
+    }
+}
+
+/// Builds hashers keyed by a seed instead of by the OS's random source, so
+/// that hashing is reproducible. Maps that aren't given a seed use zero.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct SeededState {
+    seed: u64,
+}
+
+impl SeededState {
+    pub(crate) fn new(seed: u64) -> SeededState {
+        SeededState { seed }
+    }
+}
+
+impl BuildHasher for SeededState {
+    type Hasher = DefaultHasher;
+
+    fn build_hasher(&self) -> DefaultHasher {
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u64(self.seed);
+
+        hasher
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+
+use crate::ast::{HostFnDecl, Type};
+use crate::vm::HostFunction;
+
+/// Rust function that can be bound as a host function, with its parameters
+/// and value converted from and to what programs pass around.
+///
+/// It's implemented for functions of up to four parameters, each an `i32` or a
+/// `bool`, that return an `i32`, a `bool`, `()`, or a `Result` of those with a
+/// `String` error, which stops the program. Sophia has no strings, so there's
+/// nothing to convert them from. The parameters are only there to tell the
+/// implementations apart.
+pub trait IntoHostFunction<Params> {
+    #[doc(hidden)]
+    fn bind(self, name: &str) -> HostFunctionBinding;
+}
+
+/// Function bound as the host function of a name, along with the declaration
+/// that programs calling it are compiled with.
+pub struct HostFunctionBinding {
+    pub(crate) decl: HostFnDecl,
+    pub(crate) function: HostFunction,
+}
+
+/// Type of a host function's parameter.
+pub(crate) trait HostParam {
+    const TYPE: Type;
+
+    fn from_arg(arg: i32) -> Self;
+}
+
+/// Type of a host function's value.
+pub(crate) trait HostReturn {
+    const TYPE: Type;
+
+    /// Converts the value to what the VM pushes, which it ignores for `()`.
+    fn into_result(self) -> Result<i32, String>;
+}
+
+impl HostParam for i32 {
+    const TYPE: Type = Type::I32;
+
+    fn from_arg(arg: i32) -> i32 {
+        arg
+    }
+}
+
+/// Programs have no booleans, so they're integers that are true unless zero,
+/// like conditions.
+impl HostParam for bool {
+    const TYPE: Type = Type::I32;
+
+    fn from_arg(arg: i32) -> bool {
+        arg != 0
+    }
+}
+
+impl HostReturn for i32 {
+    const TYPE: Type = Type::I32;
+
+    fn into_result(self) -> Result<i32, String> {
+        Ok(self)
+    }
+}
+
+impl HostReturn for bool {
+    const TYPE: Type = Type::I32;
+
+    fn into_result(self) -> Result<i32, String> {
+        Ok(self.into())
+    }
+}
+
+impl HostReturn for () {
+    const TYPE: Type = Type::Unit;
+
+    fn into_result(self) -> Result<i32, String> {
+        Ok(0)
+    }
+}
+
+impl<T: HostReturn> HostReturn for Result<T, String> {
+    const TYPE: Type = T::TYPE;
+
+    fn into_result(self) -> Result<i32, String> {
+        self.and_then(T::into_result)
+    }
+}
+
+macro_rules! impl_into_host_function {
+    ($($param:ident $arg:ident),*) => {
+        impl<F, R, $($param),*> IntoHostFunction<($($param,)*)> for F
+        where
+            F: FnMut($($param),*) -> R + 'static,
+            R: HostReturn,
+            $($param: HostParam,)*
+        {
+            fn bind(mut self, name: &str) -> HostFunctionBinding {
+                let param_types = vec![$($param::TYPE),*];
+                let arity = param_types.len();
+
+                HostFunctionBinding {
+                    decl: HostFnDecl {
+                        name: name.to_owned(),
+                        param_types,
+                        return_type: R::TYPE,
+                    },
+                    function: Box::new(move |args| {
+                        // The VM passes as many arguments as the module
+                        // imports, which binding checks against the
+                        // parameters.
+                        let [$($arg),*] = args else {
+                            return Err(format!("expected {} arguments", arity));
+                        };
+
+                        self($($param::from_arg(*$arg)),*).into_result()
+                    }),
+                }
+            }
+        }
+    };
+}
+
+impl_into_host_function!();
+impl_into_host_function!(A a);
+impl_into_host_function!(A a, B b);
+impl_into_host_function!(A a, B b, C c);
+impl_into_host_function!(A a, B b, C c, D d);
+
+/// Declaration of the function as a host function of the name, to compile
+/// programs that call it.
+// Only tests declare host functions without binding them.
+#[allow(dead_code)]
+pub(crate) fn decl_of<Params, F: IntoHostFunction<Params> + Clone>(
+    name: &str,
+    function: &F,
+) -> HostFnDecl {
+    function.clone().bind(name).decl
+}
+
+/// The function bound to a host function doesn't match how the module calls
+/// it.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum BindError {
+    MismatchedArity {
+        name: String,
+        expected: u32,
+        found: u32,
+    },
+    MismatchedReturnType {
+        name: String,
+        expected: Type,
+        found: Type,
+    },
+}
+
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindError::MismatchedArity {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "host function `{}` is called with {} arguments, but the bound function takes {}",
+                name, expected, found
+            ),
+            BindError::MismatchedReturnType {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "host function `{}` returns `{}`, but the bound function returns `{}`",
+                name, expected, found
+            ),
+        }
+    }
+}
@@ -0,0 +1,633 @@
+use std::cell::OnceCell;
+
+use crate::ast::{
+    ArrayExpr, BinaryExpr, BindDef, BindRef, Binding, BreakExpr, CastExpr, CompoundExpr, ConstExpr,
+    ContinueExpr, Decl, ElseIfBranch, EnumDef, Expr, ExternFn, FieldDef, FieldExpr, FieldInit,
+    FnCallExpr, ForExpr, ForIteration, Function, IfExpr, IndexAssignExpr, IndexExpr, MatchArm,
+    MatchExpr, Param, ParenExpr, Pattern, PatternKind, Program, StructDef, StructLitExpr,
+    TupleBindDef, TupleExpr, TupleFieldExpr, VariantDef, VariantExpr, VariantPattern,
+};
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::Diagnostic;
+use crate::driver::{self, Options};
+use crate::limits::Limits;
+use crate::parser::Parser;
+use crate::scanner::{BytePos, Scanner, Span};
+
+/// Edit of a program's source code, which replaces the code in `range` with
+/// `new_text`.
+#[derive(Clone, Debug)]
+pub(crate) struct TextEdit {
+    pub(crate) range: Span,
+    pub(crate) new_text: String,
+}
+
+impl TextEdit {
+    /// Edit that turns the old text into the new one, which replaces the code
+    /// in between what they start and end with alike, e.g., the code that was
+    /// typed in.
+    pub(crate) fn between(old_text: &str, new_text: &str) -> TextEdit {
+        // The ends of what they have alike are moved back to where chars
+        // start, where they do in both texts, as their bytes are the same up
+        // to there.
+        let mut prefix_len = common_len(old_text.bytes(), new_text.bytes());
+
+        while !old_text.is_char_boundary(prefix_len) {
+            prefix_len -= 1;
+        }
+
+        let mut suffix_len = common_len(old_text.bytes().rev(), new_text.bytes().rev())
+            .min(old_text.len().min(new_text.len()) - prefix_len);
+
+        while !old_text.is_char_boundary(old_text.len() - suffix_len) {
+            suffix_len -= 1;
+        }
+
+        TextEdit {
+            range: Span {
+                start: BytePos(prefix_len),
+                end: BytePos(old_text.len() - suffix_len),
+            },
+            new_text: new_text[prefix_len..new_text.len() - suffix_len].to_owned(),
+        }
+    }
+}
+
+/// How many bytes the texts start with alike.
+fn common_len(old_bytes: impl Iterator<Item = u8>, new_bytes: impl Iterator<Item = u8>) -> usize {
+    old_bytes
+        .zip(new_bytes)
+        .take_while(|(old_byte, new_byte)| old_byte == new_byte)
+        .count()
+}
+
+/// How `IncrementalParse::apply_edit` parsed the edited source code.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Reparse {
+    /// Only the code of the declarations that the edit touched was lexed and
+    /// parsed again. The declarations before them were reused as they were,
+    /// and the ones after them are to be moved to where the edit left them,
+    /// see `IncrementalParse::program`.
+    Incremental {
+        reparsed_decls: usize,
+        reused_decls: usize,
+        moved_decls: usize,
+    },
+    /// The whole source code was parsed again, as the last parse failed, or
+    /// the code that the edit touched doesn't parse on its own.
+    Full,
+}
+
+/// Declaration as it was parsed, whose spans are `delta` bytes off from
+/// where the edits since then left its code.
+#[derive(Clone, Copy)]
+pub(crate) struct MovedDecl<'ctx> {
+    pub(crate) decl: Decl<'ctx>,
+    pub(crate) delta: isize,
+}
+
+impl MovedDecl<'_> {
+    /// Where the code of the span in the declaration is now.
+    pub(crate) fn move_span(&self, span: Span) -> Span {
+        move_span(span, self.delta)
+    }
+
+    pub(crate) fn span(&self) -> Span {
+        self.move_span(decl_span(&self.decl))
+    }
+}
+
+/// Parse of a program that's kept up to date as its source code is edited,
+/// e.g., by an editor, so that an edit takes about as long to parse as the
+/// declarations it touches, rather than the whole program.
+///
+/// The declarations after an edit are only moved once the program is asked
+/// for, so edits in between move them once, and asking for where they are
+/// moves nothing, see `IncrementalParse::decls`.
+///
+/// The edited source code is kept here, as the context's is the one the parse
+/// started from, so diagnostics are to be rendered against
+/// `IncrementalParse::source_code`. Every parse is allocated in the context's
+/// arenas, which only grow, so long editing sessions are to start over with a
+/// new context now and then.
+pub(crate) struct IncrementalParse<'ctx> {
+    ctx: &'ctx CompilerContext,
+    limits: Limits,
+    source_code: String,
+    parse: Result<Vec<MovedDecl<'ctx>>, Diagnostic>,
+    /// The program with its declarations moved, once it's asked for.
+    program: OnceCell<Program<'ctx>>,
+}
+
+impl<'ctx> IncrementalParse<'ctx> {
+    /// Parses the source code of the context, which can't be a script, as the
+    /// statements of scripts aren't in declarations of their own.
+    pub(crate) fn new(ctx: &'ctx CompilerContext, options: &Options) -> IncrementalParse<'ctx> {
+        debug_assert!(!options.script_mode, "scripts are parsed as a whole");
+
+        let (parse, program) = unmoved(driver::parse(ctx, options));
+
+        IncrementalParse {
+            ctx,
+            limits: options.limits,
+            source_code: ctx.get_source_code().to_owned(),
+            parse,
+            program,
+        }
+    }
+
+    pub(crate) fn source_code(&self) -> &str {
+        &self.source_code
+    }
+
+    /// Declarations of the program, which are yet to be moved.
+    pub(crate) fn decls(&self) -> Result<&[MovedDecl<'ctx>], &Diagnostic> {
+        self.parse.as_deref()
+    }
+
+    pub(crate) fn program(&self) -> Result<Program<'ctx>, &Diagnostic> {
+        let decls = self.decls()?;
+
+        Ok(*self.program.get_or_init(|| {
+            let moved_decls: Vec<_> = decls
+                .iter()
+                .map(|moved_decl| match moved_decl.delta {
+                    0 => moved_decl.decl,
+                    delta => SpanMover {
+                        ctx: self.ctx,
+                        delta,
+                    }
+                    .move_decl(&moved_decl.decl),
+                })
+                .collect();
+
+            Program {
+                decls: self.ctx.alloc_slice_of_decl(&moved_decls),
+            }
+        }))
+    }
+
+    pub(crate) fn apply_edit(&mut self, edit: &TextEdit) -> Reparse {
+        let Span {
+            start: edit_start,
+            end: edit_end,
+        } = edit.range;
+
+        self.source_code
+            .replace_range(edit_start.0..edit_end.0, &edit.new_text);
+
+        // What was moved already is where the edit starts from.
+        if let Some(program) = self.program.take() {
+            self.parse = Ok(unmoved_decls(program));
+        }
+
+        let Ok(decls) = &self.parse else {
+            return self.reparse_fully();
+        };
+
+        // Only a full parse tells whether the code is too large.
+        if self.source_code.len() > self.limits.max_source_bytes {
+            return self.reparse_fully();
+        }
+
+        // Declarations that the edit touches, which include the ones it's only
+        // next to, as it may extend them.
+        let first_touched = decls.partition_point(|decl| decl.span().end.0 < edit_start.0);
+        let last_touched = decls.partition_point(|decl| decl.span().start.0 <= edit_end.0);
+        let touched_decls = &decls[first_touched..last_touched];
+
+        let (region_start, region_end) = match (touched_decls.first(), touched_decls.last()) {
+            (Some(first_decl), Some(last_decl)) => (
+                BytePos(first_decl.span().start.0.min(edit_start.0)),
+                BytePos(last_decl.span().end.0.max(edit_end.0)),
+            ),
+            _ => (edit_start, edit_end),
+        };
+
+        let delta = edit.new_text.len() as isize - (edit_end.0 - edit_start.0) as isize;
+        let region_text =
+            &self.source_code[region_start.0..region_end.0.wrapping_add_signed(delta)];
+
+        // A declaration can't end past the identifier that starts the next one,
+        // so code that parses on its own parses the same as part of the program.
+        // That is, unless the parser stopped before the code's end, which it
+        // does at an attribute it doesn't know, and which would leave out the
+        // rest of the program too.
+        let parse = Parser::new(
+            Scanner::for_text(region_text, region_start),
+            self.ctx,
+            self.limits,
+        )
+        .parse_program();
+        let code_end = BytePos(region_start.0 + region_text.trim_end().len());
+
+        let Some(region_program) = parse.ok().filter(|region_program| {
+            let parsed_end = region_program
+                .decls
+                .last()
+                .map_or(region_start, |decl| decl_span(decl).end);
+
+            parsed_end == code_end
+        }) else {
+            return self.reparse_fully();
+        };
+
+        let moved_decls = decls[last_touched..].iter().map(|moved_decl| MovedDecl {
+            decl: moved_decl.decl,
+            delta: moved_decl.delta + delta,
+        });
+
+        let mut new_decls = decls[..first_touched].to_vec();
+        new_decls.extend((region_program.decls.iter()).map(|&decl| MovedDecl { decl, delta: 0 }));
+        new_decls.extend(moved_decls);
+
+        let reparse = Reparse::Incremental {
+            reparsed_decls: region_program.decls.len(),
+            reused_decls: first_touched,
+            moved_decls: decls.len() - last_touched,
+        };
+        self.parse = Ok(new_decls);
+
+        reparse
+    }
+
+    fn reparse_fully(&mut self) -> Reparse {
+        let parse = driver::check_source_size(&self.source_code, &self.limits).and_then(|()| {
+            Parser::new(
+                Scanner::for_text(&self.source_code, BytePos(0)),
+                self.ctx,
+                self.limits,
+            )
+            .parse_program()
+        });
+        (self.parse, self.program) = unmoved(parse);
+
+        Reparse::Full
+    }
+}
+
+/// Declarations of the parse, which are where they are, along with the
+/// program that's made of them.
+#[allow(clippy::type_complexity)]
+fn unmoved<'ctx>(
+    parse: Result<Program<'ctx>, Diagnostic>,
+) -> (
+    Result<Vec<MovedDecl<'ctx>>, Diagnostic>,
+    OnceCell<Program<'ctx>>,
+) {
+    match parse {
+        Ok(program) => (Ok(unmoved_decls(program)), OnceCell::from(program)),
+        Err(diagnostic) => (Err(diagnostic), OnceCell::new()),
+    }
+}
+
+fn unmoved_decls(program: Program) -> Vec<MovedDecl> {
+    (program.decls.iter())
+        .map(|&decl| MovedDecl { decl, delta: 0 })
+        .collect()
+}
+
+/// Span of the declaration's code, from its name, or its `extern`, to the
+/// end of its value.
+pub(crate) fn decl_span(decl: &Decl) -> Span {
+    let value_span = decl.value.span();
+
+    Span {
+        start: BytePos(decl.identifier_span.start.0.min(value_span.start.0)),
+        end: value_span.end,
+    }
+}
+
+/// Moves the spans of declarations by as much as the edits before them
+/// changed the length of the source code.
+struct SpanMover<'ctx> {
+    ctx: &'ctx CompilerContext,
+    delta: isize,
+}
+
+impl<'ctx> SpanMover<'ctx> {
+    fn move_decl(&self, decl: &Decl<'ctx>) -> Decl<'ctx> {
+        Decl {
+            identifier: decl.identifier,
+            identifier_span: self.move_span(decl.identifier_span),
+            value: self.move_expr_ref(decl.value),
+        }
+    }
+
+    fn move_expr_ref(&self, expr: &Expr<'ctx>) -> &'ctx Expr<'ctx> {
+        self.ctx.alloc_expr(self.move_expr(expr))
+    }
+
+    fn move_exprs(&self, exprs: &[Expr<'ctx>]) -> &'ctx [Expr<'ctx>] {
+        let moved_exprs: Vec<_> = exprs.iter().map(|expr| self.move_expr(expr)).collect();
+
+        self.ctx.alloc_slice_of_expr(&moved_exprs)
+    }
+
+    fn move_params(&self, params: &[Param]) -> &'ctx [Param] {
+        let moved_params: Vec<_> = params
+            .iter()
+            .map(|param| Param {
+                span: self.move_span(param.span),
+                ..*param
+            })
+            .collect();
+
+        self.ctx.alloc_slice_of_param(&moved_params)
+    }
+
+    fn move_expr(&self, expr: &Expr<'ctx>) -> Expr<'ctx> {
+        match *expr {
+            Expr::Const(const_expr) => Expr::Const(ConstExpr {
+                span: self.move_span(const_expr.span),
+                ..const_expr
+            }),
+            Expr::BindRef(bind_ref) => Expr::BindRef(BindRef {
+                span: self.move_span(bind_ref.span),
+                ..bind_ref
+            }),
+            Expr::BindDef(bind_def) => Expr::BindDef(BindDef {
+                value: self.move_expr_ref(bind_def.value),
+                span: self.move_span(bind_def.span),
+                ..bind_def
+            }),
+            Expr::Function(function) => Expr::Function(Function {
+                parameters: self.move_params(function.parameters),
+                body: self.move_compound_expr(function.body),
+                span: self.move_span(function.span),
+                ..function
+            }),
+            Expr::Extern(extern_fn) => Expr::Extern(ExternFn {
+                parameters: self.move_params(extern_fn.parameters),
+                span: self.move_span(extern_fn.span),
+                ..extern_fn
+            }),
+            Expr::Struct(struct_def) => {
+                let fields: Vec<_> = struct_def
+                    .fields
+                    .iter()
+                    .map(|field| FieldDef {
+                        span: self.move_span(field.span),
+                        ..*field
+                    })
+                    .collect();
+
+                Expr::Struct(StructDef {
+                    fields: self.ctx.alloc_slice_of_field_def(&fields),
+                    span: self.move_span(struct_def.span),
+                })
+            }
+            Expr::StructLit(struct_lit_expr) => {
+                let fields: Vec<_> = struct_lit_expr
+                    .fields
+                    .iter()
+                    .map(|field| FieldInit {
+                        identifier: field.identifier,
+                        identifier_span: self.move_span(field.identifier_span),
+                        value: self.move_expr_ref(field.value),
+                    })
+                    .collect();
+
+                Expr::StructLit(StructLitExpr {
+                    identifier_span: self.move_span(struct_lit_expr.identifier_span),
+                    fields: self.ctx.alloc_slice_of_field_init(&fields),
+                    span: self.move_span(struct_lit_expr.span),
+                    ..struct_lit_expr
+                })
+            }
+            Expr::Field(field_expr) => Expr::Field(FieldExpr {
+                base: self.move_expr_ref(field_expr.base),
+                identifier_span: self.move_span(field_expr.identifier_span),
+                span: self.move_span(field_expr.span),
+                ..field_expr
+            }),
+            Expr::Enum(enum_def) => {
+                let variants: Vec<_> = enum_def
+                    .variants
+                    .iter()
+                    .map(|variant| VariantDef {
+                        span: self.move_span(variant.span),
+                        ..*variant
+                    })
+                    .collect();
+
+                Expr::Enum(EnumDef {
+                    variants: self.ctx.alloc_slice_of_variant_def(&variants),
+                    span: self.move_span(enum_def.span),
+                })
+            }
+            Expr::Variant(variant_expr) => Expr::Variant(VariantExpr {
+                enum_identifier_span: self.move_span(variant_expr.enum_identifier_span),
+                variant_identifier_span: self.move_span(variant_expr.variant_identifier_span),
+                payload: variant_expr
+                    .payload
+                    .map(|payload| self.move_expr_ref(payload)),
+                span: self.move_span(variant_expr.span),
+                ..variant_expr
+            }),
+            Expr::Tuple(tuple_expr) => Expr::Tuple(TupleExpr {
+                elements: self.move_exprs(tuple_expr.elements),
+                span: self.move_span(tuple_expr.span),
+            }),
+            Expr::TupleField(tuple_field_expr) => Expr::TupleField(TupleFieldExpr {
+                base: self.move_expr_ref(tuple_field_expr.base),
+                index_span: self.move_span(tuple_field_expr.index_span),
+                span: self.move_span(tuple_field_expr.span),
+                ..tuple_field_expr
+            }),
+            Expr::TupleBindDef(tuple_bind_def) => {
+                let bindings: Vec<_> = tuple_bind_def
+                    .bindings
+                    .iter()
+                    .map(|binding| Binding {
+                        span: self.move_span(binding.span),
+                        ..*binding
+                    })
+                    .collect();
+
+                Expr::TupleBindDef(TupleBindDef {
+                    bindings: self.ctx.alloc_slice_of_binding(&bindings),
+                    value: self.move_expr_ref(tuple_bind_def.value),
+                    span: self.move_span(tuple_bind_def.span),
+                })
+            }
+            Expr::Array(array_expr) => Expr::Array(ArrayExpr {
+                elements: self.move_exprs(array_expr.elements),
+                span: self.move_span(array_expr.span),
+            }),
+            Expr::Index(index_expr) => Expr::Index(self.move_index_expr(index_expr)),
+            Expr::IndexAssign(index_assign_expr) => Expr::IndexAssign(IndexAssignExpr {
+                target: self.move_index_expr(index_assign_expr.target),
+                value: self.move_expr_ref(index_assign_expr.value),
+                span: self.move_span(index_assign_expr.span),
+            }),
+            Expr::If(if_expr) => Expr::If(self.move_if_expr(if_expr)),
+            Expr::Match(match_expr) => Expr::Match(self.move_match_expr(match_expr)),
+            Expr::For(for_expr) => Expr::For(self.move_for_expr(for_expr)),
+            Expr::Break(break_expr) => Expr::Break(BreakExpr {
+                value: break_expr.value.map(|value| self.move_expr_ref(value)),
+                span: self.move_span(break_expr.span),
+            }),
+            Expr::Continue(continue_expr) => Expr::Continue(ContinueExpr {
+                span: self.move_span(continue_expr.span),
+            }),
+            Expr::Compound(compound_expr) => Expr::Compound(self.move_compound_expr(compound_expr)),
+            Expr::Semi(expr) => Expr::Semi(self.move_expr_ref(expr)),
+            Expr::FnCall(fn_call_expr) => Expr::FnCall(FnCallExpr {
+                args: self.move_exprs(fn_call_expr.args),
+                span: self.move_span(fn_call_expr.span),
+                ..fn_call_expr
+            }),
+            Expr::Binary(binary_expr) => {
+                let (operand, operations) = binary_expr.chain();
+                let mut lhs = self.move_expr(operand);
+
+                for binary_expr in operations {
+                    lhs = Expr::Binary(BinaryExpr {
+                        lhs: self.ctx.alloc_expr(lhs),
+                        rhs: self.move_expr_ref(binary_expr.rhs),
+                        span: self.move_span(binary_expr.span),
+                        ..binary_expr
+                    });
+                }
+
+                lhs
+            }
+            Expr::Cast(cast_expr) => Expr::Cast(CastExpr {
+                expr: self.move_expr_ref(cast_expr.expr),
+                span: self.move_span(cast_expr.span),
+                ..cast_expr
+            }),
+            Expr::Paren(paren_expr) => Expr::Paren(ParenExpr {
+                expr: self.move_expr_ref(paren_expr.expr),
+                span: self.move_span(paren_expr.span),
+            }),
+        }
+    }
+
+    fn move_index_expr(&self, index_expr: IndexExpr<'ctx>) -> IndexExpr<'ctx> {
+        IndexExpr {
+            base: self.move_expr_ref(index_expr.base),
+            index: self.move_expr_ref(index_expr.index),
+            span: self.move_span(index_expr.span),
+        }
+    }
+
+    fn move_if_expr(&self, if_expr: IfExpr<'ctx>) -> IfExpr<'ctx> {
+        let else_if_branches: Vec<_> = if_expr
+            .else_if_branches
+            .iter()
+            .map(|branch| ElseIfBranch {
+                cond_expr: self.move_expr_ref(branch.cond_expr),
+                true_branch: self.move_compound_expr(branch.true_branch),
+            })
+            .collect();
+
+        IfExpr {
+            cond_expr: self.move_expr_ref(if_expr.cond_expr),
+            true_branch: self.move_compound_expr(if_expr.true_branch),
+            else_if_branches: self.ctx.alloc_slice_of_else_if_branch(&else_if_branches),
+            final_branch: if_expr
+                .final_branch
+                .map(|branch| self.move_compound_expr(branch)),
+            span: self.move_span(if_expr.span),
+        }
+    }
+
+    fn move_match_expr(&self, match_expr: MatchExpr<'ctx>) -> MatchExpr<'ctx> {
+        let arms: Vec<_> = match_expr
+            .arms
+            .iter()
+            .map(|arm| MatchArm {
+                pattern: Pattern {
+                    kind: self.move_pattern_kind(arm.pattern.kind),
+                    span: self.move_span(arm.pattern.span),
+                },
+                expr: self.move_expr_ref(arm.expr),
+            })
+            .collect();
+
+        MatchExpr {
+            scrutinee: self.move_expr_ref(match_expr.scrutinee),
+            arms: self.ctx.alloc_slice_of_match_arm(&arms),
+            span: self.move_span(match_expr.span),
+        }
+    }
+
+    fn move_pattern_kind(&self, kind: PatternKind) -> PatternKind {
+        match kind {
+            PatternKind::Variant(variant_pattern) => PatternKind::Variant(VariantPattern {
+                enum_name_span: self.move_span(variant_pattern.enum_name_span),
+                variant_name_span: self.move_span(variant_pattern.variant_name_span),
+                binding: variant_pattern
+                    .binding
+                    .map(|(name, span)| (name, self.move_span(span))),
+                ..variant_pattern
+            }),
+            PatternKind::Wildcard | PatternKind::Integer(_) | PatternKind::Range { .. } => kind,
+        }
+    }
+
+    fn move_for_expr(&self, for_expr: ForExpr<'ctx>) -> ForExpr<'ctx> {
+        let iteration = for_expr.iteration.map(|iteration| match iteration {
+            ForIteration::Conditional { cond_expr } => ForIteration::Conditional {
+                cond_expr: self.move_expr_ref(cond_expr),
+            },
+            ForIteration::Iterative {
+                identifier,
+                identifier_span,
+                start_expr,
+                end_expr,
+                range_kind,
+                step_expr,
+                is_reversed,
+            } => ForIteration::Iterative {
+                identifier,
+                identifier_span: self.move_span(identifier_span),
+                start_expr: self.move_expr_ref(start_expr),
+                end_expr: self.move_expr_ref(end_expr),
+                range_kind,
+                step_expr: step_expr.map(|step_expr| self.move_expr_ref(step_expr)),
+                is_reversed,
+            },
+            ForIteration::InCollection {
+                identifier,
+                identifier_span,
+                collection_expr,
+            } => ForIteration::InCollection {
+                identifier,
+                identifier_span: self.move_span(identifier_span),
+                collection_expr: self.move_expr_ref(collection_expr),
+            },
+        });
+
+        ForExpr {
+            for_kw_span: self.move_span(for_expr.for_kw_span),
+            iteration,
+            body: self.move_compound_expr(for_expr.body),
+            else_branch: for_expr
+                .else_branch
+                .map(|branch| self.move_compound_expr(branch)),
+            span: self.move_span(for_expr.span),
+            ..for_expr
+        }
+    }
+
+    fn move_compound_expr(&self, compound_expr: CompoundExpr<'ctx>) -> CompoundExpr<'ctx> {
+        CompoundExpr {
+            exprs: self.move_exprs(compound_expr.exprs),
+            span: self.move_span(compound_expr.span),
+        }
+    }
+
+    fn move_span(&self, span: Span) -> Span {
+        move_span(span, self.delta)
+    }
+}
+
+fn move_span(span: Span, delta: isize) -> Span {
+    Span {
+        start: BytePos(span.start.0.wrapping_add_signed(delta)),
+        end: BytePos(span.end.0.wrapping_add_signed(delta)),
+    }
+}
@@ -1,8 +1,10 @@
-use std::collections::HashMap;
-
 use bumpalo::Bump;
 
-#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+use crate::ast::DISCARD_IDENTIFIER;
+use crate::builtin::Builtin;
+use crate::host::{HashMap, SeededState};
+
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
 pub(crate) struct Symbol(usize);
 
 pub(crate) struct StringInterner {
@@ -11,16 +13,20 @@ pub(crate) struct StringInterner {
     buffer: Bump,
 }
 
+// SAFETY: `Bump` isn't `Sync`, as it allocates through shared references, but
+// `buffer` is only ever allocated in through `&mut self`.
+unsafe impl Sync for StringInterner {}
+
 impl Default for StringInterner {
     fn default() -> StringInterner {
-        StringInterner::new()
+        StringInterner::with_seed(0)
     }
 }
 
 impl StringInterner {
-    pub(crate) fn new() -> StringInterner {
+    pub(crate) fn with_seed(seed: u64) -> StringInterner {
         StringInterner {
-            interned_strs: Default::default(),
+            interned_strs: HashMap::with_hasher(SeededState::new(seed)),
             indexed_strs: Default::default(),
             buffer: Default::default(),
         }
@@ -52,8 +58,8 @@ impl StringInterner {
                     let allocated_str = self.buffer.alloc_str(string);
 
                     unsafe {
-                        // SAFETY: The memory allocated by `buffer` will live for as long as the
-                        // program does, so, for practical purposes, it is essentially static.
+                        // SAFETY: `buffer` is only dropped along with the interner, and
+                        // strings borrowing from it are copied before they escape it.
                         &*(allocated_str as *const str)
                     }
                 };
@@ -73,4 +79,45 @@ impl StringInterner {
             self.indexed_strs.get_unchecked(symbol.0)
         }
     }
+
+    /// How many strings were interned.
+    pub(crate) fn len(&self) -> usize {
+        self.indexed_strs.len()
+    }
+
+    /// Memory taken up by the interned strings, in bytes.
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.buffer.allocated_bytes()
+    }
+}
+
+/// Symbols of the names that the compiler itself looks for, interned along
+/// with the context, so that names are compared to them without being
+/// resolved. Keywords, primitive types included, aren't among them, as the
+/// scanner tells them apart from names already.
+pub(crate) struct Symbols {
+    pub(crate) main: Symbol,
+    pub(crate) discard: Symbol,
+    /// Symbols of the builtins, in the order of `Builtin::ALL`.
+    builtins: [Symbol; Builtin::ALL.len()],
+}
+
+impl Symbols {
+    pub(crate) fn intern(string_interner: &mut StringInterner) -> Symbols {
+        Symbols {
+            main: string_interner.get_or_intern("main"),
+            discard: string_interner.get_or_intern(DISCARD_IDENTIFIER),
+            builtins: Builtin::ALL.map(|builtin| string_interner.get_or_intern(builtin.name())),
+        }
+    }
+
+    /// Builtin of the name, if it's the name of one.
+    pub(crate) fn builtin(&self, symbol: Symbol) -> Option<Builtin> {
+        let idx = self
+            .builtins
+            .iter()
+            .position(|&builtin| builtin == symbol)?;
+
+        Some(Builtin::ALL[idx])
+    }
 }
@@ -0,0 +1,1476 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::rc::Rc;
+use std::time::Instant;
+use std::{hint, mem, panic, thread};
+
+use crate::ast::{
+    ArrayExpr, BinaryExpr, BinaryOp, CompoundExpr, Const, Expr, ExternFn, FieldExpr, FnCallExpr,
+    ForExpr, ForIteration, Function, IfExpr, IndexAssignExpr, MatchExpr, PatternKind, Program,
+    RangeKind, StructLitExpr, TupleExpr, Type, VariantExpr, DISCARD_IDENTIFIER,
+};
+use crate::builtin::Builtin;
+use crate::compiler_context::CompilerContext;
+use crate::interner::Symbol;
+use crate::libc::LibcFn;
+use crate::profile::IntProfile;
+use crate::profiler::{ExecutionProfile, Profiler};
+use crate::resolve::Resolutions;
+use crate::scanner::Span;
+use crate::vm::{ExecutionLimit, ExecutionLimits, INSTRUCTIONS_PER_CLOCK_CHECK};
+
+/// Size of the stack of the thread that the interpreter runs on, which it
+/// recurses on for every call and nested expression. Even a call of a small
+/// function takes up kilobytes of it, so the stacks that threads get by
+/// default, of 8 MiB for the main thread and of 2 MiB for others, would run
+/// out long before `ExecutionLimits::max_call_depth` is reached.
+const STACK_SIZE: usize = 512 << 20;
+
+/// Part of the stack left for what runs between two checks of how much of it
+/// is used, e.g., builtins, and for the start of the thread.
+const STACK_RED_ZONE: usize = 1 << 20;
+
+/// Evaluates a program by walking its AST, without compiling it first.
+pub(crate) struct Interpreter<'ctx> {
+    ctx: &'ctx CompilerContext,
+    resolutions: &'ctx Resolutions,
+    int_profile: IntProfile,
+    functions: HashMap<Symbol, Function<'ctx>>,
+    /// Extern functions that the program declares, which only those of
+    /// `LibcFn` can be called of.
+    extern_fns: HashMap<Symbol, ExternFn<'ctx>>,
+    /// Closures that the program made so far, by their spans.
+    closures: HashMap<Span, Function<'ctx>>,
+    scope_stack: Vec<HashMap<Symbol, Value>>,
+    /// Where builtins print to.
+    output: Rc<RefCell<dyn io::Write>>,
+    /// Records where the program spends its time, if it's being profiled.
+    profiler: Option<Profiler>,
+    limits: ExecutionLimits,
+    /// When the run goes over the timeout of the limits, if there's one.
+    deadline: Option<Instant>,
+    /// How many expressions were evaluated so far, which count as the
+    /// instructions of the limits.
+    evaluated_exprs: u64,
+    call_depth: usize,
+    /// Address that the stack of the interpreter's thread started at, if it's
+    /// running on one of `STACK_SIZE` bytes.
+    stack_start: Option<usize>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Value {
+    Unit,
+    Int(i32),
+    /// Integer of a type other than `i32`, which is never outside of its
+    /// type's range.
+    OtherInt {
+        value: i128,
+        ty: Type,
+    },
+    /// Values of a struct's fields, in the order its literal initialized them.
+    /// They're shared, as values are copied whenever they're bound or passed.
+    Struct(Rc<[(&'static str, Value)]>),
+    /// Variant of an enum, along with the value it holds, if any, which is
+    /// shared like a struct's fields are.
+    Variant {
+        variant: &'static str,
+        payload: Option<Rc<Value>>,
+    },
+    /// Values of a tuple's elements, in order, which are shared like a
+    /// struct's fields are.
+    Tuple(Rc<[Value]>),
+    /// Values of an array's elements, in order, which are shared like a
+    /// struct's fields are, until an element is assigned to.
+    Array(Rc<[Value]>),
+    /// Function declared with this name.
+    Function(Symbol),
+    /// Function written where a value is expected, along with the values it
+    /// captured there, which are shared like a struct's fields are.
+    Closure(Rc<Closure>),
+}
+
+/// Values of the bindings that the inputs of a REPL session defined so far,
+/// which later inputs start with, rather than running the statements of the
+/// earlier ones again. Bindings are keyed by the symbols of their names, so
+/// every input of a session must be compiled with the same context.
+#[derive(Clone, Default)]
+pub(crate) struct ReplBindings(HashMap<Symbol, Value>);
+
+#[derive(PartialEq, Debug)]
+pub(crate) struct Closure {
+    /// Span of the function expression that made the closure, which tells
+    /// its code apart from other closures'.
+    function_span: Span,
+    /// Values of the bindings that the closure refers to, as they were when
+    /// it was made, so that assigning to them later doesn't change them.
+    captures: Vec<(Symbol, Value)>,
+}
+
+impl Value {
+    /// Value of an integer that fits in an `i32`, as the ones that type
+    /// checking lets widen into `i32` do.
+    fn as_int(&self) -> i32 {
+        match *self {
+            Value::Int(value) => value,
+            Value::OtherInt { value, .. } => {
+                i32::try_from(value).expect("type checking guarantees an `i32`")
+            }
+            Value::Unit
+            | Value::Struct(_)
+            | Value::Variant { .. }
+            | Value::Tuple(_)
+            | Value::Array(_)
+            | Value::Function(_)
+            | Value::Closure(_) => {
+                unreachable!("type checking guarantees an integer")
+            }
+        }
+    }
+
+    /// Value of an integer of any type, along with its type.
+    fn as_typed_int(&self) -> (i128, Type) {
+        match *self {
+            Value::Int(value) => (value.into(), Type::I32),
+            Value::OtherInt { value, ty } => (value, ty),
+            Value::Unit
+            | Value::Struct(_)
+            | Value::Variant { .. }
+            | Value::Tuple(_)
+            | Value::Array(_)
+            | Value::Function(_)
+            | Value::Closure(_) => {
+                unreachable!("type checking guarantees an integer")
+            }
+        }
+    }
+
+    /// The same value as one of the type, which it widens into, if it's an
+    /// integer. Integers are widened wherever they're given a type, so that
+    /// they overflow as values of that type do.
+    fn widened_to(self, ty: Type) -> Value {
+        if !ty.is_int() {
+            return self;
+        }
+
+        let (value, _) = self.as_typed_int();
+
+        Value::of_int(value, ty)
+    }
+
+    /// Value of the integer type, which the value is expected to fit in.
+    fn of_int(value: i128, ty: Type) -> Value {
+        if ty == Type::I32 {
+            Value::Int(value as i32)
+        } else {
+            Value::OtherInt { value, ty }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum RuntimeError {
+    MissingMain,
+    MainTakesParameters {
+        span: Span,
+    },
+    DivisionByZero {
+        span: Span,
+    },
+    Overflow {
+        op: BinaryOp,
+        span: Span,
+    },
+    IndexOutOfBounds {
+        index: i32,
+        len: usize,
+        span: Span,
+    },
+    /// The step of a range loop is zero or negative, so that the loop would
+    /// never end.
+    NonPositiveStep {
+        span: Span,
+    },
+    /// A bytecode module did something its validation when loaded doesn't
+    /// rule out, e.g., popping off an empty stack.
+    InvalidBytecode {
+        reason: &'static str,
+    },
+    /// The VM was asked to call a function the module doesn't have.
+    UndefinedFunction {
+        name: String,
+    },
+    MismatchedArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// The module imports a host function that the embedder didn't register.
+    UndefinedHostFunction {
+        name: String,
+    },
+    HostFunctionFailed {
+        name: String,
+        message: String,
+    },
+    ExecutionLimitExceeded {
+        limit: ExecutionLimit,
+    },
+    /// A replayed program called a host function other than the one the
+    /// recorded program called next, or with other arguments.
+    ReplayDiverged {
+        name: String,
+    },
+    /// A snapshot doesn't fit the module of the VM resuming it.
+    InvalidSnapshot {
+        reason: &'static str,
+    },
+    /// The program called an extern function that isn't one of `LibcFn`, which
+    /// only native code can call.
+    UnsupportedExternFn {
+        name: String,
+        span: Span,
+    },
+    /// A builtin couldn't write what it prints to the program's output.
+    OutputFailed {
+        message: String,
+    },
+    /// Cranelift failed to compile the program to machine code.
+    #[cfg(feature = "jit")]
+    Jit {
+        message: String,
+    },
+}
+
+impl RuntimeError {
+    /// Span of the code that failed, if the error is about some code of the
+    /// program rather than the whole of it.
+    pub(crate) fn span(&self) -> Option<Span> {
+        match self {
+            RuntimeError::MainTakesParameters { span }
+            | RuntimeError::DivisionByZero { span }
+            | RuntimeError::Overflow { span, .. }
+            | RuntimeError::IndexOutOfBounds { span, .. }
+            | RuntimeError::NonPositiveStep { span }
+            | RuntimeError::UnsupportedExternFn { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::MissingMain => write!(f, "`main` function not found"),
+            RuntimeError::MainTakesParameters { .. } => {
+                write!(f, "`main` function can't take parameters")
+            }
+            RuntimeError::DivisionByZero { .. } => write!(f, "attempt to divide by zero"),
+            RuntimeError::Overflow { op, .. } => {
+                write!(f, "attempt to compute `{}` with overflow", op)
+            }
+            RuntimeError::IndexOutOfBounds { index, len, .. } => {
+                write!(
+                    f,
+                    "index out of bounds: the length is {} but the index is {}",
+                    len, index
+                )
+            }
+            RuntimeError::NonPositiveStep { .. } => {
+                write!(f, "attempt to step a range by zero or less")
+            }
+            RuntimeError::InvalidBytecode { reason } => write!(f, "invalid bytecode: {}", reason),
+            RuntimeError::UndefinedFunction { name } => {
+                write!(f, "function `{}` not found", name)
+            }
+            RuntimeError::MismatchedArgumentCount {
+                name,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "function `{}` takes {} arguments, but {} were given",
+                    name, expected, found
+                )
+            }
+            RuntimeError::UndefinedHostFunction { name } => {
+                write!(f, "host function `{}` isn't registered", name)
+            }
+            RuntimeError::HostFunctionFailed { name, message } => {
+                write!(f, "host function `{}` failed: {}", name, message)
+            }
+            RuntimeError::ExecutionLimitExceeded { limit } => {
+                write!(f, "program exceeded the limit of {}", limit)
+            }
+            RuntimeError::ReplayDiverged { name } => {
+                write!(
+                    f,
+                    "replay diverged from the recorded run at a call to host function `{}`",
+                    name
+                )
+            }
+            RuntimeError::InvalidSnapshot { reason } => {
+                write!(f, "can't resume snapshot, as {}", reason)
+            }
+            RuntimeError::UnsupportedExternFn { name, .. } => {
+                write!(
+                    f,
+                    "extern function `{}` can only be called from native code",
+                    name
+                )
+            }
+            RuntimeError::OutputFailed { message } => {
+                write!(f, "couldn't write the program's output: {}", message)
+            }
+            #[cfg(feature = "jit")]
+            RuntimeError::Jit { message } => write!(f, "JIT compilation failed: {}", message),
+        }
+    }
+}
+
+/// Reason why the evaluation of an expression stopped before producing a
+/// value.
+enum Interrupt {
+    /// Exits the innermost loop, with the value the `break` carries.
+    Break(Value),
+    Continue,
+    Error(RuntimeError),
+}
+
+impl From<RuntimeError> for Interrupt {
+    fn from(error: RuntimeError) -> Interrupt {
+        Interrupt::Error(error)
+    }
+}
+
+type EvalResult = Result<Value, Interrupt>;
+
+impl<'ctx> Interpreter<'ctx> {
+    pub(crate) fn new(
+        ctx: &'ctx CompilerContext,
+        resolutions: &'ctx Resolutions,
+        int_profile: IntProfile,
+        output: Rc<RefCell<dyn io::Write>>,
+    ) -> Interpreter<'ctx> {
+        Interpreter {
+            ctx,
+            resolutions,
+            int_profile,
+            functions: Default::default(),
+            extern_fns: Default::default(),
+            closures: Default::default(),
+            scope_stack: vec![],
+            output,
+            profiler: None,
+            limits: ExecutionLimits::default(),
+            deadline: None,
+            evaluated_exprs: 0,
+            call_depth: 0,
+            stack_start: None,
+        }
+    }
+
+    pub(crate) fn with_limits(mut self, limits: ExecutionLimits) -> Interpreter<'ctx> {
+        self.limits = limits;
+        self
+    }
+
+    /// Runs the program's `main` function, returning its value as an exit
+    /// code, or zero if it returns `()`.
+    pub(crate) fn run_program(mut self, program: Program<'ctx>) -> Result<i32, RuntimeError> {
+        self.on_own_stack(|interpreter| interpreter.run_main(program))
+    }
+
+    /// Runs the program like `run_program` does, recording the time and the
+    /// allocations that every function takes up.
+    pub(crate) fn run_program_profiled(
+        mut self,
+        program: Program<'ctx>,
+    ) -> (Result<i32, RuntimeError>, ExecutionProfile) {
+        self.profiler = Some(Profiler::default());
+        let exit_code = self.on_own_stack(|interpreter| interpreter.run_main(program));
+        let profile = self.profiler.take().unwrap().finish(self.ctx);
+
+        (exit_code, profile)
+    }
+
+    /// Runs the statements of an input of a REPL session, starting with the
+    /// bindings that the statements of the inputs before it defined, which
+    /// are updated with the ones it defines if it runs fine. The program is
+    /// the whole session's, whose `main` has the statements of every input.
+    ///
+    /// If `shows_value` is set, the last statement discards the value of an
+    /// expression, `_ = <expr>`, which is returned, rendered as text, unless
+    /// it's `()`.
+    pub(crate) fn run_repl_input(
+        mut self,
+        program: Program<'ctx>,
+        stmts: &[Expr<'ctx>],
+        bindings: &mut ReplBindings,
+        shows_value: bool,
+    ) -> Result<Option<String>, RuntimeError> {
+        self.deadline = self.limits.deadline();
+        self.declare(program);
+
+        // Closures made by earlier inputs refer to their code by its span,
+        // which is the same in the session's syntax tree, parsed anew.
+        for decl in program.decls {
+            self.register_closures(decl.value);
+        }
+
+        self.on_own_stack(|interpreter| {
+            interpreter.scope_stack.push(bindings.0.clone());
+
+            let mut value = Value::Unit;
+
+            for (idx, stmt) in stmts.iter().enumerate() {
+                let result = match stmt {
+                    Expr::BindDef(bind_def) if shows_value && idx == stmts.len() - 1 => {
+                        interpreter.eval_expr(bind_def.value)
+                    }
+                    _ => interpreter.eval_expr(stmt),
+                };
+
+                value = match result {
+                    Ok(value) => value,
+                    Err(Interrupt::Error(error)) => return Err(error),
+                    Err(Interrupt::Break(_) | Interrupt::Continue) => {
+                        unreachable!("`break` and `continue` only occur inside loops")
+                    }
+                };
+            }
+
+            bindings.0 = interpreter.scope_stack.pop().unwrap();
+
+            if value == Value::Unit {
+                return Ok(None);
+            }
+
+            let mut text = String::new();
+            interpreter.render_value(&value, &mut text);
+
+            Ok(Some(text))
+        })
+    }
+
+    /// Registers the code of the closures that the expression, and the ones
+    /// nested in it, make.
+    fn register_closures(&mut self, expr: &Expr<'ctx>) {
+        match expr {
+            Expr::Const(_)
+            | Expr::BindRef(_)
+            | Expr::Extern(_)
+            | Expr::Struct(_)
+            | Expr::Enum(_)
+            | Expr::Continue(_) => {}
+            Expr::Function(function) => {
+                self.closures.insert(function.span, *function);
+                self.register_closures_of_compound(function.body);
+            }
+            Expr::BindDef(bind_def) => self.register_closures(bind_def.value),
+            Expr::StructLit(struct_lit_expr) => {
+                for field in struct_lit_expr.fields {
+                    self.register_closures(field.value);
+                }
+            }
+            Expr::Field(field_expr) => self.register_closures(field_expr.base),
+            Expr::Variant(variant_expr) => {
+                if let Some(payload) = variant_expr.payload {
+                    self.register_closures(payload);
+                }
+            }
+            Expr::Tuple(TupleExpr { elements, .. }) | Expr::Array(ArrayExpr { elements, .. }) => {
+                for element in *elements {
+                    self.register_closures(element);
+                }
+            }
+            Expr::TupleField(tuple_field_expr) => self.register_closures(tuple_field_expr.base),
+            Expr::TupleBindDef(tuple_bind_def) => self.register_closures(tuple_bind_def.value),
+            Expr::Index(index_expr) => {
+                self.register_closures(index_expr.base);
+                self.register_closures(index_expr.index);
+            }
+            Expr::IndexAssign(index_assign_expr) => {
+                self.register_closures(index_assign_expr.target.base);
+                self.register_closures(index_assign_expr.target.index);
+                self.register_closures(index_assign_expr.value);
+            }
+            Expr::If(if_expr) => {
+                self.register_closures(if_expr.cond_expr);
+                self.register_closures_of_compound(if_expr.true_branch);
+
+                for else_if_branch in if_expr.else_if_branches {
+                    self.register_closures(else_if_branch.cond_expr);
+                    self.register_closures_of_compound(else_if_branch.true_branch);
+                }
+
+                if let Some(final_branch) = if_expr.final_branch {
+                    self.register_closures_of_compound(final_branch);
+                }
+            }
+            Expr::Match(match_expr) => {
+                self.register_closures(match_expr.scrutinee);
+
+                for arm in match_expr.arms {
+                    self.register_closures(arm.expr);
+                }
+            }
+            Expr::For(for_expr) => {
+                match for_expr.iteration {
+                    Some(ForIteration::Conditional { cond_expr }) => {
+                        self.register_closures(cond_expr);
+                    }
+                    Some(ForIteration::Iterative {
+                        start_expr,
+                        end_expr,
+                        step_expr,
+                        ..
+                    }) => {
+                        self.register_closures(start_expr);
+                        self.register_closures(end_expr);
+
+                        if let Some(step_expr) = step_expr {
+                            self.register_closures(step_expr);
+                        }
+                    }
+                    Some(ForIteration::InCollection {
+                        collection_expr, ..
+                    }) => self.register_closures(collection_expr),
+                    None => {}
+                }
+
+                self.register_closures_of_compound(for_expr.body);
+
+                if let Some(else_branch) = for_expr.else_branch {
+                    self.register_closures_of_compound(else_branch);
+                }
+            }
+            Expr::Break(break_expr) => {
+                if let Some(value) = break_expr.value {
+                    self.register_closures(value);
+                }
+            }
+            Expr::Compound(compound_expr) => self.register_closures_of_compound(*compound_expr),
+            Expr::Semi(expr) => self.register_closures(expr),
+            Expr::FnCall(fn_call_expr) => {
+                for arg in fn_call_expr.args {
+                    self.register_closures(arg);
+                }
+            }
+            Expr::Binary(binary_expr) => {
+                let (operand, operations) = binary_expr.chain();
+                self.register_closures(operand);
+
+                for operation in operations {
+                    self.register_closures(operation.rhs);
+                }
+            }
+            Expr::Cast(cast_expr) => self.register_closures(cast_expr.expr),
+            Expr::Paren(paren_expr) => self.register_closures(paren_expr.expr),
+        }
+    }
+
+    fn register_closures_of_compound(&mut self, compound_expr: CompoundExpr<'ctx>) {
+        for expr in compound_expr.exprs {
+            self.register_closures(expr);
+        }
+    }
+
+    /// Runs `run` on a thread of its own, whose stack is `STACK_SIZE` bytes,
+    /// and waits for it to finish.
+    fn on_own_stack<T>(&mut self, run: impl FnOnce(&mut Self) -> T) -> T {
+        // Neither the interpreter nor the values it makes are `Send`, as they
+        // share the context and the output of the compilation, but this thread
+        // is blocked until the other one finishes, so they're never used by
+        // both at once.
+        struct Unshared<T>(T);
+
+        unsafe impl<T> Send for Unshared<T> {}
+
+        impl<T> Unshared<T> {
+            fn into_inner(self) -> T {
+                self.0
+            }
+        }
+
+        let task = Unshared((self, run));
+
+        thread::scope(|scope| {
+            let handle = thread::Builder::new()
+                .name("interpreter".to_owned())
+                .stack_size(STACK_SIZE)
+                .spawn_scoped(scope, move || {
+                    let (interpreter, run) = task.into_inner();
+
+                    interpreter.stack_start = Some(stack_address());
+                    let value = run(interpreter);
+                    interpreter.stack_start = None;
+
+                    Unshared(value)
+                })
+                .expect("couldn't start the interpreter's thread");
+
+            match handle.join() {
+                Ok(value) => value.into_inner(),
+                Err(payload) => panic::resume_unwind(payload),
+            }
+        })
+    }
+
+    fn declare(&mut self, program: Program<'ctx>) {
+        for decl in program.decls {
+            match decl.value {
+                Expr::Function(function) => {
+                    self.functions.insert(decl.identifier, *function);
+                }
+                Expr::Extern(extern_fn) => {
+                    self.extern_fns.insert(decl.identifier, *extern_fn);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Writes the value the way it'd be written in source code, except for
+    /// the names of structs and enums, which values don't keep, and for
+    /// closures, which can't be written.
+    fn render_value(&self, value: &Value, text: &mut String) {
+        match value {
+            Value::Unit => text.push_str("()"),
+            Value::Int(value) => text.push_str(&value.to_string()),
+            Value::OtherInt { value, .. } => text.push_str(&value.to_string()),
+            Value::Struct(fields) => {
+                text.push('{');
+
+                for (idx, (name, value)) in fields.iter().enumerate() {
+                    text.push_str(if idx > 0 { ", " } else { " " });
+                    text.push_str(name);
+                    text.push_str(": ");
+                    self.render_value(value, text);
+                }
+
+                text.push_str(if fields.is_empty() { "}" } else { " }" });
+            }
+            Value::Variant { variant, payload } => {
+                text.push_str(variant);
+
+                if let Some(payload) = payload {
+                    text.push('(');
+                    self.render_value(payload, text);
+                    text.push(')');
+                }
+            }
+            Value::Tuple(elements) => {
+                text.push('(');
+                self.render_values(elements, text);
+
+                if elements.len() == 1 {
+                    text.push(',');
+                }
+
+                text.push(')');
+            }
+            Value::Array(elements) => {
+                text.push('[');
+                self.render_values(elements, text);
+                text.push(']');
+            }
+            Value::Function(identifier) => text.push_str(self.ctx.resolve_symbol(*identifier)),
+            Value::Closure(_) => text.push_str("<closure>"),
+        }
+    }
+
+    fn render_values(&self, values: &[Value], text: &mut String) {
+        for (idx, value) in values.iter().enumerate() {
+            if idx > 0 {
+                text.push_str(", ");
+            }
+
+            self.render_value(value, text);
+        }
+    }
+
+    fn run_main(&mut self, program: Program<'ctx>) -> Result<i32, RuntimeError> {
+        self.deadline = self.limits.deadline();
+        self.declare(program);
+
+        let main_identifier = self.ctx.symbols().main;
+
+        let Some(&main) = self.functions.get(&main_identifier) else {
+            return Err(RuntimeError::MissingMain);
+        };
+
+        if let Some(param) = main.parameters.first() {
+            return Err(RuntimeError::MainTakesParameters { span: param.span });
+        }
+
+        match self.call_function(main_identifier, main, &[], vec![])? {
+            Value::Int(exit_code) => Ok(exit_code),
+            // Exit codes are truncated by the system anyway.
+            Value::OtherInt { value, .. } => Ok(value as i32),
+            Value::Unit
+            | Value::Struct(_)
+            | Value::Variant { .. }
+            | Value::Tuple(_)
+            | Value::Array(_)
+            | Value::Function(_)
+            | Value::Closure(_) => Ok(0),
+        }
+    }
+
+    fn call_function(
+        &mut self,
+        identifier: Symbol,
+        function: Function<'ctx>,
+        captures: &[(Symbol, Value)],
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        if self.call_depth == self.limits.max_call_depth {
+            return Err(RuntimeError::ExecutionLimitExceeded {
+                limit: ExecutionLimit::CallDepth(self.limits.max_call_depth),
+            });
+        }
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter_function(identifier);
+        }
+
+        // The callee can only see its own bindings, so the caller's are set aside
+        // until it returns.
+        let caller_scope_stack = mem::take(&mut self.scope_stack);
+
+        self.enter_scope();
+
+        for (identifier, value) in captures {
+            self.define(*identifier, value.clone());
+        }
+
+        for (param, arg) in function.parameters.iter().zip(args) {
+            self.define(param.identifier, arg.widened_to(param.ty));
+        }
+
+        self.call_depth += 1;
+        let result = self.eval_compound_expr(function.body);
+        self.call_depth -= 1;
+
+        self.scope_stack = caller_scope_stack;
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.exit_function();
+        }
+
+        match result {
+            Ok(value) => Ok(value.widened_to(function.return_type)),
+            Err(Interrupt::Error(error)) => Err(error),
+            Err(Interrupt::Break(_) | Interrupt::Continue) => {
+                unreachable!("`break` and `continue` only occur inside loops")
+            }
+        }
+    }
+
+    /// Counts an expression about to be evaluated against the limits, just
+    /// like the VM counts the instructions it runs.
+    fn count_evaluation(&mut self) -> Result<(), RuntimeError> {
+        if self.evaluated_exprs == self.limits.max_instructions {
+            return Err(RuntimeError::ExecutionLimitExceeded {
+                limit: ExecutionLimit::Instructions(self.limits.max_instructions),
+            });
+        }
+
+        if self
+            .evaluated_exprs
+            .is_multiple_of(INSTRUCTIONS_PER_CLOCK_CHECK)
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(RuntimeError::ExecutionLimitExceeded {
+                limit: ExecutionLimit::Timeout(self.limits.timeout.unwrap()),
+            });
+        }
+
+        if self
+            .stack_start
+            .is_some_and(|start| start.abs_diff(stack_address()) > STACK_SIZE - STACK_RED_ZONE)
+        {
+            return Err(RuntimeError::ExecutionLimitExceeded {
+                limit: ExecutionLimit::NativeStack(STACK_SIZE),
+            });
+        }
+
+        self.evaluated_exprs += 1;
+
+        Ok(())
+    }
+
+    fn eval_expr(&mut self, expr: &Expr<'ctx>) -> EvalResult {
+        self.count_evaluation()?;
+
+        match expr {
+            Expr::Const(const_expr) => match const_expr.value {
+                Const::IntegerConstant { value } => Ok(Value::Int(value)),
+                Const::SuffixedIntegerConstant { value, ty } => Ok(Value::of_int(value.into(), ty)),
+            },
+            Expr::BindRef(bind_ref) => Ok(self.lookup(bind_ref.identifier)),
+            Expr::BindDef(bind_def) => {
+                let mut value = self.eval_expr(bind_def.value)?;
+
+                if let Some(ty) = bind_def.ty {
+                    value = value.widened_to(ty);
+                }
+
+                if bind_def.identifier != self.ctx.symbols().discard {
+                    self.define(bind_def.identifier, value);
+                }
+
+                Ok(Value::Unit)
+            }
+            Expr::Function(function) => {
+                self.closures.insert(function.span, *function);
+
+                let captures = self
+                    .resolutions
+                    .captures(function.span)
+                    .iter()
+                    .map(|capture| (capture.identifier, self.lookup(capture.identifier)))
+                    .collect();
+
+                Ok(Value::Closure(Rc::new(Closure {
+                    function_span: function.span,
+                    captures,
+                })))
+            }
+            // Extern functions, structs and enums are declared at the top level, where
+            // there's nothing to evaluate.
+            Expr::Extern(_) | Expr::Struct(_) | Expr::Enum(_) => Ok(Value::Unit),
+            Expr::StructLit(struct_lit_expr) => self.eval_struct_lit_expr(*struct_lit_expr),
+            Expr::Field(field_expr) => self.eval_field_expr(*field_expr),
+            Expr::Variant(variant_expr) => self.eval_variant_expr(*variant_expr),
+            Expr::Tuple(tuple_expr) => {
+                let elements = tuple_expr
+                    .elements
+                    .iter()
+                    .map(|element| self.eval_expr(element))
+                    .collect::<Result<Rc<[Value]>, _>>()?;
+
+                self.record_allocation();
+
+                Ok(Value::Tuple(elements))
+            }
+            Expr::TupleField(tuple_field_expr) => {
+                let Value::Tuple(elements) = self.eval_expr(tuple_field_expr.base)? else {
+                    unreachable!("type checking guarantees a tuple");
+                };
+
+                Ok(elements[tuple_field_expr.index].clone())
+            }
+            Expr::TupleBindDef(tuple_bind_def) => {
+                let Value::Tuple(elements) = self.eval_expr(tuple_bind_def.value)? else {
+                    unreachable!("type checking guarantees a tuple");
+                };
+
+                for (binding, element) in tuple_bind_def.bindings.iter().zip(elements.iter()) {
+                    if binding.identifier != self.ctx.symbols().discard {
+                        self.define(binding.identifier, element.clone());
+                    }
+                }
+
+                Ok(Value::Unit)
+            }
+            Expr::Array(array_expr) => {
+                let elements = array_expr
+                    .elements
+                    .iter()
+                    .map(|element| self.eval_expr(element))
+                    .collect::<Result<Rc<[Value]>, _>>()?;
+
+                self.record_allocation();
+
+                Ok(Value::Array(elements))
+            }
+            Expr::Index(index_expr) => {
+                let Value::Array(elements) = self.eval_expr(index_expr.base)? else {
+                    unreachable!("type checking guarantees an array");
+                };
+                let index = self.eval_expr(index_expr.index)?.as_int();
+                let idx = element_idx(index, elements.len(), index_expr.span)?;
+
+                Ok(elements[idx].clone())
+            }
+            Expr::IndexAssign(index_assign_expr) => {
+                self.eval_index_assign_expr(*index_assign_expr)?;
+
+                Ok(Value::Unit)
+            }
+            Expr::If(if_expr) => self.eval_if_expr(*if_expr),
+            Expr::Match(match_expr) => self.eval_match_expr(*match_expr),
+            Expr::For(for_expr) => self.eval_for_expr(*for_expr),
+            Expr::Break(break_expr) => {
+                let value = match break_expr.value {
+                    Some(value) => self.eval_expr(value)?,
+                    None => Value::Unit,
+                };
+
+                Err(Interrupt::Break(value))
+            }
+            Expr::Continue(_) => Err(Interrupt::Continue),
+            Expr::Compound(compound_expr) => self.eval_compound_expr(*compound_expr),
+            Expr::Semi(expr) => {
+                self.eval_expr(expr)?;
+
+                Ok(Value::Unit)
+            }
+            Expr::FnCall(fn_call_expr) => self.eval_fn_call_expr(*fn_call_expr),
+            Expr::Binary(binary_expr) => self.eval_binary_expr(*binary_expr),
+            Expr::Cast(cast_expr) => {
+                let (value, _) = self.eval_expr(cast_expr.expr)?.as_typed_int();
+
+                Ok(Value::of_int(
+                    self.int_profile.cast(value, cast_expr.ty),
+                    cast_expr.ty,
+                ))
+            }
+            Expr::Paren(paren_expr) => self.eval_expr(paren_expr.expr),
+        }
+    }
+
+    fn eval_struct_lit_expr(&mut self, struct_lit_expr: StructLitExpr<'ctx>) -> EvalResult {
+        let mut fields = vec![];
+
+        // Fields are evaluated in the order they're written, whatever the order
+        // they're declared in.
+        for field in struct_lit_expr.fields {
+            let name = self.ctx.resolve_symbol(field.identifier);
+            fields.push((name, self.eval_expr(field.value)?));
+        }
+
+        self.record_allocation();
+
+        Ok(Value::Struct(fields.into()))
+    }
+
+    fn eval_field_expr(&mut self, field_expr: FieldExpr<'ctx>) -> EvalResult {
+        let Value::Struct(fields) = self.eval_expr(field_expr.base)? else {
+            unreachable!("type checking guarantees a struct");
+        };
+
+        let name = self.ctx.resolve_symbol(field_expr.identifier);
+        let (_, value) = fields
+            .iter()
+            .find(|&&(field_name, _)| field_name == name)
+            .expect("type checking guarantees the field exists");
+
+        Ok(value.clone())
+    }
+
+    fn eval_variant_expr(&mut self, variant_expr: VariantExpr<'ctx>) -> EvalResult {
+        let payload = match variant_expr.payload {
+            Some(payload) => {
+                let payload = self.eval_expr(payload)?;
+                self.record_allocation();
+
+                Some(Rc::new(payload))
+            }
+            None => None,
+        };
+
+        Ok(Value::Variant {
+            variant: self.ctx.resolve_symbol(variant_expr.variant_identifier),
+            payload,
+        })
+    }
+
+    /// Replaces the element, copying the arrays on the way to it that other
+    /// values share, so that only the binding's array changes.
+    fn eval_index_assign_expr(
+        &mut self,
+        index_assign_expr: IndexAssignExpr<'ctx>,
+    ) -> Result<(), Interrupt> {
+        let mut indices = vec![];
+        let identifier =
+            self.eval_assign_target(&Expr::Index(index_assign_expr.target), &mut indices)?;
+        let value = self.eval_expr(index_assign_expr.value)?;
+
+        let mut element = self.lookup_mut(identifier);
+
+        for (index, span) in indices {
+            let Value::Array(elements) = element else {
+                unreachable!("type checking guarantees an array");
+            };
+            let idx = element_idx(index, elements.len(), span)?;
+
+            element = &mut Rc::make_mut(elements)[idx];
+        }
+
+        *element = value;
+
+        Ok(())
+    }
+
+    /// Evaluates the indices of the elements that lead to the assigned one,
+    /// from the outermost array in, returning the name of the binding that
+    /// holds the outermost array.
+    fn eval_assign_target(
+        &mut self,
+        target: &Expr<'ctx>,
+        indices: &mut Vec<(i32, Span)>,
+    ) -> Result<Symbol, Interrupt> {
+        match target {
+            Expr::BindRef(bind_ref) => Ok(bind_ref.identifier),
+            Expr::Paren(paren_expr) => self.eval_assign_target(paren_expr.expr, indices),
+            Expr::Index(index_expr) => {
+                let identifier = self.eval_assign_target(index_expr.base, indices)?;
+                let index = self.eval_expr(index_expr.index)?.as_int();
+                indices.push((index, index_expr.span));
+
+                Ok(identifier)
+            }
+            _ => unreachable!("type checking guarantees an array bound to a name"),
+        }
+    }
+
+    fn eval_if_expr(&mut self, if_expr: IfExpr<'ctx>) -> EvalResult {
+        if self.eval_cond_expr(if_expr.cond_expr)? {
+            return self.eval_compound_expr(if_expr.true_branch);
+        }
+
+        for branch in if_expr.else_if_branches {
+            if self.eval_cond_expr(branch.cond_expr)? {
+                return self.eval_compound_expr(branch.true_branch);
+            }
+        }
+
+        match if_expr.final_branch {
+            Some(final_branch) => self.eval_compound_expr(final_branch),
+            None => Ok(Value::Unit),
+        }
+    }
+
+    fn eval_match_expr(&mut self, match_expr: MatchExpr<'ctx>) -> EvalResult {
+        let scrutinee = self.eval_expr(match_expr.scrutinee)?;
+
+        let arm = match_expr
+            .arms
+            .iter()
+            .find(|arm| match arm.pattern.kind {
+                PatternKind::Wildcard => true,
+                PatternKind::Variant(variant_pattern) => matches!(
+                    scrutinee,
+                    Value::Variant { variant, .. } if variant == variant_pattern.variant_name
+                ),
+                kind @ (PatternKind::Integer(_) | PatternKind::Range { .. }) => {
+                    kind.matches(scrutinee.as_int())
+                }
+            })
+            .expect("match-exprs are exhaustive");
+
+        self.enter_scope();
+
+        if let (
+            PatternKind::Variant(variant_pattern),
+            Value::Variant {
+                payload: Some(payload),
+                ..
+            },
+        ) = (arm.pattern.kind, &scrutinee)
+        {
+            if let Some((name, _)) = variant_pattern.binding {
+                if name != DISCARD_IDENTIFIER {
+                    self.define(self.ctx.get_or_intern_str(name), Value::clone(payload));
+                }
+            }
+        }
+
+        let value = self.eval_expr(arm.expr);
+        self.exit_scope();
+
+        value
+    }
+
+    fn eval_for_expr(&mut self, for_expr: ForExpr<'ctx>) -> EvalResult {
+        self.enter_scope();
+        let exit = self.run_loop(for_expr);
+        self.exit_scope();
+
+        match exit {
+            // The else-branch is evaluated outside of the loop, so that a `break` or
+            // `continue` in it refers to an enclosing loop instead.
+            Ok(()) => {
+                if let Some(else_branch) = for_expr.else_branch {
+                    self.eval_compound_expr(else_branch)?;
+                }
+
+                Ok(Value::Unit)
+            }
+            Err(Interrupt::Break(value)) => Ok(value),
+            Err(interrupt) => Err(interrupt),
+        }
+    }
+
+    /// Runs the loop's iterations until it runs to completion, or until it's
+    /// interrupted, such as by a `break`.
+    fn run_loop(&mut self, for_expr: ForExpr<'ctx>) -> Result<(), Interrupt> {
+        match for_expr.iteration {
+            Some(ForIteration::Conditional { cond_expr }) => {
+                while self.eval_cond_expr(cond_expr)? {
+                    self.run_iteration(for_expr.body)?;
+                }
+
+                Ok(())
+            }
+            Some(ForIteration::Iterative {
+                identifier,
+                start_expr,
+                end_expr,
+                range_kind,
+                step_expr,
+                is_reversed: false,
+                ..
+            }) => {
+                let (mut current, ty) = self.eval_expr(start_expr)?.as_typed_int();
+                let (end, _) = self.eval_expr(end_expr)?.as_typed_int();
+                let step = self.eval_range_step(step_expr)?;
+
+                loop {
+                    let is_in_range = match range_kind {
+                        RangeKind::Inclusive => current <= end,
+                        RangeKind::Exclusive => current < end,
+                    };
+
+                    if !is_in_range {
+                        return Ok(());
+                    }
+
+                    self.define(identifier, Value::of_int(current, ty));
+
+                    self.run_iteration(for_expr.body)?;
+
+                    // Stepping past the largest or smallest value of the type
+                    // runs the loop to completion rather than overflowing.
+                    current += step;
+
+                    if !self.fits_int(current, ty) {
+                        return Ok(());
+                    }
+                }
+            }
+            Some(ForIteration::Iterative {
+                identifier,
+                start_expr,
+                end_expr,
+                range_kind,
+                step_expr,
+                is_reversed: true,
+                ..
+            }) => {
+                let (start, ty) = self.eval_expr(start_expr)?.as_typed_int();
+                let (end, _) = self.eval_expr(end_expr)?.as_typed_int();
+                let step = self.eval_range_step(step_expr)?;
+
+                let mut current = match range_kind {
+                    RangeKind::Inclusive => end,
+                    RangeKind::Exclusive => end - 1,
+                };
+
+                // Counting is done on `i128`s, so stepping past the start, or
+                // past the range of the type, ends the loop without
+                // overflowing.
+                while start <= current && self.fits_int(current, ty) {
+                    self.define(identifier, Value::of_int(current, ty));
+
+                    self.run_iteration(for_expr.body)?;
+
+                    current -= step;
+                }
+
+                Ok(())
+            }
+            Some(ForIteration::InCollection {
+                identifier,
+                collection_expr,
+                ..
+            }) => {
+                let Value::Array(elements) = self.eval_expr(collection_expr)? else {
+                    unreachable!("type checking guarantees an array");
+                };
+
+                for element in elements.iter() {
+                    self.define(identifier, element.clone());
+
+                    self.run_iteration(for_expr.body)?;
+                }
+
+                Ok(())
+            }
+            None => loop {
+                self.run_iteration(for_expr.body)?;
+            },
+        }
+    }
+
+    /// Runs the loop's body once, which a `continue` only cuts short.
+    fn run_iteration(&mut self, body: CompoundExpr<'ctx>) -> Result<(), Interrupt> {
+        // Empty bodies evaluate nothing, yet their loops must use up fuel.
+        self.count_evaluation()?;
+
+        match self.eval_compound_expr(body) {
+            Ok(_) | Err(Interrupt::Continue) => Ok(()),
+            Err(interrupt) => Err(interrupt),
+        }
+    }
+
+    /// Evaluates the step of a range, which is one if there's none. Ranges
+    /// only count towards their end, so a step that isn't positive fails.
+    fn eval_range_step(&mut self, step_expr: Option<&Expr<'ctx>>) -> Result<i128, Interrupt> {
+        let Some(step_expr) = step_expr else {
+            return Ok(1);
+        };
+
+        let (step, _) = self.eval_expr(step_expr)?.as_typed_int();
+
+        if step <= 0 {
+            return Err(RuntimeError::NonPositiveStep {
+                span: step_expr.span(),
+            }
+            .into());
+        }
+
+        Ok(step)
+    }
+
+    fn eval_cond_expr(&mut self, cond_expr: &Expr<'ctx>) -> Result<bool, Interrupt> {
+        let (value, _) = self.eval_expr(cond_expr)?.as_typed_int();
+
+        Ok(value != 0)
+    }
+
+    fn eval_compound_expr(&mut self, compound_expr: CompoundExpr<'ctx>) -> EvalResult {
+        self.enter_scope();
+
+        let mut result = Ok(Value::Unit);
+
+        for expr in compound_expr.exprs {
+            result = self.eval_expr(expr);
+
+            if result.is_err() {
+                break;
+            }
+        }
+
+        self.exit_scope();
+
+        result
+    }
+
+    fn eval_fn_call_expr(&mut self, fn_call_expr: FnCallExpr<'ctx>) -> EvalResult {
+        let mut args = vec![];
+
+        for arg in fn_call_expr.args {
+            args.push(self.eval_expr(arg)?);
+        }
+
+        if !args.is_empty() {
+            self.record_allocation();
+        }
+
+        // The name is either the function's or a binding's that holds it.
+        match self.lookup(fn_call_expr.identifier) {
+            Value::Function(identifier) => match self.functions.get(&identifier) {
+                Some(&function) => Ok(self.call_function(identifier, function, &[], args)?),
+                None if self.extern_fns.contains_key(&identifier) => {
+                    Ok(self.call_extern_fn(identifier, &args, fn_call_expr.span)?)
+                }
+                None => {
+                    let name = self.ctx.resolve_symbol(identifier);
+                    let builtin = Builtin::from_name(name).expect("only builtins aren't declared");
+
+                    Ok(self.call_builtin(builtin, &args)?)
+                }
+            },
+            // Closures have no name of their own, so they're profiled under
+            // the binding's.
+            Value::Closure(closure) => {
+                let function = self.closures[&closure.function_span];
+
+                Ok(self.call_function(
+                    fn_call_expr.identifier,
+                    function,
+                    &closure.captures,
+                    args,
+                )?)
+            }
+            _ => unreachable!("type checking guarantees a function"),
+        }
+    }
+
+    fn call_builtin(&mut self, builtin: Builtin, args: &[Value]) -> Result<Value, RuntimeError> {
+        let [arg] = args else {
+            unreachable!("type checking guarantees a single argument");
+        };
+        let (value, _) = arg.as_typed_int();
+
+        self.output
+            .borrow_mut()
+            .write_all(builtin.format(value).as_bytes())
+            .map_err(|err| RuntimeError::OutputFailed {
+                message: err.to_string(),
+            })?;
+
+        Ok(Value::Unit)
+    }
+
+    /// Calls the extern function if it's one of the C library's that the
+    /// interpreter knows of, failing otherwise.
+    fn call_extern_fn(
+        &mut self,
+        identifier: Symbol,
+        args: &[Value],
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        let extern_fn = self.extern_fns[&identifier];
+        let name = self.ctx.resolve_symbol(identifier);
+
+        let Some(libc_fn) = LibcFn::find(name, args.len()) else {
+            return Err(RuntimeError::UnsupportedExternFn {
+                name: name.to_owned(),
+                span,
+            });
+        };
+
+        // The C library's functions take and return `int`s.
+        let args: Vec<_> = args.iter().map(|arg| arg.as_typed_int().0 as i32).collect();
+
+        let value = libc_fn
+            .call(&args, &mut *self.output.borrow_mut())
+            .map_err(|err| RuntimeError::OutputFailed {
+                message: err.to_string(),
+            })?;
+
+        Ok(match extern_fn.return_type {
+            Type::Unit => Value::Unit,
+            ty => Value::of_int(value.into(), ty),
+        })
+    }
+
+    fn eval_binary_expr(&mut self, binary_expr: BinaryExpr<'ctx>) -> EvalResult {
+        let (operand, operations) = binary_expr.chain();
+        let (mut lhs, mut lhs_ty) = self.eval_expr(operand)?.as_typed_int();
+
+        for binary_expr in operations {
+            let (rhs, rhs_ty) = self.eval_expr(binary_expr.rhs)?.as_typed_int();
+
+            if matches!(binary_expr.op, BinaryOp::Div | BinaryOp::Rem) && rhs == 0 {
+                return Err(RuntimeError::DivisionByZero {
+                    span: binary_expr.span,
+                }
+                .into());
+            }
+
+            // The operand of the narrower type is widened into the other's.
+            if lhs_ty.widens_to(rhs_ty) {
+                lhs_ty = rhs_ty;
+            }
+
+            lhs =
+                self.eval_int(binary_expr.op, lhs, rhs, lhs_ty)
+                    .ok_or(RuntimeError::Overflow {
+                        op: binary_expr.op,
+                        span: binary_expr.span,
+                    })?;
+        }
+
+        Ok(Value::of_int(lhs, lhs_ty))
+    }
+
+    /// Computes the operation on integers of the type, or returns `None` if it
+    /// should trap. They overflow as the integer profile says, which by default
+    /// is just like the native code does.
+    fn eval_int(&self, op: BinaryOp, lhs: i128, rhs: i128, ty: Type) -> Option<i128> {
+        if ty == Type::I32 {
+            self.int_profile
+                .eval(op, lhs as i32, rhs as i32)
+                .map(i128::from)
+        } else {
+            self.int_profile.eval_wide(op, lhs, rhs, ty)
+        }
+    }
+
+    /// Whether the value is of the integer type, where `i32` is as wide as the
+    /// integer profile makes it.
+    fn fits_int(&self, value: i128, ty: Type) -> bool {
+        if ty == Type::I32 {
+            i64::try_from(value).is_ok_and(|value| self.int_profile.width.contains(value))
+        } else {
+            let (min, max) = ty.int_range().expect("type checking guarantees an integer");
+            (min..=max).contains(&value)
+        }
+    }
+
+    fn lookup(&self, identifier: Symbol) -> Value {
+        self.scope_stack
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&identifier).cloned())
+            .unwrap_or_else(|| {
+                // Anything else is a declaration or a builtin, and only
+                // functions are values.
+                if self.functions.contains_key(&identifier)
+                    || self.extern_fns.contains_key(&identifier)
+                    || Builtin::from_name(self.ctx.resolve_symbol(identifier)).is_some()
+                {
+                    Value::Function(identifier)
+                } else {
+                    Value::Unit
+                }
+            })
+    }
+
+    fn lookup_mut(&mut self, identifier: Symbol) -> &mut Value {
+        self.scope_stack
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(&identifier))
+            .expect("type checking guarantees a local binding")
+    }
+
+    fn define(&mut self, identifier: Symbol, value: Value) {
+        self.scope_stack
+            .last_mut()
+            .unwrap()
+            .insert(identifier, value);
+    }
+
+    fn enter_scope(&mut self) {
+        self.record_allocation();
+        self.scope_stack.push(HashMap::default());
+    }
+
+    fn exit_scope(&mut self) {
+        self.scope_stack.pop();
+    }
+
+    fn record_allocation(&mut self) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record_allocation();
+        }
+    }
+}
+
+/// Position of the element at `index` in an array of `len` elements, failing
+/// if it's past either end.
+fn element_idx(index: i32, len: usize, span: Span) -> Result<usize, RuntimeError> {
+    usize::try_from(index)
+        .ok()
+        .filter(|&idx| idx < len)
+        .ok_or(RuntimeError::IndexOutOfBounds { index, len, span })
+}
+
+/// Address of the top of the stack, give or take the frame of the caller.
+fn stack_address() -> usize {
+    let marker = 0u8;
+
+    hint::black_box(&marker) as *const u8 as usize
+}
@@ -0,0 +1,518 @@
+use std::fmt;
+
+use crate::ast::BinaryOp;
+use crate::builtin::Builtin;
+use crate::scanner::Span;
+
+/// Mid-level representation of a program, between the AST and the backends.
+///
+/// Each function is a graph of basic blocks in SSA form: every value is
+/// defined exactly once, before any of its uses. Instead of phi nodes, blocks
+/// take parameters, which jumps pass arguments to. Values of type `()` have no
+/// representation, so only integers are values.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub(crate) struct Module {
+    pub(crate) functions: Vec<Function>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Function {
+    pub(crate) name: &'static str,
+    /// Whether the function returns a value, i.e., it returns an `i32`.
+    pub(crate) returns_value: bool,
+    /// Blocks in reverse postorder, so that every block comes after the ones
+    /// that dominate it. The first one is the entry block, whose parameters are
+    /// the function's parameters of type `i32`.
+    pub(crate) blocks: Vec<Block>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct Value(pub(crate) usize);
+
+/// Index of a block in its function.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct BlockId(pub(crate) usize);
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Block {
+    pub(crate) params: Vec<Value>,
+    pub(crate) insts: Vec<Inst>,
+    pub(crate) terminator: Terminator,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Inst {
+    /// Value the instruction defines, which is `None` for calls to functions
+    /// that return `()`.
+    pub(crate) result: Option<Value>,
+    pub(crate) kind: InstKind,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum InstKind {
+    Const(i32),
+    Binary {
+        op: BinaryOp,
+        lhs: Value,
+        rhs: Value,
+        /// Span of the expression, for reporting overflows and divisions by
+        /// zero. Stepping an iterative for-loop is an addition too, spanning
+        /// the loop's variable.
+        span: Span,
+    },
+    /// One if the left-hand side is less than the right-hand side, or zero
+    /// otherwise.
+    Less {
+        lhs: Value,
+        rhs: Value,
+    },
+    LessEqual {
+        lhs: Value,
+        rhs: Value,
+    },
+    /// One if adding or subtracting the right-hand side doesn't fit in an
+    /// integer, whatever the integer profile says about overflows, or zero
+    /// otherwise. Steps ranges without going past the largest or smallest
+    /// value of their type.
+    Overflows {
+        op: BinaryOp,
+        lhs: Value,
+        rhs: Value,
+    },
+    /// Stops the program if the step of a range isn't positive, as the range
+    /// would never end otherwise. Defines no value.
+    CheckStep {
+        step: Value,
+        span: Span,
+    },
+    /// Calls a function, passing it only the arguments of type `i32`.
+    Call {
+        callee: Callee,
+        args: Vec<Value>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Callee {
+    /// Function of the module.
+    Function(&'static str),
+    /// Builtin, which is a function of the runtime library.
+    Builtin(Builtin),
+    /// Extern function, which is a function of the C library.
+    Extern(&'static str),
+    /// Function that the embedder provides, by its index among the host
+    /// functions.
+    Host(usize),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Terminator {
+    Jump(BlockCall),
+    /// Goes to the first block if the condition isn't zero, or to the second
+    /// one otherwise.
+    Branch {
+        cond: Value,
+        then_call: BlockCall,
+        else_call: BlockCall,
+    },
+    Return(Option<Value>),
+}
+
+/// Target of a jump, along with the arguments for the block's parameters.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct BlockCall {
+    pub(crate) block: BlockId,
+    pub(crate) args: Vec<Value>,
+}
+
+impl Function {
+    /// Parameters of type `i32`, which are the entry block's parameters.
+    pub(crate) fn params(&self) -> &[Value] {
+        &self.blocks[0].params
+    }
+
+    /// How many values the function defines, which are numbered from zero.
+    pub(crate) fn value_count(&self) -> usize {
+        self.blocks
+            .iter()
+            .map(|block| {
+                let results = block.insts.iter().filter(|inst| inst.result.is_some());
+
+                block.params.len() + results.count()
+            })
+            .sum()
+    }
+
+    /// How many times each value is used, by its number.
+    pub(crate) fn use_counts(&self) -> Vec<usize> {
+        let mut use_counts = vec![0; self.value_count()];
+
+        for block in &self.blocks {
+            let inst_operands = block.insts.iter().flat_map(|inst| inst.kind.operands());
+
+            for value in inst_operands.chain(block.terminator.operands()) {
+                use_counts[value.0] += 1;
+            }
+        }
+
+        use_counts
+    }
+
+    /// Which values, by their number, have to be computed for their uses,
+    /// which are instructions other than comparisons, returns, branches that
+    /// go either way, and arguments of parameters that have to be computed in
+    /// turn. The rest can be left out, e.g., a loop's variable that's only
+    /// passed back to itself.
+    pub(crate) fn needed_values(&self) -> Vec<bool> {
+        let mut is_needed = vec![false; self.value_count()];
+
+        for block in &self.blocks {
+            let effectful_insts = block.insts.iter().filter(|inst| !inst.kind.is_comparison());
+
+            for inst in effectful_insts {
+                for operand in inst.kind.operands() {
+                    is_needed[operand.0] = true;
+                }
+            }
+
+            match &block.terminator {
+                Terminator::Branch {
+                    cond,
+                    then_call,
+                    else_call,
+                } if then_call != else_call => is_needed[cond.0] = true,
+                Terminator::Return(Some(value)) => is_needed[value.0] = true,
+                Terminator::Jump(_) | Terminator::Branch { .. } | Terminator::Return(None) => {}
+            }
+        }
+
+        let mut has_changed = true;
+
+        while has_changed {
+            has_changed = false;
+
+            for block in &self.blocks {
+                let comparison_uses = block
+                    .insts
+                    .iter()
+                    .filter(|inst| inst.kind.is_comparison())
+                    .filter_map(|inst| Some((inst.result?, inst.kind.operands())));
+
+                let arg_uses = block.terminator.calls().into_iter().flat_map(|call| {
+                    let params = &self.blocks[call.block.0].params;
+
+                    params
+                        .iter()
+                        .zip(&call.args)
+                        .map(|(&param, &arg)| (param, vec![arg]))
+                });
+
+                for (value, operands) in comparison_uses.chain(arg_uses) {
+                    for operand in operands {
+                        if is_needed[value.0] && !is_needed[operand.0] {
+                            is_needed[operand.0] = true;
+                            has_changed = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        is_needed
+    }
+
+    /// Which values, by their number, a stack machine can leave on its stack
+    /// for the one instruction or terminator that uses them, instead of
+    /// storing them in a local.
+    ///
+    /// The value has to be used only once, later in the block that defines it,
+    /// when every value pushed after it has been popped already. It's then
+    /// among the first operands of its use, the ones the stack holds on top
+    /// in order, and the others are pushed after them. The operands of a
+    /// terminator are its returned value, its jump's arguments for parameters
+    /// that are used, or its branch's condition.
+    pub(crate) fn stacked_values(&self) -> Vec<bool> {
+        let use_counts = self.use_counts();
+        let mut is_stacked = vec![false; use_counts.len()];
+
+        for block in &self.blocks {
+            let mut stack: Vec<Value> = vec![];
+
+            let uses = block
+                .insts
+                .iter()
+                .map(|inst| (inst.kind.operands(), inst.result))
+                .chain([(self.stack_operands(&block.terminator, &use_counts), None)]);
+
+            for (operands, result) in uses {
+                let taken_len = (0..=operands.len().min(stack.len()))
+                    .rev()
+                    .find(|&len| stack.ends_with(&operands[..len]))
+                    .unwrap_or(0);
+
+                for value in stack.drain(stack.len() - taken_len..) {
+                    is_stacked[value.0] = true;
+                }
+
+                // The other operands can't be reached under the ones pushed after
+                // them, so they're stored instead.
+                stack.retain(|value| !operands[taken_len..].contains(value));
+
+                if let Some(result) = result.filter(|result| use_counts[result.0] == 1) {
+                    stack.push(result);
+                }
+            }
+
+            // Whatever is left on the stack is used by other blocks, so it's
+            // stored too.
+        }
+
+        is_stacked
+    }
+
+    /// Operands that a stack machine pushes before the terminator, as a
+    /// branch's arguments are only pushed once it knows which way it goes.
+    fn stack_operands(&self, terminator: &Terminator, use_counts: &[usize]) -> Vec<Value> {
+        match terminator {
+            Terminator::Jump(call) => self
+                .used_args(call, use_counts)
+                .map(|(_, arg)| arg)
+                .collect(),
+            Terminator::Branch { cond, .. } => vec![*cond],
+            Terminator::Return(value) => value.iter().copied().collect(),
+        }
+    }
+
+    /// Parameters of the block that the jump goes to, each along with its
+    /// argument, leaving out the parameters that are never used.
+    pub(crate) fn used_args<'f>(
+        &'f self,
+        call: &'f BlockCall,
+        use_counts: &'f [usize],
+    ) -> impl Iterator<Item = (Value, Value)> + 'f {
+        let params = &self.blocks[call.block.0].params;
+
+        params
+            .iter()
+            .copied()
+            .zip(call.args.iter().copied())
+            .filter(|(param, _)| use_counts[param.0] > 0)
+    }
+
+    /// First line of the function's text, e.g., `fn add(i32, i32) -> i32`.
+    pub(crate) fn header(&self) -> String {
+        let param_types = vec!["i32"; self.params().len()];
+        let mut header = format!("fn {}({})", self.name, param_types.join(", "));
+
+        if self.returns_value {
+            header.push_str(" -> i32");
+        }
+
+        header
+    }
+}
+
+impl Block {
+    /// Label of the block in the function's text, along with its parameters,
+    /// e.g., `block1(v2)`.
+    pub(crate) fn label(&self, id: BlockId) -> String {
+        if self.params.is_empty() {
+            id.to_string()
+        } else {
+            format!("{}({})", id, ValueList(&self.params))
+        }
+    }
+}
+
+impl InstKind {
+    /// Values that the instruction uses, in the order they're evaluated.
+    pub(crate) fn operands(&self) -> Vec<Value> {
+        match self {
+            InstKind::Const(_) => vec![],
+            InstKind::Binary { lhs, rhs, .. }
+            | InstKind::Less { lhs, rhs }
+            | InstKind::LessEqual { lhs, rhs }
+            | InstKind::Overflows { lhs, rhs, .. } => vec![*lhs, *rhs],
+            InstKind::CheckStep { step, .. } => vec![*step],
+            InstKind::Call { args, .. } => args.clone(),
+        }
+    }
+
+    fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            InstKind::Less { .. } | InstKind::LessEqual { .. } | InstKind::Overflows { .. }
+        )
+    }
+}
+
+impl Terminator {
+    /// Values that the terminator uses, including the arguments of every
+    /// block it may go to.
+    pub(crate) fn operands(&self) -> Vec<Value> {
+        match self {
+            Terminator::Jump(call) => call.args.clone(),
+            Terminator::Branch {
+                cond,
+                then_call,
+                else_call,
+            } => [*cond]
+                .into_iter()
+                .chain(then_call.args.iter().copied())
+                .chain(else_call.args.iter().copied())
+                .collect(),
+            Terminator::Return(value) => value.iter().copied().collect(),
+        }
+    }
+
+    pub(crate) fn successors(&self) -> Vec<BlockId> {
+        self.calls().into_iter().map(|call| call.block).collect()
+    }
+
+    /// Jumps that the terminator may take, a branch's jump for a true
+    /// condition first.
+    pub(crate) fn calls(&self) -> Vec<&BlockCall> {
+        match self {
+            Terminator::Jump(call) => vec![call],
+            Terminator::Branch {
+                then_call,
+                else_call,
+                ..
+            } => vec![then_call, else_call],
+            Terminator::Return(_) => vec![],
+        }
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, function) in self.functions.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{}", function)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {{", self.header())?;
+
+        for (idx, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "{}:", block.label(BlockId(idx)))?;
+
+            for inst in &block.insts {
+                writeln!(f, "    {}", inst)?;
+            }
+
+            writeln!(f, "    {}", block.terminator)?;
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block{}", self.0)
+    }
+}
+
+impl fmt::Display for Inst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(result) = self.result {
+            write!(f, "{} = ", result)?;
+        }
+
+        match &self.kind {
+            InstKind::Const(value) => write!(f, "const {}", value),
+            InstKind::Binary { op, lhs, rhs, .. } => {
+                let mnemonic = match op {
+                    BinaryOp::Add => "add",
+                    BinaryOp::Sub => "sub",
+                    BinaryOp::Mul => "mul",
+                    BinaryOp::Div => "div",
+                    BinaryOp::Rem => "rem",
+                };
+
+                write!(f, "{} {}, {}", mnemonic, lhs, rhs)
+            }
+            InstKind::Less { lhs, rhs } => write!(f, "lt {}, {}", lhs, rhs),
+            InstKind::LessEqual { lhs, rhs } => write!(f, "le {}, {}", lhs, rhs),
+            InstKind::Overflows { op, lhs, rhs } => {
+                let mnemonic = match op {
+                    BinaryOp::Add => "add_overflows",
+                    BinaryOp::Sub => "sub_overflows",
+                    _ => unreachable!("only steps of ranges are checked for overflows"),
+                };
+
+                write!(f, "{} {}, {}", mnemonic, lhs, rhs)
+            }
+            InstKind::CheckStep { step, .. } => write!(f, "check_step {}", step),
+            InstKind::Call { callee, args } => write!(f, "call {}({})", callee, ValueList(args)),
+        }
+    }
+}
+
+impl fmt::Display for Callee {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Callee::Function(name) | Callee::Extern(name) => write!(f, "{}", name),
+            Callee::Builtin(builtin) => write!(f, "{}", builtin.runtime_symbol()),
+            Callee::Host(idx) => write!(f, "host{}", idx),
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminator::Jump(call) => write!(f, "jump {}", call),
+            Terminator::Branch {
+                cond,
+                then_call,
+                else_call,
+            } => write!(f, "branch {}, {}, {}", cond, then_call, else_call),
+            Terminator::Return(Some(value)) => write!(f, "return {}", value),
+            Terminator::Return(None) => write!(f, "return"),
+        }
+    }
+}
+
+impl fmt::Display for BlockCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.block)?;
+
+        if !self.args.is_empty() {
+            write!(f, "({})", ValueList(&self.args))?;
+        }
+
+        Ok(())
+    }
+}
+
+struct ValueList<'a>(&'a [Value]);
+
+impl fmt::Display for ValueList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, value) in self.0.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{}", value)?;
+        }
+
+        Ok(())
+    }
+}
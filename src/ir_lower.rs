@@ -0,0 +1,965 @@
+use crate::ast::{
+    self, BinaryExpr, BinaryOp, CompoundExpr, Const, Expr, FnCallExpr, ForExpr, ForIteration,
+    HostFnDecl, IfExpr, MatchExpr, Program, RangeKind, Type,
+};
+use crate::compiler_context::CompilerContext;
+use crate::host::HashMap;
+use crate::interner::Symbol;
+use crate::ir::{
+    Block, BlockCall, BlockId, Callee, Function, Inst, InstKind, Module, Terminator, Value,
+};
+use crate::lower::constant_value;
+use crate::resolve::{Resolution, Resolutions};
+use crate::scanner::Span;
+
+/// Lowers a program to the IR.
+///
+/// Bindings can't be reassigned, so the only values that change are the
+/// variables of iterative for-loops, which become parameters of the loops'
+/// headers, and the values of if-expressions, which become parameters of the
+/// blocks their branches join at.
+///
+/// The program is expected to have been resolved and type checked already.
+pub(crate) struct IrLowering<'ctx> {
+    ctx: &'ctx CompilerContext,
+    resolutions: &'ctx Resolutions,
+    host_fns: &'ctx [HostFnDecl],
+    functions: HashMap<Symbol, ast::Function<'ctx>>,
+    extern_fns: HashMap<Symbol, ast::ExternFn<'ctx>>,
+}
+
+impl<'ctx> IrLowering<'ctx> {
+    pub(crate) fn new(
+        ctx: &'ctx CompilerContext,
+        resolutions: &'ctx Resolutions,
+        host_fns: &'ctx [HostFnDecl],
+    ) -> IrLowering<'ctx> {
+        IrLowering {
+            ctx,
+            resolutions,
+            host_fns,
+            functions: Default::default(),
+            extern_fns: Default::default(),
+        }
+    }
+
+    pub(crate) fn lower_program(mut self, program: Program<'ctx>) -> Module {
+        for decl in program.decls {
+            match decl.value {
+                Expr::Function(function) => {
+                    self.functions.insert(decl.identifier, *function);
+                }
+                Expr::Extern(extern_fn) => {
+                    self.extern_fns.insert(decl.identifier, *extern_fn);
+                }
+                _ => {}
+            }
+        }
+
+        let functions = program
+            .decls
+            .iter()
+            .filter_map(|decl| match decl.value {
+                Expr::Function(function) => {
+                    let name = self.ctx.resolve_symbol(decl.identifier);
+
+                    Some(FunctionLowering::new(&self).lower_function(name, *function))
+                }
+                _ => None,
+            })
+            .collect();
+
+        Module { functions }
+    }
+}
+
+/// Block whose terminator isn't known yet.
+#[derive(Default)]
+struct PartialBlock {
+    params: Vec<Value>,
+    insts: Vec<Inst>,
+    terminator: Option<Terminator>,
+}
+
+/// Blocks that `continue` and `break` jump to in the innermost loop.
+struct LoopTargets {
+    continue_block: BlockId,
+    break_block: BlockId,
+}
+
+struct FunctionLowering<'l, 'ctx> {
+    lowering: &'l IrLowering<'ctx>,
+    blocks: Vec<PartialBlock>,
+    /// Block that instructions are added to.
+    current_block: BlockId,
+    value_count: usize,
+    /// Value of every binding of type `i32`, by the span of its definition.
+    bindings: HashMap<Span, Value>,
+    loop_stack: Vec<LoopTargets>,
+}
+
+impl<'l, 'ctx> FunctionLowering<'l, 'ctx> {
+    fn new(lowering: &'l IrLowering<'ctx>) -> FunctionLowering<'l, 'ctx> {
+        FunctionLowering {
+            lowering,
+            blocks: vec![PartialBlock::default()],
+            current_block: BlockId(0),
+            value_count: 0,
+            bindings: Default::default(),
+            loop_stack: vec![],
+        }
+    }
+
+    fn lower_function(mut self, name: &'static str, function: ast::Function) -> Function {
+        for param in function.parameters {
+            if param.ty == Type::I32 {
+                let value = self.add_block_param(BlockId(0));
+                self.bindings.insert(param.span, value);
+            }
+        }
+
+        let value = self.lower_compound_expr(function.body);
+        let returns_value = function.return_type == Type::I32;
+
+        self.terminate(Terminator::Return(value.filter(|_| returns_value)));
+
+        Function {
+            name,
+            returns_value,
+            blocks: self.finish(),
+        }
+    }
+
+    /// Lowers the expression, returning its value, or `None` if it's of type
+    /// `()`.
+    fn lower_expr(&mut self, expr: &Expr) -> Option<Value> {
+        match expr {
+            Expr::Const(const_expr) => match const_expr.value {
+                Const::IntegerConstant { value } => Some(self.add_inst(InstKind::Const(value))),
+                Const::SuffixedIntegerConstant { .. } => unreachable!(
+                    "programs with structs, enums, tuples, arrays, function values or integers other than `i32` are rejected before code generation"
+                ),
+            },
+            Expr::BindRef(bind_ref) => match self.lowering.resolutions.get(bind_ref.span) {
+                // Bindings of type `()` have no value.
+                Some(Resolution::Local(def_span)) => self.bindings.get(&def_span).copied(),
+                // Programs with function values are rejected before code generation.
+                _ => None,
+            },
+            Expr::BindDef(bind_def) => {
+                let value = self.lower_expr(bind_def.value);
+
+                if bind_def.identifier != self.lowering.ctx.symbols().discard {
+                    if let Some(value) = value {
+                        self.bindings.insert(bind_def.span, value);
+                    }
+                }
+
+                None
+            }
+            Expr::Function(_) => unreachable!("closures are function values, which are rejected before code generation"),
+            Expr::Extern(_) => unreachable!("extern functions are only declared at the top level"),
+            Expr::Struct(_)
+            | Expr::StructLit(_)
+            | Expr::Field(_)
+            | Expr::Enum(_)
+            | Expr::Variant(_)
+            | Expr::Tuple(_)
+            | Expr::TupleField(_)
+            | Expr::TupleBindDef(_)
+            | Expr::Array(_)
+            | Expr::Index(_)
+            | Expr::IndexAssign(_) => {
+                unreachable!(
+                    "programs with structs, enums, tuples, arrays, function values or integers other than `i32` are rejected before code generation"
+                )
+            }
+            Expr::If(if_expr) => self.lower_if_expr(*if_expr),
+            Expr::Match(match_expr) => self.lower_match_expr(*match_expr),
+            Expr::For(for_expr) => {
+                self.lower_for_expr(*for_expr);
+
+                None
+            }
+            Expr::Break(_) => {
+                let break_block = self.loop_stack.last().unwrap().break_block;
+                self.jump_away(break_block);
+
+                None
+            }
+            Expr::Continue(_) => {
+                let continue_block = self.loop_stack.last().unwrap().continue_block;
+                self.jump_away(continue_block);
+
+                None
+            }
+            Expr::Compound(compound_expr) => self.lower_compound_expr(*compound_expr),
+            Expr::Semi(expr) => {
+                self.lower_expr(expr);
+
+                None
+            }
+            Expr::FnCall(fn_call_expr) => self.lower_fn_call_expr(*fn_call_expr),
+            Expr::Binary(binary_expr) => Some(self.lower_binary_expr(*binary_expr)),
+            // Casts that reach code generation are of `i32`s to `i32`, which
+            // leave them as they are.
+            Expr::Cast(cast_expr) => self.lower_expr(cast_expr.expr),
+            Expr::Paren(paren_expr) => self.lower_expr(paren_expr.expr),
+        }
+    }
+
+    fn lower_int_expr(&mut self, expr: &Expr) -> Value {
+        self.lower_expr(expr)
+            .expect("type checking guarantees an integer")
+    }
+
+    fn lower_if_expr(&mut self, if_expr: IfExpr) -> Option<Value> {
+        // Without a final branch, the if-expression is of type `()`, so the
+        // values of its branches are discarded.
+        let keeps_value = if_expr.final_branch.is_some();
+        let join_block = self.new_block();
+        let mut join_param = None;
+
+        let branches = [(if_expr.cond_expr, if_expr.true_branch)]
+            .into_iter()
+            .chain(
+                if_expr
+                    .else_if_branches
+                    .iter()
+                    .map(|branch| (branch.cond_expr, branch.true_branch)),
+            );
+
+        let mut lower_branch = |this: &mut Self, branch: CompoundExpr| {
+            let value = this.lower_compound_expr(branch).filter(|_| keeps_value);
+
+            // The branches that don't break or continue out of a loop are all of
+            // the same type, so either all of them have a value to pass on, or
+            // none does. The others jump from blocks that are dropped.
+            if value.is_some() && join_param.is_none() {
+                join_param = Some(this.add_block_param(join_block));
+            }
+
+            this.terminate(Terminator::Jump(BlockCall {
+                block: join_block,
+                args: value.into_iter().collect(),
+            }));
+        };
+
+        for (cond_expr, true_branch) in branches {
+            let cond = self.lower_int_expr(cond_expr);
+            let then_block = self.new_block();
+            let else_block = self.new_block();
+
+            self.terminate(Terminator::Branch {
+                cond,
+                then_call: no_args(then_block),
+                else_call: no_args(else_block),
+            });
+
+            self.current_block = then_block;
+            lower_branch(self, true_branch);
+
+            self.current_block = else_block;
+        }
+
+        match if_expr.final_branch {
+            Some(final_branch) => lower_branch(self, final_branch),
+            None => self.terminate(Terminator::Jump(no_args(join_block))),
+        }
+
+        self.current_block = join_block;
+
+        join_param
+    }
+
+    /// Lowers the arms like the branches of an if-expression, whose conditions
+    /// test the scrutinee against the bounds of the arms' patterns. Match-
+    /// expressions are exhaustive, so the last arm tried isn't tested.
+    fn lower_match_expr(&mut self, match_expr: MatchExpr) -> Option<Value> {
+        let scrutinee = self.lower_int_expr(match_expr.scrutinee);
+        let join_block = self.new_block();
+        let mut join_param = None;
+
+        let arms = match_expr.tried_arms();
+
+        for (idx, arm) in arms.iter().enumerate() {
+            let next_block = match arm.pattern.kind.bounds().filter(|_| idx + 1 < arms.len()) {
+                Some((start, end)) => {
+                    let arm_block = self.new_block();
+                    let end_test_block = self.new_block();
+                    let next_block = self.new_block();
+
+                    // Type checking guarantees that the bounds are integers.
+                    let start = self.add_inst(InstKind::Const(start as i32));
+                    let is_above_start = self.add_inst(InstKind::LessEqual {
+                        lhs: start,
+                        rhs: scrutinee,
+                    });
+
+                    self.terminate(Terminator::Branch {
+                        cond: is_above_start,
+                        then_call: no_args(end_test_block),
+                        else_call: no_args(next_block),
+                    });
+
+                    self.current_block = end_test_block;
+
+                    let end = self.add_inst(InstKind::Const(end as i32));
+                    let is_below_end = self.add_inst(InstKind::LessEqual {
+                        lhs: scrutinee,
+                        rhs: end,
+                    });
+
+                    self.terminate(Terminator::Branch {
+                        cond: is_below_end,
+                        then_call: no_args(arm_block),
+                        else_call: no_args(next_block),
+                    });
+
+                    self.current_block = arm_block;
+
+                    Some(next_block)
+                }
+                None => None,
+            };
+
+            let value = self.lower_expr(arm.expr);
+
+            // The arms are all of the same type, so either all of them have a
+            // value to pass on, or none does.
+            if value.is_some() && join_param.is_none() {
+                join_param = Some(self.add_block_param(join_block));
+            }
+
+            self.terminate(Terminator::Jump(BlockCall {
+                block: join_block,
+                args: value.into_iter().collect(),
+            }));
+
+            if let Some(next_block) = next_block {
+                self.current_block = next_block;
+            }
+        }
+
+        self.current_block = join_block;
+
+        join_param
+    }
+
+    fn lower_for_expr(&mut self, for_expr: ForExpr) {
+        let exit_block = self.new_block();
+
+        match for_expr.iteration {
+            Some(ForIteration::Iterative {
+                identifier_span,
+                start_expr,
+                end_expr,
+                range_kind,
+                step_expr,
+                is_reversed: false,
+                ..
+            }) => {
+                // The range's end and step are evaluated only once, before the loop starts.
+                let start = self.lower_int_expr(start_expr);
+                let end = self.lower_int_expr(end_expr);
+                let step = step_expr.map(|step_expr| self.lower_range_step(step_expr));
+
+                let header_block = self.new_block();
+                let current = self.add_block_param(header_block);
+                self.bindings.insert(identifier_span, current);
+
+                self.terminate(Terminator::Jump(BlockCall {
+                    block: header_block,
+                    args: vec![start],
+                }));
+
+                self.current_block = header_block;
+
+                let is_in_range = match range_kind {
+                    RangeKind::Inclusive => self.add_inst(InstKind::LessEqual {
+                        lhs: current,
+                        rhs: end,
+                    }),
+                    RangeKind::Exclusive => self.add_inst(InstKind::Less {
+                        lhs: current,
+                        rhs: end,
+                    }),
+                };
+
+                let step_block = self.new_block();
+                let completion_block =
+                    self.lower_loop_body(for_expr, is_in_range, step_block, exit_block);
+
+                self.current_block = step_block;
+
+                let step = match step {
+                    Some(step) => step,
+                    None => self.add_inst(InstKind::Const(1)),
+                };
+
+                // The range also runs to completion if stepping would go past
+                // the largest or smallest value of its type, rather than
+                // overflowing.
+                let next_block =
+                    self.lower_overflow_check(BinaryOp::Add, current, step, completion_block);
+                self.current_block = next_block;
+
+                let next = self.add_inst(InstKind::Binary {
+                    op: BinaryOp::Add,
+                    lhs: current,
+                    rhs: step,
+                    span: identifier_span,
+                });
+
+                self.terminate(Terminator::Jump(BlockCall {
+                    block: header_block,
+                    args: vec![next],
+                }));
+            }
+            Some(ForIteration::Iterative {
+                is_reversed: true, ..
+            }) => self.lower_reversed_range_loop(for_expr, exit_block),
+            Some(ForIteration::Conditional { cond_expr }) => {
+                let header_block = self.new_block();
+                self.terminate(Terminator::Jump(no_args(header_block)));
+                self.current_block = header_block;
+
+                let cond = self.lower_int_expr(cond_expr);
+                self.lower_loop_body(for_expr, cond, header_block, exit_block);
+            }
+            Some(ForIteration::InCollection { .. }) => {
+                unreachable!(
+                    "programs with structs, enums, tuples, arrays, function values or integers other than `i32` are rejected before code generation"
+                )
+            }
+            None => {
+                let body_block = self.new_block();
+                self.terminate(Terminator::Jump(no_args(body_block)));
+                self.current_block = body_block;
+
+                self.loop_stack.push(LoopTargets {
+                    continue_block: body_block,
+                    break_block: exit_block,
+                });
+
+                self.lower_compound_expr(for_expr.body);
+                self.loop_stack.pop();
+
+                self.terminate(Terminator::Jump(no_args(body_block)));
+            }
+        }
+
+        self.current_block = exit_block;
+    }
+
+    /// Counts the loop's variable down from the last value of the range to its
+    /// start. Whether there's a next value is passed to the loop's header
+    /// along with it, as it's only there if stepping doesn't go past the
+    /// start, nor past the smallest value of its type.
+    fn lower_reversed_range_loop(&mut self, for_expr: ForExpr, exit_block: BlockId) {
+        let Some(ForIteration::Iterative {
+            identifier_span,
+            start_expr,
+            end_expr,
+            range_kind,
+            step_expr,
+            ..
+        }) = for_expr.iteration
+        else {
+            unreachable!("only ranges are reversed");
+        };
+
+        let start = self.lower_int_expr(start_expr);
+        let end = self.lower_int_expr(end_expr);
+        let step = match step_expr {
+            Some(step_expr) => self.lower_range_step(step_expr),
+            None => self.add_inst(InstKind::Const(1)),
+        };
+
+        let (is_in_range, last) = match range_kind {
+            RangeKind::Inclusive => (
+                self.add_inst(InstKind::LessEqual {
+                    lhs: start,
+                    rhs: end,
+                }),
+                end,
+            ),
+            RangeKind::Exclusive => {
+                let is_in_range = self.add_inst(InstKind::Less {
+                    lhs: start,
+                    rhs: end,
+                });
+                let one = self.add_inst(InstKind::Const(1));
+                let last = self.add_inst(InstKind::Binary {
+                    op: BinaryOp::Sub,
+                    lhs: end,
+                    rhs: one,
+                    span: identifier_span,
+                });
+
+                (is_in_range, last)
+            }
+        };
+
+        let header_block = self.new_block();
+        let current = self.add_block_param(header_block);
+        let has_value = self.add_block_param(header_block);
+        self.bindings.insert(identifier_span, current);
+
+        self.terminate(Terminator::Jump(BlockCall {
+            block: header_block,
+            args: vec![last, is_in_range],
+        }));
+
+        self.current_block = header_block;
+
+        let step_block = self.new_block();
+        let completion_block = self.lower_loop_body(for_expr, has_value, step_block, exit_block);
+
+        self.current_block = step_block;
+
+        let next_block = self.lower_overflow_check(BinaryOp::Sub, current, step, completion_block);
+        self.current_block = next_block;
+
+        let next = self.add_inst(InstKind::Binary {
+            op: BinaryOp::Sub,
+            lhs: current,
+            rhs: step,
+            span: identifier_span,
+        });
+        let has_next = self.add_inst(InstKind::LessEqual {
+            lhs: start,
+            rhs: next,
+        });
+
+        self.terminate(Terminator::Jump(BlockCall {
+            block: header_block,
+            args: vec![next, has_next],
+        }));
+    }
+
+    /// Evaluates the step of a range, which stops the program unless it's
+    /// positive. Constant steps are never zero, as type checking rejects
+    /// those, nor negative, as there are no negative literals.
+    fn lower_range_step(&mut self, step_expr: &Expr) -> Value {
+        let step = self.lower_int_expr(step_expr);
+
+        if constant_value(step_expr).is_none() {
+            self.block_mut(self.current_block).insts.push(Inst {
+                result: None,
+                kind: InstKind::CheckStep {
+                    step,
+                    span: step_expr.span(),
+                },
+            });
+        }
+
+        step
+    }
+
+    /// Goes to the loop's completion if stepping the loop's variable
+    /// overflows, or to the returned block otherwise, which steps it.
+    fn lower_overflow_check(
+        &mut self,
+        op: BinaryOp,
+        current: Value,
+        step: Value,
+        completion_block: BlockId,
+    ) -> BlockId {
+        let overflows = self.add_inst(InstKind::Overflows {
+            op,
+            lhs: current,
+            rhs: step,
+        });
+        let next_block = self.new_block();
+
+        self.terminate(Terminator::Branch {
+            cond: overflows,
+            then_call: no_args(completion_block),
+            else_call: no_args(next_block),
+        });
+
+        next_block
+    }
+
+    /// Branches on the condition to the loop's body, which then goes to the
+    /// continue block, or to the loop's completion, which runs the else-branch
+    /// before going to the exit block. Returns the loop's completion.
+    fn lower_loop_body(
+        &mut self,
+        for_expr: ForExpr,
+        cond: Value,
+        continue_block: BlockId,
+        exit_block: BlockId,
+    ) -> BlockId {
+        let body_block = self.new_block();
+        let completion_block = self.new_block();
+
+        self.terminate(Terminator::Branch {
+            cond,
+            then_call: no_args(body_block),
+            else_call: no_args(completion_block),
+        });
+
+        self.current_block = body_block;
+
+        self.loop_stack.push(LoopTargets {
+            continue_block,
+            break_block: exit_block,
+        });
+
+        self.lower_compound_expr(for_expr.body);
+        self.loop_stack.pop();
+
+        self.terminate(Terminator::Jump(no_args(continue_block)));
+
+        // The else-branch is lowered outside of the loop, so that a `break` or
+        // `continue` in it refers to an enclosing loop instead.
+        self.current_block = completion_block;
+
+        if let Some(else_branch) = for_expr.else_branch {
+            self.lower_compound_expr(else_branch);
+        }
+
+        self.terminate(Terminator::Jump(no_args(exit_block)));
+
+        completion_block
+    }
+
+    fn lower_compound_expr(&mut self, compound_expr: CompoundExpr) -> Option<Value> {
+        let mut value = None;
+
+        for expr in compound_expr.exprs {
+            value = self.lower_expr(expr);
+        }
+
+        value
+    }
+
+    fn lower_fn_call_expr(&mut self, fn_call_expr: FnCallExpr) -> Option<Value> {
+        let args = fn_call_expr
+            .args
+            .iter()
+            .filter_map(|arg| self.lower_expr(arg))
+            .collect();
+
+        let name = self.lowering.ctx.resolve_symbol(fn_call_expr.identifier);
+
+        let (callee, return_type) = match self.lowering.resolutions.get(fn_call_expr.span) {
+            Some(Resolution::Builtin(builtin)) => (Callee::Builtin(builtin), Type::Unit),
+            Some(Resolution::Host(host_fn_idx)) => (
+                Callee::Host(host_fn_idx),
+                self.lowering.host_fns[host_fn_idx].return_type,
+            ),
+            Some(Resolution::Decl(_) | Resolution::Local(_)) | None => {
+                match self.lowering.extern_fns.get(&fn_call_expr.identifier) {
+                    Some(extern_fn) => (Callee::Extern(name), extern_fn.return_type),
+                    None => (
+                        Callee::Function(name),
+                        self.lowering.functions[&fn_call_expr.identifier].return_type,
+                    ),
+                }
+            }
+        };
+
+        let result = (return_type == Type::I32).then(|| self.new_value());
+
+        self.block_mut(self.current_block).insts.push(Inst {
+            result,
+            kind: InstKind::Call { callee, args },
+        });
+
+        result
+    }
+
+    fn lower_binary_expr(&mut self, binary_expr: BinaryExpr) -> Value {
+        let (operand, operations) = binary_expr.chain();
+        let mut lhs = self.lower_int_expr(operand);
+
+        for binary_expr in operations {
+            let rhs = self.lower_int_expr(binary_expr.rhs);
+
+            lhs = self.add_inst(InstKind::Binary {
+                op: binary_expr.op,
+                lhs,
+                rhs,
+                span: binary_expr.span,
+            });
+        }
+
+        lhs
+    }
+
+    fn new_value(&mut self) -> Value {
+        self.value_count += 1;
+
+        Value(self.value_count - 1)
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(PartialBlock::default());
+
+        BlockId(self.blocks.len() - 1)
+    }
+
+    fn block_mut(&mut self, block: BlockId) -> &mut PartialBlock {
+        &mut self.blocks[block.0]
+    }
+
+    fn add_block_param(&mut self, block: BlockId) -> Value {
+        let value = self.new_value();
+        self.block_mut(block).params.push(value);
+
+        value
+    }
+
+    fn add_inst(&mut self, kind: InstKind) -> Value {
+        let result = self.new_value();
+
+        self.block_mut(self.current_block).insts.push(Inst {
+            result: Some(result),
+            kind,
+        });
+
+        result
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        let block = self.block_mut(self.current_block);
+        debug_assert!(block.terminator.is_none(), "block terminated twice");
+
+        block.terminator = Some(terminator);
+    }
+
+    /// Jumps to the block, leaving what comes next in a new block that nothing
+    /// jumps to, which is dropped when the function is finished.
+    fn jump_away(&mut self, target: BlockId) {
+        self.terminate(Terminator::Jump(no_args(target)));
+        self.current_block = self.new_block();
+    }
+
+    /// Makes jumps to blocks that do nothing but jump on go straight to where
+    /// those go, drops the blocks that can't be reached from the entry block
+    /// then, and renumbers the remaining blocks in reverse postorder, and the
+    /// values in the order they're defined.
+    fn finish(mut self) -> Vec<Block> {
+        thread_jumps(&mut self.blocks);
+
+        let order = reverse_postorder(&self.blocks);
+
+        let mut block_ids = HashMap::default();
+
+        for (new_idx, &old_idx) in order.iter().enumerate() {
+            block_ids.insert(BlockId(old_idx), BlockId(new_idx));
+        }
+
+        let mut values = HashMap::default();
+        let mut blocks: Vec<_> = self.blocks.into_iter().map(Some).collect();
+
+        order
+            .into_iter()
+            .map(|old_idx| {
+                let block = blocks[old_idx].take().unwrap();
+
+                let params = block
+                    .params
+                    .into_iter()
+                    .map(|param| renumber(&mut values, param))
+                    .collect();
+
+                let mut insts = vec![];
+
+                for inst in block.insts {
+                    let kind = map_inst_kind(inst.kind, |value| values[&value]);
+                    let result = inst.result.map(|result| renumber(&mut values, result));
+
+                    insts.push(Inst { result, kind });
+                }
+
+                let terminator = block
+                    .terminator
+                    .expect("every reachable block is terminated");
+
+                Block {
+                    params,
+                    insts,
+                    terminator: map_terminator(
+                        terminator,
+                        |value| values[&value],
+                        |block| block_ids[&block],
+                        |call| call,
+                    ),
+                }
+            })
+            .collect()
+    }
+}
+
+fn thread_jumps(blocks: &mut [PartialBlock]) {
+    let threaded_terminators: Vec<_> = blocks
+        .iter()
+        .map(|block| {
+            let terminator = block.terminator.clone()?;
+
+            Some(map_terminator(
+                terminator,
+                |value| value,
+                |block| block,
+                |call| threaded_call(blocks, call),
+            ))
+        })
+        .collect();
+
+    for (block, terminator) in blocks.iter_mut().zip(threaded_terminators) {
+        block.terminator = terminator;
+    }
+}
+
+/// Call of the block that a jump ends up at, following the blocks that do
+/// nothing but jump on, up to an infinite loop of them, if any.
+fn threaded_call(blocks: &[PartialBlock], call: BlockCall) -> BlockCall {
+    let mut threaded_call = call;
+    let mut passed_blocks = vec![];
+
+    while let Some(next_call) = forwarded_call(&blocks[threaded_call.block.0]) {
+        if passed_blocks.contains(&threaded_call.block) {
+            break;
+        }
+
+        passed_blocks.push(threaded_call.block);
+        threaded_call = next_call.clone();
+    }
+
+    threaded_call
+}
+
+/// Where the block jumps on to, if that's all it does.
+fn forwarded_call(block: &PartialBlock) -> Option<&BlockCall> {
+    match &block.terminator {
+        Some(Terminator::Jump(call)) if block.params.is_empty() && block.insts.is_empty() => {
+            Some(call)
+        }
+        _ => None,
+    }
+}
+
+/// Indices of the blocks reachable from the entry block, in reverse postorder,
+/// where the successors of a block come in the order it lists them.
+fn reverse_postorder(blocks: &[PartialBlock]) -> Vec<usize> {
+    let mut visited = vec![false; blocks.len()];
+    let mut postorder = vec![];
+    // Blocks along with whether their successors have been pushed already.
+    let mut stack = vec![(0, false)];
+
+    while let Some((idx, is_expanded)) = stack.pop() {
+        if is_expanded {
+            postorder.push(idx);
+            continue;
+        }
+
+        if visited[idx] {
+            continue;
+        }
+
+        visited[idx] = true;
+        stack.push((idx, true));
+
+        let successors = blocks[idx]
+            .terminator
+            .as_ref()
+            .map(Terminator::successors)
+            .unwrap_or_default();
+
+        // The last successor pushed is visited first, so it finishes first, and
+        // the first successor ends up right after the block instead.
+        for successor in successors {
+            if !visited[successor.0] {
+                stack.push((successor.0, false));
+            }
+        }
+    }
+
+    postorder.reverse();
+
+    postorder
+}
+
+/// Gives the value the next number in order of definition.
+fn renumber(values: &mut HashMap<Value, Value>, value: Value) -> Value {
+    let new_value = Value(values.len());
+    values.insert(value, new_value);
+
+    new_value
+}
+
+fn map_inst_kind(kind: InstKind, mut map: impl FnMut(Value) -> Value) -> InstKind {
+    match kind {
+        InstKind::Const(value) => InstKind::Const(value),
+        InstKind::Binary { op, lhs, rhs, span } => InstKind::Binary {
+            op,
+            lhs: map(lhs),
+            rhs: map(rhs),
+            span,
+        },
+        InstKind::Less { lhs, rhs } => InstKind::Less {
+            lhs: map(lhs),
+            rhs: map(rhs),
+        },
+        InstKind::LessEqual { lhs, rhs } => InstKind::LessEqual {
+            lhs: map(lhs),
+            rhs: map(rhs),
+        },
+        InstKind::Overflows { op, lhs, rhs } => InstKind::Overflows {
+            op,
+            lhs: map(lhs),
+            rhs: map(rhs),
+        },
+        InstKind::CheckStep { step, span } => InstKind::CheckStep {
+            step: map(step),
+            span,
+        },
+        InstKind::Call { callee, args } => InstKind::Call {
+            callee,
+            args: args.into_iter().map(map).collect(),
+        },
+    }
+}
+
+/// Maps the terminator's values and blocks, after mapping the calls of blocks
+/// as a whole.
+fn map_terminator(
+    terminator: Terminator,
+    mut map_value: impl FnMut(Value) -> Value,
+    mut map_block: impl FnMut(BlockId) -> BlockId,
+    mut map_whole_call: impl FnMut(BlockCall) -> BlockCall,
+) -> Terminator {
+    let mut map_call = |call: BlockCall, map_value: &mut dyn FnMut(Value) -> Value| {
+        let call = map_whole_call(call);
+
+        BlockCall {
+            block: map_block(call.block),
+            args: call.args.into_iter().map(map_value).collect(),
+        }
+    };
+
+    match terminator {
+        Terminator::Jump(call) => Terminator::Jump(map_call(call, &mut map_value)),
+        Terminator::Branch {
+            cond,
+            then_call,
+            else_call,
+        } => Terminator::Branch {
+            cond: map_value(cond),
+            then_call: map_call(then_call, &mut map_value),
+            else_call: map_call(else_call, &mut map_value),
+        },
+        Terminator::Return(value) => Terminator::Return(value.map(map_value)),
+    }
+}
+
+fn no_args(block: BlockId) -> BlockCall {
+    BlockCall {
+        block,
+        args: vec![],
+    }
+}
@@ -0,0 +1,1084 @@
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+use cranelift_codegen::entity::EntityRef;
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{
+    types, AbiParam, Block, FuncRef, InstBuilder, MemFlags, Signature, Value,
+};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_codegen::Context;
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, FuncId, Linkage, Module};
+
+use crate::ast::{
+    BinaryExpr, BinaryOp, CompoundExpr, Const, Expr, ExternFn, FnCallExpr, ForExpr, ForIteration,
+    Function, IfExpr, MatchExpr, Program, RangeKind, Type,
+};
+use crate::builtin::Builtin;
+use crate::compiler_context::CompilerContext;
+use crate::frame::{FrameLayout, SlotOwner};
+use crate::interner::Symbol;
+use crate::interp::RuntimeError;
+use crate::lower::constant_value;
+use crate::resolve::{Resolution, Resolutions};
+use crate::scanner::Span;
+
+/// Compiles a program to machine code in-process with Cranelift, then runs it,
+/// as a faster alternative to the interpreter.
+///
+/// Every compiled function returns a status before its value: zero if it
+/// returned normally, or otherwise the position, counting from one, of the
+/// runtime error that stopped it in `error_sites`. Callers check the status
+/// after each call and pass it on, so that errors unwind up to the entry point
+/// just like they do in the interpreter.
+pub(crate) struct Jit<'ctx> {
+    ctx: &'ctx CompilerContext,
+    resolutions: &'ctx Resolutions,
+    module: JITModule,
+    functions: HashMap<Symbol, CompiledFunction<'ctx>>,
+    /// Functions of the runtime library that the program's builtins call.
+    builtins: HashMap<Builtin, FuncId>,
+    /// Extern functions, which are looked up in the process, e.g., in the C
+    /// library that it's linked with.
+    extern_fns: HashMap<Symbol, (FuncId, ExternFn<'ctx>)>,
+    error_sites: Vec<RuntimeError>,
+}
+
+#[derive(Clone, Copy)]
+struct CompiledFunction<'ctx> {
+    id: FuncId,
+    function: Function<'ctx>,
+}
+
+/// Signature of the generated entry point, which calls `main` and stores its
+/// value through the pointer.
+type EntryPoint = unsafe extern "C" fn(*mut i32) -> i32;
+
+impl<'ctx> Jit<'ctx> {
+    pub(crate) fn new(
+        ctx: &'ctx CompilerContext,
+        resolutions: &'ctx Resolutions,
+    ) -> Result<Jit<'ctx>, RuntimeError> {
+        let mut flags = settings::builder();
+        flags.set("opt_level", "speed").map_err(jit_error)?;
+
+        let isa = cranelift_native::builder()
+            .map_err(jit_error)?
+            .finish(settings::Flags::new(flags))
+            .map_err(jit_error)?;
+
+        let mut jit_builder = JITBuilder::with_isa(isa, default_libcall_names());
+
+        for builtin in Builtin::ALL {
+            jit_builder.symbol(builtin.runtime_symbol(), runtime_function(builtin));
+        }
+
+        Ok(Jit {
+            ctx,
+            resolutions,
+            module: JITModule::new(jit_builder),
+            functions: Default::default(),
+            builtins: Default::default(),
+            extern_fns: Default::default(),
+            error_sites: vec![],
+        })
+    }
+
+    /// Runs the program's `main` function, returning its value as an exit
+    /// code, or zero if it returns `()`.
+    pub(crate) fn run_program(mut self, program: Program<'ctx>) -> Result<i32, RuntimeError> {
+        for builtin in self.resolutions.builtins() {
+            let mut signature = self.module.make_signature();
+            signature.params.push(AbiParam::new(types::I32));
+
+            let id = self
+                .module
+                .declare_function(builtin.runtime_symbol(), Linkage::Import, &signature)
+                .map_err(jit_error)?;
+
+            self.builtins.insert(builtin, id);
+        }
+
+        for decl in program.decls {
+            if let Expr::Extern(extern_fn) = decl.value {
+                let mut signature = self.module.make_signature();
+
+                for _ in extern_fn.parameters {
+                    signature.params.push(AbiParam::new(types::I32));
+                }
+
+                if extern_fn.return_type != Type::Unit {
+                    signature.returns.push(AbiParam::new(types::I32));
+                }
+
+                let id = self
+                    .module
+                    .declare_function(
+                        self.ctx.resolve_symbol(decl.identifier),
+                        Linkage::Import,
+                        &signature,
+                    )
+                    .map_err(jit_error)?;
+
+                self.extern_fns.insert(decl.identifier, (id, *extern_fn));
+            }
+
+            if let Expr::Function(function) = decl.value {
+                let name = self.ctx.resolve_symbol(decl.identifier);
+                let signature = self.signature_of(*function);
+                let id = self
+                    .module
+                    .declare_function(name, Linkage::Local, &signature)
+                    .map_err(jit_error)?;
+
+                self.functions.insert(
+                    decl.identifier,
+                    CompiledFunction {
+                        id,
+                        function: *function,
+                    },
+                );
+            }
+        }
+
+        let main_identifier = self.ctx.symbols().main;
+
+        let Some(&main) = self.functions.get(&main_identifier) else {
+            return Err(RuntimeError::MissingMain);
+        };
+
+        if let Some(param) = main.function.parameters.first() {
+            return Err(RuntimeError::MainTakesParameters { span: param.span });
+        }
+
+        let mut codegen_context = self.module.make_context();
+        let mut builder_context = FunctionBuilderContext::new();
+
+        for compiled_function in self.functions.values().copied().collect::<Vec<_>>() {
+            codegen_context.func.signature = self.signature_of(compiled_function.function);
+            self.define_function(
+                &mut codegen_context,
+                &mut builder_context,
+                compiled_function.function,
+            );
+            self.finish_definition(compiled_function.id, &mut codegen_context)?;
+        }
+
+        let entry_id = self.define_entry_point(&mut codegen_context, &mut builder_context, main)?;
+
+        self.module.finalize_definitions().map_err(jit_error)?;
+
+        let entry_point = self.module.get_finalized_function(entry_id);
+
+        let mut exit_code = 0;
+
+        // SAFETY: the entry point was generated with the `EntryPoint` signature
+        // and the platform's default calling convention, which is the C one.
+        let status = unsafe {
+            let entry_point: EntryPoint = mem::transmute(entry_point);
+            entry_point(&mut exit_code)
+        };
+
+        // SAFETY: nothing refers to the generated code anymore.
+        unsafe { self.module.free_memory() };
+
+        match status {
+            0 => Ok(exit_code),
+            status => Err(self.error_sites[status as usize - 1].clone()),
+        }
+    }
+
+    /// Returns the status, followed by the value if the function returns
+    /// `i32`. Parameters of type `()` have no representation, so they're left
+    /// out.
+    fn signature_of(&self, function: Function) -> Signature {
+        let mut signature = self.module.make_signature();
+
+        for param in function.parameters {
+            if param.ty == Type::I32 {
+                signature.params.push(AbiParam::new(types::I32));
+            }
+        }
+
+        signature.returns.push(AbiParam::new(types::I32));
+
+        if function.return_type == Type::I32 {
+            signature.returns.push(AbiParam::new(types::I32));
+        }
+
+        signature
+    }
+
+    fn define_function(
+        &mut self,
+        codegen_context: &mut Context,
+        builder_context: &mut FunctionBuilderContext,
+        function: Function<'ctx>,
+    ) {
+        let frame_layout = FrameLayout::of_function(self.ctx, function);
+        let builder = FunctionBuilder::new(&mut codegen_context.func, builder_context);
+
+        let lowering = FunctionLowering {
+            jit: self,
+            builder,
+            frame_layout,
+            return_type: function.return_type,
+            unit_bindings: Default::default(),
+            loop_stack: vec![],
+            func_refs: Default::default(),
+            next_temp_variable: 0,
+        };
+
+        lowering.lower_function(function);
+    }
+
+    fn define_entry_point(
+        &mut self,
+        codegen_context: &mut Context,
+        builder_context: &mut FunctionBuilderContext,
+        main: CompiledFunction,
+    ) -> Result<FuncId, RuntimeError> {
+        let pointer_type = self.module.target_config().pointer_type();
+
+        let mut signature = self.module.make_signature();
+        signature.params.push(AbiParam::new(pointer_type));
+        signature.returns.push(AbiParam::new(types::I32));
+
+        let entry_id = self
+            .module
+            .declare_anonymous_function(&signature)
+            .map_err(jit_error)?;
+
+        codegen_context.func.signature = signature;
+
+        {
+            let mut builder = FunctionBuilder::new(&mut codegen_context.func, builder_context);
+            let block = builder.create_block();
+            builder.append_block_params_for_function_params(block);
+            builder.switch_to_block(block);
+
+            let exit_code_ptr = builder.block_params(block)[0];
+            let main_ref = self.module.declare_func_in_func(main.id, builder.func);
+            let call = builder.ins().call(main_ref, &[]);
+            let results = builder.inst_results(call).to_vec();
+
+            if let Some(&exit_code) = results.get(1) {
+                builder
+                    .ins()
+                    .store(MemFlags::trusted(), exit_code, exit_code_ptr, 0);
+            }
+
+            builder.ins().return_(&results[..1]);
+            builder.seal_all_blocks();
+            builder.finalize();
+        }
+
+        self.finish_definition(entry_id, codegen_context)?;
+
+        Ok(entry_id)
+    }
+
+    fn finish_definition(
+        &mut self,
+        id: FuncId,
+        codegen_context: &mut Context,
+    ) -> Result<(), RuntimeError> {
+        let result = self.module.define_function(id, codegen_context);
+        self.module.clear_context(codegen_context);
+
+        result.map_err(jit_error)
+    }
+
+    /// Registers the error for generated code to report, returning its status.
+    fn error_site(&mut self, error: RuntimeError) -> i64 {
+        self.error_sites.push(error);
+
+        self.error_sites.len() as i64
+    }
+}
+
+/// Function of the runtime library that the builtin calls, which generated
+/// code calls with the C calling convention.
+fn runtime_function(builtin: Builtin) -> *const u8 {
+    extern "C" fn print_i32(value: i32) {
+        print!("{}", Builtin::Print.format(value.into()));
+    }
+
+    extern "C" fn println_i32(value: i32) {
+        print!("{}", Builtin::Println.format(value.into()));
+    }
+
+    match builtin {
+        Builtin::Print => print_i32 as *const u8,
+        Builtin::Println => println_i32 as *const u8,
+    }
+}
+
+fn jit_error(error: impl ToString) -> RuntimeError {
+    RuntimeError::Jit {
+        message: error.to_string(),
+    }
+}
+
+/// Blocks that `continue` and `break` jump to in the innermost loop.
+#[derive(Clone, Copy)]
+struct LoopTargets {
+    continue_block: Block,
+    break_block: Block,
+}
+
+/// Value that's either a constant or kept in a variable, such as the step of a
+/// range.
+#[derive(Clone, Copy)]
+enum Operand {
+    Constant(i32),
+    Variable(Variable),
+}
+
+/// How the variable of an iterative for-loop moves on to its next value.
+#[derive(Clone, Copy)]
+enum Stepping {
+    Up {
+        variable: Variable,
+        step: Operand,
+    },
+    /// Counts down, but never past the start of the range.
+    Down {
+        variable: Variable,
+        step: Operand,
+        start: Operand,
+    },
+}
+
+struct FunctionLowering<'jit, 'ctx, 'func> {
+    jit: &'jit mut Jit<'ctx>,
+    builder: FunctionBuilder<'func>,
+    /// Each slot is assigned the variable of the same number, and temporaries
+    /// are numbered after them.
+    frame_layout: FrameLayout,
+    return_type: Type,
+    /// Bindings holding a value of type `()`, which has no representation, so
+    /// they're not assigned a variable.
+    unit_bindings: HashSet<Span>,
+    loop_stack: Vec<LoopTargets>,
+    func_refs: HashMap<Symbol, FuncRef>,
+    next_temp_variable: usize,
+}
+
+impl<'ctx> FunctionLowering<'_, 'ctx, '_> {
+    fn lower_function(mut self, function: Function<'ctx>) {
+        for slot in 0..self.frame_layout.slot_count() {
+            self.builder.declare_var(Variable::new(slot), types::I32);
+        }
+
+        let entry_block = self.builder.create_block();
+        self.builder
+            .append_block_params_for_function_params(entry_block);
+        self.builder.switch_to_block(entry_block);
+
+        let mut args = self.builder.block_params(entry_block).to_vec().into_iter();
+
+        for param in function.parameters {
+            match param.ty {
+                Type::I32 => {
+                    let variable = self.slot_variable(SlotOwner::Binding(param.span));
+                    self.builder.def_var(variable, args.next().unwrap());
+                }
+                Type::Unit => {
+                    self.unit_bindings.insert(param.span);
+                }
+                Type::I8
+                | Type::I16
+                | Type::I64
+                | Type::U8
+                | Type::U16
+                | Type::U32
+                | Type::U64
+                | Type::Usize
+                | Type::Named(_)
+                | Type::Tuple(_)
+                | Type::Array { .. }
+                | Type::Function { .. } => {
+                    unreachable!(
+                        "programs with structs, enums, tuples, arrays, function values or integers other than `i32` are rejected before code generation"
+                    )
+                }
+            }
+        }
+
+        let value = self.lower_compound_expr(function.body);
+        self.return_with_status(0, value);
+
+        self.builder.seal_all_blocks();
+        self.builder.finalize();
+    }
+
+    fn lower_expr(&mut self, expr: &Expr) -> Option<Value> {
+        match expr {
+            Expr::Const(const_expr) => match const_expr.value {
+                Const::IntegerConstant { value } => {
+                    Some(self.builder.ins().iconst(types::I32, i64::from(value)))
+                }
+                Const::SuffixedIntegerConstant { .. } => unreachable!(
+                    "programs with structs, enums, tuples, arrays, function values or integers other than `i32` are rejected before code generation"
+                ),
+            },
+            Expr::BindRef(bind_ref) => match self.jit.resolutions.get(bind_ref.span) {
+                Some(Resolution::Local(def_span)) if !self.unit_bindings.contains(&def_span) => {
+                    let variable = self.slot_variable(SlotOwner::Binding(def_span));
+
+                    Some(self.builder.use_var(variable))
+                }
+                // Anything else is a binding of type `()`, which has no value, as
+                // programs with function values are rejected before code generation.
+                _ => None,
+            },
+            Expr::BindDef(bind_def) => {
+                let value = self.lower_expr(bind_def.value);
+
+                if bind_def.identifier != self.jit.ctx.symbols().discard {
+                    match value {
+                        Some(value) => {
+                            let variable = self.slot_variable(SlotOwner::Binding(bind_def.span));
+                            self.builder.def_var(variable, value);
+                        }
+                        None => {
+                            self.unit_bindings.insert(bind_def.span);
+                        }
+                    }
+                }
+
+                None
+            }
+            Expr::Function(_) => unreachable!("closures are function values, which are rejected before code generation"),
+            Expr::Extern(_) => unreachable!("extern functions are only declared at the top level"),
+            Expr::Struct(_)
+            | Expr::StructLit(_)
+            | Expr::Field(_)
+            | Expr::Enum(_)
+            | Expr::Variant(_)
+            | Expr::Tuple(_)
+            | Expr::TupleField(_)
+            | Expr::TupleBindDef(_)
+            | Expr::Array(_)
+            | Expr::Index(_)
+            | Expr::IndexAssign(_) => {
+                unreachable!(
+                    "programs with structs, enums, tuples, arrays, function values or integers other than `i32` are rejected before code generation"
+                )
+            }
+            Expr::If(if_expr) => self.lower_if_expr(*if_expr),
+            Expr::Match(match_expr) => self.lower_match_expr(*match_expr),
+            Expr::For(for_expr) => {
+                self.lower_for_expr(*for_expr);
+
+                None
+            }
+            Expr::Break(_) => {
+                let break_block = self.loop_stack.last().unwrap().break_block;
+                self.jump_away(break_block);
+
+                None
+            }
+            Expr::Continue(_) => {
+                let continue_block = self.loop_stack.last().unwrap().continue_block;
+                self.jump_away(continue_block);
+
+                None
+            }
+            Expr::Compound(compound_expr) => self.lower_compound_expr(*compound_expr),
+            Expr::Semi(expr) => {
+                self.lower_expr(expr);
+
+                None
+            }
+            Expr::FnCall(fn_call_expr) => self.lower_fn_call_expr(*fn_call_expr),
+            Expr::Binary(binary_expr) => Some(self.lower_binary_expr(*binary_expr)),
+            Expr::Cast(cast_expr) => self.lower_expr(cast_expr.expr),
+            Expr::Paren(paren_expr) => self.lower_expr(paren_expr.expr),
+        }
+    }
+
+    fn lower_int_expr(&mut self, expr: &Expr) -> Value {
+        self.lower_expr(expr)
+            .expect("type checking guarantees an integer")
+    }
+
+    fn lower_if_expr(&mut self, if_expr: IfExpr) -> Option<Value> {
+        let merge_block = self.builder.create_block();
+        // Every branch that doesn't break or continue out of a loop has the same
+        // type, so either all of them define the result, or none of them do.
+        let result_variable = self.temp_variable();
+        let mut has_value = false;
+
+        let branches = [(if_expr.cond_expr, if_expr.true_branch)]
+            .into_iter()
+            .chain(
+                if_expr
+                    .else_if_branches
+                    .iter()
+                    .map(|branch| (branch.cond_expr, branch.true_branch)),
+            );
+
+        for (cond_expr, true_branch) in branches {
+            let cond = self.lower_int_expr(cond_expr);
+            let true_block = self.builder.create_block();
+            let next_block = self.builder.create_block();
+
+            self.builder
+                .ins()
+                .brif(cond, true_block, &[], next_block, &[]);
+
+            self.builder.switch_to_block(true_block);
+
+            if let Some(value) = self.lower_compound_expr(true_branch) {
+                self.builder.def_var(result_variable, value);
+                has_value = true;
+            }
+
+            self.builder.ins().jump(merge_block, &[]);
+            self.builder.switch_to_block(next_block);
+        }
+
+        if let Some(final_branch) = if_expr.final_branch {
+            if let Some(value) = self.lower_compound_expr(final_branch) {
+                self.builder.def_var(result_variable, value);
+                has_value = true;
+            }
+        }
+
+        self.builder.ins().jump(merge_block, &[]);
+        self.builder.switch_to_block(merge_block);
+
+        // Without a final branch, the if-expression is of type `()`.
+        (has_value && if_expr.final_branch.is_some()).then(|| self.builder.use_var(result_variable))
+    }
+
+    /// Lowers the arms like the branches of an if-expression, whose conditions
+    /// test the scrutinee against the bounds of the arms' patterns. Match-
+    /// expressions are exhaustive, so the last arm tried isn't tested.
+    fn lower_match_expr(&mut self, match_expr: MatchExpr) -> Option<Value> {
+        let scrutinee = self.lower_int_expr(match_expr.scrutinee);
+        let merge_block = self.builder.create_block();
+        // Every arm has the same type, so either all of them define the result,
+        // or none of them do.
+        let result_variable = self.temp_variable();
+        let mut has_value = false;
+
+        let arms = match_expr.tried_arms();
+
+        for (idx, arm) in arms.iter().enumerate() {
+            let next_block = match arm.pattern.kind.bounds().filter(|_| idx + 1 < arms.len()) {
+                Some((start, end)) => {
+                    let arm_block = self.builder.create_block();
+                    let next_block = self.builder.create_block();
+
+                    // Type checking guarantees that the bounds are integers.
+                    let is_above_start = self.builder.ins().icmp_imm(
+                        IntCC::SignedGreaterThanOrEqual,
+                        scrutinee,
+                        start as i64,
+                    );
+                    let is_below_end = self.builder.ins().icmp_imm(
+                        IntCC::SignedLessThanOrEqual,
+                        scrutinee,
+                        end as i64,
+                    );
+                    let is_in_range = self.builder.ins().band(is_above_start, is_below_end);
+
+                    self.builder
+                        .ins()
+                        .brif(is_in_range, arm_block, &[], next_block, &[]);
+
+                    self.builder.switch_to_block(arm_block);
+
+                    Some(next_block)
+                }
+                None => None,
+            };
+
+            if let Some(value) = self.lower_expr(arm.expr) {
+                self.builder.def_var(result_variable, value);
+                has_value = true;
+            }
+
+            self.builder.ins().jump(merge_block, &[]);
+
+            if let Some(next_block) = next_block {
+                self.builder.switch_to_block(next_block);
+            }
+        }
+
+        self.builder.switch_to_block(merge_block);
+
+        has_value.then(|| self.builder.use_var(result_variable))
+    }
+
+    fn lower_for_expr(&mut self, for_expr: ForExpr) {
+        let header_block = self.builder.create_block();
+        let body_block = self.builder.create_block();
+        let step_block = self.builder.create_block();
+        let completion_block = self.builder.create_block();
+        let break_block = self.builder.create_block();
+
+        let stepping = match for_expr.iteration {
+            Some(ForIteration::Iterative {
+                identifier_span,
+                start_expr,
+                end_expr,
+                range_kind,
+                step_expr,
+                is_reversed: false,
+                ..
+            }) => {
+                let start = self.lower_int_expr(start_expr);
+                let loop_variable = self.slot_variable(SlotOwner::Binding(identifier_span));
+                self.builder.def_var(loop_variable, start);
+
+                // The range's end is evaluated only once, before the loop starts.
+                let end_variable = match constant_value(end_expr) {
+                    Some(_) => None,
+                    None => {
+                        let end = self.lower_int_expr(end_expr);
+                        let end_variable = self.slot_variable(SlotOwner::RangeEnd(end_expr.span()));
+                        self.builder.def_var(end_variable, end);
+
+                        Some(end_variable)
+                    }
+                };
+
+                // So is the step.
+                let step = self.lower_range_step(step_expr);
+
+                self.builder.ins().jump(header_block, &[]);
+                self.builder.switch_to_block(header_block);
+
+                let current = self.builder.use_var(loop_variable);
+                let end = match end_variable {
+                    Some(end_variable) => self.builder.use_var(end_variable),
+                    None => self.lower_int_expr(end_expr),
+                };
+                let cond = match range_kind {
+                    RangeKind::Inclusive => IntCC::SignedLessThanOrEqual,
+                    RangeKind::Exclusive => IntCC::SignedLessThan,
+                };
+                let is_in_range = self.builder.ins().icmp(cond, current, end);
+
+                self.builder
+                    .ins()
+                    .brif(is_in_range, body_block, &[], completion_block, &[]);
+
+                Some(Stepping::Up {
+                    variable: loop_variable,
+                    step,
+                })
+            }
+            Some(ForIteration::Iterative {
+                is_reversed: true, ..
+            }) => Some(self.lower_reversed_range_start(
+                for_expr,
+                header_block,
+                body_block,
+                completion_block,
+            )),
+            Some(ForIteration::Conditional { cond_expr }) => {
+                self.builder.ins().jump(header_block, &[]);
+                self.builder.switch_to_block(header_block);
+
+                let cond = self.lower_int_expr(cond_expr);
+
+                self.builder
+                    .ins()
+                    .brif(cond, body_block, &[], completion_block, &[]);
+
+                None
+            }
+            Some(ForIteration::InCollection { .. }) => {
+                unreachable!(
+                    "programs with structs, enums, tuples, arrays, function values or integers other than `i32` are rejected before code generation"
+                )
+            }
+            None => {
+                self.builder.ins().jump(header_block, &[]);
+                self.builder.switch_to_block(header_block);
+                self.builder.ins().jump(body_block, &[]);
+
+                None
+            }
+        };
+
+        self.builder.switch_to_block(body_block);
+
+        self.loop_stack.push(LoopTargets {
+            continue_block: step_block,
+            break_block,
+        });
+        self.lower_compound_expr(for_expr.body);
+        self.loop_stack.pop();
+
+        self.builder.ins().jump(step_block, &[]);
+        self.builder.switch_to_block(step_block);
+
+        // Stepping past the largest or smallest value of `i32` runs the loop
+        // to completion rather than wrapping around, and so does counting down
+        // past the start.
+        match stepping {
+            Some(Stepping::Up { variable, step }) => {
+                let next_block = self.builder.create_block();
+
+                let current = self.builder.use_var(variable);
+                let step = self.use_operand(step);
+                let (next, overflows) = self.builder.ins().sadd_overflow(current, step);
+
+                self.builder
+                    .ins()
+                    .brif(overflows, completion_block, &[], next_block, &[]);
+                self.builder.switch_to_block(next_block);
+                self.builder.def_var(variable, next);
+            }
+            Some(Stepping::Down {
+                variable,
+                step,
+                start,
+            }) => {
+                let next_block = self.builder.create_block();
+
+                let current = self.builder.use_var(variable);
+                let start = self.use_operand(start);
+                let step = self.use_operand(step);
+                let (next, overflows) = self.builder.ins().ssub_overflow(current, step);
+                let is_past_start = self.builder.ins().icmp(IntCC::SignedLessThan, next, start);
+                let is_done = self.builder.ins().bor(overflows, is_past_start);
+
+                self.builder
+                    .ins()
+                    .brif(is_done, completion_block, &[], next_block, &[]);
+                self.builder.switch_to_block(next_block);
+                self.builder.def_var(variable, next);
+            }
+            None => {}
+        }
+
+        self.builder.ins().jump(header_block, &[]);
+
+        // The else-branch is lowered outside of the loop, so that a `break` or
+        // `continue` in it refers to an enclosing loop instead.
+        self.builder.switch_to_block(completion_block);
+
+        if let Some(else_branch) = for_expr.else_branch {
+            self.lower_compound_expr(else_branch);
+        }
+
+        self.builder.ins().jump(break_block, &[]);
+        self.builder.switch_to_block(break_block);
+    }
+
+    /// Starts the variable of a reversed for-loop at the last value of the
+    /// range if the range isn't empty, or otherwise goes to the completion
+    /// block. The start is evaluated before the end, as it's written first, and
+    /// is kept for checking whether there's a next value.
+    fn lower_reversed_range_start(
+        &mut self,
+        for_expr: ForExpr,
+        header_block: Block,
+        body_block: Block,
+        completion_block: Block,
+    ) -> Stepping {
+        let Some(ForIteration::Iterative {
+            identifier_span,
+            start_expr,
+            end_expr,
+            range_kind,
+            step_expr,
+            ..
+        }) = for_expr.iteration
+        else {
+            unreachable!("only ranges are reversed");
+        };
+
+        let start = match constant_value(start_expr) {
+            Some(value) => Operand::Constant(value),
+            None => {
+                let start = self.lower_int_expr(start_expr);
+                let start_variable = self.slot_variable(SlotOwner::RangeStart(start_expr.span()));
+                self.builder.def_var(start_variable, start);
+
+                Operand::Variable(start_variable)
+            }
+        };
+
+        let end = self.lower_int_expr(end_expr);
+        let loop_variable = self.slot_variable(SlotOwner::Binding(identifier_span));
+        let step = self.lower_range_step(step_expr);
+
+        let first_block = self.builder.create_block();
+        let start_value = self.use_operand(start);
+        let cond = match range_kind {
+            RangeKind::Inclusive => IntCC::SignedLessThanOrEqual,
+            RangeKind::Exclusive => IntCC::SignedLessThan,
+        };
+        let is_in_range = self.builder.ins().icmp(cond, start_value, end);
+
+        self.builder
+            .ins()
+            .brif(is_in_range, first_block, &[], completion_block, &[]);
+        self.builder.switch_to_block(first_block);
+
+        // The end isn't the smallest value of its type if the range isn't
+        // empty, so the last value of an exclusive range doesn't wrap around.
+        let last = match range_kind {
+            RangeKind::Inclusive => end,
+            RangeKind::Exclusive => self.builder.ins().iadd_imm(end, -1),
+        };
+        self.builder.def_var(loop_variable, last);
+
+        self.builder.ins().jump(header_block, &[]);
+        self.builder.switch_to_block(header_block);
+        self.builder.ins().jump(body_block, &[]);
+
+        Stepping::Down {
+            variable: loop_variable,
+            step,
+            start,
+        }
+    }
+
+    /// Evaluates the step of a range once, before the loop starts, which is one
+    /// if there's none. Steps that aren't constant fail unless they're
+    /// positive, whereas constant ones always are.
+    fn lower_range_step(&mut self, step_expr: Option<&Expr>) -> Operand {
+        let Some(step_expr) = step_expr else {
+            return Operand::Constant(1);
+        };
+
+        match constant_value(step_expr) {
+            Some(value) => Operand::Constant(value),
+            None => {
+                let step = self.lower_int_expr(step_expr);
+                let isnt_positive =
+                    self.builder
+                        .ins()
+                        .icmp_imm(IntCC::SignedLessThanOrEqual, step, 0);
+                self.fail_if(
+                    isnt_positive,
+                    RuntimeError::NonPositiveStep {
+                        span: step_expr.span(),
+                    },
+                );
+
+                let step_variable = self.slot_variable(SlotOwner::RangeStep(step_expr.span()));
+                self.builder.def_var(step_variable, step);
+
+                Operand::Variable(step_variable)
+            }
+        }
+    }
+
+    fn use_operand(&mut self, operand: Operand) -> Value {
+        match operand {
+            Operand::Constant(value) => self.builder.ins().iconst(types::I32, i64::from(value)),
+            Operand::Variable(variable) => self.builder.use_var(variable),
+        }
+    }
+
+    fn lower_compound_expr(&mut self, compound_expr: CompoundExpr) -> Option<Value> {
+        let mut value = None;
+
+        for expr in compound_expr.exprs {
+            value = self.lower_expr(expr);
+        }
+
+        value
+    }
+
+    fn lower_fn_call_expr(&mut self, fn_call_expr: FnCallExpr) -> Option<Value> {
+        let mut args = vec![];
+
+        for arg in fn_call_expr.args {
+            args.extend(self.lower_expr(arg));
+        }
+
+        // Functions of the runtime library can't fail, so they return no
+        // status.
+        if let Some(Resolution::Builtin(builtin)) = self.jit.resolutions.get(fn_call_expr.span) {
+            let id = self.jit.builtins[&builtin];
+            let func_ref = self.jit.module.declare_func_in_func(id, self.builder.func);
+            self.builder.ins().call(func_ref, &args);
+
+            return None;
+        }
+
+        // So can't extern functions.
+        if let Some(&(id, extern_fn)) = self.jit.extern_fns.get(&fn_call_expr.identifier) {
+            let func_ref = self.jit.module.declare_func_in_func(id, self.builder.func);
+            let call = self.builder.ins().call(func_ref, &args);
+
+            return (extern_fn.return_type != Type::Unit)
+                .then(|| self.builder.inst_results(call)[0]);
+        }
+
+        let func_ref = self.func_ref(fn_call_expr.identifier);
+        let call = self.builder.ins().call(func_ref, &args);
+        let results = self.builder.inst_results(call).to_vec();
+
+        // The callee's error is the caller's error too.
+        let status = results[0];
+        let error_block = self.builder.create_block();
+        let ok_block = self.builder.create_block();
+
+        self.builder
+            .ins()
+            .brif(status, error_block, &[], ok_block, &[]);
+
+        self.builder.switch_to_block(error_block);
+        self.return_status(status);
+
+        self.builder.switch_to_block(ok_block);
+
+        results.get(1).copied()
+    }
+
+    fn lower_binary_expr(&mut self, binary_expr: BinaryExpr) -> Value {
+        let (operand, operations) = binary_expr.chain();
+        let mut lhs = self.lower_int_expr(operand);
+
+        for binary_expr in operations {
+            let rhs = self.lower_int_expr(binary_expr.rhs);
+            lhs = self.lower_operation(binary_expr, lhs, rhs);
+        }
+
+        lhs
+    }
+
+    fn lower_operation(&mut self, binary_expr: BinaryExpr, lhs: Value, rhs: Value) -> Value {
+        // Addition, subtraction and multiplication wrap around just like the native
+        // code does, whereas Cranelift's division traps on the cases below, so they
+        // are checked for beforehand.
+        match binary_expr.op {
+            BinaryOp::Add => self.builder.ins().iadd(lhs, rhs),
+            BinaryOp::Sub => self.builder.ins().isub(lhs, rhs),
+            BinaryOp::Mul => self.builder.ins().imul(lhs, rhs),
+            BinaryOp::Div | BinaryOp::Rem => {
+                let is_zero = self.builder.ins().icmp_imm(IntCC::Equal, rhs, 0);
+                self.fail_if(
+                    is_zero,
+                    RuntimeError::DivisionByZero {
+                        span: binary_expr.span,
+                    },
+                );
+
+                let is_min = self
+                    .builder
+                    .ins()
+                    .icmp_imm(IntCC::Equal, lhs, i64::from(i32::MIN));
+                let is_minus_one = self.builder.ins().icmp_imm(IntCC::Equal, rhs, -1);
+                let overflows = self.builder.ins().band(is_min, is_minus_one);
+                self.fail_if(
+                    overflows,
+                    RuntimeError::Overflow {
+                        op: binary_expr.op,
+                        span: binary_expr.span,
+                    },
+                );
+
+                if binary_expr.op == BinaryOp::Div {
+                    self.builder.ins().sdiv(lhs, rhs)
+                } else {
+                    self.builder.ins().srem(lhs, rhs)
+                }
+            }
+        }
+    }
+
+    /// Returns from the function with the error's status if `cond` holds.
+    fn fail_if(&mut self, cond: Value, error: RuntimeError) {
+        let status = self.jit.error_site(error);
+        let error_block = self.builder.create_block();
+        let ok_block = self.builder.create_block();
+
+        self.builder
+            .ins()
+            .brif(cond, error_block, &[], ok_block, &[]);
+
+        self.builder.switch_to_block(error_block);
+        self.return_with_status(status, None);
+
+        self.builder.switch_to_block(ok_block);
+    }
+
+    fn return_with_status(&mut self, status: i64, value: Option<Value>) {
+        let status = self.builder.ins().iconst(types::I32, status);
+
+        match value {
+            Some(value) => {
+                self.builder.ins().return_(&[status, value]);
+            }
+            None => self.return_status(status),
+        }
+    }
+
+    /// Returns the status along with a placeholder value, if the function has
+    /// to return one.
+    fn return_status(&mut self, status: Value) {
+        match self.return_type {
+            Type::I32 => {
+                let placeholder = self.builder.ins().iconst(types::I32, 0);
+                self.builder.ins().return_(&[status, placeholder]);
+            }
+            Type::Unit => {
+                self.builder.ins().return_(&[status]);
+            }
+            Type::I8
+            | Type::I16
+            | Type::I64
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::Usize
+            | Type::Named(_)
+            | Type::Tuple(_)
+            | Type::Array { .. }
+            | Type::Function { .. } => {
+                unreachable!(
+                    "programs with structs, enums, tuples, arrays, function values or integers other than `i32` are rejected before code generation"
+                )
+            }
+        }
+    }
+
+    /// Jumps to `block`, then carries on in a new block, which is unreachable,
+    /// for whatever code follows the jump.
+    fn jump_away(&mut self, block: Block) {
+        self.builder.ins().jump(block, &[]);
+
+        let unreachable_block = self.builder.create_block();
+        self.builder.switch_to_block(unreachable_block);
+    }
+
+    fn func_ref(&mut self, identifier: Symbol) -> FuncRef {
+        if let Some(&func_ref) = self.func_refs.get(&identifier) {
+            return func_ref;
+        }
+
+        let id = self.jit.functions[&identifier].id;
+        let func_ref = self.jit.module.declare_func_in_func(id, self.builder.func);
+        self.func_refs.insert(identifier, func_ref);
+
+        func_ref
+    }
+
+    fn slot_variable(&self, owner: SlotOwner) -> Variable {
+        Variable::new(self.frame_layout.slot(owner))
+    }
+
+    fn temp_variable(&mut self) -> Variable {
+        let variable = Variable::new(self.frame_layout.slot_count() + self.next_temp_variable);
+        self.next_temp_variable += 1;
+        self.builder.declare_var(variable, types::I32);
+
+        variable
+    }
+}
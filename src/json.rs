@@ -0,0 +1,407 @@
+use std::fmt;
+
+/// A JSON value, just enough of it to talk to other tools.
+///
+/// Numbers are restricted to integers, as nothing the compiler exchanges needs
+/// fractions.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    /// Members are kept in insertion order, so that output is deterministic.
+    Object(Vec<(String, Json)>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct JsonError {
+    pub(crate) message: String,
+    pub(crate) offset: usize,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl Json {
+    pub(crate) fn parse(text: &str) -> Result<Json, JsonError> {
+        let mut parser = JsonParser { text, pos: 0 };
+
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+
+        if parser.pos != text.len() {
+            return Err(parser.error("trailing characters"));
+        }
+
+        Ok(value)
+    }
+
+    pub(crate) fn object<K: Into<String>>(members: impl IntoIterator<Item = (K, Json)>) -> Json {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.into(), value))
+                .collect(),
+        )
+    }
+
+    /// Value of the object's member named `key`, if this is an object and
+    /// there's such a member.
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(member_key, _)| member_key == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_i64(&self) -> Option<i64> {
+        match self {
+            Json::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(string: &str) -> Json {
+        Json::String(string.to_owned())
+    }
+}
+
+impl From<String> for Json {
+    fn from(string: String) -> Json {
+        Json::String(string)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Json {
+        Json::Bool(value)
+    }
+}
+
+impl From<i64> for Json {
+    fn from(value: i64) -> Json {
+        Json::Number(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Json {
+        // FIXME: Should not cast usize to i64.
+        Json::Number(value as i64)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(values: Vec<T>) -> Json {
+        Json::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Json {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(string) => write_json_string(f, string),
+            Json::Array(values) => {
+                write!(f, "[")?;
+
+                for (idx, value) in values.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write!(f, "{}", value)?;
+                }
+
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+
+                for (idx, (key, value)) in members.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write_json_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_json_string(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+    write!(f, "\"")?;
+
+    for ch in string.chars() {
+        match ch {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            ch if ch.is_control() => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => write!(f, "{}", ch)?,
+        }
+    }
+
+    write!(f, "\"")
+}
+
+struct JsonParser<'text> {
+    text: &'text str,
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn parse_value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('n') => self.parse_keyword("null", Json::Null),
+            Some('t') => self.parse_keyword("true", Json::Bool(true)),
+            Some('f') => self.parse_keyword("false", Json::Bool(false)),
+            Some('"') => self.parse_string().map(Json::String),
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_object(),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn parse_keyword(&mut self, keyword: &str, value: Json) -> Result<Json, JsonError> {
+        if self.text[self.pos..].starts_with(keyword) {
+            self.pos += keyword.len();
+
+            Ok(value)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Json, JsonError> {
+        let start = self.pos;
+
+        if self.peek() == Some('-') {
+            self.bump();
+        }
+
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.bump();
+        }
+
+        if matches!(self.peek(), Some('.' | 'e' | 'E')) {
+            return Err(self.error("only integer numbers are supported"));
+        }
+
+        self.text[start..self.pos]
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| JsonError {
+                message: "invalid number".to_owned(),
+                offset: start,
+            })
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+
+        let mut string = String::new();
+
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.bump() {
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    Some('/') => string.push('/'),
+                    Some('b') => string.push('\u{8}'),
+                    Some('f') => string.push('\u{c}'),
+                    Some('n') => string.push('\n'),
+                    Some('r') => string.push('\r'),
+                    Some('t') => string.push('\t'),
+                    Some('u') => string.push(self.parse_unicode_escape()?),
+                    _ => return Err(self.error("invalid escape sequence")),
+                },
+                Some(ch) => string.push(ch),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn parse_unicode_escape(&mut self) -> Result<char, JsonError> {
+        let code_unit = self.parse_hex_code_unit()?;
+
+        // Characters outside of the basic multilingual plane are escaped as a
+        // surrogate pair.
+        let code_point = if (0xd800..0xdc00).contains(&code_unit) {
+            if !self.text[self.pos..].starts_with("\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+
+            self.pos += 2;
+
+            let low_code_unit = self.parse_hex_code_unit()?;
+
+            if !(0xdc00..0xe000).contains(&low_code_unit) {
+                return Err(self.error("unpaired surrogate"));
+            }
+
+            0x10000 + ((code_unit - 0xd800) << 10) + (low_code_unit - 0xdc00)
+        } else {
+            code_unit
+        };
+
+        char::from_u32(code_point).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn parse_hex_code_unit(&mut self) -> Result<u32, JsonError> {
+        let hex_digits = self
+            .text
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+
+        let code_unit = u32::from_str_radix(hex_digits, 16)
+            .map_err(|_| self.error("invalid unicode escape"))?;
+
+        self.pos += 4;
+
+        Ok(code_unit)
+    }
+
+    fn parse_array(&mut self) -> Result<Json, JsonError> {
+        self.expect('[')?;
+
+        let mut values = vec![];
+
+        self.skip_whitespace();
+
+        if self.peek() == Some(']') {
+            self.bump();
+
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.parse_value()?);
+
+            self.skip_whitespace();
+
+            match self.bump() {
+                Some(',') => {}
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, JsonError> {
+        self.expect('{')?;
+
+        let mut members = vec![];
+
+        self.skip_whitespace();
+
+        if self.peek() == Some('}') {
+            self.bump();
+
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+
+            self.skip_whitespace();
+            self.expect(':')?;
+
+            let value = self.parse_value()?;
+            members.push((key, value));
+
+            self.skip_whitespace();
+
+            match self.bump() {
+                Some(',') => {}
+                Some('}') => return Ok(Json::Object(members)),
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        if self.peek() == Some(expected) {
+            self.bump();
+
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", expected)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.bump();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+
+        Some(ch)
+    }
+
+    fn error(&self, message: &str) -> JsonError {
+        JsonError {
+            message: message.to_owned(),
+            offset: self.pos,
+        }
+    }
+}
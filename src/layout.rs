@@ -0,0 +1,379 @@
+use std::collections::HashSet;
+
+use crate::ast::{EnumDef, Expr, Program, StructDef, Type};
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::CompileError;
+use crate::host::HashMap;
+use crate::profile::IntWidth;
+use crate::scanner::Span;
+
+/// Where the values of a struct keep their fields, like C compilers lay out
+/// their structs: fields are in the order they're declared, each at the first
+/// offset after the previous one that's a multiple of its alignment.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct StructLayout {
+    pub(crate) name: &'static str,
+    pub(crate) fields: Vec<FieldLayout>,
+    // The backends don't lay out values in memory yet, as they don't support
+    // structs, so nothing reads the sizes nor the offsets but tests.
+    #[allow(dead_code)]
+    pub(crate) size: usize,
+    #[allow(dead_code)]
+    pub(crate) align: usize,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct FieldLayout {
+    pub(crate) name: &'static str,
+    pub(crate) ty: Type,
+    /// Offset from the start of the struct, in bytes.
+    #[allow(dead_code)]
+    pub(crate) offset: usize,
+}
+
+impl StructLayout {
+    pub(crate) fn field(&self, name: &str) -> Option<&FieldLayout> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+/// Where the values of an enum keep their tag, which tells what variant they
+/// are, and the value their variant holds: the tag comes first, as the
+/// smallest unsigned integer that tells the variants apart, and the variants'
+/// values all start at the same offset after it. Enums of one variant or less
+/// need no tag.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct EnumLayout {
+    pub(crate) name: &'static str,
+    pub(crate) variants: Vec<VariantLayout>,
+    // The backends don't lay out values in memory yet, as they don't support
+    // enums, so nothing reads the sizes nor the offsets but tests.
+    #[allow(dead_code)]
+    pub(crate) tag_size: usize,
+    #[allow(dead_code)]
+    pub(crate) payload_offset: usize,
+    #[allow(dead_code)]
+    pub(crate) size: usize,
+    #[allow(dead_code)]
+    pub(crate) align: usize,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct VariantLayout {
+    pub(crate) name: &'static str,
+    pub(crate) payload: Option<Type>,
+    /// Value of the tag of the variant's values, which is its index in the
+    /// enum's declaration.
+    #[allow(dead_code)]
+    pub(crate) tag: u32,
+}
+
+impl EnumLayout {
+    pub(crate) fn variant(&self, name: &str) -> Option<&VariantLayout> {
+        self.variants.iter().find(|variant| variant.name == name)
+    }
+}
+
+/// Layouts of the structs and enums that a program declares.
+#[derive(Default)]
+pub(crate) struct Layouts {
+    struct_layout_by_decl: HashMap<usize, StructLayout>,
+    enum_layout_by_decl: HashMap<usize, EnumLayout>,
+    decl_idx_by_name: HashMap<&'static str, usize>,
+}
+
+impl Layouts {
+    /// Lays out the program's structs and enums, whose fields and variants
+    /// are expected to have had their types resolved already.
+    ///
+    /// Fails if a struct declares a field more than once, if an enum declares
+    /// a variant more than once, or if either contains itself, even through
+    /// other types, as its values would be infinitely large.
+    pub(crate) fn of_program(
+        ctx: &CompilerContext,
+        program: Program,
+        int_width: IntWidth,
+    ) -> Result<Layouts, Vec<CompileError>> {
+        let mut builder = LayoutBuilder {
+            int_width,
+            type_defs: Default::default(),
+            layouts: Default::default(),
+            in_progress: vec![],
+            failed: Default::default(),
+            errors: vec![],
+        };
+
+        for (decl_idx, decl) in program.decls.iter().enumerate() {
+            match decl.value {
+                Expr::Struct(struct_def) => builder.check_fields_are_unique(ctx, *struct_def),
+                Expr::Enum(enum_def) => builder.check_variants_are_unique(ctx, *enum_def),
+                _ => continue,
+            }
+
+            builder.type_defs.insert(
+                ctx.resolve_symbol(decl.identifier),
+                (decl_idx, decl.identifier_span, *decl.value),
+            );
+        }
+
+        for decl in program.decls {
+            if let Expr::Struct(_) | Expr::Enum(_) = decl.value {
+                builder.lay_out(ctx, ctx.resolve_symbol(decl.identifier));
+            }
+        }
+
+        if builder.errors.is_empty() {
+            Ok(builder.layouts)
+        } else {
+            Err(builder.errors)
+        }
+    }
+
+    pub(crate) fn get_struct(&self, name: &str) -> Option<&StructLayout> {
+        let decl_idx = self.decl_idx_by_name.get(name)?;
+
+        self.struct_layout_by_decl.get(decl_idx)
+    }
+
+    /// Layout of the struct declared at this index of `Program::decls`, or
+    /// `None` if the declaration isn't of a struct.
+    pub(crate) fn struct_of_decl(&self, decl_idx: usize) -> Option<&StructLayout> {
+        self.struct_layout_by_decl.get(&decl_idx)
+    }
+
+    pub(crate) fn get_enum(&self, name: &str) -> Option<&EnumLayout> {
+        let decl_idx = self.decl_idx_by_name.get(name)?;
+
+        self.enum_layout_by_decl.get(decl_idx)
+    }
+
+    /// Layout of the enum declared at this index of `Program::decls`, or
+    /// `None` if the declaration isn't of an enum.
+    pub(crate) fn enum_of_decl(&self, decl_idx: usize) -> Option<&EnumLayout> {
+        self.enum_layout_by_decl.get(&decl_idx)
+    }
+}
+
+struct LayoutBuilder<'ctx> {
+    int_width: IntWidth,
+    /// Declarations of the structs and enums by name, along with their
+    /// indices in `Program::decls` and the spans of their names.
+    type_defs: HashMap<&'static str, (usize, Span, Expr<'ctx>)>,
+    layouts: Layouts,
+    /// Types being laid out, each containing the ones after it, so a type
+    /// that's laid out again while it's here contains itself.
+    in_progress: Vec<&'static str>,
+    /// Types that couldn't be laid out, which have been reported already.
+    failed: HashSet<&'static str>,
+    errors: Vec<CompileError>,
+}
+
+impl LayoutBuilder<'_> {
+    fn check_variants_are_unique(&mut self, ctx: &CompilerContext, enum_def: EnumDef) {
+        for (variant_idx, variant) in enum_def.variants.iter().enumerate() {
+            let is_duplicate = enum_def.variants[..variant_idx]
+                .iter()
+                .any(|prev_variant| prev_variant.identifier == variant.identifier);
+
+            if is_duplicate {
+                self.errors.push(CompileError::DuplicateVariant {
+                    variant: ctx.resolve_symbol(variant.identifier).to_owned(),
+                    span: variant.span,
+                });
+            }
+        }
+    }
+
+    fn check_fields_are_unique(&mut self, ctx: &CompilerContext, struct_def: StructDef) {
+        for (field_idx, field) in struct_def.fields.iter().enumerate() {
+            let is_duplicate = struct_def.fields[..field_idx]
+                .iter()
+                .any(|prev_field| prev_field.identifier == field.identifier);
+
+            if is_duplicate {
+                self.errors.push(CompileError::DuplicateField {
+                    field: ctx.resolve_symbol(field.identifier).to_owned(),
+                    span: field.span,
+                });
+            }
+        }
+    }
+
+    /// Lays out the struct or enum, if it wasn't already, returning its size
+    /// and alignment, or `None` if it can't be laid out.
+    fn lay_out(&mut self, ctx: &CompilerContext, name: &'static str) -> Option<(usize, usize)> {
+        if let Some(layout) = self.layouts.get_struct(name) {
+            return Some((layout.size, layout.align));
+        }
+
+        if let Some(layout) = self.layouts.get_enum(name) {
+            return Some((layout.size, layout.align));
+        }
+
+        let (decl_idx, identifier_span, type_def) = self.type_defs[name];
+
+        if self.failed.contains(name) {
+            return None;
+        }
+
+        if self.in_progress.contains(&name) {
+            self.failed.insert(name);
+            self.errors.push(CompileError::RecursiveType {
+                name: name.to_owned(),
+                span: identifier_span,
+            });
+
+            return None;
+        }
+
+        self.in_progress.push(name);
+
+        let size_and_align = match type_def {
+            Expr::Struct(struct_def) => self.lay_out_struct(ctx, name, decl_idx, struct_def),
+            Expr::Enum(enum_def) => self.lay_out_enum(ctx, name, decl_idx, enum_def),
+            _ => unreachable!("only structs and enums are laid out"),
+        };
+
+        self.in_progress.pop();
+
+        if size_and_align.is_none() {
+            self.failed.insert(name);
+        }
+
+        size_and_align
+    }
+
+    fn lay_out_struct(
+        &mut self,
+        ctx: &CompilerContext,
+        name: &'static str,
+        decl_idx: usize,
+        struct_def: StructDef,
+    ) -> Option<(usize, usize)> {
+        let mut fields = vec![];
+        let mut offset: usize = 0;
+        let mut struct_align = 1;
+
+        for field in struct_def.fields {
+            let (size, align) = self.size_and_align(ctx, field.ty)?;
+
+            offset = offset.next_multiple_of(align);
+            fields.push(FieldLayout {
+                name: ctx.resolve_symbol(field.identifier),
+                ty: field.ty,
+                offset,
+            });
+
+            offset += size;
+            struct_align = struct_align.max(align);
+        }
+
+        let size = offset.next_multiple_of(struct_align);
+
+        self.layouts.decl_idx_by_name.insert(name, decl_idx);
+        self.layouts.struct_layout_by_decl.insert(
+            decl_idx,
+            StructLayout {
+                name,
+                fields,
+                size,
+                align: struct_align,
+            },
+        );
+
+        Some((size, struct_align))
+    }
+
+    fn lay_out_enum(
+        &mut self,
+        ctx: &CompilerContext,
+        name: &'static str,
+        decl_idx: usize,
+        enum_def: EnumDef,
+    ) -> Option<(usize, usize)> {
+        let tag_size: usize = match enum_def.variants.len() {
+            0..=1 => 0,
+            2..=0x100 => 1,
+            0x101..=0x1_0000 => 2,
+            _ => 4,
+        };
+
+        let mut variants = vec![];
+        let mut payload_size = 0;
+        let mut payload_align = 1;
+
+        for (tag, variant) in (0..).zip(enum_def.variants) {
+            if let Some(payload) = variant.payload {
+                let (size, align) = self.size_and_align(ctx, payload)?;
+
+                payload_size = payload_size.max(size);
+                payload_align = payload_align.max(align);
+            }
+
+            variants.push(VariantLayout {
+                name: ctx.resolve_symbol(variant.identifier),
+                payload: variant.payload,
+                tag,
+            });
+        }
+
+        let payload_offset = tag_size.next_multiple_of(payload_align);
+        let enum_align = payload_align.max(tag_size).max(1);
+        let size = (payload_offset + payload_size).next_multiple_of(enum_align);
+
+        self.layouts.decl_idx_by_name.insert(name, decl_idx);
+        self.layouts.enum_layout_by_decl.insert(
+            decl_idx,
+            EnumLayout {
+                name,
+                variants,
+                tag_size,
+                payload_offset,
+                size,
+                align: enum_align,
+            },
+        );
+
+        Some((size, enum_align))
+    }
+
+    fn size_and_align(&mut self, ctx: &CompilerContext, ty: Type) -> Option<(usize, usize)> {
+        match ty {
+            Type::Unit => Some((0, 1)),
+            Type::I32 => {
+                let bytes = self.int_width.bits() as usize / 8;
+
+                Some((bytes, bytes))
+            }
+            Type::I8 | Type::U8 => Some((1, 1)),
+            Type::I16 | Type::U16 => Some((2, 2)),
+            Type::U32 => Some((4, 4)),
+            Type::I64 | Type::U64 | Type::Usize => Some((8, 8)),
+            Type::Named(type_name) => self.lay_out(ctx, type_name),
+            // Function values are addresses of code.
+            Type::Function { .. } => Some((8, 8)),
+            // Tuples are laid out like structs whose fields are their
+            // elements.
+            Type::Tuple(element_types) => {
+                let mut offset: usize = 0;
+                let mut tuple_align = 1;
+
+                for &element_ty in element_types {
+                    let (size, align) = self.size_and_align(ctx, element_ty)?;
+
+                    offset = offset.next_multiple_of(align) + size;
+                    tuple_align = tuple_align.max(align);
+                }
+
+                Some((offset.next_multiple_of(tuple_align), tuple_align))
+            }
+            // Elements follow each other, as their size is a multiple of
+            // their alignment already.
+            Type::Array { element, len } => {
+                let (size, align) = self.size_and_align(ctx, *element)?;
+
+                Some((size.saturating_mul(len), align))
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+#![feature(hash_raw_entry, hasher_prefixfree_extras)]
+
+//! Compiler of the Sophia programming language.
+//!
+//! Programs that embed the compiler, e.g., editors, build tools and tests,
+//! drive it through a [`Session`], or through [`compile_str`] for a program
+//! of a single file, and read programs they parse through [`ast_owned`].
+//! Tools that rewrite programs check that they're still the same parse through
+//! [`diff_parses`].
+//! Applications that call into programs, rather than only run them, load them
+//! into a [`Vm`].
+//! They can tell what the compiler they're built with supports through
+//! [`build_info`], and control what it learns from the system it runs on
+//! through a [`Host`]. Nothing else of the crate is stable, not even the
+//! command line interface of the `sophia` binary that it's built with.
+
+pub use crate::api::{
+    compile_str, diff_parses, parse_decl_fragment, parse_expr_fragment, CompiledProgram,
+    Diagnostics, Execution, ExecutionError, Location, Message, Session, Vm,
+};
+pub use crate::build_info::{build_info, BuildInfo};
+pub use crate::driver::Emit;
+pub use crate::host::Host;
+pub use crate::host_binding::IntoHostFunction;
+pub use crate::snapshot::{Snapshot, SnapshotError};
+pub use crate::structure::diff_structures;
+pub use crate::timing::{Pass, PassTiming};
+pub use crate::vm::{ExecutionLimits, HostCall, Progress};
+
+mod api;
+mod ast;
+pub mod ast_owned;
+mod build_info;
+mod builtin;
+mod bytecode;
+mod bytecode_gen;
+mod c;
+mod cfg;
+#[doc(hidden)]
+pub mod cli;
+mod codegen;
+mod compiler_context;
+mod cst;
+mod daemon;
+mod dce;
+mod diagnostics;
+mod doctest;
+mod driver;
+mod error_codes;
+mod features;
+mod fixer;
+mod formatter;
+#[cfg(feature = "jit")]
+mod frame;
+mod host;
+mod host_binding;
+mod incremental;
+mod interner;
+mod interp;
+mod ir;
+mod ir_lower;
+#[cfg(feature = "jit")]
+mod jit;
+mod json;
+mod layout;
+mod libc;
+mod limits;
+mod line_index;
+mod linker;
+mod lint;
+mod lint_config;
+mod llvm;
+mod lower;
+mod lsp;
+mod manifest;
+mod object;
+mod parser;
+mod printer;
+mod profile;
+mod profiler;
+mod query;
+mod render;
+mod repl;
+mod resolve;
+mod rewrite;
+mod runtime;
+mod scanner;
+mod snapshot;
+mod source_map;
+mod structure;
+mod suggest;
+mod timing;
+mod typeck;
+mod unused;
+mod vm;
+mod wasm;
+mod watch;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,52 @@
+use std::io;
+
+/// Function of the C library that the interpreter and the VM run themselves,
+/// as they can't call native code, so that programs that declare it as an
+/// extern function run there too.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum LibcFn {
+    /// `int abs(int n)`.
+    Abs,
+    /// `int putchar(int c)`, which writes the character to the standard output.
+    Putchar,
+}
+
+impl LibcFn {
+    pub(crate) const ALL: [LibcFn; 2] = [LibcFn::Abs, LibcFn::Putchar];
+
+    /// Function of the name, if it takes that many arguments, as an extern
+    /// declaration of another signature isn't of this function.
+    pub(crate) fn find(name: &str, arity: usize) -> Option<LibcFn> {
+        LibcFn::ALL
+            .into_iter()
+            .find(|libc_fn| libc_fn.name() == name && libc_fn.arity() == arity)
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            LibcFn::Abs => "abs",
+            LibcFn::Putchar => "putchar",
+        }
+    }
+
+    pub(crate) fn arity(self) -> usize {
+        match self {
+            LibcFn::Abs | LibcFn::Putchar => 1,
+        }
+    }
+
+    /// Calls the function with arguments of type `int`, writing what it prints
+    /// to the output.
+    pub(crate) fn call(self, args: &[i32], output: &mut dyn io::Write) -> io::Result<i32> {
+        match (self, args) {
+            (LibcFn::Abs, &[n]) => Ok(n.wrapping_abs()),
+            (LibcFn::Putchar, &[c]) => {
+                let c = c as u8;
+                output.write_all(&[c])?;
+
+                Ok(c.into())
+            }
+            _ => unreachable!("callers check the arity"),
+        }
+    }
+}
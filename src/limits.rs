@@ -0,0 +1,31 @@
+/// Bounds on how much a single compilation may take, so that pathological
+/// inputs fail with a clear error, or compile with fewer analyses, instead of
+/// running out of memory or hanging.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Limits {
+    /// Largest source code accepted, in bytes.
+    pub(crate) max_source_bytes: usize,
+    /// Most memory that the program's AST may take up, in bytes.
+    pub(crate) max_ast_bytes: usize,
+    /// How deep expressions may nest, which bounds the recursion of every pass
+    /// that walks them. Chains of left-associative operators, as in
+    /// `1 + 2 + 3`, don't nest. Past the default, a debug build of the
+    /// compiler may overflow the 8 MiB stack that the main thread usually
+    /// gets.
+    pub(crate) max_nesting_depth: usize,
+    /// Most declarations a program may have for lints to run on it. Past that,
+    /// linting is skipped with a warning, as the program is still correct
+    /// without it.
+    pub(crate) max_decls_to_lint: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_source_bytes: 64 * 1024 * 1024,
+            max_ast_bytes: 1024 * 1024 * 1024,
+            max_nesting_depth: 256,
+            max_decls_to_lint: 100_000,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::scanner::BytePos;
+
+/// Line of source code, counting from one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct Line(pub(crate) usize);
+
+/// Column of source code, counting characters from one, so that it's where
+/// people see the position in an editor.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct Col(pub(crate) usize);
+
+/// Offsets that the lines of source code start at, to look up the line and
+/// column of a position without scanning the source code up to it.
+#[derive(Clone)]
+pub(crate) struct LineIndex {
+    /// Offset of every line's start, the first one being zero.
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(source_code: &str) -> LineIndex {
+        let line_starts = std::iter::once(0)
+            .chain(source_code.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+
+        LineIndex { line_starts }
+    }
+
+    /// Line and column of the position in the source code the index was made
+    /// from. Positions past its end are at its end.
+    pub(crate) fn line_col(&self, source_code: &str, pos: BytePos) -> (Line, Col) {
+        let offset = pos.0.min(source_code.len());
+        let line_idx = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let col = source_code[self.line_starts[line_idx]..offset]
+            .chars()
+            .count();
+
+        (Line(line_idx + 1), Col(col + 1))
+    }
+
+    /// Position that the line starts at.
+    pub(crate) fn line_start(&self, line: Line) -> BytePos {
+        BytePos(self.line_starts[line.0 - 1])
+    }
+}
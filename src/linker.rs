@@ -0,0 +1,160 @@
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+
+use crate::runtime;
+
+/// What linking the assembly of a program produces.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum LinkOutput {
+    /// Executable that runs on its own.
+    Executable,
+    /// Object file of the program and the runtime library, which `ld` links
+    /// into an executable without anything else, unless the program calls
+    /// functions of the C library.
+    Object,
+}
+
+impl LinkOutput {
+    /// Path of the output when none is given, which is named after the input
+    /// file and goes to the current directory, e.g., `hello` or `hello.o` for
+    /// `examples/hello.sph`.
+    pub(crate) fn default_path(self, input_path: &Path) -> PathBuf {
+        let stem = input_path.file_stem().unwrap_or(OsStr::new("a"));
+        let path = match self {
+            LinkOutput::Executable => PathBuf::from(stem),
+            LinkOutput::Object => Path::new(stem).with_extension("o"),
+        };
+
+        // The input may have no extension, and it mustn't be overwritten.
+        if path == input_path {
+            path.with_extension("out")
+        } else {
+            path
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum LinkError {
+    /// The C compiler couldn't be run, e.g., because it isn't installed.
+    Spawn(OsString, io::Error),
+    /// The C compiler ran, but failed with what it wrote to its standard error.
+    Failed(OsString, ExitStatus, String),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::Spawn(compiler, err) => {
+                write!(f, "couldn't run `{}`: {}", compiler.to_string_lossy(), err)
+            }
+            LinkError::Failed(compiler, status, stderr) if stderr.is_empty() => {
+                write!(f, "`{}` failed with {}", compiler.to_string_lossy(), status)
+            }
+            LinkError::Failed(compiler, status, stderr) => {
+                write!(
+                    f,
+                    "`{}` failed with {}:\n{}",
+                    compiler.to_string_lossy(),
+                    status,
+                    stderr
+                )
+            }
+        }
+    }
+}
+
+/// Assembles the program together with the runtime library, then links them
+/// into an executable, unless only an object file is asked for.
+///
+/// The work is left to the system's C compiler, `cc` or the one in the `CC`
+/// environment variable, which runs the assembler and the linker. It doesn't
+/// link the C runtime, as the runtime library takes its place, unless the
+/// program calls functions of the C library, i.e., it declares extern
+/// functions.
+///
+/// The C library buffers what its functions print, e.g., `putchar`, whereas
+/// the runtime library's functions write straight away, so what they print
+/// may come out of order.
+pub(crate) fn link(
+    assembly: &str,
+    output: LinkOutput,
+    links_c_library: bool,
+    output_path: &Path,
+) -> Result<(), LinkError> {
+    let compiler = env::var_os("CC").unwrap_or_else(|| OsString::from("cc"));
+
+    let mut child = Command::new(&compiler)
+        .args(compiler_args(output, links_c_library, output_path))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| LinkError::Spawn(compiler.clone(), err))?;
+
+    // Writing only fails if the compiler exited early, which its status
+    // already tells about.
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(linker_input(assembly, links_c_library).as_bytes());
+    }
+
+    let process_output = child
+        .wait_with_output()
+        .map_err(|err| LinkError::Spawn(compiler.clone(), err))?;
+
+    if process_output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&process_output.stderr);
+
+        Err(LinkError::Failed(
+            compiler,
+            process_output.status,
+            stderr.trim_end().to_owned(),
+        ))
+    }
+}
+
+/// Arguments of the C compiler, which reads the assembly from its standard
+/// input.
+pub(crate) fn compiler_args(
+    output: LinkOutput,
+    links_c_library: bool,
+    output_path: &Path,
+) -> Vec<OsString> {
+    let mut args: Vec<OsString> = match output {
+        LinkOutput::Executable if links_c_library => vec![],
+        LinkOutput::Executable => vec!["-nostdlib".into(), "-static".into()],
+        LinkOutput::Object => vec!["-c".into()],
+    };
+
+    args.extend([
+        "-o".into(),
+        output_path.into(),
+        "-x".into(),
+        "assembler".into(),
+        "-".into(),
+    ]);
+
+    args
+}
+
+/// Assembly that the C compiler gets, which is the program's followed by the
+/// runtime library's, so that they end up in the same object file. The entry
+/// point comes from the C runtime if the C library is linked.
+pub(crate) fn linker_input(assembly: &str, links_c_library: bool) -> String {
+    if links_c_library {
+        format!("{}\n{}", assembly, runtime::ASSEMBLY)
+    } else {
+        format!(
+            "{}\n{}\n{}",
+            assembly,
+            runtime::ENTRY_POINT,
+            runtime::ASSEMBLY
+        )
+    }
+}
@@ -0,0 +1,433 @@
+use std::iter;
+
+use crate::ast::{
+    BinaryOp, BindDef, CastExpr, CompoundExpr, Const, Decl, Expr, ForExpr, ForIteration, IfExpr,
+    LoopKeyword, ParenExpr, Program, Type,
+};
+use crate::compiler_context::CompilerContext;
+use crate::dce::{diverges, reachable_len};
+use crate::diagnostics::CompileWarning;
+use crate::profile::IntWidth;
+use crate::resolve::Resolutions;
+use crate::scanner::Span;
+
+pub(crate) struct Linter<'a> {
+    ctx: &'a CompilerContext,
+    resolutions: &'a Resolutions,
+    warnings: Vec<CompileWarning>,
+}
+
+impl<'a> Linter<'a> {
+    pub(crate) fn new(ctx: &'a CompilerContext, resolutions: &'a Resolutions) -> Linter<'a> {
+        Linter {
+            ctx,
+            resolutions,
+            warnings: vec![],
+        }
+    }
+
+    pub(crate) fn lint_program(self, program: Program) -> Vec<CompileWarning> {
+        self.lint_decls(program.decls)
+    }
+
+    pub(crate) fn lint_decls<'d>(
+        mut self,
+        decls: impl IntoIterator<Item = &'d Decl<'d>>,
+    ) -> Vec<CompileWarning> {
+        for decl in decls {
+            self.lint_decl(decl);
+        }
+
+        self.warnings
+    }
+
+    fn lint_decl(&mut self, decl: &Decl) {
+        self.lint_expr(decl.value);
+    }
+
+    fn lint_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Const(_)
+            | Expr::BindRef(_)
+            | Expr::Continue(_)
+            | Expr::Extern(_)
+            | Expr::Struct(_)
+            | Expr::Enum(_) => {}
+            Expr::Break(break_expr) => {
+                if let Some(value) = break_expr.value {
+                    self.lint_expr(value);
+                }
+            }
+            Expr::BindDef(bind_def) => {
+                self.lint_expr(bind_def.value);
+                self.lint_shadowing(*bind_def);
+            }
+            Expr::Function(function) => self.lint_compound_expr(function.body),
+            Expr::StructLit(struct_lit_expr) => {
+                for field in struct_lit_expr.fields {
+                    self.lint_expr(field.value);
+                }
+            }
+            Expr::Field(field_expr) => self.lint_expr(field_expr.base),
+            Expr::Variant(variant_expr) => {
+                if let Some(payload) = variant_expr.payload {
+                    self.lint_expr(payload);
+                }
+            }
+            Expr::Tuple(tuple_expr) => {
+                for element in tuple_expr.elements {
+                    self.lint_expr(element);
+                }
+            }
+            Expr::TupleField(tuple_field_expr) => self.lint_expr(tuple_field_expr.base),
+            Expr::TupleBindDef(tuple_bind_def) => self.lint_expr(tuple_bind_def.value),
+            Expr::Array(array_expr) => {
+                for element in array_expr.elements {
+                    self.lint_expr(element);
+                }
+            }
+            Expr::Index(index_expr) => {
+                self.lint_expr(index_expr.base);
+                self.lint_expr(index_expr.index);
+            }
+            Expr::IndexAssign(index_assign_expr) => {
+                self.lint_expr(index_assign_expr.target.base);
+                self.lint_expr(index_assign_expr.target.index);
+                self.lint_expr(index_assign_expr.value);
+            }
+            Expr::If(if_expr) => self.lint_if_expr(*if_expr),
+            Expr::Match(match_expr) => {
+                self.lint_expr(match_expr.scrutinee);
+
+                for arm in match_expr.arms {
+                    self.lint_expr(arm.expr);
+                }
+            }
+            Expr::For(for_expr) => self.lint_for_expr(*for_expr),
+            Expr::Compound(compound_expr) => self.lint_compound_expr(*compound_expr),
+            Expr::Semi(expr)
+            | Expr::Cast(CastExpr { expr, .. })
+            | Expr::Paren(ParenExpr { expr, .. }) => self.lint_expr(expr),
+            Expr::FnCall(fn_call_expr) => {
+                for arg in fn_call_expr.args {
+                    self.lint_expr(arg);
+                }
+            }
+            Expr::Binary(binary_expr) => {
+                let (operand, operations) = binary_expr.chain();
+                self.lint_expr(operand);
+
+                for binary_expr in operations {
+                    self.lint_expr(binary_expr.rhs);
+                }
+            }
+        }
+    }
+
+    fn lint_if_expr(&mut self, if_expr: IfExpr) {
+        let branches = iter::once((if_expr.cond_expr, if_expr.true_branch)).chain(
+            if_expr
+                .else_if_branches
+                .iter()
+                .map(|branch| (branch.cond_expr, branch.true_branch)),
+        );
+
+        for (idx, (cond_expr, true_branch)) in branches.enumerate() {
+            self.lint_expr(cond_expr);
+
+            if let Some(value) = const_condition(cond_expr) {
+                // An always true condition leaves every branch after its own
+                // dead, and an always false one its own.
+                let dead_span = if value {
+                    if_expr
+                        .else_if_branches
+                        .get(idx)
+                        .map(|branch| branch.cond_expr.span())
+                        .or(if_expr.final_branch.map(|branch| branch.span))
+                        .map(|rest| Span {
+                            start: rest.start,
+                            end: if_expr.span.end,
+                        })
+                } else {
+                    Some(true_branch.span)
+                };
+
+                self.warnings.push(CompileWarning::ConstantCondition {
+                    value,
+                    is_loop: false,
+                    span: cond_expr.span(),
+                    dead_span,
+                });
+            }
+
+            self.lint_compound_expr(true_branch);
+        }
+
+        if let Some(final_branch) = if_expr.final_branch {
+            self.lint_compound_expr(final_branch);
+        }
+    }
+
+    fn lint_for_expr(&mut self, for_expr: ForExpr) {
+        match for_expr.iteration {
+            Some(ForIteration::Conditional { cond_expr }) => {
+                self.lint_expr(cond_expr);
+
+                if let Some(value) = const_condition(cond_expr) {
+                    // The loop never completes when its condition stays true,
+                    // which is when its else-branch would run.
+                    let dead_span = if value {
+                        for_expr.else_branch.map(|else_branch| else_branch.span)
+                    } else {
+                        Some(for_expr.body.span)
+                    };
+
+                    self.warnings.push(CompileWarning::ConstantCondition {
+                        value,
+                        is_loop: true,
+                        span: cond_expr.span(),
+                        dead_span,
+                    });
+                }
+            }
+            Some(ForIteration::Iterative {
+                start_expr,
+                end_expr,
+                range_kind,
+                step_expr,
+                ..
+            }) => {
+                self.lint_expr(start_expr);
+                self.lint_expr(end_expr);
+
+                if let Some(step_expr) = step_expr {
+                    self.lint_expr(step_expr);
+                }
+
+                if let (Some((start, _)), Some((end, _))) =
+                    (const_value(start_expr), const_value(end_expr))
+                {
+                    if start > end {
+                        self.warnings.push(CompileWarning::ReversedRange {
+                            start,
+                            end,
+                            range_kind,
+                            span: Span {
+                                start: start_expr.span().start,
+                                end: end_expr.span().end,
+                            },
+                        });
+                    }
+                }
+            }
+            Some(ForIteration::InCollection {
+                collection_expr, ..
+            }) => self.lint_expr(collection_expr),
+            // Writing `loop` wouldn't make a loop that never exits any better,
+            // so it's only suggested for loops that do.
+            None => {
+                if !for_expr.is_main_loop && !has_reachable_break(for_expr.body) {
+                    self.warnings.push(CompileWarning::InfiniteLoop {
+                        for_span: for_expr.for_kw_span,
+                    });
+                } else if for_expr.keyword == LoopKeyword::For {
+                    self.warnings.push(CompileWarning::BareFor {
+                        for_span: for_expr.for_kw_span,
+                    });
+                }
+            }
+        }
+
+        self.lint_compound_expr(for_expr.body);
+
+        if let Some(else_branch) = for_expr.else_branch {
+            self.lint_compound_expr(else_branch);
+        }
+    }
+
+    /// Warns about the `BindDef` if it shadows a binding of an enclosing
+    /// scope, unless its name starts with `_`, as shadowing can be meant.
+    fn lint_shadowing(&mut self, bind_def: BindDef) {
+        let Some(shadowing) = self.resolutions.shadowing(bind_def.span) else {
+            return;
+        };
+
+        let name = self.ctx.resolve_symbol(bind_def.identifier);
+
+        if !name.starts_with('_') {
+            self.warnings.push(CompileWarning::ShadowedBinding {
+                name: name.to_owned(),
+                span: bind_def.span,
+                shadowed_span: shadowing.def_span,
+                shadows_param: shadowing.is_param,
+            });
+        }
+    }
+
+    fn lint_compound_expr(&mut self, compound_expr: CompoundExpr) {
+        for expr in compound_expr.exprs {
+            self.lint_expr(expr);
+        }
+
+        let unreachable_exprs = &compound_expr.exprs[reachable_len(compound_expr)..];
+
+        if let (Some(first), Some(last)) = (unreachable_exprs.first(), unreachable_exprs.last()) {
+            self.warnings.push(CompileWarning::UnreachableCode {
+                span: Span {
+                    start: first.span().start,
+                    end: last.span().end,
+                },
+            });
+        }
+    }
+}
+
+/// Whether the condition is always true, or always false, as it's made up of
+/// constants only, or `None` if it depends on anything else.
+fn const_condition(cond_expr: &Expr) -> Option<bool> {
+    const_value(cond_expr).map(|(value, _)| value != 0)
+}
+
+/// Value of the expression, if it's made up of constants only, along with its
+/// type. Computations that overflow their type, or divide by zero, have no
+/// value here, as what they do depends on the integer profile.
+fn const_value(expr: &Expr) -> Option<(i128, Type)> {
+    let (value, ty) = match expr {
+        Expr::Const(const_expr) => match const_expr.value {
+            Const::IntegerConstant { value } => (value.into(), Type::I32),
+            Const::SuffixedIntegerConstant { value, ty } => (value.into(), ty),
+        },
+        Expr::Paren(ParenExpr { expr, .. }) => return const_value(expr),
+        Expr::Cast(cast_expr) => (const_value(cast_expr.expr)?.0, cast_expr.ty),
+        Expr::Binary(binary_expr) => {
+            let (operand, operations) = binary_expr.chain();
+            let mut lhs = const_value(operand)?;
+
+            for binary_expr in operations {
+                lhs = const_operation(binary_expr.op, lhs, const_value(binary_expr.rhs)?)?;
+            }
+
+            return Some(lhs);
+        }
+        _ => return None,
+    };
+
+    fitting_const(value, ty)
+}
+
+/// Value of the operation on constants, if it has one, see `const_value`.
+fn const_operation(
+    op: BinaryOp,
+    (lhs, lhs_ty): (i128, Type),
+    (rhs, rhs_ty): (i128, Type),
+) -> Option<(i128, Type)> {
+    let ty = if lhs_ty.widens_to(rhs_ty) {
+        rhs_ty
+    } else if rhs_ty.widens_to(lhs_ty) {
+        lhs_ty
+    } else {
+        return None;
+    };
+
+    let value = match op {
+        BinaryOp::Add => lhs.checked_add(rhs),
+        BinaryOp::Sub => lhs.checked_sub(rhs),
+        BinaryOp::Mul => lhs.checked_mul(rhs),
+        BinaryOp::Div => lhs.checked_div(rhs),
+        BinaryOp::Rem => lhs.checked_rem(rhs),
+    };
+
+    fitting_const(value?, ty)
+}
+
+/// The constant, if its value fits in its type.
+fn fitting_const(value: i128, ty: Type) -> Option<(i128, Type)> {
+    // `i32` is only as wide as the narrowest integer profile makes it.
+    let fits = if ty == Type::I32 {
+        IntWidth::Bits16.contains(value.try_into().ok()?)
+    } else {
+        let (min, max) = ty.int_range()?;
+        (min..=max).contains(&value)
+    };
+
+    fits.then_some((value, ty))
+}
+
+/// Whether control can reach a `break` in `compound_expr` that exits the loop
+/// it's the body of. Breaks in nested loops only count when they are in the
+/// nested loop's else-branch, as those exit the outer loop instead.
+fn has_reachable_break(compound_expr: CompoundExpr) -> bool {
+    for expr in compound_expr.exprs {
+        if expr_has_reachable_break(expr) {
+            return true;
+        }
+
+        if diverges(expr) {
+            return false;
+        }
+    }
+
+    false
+}
+
+fn expr_has_reachable_break(expr: &Expr) -> bool {
+    match expr {
+        Expr::Break(_) => true,
+        Expr::Const(_)
+        | Expr::BindRef(_)
+        | Expr::Continue(_)
+        | Expr::Function(_)
+        | Expr::Extern(_)
+        | Expr::Struct(_)
+        | Expr::Enum(_) => false,
+        Expr::BindDef(bind_def) => expr_has_reachable_break(bind_def.value),
+        Expr::StructLit(struct_lit_expr) => struct_lit_expr
+            .fields
+            .iter()
+            .any(|field| expr_has_reachable_break(field.value)),
+        Expr::Field(field_expr) => expr_has_reachable_break(field_expr.base),
+        Expr::Variant(variant_expr) => variant_expr.payload.is_some_and(expr_has_reachable_break),
+        Expr::Tuple(tuple_expr) => tuple_expr.elements.iter().any(expr_has_reachable_break),
+        Expr::TupleField(tuple_field_expr) => expr_has_reachable_break(tuple_field_expr.base),
+        Expr::TupleBindDef(tuple_bind_def) => expr_has_reachable_break(tuple_bind_def.value),
+        Expr::Array(array_expr) => array_expr.elements.iter().any(expr_has_reachable_break),
+        Expr::Index(index_expr) => {
+            expr_has_reachable_break(index_expr.base) || expr_has_reachable_break(index_expr.index)
+        }
+        Expr::IndexAssign(index_assign_expr) => {
+            expr_has_reachable_break(index_assign_expr.target.base)
+                || expr_has_reachable_break(index_assign_expr.target.index)
+                || expr_has_reachable_break(index_assign_expr.value)
+        }
+        Expr::If(if_expr) => {
+            expr_has_reachable_break(if_expr.cond_expr)
+                || has_reachable_break(if_expr.true_branch)
+                || if_expr.else_if_branches.iter().any(|branch| {
+                    expr_has_reachable_break(branch.cond_expr)
+                        || has_reachable_break(branch.true_branch)
+                })
+                || if_expr.final_branch.is_some_and(has_reachable_break)
+        }
+        Expr::Match(match_expr) => {
+            expr_has_reachable_break(match_expr.scrutinee)
+                || match_expr
+                    .arms
+                    .iter()
+                    .any(|arm| expr_has_reachable_break(arm.expr))
+        }
+        Expr::For(for_expr) => for_expr.else_branch.is_some_and(has_reachable_break),
+        Expr::Compound(compound_expr) => has_reachable_break(*compound_expr),
+        Expr::Semi(expr)
+        | Expr::Cast(CastExpr { expr, .. })
+        | Expr::Paren(ParenExpr { expr, .. }) => expr_has_reachable_break(expr),
+        Expr::FnCall(fn_call_expr) => fn_call_expr.args.iter().any(expr_has_reachable_break),
+        Expr::Binary(binary_expr) => {
+            let (operand, operations) = binary_expr.chain();
+
+            expr_has_reachable_break(operand)
+                || operations
+                    .iter()
+                    .any(|binary_expr| expr_has_reachable_break(binary_expr.rhs))
+        }
+    }
+}
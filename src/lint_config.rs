@@ -0,0 +1,194 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{self, Path, PathBuf};
+
+use crate::diagnostics::{Lint, LintLevel, LintLevels};
+
+/// Name of the files that set the lint levels of the source files in their
+/// directory and in the directories below it.
+pub(crate) const LINT_CONFIG_FILE_NAME: &str = "sophia-lints.toml";
+
+/// Lint levels that a `sophia-lints.toml` file sets, which is written in just
+/// enough TOML to say them:
+///
+/// ```toml
+/// # Files in the directories above don't apply to the ones in this one.
+/// root = true
+///
+/// [lints]
+/// all = "deny"
+/// unused-decl = "allow"
+/// ```
+///
+/// Lints are named the way they're on the command line, which groups of them,
+/// e.g., `all` or `unused`, are named along with.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub(crate) struct LintConfig {
+    /// Whether discovery stops at this file, as it's the root of a workspace.
+    pub(crate) root: bool,
+    /// Levels in the order they're set in the file, as a later level of a lint
+    /// overrides an earlier one.
+    pub(crate) levels: Vec<(Lint, LintLevel)>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct LintConfigError {
+    pub(crate) message: String,
+    pub(crate) line: usize,
+}
+
+impl fmt::Display for LintConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} on line {}", self.message, self.line)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum DiscoveryError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, LintConfigError),
+}
+
+impl fmt::Display for DiscoveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiscoveryError::Read(path, err) => {
+                write!(f, "couldn't read `{}`: {}", path.display(), err)
+            }
+            DiscoveryError::Parse(path, err) => {
+                write!(
+                    f,
+                    "invalid lint configuration `{}`: {}",
+                    path.display(),
+                    err
+                )
+            }
+        }
+    }
+}
+
+impl LintConfig {
+    pub(crate) fn parse(text: &str) -> Result<LintConfig, LintConfigError> {
+        let mut config = LintConfig::default();
+        let mut in_lints_table = false;
+
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| LintConfigError {
+                message,
+                line: index + 1,
+            };
+            // Neither keys nor values can have a `#` in them, so it always
+            // starts a comment.
+            let line = line.split('#').next().unwrap_or_default().trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(table) = line.strip_prefix('[') {
+                match table.strip_suffix(']').map(str::trim) {
+                    Some("lints") => in_lints_table = true,
+                    Some(name) => return Err(error(format!("unknown table `{}`", name))),
+                    None => return Err(error("expected `]` after the table's name".to_owned())),
+                }
+
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(error("expected `<key> = <value>`".to_owned()));
+            };
+            let (key, value) = (unquote(key.trim()), value.trim());
+
+            if in_lints_table {
+                let Some(lints) = Lint::lints_named(key) else {
+                    return Err(error(format!("unknown lint `{}`", key)));
+                };
+                let Some(level) = LintLevel::from_name(unquote(value)) else {
+                    return Err(error(format!(
+                        "expected `\"allow\"`, `\"warn\"` or `\"deny\"` as the level of `{}`",
+                        key
+                    )));
+                };
+
+                config
+                    .levels
+                    .extend(lints.iter().map(|&lint| (lint, level)));
+            } else if key == "root" {
+                config.root = match value {
+                    "true" => true,
+                    "false" => false,
+                    _ => {
+                        return Err(error(
+                            "expected `true` or `false` as the value of `root`".to_owned(),
+                        ));
+                    }
+                };
+            } else {
+                return Err(error(format!("unknown key `{}`", key)));
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Finds the configurations that apply to the source file at the path,
+    /// going up from the file's directory until one that's a `root`, or until
+    /// the root of the file system.
+    ///
+    /// They're in order from the outermost directory's to the file's own
+    /// directory's, which is the order to apply them in, so that the nearer a
+    /// configuration is to the file, the more it has the last word.
+    pub(crate) fn discover(source_path: &Path) -> Result<Vec<LintConfig>, DiscoveryError> {
+        let source_path = path::absolute(source_path)
+            .map_err(|err| DiscoveryError::Read(source_path.to_owned(), err))?;
+        let mut configs = vec![];
+
+        for dir in source_path.ancestors().skip(1) {
+            let config_path = dir.join(LINT_CONFIG_FILE_NAME);
+
+            let text = match fs::read_to_string(&config_path) {
+                Ok(text) => text,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(DiscoveryError::Read(config_path, err)),
+            };
+            let config =
+                LintConfig::parse(&text).map_err(|err| DiscoveryError::Parse(config_path, err))?;
+            let is_root = config.root;
+
+            configs.push(config);
+
+            if is_root {
+                break;
+            }
+        }
+
+        configs.reverse();
+
+        Ok(configs)
+    }
+}
+
+/// Lint levels of a source file that the configurations apply to, in the order
+/// `LintConfig::discover` finds them in, and then the overrides, i.e., the
+/// levels given on the command line, which have the last word.
+pub(crate) fn lint_levels(configs: &[LintConfig], overrides: &[(Lint, LintLevel)]) -> LintLevels {
+    let mut lint_levels = LintLevels::default();
+
+    for &(lint, level) in configs
+        .iter()
+        .flat_map(|config| &config.levels)
+        .chain(overrides)
+    {
+        lint_levels.set(lint, level);
+    }
+
+    lint_levels
+}
+
+fn unquote(text: &str) -> &str {
+    text.strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text)
+}
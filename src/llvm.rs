@@ -0,0 +1,550 @@
+use std::fmt::Write;
+
+use crate::ast::{BinaryOp, Expr, ExternFn, Program, Type};
+use crate::compiler_context::CompilerContext;
+use crate::ir::{self, BlockCall, BlockId, Callee, Inst, InstKind, Terminator, Value};
+use crate::ir_lower::IrLowering;
+use crate::resolve::Resolutions;
+
+/// Lowers a program to textual LLVM IR, so that it can be compiled further by
+/// `clang` or `llc`.
+///
+/// The program is lowered to the IR first, whose values keep their names, and
+/// whose blocks' parameters become phi nodes. Arithmetic wraps around just
+/// like the native code does, and divisions that would trap on x86 trap here
+/// too, instead of being undefined behavior.
+pub(crate) struct LlvmCodeGen<'ctx> {
+    ctx: &'ctx CompilerContext,
+    resolutions: &'ctx Resolutions,
+    uses_trap: bool,
+    /// Whether ranges are stepped up or down, which checks for overflows with
+    /// LLVM's intrinsics.
+    steps_up: bool,
+    steps_down: bool,
+}
+
+impl<'ctx> LlvmCodeGen<'ctx> {
+    pub(crate) fn new(
+        ctx: &'ctx CompilerContext,
+        resolutions: &'ctx Resolutions,
+    ) -> LlvmCodeGen<'ctx> {
+        LlvmCodeGen {
+            ctx,
+            resolutions,
+            uses_trap: false,
+            steps_up: false,
+            steps_down: false,
+        }
+    }
+
+    pub(crate) fn gen_module(mut self, program: Program<'ctx>) -> String {
+        let mut module = String::new();
+        writeln!(module, "; ModuleID = 'sophia'").unwrap();
+        writeln!(module, "source_filename = \"sophia\"").unwrap();
+
+        let ir_module = IrLowering::new(self.ctx, self.resolutions, &[]).lower_program(program);
+
+        for function in &ir_module.functions {
+            let function_ir = FunctionGen::new(&mut self, &ir_module, function).gen_function();
+
+            writeln!(module).unwrap();
+            module.push_str(&function_ir);
+        }
+
+        if self.uses_trap {
+            writeln!(module).unwrap();
+            writeln!(module, "declare void @llvm.trap()").unwrap();
+        }
+
+        for (is_used, intrinsic) in [
+            (self.steps_up, "sadd.with.overflow"),
+            (self.steps_down, "ssub.with.overflow"),
+        ] {
+            if is_used {
+                writeln!(module).unwrap();
+                writeln!(
+                    module,
+                    "declare {{ i32, i1 }} @llvm.{}.i32(i32, i32)",
+                    intrinsic
+                )
+                .unwrap();
+            }
+        }
+
+        // Builtins are functions of the runtime library that's linked in.
+        for builtin in self.resolutions.builtins() {
+            writeln!(module).unwrap();
+            writeln!(module, "declare void @{}(i32)", builtin.runtime_symbol()).unwrap();
+        }
+
+        for decl in program.decls {
+            if let Expr::Extern(extern_fn) = decl.value {
+                let params = vec!["i32"; extern_fn.parameters.len()];
+
+                writeln!(module).unwrap();
+                writeln!(
+                    module,
+                    "declare {} @{}({})",
+                    extern_return_type(*extern_fn),
+                    self.ctx.resolve_symbol(decl.identifier),
+                    params.join(", ")
+                )
+                .unwrap();
+            }
+        }
+
+        module
+    }
+}
+
+/// Jump from a block to another, for the phi nodes of the block it goes to.
+struct Incoming {
+    /// Label of the LLVM block that jumps, which is the last one of its IR
+    /// block, as checks for traps split IR blocks.
+    label: String,
+    /// Arguments of the parameters that are needed.
+    args: Vec<(Value, String)>,
+}
+
+struct FunctionGen<'gen, 'ctx> {
+    codegen: &'gen mut LlvmCodeGen<'ctx>,
+    module: &'gen ir::Module,
+    function: &'gen ir::Function,
+    use_counts: Vec<usize>,
+    is_needed: Vec<bool>,
+    /// Constant of every value that is one, by the value's number.
+    constants: Vec<Option<i32>>,
+    /// Comparisons, by the number of their value, that are used only by the
+    /// branch of their block, which stay `i1`s instead of being extended to
+    /// `i32`s.
+    is_branch_cond: Vec<bool>,
+    incomings: Vec<Vec<Incoming>>,
+    /// Label of the LLVM block being generated, and how many times its IR
+    /// block has been split so far.
+    label: String,
+    split_count: usize,
+    uses_trap: bool,
+    /// Code of the block being generated.
+    code: String,
+}
+
+impl<'gen, 'ctx> FunctionGen<'gen, 'ctx> {
+    fn new(
+        codegen: &'gen mut LlvmCodeGen<'ctx>,
+        module: &'gen ir::Module,
+        function: &'gen ir::Function,
+    ) -> FunctionGen<'gen, 'ctx> {
+        let use_counts = function.use_counts();
+        let value_count = use_counts.len();
+
+        let mut function_gen = FunctionGen {
+            codegen,
+            module,
+            function,
+            use_counts,
+            is_needed: function.needed_values(),
+            constants: vec![None; value_count],
+            is_branch_cond: vec![false; value_count],
+            incomings: (0..function.blocks.len()).map(|_| vec![]).collect(),
+            label: String::new(),
+            split_count: 0,
+            uses_trap: false,
+            code: String::new(),
+        };
+
+        for inst in function.blocks.iter().flat_map(|block| &block.insts) {
+            if let (Some(result), InstKind::Const(value)) = (inst.result, &inst.kind) {
+                function_gen.constants[result.0] = Some(*value);
+            }
+        }
+
+        for block in &function.blocks {
+            let Terminator::Branch {
+                cond,
+                then_call,
+                else_call,
+            } = &block.terminator
+            else {
+                continue;
+            };
+
+            let is_comparison = block.insts.iter().any(|inst| {
+                inst.result == Some(*cond)
+                    && matches!(
+                        inst.kind,
+                        InstKind::Less { .. }
+                            | InstKind::LessEqual { .. }
+                            | InstKind::Overflows { .. }
+                    )
+            });
+
+            if is_comparison && function_gen.use_counts[cond.0] == 1 && then_call != else_call {
+                function_gen.is_branch_cond[cond.0] = true;
+            }
+        }
+
+        function_gen
+    }
+
+    fn gen_function(mut self) -> String {
+        let mut block_codes = vec![];
+
+        for idx in 0..self.function.blocks.len() {
+            self.gen_block(BlockId(idx));
+            block_codes.push(std::mem::take(&mut self.code));
+        }
+
+        let params: Vec<_> = (self.function.params().iter())
+            .map(|param| format!("i32 %{}", param))
+            .collect();
+
+        let mut body = String::new();
+
+        for (idx, block_code) in block_codes.into_iter().enumerate() {
+            writeln!(body, "{}:", BlockId(idx)).unwrap();
+
+            // The entry block's parameters are the function's.
+            let params = match idx {
+                0 => &[][..],
+                _ => &self.function.blocks[idx].params[..],
+            };
+
+            for &param in params {
+                if self.is_needed[param.0] {
+                    let incomings: Vec<_> = (self.incomings[idx].iter())
+                        .map(|incoming| {
+                            let (_, arg) = (incoming.args.iter())
+                                .find(|(arg_param, _)| *arg_param == param)
+                                .unwrap();
+
+                            format!("[ {}, %{} ]", arg, incoming.label)
+                        })
+                        .collect();
+
+                    writeln!(body, "  %{} = phi i32 {}", param, incomings.join(", ")).unwrap();
+                }
+            }
+
+            body.push_str(&block_code);
+        }
+
+        if self.uses_trap {
+            writeln!(body, "trap:").unwrap();
+            writeln!(body, "  call void @llvm.trap()").unwrap();
+            writeln!(body, "  unreachable").unwrap();
+
+            self.codegen.uses_trap = true;
+        }
+
+        format!(
+            "define {} @{}({}) {{\n{}}}\n",
+            return_type(self.function),
+            self.function.name,
+            params.join(", "),
+            body
+        )
+    }
+
+    fn gen_block(&mut self, block_id: BlockId) {
+        let block = &self.function.blocks[block_id.0];
+
+        self.label = block_id.to_string();
+        self.split_count = 0;
+
+        for inst in &block.insts {
+            self.gen_inst(inst);
+        }
+
+        match &block.terminator {
+            Terminator::Jump(call) => self.gen_jump(call),
+            Terminator::Branch {
+                then_call,
+                else_call,
+                ..
+            } if then_call == else_call => self.gen_jump(then_call),
+            Terminator::Branch {
+                cond,
+                then_call,
+                else_call,
+            } => {
+                let cond = if self.is_branch_cond[cond.0] {
+                    format!("%{}", cond)
+                } else {
+                    let operand = self.operand(*cond);
+                    self.emit_value("cond", format!("icmp ne i32 {}, 0", operand))
+                };
+
+                self.gen_branch(&cond, then_call, else_call);
+            }
+            Terminator::Return(Some(value)) => {
+                let value = self.operand(*value);
+                self.emit(format!("ret i32 {}", value));
+            }
+            // The function never returns, e.g., it ends in an infinite loop.
+            Terminator::Return(None) if self.function.returns_value => self.emit("unreachable"),
+            Terminator::Return(None) if self.function.name == "main" => self.emit("ret i32 0"),
+            Terminator::Return(None) => self.emit("ret void"),
+        }
+    }
+
+    fn gen_inst(&mut self, inst: &Inst) {
+        match (&inst.kind, inst.result) {
+            (InstKind::Const(_), _) => {}
+            (InstKind::Binary { op, lhs, rhs, .. }, Some(result)) => {
+                let lhs = self.operand(*lhs);
+                let rhs = self.operand(*rhs);
+
+                let instruction = match op {
+                    BinaryOp::Add => "add",
+                    BinaryOp::Sub => "sub",
+                    BinaryOp::Mul => "mul",
+                    BinaryOp::Div => "sdiv",
+                    BinaryOp::Rem => "srem",
+                };
+
+                // Dividing by zero, or dividing the minimum integer by minus one, is
+                // undefined behavior in LLVM, whereas it traps in the native code.
+                if matches!(op, BinaryOp::Div | BinaryOp::Rem) {
+                    let is_zero = self.emit_value("is_zero", format!("icmp eq i32 {}, 0", rhs));
+                    let is_min =
+                        self.emit_value("is_min", format!("icmp eq i32 {}, {}", lhs, i32::MIN));
+                    let is_minus_one =
+                        self.emit_value("is_minus_one", format!("icmp eq i32 {}, -1", rhs));
+                    let overflows = self
+                        .emit_value("overflows", format!("and i1 {}, {}", is_min, is_minus_one));
+                    let traps =
+                        self.emit_value("traps", format!("or i1 {}, {}", is_zero, overflows));
+
+                    self.trap_if(&traps);
+                }
+
+                self.emit(format!(
+                    "%{} = {} i32 {}, {}",
+                    result, instruction, lhs, rhs
+                ));
+            }
+            // Comparisons that aren't needed can be left out.
+            (
+                InstKind::Less { .. } | InstKind::LessEqual { .. } | InstKind::Overflows { .. },
+                Some(result),
+            ) if !self.is_needed[result.0] => {}
+            (InstKind::Less { lhs, rhs }, Some(result)) => {
+                let comparison = format!(
+                    "icmp slt i32 {}, {}",
+                    self.operand(*lhs),
+                    self.operand(*rhs)
+                );
+                self.gen_comparison(result, comparison);
+            }
+            (InstKind::LessEqual { lhs, rhs }, Some(result)) => {
+                let comparison = format!(
+                    "icmp sle i32 {}, {}",
+                    self.operand(*lhs),
+                    self.operand(*rhs)
+                );
+                self.gen_comparison(result, comparison);
+            }
+            (InstKind::Overflows { op, lhs, rhs }, Some(result)) => {
+                let intrinsic = match op {
+                    BinaryOp::Add => {
+                        self.codegen.steps_up = true;
+                        "sadd"
+                    }
+                    BinaryOp::Sub => {
+                        self.codegen.steps_down = true;
+                        "ssub"
+                    }
+                    _ => unreachable!("only steps of ranges are checked for overflows"),
+                };
+
+                self.emit(format!(
+                    "%{}.with_overflow = call {{ i32, i1 }} @llvm.{}.with.overflow.i32(i32 {}, i32 {})",
+                    result,
+                    intrinsic,
+                    self.operand(*lhs),
+                    self.operand(*rhs)
+                ));
+                self.gen_comparison(
+                    result,
+                    format!("extractvalue {{ i32, i1 }} %{}.with_overflow, 1", result),
+                );
+            }
+            (InstKind::CheckStep { step, .. }, _) => {
+                let step = self.operand(*step);
+                let is_not_positive =
+                    self.emit_value("is_not_positive", format!("icmp sle i32 {}, 0", step));
+
+                self.trap_if(&is_not_positive);
+            }
+            (InstKind::Call { callee, args }, result) => {
+                let (return_type, callee) = match *callee {
+                    Callee::Function(name) => {
+                        let callee_function = self.module.functions.iter().find(|f| f.name == name);
+
+                        (return_type(callee_function.unwrap()), name)
+                    }
+                    Callee::Builtin(builtin) => ("void", builtin.runtime_symbol()),
+                    Callee::Extern(name) if result.is_some() => ("i32", name),
+                    Callee::Extern(name) => ("void", name),
+                    Callee::Host(_) => unreachable!(
+                        "programs that call host functions are rejected before code generation"
+                    ),
+                };
+
+                let args: Vec<_> = (args.iter())
+                    .map(|&arg| format!("i32 {}", self.operand(arg)))
+                    .collect();
+                let call = format!("call {} @{}({})", return_type, callee, args.join(", "));
+
+                match result {
+                    Some(result) => self.emit(format!("%{} = {}", result, call)),
+                    None => self.emit(call),
+                }
+            }
+            (
+                InstKind::Binary { .. }
+                | InstKind::Less { .. }
+                | InstKind::LessEqual { .. }
+                | InstKind::Overflows { .. },
+                None,
+            ) => unreachable!("arithmetic and comparisons define values"),
+        }
+    }
+
+    /// Defines the comparison's value, which is extended to an `i32` unless
+    /// it's only the condition of its block's branch.
+    fn gen_comparison(&mut self, result: Value, comparison: String) {
+        if self.is_branch_cond[result.0] {
+            self.emit(format!("%{} = {}", result, comparison));
+        } else {
+            self.emit(format!("%{}.bit = {}", result, comparison));
+            self.emit(format!("%{} = zext i1 %{}.bit to i32", result, result));
+        }
+    }
+
+    /// Goes to one block or the other depending on the condition, which is an
+    /// `i1`.
+    fn gen_branch(&mut self, cond: &str, then_call: &BlockCall, else_call: &BlockCall) {
+        // The phi nodes of a block that's gone to either way can't tell which
+        // way it was, so its arguments are selected before going to it.
+        if then_call.block == else_call.block {
+            let params = &self.function.blocks[then_call.block.0].params;
+            let mut args = vec![];
+
+            for ((&param, &then_arg), &else_arg) in
+                params.iter().zip(&then_call.args).zip(&else_call.args)
+            {
+                if !self.is_needed[param.0] {
+                    continue;
+                }
+
+                let arg = if then_arg == else_arg {
+                    self.operand(then_arg)
+                } else {
+                    let then_arg = self.operand(then_arg);
+                    let else_arg = self.operand(else_arg);
+
+                    self.emit_value(
+                        &param.to_string(),
+                        format!("select i1 {}, i32 {}, i32 {}", cond, then_arg, else_arg),
+                    )
+                };
+
+                args.push((param, arg));
+            }
+
+            self.add_incoming(then_call.block, args);
+            self.emit(format!("br label %{}", then_call.block));
+        } else {
+            self.add_incoming_call(then_call);
+            self.add_incoming_call(else_call);
+            self.emit(format!(
+                "br i1 {}, label %{}, label %{}",
+                cond, then_call.block, else_call.block
+            ));
+        }
+    }
+
+    fn gen_jump(&mut self, call: &BlockCall) {
+        self.add_incoming_call(call);
+        self.emit(format!("br label %{}", call.block));
+    }
+
+    /// Passes the arguments of the needed parameters to the block's phi nodes.
+    fn add_incoming_call(&mut self, call: &BlockCall) {
+        let params = &self.function.blocks[call.block.0].params;
+
+        let args = (params.iter().zip(&call.args))
+            .filter(|(param, _)| self.is_needed[param.0])
+            .map(|(&param, &arg)| (param, self.operand(arg)))
+            .collect();
+
+        self.add_incoming(call.block, args);
+    }
+
+    fn add_incoming(&mut self, block: BlockId, args: Vec<(Value, String)>) {
+        self.incomings[block.0].push(Incoming {
+            label: self.label.clone(),
+            args,
+        });
+    }
+
+    /// Traps if the condition holds, carrying on in a new LLVM block otherwise.
+    fn trap_if(&mut self, cond: &str) {
+        self.split_count += 1;
+
+        let label = format!(
+            "{}.{}",
+            self.label.split('.').next().unwrap(),
+            self.split_count
+        );
+
+        self.emit(format!("br i1 {}, label %trap, label %{}", cond, label));
+        writeln!(self.code, "{}:", label).unwrap();
+
+        self.label = label;
+        self.uses_trap = true;
+    }
+
+    /// LLVM operand for the value, which is either a constant or a register.
+    fn operand(&self, value: Value) -> String {
+        match self.constants[value.0] {
+            Some(constant) => constant.to_string(),
+            None => format!("%{}", value),
+        }
+    }
+
+    fn emit(&mut self, instruction: impl AsRef<str>) {
+        writeln!(self.code, "  {}", instruction.as_ref()).unwrap();
+    }
+
+    /// Emits an instruction that produces a temporary, which is named after
+    /// the LLVM block it's in, returning the temporary.
+    fn emit_value(&mut self, name: &str, instruction: String) -> String {
+        let value = format!("%{}.{}", self.label, name);
+
+        self.emit(format!("{} = {}", value, instruction));
+
+        value
+    }
+}
+
+/// LLVM type that the function returns. `main` returns an exit code to the C
+/// runtime, which is zero if it returns `()`.
+fn return_type(function: &ir::Function) -> &'static str {
+    if function.returns_value || function.name == "main" {
+        "i32"
+    } else {
+        "void"
+    }
+}
+
+/// Type that an extern function returns, which is an `i32` unless it returns
+/// nothing, as programs with integers of other types are rejected before code
+/// generation.
+fn extern_return_type(extern_fn: ExternFn) -> &'static str {
+    match extern_fn.return_type {
+        Type::Unit => "void",
+        _ => "i32",
+    }
+}
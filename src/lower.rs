@@ -0,0 +1,12 @@
+use crate::ast::{Const, ConstExpr, Expr};
+
+/// Value of `expr` if it's an integer constant.
+pub(crate) fn constant_value(expr: &Expr) -> Option<i32> {
+    match expr {
+        Expr::Const(ConstExpr {
+            value: Const::IntegerConstant { value },
+            ..
+        }) => Some(*value),
+        _ => None,
+    }
+}
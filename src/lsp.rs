@@ -0,0 +1,694 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::ast::{Decl, Expr, Program};
+use crate::compiler_context::CompilerContext;
+use crate::daemon::{
+    error_response, RpcError, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR,
+};
+use crate::diagnostics::Diagnostic;
+use crate::driver::{self, Options};
+use crate::incremental::TextEdit;
+use crate::json::Json;
+use crate::layout::Layouts;
+use crate::line_index::LineIndex;
+use crate::query::QueryDatabase;
+use crate::resolve::{Resolution, Resolver};
+use crate::scanner::{BytePos, Scanner, Span};
+use crate::typeck::TypeChecker;
+
+/// Severities of diagnostics, as defined by the Language Server Protocol.
+const SEVERITY_ERROR: i64 = 1;
+const SEVERITY_WARNING: i64 = 2;
+
+/// Kinds of document symbols, as defined by the Language Server Protocol.
+const SYMBOL_KIND_FUNCTION: i64 = 12;
+const SYMBOL_KIND_STRUCT: i64 = 23;
+const SYMBOL_KIND_ENUM: i64 = 10;
+const SYMBOL_KIND_VARIABLE: i64 = 13;
+
+/// Language server of `sophia lsp`, which editors run to check the code as
+/// it's edited and to navigate it.
+///
+/// It speaks the Language Server Protocol over the standard input and output,
+/// and it supports:
+///
+/// - publishing the errors and warnings of a document whenever it's opened or
+///   changed, which editors send either whole or as edits, parsing only the
+///   declarations that the edits touched and checking only the ones that
+///   changed, see `QueryDatabase`;
+/// - `textDocument/definition`, which goes to where a name is defined;
+/// - `textDocument/hover`, which shows a name's type, as in `x : i32`;
+/// - `textDocument/documentSymbol`, which lists the declarations.
+///
+/// Names are known wherever they resolve, and types in the declarations whose
+/// names all resolve, as long as the structs and enums are all valid, which
+/// type errors don't get in the way of. Requests are answered from the parse
+/// that checking the document cached, except for scripts, which are parsed
+/// again. Positions are in UTF-16 code units, as the protocol requires.
+pub(crate) struct LanguageServer {
+    options: Options,
+    /// Source code of the open documents, by their URIs.
+    documents: HashMap<String, String>,
+    /// Queries that check the open documents as they're edited, by their
+    /// URIs.
+    queries: HashMap<String, QueryDatabase>,
+    /// Context that requests about names and symbols parse scripts into,
+    /// which is reset for each of them rather than allocated anew.
+    context: RefCell<CompilerContext>,
+    is_shut_down: bool,
+    is_exiting: bool,
+}
+
+impl LanguageServer {
+    pub(crate) fn new(options: Options) -> LanguageServer {
+        LanguageServer {
+            options,
+            documents: Default::default(),
+            queries: Default::default(),
+            context: RefCell::new(CompilerContext::new(String::new())),
+            is_shut_down: false,
+            is_exiting: false,
+        }
+    }
+
+    /// Serves messages from the input until an `exit` notification arrives or
+    /// the input ends, returning whether a `shutdown` request came before.
+    pub(crate) fn serve(
+        mut self,
+        mut input: impl BufRead,
+        mut output: impl Write,
+    ) -> io::Result<bool> {
+        while let Some(message) = read_message(&mut input)? {
+            for outgoing in self.handle_message(&message) {
+                write_message(&mut output, &outgoing)?;
+            }
+
+            if self.is_exiting {
+                break;
+            }
+        }
+
+        Ok(self.is_shut_down)
+    }
+
+    /// Handles a single JSON-RPC message, returning what's sent back: the
+    /// response to a request, along with notifications, e.g., of a changed
+    /// document's diagnostics.
+    pub(crate) fn handle_message(&mut self, message: &str) -> Vec<Json> {
+        let message = match Json::parse(message) {
+            Ok(message) => message,
+            Err(error) => {
+                return vec![error_response(
+                    Json::Null,
+                    RpcError::new(PARSE_ERROR, error.to_string()),
+                )];
+            }
+        };
+
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let method = message.get("method").and_then(Json::as_str);
+
+        let Some(id) = message.get("id").cloned() else {
+            return match method {
+                Some(method) => self.handle_notification(method, params),
+                None => vec![],
+            };
+        };
+
+        let result = match method {
+            Some(method) => self.handle_request(method, params),
+            None => Err(RpcError::new(INVALID_REQUEST, "missing `method`")),
+        };
+
+        vec![match result {
+            Ok(result) => Json::object([
+                ("jsonrpc", Json::from("2.0")),
+                ("id", id),
+                ("result", result),
+            ]),
+            Err(error) => error_response(id, error),
+        }]
+    }
+
+    fn handle_request(&mut self, method: &str, params: &Json) -> Result<Json, RpcError> {
+        match method {
+            "initialize" => Ok(Json::object([
+                (
+                    "capabilities",
+                    Json::object([
+                        (
+                            "textDocumentSync",
+                            // Documents are opened and closed, and their
+                            // changes are sent as edits.
+                            Json::object([
+                                ("openClose", Json::from(true)),
+                                ("change", Json::from(2i64)),
+                            ]),
+                        ),
+                        ("definitionProvider", Json::from(true)),
+                        ("hoverProvider", Json::from(true)),
+                        ("documentSymbolProvider", Json::from(true)),
+                    ]),
+                ),
+                (
+                    "serverInfo",
+                    Json::object([
+                        ("name", Json::from("sophia")),
+                        ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+                    ]),
+                ),
+            ])),
+            "shutdown" => {
+                self.is_shut_down = true;
+
+                Ok(Json::Null)
+            }
+            "textDocument/definition" => {
+                let (source_code, pos) = self.document_position(params)?;
+                let uri = document_uri(params)?;
+
+                Ok(self
+                    .find_name(uri, source_code, pos)
+                    .and_then(|name| name.def_span)
+                    .map_or(Json::Null, |def_span| {
+                        Json::object([
+                            ("uri", Json::from(uri)),
+                            ("range", to_range(source_code, def_span)),
+                        ])
+                    }))
+            }
+            "textDocument/hover" => {
+                let (source_code, pos) = self.document_position(params)?;
+                let uri = document_uri(params)?;
+
+                Ok(self
+                    .find_name(uri, source_code, pos)
+                    .and_then(|name| Some((name.span, name.signature?)))
+                    .map_or(Json::Null, |(span, signature)| {
+                        Json::object([
+                            (
+                                "contents",
+                                Json::object([
+                                    ("kind", Json::from("markdown")),
+                                    (
+                                        "value",
+                                        Json::from(format!("```sophia\n{}\n```", signature)),
+                                    ),
+                                ]),
+                            ),
+                            ("range", to_range(source_code, span)),
+                        ])
+                    }))
+            }
+            "textDocument/documentSymbol" => {
+                let source_code = self.document(params)?;
+                let uri = document_uri(params)?;
+
+                Ok(Json::Array(
+                    self.with_program(uri, source_code, |_, program| {
+                        document_symbols(source_code, program)
+                    })
+                    .unwrap_or_default(),
+                ))
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method `{}`", method),
+            )),
+        }
+    }
+
+    /// Handles a notification, which isn't responded to, even if it's
+    /// invalid.
+    fn handle_notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        if method == "exit" {
+            self.is_exiting = true;
+
+            return vec![];
+        }
+
+        let Ok(uri) = document_uri(params) else {
+            return vec![];
+        };
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params
+                    .get("textDocument")
+                    .and_then(|document| document.get("text"))
+                    .and_then(Json::as_str)
+                    .unwrap_or_default();
+                self.documents.insert(uri.to_owned(), text.to_owned());
+            }
+            "textDocument/didChange" => {
+                let Some(source_code) = self.documents.get_mut(uri) else {
+                    return vec![];
+                };
+                let changes = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .unwrap_or_default();
+
+                for change in changes {
+                    apply_change(source_code, change);
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                self.queries.remove(uri);
+
+                // Closed documents' diagnostics are cleared.
+                return vec![publish_diagnostics(uri, vec![])];
+            }
+            _ => return vec![],
+        }
+
+        let source_code = &self.documents[uri];
+        let diagnostic = if self.options.script_mode {
+            driver::check(source_code.as_str(), &self.options)
+        } else {
+            // Only the declarations that were edited are checked again.
+            self.queries
+                .entry(uri.to_owned())
+                .or_default()
+                .check(source_code, &self.options)
+        };
+
+        vec![publish_diagnostics(
+            uri,
+            diagnostics_to_json(&diagnostic, source_code),
+        )]
+    }
+
+    /// Name at the position of the document.
+    fn find_name(&self, uri: &str, source_code: &str, pos: BytePos) -> Option<Name> {
+        self.with_program(uri, source_code, |ctx, program| {
+            names(ctx, program, source_code, &self.options)
+                .into_iter()
+                .find(|name| name.span.start.0 <= pos.0 && pos.0 <= name.span.end.0)
+        })
+        .flatten()
+    }
+
+    /// Runs `f` on the syntax tree of the document, which is the one that
+    /// checking it cached, unless it's a script, which is parsed into
+    /// `context`. It's `None` if the document doesn't parse.
+    fn with_program<T>(
+        &self,
+        uri: &str,
+        source_code: &str,
+        f: impl FnOnce(&CompilerContext, Program) -> T,
+    ) -> Option<T> {
+        if let Some(queries) = self.queries.get(uri) {
+            let (ctx, program) = queries.last_program()?;
+
+            return Some(f(ctx, program));
+        }
+
+        let mut ctx = self.context.borrow_mut();
+        ctx.reset(source_code.to_owned());
+        let program = driver::parse(&ctx, &self.options).ok()?;
+
+        Some(f(&ctx, program))
+    }
+
+    fn document(&self, params: &Json) -> Result<&str, RpcError> {
+        let uri = document_uri(params)?;
+
+        self.documents
+            .get(uri)
+            .map(String::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("`{}` isn't open", uri)))
+    }
+
+    /// Source code of the document that the request is about, along with the
+    /// position in it that it's about.
+    fn document_position(&self, params: &Json) -> Result<(&str, BytePos), RpcError> {
+        let source_code = self.document(params)?;
+        let pos = params
+            .get("position")
+            .and_then(|position| from_position(source_code, position))
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "expected a `position`"))?;
+
+        Ok((source_code, pos))
+    }
+}
+
+fn document_uri(params: &Json) -> Result<&str, RpcError> {
+    params
+        .get("textDocument")
+        .and_then(|document| document.get("uri"))
+        .and_then(Json::as_str)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "expected a `textDocument`"))
+}
+
+/// Applies a change of a document, which replaces either the text in its
+/// range or, if it has none, the whole text.
+fn apply_change(source_code: &mut String, change: &Json) {
+    let Some(new_text) = change.get("text").and_then(Json::as_str) else {
+        return;
+    };
+
+    let range = change.get("range").and_then(|range| {
+        Some(Span {
+            start: from_position(source_code, range.get("start")?)?,
+            end: from_position(source_code, range.get("end")?)?,
+        })
+    });
+
+    let edit = TextEdit {
+        range: range.unwrap_or(Span {
+            start: BytePos(0),
+            end: BytePos(source_code.len()),
+        }),
+        new_text: new_text.to_owned(),
+    };
+
+    source_code.replace_range(edit.range.start.0..edit.range.end.0, &edit.new_text);
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object([
+        ("jsonrpc", Json::from("2.0")),
+        ("method", Json::from("textDocument/publishDiagnostics")),
+        (
+            "params",
+            Json::object([
+                ("uri", Json::from(uri)),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+fn diagnostics_to_json(diagnostic: &Diagnostic, source_code: &str) -> Vec<Json> {
+    let to_json = |severity: i64, code: &str, message: String, span| {
+        Json::object([
+            ("range", to_range(source_code, span)),
+            ("severity", Json::from(severity)),
+            ("code", Json::from(code)),
+            ("source", Json::from("sophia")),
+            ("message", Json::from(message)),
+        ])
+    };
+
+    let errors = diagnostic.errors.iter().map(|error| {
+        to_json(
+            SEVERITY_ERROR,
+            error.code(),
+            error.to_string(),
+            error.span(),
+        )
+    });
+    let warnings = diagnostic.warnings.iter().map(|warning| {
+        to_json(
+            SEVERITY_WARNING,
+            warning.code(),
+            warning.to_string(),
+            warning.span(),
+        )
+    });
+
+    errors.chain(warnings).collect()
+}
+
+/// Occurrence of a name in the code, either where it's defined or where it's
+/// referred to.
+struct Name {
+    span: Span,
+    /// Span of the name where what it refers to is defined, which builtins
+    /// and host functions have none of.
+    def_span: Option<Span>,
+    /// How hovering the name shows it, e.g., `x : i32` for bindings or
+    /// `main :: () -> i32` for declarations.
+    signature: Option<String>,
+}
+
+/// Names of the program, along with where they're defined and their
+/// signatures. Names that don't resolve are left out, but they don't keep the
+/// others from being known.
+fn names(
+    ctx: &CompilerContext,
+    program: Program,
+    source_code: &str,
+    options: &Options,
+) -> Vec<Name> {
+    let decl_indices: Vec<_> = (0..program.decls.len()).collect();
+    let (resolutions, resolve_errors) =
+        Resolver::new(ctx, &options.host_functions).resolve_decls(program, &decl_indices);
+
+    // Type checking expects every name of the declarations it checks to be
+    // resolved.
+    let resolved_decl_indices: Vec<_> = decl_indices
+        .into_iter()
+        .filter(|&decl_idx| {
+            let decl_span = decl_span(&program.decls[decl_idx]);
+
+            !resolve_errors.iter().any(|error| {
+                let span = error.span();
+
+                decl_span.start.0 <= span.start.0 && span.end.0 <= decl_span.end.0
+            })
+        })
+        .collect();
+
+    let inferred_types = Layouts::of_program(ctx, program, options.int_profile.width)
+        .ok()
+        .map(|layouts| {
+            TypeChecker::new(
+                ctx,
+                &resolutions,
+                &layouts,
+                options.int_profile.width,
+                &options.host_functions,
+            )
+            .infer_decl_types(program, &resolved_decl_indices)
+            .1
+        });
+
+    let name_text = |span: Span| &source_code[span.start.0..span.end.0];
+
+    // Names are shown as they are where they're defined.
+    let mut decl_defs = HashMap::new();
+    let mut local_defs = HashMap::new();
+
+    for (decl_idx, decl) in program.decls.iter().enumerate() {
+        let name = name_text(decl.identifier_span);
+        let signature = match decl.value {
+            Expr::Struct(_) => Some(format!("{} :: struct", name)),
+            Expr::Enum(_) => Some(format!("{} :: enum", name)),
+            _ => inferred_types
+                .as_ref()
+                .and_then(|types| types.of(Resolution::Decl(decl_idx)))
+                .map(|ty| format!("{} :: {}", name, ty)),
+        };
+
+        decl_defs.insert(decl_idx, (decl.identifier_span, signature));
+    }
+
+    for (def_span, ty) in inferred_types.iter().flat_map(|types| types.locals()) {
+        let span = name_span(source_code, def_span.start);
+        let signature = format!("{} : {}", name_text(span), ty);
+
+        local_defs.insert(def_span, (span, Some(signature)));
+    }
+
+    let mut names: Vec<_> = decl_defs
+        .values()
+        .chain(local_defs.values())
+        .map(|(span, signature)| Name {
+            span: *span,
+            def_span: Some(*span),
+            signature: signature.clone(),
+        })
+        .collect();
+
+    for (ref_span, resolution) in resolutions.iter() {
+        let span = name_span(source_code, ref_span.start);
+
+        let (def_span, signature) = match resolution {
+            Resolution::Decl(decl_idx) => decl_defs[&decl_idx].clone(),
+            // Without types, bindings aren't known, but where they're defined
+            // still is.
+            Resolution::Local(def_span) => local_defs
+                .get(&def_span)
+                .cloned()
+                .unwrap_or((name_span(source_code, def_span.start), None)),
+            Resolution::Host(_) | Resolution::Builtin(_) => {
+                let signature = inferred_types
+                    .as_ref()
+                    .and_then(|types| types.of(resolution))
+                    .map(|ty| format!("{} :: {}", name_text(span), ty));
+
+                names.push(Name {
+                    span,
+                    def_span: None,
+                    signature,
+                });
+
+                continue;
+            }
+        };
+
+        names.push(Name {
+            span,
+            def_span: Some(def_span),
+            signature,
+        });
+    }
+
+    names
+}
+
+/// Span of the name that starts at the position.
+fn name_span(source_code: &str, start: BytePos) -> Span {
+    Scanner::for_text(&source_code[start.0..], start)
+        .next()
+        .map_or(Span { start, end: start }, |token| token.span)
+}
+
+/// Declarations of the program, without the local bindings of their bodies.
+fn document_symbols(source_code: &str, program: Program) -> Vec<Json> {
+    program
+        .decls
+        .iter()
+        .map(|decl| {
+            let kind = match decl.value {
+                Expr::Function(_) | Expr::Extern(_) => SYMBOL_KIND_FUNCTION,
+                Expr::Struct(_) => SYMBOL_KIND_STRUCT,
+                Expr::Enum(_) => SYMBOL_KIND_ENUM,
+                _ => SYMBOL_KIND_VARIABLE,
+            };
+            let span = decl_span(decl);
+
+            Json::object([
+                (
+                    "name",
+                    Json::from(
+                        &source_code[decl.identifier_span.start.0..decl.identifier_span.end.0],
+                    ),
+                ),
+                ("kind", Json::from(kind)),
+                ("range", to_range(source_code, span)),
+                (
+                    "selectionRange",
+                    to_range(source_code, decl.identifier_span),
+                ),
+            ])
+        })
+        .collect()
+}
+
+/// Span of the declaration's code, `extern` included.
+fn decl_span(decl: &Decl) -> Span {
+    // An extern declaration's value starts before its name, at the `extern`.
+    let value_span = decl.value.span();
+
+    Span {
+        start: BytePos(decl.identifier_span.start.0.min(value_span.start.0)),
+        end: value_span.end,
+    }
+}
+
+fn to_range(source_code: &str, span: Span) -> Json {
+    let line_index = LineIndex::new(source_code);
+
+    Json::object([
+        ("start", to_position(source_code, &line_index, span.start)),
+        ("end", to_position(source_code, &line_index, span.end)),
+    ])
+}
+
+/// Position as the protocol has it, i.e., its line and its character,
+/// counting from zero, where characters are UTF-16 code units.
+fn to_position(source_code: &str, line_index: &LineIndex, pos: BytePos) -> Json {
+    let (line, _) = line_index.line_col(source_code, pos);
+    let line_start = line_index.line_start(line);
+    let character = source_code[line_start.0..pos.0.min(source_code.len())]
+        .encode_utf16()
+        .count();
+
+    Json::object([
+        ("line", Json::from(line.0 - 1)),
+        ("character", Json::from(character)),
+    ])
+}
+
+/// Position in the source code of the protocol's position, which is at the
+/// end of its line if its character is past it, or at the end of the source
+/// code if its line is.
+fn from_position(source_code: &str, position: &Json) -> Option<BytePos> {
+    let line = usize::try_from(position.get("line")?.as_i64()?).ok()?;
+    let character = usize::try_from(position.get("character")?.as_i64()?).ok()?;
+
+    let line_start: usize = source_code
+        .split_inclusive('\n')
+        .take(line)
+        .map(str::len)
+        .sum();
+    let mut utf16_len = 0;
+    let mut offset = line_start;
+
+    for ch in source_code[line_start..].chars() {
+        if ch == '\n' || utf16_len >= character {
+            break;
+        }
+
+        utf16_len += ch.len_utf16();
+        offset += ch.len_utf8();
+    }
+
+    Some(BytePos(offset))
+}
+
+/// Reads a message of the protocol, which is its content's length, in a
+/// header, followed by its content. It's `None` at the end of the input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut content_len = None;
+
+    loop {
+        let mut header = String::new();
+
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(len) = header.strip_prefix("Content-Length:") {
+            content_len = len.trim().parse().ok();
+        }
+    }
+
+    let Some(content_len) = content_len else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message without a `Content-Length`",
+        ));
+    };
+
+    let mut content = vec![0; content_len];
+    input.read_exact(&mut content)?;
+
+    String::from_utf8(content)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let content = message.to_string();
+
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
@@ -1,18 +1,5 @@
-#![feature(hash_raw_entry, hasher_prefixfree_extras)]
+use std::process::ExitCode;
 
-use crate::driver::compile;
-
-mod ast;
-mod codegen;
-mod compiler_context;
-mod driver;
-mod interner;
-mod parser;
-mod scanner;
-
-#[cfg(test)]
-mod tests;
-
-fn main() {
-    let _ = compile("main :: () {}");
+fn main() -> ExitCode {
+    sophia::cli::main()
 }
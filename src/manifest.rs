@@ -0,0 +1,139 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::json::Json;
+
+/// Version of the manifest's format, bumped whenever it changes in a way that
+/// readers would notice.
+const MANIFEST_VERSION: i64 = 1;
+
+/// Record of the artifacts a build produced and of the source files they were
+/// produced from, so that external build systems and caching layers can track
+/// them precisely instead of guessing.
+#[derive(Default)]
+pub(crate) struct Manifest {
+    sources: Vec<FileEntry>,
+    artifacts: Vec<(ArtifactKind, FileEntry)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum ArtifactKind {
+    Assembly,
+    LlvmIr,
+    Wat,
+    C,
+    Ir,
+    Cfg,
+    Source,
+    Tokens,
+    Ast,
+    Cst,
+    Bytecode,
+    Executable,
+    Object,
+}
+
+impl fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArtifactKind::Assembly => write!(f, "assembly"),
+            ArtifactKind::LlvmIr => write!(f, "llvm-ir"),
+            ArtifactKind::Wat => write!(f, "wat"),
+            ArtifactKind::C => write!(f, "c"),
+            ArtifactKind::Ir => write!(f, "ir"),
+            ArtifactKind::Cfg => write!(f, "cfg"),
+            ArtifactKind::Source => write!(f, "source"),
+            ArtifactKind::Tokens => write!(f, "tokens"),
+            ArtifactKind::Ast => write!(f, "ast"),
+            ArtifactKind::Cst => write!(f, "cst"),
+            ArtifactKind::Bytecode => write!(f, "bytecode"),
+            ArtifactKind::Executable => write!(f, "executable"),
+            ArtifactKind::Object => write!(f, "object"),
+        }
+    }
+}
+
+struct FileEntry {
+    path: PathBuf,
+    size: usize,
+    hash: ContentHash,
+}
+
+impl FileEntry {
+    fn new(path: &Path, contents: &[u8]) -> FileEntry {
+        FileEntry {
+            path: path.to_owned(),
+            size: contents.len(),
+            hash: ContentHash::of(contents),
+        }
+    }
+
+    fn to_json(&self) -> Vec<(&'static str, Json)> {
+        vec![
+            ("path", Json::from(self.path.display().to_string())),
+            ("size", Json::from(self.size)),
+            ("hash", Json::from(self.hash.to_string())),
+        ]
+    }
+}
+
+/// 64-bit FNV-1a hash of a file's contents.
+///
+/// It's not meant to resist tampering, only to tell apart different versions
+/// of a file cheaply and the same way on every platform.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct ContentHash(pub(crate) u64);
+
+impl ContentHash {
+    pub(crate) fn of(contents: &[u8]) -> ContentHash {
+        const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+
+        let hash = contents.iter().fold(OFFSET_BASIS, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        });
+
+        ContentHash(hash)
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fnv1a64:{:016x}", self.0)
+    }
+}
+
+impl Manifest {
+    pub(crate) fn add_source(&mut self, path: &Path, contents: &[u8]) {
+        self.sources.push(FileEntry::new(path, contents));
+    }
+
+    pub(crate) fn add_artifact(&mut self, kind: ArtifactKind, path: &Path, contents: &[u8]) {
+        self.artifacts.push((kind, FileEntry::new(path, contents)));
+    }
+
+    pub(crate) fn to_json(&self) -> Json {
+        let sources = self
+            .sources
+            .iter()
+            .map(|source| Json::object(source.to_json()))
+            .collect();
+
+        let artifacts = self
+            .artifacts
+            .iter()
+            .map(|(kind, artifact)| {
+                let mut members = vec![("kind", Json::from(kind.to_string()))];
+                members.extend(artifact.to_json());
+
+                Json::object(members)
+            })
+            .collect();
+
+        Json::object([
+            ("version", Json::from(MANIFEST_VERSION)),
+            ("sources", Json::Array(sources)),
+            ("artifacts", Json::Array(artifacts)),
+        ])
+    }
+}
@@ -0,0 +1,306 @@
+/// Relocatable object file of the machine code of a program, which the system's
+/// linker links with the runtime library into an executable.
+///
+/// Only ELF for x86-64 is written, as the native backend only generates code
+/// for Linux.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub(crate) struct ObjectFile {
+    /// Machine code of every function, which goes to the `.text` section.
+    pub(crate) text: Vec<u8>,
+    /// Functions that the code defines, in the order of their code.
+    pub(crate) functions: Vec<FunctionSymbol>,
+    /// Calls to functions that the code doesn't define, e.g., the runtime
+    /// library's, which the linker resolves.
+    pub(crate) relocations: Vec<Relocation>,
+}
+
+/// Function that's visible to the linker.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct FunctionSymbol {
+    pub(crate) name: String,
+    /// Offset of the function's first instruction in the code.
+    pub(crate) offset: usize,
+    /// Size of the function's code, in bytes.
+    pub(crate) size: usize,
+}
+
+/// Call to a function that's defined elsewhere, whose 32-bit displacement
+/// from the next instruction is filled in by the linker.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Relocation {
+    /// Offset of the displacement in the code.
+    pub(crate) offset: usize,
+    pub(crate) symbol: String,
+}
+
+const HEADER_BYTES: usize = 64;
+const SECTION_HEADER_BYTES: usize = 64;
+const SYMBOL_BYTES: usize = 24;
+const RELOCATION_BYTES: usize = 24;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+const SHF_INFO_LINK: u64 = 0x40;
+
+const STB_GLOBAL: u8 = 1;
+const STT_NOTYPE: u8 = 0;
+const STT_FUNC: u8 = 2;
+
+/// Relocation of a call through the procedure linkage table, which the linker
+/// turns into a direct call if the function is linked statically.
+const R_X86_64_PLT32: u64 = 4;
+
+/// Indices of the sections that are written, after the null section.
+const TEXT_SECTION: u16 = 1;
+const SYMTAB_SECTION: u32 = 4;
+const STRTAB_SECTION: u32 = 5;
+const SHSTRTAB_SECTION: u16 = 6;
+const SECTION_COUNT: u16 = 7;
+
+struct Section {
+    /// Offset of the name in the section names' string table.
+    name: u32,
+    kind: u32,
+    flags: u64,
+    contents: Vec<u8>,
+    link: u32,
+    info: u32,
+    align: usize,
+    entry_size: usize,
+}
+
+impl ObjectFile {
+    /// Writes the object file in the ELF format, with a symbol table of every
+    /// function and of the functions called from elsewhere.
+    pub(crate) fn to_elf(&self) -> Vec<u8> {
+        let mut strtab = StringTable::default();
+        // The null symbol comes first, and there are no local symbols after it.
+        let mut symtab = vec![0; SYMBOL_BYTES];
+        let mut symbol_indices = vec![];
+
+        for function in &self.functions {
+            write_symbol(
+                &mut symtab,
+                strtab.add(&function.name),
+                STT_FUNC,
+                TEXT_SECTION,
+                function.offset,
+                function.size,
+            );
+            symbol_indices.push(function.name.as_str());
+        }
+
+        let mut rela = vec![];
+
+        for relocation in &self.relocations {
+            let index = match symbol_indices
+                .iter()
+                .position(|&name| name == relocation.symbol)
+            {
+                Some(index) => index,
+                None => {
+                    // Functions that aren't defined here are undefined symbols.
+                    write_symbol(
+                        &mut symtab,
+                        strtab.add(&relocation.symbol),
+                        STT_NOTYPE,
+                        0,
+                        0,
+                        0,
+                    );
+                    symbol_indices.push(&relocation.symbol);
+                    symbol_indices.len() - 1
+                }
+            };
+
+            // The displacement is from the end of the call, which is 4 bytes
+            // past the start of the displacement.
+            push_u64(&mut rela, relocation.offset as u64);
+            push_u64(&mut rela, ((index as u64 + 1) << 32) | R_X86_64_PLT32);
+            push_u64(&mut rela, -4i64 as u64);
+        }
+
+        let mut shstrtab = StringTable::default();
+        let mut sections = vec![
+            Section {
+                name: shstrtab.add(".text"),
+                kind: SHT_PROGBITS,
+                flags: SHF_ALLOC | SHF_EXECINSTR,
+                contents: self.text.clone(),
+                link: 0,
+                info: 0,
+                align: 16,
+                entry_size: 0,
+            },
+            Section {
+                name: shstrtab.add(".rela.text"),
+                kind: SHT_RELA,
+                flags: SHF_INFO_LINK,
+                contents: rela,
+                link: SYMTAB_SECTION,
+                info: TEXT_SECTION as u32,
+                align: 8,
+                entry_size: RELOCATION_BYTES,
+            },
+            // Tells the linker that the stack doesn't need to be executable.
+            Section {
+                name: shstrtab.add(".note.GNU-stack"),
+                kind: SHT_PROGBITS,
+                flags: 0,
+                contents: vec![],
+                link: 0,
+                info: 0,
+                align: 1,
+                entry_size: 0,
+            },
+            Section {
+                name: shstrtab.add(".symtab"),
+                kind: SHT_SYMTAB,
+                flags: 0,
+                contents: symtab,
+                link: STRTAB_SECTION,
+                // Index of the first global symbol.
+                info: 1,
+                align: 8,
+                entry_size: SYMBOL_BYTES,
+            },
+            Section {
+                name: shstrtab.add(".strtab"),
+                kind: SHT_STRTAB,
+                flags: 0,
+                contents: strtab.bytes,
+                link: 0,
+                info: 0,
+                align: 1,
+                entry_size: 0,
+            },
+        ];
+
+        // The names of the sections are complete once this one's is in.
+        let name = shstrtab.add(".shstrtab");
+        sections.push(Section {
+            name,
+            kind: SHT_STRTAB,
+            flags: 0,
+            contents: shstrtab.bytes,
+            link: 0,
+            info: 0,
+            align: 1,
+            entry_size: 0,
+        });
+
+        let mut elf = vec![0; HEADER_BYTES];
+        let mut section_offsets = vec![];
+
+        for section in &sections {
+            pad_to(&mut elf, section.align);
+            section_offsets.push(elf.len());
+            elf.extend_from_slice(&section.contents);
+        }
+
+        pad_to(&mut elf, 8);
+        let section_headers_offset = elf.len();
+
+        // The null section comes first.
+        elf.extend_from_slice(&[0; SECTION_HEADER_BYTES]);
+
+        for (section, offset) in sections.iter().zip(section_offsets) {
+            push_u32(&mut elf, section.name);
+            push_u32(&mut elf, section.kind);
+            push_u64(&mut elf, section.flags);
+            push_u64(&mut elf, 0);
+            push_u64(&mut elf, offset as u64);
+            push_u64(&mut elf, section.contents.len() as u64);
+            push_u32(&mut elf, section.link);
+            push_u32(&mut elf, section.info);
+            push_u64(&mut elf, section.align as u64);
+            push_u64(&mut elf, section.entry_size as u64);
+        }
+
+        let mut header = vec![];
+        // Magic number, then 64 bits, little-endian, version 1 and the System V ABI.
+        header.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+        header.extend_from_slice(&[0; 8]);
+        // Relocatable file for x86-64, of version 1, without an entry point
+        // nor program headers.
+        push_u16(&mut header, 1);
+        push_u16(&mut header, 62);
+        push_u32(&mut header, 1);
+        push_u64(&mut header, 0);
+        push_u64(&mut header, 0);
+        push_u64(&mut header, section_headers_offset as u64);
+        push_u32(&mut header, 0);
+        push_u16(&mut header, HEADER_BYTES as u16);
+        push_u16(&mut header, 0);
+        push_u16(&mut header, 0);
+        push_u16(&mut header, SECTION_HEADER_BYTES as u16);
+        push_u16(&mut header, SECTION_COUNT);
+        push_u16(&mut header, SHSTRTAB_SECTION);
+
+        elf[..HEADER_BYTES].copy_from_slice(&header);
+
+        elf
+    }
+}
+
+/// Names of symbols or sections, each ending with a null byte, which are
+/// referred to by their offset.
+struct StringTable {
+    bytes: Vec<u8>,
+}
+
+impl Default for StringTable {
+    fn default() -> StringTable {
+        // The empty name is at offset zero.
+        StringTable { bytes: vec![0] }
+    }
+}
+
+impl StringTable {
+    fn add(&mut self, name: &str) -> u32 {
+        let offset = self.bytes.len() as u32;
+        self.bytes.extend_from_slice(name.as_bytes());
+        self.bytes.push(0);
+
+        offset
+    }
+}
+
+fn write_symbol(
+    symtab: &mut Vec<u8>,
+    name: u32,
+    kind: u8,
+    section: u16,
+    value: usize,
+    size: usize,
+) {
+    push_u32(symtab, name);
+    symtab.push((STB_GLOBAL << 4) | kind);
+    symtab.push(0);
+    push_u16(symtab, section);
+    push_u64(symtab, value as u64);
+    push_u64(symtab, size as u64);
+}
+
+fn pad_to(bytes: &mut Vec<u8>, align: usize) {
+    while !bytes.len().is_multiple_of(align) {
+        bytes.push(0);
+    }
+}
+
+fn push_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u64(bytes: &mut Vec<u8>, value: u64) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
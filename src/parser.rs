@@ -1,305 +1,1928 @@
+use std::mem;
+
 use crate::ast::*;
 use crate::compiler_context::CompilerContext;
-use crate::scanner::{Delim, Keyword, Token, TokenKind};
-
-pub(crate) struct Parser<'ctx> {
+use crate::diagnostics::{CompileError, Diagnostic};
+use crate::limits::Limits;
+use crate::scanner::{BytePos, Delim, Keyword, Scanner, Span, Token, TokenKind};
+use crate::suggest::is_typo_of;
+
+/// Tokens that an expression can start with.
+const EXPR_START_TOKENS: [TokenKind; 12] = [
+    TokenKind::IntegerConstant,
+    TokenKind::Identifier,
+    TokenKind::Open(Delim::Paren),
+    TokenKind::Open(Delim::Bracket),
+    TokenKind::Open(Delim::Curly),
+    TokenKind::Keyword(Keyword::If),
+    TokenKind::Keyword(Keyword::Match),
+    TokenKind::Keyword(Keyword::For),
+    TokenKind::Keyword(Keyword::Loop),
+    TokenKind::Keyword(Keyword::Break),
+    TokenKind::Keyword(Keyword::Continue),
+    TokenKind::Hash,
+];
+
+/// Most tokens that the parser looks at before consuming them, i.e., the
+/// next one and the one after it.
+const MAX_LOOKAHEAD: usize = 2;
+
+const BINARY_OP_TOKENS: [TokenKind; 5] = [
+    TokenKind::Plus,
+    TokenKind::Dash,
+    TokenKind::Star,
+    TokenKind::Slash,
+    TokenKind::Percent,
+];
+
+pub(crate) struct Parser<'ctx, 'src> {
     ctx: &'ctx CompilerContext,
-    tokens: Vec<Token>,
-    current_token_idx: usize,
+    scanner: Scanner<'src>,
+    /// Tokens scanned ahead of the current one, which are only as many as the
+    /// parser has looked ahead, as the scanner is pulled from on demand.
+    lookahead_tokens: Lookahead,
+    prev_token: Option<Token>,
+    /// Kinds of tokens that the parser checked the next token for since it
+    /// consumed the previous one, which are what it expected if the next
+    /// token is none of them.
+    expected_tokens: Vec<TokenKind>,
+    /// Opening delimiters that haven't been closed yet, innermost last.
+    open_delims: Vec<Token>,
+    /// Whether a name followed by `{` starts a struct literal, which it doesn't
+    /// in the condition of an if, for or match expression, as the `{` starts
+    /// the expression's block there, unless it's in delimiters of its own.
+    struct_lits_allowed: bool,
+    limits: Limits,
+    nesting_depth: usize,
+    /// Limit that stopped the parser, if any.
+    limit_error: Option<CompileError>,
+    /// Token that stopped the parser, as it's none of the expected ones, if
+    /// any.
+    syntax_error: Option<CompileError>,
+    /// Errors that the parser went on after, parsing the code as if it was
+    /// fixed, e.g., misspelled keywords, in the order they were found.
+    recovered_errors: Vec<CompileError>,
 }
 
-impl<'ctx> Parser<'ctx> {
-    pub(crate) fn new(tokens: Vec<Token>, ctx: &'ctx CompilerContext) -> Parser {
+impl<'ctx, 'src> Parser<'ctx, 'src> {
+    pub(crate) fn new(
+        scanner: Scanner<'src>,
+        ctx: &'ctx CompilerContext,
+        limits: Limits,
+    ) -> Parser<'ctx, 'src> {
         Parser {
             ctx,
-            tokens,
-            current_token_idx: 0,
+            scanner,
+            lookahead_tokens: Lookahead::default(),
+            prev_token: None,
+            expected_tokens: vec![],
+            open_delims: vec![],
+            struct_lits_allowed: true,
+            limits,
+            nesting_depth: 0,
+            limit_error: None,
+            syntax_error: None,
+            recovered_errors: vec![],
         }
     }
 
-    pub(crate) fn parse_program(&mut self) -> Option<Program> {
+    pub(crate) fn parse_program(&mut self) -> Result<Program<'ctx>, Diagnostic> {
         let mut decls = vec![];
 
-        while let Some(decl) = self.parse_decl() {
+        while self.peek().is_some() {
+            let Some(decl) = self.parse_decl() else {
+                break;
+            };
+
             decls.push(decl);
+
+            if self.check_ast_size(decl.identifier_span).is_none() {
+                break;
+            }
+        }
+
+        self.finish_program(decls, Diagnostic::default())
+    }
+
+    /// Parses a script, which may contain statements in between declarations.
+    /// Those statements are gathered, in order, into an implicit `main`
+    /// function.
+    pub(crate) fn parse_script(&mut self) -> Result<Program<'ctx>, Diagnostic> {
+        let (mut decls, stmts) = self.parse_script_items();
+
+        let mut diagnostic = Diagnostic::default();
+
+        if let (Some(first_stmt), Some(last_stmt)) = (stmts.first(), stmts.last()) {
+            let main_identifier = self.ctx.symbols().main;
+            let stmts_span = first_stmt.span().to(last_stmt.span());
+
+            if let Some(main_decl) = decls.iter().find(|decl| decl.identifier == main_identifier) {
+                diagnostic
+                    .errors
+                    .push(CompileError::ScriptStatementsWithMain {
+                        main_span: main_decl.identifier_span,
+                        stmt_span: first_stmt.span(),
+                    });
+            }
+
+            let body = CompoundExpr {
+                exprs: self.ctx.alloc_slice_of_expr(&stmts),
+                span: stmts_span,
+            };
+
+            decls.push(Decl {
+                identifier: main_identifier,
+                identifier_span: first_stmt.span(),
+                value: self.ctx.alloc_expr(Expr::Function(Function {
+                    return_type: Type::Unit,
+                    parameters: self.ctx.alloc_slice_of_param(&[]),
+                    body,
+                    span: stmts_span,
+                })),
+            });
+        }
+
+        self.finish_program(decls, diagnostic)
+    }
+
+    /// Parses an input of a REPL, which is a script whose statements are kept
+    /// apart from its declarations, as the REPL runs them itself rather than
+    /// as a `main` function.
+    pub(crate) fn parse_repl_input(
+        &mut self,
+    ) -> Result<(Program<'ctx>, &'ctx [Expr<'ctx>]), Diagnostic> {
+        let (decls, stmts) = self.parse_script_items();
+        let program = self.finish_program(decls, Diagnostic::default())?;
+
+        Ok((program, self.ctx.alloc_slice_of_expr(&stmts)))
+    }
+
+    /// Declarations and statements of a script, each in the order they're in.
+    fn parse_script_items(&mut self) -> (Vec<Decl<'ctx>>, Vec<Expr<'ctx>>) {
+        let mut decls = vec![];
+        let mut stmts = vec![];
+
+        while let Some(tok) = self.peek() {
+            let is_decl = tok.kind == TokenKind::Keyword(Keyword::Extern)
+                || tok.kind == TokenKind::Identifier
+                    && self.look_ahead_kind(1) == Some(TokenKind::ColonColon);
+
+            if is_decl {
+                let Some(decl) = self.parse_decl() else {
+                    break;
+                };
+
+                decls.push(decl);
+            } else {
+                let Some(stmt) = self.parse_expr() else {
+                    break;
+                };
+
+                stmts.push(stmt);
+            }
+
+            if self.check_ast_size(tok.span).is_none() {
+                break;
+            }
+        }
+
+        (decls, stmts)
+    }
+
+    /// Parses the code as a single expression, e.g., one typed into a REPL,
+    /// which may be followed by a `;`.
+    pub(crate) fn parse_expr_fragment(&mut self) -> Result<Expr<'ctx>, Diagnostic> {
+        let expr = self.parse_expr();
+
+        self.finish_fragment(expr)
+    }
+
+    /// Parses the code as a single declaration, e.g., one in an example of
+    /// the documentation.
+    pub(crate) fn parse_decl_fragment(&mut self) -> Result<Decl<'ctx>, Diagnostic> {
+        let decl = self.parse_decl();
+
+        self.finish_fragment(decl)
+    }
+
+    /// Fails if there's code after the fragment, as it was to be all of it.
+    fn finish_fragment<T>(&mut self, fragment: Option<T>) -> Result<T, Diagnostic> {
+        if fragment.is_some() && self.peek().is_some() {
+            self.fail_on_unexpected_token::<()>();
+        }
+
+        let mut diagnostic = Diagnostic::default();
+        self.report_errors(&mut diagnostic);
+
+        match fragment {
+            Some(fragment) if !diagnostic.has_errors() => Ok(fragment),
+            _ => Err(diagnostic),
+        }
+    }
+
+    fn finish_program(
+        &mut self,
+        decls: Vec<Decl<'ctx>>,
+        mut diagnostic: Diagnostic,
+    ) -> Result<Program<'ctx>, Diagnostic> {
+        self.report_errors(&mut diagnostic);
+
+        if diagnostic.has_errors() {
+            return Err(diagnostic);
         }
 
-        Some(Program {
+        Ok(Program {
             decls: self.ctx.alloc_slice_of_decl(&decls),
         })
     }
 
+    /// Reports the errors that the parser recovered from, and why it stopped,
+    /// if it stopped early.
+    fn report_errors(&mut self, diagnostic: &mut Diagnostic) {
+        diagnostic.errors.append(&mut self.recovered_errors);
+
+        if let Some(limit_error) = self.limit_error.take() {
+            diagnostic.errors.push(limit_error);
+        } else if self.peek().is_none() && !self.open_delims.is_empty() {
+            // The code ended before the delimiters were closed, which is what
+            // people are to fix, rather than the tokens expected at its end.
+            let eof_pos = self.scanner.end_pos();
+            let eof_span = Span {
+                start: eof_pos,
+                end: eof_pos,
+            };
+
+            for open_tok in &self.open_delims {
+                let TokenKind::Open(delim) = open_tok.kind else {
+                    unreachable!("only opening delimiters are tracked");
+                };
+
+                diagnostic.errors.push(CompileError::UnclosedDelimiter {
+                    delim,
+                    open_span: open_tok.span,
+                    eof_span,
+                });
+            }
+        } else if let Some(syntax_error) = self.syntax_error.take() {
+            diagnostic.errors.push(syntax_error);
+        }
+    }
+
     fn parse_decl(&mut self) -> Option<Decl<'ctx>> {
-        let ident_tok = self.consume()?;
-        debug_assert_eq!(ident_tok.kind, TokenKind::Identifier);
+        if let Some(extern_kw_tok) = self.eat(TokenKind::Keyword(Keyword::Extern)) {
+            return self.parse_extern_decl(extern_kw_tok);
+        }
+
+        let ident_tok = self.expect(TokenKind::Identifier)?;
+        self.expect(TokenKind::ColonColon)?;
+
+        // Structs and enums are declared at the top level only, so they're not
+        // among the expressions.
+        let expr = if let Some(struct_kw_tok) = self.eat(TokenKind::Keyword(Keyword::Struct)) {
+            self.parse_struct_def(struct_kw_tok)?
+        } else if let Some(enum_kw_tok) = self.eat(TokenKind::Keyword(Keyword::Enum)) {
+            self.parse_enum_def(enum_kw_tok)?
+        } else if let Some(type_def) =
+            self.parse_misspelled_keyword(&[Keyword::Struct, Keyword::Enum], |parser, kw_tok| {
+                match kw_tok.kind {
+                    TokenKind::Keyword(Keyword::Struct) => parser.parse_struct_def(kw_tok),
+                    _ => parser.parse_enum_def(kw_tok),
+                }
+            })
+        {
+            type_def
+        } else {
+            let expr = self.parse_statement_expr()?;
 
-        let op = self.consume()?;
-        debug_assert_eq!(op.kind, TokenKind::ColonColon);
+            if !matches!(expr, Expr::Function(_)) {
+                self.recovered_errors
+                    .push(CompileError::InvalidDeclValue { span: expr.span() });
+            }
 
-        let expr = self.parse_statement_expr()?;
+            expr
+        };
 
-        let identifier = self.ctx.get_or_intern_str(
-            &self.ctx.get_source_code()[ident_tok.span.start.0..ident_tok.span.end.0],
-        );
+        let identifier = self
+            .ctx
+            .get_or_intern_str(self.scanner.token_text(ident_tok.span));
 
         Some(Decl {
             identifier,
+            identifier_span: ident_tok.span,
             value: self.ctx.alloc_expr(expr),
         })
     }
 
+    /// Parses the declaration of an extern function, which has a signature but
+    /// no body, as its code is linked in from elsewhere.
+    fn parse_extern_decl(&mut self, extern_kw_tok: Token) -> Option<Decl<'ctx>> {
+        debug_assert_eq!(extern_kw_tok.kind, TokenKind::Keyword(Keyword::Extern));
+
+        let ident_tok = self.expect(TokenKind::Identifier)?;
+        self.expect(TokenKind::ColonColon)?;
+
+        let open_paren_tok = self.expect(TokenKind::Open(Delim::Paren))?;
+        let params = self.parse_params(open_paren_tok)?;
+        let return_type = self.parse_return_type()?;
+        let semi_tok = self.expect(TokenKind::Semi)?;
+
+        let identifier = self
+            .ctx
+            .get_or_intern_str(self.scanner.token_text(ident_tok.span));
+
+        Some(Decl {
+            identifier,
+            identifier_span: ident_tok.span,
+            value: self.ctx.alloc_expr(Expr::Extern(ExternFn {
+                return_type,
+                parameters: self.ctx.alloc_slice_of_param(&params),
+                span: extern_kw_tok.span.to(semi_tok.span),
+            })),
+        })
+    }
+
     fn parse_statement_expr(&mut self) -> Option<Expr<'ctx>> {
-        let tok = self.consume()?;
+        let expr = self.parse_binary_expr(0)?;
 
-        match tok.kind {
-            TokenKind::IntegerConstant => {
-                let expr = Expr::Const(Const::IntegerConstant {
-                    value: self.ctx.get_source_code()[tok.span.start.0..tok.span.end.0]
-                        .parse::<i32>()
-                        .unwrap(),
-                });
+        // Elements of arrays are the only places that can be assigned to,
+        // besides `_`.
+        match expr {
+            Expr::Index(target) if self.check(TokenKind::Equal) => {
+                self.parse_index_assign_expr(target)
+            }
+            _ => Some(expr),
+        }
+    }
+
+    fn parse_index_assign_expr(&mut self, target: IndexExpr<'ctx>) -> Option<Expr<'ctx>> {
+        self.expect(TokenKind::Equal)?;
+        let value = self.parse_statement_expr()?;
+
+        Some(Expr::IndexAssign(IndexAssignExpr {
+            target,
+            value: self.ctx.alloc_expr(value),
+            span: target.span.to(value.span()),
+        }))
+    }
+
+    /// Parses a chain of left-associative binary operations whose operators
+    /// have a precedence of at least `min_precedence`.
+    fn parse_binary_expr(&mut self, min_precedence: u8) -> Option<Expr<'ctx>> {
+        let nesting_depth = self.nesting_depth;
+        // Casts are parsed once their operand is, rather than by a function
+        // that parses the operand too, which would take another stack frame
+        // per level of nesting.
+        let lhs = self.parse_postfix_expr()?;
+        let mut lhs = self.parse_casts(lhs)?;
+
+        while let Some(op) = self.check_one_of(&BINARY_OP_TOKENS).and_then(binary_op) {
+            if op.precedence() < min_precedence {
+                break;
+            }
+
+            // The chain parsed so far isn't nested any deeper by the
+            // operation, as passes walk chains without recursing into them.
+            self.consume()?;
+
+            let rhs = self.parse_binary_expr(op.precedence() + 1)?;
+
+            lhs = Expr::Binary(BinaryExpr {
+                op,
+                lhs: self.ctx.alloc_expr(lhs),
+                rhs: self.ctx.alloc_expr(rhs),
+                span: lhs.span().to(rhs.span()),
+            });
+        }
+
+        self.nesting_depth = nesting_depth;
+
+        Some(lhs)
+    }
+
+    /// Parses the types that the expression is cast to, if any, as casts
+    /// bind tighter than binary operators do.
+    fn parse_casts(&mut self, mut expr: Expr<'ctx>) -> Option<Expr<'ctx>> {
+        while let Some(as_kw_tok) = self.eat(TokenKind::Keyword(Keyword::As)) {
+            // Each cast nests the expression cast so far one level deeper.
+            self.enter_nesting(as_kw_tok.span)?;
+
+            let ty = self.parse_type()?;
+
+            expr = Expr::Cast(CastExpr {
+                expr: self.ctx.alloc_expr(expr),
+                ty,
+                span: expr.span().to(self.prev_tok_span()),
+            });
+        }
+
+        Some(expr)
+    }
+
+    /// Parses a primary expression followed by the fields and elements
+    /// accessed on it, if any.
+    fn parse_postfix_expr(&mut self) -> Option<Expr<'ctx>> {
+        let nesting_depth = self.nesting_depth;
+        let mut expr = self.parse_primary_expr()?;
+
+        while let Some(tok) = self
+            .eat(TokenKind::Period)
+            .or_else(|| self.eat(TokenKind::Open(Delim::Bracket)))
+        {
+            // Each access nests the expression accessed so far one level deeper.
+            self.enter_nesting(tok.span)?;
+
+            if tok.kind == TokenKind::Open(Delim::Bracket) {
+                expr = self.parse_index_expr(expr, tok)?;
+
+                continue;
+            }
+
+            let field_tok =
+                self.expect_one_of(&[TokenKind::Identifier, TokenKind::IntegerConstant])?;
+
+            expr = if field_tok.kind == TokenKind::IntegerConstant {
+                // Indices too large for any tuple are reported as missing
+                // fields by the type checker.
+                let index = self
+                    .scanner
+                    .token_text(field_tok.span)
+                    .parse()
+                    .unwrap_or(usize::MAX);
+
+                Expr::TupleField(TupleFieldExpr {
+                    base: self.ctx.alloc_expr(expr),
+                    index,
+                    index_span: field_tok.span,
+                    span: expr.span().to(field_tok.span),
+                })
+            } else {
+                let identifier = self
+                    .ctx
+                    .get_or_intern_str(self.scanner.token_text(field_tok.span));
+
+                Expr::Field(FieldExpr {
+                    base: self.ctx.alloc_expr(expr),
+                    identifier,
+                    identifier_span: field_tok.span,
+                    span: expr.span().to(field_tok.span),
+                })
+            };
+        }
+
+        self.nesting_depth = nesting_depth;
+
+        Some(expr)
+    }
+
+    fn parse_index_expr(
+        &mut self,
+        base: Expr<'ctx>,
+        open_bracket_tok: Token,
+    ) -> Option<Expr<'ctx>> {
+        self.open_delim(open_bracket_tok);
+
+        let index = self.with_struct_lits(true, Parser::parse_statement_expr)?;
+        let close_bracket_tok = self.close_delim(Delim::Bracket)?;
+
+        Some(Expr::Index(IndexExpr {
+            base: self.ctx.alloc_expr(base),
+            index: self.ctx.alloc_expr(index),
+            span: base.span().to(close_bracket_tok.span),
+        }))
+    }
+
+    fn parse_primary_expr(&mut self) -> Option<Expr<'ctx>> {
+        // A name followed by another name or by a constant would be an
+        // expression of its own followed by another one, which is rarely
+        // meant, unless it's a keyword followed by its condition, as in
+        // `fro i : 0..10 { ... }`.
+        let may_be_keyword = matches!(
+            self.look_ahead_kind(1),
+            Some(TokenKind::Identifier | TokenKind::IntegerConstant)
+        );
+
+        if may_be_keyword {
+            let keyword_expr = self.parse_misspelled_keyword(
+                &[Keyword::If, Keyword::Match, Keyword::For],
+                Parser::parse_nested_primary_expr_after,
+            );
 
-                Some(expr)
+            if keyword_expr.is_some() {
+                return keyword_expr;
             }
-            TokenKind::Keyword(Keyword::If) => self.parse_if_expr(),
-            TokenKind::Keyword(Keyword::For) => self.parse_for_expr(),
-            TokenKind::Keyword(Keyword::Break) => self.parse_break_expr(),
-            TokenKind::Keyword(Keyword::Continue) => self.parse_continue_expr(),
-            TokenKind::Open(Delim::Paren) => self.parse_function(),
+        }
+
+        let tok = self.expect_one_of(&EXPR_START_TOKENS)?;
+
+        self.parse_nested_primary_expr_after(tok)
+    }
+
+    /// Parses the primary expression that starts with the token, which is
+    /// already consumed, one level of nesting deeper.
+    fn parse_nested_primary_expr_after(&mut self, tok: Token) -> Option<Expr<'ctx>> {
+        self.enter_nesting(tok.span)?;
+        let expr = self.parse_primary_expr_after(tok);
+        self.nesting_depth -= 1;
+
+        expr
+    }
+
+    fn parse_primary_expr_after(&mut self, tok: Token) -> Option<Expr<'ctx>> {
+        match tok.kind {
+            TokenKind::IntegerConstant => Some(self.parse_integer_constant(tok)),
+            TokenKind::Keyword(Keyword::If) => self.parse_if_expr(tok),
+            TokenKind::Keyword(Keyword::Match) => self.parse_match_expr(tok),
+            TokenKind::Keyword(Keyword::For) => self.parse_for_expr(tok, false),
+            TokenKind::Keyword(Keyword::Loop) => self.parse_loop_expr(tok, false),
+            TokenKind::Keyword(Keyword::Break) => self.parse_break_expr(tok),
+            TokenKind::Keyword(Keyword::Continue) => self.parse_continue_expr(tok),
+            TokenKind::Open(Delim::Paren) => {
+                let next_kinds = (self.look_ahead_kind(0), self.look_ahead_kind(1));
+
+                let is_function = matches!(
+                    next_kinds,
+                    (Some(TokenKind::Closed(Delim::Paren)), _)
+                        | (Some(TokenKind::Identifier), Some(TokenKind::Colon))
+                        | (None, _)
+                );
+
+                if is_function {
+                    self.parse_function(tok)
+                } else {
+                    self.parse_paren_expr(tok)
+                }
+            }
+            TokenKind::Open(Delim::Bracket) => self.parse_array_expr(tok),
             TokenKind::Open(Delim::Curly) => self.parse_compound_expr(tok).map(Expr::Compound),
+            TokenKind::Hash => self.parse_main_loop_attribute(tok),
             TokenKind::Identifier => {
-                if self.peek()?.kind == TokenKind::ColonEqual {
+                let is_discard = self.scanner.token_text(tok.span) == DISCARD_IDENTIFIER;
+
+                // Discarding a value can be spelled either as a binding definition or as an
+                // assignment, as both mean the same for `_`.
+                if self.check(TokenKind::ColonEqual) || (is_discard && self.check(TokenKind::Equal))
+                {
                     self.consume()?;
                     let value = self.parse_statement_expr()?;
 
-                    let identifier = self.ctx.get_or_intern_str(
-                        &self.ctx.get_source_code()[tok.span.start.0..tok.span.end.0],
-                    );
+                    let identifier = self
+                        .ctx
+                        .get_or_intern_str(self.scanner.token_text(tok.span));
 
                     Some(Expr::BindDef(BindDef {
                         identifier,
+                        ty: None,
                         value: self.ctx.alloc_expr(value),
+                        span: tok.span.to(value.span()),
                     }))
-                } else if self.peek()?.kind == TokenKind::Open(Delim::Paren) {
-                    self.consume()?;
+                } else if self.check(TokenKind::Colon) {
+                    self.parse_typed_bind_def(tok)
+                } else if self.check(TokenKind::Open(Delim::Paren)) {
+                    let open_paren_tok = self.consume()?;
+                    self.open_delim(open_paren_tok);
+
+                    let mut args = vec![];
+
+                    while !self.check(TokenKind::Closed(Delim::Paren)) {
+                        args.push(self.with_struct_lits(true, Parser::parse_statement_expr)?);
 
-                    let close_paren_tok = self.consume()?;
-                    debug_assert_eq!(close_paren_tok.kind, TokenKind::Closed(Delim::Paren));
+                        if self.eat(TokenKind::Comma).is_none() {
+                            break;
+                        }
+                    }
 
-                    let identifier = self.ctx.get_or_intern_str(
-                        &self.ctx.get_source_code()[tok.span.start.0..tok.span.end.0],
-                    );
+                    let close_paren_tok = self.close_delim(Delim::Paren)?;
 
-                    Some(Expr::FnCall(FnCallExpr { identifier }))
+                    let identifier = self
+                        .ctx
+                        .get_or_intern_str(self.scanner.token_text(tok.span));
+
+                    Some(Expr::FnCall(FnCallExpr {
+                        identifier,
+                        args: self.ctx.alloc_slice_of_expr(&args),
+                        span: tok.span.to(close_paren_tok.span),
+                    }))
+                } else if self.check(TokenKind::ColonColon) {
+                    self.parse_variant_expr(tok)
+                } else if self.struct_lits_allowed && self.check(TokenKind::Open(Delim::Curly)) {
+                    self.parse_struct_lit_expr(tok)
                 } else {
-                    let identifier = self.ctx.get_or_intern_str(
-                        &self.ctx.get_source_code()[tok.span.start.0..tok.span.end.0],
-                    );
+                    let identifier = self
+                        .ctx
+                        .get_or_intern_str(self.scanner.token_text(tok.span));
 
-                    Some(Expr::BindRef(BindRef { identifier }))
+                    Some(Expr::BindRef(BindRef {
+                        identifier,
+                        span: tok.span,
+                    }))
                 }
             }
-            _ => None,
+            _ => unreachable!("only the start of an expression was expected"),
+        }
+    }
+
+    /// Parses the digits of the constant, and its suffix, if it has one.
+    /// Constants too large for their type, or with a suffix that isn't an
+    /// integer type, are reported and parsed as `0` and as `i32`
+    /// respectively.
+    fn parse_integer_constant(&mut self, tok: Token) -> Expr<'ctx> {
+        let text = self.scanner.token_text(tok.span);
+        let (digits, suffix) = text.split_at(
+            text.find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(text.len()),
+        );
+
+        let ty = if suffix.is_empty() {
+            Type::I32
+        } else if let Some(ty) = Type::INTS.into_iter().find(|ty| ty.to_string() == suffix) {
+            ty
+        } else {
+            self.recovered_errors
+                .push(CompileError::InvalidIntegerSuffix {
+                    suffix: suffix.to_owned(),
+                    span: tok.span,
+                });
+
+            Type::I32
+        };
+
+        let value = digits.parse::<u64>().ok().filter(|&value| {
+            let (_, max) = ty.int_range().unwrap();
+            i128::from(value) <= max
+        });
+
+        if value.is_none() {
+            self.recovered_errors
+                .push(CompileError::IntegerConstantTooLarge {
+                    ty: ty.into(),
+                    span: tok.span,
+                });
         }
+
+        let value = value.unwrap_or(0);
+
+        Expr::Const(ConstExpr {
+            value: match ty {
+                Type::I32 => Const::IntegerConstant {
+                    value: value as i32,
+                },
+                _ => Const::SuffixedIntegerConstant { value, ty },
+            },
+            span: tok.span,
+        })
     }
 
     fn parse_expr(&mut self) -> Option<Expr<'ctx>> {
-        let stmt_expr = self.parse_statement_expr()?;
+        // Expressions aren't separated by commas, so a name followed by one
+        // can only start the names that a tuple is destructured into.
+        let is_tuple_bind_def =
+            self.check(TokenKind::Identifier) && self.look_ahead_kind(1) == Some(TokenKind::Comma);
 
-        if self.peek()?.kind == TokenKind::Semi {
-            self.consume()?;
+        let stmt_expr = if is_tuple_bind_def {
+            self.parse_tuple_bind_def()?
+        } else {
+            self.parse_statement_expr()?
+        };
 
+        if self.eat(TokenKind::Semi).is_some() {
             Some(Expr::Semi(self.ctx.alloc_expr(stmt_expr)))
         } else {
             Some(stmt_expr)
         }
     }
 
-    fn parse_if_expr(&mut self) -> Option<Expr<'ctx>> {
-        let cond_expr = self.parse_expr()?;
+    /// Parses a binding definition whose type is spelled out, as in
+    /// `x : i32 = 0`.
+    fn parse_typed_bind_def(&mut self, ident_tok: Token) -> Option<Expr<'ctx>> {
+        self.expect(TokenKind::Colon)?;
+        let ty = self.parse_type()?;
+        self.expect(TokenKind::Equal)?;
 
-        let open_curly_tok = self.consume()?;
-        debug_assert_eq!(open_curly_tok.kind, TokenKind::Open(Delim::Curly));
+        let value = self.parse_statement_expr()?;
 
-        let true_branch = self.parse_compound_expr(open_curly_tok)?;
+        let identifier = self
+            .ctx
+            .get_or_intern_str(self.scanner.token_text(ident_tok.span));
 
-        let mut else_if_branches = vec![];
+        Some(Expr::BindDef(BindDef {
+            identifier,
+            ty: Some(ty),
+            value: self.ctx.alloc_expr(value),
+            span: ident_tok.span.to(value.span()),
+        }))
+    }
+
+    fn parse_tuple_bind_def(&mut self) -> Option<Expr<'ctx>> {
+        let mut bindings = vec![];
+
+        loop {
+            let ident_tok = self.expect(TokenKind::Identifier)?;
+
+            bindings.push(Binding {
+                identifier: self
+                    .ctx
+                    .get_or_intern_str(self.scanner.token_text(ident_tok.span)),
+                span: ident_tok.span,
+            });
 
-        while self.peek()?.kind == TokenKind::Keyword(Keyword::Else) {
-            if self.look_ahead(1)?.kind != TokenKind::Keyword(Keyword::If) {
+            if self.eat(TokenKind::Comma).is_none() {
                 break;
             }
+        }
 
-            self.consume()?;
-            self.consume()?;
+        self.expect(TokenKind::ColonEqual)?;
 
-            let cond_expr = self.parse_expr()?;
+        let value = self.parse_statement_expr()?;
 
-            let open_curly_tok = self.consume()?;
-            debug_assert_eq!(open_curly_tok.kind, TokenKind::Open(Delim::Curly));
+        Some(Expr::TupleBindDef(TupleBindDef {
+            bindings: self.ctx.alloc_slice_of_binding(&bindings),
+            value: self.ctx.alloc_expr(value),
+            span: bindings[0].span.to(value.span()),
+        }))
+    }
 
-            let true_branch = self.parse_compound_expr(open_curly_tok)?;
+    fn parse_if_expr(&mut self, if_kw_tok: Token) -> Option<Expr<'ctx>> {
+        debug_assert_eq!(if_kw_tok.kind, TokenKind::Keyword(Keyword::If));
+        let cond_expr = self.with_struct_lits(false, Parser::parse_expr)?;
 
-            else_if_branches.push(ElseIfBranch {
-                cond_expr: self.ctx.alloc_expr(cond_expr),
-                true_branch,
-            });
-        }
+        let open_curly_tok = self.expect(TokenKind::Open(Delim::Curly))?;
+        let true_branch = self.parse_compound_expr(open_curly_tok)?;
 
-        let final_branch = if self.peek()?.kind == TokenKind::Keyword(Keyword::Else) {
-            self.consume()?;
+        let mut else_if_branches = vec![];
+        let mut final_branch = None;
 
-            let open_curly_tok = self.consume()?;
-            debug_assert_eq!(open_curly_tok.kind, TokenKind::Open(Delim::Curly));
+        loop {
+            let (cond_expr, branch) = if self.eat(TokenKind::Keyword(Keyword::Else)).is_some() {
+                self.parse_else_branch()?
+            } else {
+                let else_branch = self.parse_misspelled_keyword(&[Keyword::Else], |parser, _| {
+                    parser.parse_else_branch()
+                });
 
-            let branch = self.parse_compound_expr(open_curly_tok)?;
+                match else_branch {
+                    Some(else_branch) => else_branch,
+                    None => break,
+                }
+            };
 
-            Some(branch)
-        } else {
-            None
-        };
+            let Some(cond_expr) = cond_expr else {
+                final_branch = Some(branch);
+
+                break;
+            };
+
+            else_if_branches.push(ElseIfBranch {
+                cond_expr: self.ctx.alloc_expr(cond_expr),
+                true_branch: branch,
+            });
+        }
 
         Some(Expr::If(IfExpr {
             cond_expr: self.ctx.alloc_expr(cond_expr),
             true_branch,
             else_if_branches: self.ctx.alloc_slice_of_else_if_branch(&else_if_branches),
             final_branch,
+            span: if_kw_tok.span.to(self.prev_tok_span()),
         }))
     }
 
-    fn parse_for_expr(&mut self) -> Option<Expr<'ctx>> {
-        let iteration = if self.peek()?.kind == TokenKind::Identifier
-            && self.look_ahead(1)?.kind == TokenKind::Colon
-        {
-            let ident_tok = self.consume()?;
-            let identifier = self.ctx.get_or_intern_str(
-                &self.ctx.get_source_code()[ident_tok.span.start.0..ident_tok.span.end.0],
-            );
+    /// Parses what follows an `else`, which is either the condition and the
+    /// branch of another `if`, or the final branch, which has no condition.
+    fn parse_else_branch(&mut self) -> Option<(Option<Expr<'ctx>>, CompoundExpr<'ctx>)> {
+        let cond_expr = if self.eat(TokenKind::Keyword(Keyword::If)).is_some() {
+            Some(self.with_struct_lits(false, Parser::parse_expr)?)
+        } else {
+            None
+        };
 
-            let in_kw_tok = self.consume()?;
-            debug_assert_eq!(in_kw_tok.kind, TokenKind::Colon);
+        let open_curly_tok = self.expect(TokenKind::Open(Delim::Curly))?;
+        let branch = self.parse_compound_expr(open_curly_tok)?;
 
-            let start_expr = self.parse_expr()?;
+        Some((cond_expr, branch))
+    }
 
-            let range_tok = self.consume()?;
-            let range_kind = if range_tok.kind == TokenKind::PeriodPeriodEqual {
-                RangeKind::Inclusive
-            } else {
-                debug_assert_eq!(range_tok.kind, TokenKind::PeriodPeriod);
+    fn parse_match_expr(&mut self, match_kw_tok: Token) -> Option<Expr<'ctx>> {
+        debug_assert_eq!(match_kw_tok.kind, TokenKind::Keyword(Keyword::Match));
+        let scrutinee = self.with_struct_lits(false, Parser::parse_statement_expr)?;
 
-                RangeKind::Exclusive
-            };
+        let open_curly_tok = self.expect(TokenKind::Open(Delim::Curly))?;
+        self.open_delim(open_curly_tok);
 
-            let end_expr = self.parse_expr()?;
+        let mut arms = vec![];
 
-            Some(ForIteration::Iterative {
-                identifier,
-                start_expr: self.ctx.alloc_expr(start_expr),
-                end_expr: self.ctx.alloc_expr(end_expr),
-                range_kind,
-            })
-        } else if self.peek()?.kind != TokenKind::Open(Delim::Curly) {
-            let cond_expr = self.parse_expr()?;
+        while !self.check(TokenKind::Closed(Delim::Curly)) {
+            let pattern = self.parse_pattern()?;
+            self.expect(TokenKind::EqualGreater)?;
 
-            Some(ForIteration::Conditional {
-                cond_expr: self.ctx.alloc_expr(cond_expr),
-            })
-        } else {
-            None
-        };
+            let expr = self.parse_match_arm_expr()?;
+            arms.push(MatchArm {
+                pattern,
+                expr: self.ctx.alloc_expr(expr),
+            });
 
-        let open_curly_tok = self.consume()?;
-        debug_assert_eq!(open_curly_tok.kind, TokenKind::Open(Delim::Curly));
+            self.check_ast_size(expr.span())?;
 
-        let for_loop_body = self.parse_compound_expr(open_curly_tok)?;
+            // Arms that are blocks end where their block does, so only the
+            // others need a `,` to tell them apart from the next arm.
+            if self.eat(TokenKind::Comma).is_none() && !matches!(expr, Expr::Compound(_)) {
+                break;
+            }
+        }
 
-        Some(Expr::For(ForExpr {
-            iteration,
-            body: for_loop_body,
+        let closed_curly_tok = self.close_delim(Delim::Curly)?;
+
+        Some(Expr::Match(MatchExpr {
+            scrutinee: self.ctx.alloc_expr(scrutinee),
+            arms: self.ctx.alloc_slice_of_match_arm(&arms),
+            span: match_kw_tok.span.to(closed_curly_tok.span),
         }))
     }
 
-    fn parse_break_expr(&mut self) -> Option<Expr<'ctx>> {
-        Some(Expr::Break)
-    }
+    /// Parses the expression of a match-arm. An arm that's a block ends with
+    /// it, rather than going on as the left-hand side of a binary operation,
+    /// so that the next arm may start with `-`.
+    fn parse_match_arm_expr(&mut self) -> Option<Expr<'ctx>> {
+        let Some(open_curly_tok) = self.eat(TokenKind::Open(Delim::Curly)) else {
+            return self.parse_statement_expr();
+        };
+
+        self.enter_nesting(open_curly_tok.span)?;
+        let compound_expr = self.parse_compound_expr(open_curly_tok);
+        self.nesting_depth -= 1;
 
-    fn parse_continue_expr(&mut self) -> Option<Expr<'ctx>> {
-        Some(Expr::Continue)
+        compound_expr.map(Expr::Compound)
     }
 
-    fn parse_function(&mut self) -> Option<Expr<'ctx>> {
-        let closed_paren = self.consume()?;
-        debug_assert_eq!(closed_paren.kind, TokenKind::Closed(Delim::Paren));
+    fn parse_pattern(&mut self) -> Option<Pattern> {
+        if let Some(ident_tok) = self.eat(TokenKind::Identifier) {
+            if self.scanner.token_text(ident_tok.span) == DISCARD_IDENTIFIER {
+                return Some(Pattern {
+                    kind: PatternKind::Wildcard,
+                    span: ident_tok.span,
+                });
+            }
 
-        let (return_type, open_curly_tok) = if self.peek()?.kind == TokenKind::DashGreater {
-            self.consume()?;
+            return self.parse_variant_pattern(ident_tok);
+        }
 
-            let type_tok = self.consume()?;
-            debug_assert_eq!(type_tok.kind, TokenKind::Keyword(Keyword::I32));
+        let (start, start_span) = self.parse_pattern_integer()?;
 
-            let open_curly_tok = self.consume()?;
-            debug_assert_eq!(open_curly_tok.kind, TokenKind::Open(Delim::Curly));
+        let Some(range_kind) =
+            self.check_one_of(&[TokenKind::PeriodPeriod, TokenKind::PeriodPeriodEqual])
+        else {
+            return Some(Pattern {
+                kind: PatternKind::Integer(start),
+                span: start_span,
+            });
+        };
 
-            (Type::I32, open_curly_tok)
-        } else {
-            let open_curly_tok = self.consume()?;
-            debug_assert_eq!(open_curly_tok.kind, TokenKind::Open(Delim::Curly));
+        self.consume()?;
+        let (end, end_span) = self.parse_pattern_integer()?;
 
-            (Type::Unit, open_curly_tok)
+        let range_kind = if range_kind == TokenKind::PeriodPeriodEqual {
+            RangeKind::Inclusive
+        } else {
+            RangeKind::Exclusive
         };
 
-        let compound_expr = self.parse_compound_expr(open_curly_tok)?;
-
-        Some(Expr::Function(Function {
-            return_type,
-            parameters: self.ctx.alloc_slice_of_param(&[]),
-            body: compound_expr,
-        }))
+        Some(Pattern {
+            kind: PatternKind::Range {
+                start,
+                end,
+                range_kind,
+            },
+            span: start_span.to(end_span),
+        })
     }
 
-    fn parse_compound_expr(&mut self, open_curly_tok: Token) -> Option<CompoundExpr<'ctx>> {
-        debug_assert_eq!(open_curly_tok.kind, TokenKind::Open(Delim::Curly));
+    /// Parses a pattern that matches an enum's variant, as in
+    /// `Shape::Circle(radius)`, whose enum's name is already parsed.
+    fn parse_variant_pattern(&mut self, enum_tok: Token) -> Option<Pattern> {
+        self.expect(TokenKind::ColonColon)?;
+        let variant_tok = self.expect(TokenKind::Identifier)?;
 
-        let mut exprs = vec![];
+        let binding = match self.eat(TokenKind::Open(Delim::Paren)) {
+            Some(open_paren_tok) => {
+                self.open_delim(open_paren_tok);
+                let binding_tok = self.expect(TokenKind::Identifier)?;
+                self.close_delim(Delim::Paren)?;
 
-        while self.peek()?.kind != TokenKind::Closed(Delim::Curly) {
-            let expr = self.parse_expr()?;
-            exprs.push(expr);
+                Some((self.interned_token_text(binding_tok), binding_tok.span))
+            }
+            None => None,
+        };
+
+        Some(Pattern {
+            kind: PatternKind::Variant(VariantPattern {
+                enum_name: self.interned_token_text(enum_tok),
+                enum_name_span: enum_tok.span,
+                variant_name: self.interned_token_text(variant_tok),
+                variant_name_span: variant_tok.span,
+                binding,
+            }),
+            span: enum_tok.span.to(self.prev_tok_span()),
+        })
+    }
+
+    /// Parses an integer constant of a pattern, which may be negative.
+    fn parse_pattern_integer(&mut self) -> Option<(i128, Span)> {
+        let dash_tok = self.eat(TokenKind::Dash);
+        let integer_tok = self.expect(TokenKind::IntegerConstant)?;
+        let magnitude = self.parse_pattern_magnitude(integer_tok);
+
+        match dash_tok {
+            Some(dash_tok) => Some((-magnitude, dash_tok.span.to(integer_tok.span))),
+            None => Some((magnitude, integer_tok.span)),
+        }
+    }
+
+    /// Parses the digits of a pattern's integer. Patterns are matched against
+    /// `i32`s, so their integers take no suffix. Integers that don't even fit
+    /// in a `u64` are reported and parsed as `0`, and type checking reports
+    /// the ones that don't fit in an `i32`.
+    fn parse_pattern_magnitude(&mut self, integer_tok: Token) -> i128 {
+        let text = self.scanner.token_text(integer_tok.span);
+
+        if let Some(suffix_start) = text.find(|c: char| !c.is_ascii_digit()) {
+            self.recovered_errors
+                .push(CompileError::InvalidIntegerSuffix {
+                    suffix: text[suffix_start..].to_owned(),
+                    span: integer_tok.span,
+                });
+
+            return 0;
+        }
+
+        match text.parse::<u64>() {
+            Ok(magnitude) => magnitude.into(),
+            Err(_) => {
+                self.recovered_errors
+                    .push(CompileError::IntegerConstantTooLarge {
+                        ty: Type::U64.into(),
+                        span: integer_tok.span,
+                    });
+
+                0
+            }
         }
+    }
+
+    fn parse_for_expr(&mut self, for_kw_tok: Token, is_main_loop: bool) -> Option<Expr<'ctx>> {
+        debug_assert_eq!(for_kw_tok.kind, TokenKind::Keyword(Keyword::For));
+
+        let iteration = if self.check(TokenKind::Identifier)
+            && self.look_ahead_kind(1) == Some(TokenKind::Colon)
+        {
+            Some(self.parse_variable_iteration()?)
+        } else if !self.check(TokenKind::Open(Delim::Curly)) {
+            let cond_expr = self.with_struct_lits(false, Parser::parse_expr)?;
+
+            Some(ForIteration::Conditional {
+                cond_expr: self.ctx.alloc_expr(cond_expr),
+            })
+        } else {
+            None
+        };
+
+        let open_curly_tok = self.expect(TokenKind::Open(Delim::Curly))?;
+        let for_loop_body = self.parse_compound_expr(open_curly_tok)?;
+
+        let else_branch = if self.eat(TokenKind::Keyword(Keyword::Else)).is_some() {
+            let open_curly_tok = self.expect(TokenKind::Open(Delim::Curly))?;
+            let branch = self.parse_compound_expr(open_curly_tok)?;
+
+            Some(branch)
+        } else {
+            self.parse_misspelled_keyword(&[Keyword::Else], |parser, _| {
+                let open_curly_tok = parser.expect(TokenKind::Open(Delim::Curly))?;
+
+                parser.parse_compound_expr(open_curly_tok)
+            })
+        };
+
+        Some(Expr::For(ForExpr {
+            keyword: LoopKeyword::For,
+            for_kw_span: for_kw_tok.span,
+            iteration,
+            body: for_loop_body,
+            else_branch,
+            is_main_loop,
+            span: for_kw_tok.span.to(self.prev_tok_span()),
+        }))
+    }
+
+    /// Loop that runs until a `break`, which is a for-loop without an iteration
+    /// nor an else-branch, as the loop never completes.
+    fn parse_loop_expr(&mut self, loop_kw_tok: Token, is_main_loop: bool) -> Option<Expr<'ctx>> {
+        debug_assert_eq!(loop_kw_tok.kind, TokenKind::Keyword(Keyword::Loop));
+
+        let open_curly_tok = self.expect(TokenKind::Open(Delim::Curly))?;
+        let body = self.parse_compound_expr(open_curly_tok)?;
+
+        Some(Expr::For(ForExpr {
+            keyword: LoopKeyword::Loop,
+            for_kw_span: loop_kw_tok.span,
+            iteration: None,
+            body,
+            else_branch: None,
+            is_main_loop,
+            span: loop_kw_tok.span.to(self.prev_tok_span()),
+        }))
+    }
+
+    /// Iteration of a for-loop whose variable takes the values of a range, as
+    /// in `i : 0..10 step 2` or `i : rev 0..10`, or the elements of a
+    /// collection, as in `x : xs`.
+    ///
+    /// It's parsed apart from the rest of the loop, whose body nests other
+    /// loops, so that its locals don't take up stack for each of them.
+    fn parse_variable_iteration(&mut self) -> Option<ForIteration<'ctx>> {
+        let ident_tok = self.consume()?;
+        let identifier = self
+            .ctx
+            .get_or_intern_str(self.scanner.token_text(ident_tok.span));
+
+        // It's the colon looked ahead at.
+        self.consume()?;
+
+        // An operand right after `rev` can't go on an expression that starts
+        // with it, so it's a binding's name otherwise, as in `rev..10`.
+        let is_reversed = self.check_contextual(Keyword::Rev)
+            && matches!(
+                self.look_ahead_kind(1),
+                Some(TokenKind::Identifier | TokenKind::IntegerConstant)
+            );
+
+        if is_reversed {
+            self.consume()?;
+        }
+
+        let start_expr = self.with_struct_lits(false, Parser::parse_expr)?;
+
+        // Without a range, the loop iterates over the elements of the value
+        // instead, which it can't do in reverse.
+        let range_tok_kinds = [TokenKind::PeriodPeriod, TokenKind::PeriodPeriodEqual];
+        let range_tok = if is_reversed {
+            Some(self.expect_one_of(&range_tok_kinds)?)
+        } else if self.check_one_of(&range_tok_kinds).is_some() {
+            Some(self.consume()?)
+        } else {
+            None
+        };
+
+        match range_tok {
+            Some(range_tok) => {
+                let range_kind = if range_tok.kind == TokenKind::PeriodPeriodEqual {
+                    RangeKind::Inclusive
+                } else {
+                    RangeKind::Exclusive
+                };
+
+                let end_expr = self.with_struct_lits(false, Parser::parse_expr)?;
+
+                let step_expr = if self.eat_contextual(Keyword::Step).is_some() {
+                    let step_expr = self.with_struct_lits(false, Parser::parse_expr)?;
+
+                    Some(self.ctx.alloc_expr(step_expr))
+                } else {
+                    None
+                };
+
+                Some(ForIteration::Iterative {
+                    identifier,
+                    identifier_span: ident_tok.span,
+                    start_expr: self.ctx.alloc_expr(start_expr),
+                    end_expr: self.ctx.alloc_expr(end_expr),
+                    range_kind,
+                    step_expr,
+                    is_reversed,
+                })
+            }
+            None => Some(ForIteration::InCollection {
+                identifier,
+                identifier_span: ident_tok.span,
+                collection_expr: self.ctx.alloc_expr(start_expr),
+            }),
+        }
+    }
+
+    fn parse_main_loop_attribute(&mut self, hash_tok: Token) -> Option<Expr<'ctx>> {
+        debug_assert_eq!(hash_tok.kind, TokenKind::Hash);
+
+        let open_bracket_tok = self.expect(TokenKind::Open(Delim::Bracket))?;
+        self.open_delim(open_bracket_tok);
+
+        let ident_tok = self.expect(TokenKind::Identifier)?;
+
+        if self.scanner.token_text(ident_tok.span) != "main_loop" {
+            return None;
+        }
+
+        self.close_delim(Delim::Bracket)?;
+
+        let kw_tok = self.expect_one_of(&[
+            TokenKind::Keyword(Keyword::For),
+            TokenKind::Keyword(Keyword::Loop),
+        ])?;
+
+        match kw_tok.kind {
+            TokenKind::Keyword(Keyword::For) => self.parse_for_expr(kw_tok, true),
+            _ => self.parse_loop_expr(kw_tok, true),
+        }
+    }
+
+    fn parse_break_expr(&mut self, break_kw_tok: Token) -> Option<Expr<'ctx>> {
+        // Whatever expression follows the keyword is the loop's value, as
+        // nothing else could follow it.
+        let value = match self.check_one_of(&EXPR_START_TOKENS) {
+            Some(_) => Some(self.parse_binary_expr(0)?),
+            None => None,
+        };
+
+        Some(Expr::Break(BreakExpr {
+            value: value.map(|value| self.ctx.alloc_expr(value)),
+            span: match value {
+                Some(value) => break_kw_tok.span.to(value.span()),
+                None => break_kw_tok.span,
+            },
+        }))
+    }
+
+    fn parse_continue_expr(&mut self, continue_kw_tok: Token) -> Option<Expr<'ctx>> {
+        Some(Expr::Continue(ContinueExpr {
+            span: continue_kw_tok.span,
+        }))
+    }
+
+    fn parse_function(&mut self, open_paren_tok: Token) -> Option<Expr<'ctx>> {
+        let params = self.parse_params(open_paren_tok)?;
+        let return_type = self.parse_return_type()?;
+
+        let open_curly_tok = self.expect(TokenKind::Open(Delim::Curly))?;
+        let compound_expr = self.parse_compound_expr(open_curly_tok)?;
+
+        Some(Expr::Function(Function {
+            return_type,
+            parameters: self.ctx.alloc_slice_of_param(&params),
+            body: compound_expr,
+            span: open_paren_tok.span.to(compound_expr.span),
+        }))
+    }
+
+    /// Parses the parameters of a function, up to the closing `)`.
+    fn parse_params(&mut self, open_paren_tok: Token) -> Option<Vec<Param>> {
+        debug_assert_eq!(open_paren_tok.kind, TokenKind::Open(Delim::Paren));
+        self.open_delim(open_paren_tok);
+
+        let mut params = vec![];
+
+        while !self.check(TokenKind::Closed(Delim::Paren)) {
+            params.push(self.parse_param()?);
+
+            if self.eat(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+
+        self.close_delim(Delim::Paren)?;
+
+        Some(params)
+    }
+
+    /// Parses the `-> type` of a function, which returns `()` without one.
+    fn parse_return_type(&mut self) -> Option<Type> {
+        if self.eat(TokenKind::DashGreater).is_some() {
+            self.parse_type()
+        } else {
+            Some(Type::Unit)
+        }
+    }
+
+    fn parse_paren_expr(&mut self, open_paren_tok: Token) -> Option<Expr<'ctx>> {
+        debug_assert_eq!(open_paren_tok.kind, TokenKind::Open(Delim::Paren));
+        self.open_delim(open_paren_tok);
+
+        let expr = self.with_struct_lits(true, Parser::parse_statement_expr)?;
+
+        // A comma after the first expression makes it the first element of a
+        // tuple, even if it's the only one.
+        if self.eat(TokenKind::Comma).is_none() {
+            let close_paren_tok = self.close_delim(Delim::Paren)?;
+
+            return Some(Expr::Paren(ParenExpr {
+                expr: self.ctx.alloc_expr(expr),
+                span: open_paren_tok.span.to(close_paren_tok.span),
+            }));
+        }
+
+        let mut elements = vec![expr];
+
+        while !self.check(TokenKind::Closed(Delim::Paren)) {
+            elements.push(self.with_struct_lits(true, Parser::parse_statement_expr)?);
+
+            if self.eat(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+
+        let close_paren_tok = self.close_delim(Delim::Paren)?;
+
+        Some(Expr::Tuple(TupleExpr {
+            elements: self.ctx.alloc_slice_of_expr(&elements),
+            span: open_paren_tok.span.to(close_paren_tok.span),
+        }))
+    }
+
+    fn parse_array_expr(&mut self, open_bracket_tok: Token) -> Option<Expr<'ctx>> {
+        debug_assert_eq!(open_bracket_tok.kind, TokenKind::Open(Delim::Bracket));
+        self.open_delim(open_bracket_tok);
+
+        // The first element is required, as it tells the type of the others.
+        let mut elements = vec![self.with_struct_lits(true, Parser::parse_statement_expr)?];
+
+        while self.eat(TokenKind::Comma).is_some() {
+            if self.check(TokenKind::Closed(Delim::Bracket)) {
+                break;
+            }
+
+            elements.push(self.with_struct_lits(true, Parser::parse_statement_expr)?);
+        }
+
+        let close_bracket_tok = self.close_delim(Delim::Bracket)?;
+
+        Some(Expr::Array(ArrayExpr {
+            elements: self.ctx.alloc_slice_of_expr(&elements),
+            span: open_bracket_tok.span.to(close_bracket_tok.span),
+        }))
+    }
+
+    fn parse_struct_def(&mut self, struct_kw_tok: Token) -> Option<Expr<'ctx>> {
+        debug_assert_eq!(struct_kw_tok.kind, TokenKind::Keyword(Keyword::Struct));
+
+        let open_curly_tok = self.expect(TokenKind::Open(Delim::Curly))?;
+        self.open_delim(open_curly_tok);
+
+        let mut fields = vec![];
+
+        while !self.check(TokenKind::Closed(Delim::Curly)) {
+            let ident_tok = self.expect(TokenKind::Identifier)?;
+            self.expect(TokenKind::Colon)?;
+
+            let ty = self.parse_type()?;
+
+            let identifier = self
+                .ctx
+                .get_or_intern_str(self.scanner.token_text(ident_tok.span));
+
+            fields.push(FieldDef {
+                identifier,
+                ty,
+                span: ident_tok.span.to(self.prev_tok_span()),
+            });
+
+            if self.eat(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+
+        let closed_curly_tok = self.close_delim(Delim::Curly)?;
+
+        Some(Expr::Struct(StructDef {
+            fields: self.ctx.alloc_slice_of_field_def(&fields),
+            span: struct_kw_tok.span.to(closed_curly_tok.span),
+        }))
+    }
+
+    fn parse_struct_lit_expr(&mut self, ident_tok: Token) -> Option<Expr<'ctx>> {
+        let open_curly_tok = self.expect(TokenKind::Open(Delim::Curly))?;
+        self.open_delim(open_curly_tok);
+
+        let mut fields = vec![];
+
+        while !self.check(TokenKind::Closed(Delim::Curly)) {
+            let field_tok = self.expect(TokenKind::Identifier)?;
+            self.expect(TokenKind::Colon)?;
+
+            let value = self.with_struct_lits(true, Parser::parse_statement_expr)?;
+
+            let identifier = self
+                .ctx
+                .get_or_intern_str(self.scanner.token_text(field_tok.span));
+
+            fields.push(FieldInit {
+                identifier,
+                identifier_span: field_tok.span,
+                value: self.ctx.alloc_expr(value),
+            });
+
+            if self.eat(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+
+        let closed_curly_tok = self.close_delim(Delim::Curly)?;
+
+        let identifier = self
+            .ctx
+            .get_or_intern_str(self.scanner.token_text(ident_tok.span));
+
+        Some(Expr::StructLit(StructLitExpr {
+            identifier,
+            identifier_span: ident_tok.span,
+            fields: self.ctx.alloc_slice_of_field_init(&fields),
+            span: ident_tok.span.to(closed_curly_tok.span),
+        }))
+    }
+
+    fn parse_enum_def(&mut self, enum_kw_tok: Token) -> Option<Expr<'ctx>> {
+        debug_assert_eq!(enum_kw_tok.kind, TokenKind::Keyword(Keyword::Enum));
 
-        let closed_curly_tok = self.consume()?;
-        debug_assert_eq!(closed_curly_tok.kind, TokenKind::Closed(Delim::Curly));
+        let open_curly_tok = self.expect(TokenKind::Open(Delim::Curly))?;
+        self.open_delim(open_curly_tok);
+
+        let mut variants = vec![];
+
+        while !self.check(TokenKind::Closed(Delim::Curly)) {
+            let ident_tok = self.expect(TokenKind::Identifier)?;
+
+            let payload = match self.eat(TokenKind::Open(Delim::Paren)) {
+                Some(open_paren_tok) => {
+                    self.open_delim(open_paren_tok);
+                    let ty = self.parse_type()?;
+                    self.close_delim(Delim::Paren)?;
+
+                    Some(ty)
+                }
+                None => None,
+            };
+
+            let identifier = self
+                .ctx
+                .get_or_intern_str(self.scanner.token_text(ident_tok.span));
+
+            variants.push(VariantDef {
+                identifier,
+                payload,
+                span: ident_tok.span.to(self.prev_tok_span()),
+            });
+
+            if self.eat(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+
+        let closed_curly_tok = self.close_delim(Delim::Curly)?;
+
+        Some(Expr::Enum(EnumDef {
+            variants: self.ctx.alloc_slice_of_variant_def(&variants),
+            span: enum_kw_tok.span.to(closed_curly_tok.span),
+        }))
+    }
+
+    /// Parses the value of an enum's variant, as in `Shape::Circle(2)`, whose
+    /// enum's name is already parsed.
+    fn parse_variant_expr(&mut self, enum_tok: Token) -> Option<Expr<'ctx>> {
+        self.expect(TokenKind::ColonColon)?;
+        let variant_tok = self.expect(TokenKind::Identifier)?;
+
+        let payload = match self.eat(TokenKind::Open(Delim::Paren)) {
+            Some(open_paren_tok) => {
+                self.open_delim(open_paren_tok);
+                let payload = self.with_struct_lits(true, Parser::parse_statement_expr)?;
+                self.close_delim(Delim::Paren)?;
+
+                Some(self.ctx.alloc_expr(payload))
+            }
+            None => None,
+        };
+
+        let enum_identifier = self
+            .ctx
+            .get_or_intern_str(self.scanner.token_text(enum_tok.span));
+        let variant_identifier = self
+            .ctx
+            .get_or_intern_str(self.scanner.token_text(variant_tok.span));
+
+        Some(Expr::Variant(VariantExpr {
+            enum_identifier,
+            enum_identifier_span: enum_tok.span,
+            variant_identifier,
+            variant_identifier_span: variant_tok.span,
+            payload,
+            span: enum_tok.span.to(self.prev_tok_span()),
+        }))
+    }
+
+    fn parse_param(&mut self) -> Option<Param> {
+        let ident_tok = self.expect(TokenKind::Identifier)?;
+        self.expect(TokenKind::Colon)?;
+
+        let ty = self.parse_type()?;
+
+        let identifier = self
+            .ctx
+            .get_or_intern_str(self.scanner.token_text(ident_tok.span));
+
+        Some(Param {
+            identifier,
+            ty,
+            span: ident_tok.span.to(self.prev_tok_span()),
+        })
+    }
+
+    fn parse_type(&mut self) -> Option<Type> {
+        let type_tok = self.expect_one_of(&[
+            TokenKind::Keyword(Keyword::I8),
+            TokenKind::Keyword(Keyword::I16),
+            TokenKind::Keyword(Keyword::I32),
+            TokenKind::Keyword(Keyword::I64),
+            TokenKind::Keyword(Keyword::U8),
+            TokenKind::Keyword(Keyword::U16),
+            TokenKind::Keyword(Keyword::U32),
+            TokenKind::Keyword(Keyword::U64),
+            TokenKind::Keyword(Keyword::Usize),
+            TokenKind::Open(Delim::Paren),
+            TokenKind::Open(Delim::Bracket),
+            TokenKind::Identifier,
+        ])?;
+
+        match type_tok.kind {
+            TokenKind::Keyword(Keyword::I8) => Some(Type::I8),
+            TokenKind::Keyword(Keyword::I16) => Some(Type::I16),
+            TokenKind::Keyword(Keyword::I32) => Some(Type::I32),
+            TokenKind::Keyword(Keyword::I64) => Some(Type::I64),
+            TokenKind::Keyword(Keyword::U8) => Some(Type::U8),
+            TokenKind::Keyword(Keyword::U16) => Some(Type::U16),
+            TokenKind::Keyword(Keyword::U32) => Some(Type::U32),
+            TokenKind::Keyword(Keyword::U64) => Some(Type::U64),
+            TokenKind::Keyword(Keyword::Usize) => Some(Type::Usize),
+            TokenKind::Identifier => Some(Type::Named(self.interned_token_text(type_tok))),
+            TokenKind::Open(Delim::Paren) => {
+                self.open_delim(type_tok);
+                // Tuple types nest like the values of their elements do.
+                self.enter_nesting(type_tok.span)?;
+
+                let mut element_types = vec![];
+                let mut has_trailing_comma = false;
+
+                while !self.check(TokenKind::Closed(Delim::Paren)) {
+                    element_types.push(self.parse_type()?);
+                    has_trailing_comma = self.eat(TokenKind::Comma).is_some();
+
+                    if !has_trailing_comma {
+                        break;
+                    }
+                }
+
+                self.close_delim(Delim::Paren)?;
+                self.nesting_depth -= 1;
+
+                // Types in parentheses followed by an arrow are the parameter
+                // types of a function type instead, as in `(i32) -> ()`.
+                if self.eat(TokenKind::DashGreater).is_some() {
+                    let return_type = self.parse_type()?;
+
+                    return Some(Type::Function {
+                        params: self.ctx.alloc_type_list(&element_types),
+                        return_type: &self.ctx.alloc_type_list(&[return_type])[0],
+                    });
+                }
+
+                // Like values, types of one element are tuples only if they're
+                // followed by a comma, as in `(i32,)`.
+                match element_types[..] {
+                    [] => Some(Type::Unit),
+                    [element_ty] if !has_trailing_comma => Some(element_ty),
+                    _ => Some(Type::Tuple(self.ctx.alloc_type_list(&element_types))),
+                }
+            }
+            TokenKind::Open(Delim::Bracket) => {
+                self.open_delim(type_tok);
+                self.enter_nesting(type_tok.span)?;
+
+                let element = self.parse_type()?;
+                self.expect(TokenKind::Semi)?;
+                let len_tok = self.expect(TokenKind::IntegerConstant)?;
+
+                self.close_delim(Delim::Bracket)?;
+                self.nesting_depth -= 1;
+
+                // No array that long would fit in memory anyway, so lengths
+                // too large for a `usize` are kept as the largest one.
+                let len = self
+                    .scanner
+                    .token_text(len_tok.span)
+                    .parse()
+                    .unwrap_or(usize::MAX);
+
+                Some(Type::Array {
+                    element: &self.ctx.alloc_type_list(&[element])[0],
+                    len,
+                })
+            }
+            _ => unreachable!("only a type was expected"),
+        }
+    }
+
+    fn parse_compound_expr(&mut self, open_curly_tok: Token) -> Option<CompoundExpr<'ctx>> {
+        debug_assert_eq!(open_curly_tok.kind, TokenKind::Open(Delim::Curly));
+        self.open_delim(open_curly_tok);
+
+        let mut exprs = vec![];
+
+        while !self.check(TokenKind::Closed(Delim::Curly)) {
+            let expr = self.with_struct_lits(true, Parser::parse_expr)?;
+            exprs.push(expr);
+
+            self.check_ast_size(expr.span())?;
+        }
+
+        let closed_curly_tok = self.close_delim(Delim::Curly)?;
 
         Some(CompoundExpr {
             exprs: self.ctx.alloc_slice_of_expr(&exprs),
+            span: open_curly_tok.span.to(closed_curly_tok.span),
+        })
+    }
+
+    /// Text of an identifier's token, interned so that it lives as long as the
+    /// names that types and patterns keep.
+    fn interned_token_text(&self, tok: Token) -> &'static str {
+        let identifier = self
+            .ctx
+            .get_or_intern_str(self.scanner.token_text(tok.span));
+
+        self.ctx.resolve_symbol(identifier)
+    }
+
+    /// Parses with struct literals allowed or not, see `struct_lits_allowed`.
+    fn with_struct_lits<T>(
+        &mut self,
+        allowed: bool,
+        parse: impl FnOnce(&mut Parser<'ctx, 'src>) -> T,
+    ) -> T {
+        let struct_lits_allowed = mem::replace(&mut self.struct_lits_allowed, allowed);
+        let parsed = parse(self);
+        self.struct_lits_allowed = struct_lits_allowed;
+
+        parsed
+    }
+
+    fn open_delim(&mut self, open_tok: Token) {
+        self.open_delims.push(open_tok);
+    }
+
+    fn close_delim(&mut self, delim: Delim) -> Option<Token> {
+        let closed_tok = self.expect(TokenKind::Closed(delim))?;
+
+        let open_tok = self.open_delims.pop();
+        debug_assert_eq!(open_tok.map(|tok| tok.kind), Some(TokenKind::Open(delim)));
+
+        Some(closed_tok)
+    }
+
+    /// Goes one level deeper into nested expressions, failing if that's past
+    /// the limit.
+    fn enter_nesting(&mut self, span: Span) -> Option<()> {
+        if self.nesting_depth == self.limits.max_nesting_depth {
+            return self.fail_on_limit(CompileError::NestingTooDeep {
+                limit: self.limits.max_nesting_depth,
+                span,
+            });
+        }
+
+        self.nesting_depth += 1;
+
+        Some(())
+    }
+
+    fn check_ast_size(&mut self, span: Span) -> Option<()> {
+        if self.ctx.allocated_bytes() > self.limits.max_ast_bytes {
+            return self.fail_on_limit(CompileError::AstTooLarge {
+                limit: self.limits.max_ast_bytes,
+                span,
+            });
+        }
+
+        Some(())
+    }
+
+    /// Stops the parser, as it can't go on without exceeding a limit.
+    fn fail_on_limit(&mut self, error: CompileError) -> Option<()> {
+        self.limit_error.get_or_insert(error);
+
+        None
+    }
+
+    /// Parses on with `parse` as if the next token was one of the keywords, if
+    /// it's a name that's a typo of it, e.g., `fro` for `for`, reporting the
+    /// typo. Fails without consuming anything if the next token isn't such a
+    /// name, or if the code doesn't parse with the keyword in its place.
+    fn parse_misspelled_keyword<T>(
+        &mut self,
+        keywords: &[Keyword],
+        parse: impl FnOnce(&mut Parser<'ctx, 'src>, Token) -> Option<T>,
+    ) -> Option<T> {
+        let ident_tok = self
+            .peek()
+            .filter(|tok| tok.kind == TokenKind::Identifier)?;
+        let found = self.scanner.token_text(ident_tok.span);
+        let keyword = keywords
+            .iter()
+            .copied()
+            .find(|keyword| is_typo_of(found, keyword.as_str()))?;
+        let error = CompileError::MisspelledKeyword {
+            found: found.to_owned(),
+            keyword,
+            span: ident_tok.span,
+        };
+
+        self.speculate(|parser| {
+            parser.consume()?;
+            parser.recovered_errors.push(error);
+
+            let kw_tok = Token {
+                kind: TokenKind::Keyword(keyword),
+                span: ident_tok.span,
+            };
+
+            parse(parser, kw_tok)
+        })
+    }
+
+    /// Parses with `parse`, going back to where the parser was before if it
+    /// fails, as if it never tried.
+    fn speculate<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Parser<'ctx, 'src>) -> Option<T>,
+    ) -> Option<T> {
+        let scanner = self.scanner.clone();
+        let lookahead_tokens = self.lookahead_tokens;
+        let prev_token = self.prev_token;
+        let expected_tokens = self.expected_tokens.clone();
+        let open_delims = self.open_delims.clone();
+        let struct_lits_allowed = self.struct_lits_allowed;
+        let nesting_depth = self.nesting_depth;
+        let recovered_errors_len = self.recovered_errors.len();
+
+        let parsed = parse(self);
+
+        if parsed.is_none() {
+            self.scanner = scanner;
+            self.lookahead_tokens = lookahead_tokens;
+            self.prev_token = prev_token;
+            self.expected_tokens = expected_tokens;
+            self.open_delims = open_delims;
+            self.struct_lits_allowed = struct_lits_allowed;
+            self.nesting_depth = nesting_depth;
+            self.recovered_errors.truncate(recovered_errors_len);
+            // The parser stops at its first failure, so it hadn't failed
+            // before trying.
+            self.limit_error = None;
+            self.syntax_error = None;
+        }
+
+        parsed
+    }
+
+    /// Whether the next token is of the kind, which is one of the tokens
+    /// expected next from now on.
+    fn check(&mut self, kind: TokenKind) -> bool {
+        if !self.expected_tokens.contains(&kind) {
+            self.expected_tokens.push(kind);
+        }
+
+        self.look_ahead_kind(0) == Some(kind)
+    }
+
+    /// Kind of the next token if it's one of the kinds, which are all expected
+    /// next from now on.
+    fn check_one_of(&mut self, kinds: &[TokenKind]) -> Option<TokenKind> {
+        let mut found_kind = None;
+
+        for &kind in kinds {
+            if self.check(kind) {
+                found_kind = Some(kind);
+            }
+        }
+
+        found_kind
+    }
+
+    /// Whether the next token is the contextual keyword, which is scanned as
+    /// an identifier, see `Keyword::is_contextual`. The keyword is one of the
+    /// tokens expected next from now on.
+    fn check_contextual(&mut self, keyword: Keyword) -> bool {
+        let kind = TokenKind::Keyword(keyword);
+
+        if !self.expected_tokens.contains(&kind) {
+            self.expected_tokens.push(kind);
+        }
+
+        self.look_ahead(0).is_some_and(|tok| {
+            tok.kind == TokenKind::Identifier
+                && self.scanner.token_text(tok.span) == keyword.as_str()
         })
     }
 
-    fn peek(&self) -> Option<Token> {
-        if self.current_token_idx < self.tokens.len() {
-            Some(self.tokens[self.current_token_idx])
+    /// Consumes the next token if it's the contextual keyword.
+    fn eat_contextual(&mut self, keyword: Keyword) -> Option<Token> {
+        if self.check_contextual(keyword) {
+            self.consume()
         } else {
             None
         }
     }
 
-    fn look_ahead(&self, amount: usize) -> Option<Token> {
-        let look_ahead_idx = self.current_token_idx + amount;
-
-        if look_ahead_idx < self.tokens.len() {
-            Some(self.tokens[look_ahead_idx])
+    /// Consumes the next token if it's of the kind.
+    fn eat(&mut self, kind: TokenKind) -> Option<Token> {
+        if self.check(kind) {
+            self.consume()
         } else {
             None
         }
     }
 
+    /// Consumes the next token, failing if it isn't of the kind.
+    fn expect(&mut self, kind: TokenKind) -> Option<Token> {
+        self.expect_one_of(&[kind])
+    }
+
+    /// Consumes the next token, failing if it's none of the kinds.
+    fn expect_one_of(&mut self, kinds: &[TokenKind]) -> Option<Token> {
+        if self.check_one_of(kinds).is_some() {
+            self.consume()
+        } else {
+            self.fail_on_unexpected_token()
+        }
+    }
+
+    /// Stops the parser, as the next token is none of the expected ones.
+    fn fail_on_unexpected_token<T>(&mut self) -> Option<T> {
+        let found_tok = self.peek();
+        let span = found_tok.map_or_else(
+            || {
+                let eof_pos = self.scanner.end_pos();
+
+                Span {
+                    start: eof_pos,
+                    end: eof_pos,
+                }
+            },
+            |tok| tok.span,
+        );
+
+        self.syntax_error
+            .get_or_insert(CompileError::ExpectedButFound {
+                expected: mem::take(&mut self.expected_tokens),
+                found: found_tok.map(|tok| tok.kind),
+                span,
+            });
+
+        None
+    }
+
+    fn prev_tok_span(&self) -> Span {
+        self.prev_token
+            .expect("a token should have been consumed")
+            .span
+    }
+
+    fn peek(&mut self) -> Option<Token> {
+        self.look_ahead(0)
+    }
+
+    fn look_ahead(&mut self, amount: usize) -> Option<Token> {
+        self.fill_lookahead(amount)?;
+
+        Some(self.lookahead_tokens.get(amount))
+    }
+
+    /// Kind of the token `amount` tokens ahead, which is all that checking for
+    /// a token reads, so it doesn't touch the spans.
+    fn look_ahead_kind(&mut self, amount: usize) -> Option<TokenKind> {
+        self.fill_lookahead(amount)?;
+
+        Some(self.lookahead_tokens.kind(amount))
+    }
+
+    fn fill_lookahead(&mut self, amount: usize) -> Option<()> {
+        while self.lookahead_tokens.len() <= amount {
+            let token = self.scan_known_token()?;
+            self.lookahead_tokens.push_back(token);
+        }
+
+        Some(())
+    }
+
+    /// Scans the next token, reporting the chars that start none and going on
+    /// as if they weren't there.
+    fn scan_known_token(&mut self) -> Option<Token> {
+        loop {
+            let token = self.scanner.next()?;
+
+            if token.kind != TokenKind::Unknown {
+                return Some(token);
+            }
+
+            let ch = self.scanner.token_text(token.span).chars().next().unwrap();
+
+            self.recovered_errors.push(CompileError::UnknownChar {
+                ch,
+                span: token.span,
+            });
+        }
+    }
+
     fn consume(&mut self) -> Option<Token> {
-        let peeked_tok = self.peek();
+        let token = self.peek()?;
+        self.lookahead_tokens.pop_front();
+        self.prev_token = Some(token);
+        self.expected_tokens.clear();
 
-        if self.current_token_idx < self.tokens.len() {
-            self.current_token_idx += 1;
+        Some(token)
+    }
+}
+
+/// Ring buffer of the tokens that the parser looked ahead at, which never
+/// holds more than `MAX_LOOKAHEAD` of them, so parsing takes as much memory
+/// for tokens whatever the size of the file, and it's copied for free when the
+/// parser speculates. Kinds and spans are kept apart, as the parser checks the
+/// kinds of the tokens ahead far more often than it reads their spans.
+#[derive(Clone, Copy)]
+struct Lookahead {
+    kinds: [TokenKind; MAX_LOOKAHEAD],
+    spans: [Span; MAX_LOOKAHEAD],
+    start: usize,
+    len: usize,
+}
+
+impl Default for Lookahead {
+    fn default() -> Lookahead {
+        let empty_span = Span {
+            start: BytePos(0),
+            end: BytePos(0),
+        };
+
+        Lookahead {
+            kinds: [TokenKind::Unknown; MAX_LOOKAHEAD],
+            spans: [empty_span; MAX_LOOKAHEAD],
+            start: 0,
+            len: 0,
         }
+    }
+}
+
+impl Lookahead {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn get(&self, idx: usize) -> Token {
+        debug_assert!(idx < self.len, "token {} wasn't looked ahead at", idx);
+
+        let slot = (self.start + idx) % MAX_LOOKAHEAD;
+
+        Token {
+            kind: self.kinds[slot],
+            span: self.spans[slot],
+        }
+    }
+
+    fn kind(&self, idx: usize) -> TokenKind {
+        debug_assert!(idx < self.len, "token {} wasn't looked ahead at", idx);
+
+        self.kinds[(self.start + idx) % MAX_LOOKAHEAD]
+    }
+
+    fn push_back(&mut self, token: Token) {
+        assert!(
+            self.len < MAX_LOOKAHEAD,
+            "the parser looks at most {} tokens ahead",
+            MAX_LOOKAHEAD
+        );
+
+        let slot = (self.start + self.len) % MAX_LOOKAHEAD;
+        self.kinds[slot] = token.kind;
+        self.spans[slot] = token.span;
+        self.len += 1;
+    }
+
+    fn pop_front(&mut self) {
+        debug_assert!(self.len > 0, "no token was looked ahead at");
+
+        self.start = (self.start + 1) % MAX_LOOKAHEAD;
+        self.len -= 1;
+    }
+}
 
-        peeked_tok
+fn binary_op(tok_kind: TokenKind) -> Option<BinaryOp> {
+    match tok_kind {
+        TokenKind::Plus => Some(BinaryOp::Add),
+        TokenKind::Dash => Some(BinaryOp::Sub),
+        TokenKind::Star => Some(BinaryOp::Mul),
+        TokenKind::Slash => Some(BinaryOp::Div),
+        TokenKind::Percent => Some(BinaryOp::Rem),
+        _ => None,
     }
 }
@@ -0,0 +1,573 @@
+use std::fmt::Write;
+
+use crate::ast::{
+    CompoundExpr, Const, Expr, ForExpr, ForIteration, Function, IfExpr, IndexExpr, LoopKeyword,
+    MatchExpr, Param, Program, RangeKind, Type, DISCARD_IDENTIFIER,
+};
+use crate::compiler_context::CompilerContext;
+use crate::cst::lossless_tokens;
+use crate::interner::Symbol;
+use crate::scanner::{BytePos, Span};
+
+/// How tightly casts bind their operand, which is tighter than any binary
+/// operator, but not as tight as field accesses and indexing.
+const CAST_PRECEDENCE: u8 = 3;
+
+/// Prints a program back to Sophia source code, which parses to the same AST,
+/// whether the parser built the AST or a tool built or transformed it.
+///
+/// The AST keeps no layout, so the source code is laid out anew: a statement
+/// per line, indented by four spaces. Operands are parenthesized wherever the
+/// precedence of operators asks for it, even if the AST has no parentheses
+/// there.
+///
+/// Nor does the AST keep comments, so they're carried over from the source
+/// code by where they are in it: a comment is printed on a line of its own
+/// before the statement or the declaration that follows it, or before the end
+/// of the block it ends, unless it follows a statement on the same line, as it
+/// does then too. Comments within a statement are thus printed after it.
+pub(crate) struct SourcePrinter<'ctx> {
+    ctx: &'ctx CompilerContext,
+    text: String,
+    indent: usize,
+    /// Spans of the comments of the source code, in order.
+    comments: Vec<Span>,
+    /// Index of the first comment of `comments` that's left to print.
+    next_comment: usize,
+}
+
+impl<'ctx> SourcePrinter<'ctx> {
+    pub(crate) fn new(ctx: &'ctx CompilerContext) -> SourcePrinter<'ctx> {
+        SourcePrinter {
+            ctx,
+            text: String::new(),
+            indent: 0,
+            comments: comment_spans(ctx.get_source_code()),
+            next_comment: 0,
+        }
+    }
+
+    pub(crate) fn print_program(mut self, program: Program) -> String {
+        for decl in program.decls {
+            self.print_comments_before(decl.identifier_span.start);
+
+            if let Expr::Extern(_) = decl.value {
+                self.text.push_str("extern ");
+            }
+
+            write!(self.text, "{} :: ", self.name(decl.identifier)).unwrap();
+            self.print_expr(decl.value);
+            self.print_trailing_comment(decl.value.span().end);
+            self.text.push('\n');
+        }
+
+        self.print_comments_before(BytePos(usize::MAX));
+
+        self.text
+    }
+
+    fn print_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Const(const_expr) => match const_expr.value {
+                Const::IntegerConstant { value } => write!(self.text, "{}", value).unwrap(),
+                Const::SuffixedIntegerConstant { value, ty } => {
+                    write!(self.text, "{}{}", value, ty).unwrap()
+                }
+            },
+            Expr::BindRef(bind_ref) => self.text.push_str(self.name(bind_ref.identifier)),
+            Expr::BindDef(bind_def) => {
+                let name = self.name(bind_def.identifier);
+
+                // Discarding is spelled like an assignment, though both parse the same.
+                match bind_def.ty {
+                    Some(ty) => write!(self.text, "{} : {} = ", name, ty).unwrap(),
+                    None if name == DISCARD_IDENTIFIER => self.text.push_str("_ = "),
+                    None => write!(self.text, "{} := ", name).unwrap(),
+                }
+
+                self.print_expr(bind_def.value);
+            }
+            Expr::Function(function) => self.print_function(*function),
+            Expr::Extern(extern_fn) => {
+                self.print_signature(extern_fn.parameters, extern_fn.return_type);
+                self.text.push(';');
+            }
+            Expr::Struct(struct_def) => {
+                self.text.push_str("struct {");
+
+                for (idx, field) in struct_def.fields.iter().enumerate() {
+                    let separator = if idx > 0 { ", " } else { " " };
+
+                    write!(
+                        self.text,
+                        "{}{}: {}",
+                        separator,
+                        self.name(field.identifier),
+                        field.ty
+                    )
+                    .unwrap();
+                }
+
+                if !struct_def.fields.is_empty() {
+                    self.text.push(' ');
+                }
+
+                self.text.push('}');
+            }
+            Expr::StructLit(struct_lit_expr) => {
+                write!(self.text, "{} {{", self.name(struct_lit_expr.identifier)).unwrap();
+
+                for (idx, field) in struct_lit_expr.fields.iter().enumerate() {
+                    let separator = if idx > 0 { ", " } else { " " };
+
+                    write!(self.text, "{}{}: ", separator, self.name(field.identifier)).unwrap();
+                    self.print_expr(field.value);
+                }
+
+                if !struct_lit_expr.fields.is_empty() {
+                    self.text.push(' ');
+                }
+
+                self.text.push('}');
+            }
+            Expr::Field(field_expr) => {
+                // Fields are accessed on the operand right before the `.`, so
+                // any operation is parenthesized.
+                self.print_operand(field_expr.base, u8::MAX);
+                write!(self.text, ".{}", self.name(field_expr.identifier)).unwrap();
+            }
+            Expr::Enum(enum_def) => {
+                self.text.push_str("enum {");
+
+                for (idx, variant) in enum_def.variants.iter().enumerate() {
+                    let separator = if idx > 0 { ", " } else { " " };
+
+                    write!(self.text, "{}{}", separator, self.name(variant.identifier)).unwrap();
+
+                    if let Some(payload) = variant.payload {
+                        write!(self.text, "({})", payload).unwrap();
+                    }
+                }
+
+                if !enum_def.variants.is_empty() {
+                    self.text.push(' ');
+                }
+
+                self.text.push('}');
+            }
+            Expr::Variant(variant_expr) => {
+                write!(
+                    self.text,
+                    "{}::{}",
+                    self.name(variant_expr.enum_identifier),
+                    self.name(variant_expr.variant_identifier)
+                )
+                .unwrap();
+
+                if let Some(payload) = variant_expr.payload {
+                    self.text.push('(');
+                    self.print_expr(payload);
+                    self.text.push(')');
+                }
+            }
+            Expr::Tuple(tuple_expr) => {
+                self.text.push('(');
+
+                for (idx, element) in tuple_expr.elements.iter().enumerate() {
+                    if idx > 0 {
+                        self.text.push_str(", ");
+                    }
+
+                    self.print_expr(element);
+                }
+
+                // Without the comma, a tuple of one element would be
+                // parenthesized instead.
+                if tuple_expr.elements.len() == 1 {
+                    self.text.push(',');
+                }
+
+                self.text.push(')');
+            }
+            Expr::TupleField(tuple_field_expr) => {
+                self.print_operand(tuple_field_expr.base, u8::MAX);
+                write!(self.text, ".{}", tuple_field_expr.index).unwrap();
+            }
+            Expr::TupleBindDef(tuple_bind_def) => {
+                for (idx, binding) in tuple_bind_def.bindings.iter().enumerate() {
+                    if idx > 0 {
+                        self.text.push_str(", ");
+                    }
+
+                    self.text.push_str(self.name(binding.identifier));
+                }
+
+                self.text.push_str(" := ");
+                self.print_expr(tuple_bind_def.value);
+            }
+            Expr::Array(array_expr) => {
+                self.text.push('[');
+
+                for (idx, element) in array_expr.elements.iter().enumerate() {
+                    if idx > 0 {
+                        self.text.push_str(", ");
+                    }
+
+                    self.print_expr(element);
+                }
+
+                self.text.push(']');
+            }
+            Expr::Index(index_expr) => self.print_index_expr(*index_expr),
+            Expr::IndexAssign(index_assign_expr) => {
+                self.print_index_expr(index_assign_expr.target);
+                self.text.push_str(" = ");
+                self.print_expr(index_assign_expr.value);
+            }
+            Expr::If(if_expr) => self.print_if_expr(*if_expr),
+            Expr::Match(match_expr) => self.print_match_expr(*match_expr),
+            Expr::For(for_expr) => self.print_for_expr(*for_expr),
+            Expr::Break(break_expr) => {
+                self.text.push_str("break");
+
+                if let Some(value) = break_expr.value {
+                    self.text.push(' ');
+                    self.print_expr(value);
+                }
+            }
+            Expr::Continue(_) => self.text.push_str("continue"),
+            Expr::Compound(compound_expr) => self.print_compound_expr(*compound_expr),
+            Expr::Semi(expr) => {
+                self.print_expr(expr);
+                self.text.push(';');
+            }
+            Expr::FnCall(fn_call_expr) => {
+                write!(self.text, "{}(", self.name(fn_call_expr.identifier)).unwrap();
+
+                for (idx, arg) in fn_call_expr.args.iter().enumerate() {
+                    if idx > 0 {
+                        self.text.push_str(", ");
+                    }
+
+                    self.print_expr(arg);
+                }
+
+                self.text.push(')');
+            }
+            Expr::Binary(binary_expr) => {
+                let (operand, operations) = binary_expr.chain();
+
+                // Operators are left-associative, so an operation of the same
+                // precedence only needs parentheses on the right-hand side. On
+                // the left-hand side, they wrap all of the chain before the
+                // operation that binds more tightly.
+                let closes_parens: Vec<_> = operations
+                    .windows(2)
+                    .map(|pair| pair[0].op.precedence() < pair[1].op.precedence())
+                    .collect();
+
+                for _ in closes_parens.iter().filter(|&&closes_paren| closes_paren) {
+                    self.text.push('(');
+                }
+
+                self.print_operand(operand, operations[0].op.precedence());
+
+                for (idx, binary_expr) in operations.iter().enumerate() {
+                    write!(self.text, " {} ", binary_expr.op).unwrap();
+                    self.print_operand(binary_expr.rhs, binary_expr.op.precedence() + 1);
+
+                    if closes_parens.get(idx) == Some(&true) {
+                        self.text.push(')');
+                    }
+                }
+            }
+            Expr::Cast(cast_expr) => {
+                self.print_operand(cast_expr.expr, CAST_PRECEDENCE);
+                write!(self.text, " as {}", cast_expr.ty).unwrap();
+            }
+            Expr::Paren(paren_expr) => {
+                self.text.push('(');
+                self.print_expr(paren_expr.expr);
+                self.text.push(')');
+            }
+        }
+    }
+
+    fn print_index_expr(&mut self, index_expr: IndexExpr) {
+        self.print_operand(index_expr.base, u8::MAX);
+        self.text.push('[');
+        self.print_expr(index_expr.index);
+        self.text.push(']');
+    }
+
+    /// Prints an operand of a binary operation, parenthesized if it binds
+    /// less tightly than `min_precedence`.
+    fn print_operand(&mut self, operand: &Expr, min_precedence: u8) {
+        let needs_parens = match operand {
+            Expr::Binary(binary_expr) => binary_expr.op.precedence() < min_precedence,
+            Expr::Cast(_) => CAST_PRECEDENCE < min_precedence,
+            // The binding would take the rest of the operation as its value.
+            Expr::BindDef(_) => true,
+            _ => false,
+        };
+
+        if needs_parens {
+            self.text.push('(');
+            self.print_expr(operand);
+            self.text.push(')');
+        } else {
+            self.print_expr(operand);
+        }
+    }
+
+    fn print_function(&mut self, function: Function) {
+        self.print_signature(function.parameters, function.return_type);
+        self.text.push(' ');
+        self.print_compound_expr(function.body);
+    }
+
+    fn print_signature(&mut self, parameters: &[Param], return_type: Type) {
+        self.text.push('(');
+
+        for (idx, param) in parameters.iter().enumerate() {
+            if idx > 0 {
+                self.text.push_str(", ");
+            }
+
+            write!(self.text, "{}: {}", self.name(param.identifier), param.ty).unwrap();
+        }
+
+        self.text.push(')');
+
+        if return_type != Type::Unit {
+            write!(self.text, " -> {}", return_type).unwrap();
+        }
+    }
+
+    fn print_if_expr(&mut self, if_expr: IfExpr) {
+        self.text.push_str("if ");
+        self.print_cond_expr(if_expr.cond_expr);
+        self.text.push(' ');
+        self.print_compound_expr(if_expr.true_branch);
+
+        for branch in if_expr.else_if_branches {
+            self.text.push_str(" else if ");
+            self.print_cond_expr(branch.cond_expr);
+            self.text.push(' ');
+            self.print_compound_expr(branch.true_branch);
+        }
+
+        if let Some(final_branch) = if_expr.final_branch {
+            self.text.push_str(" else ");
+            self.print_compound_expr(final_branch);
+        }
+    }
+
+    fn print_match_expr(&mut self, match_expr: MatchExpr) {
+        self.text.push_str("match ");
+        self.print_cond_expr(match_expr.scrutinee);
+
+        if match_expr.arms.is_empty() {
+            self.text.push_str(" {}");
+            return;
+        }
+
+        self.text.push_str(" {\n");
+        self.indent += 1;
+
+        for arm in match_expr.arms {
+            self.text.push_str(&"    ".repeat(self.indent));
+            write!(self.text, "{} => ", arm.pattern.kind).unwrap();
+            self.print_expr(arm.expr);
+
+            // Blocks end arms by themselves.
+            if !matches!(arm.expr, Expr::Compound(_)) {
+                self.text.push(',');
+            }
+
+            self.text.push('\n');
+        }
+
+        self.indent -= 1;
+        self.text.push_str(&"    ".repeat(self.indent));
+        self.text.push('}');
+    }
+
+    fn print_for_expr(&mut self, for_expr: ForExpr) {
+        if for_expr.is_main_loop {
+            self.text.push_str("#[main_loop] ");
+        }
+
+        self.text.push_str(match for_expr.keyword {
+            LoopKeyword::For => "for ",
+            LoopKeyword::Loop => "loop ",
+        });
+
+        match for_expr.iteration {
+            Some(ForIteration::Conditional { cond_expr }) => {
+                self.print_cond_expr(cond_expr);
+                self.text.push(' ');
+            }
+            Some(ForIteration::Iterative {
+                identifier,
+                start_expr,
+                end_expr,
+                range_kind,
+                step_expr,
+                is_reversed,
+                ..
+            }) => {
+                write!(self.text, "{} : ", self.name(identifier)).unwrap();
+
+                if is_reversed {
+                    self.text.push_str("rev ");
+                }
+
+                self.print_expr(start_expr);
+                self.text.push_str(match range_kind {
+                    RangeKind::Exclusive => "..",
+                    RangeKind::Inclusive => "..=",
+                });
+                self.print_expr(end_expr);
+
+                if let Some(step_expr) = step_expr {
+                    self.text.push_str(" step ");
+                    self.print_expr(step_expr);
+                }
+
+                self.text.push(' ');
+            }
+            Some(ForIteration::InCollection {
+                identifier,
+                collection_expr,
+                ..
+            }) => {
+                write!(self.text, "{} : ", self.name(identifier)).unwrap();
+                self.print_expr(collection_expr);
+                self.text.push(' ');
+            }
+            None => {}
+        }
+
+        self.print_compound_expr(for_expr.body);
+
+        if let Some(else_branch) = for_expr.else_branch {
+            self.text.push_str(" else ");
+            self.print_compound_expr(else_branch);
+        }
+    }
+
+    /// Prints the condition of an if-expression or of a for-loop, or the
+    /// scrutinee of a match-expression, which is parenthesized if it's a
+    /// block, as it'd be taken for the body otherwise.
+    fn print_cond_expr(&mut self, cond_expr: &Expr) {
+        if let Expr::Compound(_) = cond_expr {
+            self.text.push('(');
+            self.print_expr(cond_expr);
+            self.text.push(')');
+        } else {
+            self.print_expr(cond_expr);
+        }
+    }
+
+    fn print_compound_expr(&mut self, compound_expr: CompoundExpr) {
+        let end = compound_expr.span.end;
+
+        if compound_expr.exprs.is_empty() && !self.has_comment_before(end) {
+            self.text.push_str("{}");
+            return;
+        }
+
+        self.text.push_str("{\n");
+        self.indent += 1;
+
+        for expr in compound_expr.exprs {
+            self.print_comments_before(expr.span().start);
+            self.text.push_str(&"    ".repeat(self.indent));
+            self.print_expr(expr);
+            self.print_trailing_comment(expr.span().end);
+            self.text.push('\n');
+        }
+
+        self.print_comments_before(end);
+        self.indent -= 1;
+        self.text.push_str(&"    ".repeat(self.indent));
+        self.text.push('}');
+    }
+
+    fn has_comment_before(&self, pos: BytePos) -> bool {
+        (self.comments.get(self.next_comment)).is_some_and(|comment| comment.start.0 < pos.0)
+    }
+
+    /// Prints the comments left that start before the position, each on a
+    /// line of its own.
+    fn print_comments_before(&mut self, pos: BytePos) {
+        while self.has_comment_before(pos) {
+            let comment = self.comment_text(self.next_comment);
+
+            writeln!(self.text, "{}{}", "    ".repeat(self.indent), comment).unwrap();
+            self.next_comment += 1;
+        }
+    }
+
+    /// Prints the next comment after the code that ends at the position, if
+    /// it's on the same line as it, with only a `;` in between, if anything.
+    fn print_trailing_comment(&mut self, end: BytePos) {
+        let Some(comment) = self.comments.get(self.next_comment) else {
+            return;
+        };
+
+        let source_code = self.ctx.get_source_code();
+        let is_trailing = (comment.start.0 >= end.0)
+            && (source_code[end.0..comment.start.0].chars())
+                .all(|ch| matches!(ch, ';' | ' ' | '\t'));
+
+        if is_trailing {
+            write!(self.text, " {}", self.comment_text(self.next_comment)).unwrap();
+            self.next_comment += 1;
+        }
+    }
+
+    fn comment_text(&self, comment_idx: usize) -> &'ctx str {
+        let span = self.comments[comment_idx];
+
+        self.ctx.get_source_code()[span.start.0..span.end.0].trim_end()
+    }
+
+    fn name(&self, symbol: Symbol) -> &'static str {
+        self.ctx.resolve_symbol(symbol)
+    }
+}
+
+/// Spans of the comments of the source code, in order, which go from `//` to
+/// the end of the line.
+fn comment_spans(source_code: &str) -> Vec<Span> {
+    let (tokens, trailing_trivia) = lossless_tokens(source_code);
+    let trivias = (tokens.iter())
+        .map(|token| {
+            let start = token.token.span.start.0 - token.leading_trivia.len();
+
+            (start, token.leading_trivia)
+        })
+        .chain([(source_code.len() - trailing_trivia.len(), trailing_trivia)]);
+    let mut spans = vec![];
+
+    for (trivia_start, trivia) in trivias {
+        let mut offset = 0;
+
+        while let Some(comment_offset) = trivia[offset..].find("//") {
+            let start = offset + comment_offset;
+            let end = trivia[start..]
+                .find('\n')
+                .map_or(trivia.len(), |len| start + len);
+
+            spans.push(Span {
+                start: BytePos(trivia_start + start),
+                end: BytePos(trivia_start + end),
+            });
+            offset = end;
+        }
+    }
+
+    spans
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+
+use crate::ast::{BinaryOp, Type};
+
+/// How integers behave on the target, so that programs for microcontrollers
+/// can be compiled, through the C backend, with the semantics of their
+/// hardware. The default matches the native code.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct IntProfile {
+    pub(crate) width: IntWidth,
+    pub(crate) overflow: Overflow,
+    /// Whether the target has a division instruction. Without one, divisions
+    /// call into a software routine instead.
+    pub(crate) has_hardware_division: bool,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub(crate) enum IntWidth {
+    Bits16,
+    #[default]
+    Bits32,
+}
+
+/// What happens when the result of an operation doesn't fit in an integer.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub(crate) enum Overflow {
+    /// The result wraps around, in two's complement.
+    #[default]
+    Wrap,
+    /// The program stops with an error.
+    Trap,
+}
+
+impl Default for IntProfile {
+    fn default() -> IntProfile {
+        IntProfile {
+            width: IntWidth::default(),
+            overflow: Overflow::default(),
+            has_hardware_division: true,
+        }
+    }
+}
+
+impl IntWidth {
+    pub(crate) fn bits(self) -> u32 {
+        match self {
+            IntWidth::Bits16 => 16,
+            IntWidth::Bits32 => 32,
+        }
+    }
+
+    pub(crate) fn min(self) -> i32 {
+        match self {
+            IntWidth::Bits16 => i16::MIN.into(),
+            IntWidth::Bits32 => i32::MIN,
+        }
+    }
+
+    pub(crate) fn max(self) -> i32 {
+        match self {
+            IntWidth::Bits16 => i16::MAX.into(),
+            IntWidth::Bits32 => i32::MAX,
+        }
+    }
+
+    pub(crate) fn contains(self, value: i64) -> bool {
+        (i64::from(self.min())..=i64::from(self.max())).contains(&value)
+    }
+
+    /// Wraps the value around, in two's complement, until it fits.
+    fn wrap(self, value: i64) -> i32 {
+        match self {
+            IntWidth::Bits16 => (value as i16).into(),
+            IntWidth::Bits32 => value as i32,
+        }
+    }
+}
+
+impl fmt::Display for IntWidth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-bit", self.bits())
+    }
+}
+
+impl IntProfile {
+    /// Computes the operation, or returns `None` if it should trap, be it on
+    /// overflow, or on a division by zero.
+    pub(crate) fn eval(self, op: BinaryOp, lhs: i32, rhs: i32) -> Option<i32> {
+        let (lhs, rhs) = (i64::from(lhs), i64::from(rhs));
+
+        let value = match op {
+            BinaryOp::Add => lhs + rhs,
+            BinaryOp::Sub => lhs - rhs,
+            BinaryOp::Mul => lhs * rhs,
+            // Division traps on overflow regardless of the profile, just like
+            // the hardware does.
+            BinaryOp::Div | BinaryOp::Rem => {
+                if rhs == 0 || !self.width.contains(lhs / rhs) {
+                    return None;
+                }
+
+                if op == BinaryOp::Div {
+                    lhs / rhs
+                } else {
+                    lhs % rhs
+                }
+            }
+        };
+
+        match self.overflow {
+            Overflow::Wrap => Some(self.width.wrap(value)),
+            Overflow::Trap => self.width.contains(value).then_some(value as i32),
+        }
+    }
+
+    /// Computes the operation on integers of a type other than `i32`, which
+    /// overflow at their type's bounds rather than at the profile's width, just
+    /// like Rust's integers of the same name. Returns `None` if it should trap,
+    /// like `eval` does.
+    pub(crate) fn eval_wide(self, op: BinaryOp, lhs: i128, rhs: i128, ty: Type) -> Option<i128> {
+        let (min, max) = ty.int_range().expect("only integers are computed");
+
+        // Operands are at most 64 bits wide, so only their product can overflow
+        // an `i128`, in which case it doesn't fit in the type either.
+        let value = match op {
+            BinaryOp::Add => Some(lhs + rhs),
+            BinaryOp::Sub => Some(lhs - rhs),
+            BinaryOp::Mul => lhs.checked_mul(rhs),
+            BinaryOp::Div | BinaryOp::Rem => {
+                if rhs == 0 || !(min..=max).contains(&(lhs / rhs)) {
+                    return None;
+                }
+
+                Some(if op == BinaryOp::Div {
+                    lhs / rhs
+                } else {
+                    lhs % rhs
+                })
+            }
+        };
+
+        match self.overflow {
+            Overflow::Wrap => Some(wrap(ty, value.unwrap_or_else(|| lhs.wrapping_mul(rhs)))),
+            Overflow::Trap => value.filter(|value| (min..=max).contains(value)),
+        }
+    }
+
+    /// Converts the integer to the type, truncating it to the type's width,
+    /// which `i32` takes from the profile, or extending it, by its sign if
+    /// it's negative. Casts never trap, whatever the profile says about
+    /// overflows, just like Rust's `as` casts.
+    pub(crate) fn cast(self, value: i128, ty: Type) -> i128 {
+        if ty == Type::I32 {
+            self.width.wrap(value as i64).into()
+        } else {
+            wrap(ty, value)
+        }
+    }
+}
+
+/// Wraps the value around, in two's complement, until it fits in the type.
+fn wrap(ty: Type, value: i128) -> i128 {
+    match ty {
+        Type::I8 => (value as i8).into(),
+        Type::I16 => (value as i16).into(),
+        Type::I32 => (value as i32).into(),
+        Type::I64 => (value as i64).into(),
+        Type::U8 => (value as u8).into(),
+        Type::U16 => (value as u16).into(),
+        Type::U32 => (value as u32).into(),
+        Type::U64 | Type::Usize => (value as u64).into(),
+        Type::Unit
+        | Type::Named(_)
+        | Type::Tuple(_)
+        | Type::Array { .. }
+        | Type::Function { .. } => unreachable!("only integers are wrapped"),
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::compiler_context::CompilerContext;
+use crate::interner::Symbol;
+
+/// Time and allocations that every function of a program took up while the
+/// interpreter ran it.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct ExecutionProfile {
+    /// Functions in the order they were first called.
+    pub(crate) functions: Vec<FunctionProfile>,
+    /// Time spent in each stack of calls, outermost function first, not
+    /// counting the time spent in calls made from the innermost one.
+    pub(crate) stacks: Vec<(Vec<String>, Duration)>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct FunctionProfile {
+    pub(crate) name: String,
+    pub(crate) calls: u64,
+    /// Time spent in the function, not counting the time spent in the
+    /// functions it calls.
+    pub(crate) self_time: Duration,
+    /// Time spent in the function, counting the time spent in the functions it
+    /// calls. The time of recursive calls is only counted once.
+    pub(crate) total_time: Duration,
+    /// Environments the interpreter allocated while running the function,
+    /// i.e., its scopes and the argument lists of the calls it makes.
+    pub(crate) allocations: u64,
+}
+
+impl ExecutionProfile {
+    /// Table of the functions, the ones that took up the most time of their
+    /// own first.
+    pub(crate) fn to_flat_profile(&self) -> String {
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by(|a, b| {
+            b.self_time
+                .cmp(&a.self_time)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let mut text = format!(
+            "{:>12} {:>12} {:>10} {:>10}  {}\n",
+            "self", "total", "calls", "allocs", "function"
+        );
+
+        for function in functions {
+            writeln!(
+                text,
+                "{:>12} {:>12} {:>10} {:>10}  {}",
+                format_duration(function.self_time),
+                format_duration(function.total_time),
+                function.calls,
+                function.allocations,
+                function.name
+            )
+            .unwrap();
+        }
+
+        text
+    }
+
+    /// Stacks in the folded format that flame graph tools read, i.e., a line
+    /// per stack of calls with the functions separated by semicolons, followed
+    /// by the microseconds spent in it.
+    pub(crate) fn to_folded_stacks(&self) -> String {
+        let mut lines: Vec<_> = self
+            .stacks
+            .iter()
+            .map(|(stack, time)| format!("{} {}", stack.join(";"), time.as_micros()))
+            .collect();
+        lines.sort();
+
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Records the calls the interpreter makes, to build an `ExecutionProfile`
+/// once the program is done running.
+#[derive(Default)]
+pub(crate) struct Profiler {
+    call_stack: Vec<Frame>,
+    stats: Vec<(Symbol, FunctionStats)>,
+    stat_idx_by_function: HashMap<Symbol, usize>,
+    self_time_by_stack: HashMap<Vec<Symbol>, Duration>,
+}
+
+struct Frame {
+    function: Symbol,
+    start: Instant,
+    /// Time spent in the calls made from this one so far.
+    callee_time: Duration,
+}
+
+#[derive(Default)]
+struct FunctionStats {
+    calls: u64,
+    self_time: Duration,
+    total_time: Duration,
+    allocations: u64,
+}
+
+impl Profiler {
+    pub(crate) fn enter_function(&mut self, function: Symbol) {
+        self.stats_of(function).calls += 1;
+        self.call_stack.push(Frame {
+            function,
+            start: Instant::now(),
+            callee_time: Duration::ZERO,
+        });
+    }
+
+    pub(crate) fn exit_function(&mut self) {
+        let stack: Vec<_> = self.call_stack.iter().map(|frame| frame.function).collect();
+        let frame = self.call_stack.pop().expect("a function was entered");
+        let total_time = frame.start.elapsed();
+        let self_time = total_time.saturating_sub(frame.callee_time);
+        // Counting the time of a recursive call would count it twice, as the
+        // outermost call of the function counts it already.
+        let is_recursive = self
+            .call_stack
+            .iter()
+            .any(|caller| caller.function == frame.function);
+
+        if let Some(caller) = self.call_stack.last_mut() {
+            caller.callee_time += total_time;
+        }
+
+        let stats = self.stats_of(frame.function);
+        stats.self_time += self_time;
+
+        if !is_recursive {
+            stats.total_time += total_time;
+        }
+
+        *self.self_time_by_stack.entry(stack).or_default() += self_time;
+    }
+
+    /// Counts an allocation the interpreter made for the function that's
+    /// running.
+    pub(crate) fn record_allocation(&mut self) {
+        if let Some(frame) = self.call_stack.last() {
+            let function = frame.function;
+            self.stats_of(function).allocations += 1;
+        }
+    }
+
+    pub(crate) fn finish(self, ctx: &CompilerContext) -> ExecutionProfile {
+        let name = |function: Symbol| ctx.resolve_symbol(function).to_owned();
+        let mut stacks: Vec<_> = self
+            .self_time_by_stack
+            .into_iter()
+            .map(|(stack, time)| (stack.into_iter().map(name).collect::<Vec<_>>(), time))
+            .collect();
+        stacks.sort();
+
+        ExecutionProfile {
+            functions: self
+                .stats
+                .into_iter()
+                .map(|(function, stats)| FunctionProfile {
+                    name: name(function),
+                    calls: stats.calls,
+                    self_time: stats.self_time,
+                    total_time: stats.total_time,
+                    allocations: stats.allocations,
+                })
+                .collect(),
+            stacks,
+        }
+    }
+
+    fn stats_of(&mut self, function: Symbol) -> &mut FunctionStats {
+        let stat_idx = *self
+            .stat_idx_by_function
+            .entry(function)
+            .or_insert_with(|| {
+                self.stats.push((function, FunctionStats::default()));
+                self.stats.len() - 1
+            });
+
+        &mut self.stats[stat_idx].1
+    }
+}
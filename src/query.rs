@@ -0,0 +1,559 @@
+use std::rc::Rc;
+
+use crate::ast::{Expr, Program};
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::{CompileWarning, Diagnostic};
+use crate::driver::{self, Options};
+use crate::host::HashMap;
+use crate::incremental::{IncrementalParse, MovedDecl, Reparse, TextEdit};
+use crate::layout::Layouts;
+use crate::lint::Linter;
+use crate::manifest::ContentHash;
+use crate::resolve::Resolver;
+use crate::scanner::Span;
+use crate::source_map::SourceMap;
+use crate::typeck::TypeChecker;
+use crate::unused::{self, UnusedChecker};
+
+/// Checks versions of a program, e.g., as it's edited, by running memoized
+/// queries whose results are reused for as long as what they depend on stays
+/// the same, so that checking a version only redoes the work for the
+/// declarations that changed since the last one.
+///
+/// The queries are:
+///
+/// - `parse(file)`, which lexes and parses the file, keyed by the hash of its
+///   contents, only parsing again the declarations that an edit of the last
+///   version touched, see `IncrementalParse`;
+/// - `resolve(decl)`, which resolves the names in a declaration, keyed by the
+///   hash of the declaration's code and the hash of the names of every
+///   declaration;
+/// - `typecheck(decl)`, which type checks and lints a declaration, keyed by the
+///   hash of the declaration's code and the hash of the signatures of every
+///   declaration, i.e., of their code up to the bodies of functions.
+///
+/// Diagnostics are kept relative to where their declarations start, so they
+/// stay valid when code above them grows or shrinks. Only the results for the
+/// last version are kept, as the next version is most likely an edit of it.
+///
+/// Every version is expected to be checked with the same options. Scripts
+/// aren't supported, as their statements aren't declarations.
+#[derive(Default)]
+pub(crate) struct QueryDatabase {
+    parse: Option<Parse>,
+    resolved_decls: HashMap<QueryKey, ResolvedDecl>,
+    checked_decls: HashMap<QueryKey, Rc<CheckedDecl>>,
+    stats: QueryStats,
+}
+
+/// How many times each query was run, rather than reused.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub(crate) struct QueryStats {
+    pub(crate) parsed_files: usize,
+    /// Declarations that were lexed and parsed, which are only the ones that
+    /// an edit touched when a file is parsed incrementally.
+    pub(crate) parsed_decls: usize,
+    pub(crate) resolved_decls: usize,
+    pub(crate) checked_decls: usize,
+}
+
+/// Index of a declaration, the hash of its code and the hash of what else
+/// the query depends on.
+type QueryKey = (usize, ContentHash, ContentHash);
+
+/// How many times as much memory as parsing a file from scratch took its
+/// context can take up as its edits are parsed into it, which only grows,
+/// before the file is parsed from scratch into a new one.
+const MAX_CONTEXT_GROWTH: usize = 4;
+
+/// Result of `parse(file)`, which is kept up to date as the file is edited.
+struct Parse {
+    /// Parse of the file, whose syntax tree is in `context`'s arenas, so it
+    /// only borrows from it for as long as they're both here.
+    incremental: IncrementalParse<'static>,
+    context: Rc<CompilerContext>,
+    /// Memory that the context took up once the file was first parsed.
+    fresh_bytes: usize,
+    file_hash: ContentHash,
+    file: Result<ParsedFile, Diagnostic>,
+}
+
+struct ParsedFile {
+    decls: Vec<ParsedDecl>,
+    /// Hash of the names of the declarations, and of whether they're structs
+    /// or enums, which is what resolving a declaration depends on.
+    names_hash: ContentHash,
+    /// Hash of the signatures of the declarations, which is what type
+    /// checking a declaration depends on.
+    signatures_hash: ContentHash,
+}
+
+struct ParsedDecl {
+    name: &'static str,
+    identifier_span: Span,
+    /// Span of the declaration's code, `extern` included.
+    span: Span,
+    hash: ContentHash,
+}
+
+/// Result of `resolve(decl)`.
+struct ResolvedDecl {
+    errors: Diagnostic,
+}
+
+/// Result of `typecheck(decl)`. Only `layout_errors` is there if any type of
+/// the program can't be laid out, as nothing is type checked then.
+struct CheckedDecl {
+    layout_errors: Diagnostic,
+    diagnostic: Diagnostic,
+    lint_warnings: Diagnostic,
+    unused_bindings: Diagnostic,
+    /// Indices of the declarations that the declaration refers to.
+    decl_refs: Vec<usize>,
+}
+
+impl QueryDatabase {
+    // Nothing but tests asks how much was reused yet.
+    #[allow(dead_code)]
+    pub(crate) fn stats(&self) -> QueryStats {
+        self.stats
+    }
+
+    /// Syntax tree of the last version that was checked, along with the
+    /// context it's in, unless it didn't parse, so that tools can ask about
+    /// the version without parsing it again.
+    pub(crate) fn last_program(&self) -> Option<(&CompilerContext, Program<'_>)> {
+        let parse = self.parse.as_ref()?;
+        parse.file.as_ref().ok()?;
+        let program = parse.incremental.program().ok()?;
+
+        Some((&parse.context, program))
+    }
+
+    /// Checks the version of the program, reporting the same errors and
+    /// warnings as `driver::check` does, though errors come in the order of
+    /// the declarations they're in.
+    pub(crate) fn check(
+        &mut self,
+        source_code: impl Into<SourceMap>,
+        options: &Options,
+    ) -> Diagnostic {
+        debug_assert!(!options.script_mode, "scripts can't be checked by query");
+
+        let source_map = source_code.into();
+        let parse = self.parse(&source_map, options);
+        let diagnostic = match &parse.file {
+            Ok(parsed_file) => self.check_parsed_file(
+                &source_map,
+                options,
+                &parse.context,
+                &parse.incremental,
+                parsed_file,
+            ),
+            Err(diagnostic) => diagnostic.clone(),
+        };
+        self.parse = Some(parse);
+
+        diagnostic
+    }
+
+    fn check_parsed_file(
+        &mut self,
+        source_map: &SourceMap,
+        options: &Options,
+        context: &CompilerContext,
+        parse: &IncrementalParse,
+        parsed_file: &ParsedFile,
+    ) -> Diagnostic {
+        let resolve_keys = query_keys(parsed_file, parsed_file.names_hash);
+        self.resolved_decls
+            .retain(|key, _| resolve_keys.contains(key));
+        self.resolve(context, parse, options, parsed_file, &resolve_keys);
+
+        let resolve_errors = collect_moved(parsed_file, |decl_idx| {
+            self.resolved_decls[&resolve_keys[decl_idx]].errors.clone()
+        });
+
+        if resolve_errors.has_errors() {
+            return resolve_errors;
+        }
+
+        let check_keys = query_keys(parsed_file, parsed_file.signatures_hash);
+        self.checked_decls.retain(|key, _| check_keys.contains(key));
+        self.typecheck(context, parse, options, parsed_file, &check_keys);
+
+        let checked_decls: Vec<_> = check_keys
+            .iter()
+            .map(|key| self.checked_decls[key].clone())
+            .collect();
+
+        let layout_errors = collect_moved(parsed_file, |decl_idx| {
+            checked_decls[decl_idx].layout_errors.clone()
+        });
+
+        if layout_errors.has_errors() {
+            return layout_errors;
+        }
+
+        let mut diagnostic = collect_moved(parsed_file, |decl_idx| {
+            checked_decls[decl_idx].diagnostic.clone()
+        });
+
+        let limit = options.limits.max_decls_to_lint;
+
+        let lint_warnings = match parsed_file.decls.get(limit) {
+            Some(first_unlinted_decl) => vec![CompileWarning::LintsSkipped {
+                decl_count: parsed_file.decls.len(),
+                limit,
+                span: first_unlinted_decl.identifier_span,
+            }],
+            None => {
+                let mut lint_warnings = collect_moved(parsed_file, |decl_idx| {
+                    checked_decls[decl_idx].lint_warnings.clone()
+                })
+                .warnings;
+                let mut unused_warnings = collect_moved(parsed_file, |decl_idx| {
+                    checked_decls[decl_idx].unused_bindings.clone()
+                })
+                .warnings;
+
+                let decl_names: Vec<_> = parsed_file
+                    .decls
+                    .iter()
+                    .map(|decl| (decl.name, decl.identifier_span))
+                    .collect();
+                let decl_refs: Vec<_> = checked_decls
+                    .iter()
+                    .map(|checked_decl| checked_decl.decl_refs.clone())
+                    .collect();
+                unused_warnings.extend(unused::unused_decls(&decl_names, &decl_refs));
+                unused_warnings.sort_by_key(|warning| warning.span().start.0);
+
+                lint_warnings.extend(unused_warnings);
+
+                lint_warnings
+            }
+        };
+
+        diagnostic.warnings.splice(0..0, lint_warnings);
+        driver::apply_lint_levels(&mut diagnostic.warnings, source_map, options);
+
+        diagnostic
+    }
+
+    /// Runs `parse(file)`, which only parses the declarations that the edit
+    /// from the last version touched, unless the last version's context has
+    /// grown too large.
+    fn parse(&mut self, source_map: &SourceMap, options: &Options) -> Parse {
+        let source_code = source_map.source_code();
+        let file_hash = ContentHash::of(source_code.as_bytes());
+
+        let parse = match self.parse.take() {
+            Some(parse) if parse.file_hash == file_hash => return parse,
+            Some(mut parse)
+                if parse.context.allocated_bytes() <= parse.fresh_bytes * MAX_CONTEXT_GROWTH =>
+            {
+                let edit = TextEdit::between(parse.incremental.source_code(), source_code);
+
+                self.stats.parsed_decls += match parse.incremental.apply_edit(&edit) {
+                    Reparse::Incremental { reparsed_decls, .. } => reparsed_decls,
+                    Reparse::Full => parse.decl_count(),
+                };
+
+                parse.file_hash = file_hash;
+                parse.file = ParsedFile::of(&parse.context, parse.incremental.decls(), source_code);
+
+                parse
+            }
+            _ => {
+                let parse = Parse::new(source_map, options);
+                self.stats.parsed_decls += parse.decl_count();
+
+                parse
+            }
+        };
+
+        self.stats.parsed_files += 1;
+
+        parse
+    }
+
+    /// Runs `resolve(decl)` for the declarations whose results aren't known.
+    fn resolve(
+        &mut self,
+        context: &CompilerContext,
+        parse: &IncrementalParse,
+        options: &Options,
+        parsed_file: &ParsedFile,
+        keys: &[QueryKey],
+    ) {
+        let missing: Vec<_> = (0..keys.len())
+            .filter(|&decl_idx| !self.resolved_decls.contains_key(&keys[decl_idx]))
+            .collect();
+
+        if missing.is_empty() {
+            return;
+        }
+
+        self.stats.resolved_decls += missing.len();
+        let program = moved_program(parse);
+
+        let (_, errors) =
+            Resolver::new(context, &options.host_functions).resolve_decls(program, &missing);
+        let mut errors = partition(parsed_file, &missing, Diagnostic::from_errors(errors));
+
+        for &decl_idx in &missing {
+            let resolved_decl = ResolvedDecl {
+                errors: errors.remove(&decl_idx).unwrap_or_default(),
+            };
+            self.resolved_decls.insert(keys[decl_idx], resolved_decl);
+        }
+    }
+
+    /// Runs `typecheck(decl)` for the declarations whose results aren't
+    /// known, whose names are known to resolve.
+    fn typecheck(
+        &mut self,
+        context: &CompilerContext,
+        parse: &IncrementalParse,
+        options: &Options,
+        parsed_file: &ParsedFile,
+        keys: &[QueryKey],
+    ) {
+        let missing: Vec<_> = (0..keys.len())
+            .filter(|&decl_idx| !self.checked_decls.contains_key(&keys[decl_idx]))
+            .collect();
+
+        if missing.is_empty() {
+            return;
+        }
+
+        self.stats.checked_decls += missing.len();
+        let program = moved_program(parse);
+
+        // Only the errors of `resolve(decl)` are kept, so the declarations are
+        // resolved again for their resolutions.
+        let (resolutions, _) =
+            Resolver::new(context, &options.host_functions).resolve_decls(program, &missing);
+
+        let layouts = match Layouts::of_program(context, program, options.int_profile.width) {
+            Ok(layouts) => layouts,
+            Err(errors) => {
+                let mut errors = partition(parsed_file, &missing, Diagnostic::from_errors(errors));
+
+                for &decl_idx in &missing {
+                    let checked_decl = CheckedDecl {
+                        layout_errors: errors.remove(&decl_idx).unwrap_or_default(),
+                        diagnostic: Diagnostic::default(),
+                        lint_warnings: Diagnostic::default(),
+                        unused_bindings: Diagnostic::default(),
+                        decl_refs: vec![],
+                    };
+                    self.checked_decls
+                        .insert(keys[decl_idx], Rc::new(checked_decl));
+                }
+
+                return;
+            }
+        };
+
+        let diagnostic = TypeChecker::new(
+            context,
+            &resolutions,
+            &layouts,
+            options.int_profile.width,
+            &options.host_functions,
+        )
+        .check_decls(program, &missing);
+        let lint_warnings = Linter::new(context, &resolutions)
+            .lint_decls(missing.iter().map(|&decl_idx| &program.decls[decl_idx]));
+        let (unused_bindings, decl_refs) =
+            UnusedChecker::new(context, &resolutions).check_decls(program, &missing);
+
+        let mut diagnostics = partition(parsed_file, &missing, diagnostic);
+        let mut lint_warnings = partition(parsed_file, &missing, warnings_only(lint_warnings));
+        let mut unused_bindings = partition(parsed_file, &missing, warnings_only(unused_bindings));
+
+        for (&decl_idx, decl_refs) in missing.iter().zip(decl_refs) {
+            let checked_decl = CheckedDecl {
+                layout_errors: Diagnostic::default(),
+                diagnostic: diagnostics.remove(&decl_idx).unwrap_or_default(),
+                lint_warnings: lint_warnings.remove(&decl_idx).unwrap_or_default(),
+                unused_bindings: unused_bindings.remove(&decl_idx).unwrap_or_default(),
+                decl_refs,
+            };
+            self.checked_decls
+                .insert(keys[decl_idx], Rc::new(checked_decl));
+        }
+    }
+}
+
+impl Parse {
+    fn new(source_map: &SourceMap, options: &Options) -> Parse {
+        let context = Rc::new(driver::new_context(source_map.clone(), options));
+
+        let ctx: &'static CompilerContext = unsafe {
+            // SAFETY: The context stays where it is in the `Rc` for as long as
+            // the `Parse` holds it, which is for as long as the parse that
+            // borrows from it, whose syntax tree is only handed out along with
+            // a borrow of the `Parse`, see `QueryDatabase::last_program`.
+            &*Rc::as_ptr(&context)
+        };
+        let incremental = IncrementalParse::new(ctx, options);
+        let source_code = source_map.source_code();
+
+        Parse {
+            fresh_bytes: context.allocated_bytes(),
+            file_hash: ContentHash::of(source_code.as_bytes()),
+            file: ParsedFile::of(&context, incremental.decls(), source_code),
+            incremental,
+            context,
+        }
+    }
+
+    fn decl_count(&self) -> usize {
+        self.incremental.decls().map_or(0, <[_]>::len)
+    }
+}
+
+impl ParsedFile {
+    /// Declarations of the file, if it parsed, or why it didn't.
+    fn of(
+        context: &CompilerContext,
+        parse: Result<&[MovedDecl], &Diagnostic>,
+        source_code: &str,
+    ) -> Result<ParsedFile, Diagnostic> {
+        let moved_decls = parse.map_err(Clone::clone)?;
+        let mut names = String::new();
+        let mut signatures = String::new();
+
+        let decls = moved_decls
+            .iter()
+            .map(|moved_decl| {
+                let decl = moved_decl.decl;
+                let name = context.resolve_symbol(decl.identifier);
+                let span = moved_decl.span();
+
+                let kind = match decl.value {
+                    Expr::Struct(_) => "struct",
+                    Expr::Enum(_) => "enum",
+                    _ => "value",
+                };
+                names.push_str(&format!("{} {}\n", name, kind));
+
+                // Functions are called the same whatever their bodies are.
+                let signature_end = match decl.value {
+                    Expr::Function(function) => moved_decl.move_span(function.body.span).start,
+                    _ => span.end,
+                };
+                signatures.push_str(&source_code[span.start.0..signature_end.0]);
+                signatures.push('\n');
+
+                ParsedDecl {
+                    name,
+                    identifier_span: moved_decl.move_span(decl.identifier_span),
+                    span,
+                    hash: ContentHash::of(&source_code.as_bytes()[span.start.0..span.end.0]),
+                }
+            })
+            .collect();
+
+        Ok(ParsedFile {
+            decls,
+            names_hash: ContentHash::of(names.as_bytes()),
+            signatures_hash: ContentHash::of(signatures.as_bytes()),
+        })
+    }
+}
+
+/// Program of the parse, which the file was parsed from, with its
+/// declarations moved to where they are now.
+fn moved_program<'ctx>(parse: &IncrementalParse<'ctx>) -> Program<'ctx> {
+    let Ok(program) = parse.program() else {
+        unreachable!("files are only parsed from programs");
+    };
+
+    program
+}
+
+fn query_keys(parsed_file: &ParsedFile, dependency_hash: ContentHash) -> Vec<QueryKey> {
+    parsed_file
+        .decls
+        .iter()
+        .enumerate()
+        .map(|(decl_idx, decl)| (decl_idx, decl.hash, dependency_hash))
+        .collect()
+}
+
+fn warnings_only(warnings: Vec<CompileWarning>) -> Diagnostic {
+    Diagnostic {
+        errors: vec![],
+        warnings,
+    }
+}
+
+/// Puts together the diagnostics of every declaration, in order, moving them
+/// back to where the declarations are.
+fn collect_moved(
+    parsed_file: &ParsedFile,
+    mut diagnostic_of: impl FnMut(usize) -> Diagnostic,
+) -> Diagnostic {
+    let mut collected = Diagnostic::default();
+
+    for (decl_idx, decl) in parsed_file.decls.iter().enumerate() {
+        let mut diagnostic = diagnostic_of(decl_idx);
+        diagnostic.move_spans(decl.span.start.0 as isize);
+
+        collected.errors.extend(diagnostic.errors);
+        collected.warnings.extend(diagnostic.warnings);
+    }
+
+    collected
+}
+
+/// Splits the diagnostic among the declarations at the indices, by the
+/// declaration that each error's or warning's span starts in, moving the spans
+/// to be relative to where the declaration starts.
+fn partition(
+    parsed_file: &ParsedFile,
+    decl_indices: &[usize],
+    diagnostic: Diagnostic,
+) -> HashMap<usize, Diagnostic> {
+    let decl_idx_of = |span: Span| {
+        let decl_idx = parsed_file
+            .decls
+            .partition_point(|decl| decl.span.start.0 <= span.start.0)
+            .max(1)
+            - 1;
+
+        decl_indices.contains(&decl_idx).then_some(decl_idx)
+    };
+
+    let mut partitions: HashMap<usize, Diagnostic> = decl_indices
+        .iter()
+        .map(|&decl_idx| (decl_idx, Diagnostic::default()))
+        .collect();
+
+    // Whatever is about the other declarations is already known.
+    for error in diagnostic.errors {
+        if let Some(decl_idx) = decl_idx_of(error.span()) {
+            partitions.get_mut(&decl_idx).unwrap().errors.push(error);
+        }
+    }
+
+    for warning in diagnostic.warnings {
+        if let Some(decl_idx) = decl_idx_of(warning.span()) {
+            partitions
+                .get_mut(&decl_idx)
+                .unwrap()
+                .warnings
+                .push(warning);
+        }
+    }
+
+    for (&decl_idx, diagnostic) in &mut partitions {
+        diagnostic.move_spans(-(parsed_file.decls[decl_idx].span.start.0 as isize));
+    }
+
+    partitions
+}
@@ -0,0 +1,339 @@
+use std::fmt::Write;
+
+use crate::diagnostics::{CompileError, CompileWarning, Diagnostic, Remark};
+use crate::json::Json;
+use crate::scanner::Span;
+use crate::source_map::SourceMap;
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders diagnostics the way rustc does: a header with the message, and with
+/// the code of errors, which `sophia --explain` explains, the file, line and
+/// column the diagnostic is about, and the line of source code with the span
+/// underlined by carets. Spans are looked up in the source map, so diagnostics
+/// point at whichever file the program's code came from.
+///
+/// ```text
+/// error[E0002]: cannot find `x` in this scope
+///  --> main.sph:2:5
+///   |
+/// 2 |     x + 1
+///   |     ^
+/// ```
+///
+/// Diagnostics can be rendered as JSON instead, for tools, see `ErrorFormat`.
+pub(crate) struct DiagnosticRenderer<'a> {
+    source_map: &'a SourceMap,
+    /// Whether to color the output with ANSI escape codes, for terminals.
+    colors: bool,
+    format: ErrorFormat,
+}
+
+/// How diagnostics are rendered.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum ErrorFormat {
+    /// For people to read, with the line of source code they're about.
+    Human,
+    /// A JSON object per line, for editors and CI tools to read:
+    ///
+    /// ```text
+    /// {"code":"E0002","severity":"error","message":"cannot find `x` in this scope",
+    ///  "spans":[{"file":"main.sph","start":24,"end":25,"line":2,"col":5}]}
+    /// ```
+    ///
+    /// The code of a warning is its lint, and the code of a remark is its
+    /// pass. Diagnostics about the whole program have no spans. Spans after
+    /// the first are of notes, whose messages are their `label`s.
+    Json,
+}
+
+/// How many diagnostics of each severity were reported, where warnings of
+/// denied lints count as errors.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub(crate) struct DiagnosticCounts {
+    pub(crate) errors: usize,
+    pub(crate) warnings: usize,
+    pub(crate) remarks: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Severity {
+    Error,
+    Warning,
+    /// Remark of the named pass.
+    Remark(&'static str),
+}
+
+impl<'a> DiagnosticRenderer<'a> {
+    pub(crate) fn new(source_map: &'a SourceMap) -> DiagnosticRenderer<'a> {
+        DiagnosticRenderer {
+            source_map,
+            colors: false,
+            format: ErrorFormat::Human,
+        }
+    }
+
+    pub(crate) fn with_format(self, format: ErrorFormat) -> DiagnosticRenderer<'a> {
+        DiagnosticRenderer { format, ..self }
+    }
+
+    pub(crate) fn with_colors(self, colors: bool) -> DiagnosticRenderer<'a> {
+        DiagnosticRenderer { colors, ..self }
+    }
+
+    /// Renderer of diagnostics about another program, e.g., an example in the
+    /// program's doc comments, that renders them the same way.
+    pub(crate) fn with_source_map<'b>(&self, source_map: &'b SourceMap) -> DiagnosticRenderer<'b> {
+        DiagnosticRenderer {
+            source_map,
+            colors: self.colors,
+            format: self.format,
+        }
+    }
+
+    /// Renders the warnings first, and then the errors, as the errors are
+    /// what stopped the compiler.
+    pub(crate) fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut text = String::new();
+
+        for warning in &diagnostic.warnings {
+            text.push_str(&self.render_warning(warning));
+        }
+
+        for error in &diagnostic.errors {
+            text.push_str(&self.render_error(error));
+        }
+
+        text
+    }
+
+    pub(crate) fn render_error(&self, error: &CompileError) -> String {
+        let span = (!error.is_about_program()).then(|| error.span());
+
+        self.render_message(
+            Severity::Error,
+            error.code(),
+            &error.to_string(),
+            span,
+            error.note(),
+        )
+    }
+
+    pub(crate) fn render_warning(&self, warning: &CompileWarning) -> String {
+        self.render_message(
+            Severity::Warning,
+            warning.code(),
+            &warning.to_string(),
+            Some(warning.span()),
+            warning.note(),
+        )
+    }
+
+    /// Renders a warning of a denied lint as an error, as it fails the
+    /// compilation like one.
+    pub(crate) fn render_denied_warning(&self, warning: &CompileWarning) -> String {
+        self.render_message(
+            Severity::Error,
+            warning.code(),
+            &warning.to_string(),
+            Some(warning.span()),
+            warning.note(),
+        )
+    }
+
+    pub(crate) fn render_remark(&self, remark: &Remark) -> String {
+        self.render_message(
+            Severity::Remark(remark.pass()),
+            remark.pass(),
+            &remark.to_string(),
+            Some(remark.span()),
+            None,
+        )
+    }
+
+    /// Renders how many diagnostics of each severity there were, e.g.,
+    /// `summary: 1 error, 2 warnings, 0 remarks`, or the same counts as a JSON
+    /// object.
+    pub(crate) fn render_summary(&self, counts: DiagnosticCounts) -> String {
+        if self.format == ErrorFormat::Json {
+            let json = Json::object([
+                ("errors", Json::from(counts.errors)),
+                ("warnings", Json::from(counts.warnings)),
+                ("remarks", Json::from(counts.remarks)),
+            ]);
+
+            return format!("{}\n", json);
+        }
+
+        format!(
+            "{}: {}, {}, {}\n",
+            self.paint(BOLD, "summary"),
+            count_of(counts.errors, "error"),
+            count_of(counts.warnings, "warning"),
+            count_of(counts.remarks, "remark")
+        )
+    }
+
+    fn render_message(
+        &self,
+        severity: Severity,
+        code: &str,
+        message: &str,
+        span: Option<Span>,
+        note: Option<(Span, String)>,
+    ) -> String {
+        if self.format == ErrorFormat::Json {
+            return self.render_json_message(severity, code, message, span, note);
+        }
+
+        let (label, color) = match severity {
+            Severity::Error => (format!("error[{}]", code), RED),
+            Severity::Warning => ("warning".to_owned(), YELLOW),
+            Severity::Remark(pass) => (format!("remark[{}]", pass), GREEN),
+        };
+
+        let mut text = format!(
+            "{}{}\n",
+            self.paint(color, &label),
+            self.paint(BOLD, &format!(": {}", message))
+        );
+
+        let Some(span) = span else {
+            return text;
+        };
+
+        self.render_snippet(&mut text, span, '^', color);
+
+        if let Some((note_span, note)) = note {
+            writeln!(
+                text,
+                "{}{}",
+                self.paint(BLUE, "note"),
+                self.paint(BOLD, &format!(": {}", note))
+            )
+            .unwrap();
+            self.render_snippet(&mut text, note_span, '-', BLUE);
+        }
+
+        text
+    }
+
+    /// Renders the line of source code that the span starts on, with the
+    /// span's code underlined.
+    fn render_snippet(&self, text: &mut String, span: Span, underline: char, color: &str) {
+        let (file_id, line, col) = self.source_map.lookup_line_col(span.start);
+        let line_start = self.source_map.line_start(file_id, line).0;
+        let line_text = self.source_map.line_text(file_id, line);
+        let line_number = line.0.to_string();
+
+        // The underline ends with the line if the span goes past it.
+        let underline_start = span.start.0.min(line_start + line_text.len());
+        let underline_end = span
+            .end
+            .0
+            .clamp(underline_start, line_start + line_text.len());
+        let underline_len = self.source_map.source_code()[underline_start..underline_end]
+            .chars()
+            .count()
+            .max(1);
+
+        let gutter = " ".repeat(line_number.len());
+        let bar = self.paint(BLUE, "|");
+
+        writeln!(
+            text,
+            "{}{} {}:{}:{}",
+            gutter,
+            self.paint(BLUE, "-->"),
+            self.source_map.file_name(file_id),
+            line_number,
+            col.0
+        )
+        .unwrap();
+        writeln!(text, "{} {}", gutter, bar).unwrap();
+        writeln!(
+            text,
+            "{} {} {}",
+            self.paint(BLUE, &line_number),
+            bar,
+            line_text
+        )
+        .unwrap();
+        writeln!(
+            text,
+            "{} {} {}{}",
+            gutter,
+            bar,
+            " ".repeat(col.0 - 1),
+            self.paint(color, &underline.to_string().repeat(underline_len))
+        )
+        .unwrap();
+    }
+
+    fn render_json_message(
+        &self,
+        severity: Severity,
+        code: &str,
+        message: &str,
+        span: Option<Span>,
+        note: Option<(Span, String)>,
+    ) -> String {
+        let severity = match severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Remark(_) => "remark",
+        };
+        let span_members = |span: Span| {
+            let (file_id, line, col) = self.source_map.lookup_line_col(span.start);
+
+            vec![
+                ("file", Json::from(self.source_map.file_name(file_id))),
+                ("start", Json::from(span.start.0)),
+                ("end", Json::from(span.end.0)),
+                ("line", Json::from(line.0)),
+                ("col", Json::from(col.0)),
+            ]
+        };
+        let note_span = note.map(|(span, note)| {
+            let mut members = span_members(span);
+            members.push(("label", Json::from(note)));
+
+            Json::object(members)
+        });
+        let spans = span
+            .map(|span| Json::object(span_members(span)))
+            .into_iter()
+            .chain(note_span);
+
+        let json = Json::object([
+            ("code", Json::from(code)),
+            ("severity", Json::from(severity)),
+            ("message", Json::from(message)),
+            ("spans", Json::Array(spans.collect())),
+        ]);
+
+        format!("{}\n", json)
+    }
+
+    /// Wraps the text in the escape codes of the color, if colors are on.
+    fn paint(&self, color: &str, text: &str) -> String {
+        if !self.colors {
+            return text.to_owned();
+        }
+
+        format!("{}{}{}", color, text, RESET)
+    }
+}
+
+/// Count followed by the noun, which is plural unless the count is one.
+pub(crate) fn count_of(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {}", noun),
+        _ => format!("{} {}s", count, noun),
+    }
+}
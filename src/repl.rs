@@ -0,0 +1,80 @@
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::{Diagnostic, Lint};
+use crate::driver::{self, Options, ReplEvaluation};
+use crate::interp::ReplBindings;
+use crate::scanner::{BytePos, Scanner, TokenKind};
+use crate::source_map::SourceMap;
+
+/// Session of `sophia repl`, which evaluates code typed into it one input at
+/// a time. Inputs can use the declarations and the bindings of the inputs
+/// before them, as every input is checked along with the ones that were
+/// evaluated fine so far, and runs with the values they bound.
+pub(crate) struct Repl {
+    /// Inputs evaluated fine so far, each a file of its own.
+    source_map: SourceMap,
+    /// Context that every input is compiled with, so that the bindings keep
+    /// their symbols from one input to the next.
+    context: CompilerContext,
+    bindings: ReplBindings,
+    options: Options,
+}
+
+/// Outcome of evaluating an input, along with the source code it was
+/// evaluated with, which its diagnostics point into.
+pub(crate) struct ReplOutcome {
+    pub(crate) source_map: SourceMap,
+    pub(crate) evaluation: Result<ReplEvaluation, Diagnostic>,
+}
+
+impl Repl {
+    pub(crate) fn new(mut options: Options) -> Repl {
+        // Whatever is declared or bound is only used by later inputs, if ever.
+        options.lint_levels.allow(Lint::UnusedBinding);
+        options.lint_levels.allow(Lint::UnusedDecl);
+
+        Repl {
+            source_map: SourceMap::new(),
+            context: driver::new_context(SourceMap::new(), &options),
+            bindings: ReplBindings::default(),
+            options,
+        }
+    }
+
+    /// Evaluates the input, which later inputs can build upon unless it
+    /// failed to compile or to run.
+    pub(crate) fn eval(&mut self, input: &str) -> ReplOutcome {
+        let mut source_map = self.source_map.clone();
+        let input_number = source_map.file_ids().count() + 1;
+        source_map.add_file(format!("<input {}>", input_number), input);
+
+        self.context.reset(source_map.clone());
+        let mut bindings = self.bindings.clone();
+        let evaluation = driver::eval_repl_input(&self.context, &mut bindings, &self.options);
+
+        if let Ok(ReplEvaluation { value: Ok(_), .. }) = evaluation {
+            self.source_map = source_map.clone();
+            self.bindings = bindings;
+        }
+
+        ReplOutcome {
+            source_map,
+            evaluation,
+        }
+    }
+}
+
+/// Whether the input leaves delimiters open, e.g., the `{` of a function's
+/// body, so that the REPL reads more lines before evaluating it.
+pub(crate) fn is_incomplete(input: &str) -> bool {
+    let mut depth = 0usize;
+
+    for tok in Scanner::for_text(input, BytePos(0)) {
+        match tok.kind {
+            TokenKind::Open(_) => depth += 1,
+            TokenKind::Closed(_) => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    depth > 0
+}
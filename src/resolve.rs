@@ -0,0 +1,726 @@
+use crate::ast::{
+    CompoundExpr, Expr, ForExpr, ForIteration, HostFnDecl, IfExpr, MatchExpr, ParenExpr,
+    PatternKind, Program, StructLitExpr, Type, VariantExpr,
+};
+use crate::builtin::Builtin;
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::CompileError;
+use crate::host::HashMap;
+use crate::interner::Symbol;
+use crate::scanner::{Keyword, Span};
+use crate::suggest::find_similar_name;
+
+/// What a name refers to.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Resolution {
+    /// The top-level declaration at this index of `Program::decls`.
+    Decl(usize),
+    /// The host function at this index of `Options::host_functions`, which
+    /// declarations of the same name shadow.
+    Host(usize),
+    /// The builtin function, which declarations and host functions of the same
+    /// name shadow.
+    Builtin(Builtin),
+    /// The local binding defined at this span, which is either a `BindDef`'s
+    /// span, a `Param`'s span, a loop variable's identifier span, the span of
+    /// a pattern's binding, or the span of a `Binding` of a `TupleBindDef`.
+    Local(Span),
+}
+
+/// Local binding of an enclosing function that a closure refers to, whose
+/// value the closure copies when it's made.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct Capture {
+    pub(crate) identifier: Symbol,
+    pub(crate) def_span: Span,
+}
+
+/// Binding of an enclosing scope that a `BindDef` shadows, i.e., that the
+/// code after the `BindDef` can't refer to until its scope ends.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct Shadowing {
+    /// Span the shadowed binding is defined at, see `Resolution::Local`.
+    pub(crate) def_span: Span,
+    /// Whether the shadowed binding is a parameter of the function.
+    pub(crate) is_param: bool,
+}
+
+/// Links every name reference in a program, keyed by the reference's span, to
+/// the definition it refers to.
+#[derive(Default)]
+pub(crate) struct Resolutions {
+    resolution_by_span: HashMap<Span, Resolution>,
+    capture_by_function: HashMap<Span, Vec<Capture>>,
+    /// Bindings that `BindDef`s shadow, keyed by the `BindDef`s' spans.
+    shadowing_by_span: HashMap<Span, Shadowing>,
+}
+
+impl Resolutions {
+    pub(crate) fn get(&self, ref_span: Span) -> Option<Resolution> {
+        self.resolution_by_span.get(&ref_span).copied()
+    }
+
+    /// References in the program along with what they resolve to, in no
+    /// particular order. Every reference's span starts with the name.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Span, Resolution)> + '_ {
+        self.resolution_by_span
+            .iter()
+            .map(|(&span, &resolution)| (span, resolution))
+    }
+
+    /// Builtins that the program refers to, in the order of `Builtin::ALL`.
+    pub(crate) fn builtins(&self) -> Vec<Builtin> {
+        Builtin::ALL
+            .into_iter()
+            .filter(|&builtin| {
+                self.resolution_by_span
+                    .values()
+                    .any(|&resolution| resolution == Resolution::Builtin(builtin))
+            })
+            .collect()
+    }
+
+    /// Binding of an enclosing scope that the `BindDef` with this span
+    /// shadows, if any. Bindings of the same scope are rebound rather than
+    /// shadowed, as nothing can refer to them anymore.
+    pub(crate) fn shadowing(&self, bind_def_span: Span) -> Option<Shadowing> {
+        self.shadowing_by_span.get(&bind_def_span).copied()
+    }
+
+    /// Bindings that the function with this span captures, in the order the
+    /// function first refers to them, which only closures do.
+    pub(crate) fn captures(&self, function_span: Span) -> &[Capture] {
+        self.capture_by_function
+            .get(&function_span)
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+pub(crate) struct Resolver<'ctx> {
+    ctx: &'ctx CompilerContext,
+    decl_idx_by_identifier: HashMap<Symbol, usize>,
+    /// Declarations of structs and enums, which are the only ones that name
+    /// types.
+    struct_decl_idx_by_identifier: HashMap<Symbol, usize>,
+    enum_decl_idx_by_identifier: HashMap<Symbol, usize>,
+    host_fn_idx_by_identifier: HashMap<Symbol, usize>,
+    scope_stack: Vec<HashMap<Symbol, Span>>,
+    /// Functions the current expression is in the body of, innermost last,
+    /// along with the index in `scope_stack` of their parameters' scope, so
+    /// that bindings of scopes before it are captured.
+    function_stack: Vec<(Span, usize)>,
+    resolutions: Resolutions,
+    /// How many loops the current expression is in the body of, within its
+    /// function.
+    loop_depth: usize,
+    errors: Vec<CompileError>,
+}
+
+impl<'ctx> Resolver<'ctx> {
+    pub(crate) fn new(ctx: &'ctx CompilerContext, host_fns: &[HostFnDecl]) -> Resolver<'ctx> {
+        let host_fn_idx_by_identifier = host_fns
+            .iter()
+            .enumerate()
+            .map(|(idx, host_fn)| (ctx.get_or_intern_str(&host_fn.name), idx))
+            .collect();
+
+        Resolver {
+            ctx,
+            decl_idx_by_identifier: Default::default(),
+            struct_decl_idx_by_identifier: Default::default(),
+            enum_decl_idx_by_identifier: Default::default(),
+            host_fn_idx_by_identifier,
+            scope_stack: vec![],
+            function_stack: vec![],
+            resolutions: Default::default(),
+            loop_depth: 0,
+            errors: vec![],
+        }
+    }
+
+    /// Resolves every name in the program. Declarations are all named before
+    /// any is resolved, so they can refer to each other whatever their order
+    /// in the source code, as mutually recursive functions do.
+    pub(crate) fn resolve_program(
+        self,
+        program: Program,
+    ) -> Result<Resolutions, Vec<CompileError>> {
+        let decl_indices: Vec<_> = (0..program.decls.len()).collect();
+        let (resolutions, errors) = self.resolve_decls(program, &decl_indices);
+
+        if errors.is_empty() {
+            Ok(resolutions)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Resolves the names in the declarations at the indices, which can refer
+    /// to any declaration of the program, returning the errors along with
+    /// whatever was resolved.
+    pub(crate) fn resolve_decls(
+        mut self,
+        program: Program,
+        decl_indices: &[usize],
+    ) -> (Resolutions, Vec<CompileError>) {
+        for (decl_idx, decl) in program.decls.iter().enumerate() {
+            // Names refer to the first declaration of the name, and the ones
+            // after it are reported along with the declarations they're in.
+            if let Some(&first_decl_idx) = self.decl_idx_by_identifier.get(&decl.identifier) {
+                if decl_indices.contains(&decl_idx) {
+                    self.errors.push(CompileError::DuplicateDecl {
+                        name: self.ctx.resolve_symbol(decl.identifier).to_owned(),
+                        first_span: program.decls[first_decl_idx].identifier_span,
+                        span: decl.identifier_span,
+                    });
+                }
+
+                continue;
+            }
+
+            self.decl_idx_by_identifier
+                .insert(decl.identifier, decl_idx);
+
+            match decl.value {
+                Expr::Struct(_) => {
+                    self.struct_decl_idx_by_identifier
+                        .insert(decl.identifier, decl_idx);
+                }
+                Expr::Enum(_) => {
+                    self.enum_decl_idx_by_identifier
+                        .insert(decl.identifier, decl_idx);
+                }
+                _ => {}
+            }
+        }
+
+        for &decl_idx in decl_indices {
+            self.enter_scope();
+            self.resolve_expr(program.decls[decl_idx].value);
+            self.exit_scope();
+        }
+
+        (self.resolutions, self.errors)
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Const(_) => {}
+            Expr::Break(break_expr) => {
+                if let Some(value) = break_expr.value {
+                    self.resolve_expr(value);
+                }
+
+                self.check_in_loop(Keyword::Break, break_expr.span);
+            }
+            Expr::Continue(continue_expr) => {
+                self.check_in_loop(Keyword::Continue, continue_expr.span)
+            }
+            Expr::BindRef(bind_ref) => {
+                if self.is_discard(bind_ref.identifier) {
+                    self.errors.push(CompileError::DiscardUsedAsValue {
+                        span: bind_ref.span,
+                    });
+                } else {
+                    self.resolve_name(bind_ref.identifier, bind_ref.span);
+                }
+            }
+            Expr::BindDef(bind_def) => {
+                if let Some(ty) = bind_def.ty {
+                    self.resolve_type(ty, bind_def.span);
+                }
+
+                self.resolve_expr(bind_def.value);
+
+                // Discarded values aren't bound to anything, so there's nothing to refer to.
+                if !self.is_discard(bind_def.identifier) {
+                    self.find_shadowing(bind_def.identifier, bind_def.span);
+                    self.define_local(bind_def.identifier, bind_def.span);
+                }
+            }
+            Expr::Function(function) => {
+                // Loops can't be broken out of from the functions they contain.
+                let loop_depth = std::mem::take(&mut self.loop_depth);
+                self.enter_scope();
+                self.function_stack
+                    .push((function.span, self.scope_stack.len() - 1));
+
+                for param in function.parameters {
+                    self.resolve_type(param.ty, param.span);
+                    self.define_local(param.identifier, param.span);
+                }
+
+                self.resolve_type(function.return_type, function.span);
+
+                self.resolve_compound_expr(function.body);
+                self.function_stack.pop();
+                self.exit_scope();
+                self.loop_depth = loop_depth;
+            }
+            // Parameters of extern functions name nothing that code could refer to.
+            Expr::Extern(extern_fn) => {
+                for param in extern_fn.parameters {
+                    self.resolve_type(param.ty, param.span);
+                }
+
+                self.resolve_type(extern_fn.return_type, extern_fn.span);
+            }
+            Expr::Struct(struct_def) => {
+                for field in struct_def.fields {
+                    self.resolve_type(field.ty, field.span);
+                }
+            }
+            Expr::StructLit(struct_lit_expr) => self.resolve_struct_lit_expr(*struct_lit_expr),
+            Expr::Field(field_expr) => self.resolve_expr(field_expr.base),
+            Expr::Enum(enum_def) => {
+                for variant in enum_def.variants {
+                    if let Some(payload) = variant.payload {
+                        self.resolve_type(payload, variant.span);
+                    }
+                }
+            }
+            Expr::Variant(variant_expr) => self.resolve_variant_expr(*variant_expr),
+            Expr::Tuple(tuple_expr) => {
+                for element in tuple_expr.elements {
+                    self.resolve_expr(element);
+                }
+            }
+            Expr::TupleField(tuple_field_expr) => self.resolve_expr(tuple_field_expr.base),
+            Expr::TupleBindDef(tuple_bind_def) => {
+                self.resolve_expr(tuple_bind_def.value);
+
+                for binding in tuple_bind_def.bindings {
+                    if !self.is_discard(binding.identifier) {
+                        self.define_local(binding.identifier, binding.span);
+                    }
+                }
+            }
+            Expr::Array(array_expr) => {
+                for element in array_expr.elements {
+                    self.resolve_expr(element);
+                }
+            }
+            Expr::Index(index_expr) => {
+                self.resolve_expr(index_expr.base);
+                self.resolve_expr(index_expr.index);
+            }
+            Expr::IndexAssign(index_assign_expr) => {
+                self.resolve_expr(index_assign_expr.target.base);
+                self.resolve_expr(index_assign_expr.target.index);
+                self.resolve_expr(index_assign_expr.value);
+            }
+            Expr::If(if_expr) => self.resolve_if_expr(*if_expr),
+            Expr::Match(match_expr) => self.resolve_match_expr(*match_expr),
+            Expr::For(for_expr) => self.resolve_for_expr(*for_expr),
+            Expr::Compound(compound_expr) => self.resolve_compound_expr(*compound_expr),
+            Expr::Cast(cast_expr) => {
+                self.resolve_expr(cast_expr.expr);
+                self.resolve_type(cast_expr.ty, cast_expr.span);
+            }
+            Expr::Semi(expr) | Expr::Paren(ParenExpr { expr, .. }) => self.resolve_expr(expr),
+            Expr::FnCall(fn_call_expr) => {
+                for arg in fn_call_expr.args {
+                    self.resolve_expr(arg);
+                }
+
+                self.resolve_name(fn_call_expr.identifier, fn_call_expr.span);
+            }
+            Expr::Binary(binary_expr) => {
+                let (operand, operations) = binary_expr.chain();
+                self.resolve_expr(operand);
+
+                for binary_expr in operations {
+                    self.resolve_expr(binary_expr.rhs);
+                }
+            }
+        }
+    }
+
+    fn resolve_struct_lit_expr(&mut self, struct_lit_expr: StructLitExpr) {
+        for field in struct_lit_expr.fields {
+            self.resolve_expr(field.value);
+        }
+
+        if let Some(decl_idx) =
+            self.resolve_struct_name(struct_lit_expr.identifier, struct_lit_expr.identifier_span)
+        {
+            self.resolutions
+                .resolution_by_span
+                .insert(struct_lit_expr.identifier_span, Resolution::Decl(decl_idx));
+        }
+    }
+
+    fn resolve_variant_expr(&mut self, variant_expr: VariantExpr) {
+        if let Some(payload) = variant_expr.payload {
+            self.resolve_expr(payload);
+        }
+
+        self.resolve_enum_name(
+            variant_expr.enum_identifier,
+            variant_expr.enum_identifier_span,
+        );
+    }
+
+    fn resolve_match_expr(&mut self, match_expr: MatchExpr) {
+        self.resolve_expr(match_expr.scrutinee);
+
+        // Arms are only evaluated when they match, so what they bind isn't
+        // defined after them.
+        for arm in match_expr.arms {
+            self.enter_scope();
+
+            if let PatternKind::Variant(variant_pattern) = arm.pattern.kind {
+                self.resolve_enum_name(
+                    self.ctx.get_or_intern_str(variant_pattern.enum_name),
+                    variant_pattern.enum_name_span,
+                );
+
+                if let Some((name, span)) = variant_pattern.binding {
+                    let identifier = self.ctx.get_or_intern_str(name);
+
+                    if !self.is_discard(identifier) {
+                        self.define_local(identifier, span);
+                    }
+                }
+            }
+
+            self.resolve_expr(arm.expr);
+            self.exit_scope();
+        }
+    }
+
+    /// Checks that the type names a struct or an enum, if it names a
+    /// declaration at all, as do the types of a tuple's or an array's elements,
+    /// and the types of a function's parameters and return value.
+    fn resolve_type(&mut self, ty: Type, span: Span) {
+        let name = match ty {
+            Type::Unit
+            | Type::I8
+            | Type::I16
+            | Type::I32
+            | Type::I64
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::Usize => return,
+            Type::Named(name) => name,
+            Type::Tuple(element_types) => {
+                for &element_ty in element_types {
+                    self.resolve_type(element_ty, span);
+                }
+
+                return;
+            }
+            Type::Array { element, .. } => return self.resolve_type(*element, span),
+            Type::Function {
+                params,
+                return_type,
+            } => {
+                for &param_ty in params {
+                    self.resolve_type(param_ty, span);
+                }
+
+                return self.resolve_type(*return_type, span);
+            }
+        };
+        let identifier = self.ctx.get_or_intern_str(name);
+
+        if self.struct_decl_idx_by_identifier.contains_key(&identifier)
+            || self.enum_decl_idx_by_identifier.contains_key(&identifier)
+        {
+            return;
+        }
+
+        let type_names = self
+            .struct_decl_idx_by_identifier
+            .keys()
+            .chain(self.enum_decl_idx_by_identifier.keys())
+            .copied()
+            .collect::<Vec<_>>();
+
+        self.report_unresolved_decl(identifier, span, &type_names, |name, span| {
+            CompileError::NotAType { name, span }
+        });
+    }
+
+    /// Index of the struct's declaration, or `None` if there's no struct of the
+    /// name, which is an error.
+    fn resolve_struct_name(&mut self, identifier: Symbol, ref_span: Span) -> Option<usize> {
+        if let Some(&decl_idx) = self.struct_decl_idx_by_identifier.get(&identifier) {
+            return Some(decl_idx);
+        }
+
+        let struct_names = self
+            .struct_decl_idx_by_identifier
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+
+        self.report_unresolved_decl(identifier, ref_span, &struct_names, |name, span| {
+            CompileError::NotAStruct { name, span }
+        });
+
+        None
+    }
+
+    /// Links the name to the enum's declaration, reporting an error if
+    /// there's no enum of the name.
+    fn resolve_enum_name(&mut self, identifier: Symbol, ref_span: Span) {
+        if let Some(&decl_idx) = self.enum_decl_idx_by_identifier.get(&identifier) {
+            self.resolutions
+                .resolution_by_span
+                .insert(ref_span, Resolution::Decl(decl_idx));
+
+            return;
+        }
+
+        let enum_names = self
+            .enum_decl_idx_by_identifier
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+
+        self.report_unresolved_decl(identifier, ref_span, &enum_names, |name, span| {
+            CompileError::NotAnEnum { name, span }
+        });
+    }
+
+    /// Reports that the name doesn't name a declaration of the kind expected:
+    /// either it names one of another kind, or it names none, in which case
+    /// one of the expected ones is suggested.
+    fn report_unresolved_decl(
+        &mut self,
+        identifier: Symbol,
+        ref_span: Span,
+        expected_identifiers: &[Symbol],
+        wrong_kind_error: fn(String, Span) -> CompileError,
+    ) {
+        let name = self.ctx.resolve_symbol(identifier);
+
+        if self.decl_idx_by_identifier.contains_key(&identifier) {
+            self.errors
+                .push(wrong_kind_error(name.to_owned(), ref_span));
+        } else {
+            let expected_names = expected_identifiers
+                .iter()
+                .map(|&identifier| self.ctx.resolve_symbol(identifier));
+
+            self.errors.push(CompileError::UndefinedName {
+                name: name.to_owned(),
+                span: ref_span,
+                suggestion: find_similar_name(name, expected_names).map(str::to_owned),
+            });
+        }
+    }
+
+    /// Each branch is a scope of its own, so what a branch binds isn't defined
+    /// after it. Along with names being defined as they're evaluated, that
+    /// makes every binding in scope definitely assigned wherever it's read,
+    /// with no need for an analysis of the paths to the read.
+    fn resolve_if_expr(&mut self, if_expr: IfExpr) {
+        self.resolve_expr(if_expr.cond_expr);
+        self.resolve_compound_expr(if_expr.true_branch);
+
+        for branch in if_expr.else_if_branches {
+            self.resolve_expr(branch.cond_expr);
+            self.resolve_compound_expr(branch.true_branch);
+        }
+
+        if let Some(final_branch) = if_expr.final_branch {
+            self.resolve_compound_expr(final_branch);
+        }
+    }
+
+    fn resolve_for_expr(&mut self, for_expr: ForExpr) {
+        self.enter_scope();
+
+        match for_expr.iteration {
+            Some(ForIteration::Conditional { cond_expr }) => self.resolve_expr(cond_expr),
+            Some(ForIteration::Iterative {
+                identifier,
+                identifier_span,
+                start_expr,
+                end_expr,
+                step_expr,
+                ..
+            }) => {
+                self.resolve_expr(start_expr);
+                self.resolve_expr(end_expr);
+
+                if let Some(step_expr) = step_expr {
+                    self.resolve_expr(step_expr);
+                }
+
+                self.define_local(identifier, identifier_span);
+            }
+            Some(ForIteration::InCollection {
+                identifier,
+                identifier_span,
+                collection_expr,
+            }) => {
+                self.resolve_expr(collection_expr);
+                self.define_local(identifier, identifier_span);
+            }
+            None => {}
+        }
+
+        self.loop_depth += 1;
+        self.resolve_compound_expr(for_expr.body);
+        self.loop_depth -= 1;
+
+        self.exit_scope();
+
+        if let Some(else_branch) = for_expr.else_branch {
+            self.resolve_compound_expr(else_branch);
+        }
+    }
+
+    fn resolve_compound_expr(&mut self, compound_expr: CompoundExpr) {
+        self.enter_scope();
+
+        for expr in compound_expr.exprs {
+            self.resolve_expr(expr);
+        }
+
+        self.exit_scope();
+    }
+
+    fn resolve_name(&mut self, identifier: Symbol, ref_span: Span) {
+        let local_def = self
+            .scope_stack
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(scope_idx, scope)| Some((scope_idx, *scope.get(&identifier)?)));
+
+        let resolution = match local_def {
+            Some((scope_idx, def_span)) => {
+                self.capture(identifier, def_span, scope_idx);
+
+                Resolution::Local(def_span)
+            }
+            None => match self.decl_idx_by_identifier.get(&identifier) {
+                Some(&decl_idx) => Resolution::Decl(decl_idx),
+                None if self.host_fn_idx_by_identifier.contains_key(&identifier) => {
+                    Resolution::Host(self.host_fn_idx_by_identifier[&identifier])
+                }
+                None => match self.ctx.symbols().builtin(identifier) {
+                    Some(builtin) => Resolution::Builtin(builtin),
+                    None => {
+                        let name = self.ctx.resolve_symbol(identifier);
+
+                        self.errors.push(CompileError::UndefinedName {
+                            name: name.to_owned(),
+                            span: ref_span,
+                            suggestion: self.find_similar_name(name).map(str::to_owned),
+                        });
+
+                        return;
+                    }
+                },
+            },
+        };
+
+        self.resolutions
+            .resolution_by_span
+            .insert(ref_span, resolution);
+    }
+
+    /// Records that the functions the binding is defined outside of capture
+    /// it, including ones that only contain the closure that refers to it, as
+    /// they need its value to make that closure.
+    fn capture(&mut self, identifier: Symbol, def_span: Span, scope_idx: usize) {
+        for &(function_span, params_scope_idx) in self.function_stack.iter().rev() {
+            if scope_idx >= params_scope_idx {
+                break;
+            }
+
+            let captures = self
+                .resolutions
+                .capture_by_function
+                .entry(function_span)
+                .or_default();
+
+            if !captures.iter().any(|capture| capture.def_span == def_span) {
+                captures.push(Capture {
+                    identifier,
+                    def_span,
+                });
+            }
+        }
+    }
+
+    /// Name in scope, or keyword, that the name is likely a misspelling of.
+    fn find_similar_name(&self, name: &str) -> Option<&'static str> {
+        let names_in_scope = self
+            .scope_stack
+            .iter()
+            .flat_map(|scope| scope.keys())
+            .chain(self.decl_idx_by_identifier.keys())
+            .chain(self.host_fn_idx_by_identifier.keys())
+            .map(|&identifier| self.ctx.resolve_symbol(identifier))
+            .chain(Builtin::ALL.into_iter().map(Builtin::name));
+        let keywords = Keyword::ALL
+            .into_iter()
+            .filter(|keyword| !keyword.is_contextual())
+            .map(Keyword::as_str);
+
+        find_similar_name(name, names_in_scope.chain(keywords))
+    }
+
+    fn check_in_loop(&mut self, keyword: Keyword, span: Span) {
+        if self.loop_depth == 0 {
+            self.errors
+                .push(CompileError::BreakOutsideLoop { keyword, span });
+        }
+    }
+
+    fn is_discard(&self, identifier: Symbol) -> bool {
+        identifier == self.ctx.symbols().discard
+    }
+
+    /// Records the binding of an enclosing scope that the `BindDef` with this
+    /// span shadows, if any, unless it rebinds one of its own scope.
+    fn find_shadowing(&mut self, identifier: Symbol, bind_def_span: Span) {
+        let Some((scope, enclosing_scopes)) = self.scope_stack.split_last() else {
+            return;
+        };
+
+        if scope.contains_key(&identifier) {
+            return;
+        }
+
+        let shadowed = enclosing_scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(scope_idx, scope)| Some((scope_idx, *scope.get(&identifier)?)));
+
+        if let Some((scope_idx, def_span)) = shadowed {
+            let is_param = self
+                .function_stack
+                .iter()
+                .any(|&(_, params_scope_idx)| params_scope_idx == scope_idx);
+
+            self.resolutions
+                .shadowing_by_span
+                .insert(bind_def_span, Shadowing { def_span, is_param });
+        }
+    }
+
+    fn define_local(&mut self, identifier: Symbol, def_span: Span) {
+        self.scope_stack
+            .last_mut()
+            .unwrap()
+            .insert(identifier, def_span);
+    }
+
+    fn enter_scope(&mut self) {
+        self.scope_stack.push(HashMap::default());
+    }
+
+    fn exit_scope(&mut self) {
+        self.scope_stack.pop();
+    }
+}
@@ -0,0 +1,181 @@
+use crate::compiler_context::CompilerContext;
+use crate::scanner::{BytePos, Scanner, Span, Token};
+
+/// Tokens to insert, delete or replace in a program, e.g., to fix it, see
+/// `fixer::fix_source`, or to simulate an edit made to it.
+///
+/// Rewrites are applied to the program's source code, which leaves the code
+/// around the rewritten tokens as it is, so that the result can be parsed
+/// again. Tokens are named by their index in `TokenRewriter::tokens`, which
+/// rewrites don't change.
+pub(crate) struct TokenRewriter<'ctx> {
+    source_code: &'ctx str,
+    tokens: Vec<Token>,
+    /// Text inserted right after each token.
+    inserted_after: Vec<Vec<String>>,
+    /// Text that each token is replaced with, which is empty for deleted ones.
+    replacements: Vec<Option<String>>,
+}
+
+/// Source code of a rewritten program.
+pub(crate) struct Rewrite {
+    pub(crate) source_code: String,
+    /// Pieces of the source code, in order, either copied from the original
+    /// one or synthesized by a rewrite.
+    segments: Vec<Segment>,
+}
+
+struct Segment {
+    span: Span,
+    /// Code that the segment was copied from, or, for synthesized segments,
+    /// the token they replace or the empty span they were inserted at.
+    original_span: Span,
+    is_synthesized: bool,
+}
+
+impl<'ctx> TokenRewriter<'ctx> {
+    pub(crate) fn new(ctx: &'ctx CompilerContext) -> TokenRewriter<'ctx> {
+        let tokens: Vec<_> = Scanner::new(ctx).collect();
+        let token_count = tokens.len();
+
+        TokenRewriter {
+            source_code: ctx.get_source_code(),
+            tokens,
+            inserted_after: vec![vec![]; token_count],
+            replacements: vec![None; token_count],
+        }
+    }
+
+    pub(crate) fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// Inserts tokens after the token, separated from it by a space. Text
+    /// inserted after the same token goes in the order it's inserted in.
+    pub(crate) fn insert_after(&mut self, token_idx: usize, text: impl Into<String>) {
+        self.inserted_after[token_idx].push(text.into());
+    }
+
+    pub(crate) fn replace(&mut self, token_idx: usize, text: impl Into<String>) {
+        self.replacements[token_idx] = Some(text.into());
+    }
+
+    /// Deletes the token, leaving the whitespace around it.
+    pub(crate) fn delete(&mut self, token_idx: usize) {
+        self.replace(token_idx, "");
+    }
+
+    pub(crate) fn finish(self) -> Rewrite {
+        let mut rewrite = Rewrite {
+            source_code: String::with_capacity(self.source_code.len()),
+            segments: vec![],
+        };
+        let mut copied_up_to = BytePos(0);
+
+        for (token_idx, token) in self.tokens.iter().enumerate() {
+            rewrite.copy(self.source_code, copied_up_to, token.span.start);
+
+            match &self.replacements[token_idx] {
+                Some(text) => rewrite.synthesize(text, token.span),
+                None => rewrite.copy(self.source_code, token.span.start, token.span.end),
+            }
+
+            for text in &self.inserted_after[token_idx] {
+                rewrite.synthesize(&format!(" {}", text), empty_span(token.span.end));
+            }
+
+            copied_up_to = token.span.end;
+        }
+
+        rewrite.copy(
+            self.source_code,
+            copied_up_to,
+            BytePos(self.source_code.len()),
+        );
+
+        rewrite
+    }
+}
+
+impl Rewrite {
+    /// Span of the original source code that the span of the rewritten one
+    /// comes from, e.g., to point diagnostics about the rewritten program at
+    /// the code that people wrote. Synthesized code comes from the token it
+    /// replaces, or from the empty span it was inserted at.
+    pub(crate) fn original_span(&self, span: Span) -> Span {
+        Span {
+            start: self.original_pos(span.start, true),
+            end: self.original_pos(span.end, false),
+        }
+    }
+
+    /// Position of the original source code that the position of the
+    /// rewritten one comes from. A position between two segments is the end
+    /// of the first one, unless it's the start of a span, which is the start
+    /// of the second one.
+    fn original_pos(&self, pos: BytePos, is_start: bool) -> BytePos {
+        let segment_idx = self.segments.partition_point(|segment| {
+            if is_start {
+                segment.span.end.0 <= pos.0
+            } else {
+                segment.span.end.0 < pos.0
+            }
+        });
+
+        let Some(segment) = self
+            .segments
+            .get(segment_idx)
+            .or_else(|| self.segments.last())
+        else {
+            return pos;
+        };
+
+        if segment.is_synthesized {
+            if is_start {
+                segment.original_span.start
+            } else {
+                segment.original_span.end
+            }
+        } else {
+            let offset =
+                pos.0.clamp(segment.span.start.0, segment.span.end.0) - segment.span.start.0;
+
+            BytePos(segment.original_span.start.0 + offset)
+        }
+    }
+
+    fn copy(&mut self, source_code: &str, start: BytePos, end: BytePos) {
+        let original_span = Span { start, end };
+
+        self.push_segment(&source_code[start.0..end.0], original_span, false);
+    }
+
+    fn synthesize(&mut self, text: &str, original_span: Span) {
+        self.push_segment(text, original_span, true);
+    }
+
+    fn push_segment(&mut self, text: &str, original_span: Span, is_synthesized: bool) {
+        if text.is_empty() {
+            return;
+        }
+
+        let start = BytePos(self.source_code.len());
+        self.source_code.push_str(text);
+
+        self.segments.push(Segment {
+            span: Span {
+                start,
+                end: BytePos(self.source_code.len()),
+            },
+            original_span,
+            is_synthesized,
+        });
+    }
+}
+
+fn empty_span(pos: BytePos) -> Span {
+    Span {
+        start: pos,
+        end: pos,
+    }
+}
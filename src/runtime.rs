@@ -0,0 +1,104 @@
+/// Runtime library of native executables, in x86-64 assembly for Linux, which
+/// is linked with the assembly of the program in place of the C runtime.
+///
+/// It has no dependencies, not even on libc, and it doesn't care how the
+/// stack is aligned, as the generated code doesn't keep it aligned. Its entry
+/// point is apart, in `ENTRY_POINT`. It provides:
+///
+/// - `sophia_exit`, which exits the process with the code in `edi`.
+/// - `sophia_abort`, which stops the program like a runtime error does, by
+///   raising `SIGABRT`, or exiting with code 134 if that fails.
+/// - The functions that builtins call, e.g., `sophia_println_i32`, which print
+///   the `i32` in `edi` to the standard output.
+///
+/// `sophia_print_i32` and `sophia_println_i32` share the code that formats
+/// integers, which gets whether to print a newline in `esi`.
+pub(crate) const ASSEMBLY: &str = r#"    .intel_syntax noprefix
+    .text
+    .globl sophia_exit
+sophia_exit:
+    mov eax, 231
+    syscall
+    hlt
+
+    .globl sophia_abort
+sophia_abort:
+    mov eax, 39
+    syscall
+    mov edi, eax
+    mov esi, 6
+    mov eax, 62
+    syscall
+    mov edi, 134
+    jmp sophia_exit
+
+    .globl sophia_print_i32
+sophia_print_i32:
+    xor esi, esi
+    jmp .Lsophia_write_i32
+
+    .globl sophia_println_i32
+sophia_println_i32:
+    mov esi, 1
+
+.Lsophia_write_i32:
+    push rbp
+    mov rbp, rsp
+    sub rsp, 16
+    mov r8, rbp
+    mov rcx, rbp
+    test esi, esi
+    je .Lsophia_digits
+    dec rcx
+    mov BYTE PTR [rcx], 10
+
+.Lsophia_digits:
+    movsxd rax, edi
+    mov r9, rax
+    test rax, rax
+    jns .Lsophia_next_digit
+    neg rax
+
+.Lsophia_next_digit:
+    mov r10, 10
+    xor edx, edx
+    div r10
+    add dl, 48
+    dec rcx
+    mov BYTE PTR [rcx], dl
+    test rax, rax
+    jne .Lsophia_next_digit
+    test r9, r9
+    jns .Lsophia_write
+    dec rcx
+    mov BYTE PTR [rcx], 45
+
+.Lsophia_write:
+    mov eax, 1
+    mov edi, 1
+    mov rsi, rcx
+    mov rdx, r8
+    sub rdx, rcx
+    syscall
+    leave
+    ret
+
+    .section .note.GNU-stack,"",@progbits
+"#;
+
+/// Entry point of native executables, `_start`, which calls the program's
+/// `main` and exits with its value, the lowest byte of which is the process'
+/// exit code. The code of a `main` of type `()` is whatever it leaves in `eax`.
+///
+/// Programs that call extern functions are linked with the C runtime instead,
+/// whose own `_start` calls `main` the same way, after setting up the C
+/// library.
+pub(crate) const ENTRY_POINT: &str = r#"    .intel_syntax noprefix
+    .text
+    .globl _start
+_start:
+    xor ebp, ebp
+    call main
+    mov edi, eax
+    jmp sophia_exit
+"#;
@@ -1,49 +1,62 @@
-use std::iter::Peekable;
-use std::str::Chars;
+use std::fmt;
 
 use crate::compiler_context::CompilerContext;
 
-pub(crate) struct Scanner<'ctx> {
-    ctx: &'ctx CompilerContext,
-    char_stream: Peekable<Chars<'ctx>>,
-    current_peek_pos: BytePos,
+/// Scanner of the tokens of source code, which goes over its bytes rather
+/// than its chars, as every token is ASCII: only bytes that aren't are
+/// decoded, and only to tell what they are.
+#[derive(Clone)]
+pub(crate) struct Scanner<'src> {
+    source_code: &'src str,
+    /// Position in the program's source code that `source_code` starts at.
+    start_pos: BytePos,
+    /// Offset in `source_code` of the next byte to scan.
+    offset: usize,
 }
 
-impl Scanner<'_> {
-    const EOF_CHAR: char = '\0';
+impl<'src> Scanner<'src> {
+    const EOF_BYTE: u8 = b'\0';
 
-    pub(crate) fn new<'ctx>(ctx: &'ctx CompilerContext) -> Scanner {
+    pub(crate) fn new(ctx: &'src CompilerContext) -> Scanner<'src> {
+        Scanner::for_text(ctx.get_source_code(), BytePos(0))
+    }
+
+    /// Scans a piece of the program's source code that starts at the given
+    /// position, e.g., the code that an edit changed, so that the tokens'
+    /// spans are positions in the whole program.
+    pub(crate) fn for_text(source_code: &'src str, start_pos: BytePos) -> Scanner<'src> {
         Scanner {
-            ctx,
-            char_stream: ctx.get_source_code().chars().peekable(),
-            current_peek_pos: BytePos(0),
+            source_code,
+            start_pos,
+            offset: 0,
         }
     }
 
-    pub(crate) fn scan_all_tokens(&mut self) -> Vec<Token> {
-        let mut tokens = vec![];
-
-        while let Some(token) = self.scan_next_token() {
-            tokens.push(token)
-        }
+    /// Source code of a token that the scanner scanned.
+    pub(crate) fn token_text(&self, span: Span) -> &'src str {
+        &self.source_code[span.start.0 - self.start_pos.0..span.end.0 - self.start_pos.0]
+    }
 
-        tokens
+    /// Position that the scanned source code ends at.
+    pub(crate) fn end_pos(&self) -> BytePos {
+        BytePos(self.start_pos.0 + self.source_code.len())
     }
 
     fn scan_next_token(&mut self) -> Option<Token> {
-        self.skip_whitespace();
+        self.skip_whitespace_and_comments();
 
-        let span_start = self.current_peek_pos;
+        let span_start = self.current_pos();
 
         let token_kind = match self.bump() {
-            Scanner::EOF_CHAR => return None,
-            ';' => TokenKind::Semi,
-            ':' => {
-                if self.peek() == ':' {
+            Scanner::EOF_BYTE => return None,
+            b';' => TokenKind::Semi,
+            b',' => TokenKind::Comma,
+            b':' => {
+                if self.peek() == b':' {
                     self.bump();
 
                     TokenKind::ColonColon
-                } else if self.peek() == '=' {
+                } else if self.peek() == b'=' {
                     self.bump();
 
                     TokenKind::ColonEqual
@@ -51,19 +64,33 @@ impl Scanner<'_> {
                     TokenKind::Colon
                 }
             }
-            '(' => TokenKind::Open(Delim::Paren),
-            ')' => TokenKind::Closed(Delim::Paren),
-            '{' => TokenKind::Open(Delim::Curly),
-            '}' => TokenKind::Closed(Delim::Curly),
-            '-' if self.peek() == '>' => {
+            b'(' => TokenKind::Open(Delim::Paren),
+            b')' => TokenKind::Closed(Delim::Paren),
+            b'{' => TokenKind::Open(Delim::Curly),
+            b'}' => TokenKind::Closed(Delim::Curly),
+            b'[' => TokenKind::Open(Delim::Bracket),
+            b']' => TokenKind::Closed(Delim::Bracket),
+            b'#' => TokenKind::Hash,
+            b'=' if self.peek() == b'>' => {
+                self.bump();
+
+                TokenKind::EqualGreater
+            }
+            b'=' => TokenKind::Equal,
+            b'+' => TokenKind::Plus,
+            b'*' => TokenKind::Star,
+            b'/' => TokenKind::Slash,
+            b'%' => TokenKind::Percent,
+            b'-' if self.peek() == b'>' => {
                 self.bump();
 
                 TokenKind::DashGreater
             }
-            '.' if self.peek() == '.' => {
+            b'-' => TokenKind::Dash,
+            b'.' if self.peek() == b'.' => {
                 self.bump();
 
-                if self.peek() == '=' {
+                if self.peek() == b'=' {
                     self.bump();
 
                     TokenKind::PeriodPeriodEqual
@@ -71,14 +98,22 @@ impl Scanner<'_> {
                     TokenKind::PeriodPeriod
                 }
             }
-            '0'..='9' => self.scan_integer_constant(),
-            'a'..='z' | 'A'..='Z' | '_' => self.scan_identifier(span_start),
-            ch => todo!("char not recognized: '{}'", ch),
+            b'.' => TokenKind::Period,
+            b'0'..=b'9' => self.scan_integer_constant(),
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => self.scan_identifier(span_start),
+            _ => {
+                // The byte starts a char that may take up more bytes, which
+                // go along with it.
+                let ch = self.source_code[self.offset - 1..].chars().next().unwrap();
+                self.offset += ch.len_utf8() - 1;
+
+                TokenKind::Unknown
+            }
         };
 
         let token_span = Span {
             start: span_start,
-            end: self.current_peek_pos,
+            end: self.current_pos(),
         };
 
         Some(Token {
@@ -87,57 +122,91 @@ impl Scanner<'_> {
         })
     }
 
-    fn skip_whitespace(&mut self) {
-        while self.peek().is_ascii_whitespace() {
-            self.bump();
+    /// Skips everything up to the next token, i.e., whitespace and comments,
+    /// which go from `//` to the end of the line. Doc comments, which start
+    /// with `///`, are only read by tools, see `doctest`.
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            while self.peek().is_ascii_whitespace() {
+                self.bump();
+            }
+
+            if !self.source_code.as_bytes()[self.offset..].starts_with(b"//") {
+                break;
+            }
+
+            // Comments may have any chars, whose bytes other than the first
+            // are never newlines, so they're skipped byte by byte too.
+            while !matches!(self.peek(), b'\n' | Scanner::EOF_BYTE) {
+                self.bump();
+            }
         }
     }
 
+    /// Scans the digits of an integer constant, along with its suffix, if it
+    /// has one, as in `42u8`.
     fn scan_integer_constant(&mut self) -> TokenKind {
         while self.peek().is_ascii_digit() {
             self.bump();
         }
 
+        while matches!(self.peek(), b'a'..=b'z' | b'A'..=b'Z' | b'_' | b'0'..=b'9') {
+            self.bump();
+        }
+
         TokenKind::IntegerConstant
     }
 
     fn scan_identifier(&mut self, ident_span_start: BytePos) -> TokenKind {
-        while matches!(self.peek(), 'a'..='z' | 'A'..='Z' | '_' | '0'..='9') {
+        while matches!(self.peek(), b'a'..=b'z' | b'A'..=b'Z' | b'_' | b'0'..=b'9') {
             self.bump();
         }
 
-        let ident_text = &self.ctx.get_source_code()[ident_span_start.0..self.current_peek_pos.0];
+        let ident_text = self.token_text(Span {
+            start: ident_span_start,
+            end: self.current_pos(),
+        });
 
-        match ident_text {
-            "i32" => TokenKind::Keyword(Keyword::I32),
-            "if" => TokenKind::Keyword(Keyword::If),
-            "else" => TokenKind::Keyword(Keyword::Else),
-            "for" => TokenKind::Keyword(Keyword::For),
-            "break" => TokenKind::Keyword(Keyword::Break),
-            "continue" => TokenKind::Keyword(Keyword::Continue),
-            _ => TokenKind::Identifier,
-        }
+        Keyword::ALL
+            .into_iter()
+            .filter(|keyword| !keyword.is_contextual())
+            .find(|keyword| keyword.as_str() == ident_text)
+            .map_or(TokenKind::Identifier, TokenKind::Keyword)
+    }
+
+    fn current_pos(&self) -> BytePos {
+        BytePos(self.start_pos.0 + self.offset)
     }
 
-    fn peek(&mut self) -> char {
-        self.char_stream
-            .peek()
-            .cloned()
-            .unwrap_or(Scanner::EOF_CHAR)
+    fn peek(&self) -> u8 {
+        self.source_code
+            .as_bytes()
+            .get(self.offset)
+            .copied()
+            .unwrap_or(Scanner::EOF_BYTE)
     }
 
-    fn bump(&mut self) -> char {
+    fn bump(&mut self) -> u8 {
         let peeked = self.peek();
 
-        if peeked != Scanner::EOF_CHAR {
-            self.current_peek_pos = BytePos(self.current_peek_pos.0 + peeked.len_utf8());
-            self.char_stream.next();
+        if peeked != Scanner::EOF_BYTE {
+            self.offset += 1;
         }
 
         peeked
     }
 }
 
+/// Tokens are scanned on demand, so that they don't all have to be held in
+/// memory at once.
+impl Iterator for Scanner<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        self.scan_next_token()
+    }
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct Token {
     pub(crate) kind: TokenKind,
@@ -151,10 +220,12 @@ pub(crate) enum TokenKind {
     Identifier,
     Comma,
     Excla,
+    Hash,
     Star,
     Slash,
     Plus,
     Dash,
+    Percent,
     Less,
     Greater,
     LessLess,
@@ -164,36 +235,191 @@ pub(crate) enum TokenKind {
     Colon,
     ColonColon,
     ColonEqual,
+    Equal,
+    EqualGreater,
     Semi,
     DashGreater,
+    Period,
     PeriodPeriod,
     PeriodPeriodEqual,
     Keyword(Keyword),
     Open(Delim),
     Closed(Delim),
+    /// Char that starts no token, e.g., `@`, which is left to the parser to
+    /// report.
+    Unknown,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub(crate) enum Keyword {
+    I8,
+    I16,
     I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    Usize,
     If,
     Else,
     For,
+    Loop,
     Break,
     Continue,
+    Match,
+    Struct,
+    Enum,
+    Extern,
+    As,
+    Step,
+    Rev,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub(crate) enum Delim {
     Paren,
     Curly,
+    Bracket,
 }
 
-#[derive(Clone, Copy)]
+impl Keyword {
+    pub(crate) const ALL: [Keyword; 22] = [
+        Keyword::I8,
+        Keyword::I16,
+        Keyword::I32,
+        Keyword::I64,
+        Keyword::U8,
+        Keyword::U16,
+        Keyword::U32,
+        Keyword::U64,
+        Keyword::Usize,
+        Keyword::If,
+        Keyword::Else,
+        Keyword::For,
+        Keyword::Loop,
+        Keyword::Break,
+        Keyword::Continue,
+        Keyword::Match,
+        Keyword::Struct,
+        Keyword::Enum,
+        Keyword::Extern,
+        Keyword::As,
+        Keyword::Step,
+        Keyword::Rev,
+    ];
+
+    /// Whether the keyword is only one where the parser looks for it, e.g.,
+    /// `step` in a range, so it's scanned as an identifier and can name
+    /// bindings everywhere else.
+    pub(crate) fn is_contextual(self) -> bool {
+        matches!(self, Keyword::Step | Keyword::Rev)
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Keyword::I8 => "i8",
+            Keyword::I16 => "i16",
+            Keyword::I32 => "i32",
+            Keyword::I64 => "i64",
+            Keyword::U8 => "u8",
+            Keyword::U16 => "u16",
+            Keyword::U32 => "u32",
+            Keyword::U64 => "u64",
+            Keyword::Usize => "usize",
+            Keyword::If => "if",
+            Keyword::Else => "else",
+            Keyword::For => "for",
+            Keyword::Loop => "loop",
+            Keyword::Break => "break",
+            Keyword::Continue => "continue",
+            Keyword::Match => "match",
+            Keyword::Struct => "struct",
+            Keyword::Enum => "enum",
+            Keyword::Extern => "extern",
+            Keyword::As => "as",
+            Keyword::Step => "step",
+            Keyword::Rev => "rev",
+        }
+    }
+}
+
+impl Delim {
+    pub(crate) fn open_char(self) -> char {
+        match self {
+            Delim::Paren => '(',
+            Delim::Curly => '{',
+            Delim::Bracket => '[',
+        }
+    }
+
+    pub(crate) fn closed_char(self) -> char {
+        match self {
+            Delim::Paren => ')',
+            Delim::Curly => '}',
+            Delim::Bracket => ']',
+        }
+    }
+}
+
+/// Tokens of a kind, as diagnostics name them: quoted if every token of the
+/// kind is spelled the same, or described otherwise.
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            TokenKind::IntegerConstant => return write!(f, "integer"),
+            TokenKind::Identifier => return write!(f, "identifier"),
+            TokenKind::Unknown => return write!(f, "unknown character"),
+            TokenKind::Open(delim) => return write!(f, "`{}`", delim.open_char()),
+            TokenKind::Closed(delim) => return write!(f, "`{}`", delim.closed_char()),
+            TokenKind::UnitConstant => "()",
+            TokenKind::Comma => ",",
+            TokenKind::Excla => "!",
+            TokenKind::Hash => "#",
+            TokenKind::Star => "*",
+            TokenKind::Slash => "/",
+            TokenKind::Plus => "+",
+            TokenKind::Dash => "-",
+            TokenKind::Percent => "%",
+            TokenKind::Less => "<",
+            TokenKind::Greater => ">",
+            TokenKind::LessLess => "<<",
+            TokenKind::GreaterGreater => ">>",
+            TokenKind::LessEqual => "<=",
+            TokenKind::GreaterEqual => ">=",
+            TokenKind::Colon => ":",
+            TokenKind::ColonColon => "::",
+            TokenKind::ColonEqual => ":=",
+            TokenKind::Equal => "=",
+            TokenKind::EqualGreater => "=>",
+            TokenKind::Semi => ";",
+            TokenKind::DashGreater => "->",
+            TokenKind::Period => ".",
+            TokenKind::PeriodPeriod => "..",
+            TokenKind::PeriodPeriodEqual => "..=",
+            TokenKind::Keyword(keyword) => keyword.as_str(),
+        };
+
+        write!(f, "`{}`", text)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub(crate) struct Span {
     pub(crate) start: BytePos,
     pub(crate) end: BytePos,
 }
 
-#[derive(Clone, Copy)]
+impl Span {
+    /// Makes a span that starts where `self` starts and ends where `other`
+    /// ends.
+    pub(crate) fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub(crate) struct BytePos(pub(crate) usize);
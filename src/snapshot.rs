@@ -0,0 +1,194 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::bytecode::{LoadError, Reader, Writer};
+use crate::manifest::ContentHash;
+
+/// Bytes that every snapshot file starts with.
+pub(crate) const SNAPSHOT_MAGIC: &[u8; 4] = b"SVS\0";
+
+/// Version of the snapshot format, bumped whenever it changes in a way that
+/// older loaders can't read.
+pub(crate) const SNAPSHOT_VERSION: u16 = 1;
+
+/// Execution of a function in the `Vm` that's paused, which can be saved to
+/// disk and resumed later, or kept around to go back to, e.g., to step through
+/// a program backwards.
+///
+/// It's the whole state of the execution, as programs have no globals or heap:
+/// the operand stack, the slots and the call stack.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Snapshot {
+    /// Hash of the saved module the execution runs, so that it can't be
+    /// resumed with another one.
+    pub(crate) module_hash: ContentHash,
+    /// Function the execution started at, which tells whether it returns a
+    /// value.
+    pub(crate) entry_function_idx: u32,
+    pub(crate) executed_instructions: u64,
+    pub(crate) stack: Vec<i32>,
+    /// Slots of every frame in the call stack, one after the other.
+    pub(crate) slots: Vec<i32>,
+    /// Call stack, which is empty once the entry function returns.
+    pub(crate) frames: Vec<Frame>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct Frame {
+    pub(crate) function_idx: u32,
+    /// Index of the next instruction to run.
+    pub(crate) pc: u32,
+    /// Where the frame's slots start.
+    pub(crate) slots_base: u32,
+}
+
+/// Reason why bytes couldn't be loaded as a snapshot.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SnapshotError {
+    NotSnapshot,
+    UnsupportedVersion { version: u16 },
+    Malformed { reason: &'static str },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::NotSnapshot => write!(f, "not a sophia VM snapshot"),
+            SnapshotError::UnsupportedVersion { version } => write!(
+                f,
+                "snapshot format version {} is not supported, only version {} is",
+                version, SNAPSHOT_VERSION
+            ),
+            SnapshotError::Malformed { reason } => write!(f, "malformed snapshot: {}", reason),
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+impl From<LoadError> for SnapshotError {
+    fn from(error: LoadError) -> SnapshotError {
+        match error {
+            LoadError::Malformed { reason } => SnapshotError::Malformed { reason },
+            // Only the module's header can be anything else.
+            LoadError::NotBytecode | LoadError::UnsupportedVersion { .. } => {
+                unreachable!("snapshots have their own header")
+            }
+        }
+    }
+}
+
+impl Snapshot {
+    /// Serializes the snapshot, in the byte order of bytecode modules:
+    ///
+    /// ```text
+    /// magic, version: u16,
+    /// module hash: u64, entry function: u32, executed instructions: u64,
+    /// stack length: u32, values: i32...,
+    /// slot count: u32, values: i32...,
+    /// frame count: u32, (function: u32, pc: u32, slots base: u32)...
+    /// ```
+    pub fn save(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes.extend_from_slice(SNAPSHOT_MAGIC);
+        writer.u16(SNAPSHOT_VERSION);
+
+        writer.u64(self.module_hash.0);
+        writer.u32(self.entry_function_idx);
+        writer.u64(self.executed_instructions);
+
+        for values in [&self.stack, &self.slots] {
+            writer.u32(values.len() as u32);
+
+            for &value in values {
+                writer.u32(value as u32);
+            }
+        }
+
+        writer.u32(self.frames.len() as u32);
+
+        for frame in &self.frames {
+            writer.u32(frame.function_idx);
+            writer.u32(frame.pc);
+            writer.u32(frame.slots_base);
+        }
+
+        writer.bytes
+    }
+
+    /// Deserializes a snapshot saved by `save`. Whether it fits the module is
+    /// only checked once it's resumed.
+    ///
+    /// ```
+    /// let mut session = sophia::Session::new();
+    /// session.add_file("main.sph", "triple :: (n: i32) -> i32 { n * 3 }\nmain :: () {}");
+    ///
+    /// let mut vm = session.load_vm().unwrap();
+    /// let bytes = vm.start("triple", &[5]).unwrap().save();
+    ///
+    /// let mut snapshot = sophia::Snapshot::load(&bytes).unwrap();
+    /// assert_eq!(
+    ///     vm.resume(&mut snapshot, u64::MAX),
+    ///     Ok(sophia::Progress::Finished(Some(15)))
+    /// );
+    /// ```
+    pub fn load(bytes: &[u8]) -> Result<Snapshot, SnapshotError> {
+        if !bytes.starts_with(SNAPSHOT_MAGIC) {
+            return Err(SnapshotError::NotSnapshot);
+        }
+
+        let mut reader = Reader {
+            bytes,
+            pos: SNAPSHOT_MAGIC.len(),
+        };
+
+        let version = reader.u16()?;
+
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion { version });
+        }
+
+        let module_hash = ContentHash(reader.u64()?);
+        let entry_function_idx = reader.u32()?;
+        let executed_instructions = reader.u64()?;
+        let stack = read_values(&mut reader)?;
+        let slots = read_values(&mut reader)?;
+
+        let frame_count = reader.u32()?;
+        let mut frames = vec![];
+
+        for _ in 0..frame_count {
+            frames.push(Frame {
+                function_idx: reader.u32()?,
+                pc: reader.u32()?,
+                slots_base: reader.u32()?,
+            });
+        }
+
+        if reader.pos != bytes.len() {
+            return Err(SnapshotError::Malformed {
+                reason: "trailing bytes after the last frame",
+            });
+        }
+
+        Ok(Snapshot {
+            module_hash,
+            entry_function_idx,
+            executed_instructions,
+            stack,
+            slots,
+            frames,
+        })
+    }
+}
+
+fn read_values(reader: &mut Reader) -> Result<Vec<i32>, SnapshotError> {
+    let len = reader.u32()?;
+    let mut values = vec![];
+
+    for _ in 0..len {
+        values.push(reader.u32()? as i32);
+    }
+
+    Ok(values)
+}
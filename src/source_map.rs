@@ -0,0 +1,157 @@
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use crate::line_index::{Col, Line, LineIndex};
+use crate::scanner::{BytePos, Span};
+
+/// Identifies a file of a `SourceMap`, by the order it was added in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct FileId(pub(crate) usize);
+
+#[derive(Clone)]
+struct SourceFile {
+    name: String,
+    /// Where the file's source code is in the source code of the program.
+    span: Span,
+    /// Built the first time a position in the file is looked up, as most
+    /// compilations never show one to people.
+    line_index: OnceLock<LineIndex>,
+}
+
+/// Source code of a program, which may be split across files.
+///
+/// The files are laid out one after another, separated by a newline so that
+/// no token runs from one file into the next, and the compiler scans them as
+/// the source code of one program. So spans stay positions in the program's
+/// source code, and it's the source map that tells which file they're in.
+#[derive(Clone, Default)]
+pub(crate) struct SourceMap {
+    source_code: String,
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub(crate) fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    pub(crate) fn add_file(&mut self, name: impl Into<String>, source_code: &str) -> FileId {
+        if !self.files.is_empty() {
+            self.source_code.push('\n');
+        }
+
+        let start = BytePos(self.source_code.len());
+        self.source_code.push_str(source_code);
+
+        self.files.push(SourceFile {
+            name: name.into(),
+            span: Span {
+                start,
+                end: BytePos(self.source_code.len()),
+            },
+            line_index: OnceLock::new(),
+        });
+
+        FileId(self.files.len() - 1)
+    }
+
+    pub(crate) fn file_ids(&self) -> impl Iterator<Item = FileId> {
+        (0..self.files.len()).map(FileId)
+    }
+
+    /// Source code of every file, as the compiler scans it.
+    pub(crate) fn source_code(&self) -> &str {
+        &self.source_code
+    }
+
+    pub(crate) fn file_name(&self, file_id: FileId) -> &str {
+        &self.files[file_id.0].name
+    }
+
+    /// Where the file's source code is in the source code of the program.
+    pub(crate) fn file_span(&self, file_id: FileId) -> Span {
+        self.files[file_id.0].span
+    }
+
+    pub(crate) fn file_source_code(&self, file_id: FileId) -> &str {
+        let span = self.file_span(file_id);
+
+        &self.source_code[span.start.0..span.end.0]
+    }
+
+    /// File that the position is in. The newline after a file, and positions
+    /// past the end of the program, are in the file before them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no files.
+    pub(crate) fn lookup_file(&self, pos: BytePos) -> FileId {
+        let file_idx = self
+            .files
+            .partition_point(|file| file.span.start.0 <= pos.0)
+            .max(1);
+
+        FileId(file_idx - 1)
+    }
+
+    /// File that the span starts in, and the span's byte offsets in it, which
+    /// are clamped to the file, as spans at the end of the program may be
+    /// past it.
+    pub(crate) fn file_range(&self, span: Span) -> (FileId, Range<usize>) {
+        let file_id = self.lookup_file(span.start);
+        let file_span = self.file_span(file_id);
+        let offset =
+            |pos: BytePos| pos.0.clamp(file_span.start.0, file_span.end.0) - file_span.start.0;
+
+        (file_id, offset(span.start)..offset(span.end))
+    }
+
+    /// File, line and column of the position, for showing it to people.
+    /// Lines count from the start of the file the position is in.
+    pub(crate) fn lookup_line_col(&self, pos: BytePos) -> (FileId, Line, Col) {
+        let file_id = self.lookup_file(pos);
+        let source_code = self.file_source_code(file_id);
+        let offset = BytePos(pos.0.saturating_sub(self.file_span(file_id).start.0));
+        let (line, col) = self.line_index(file_id).line_col(source_code, offset);
+
+        (file_id, line, col)
+    }
+
+    /// Text of the line of the file, without its line break.
+    pub(crate) fn line_text(&self, file_id: FileId, line: Line) -> &str {
+        let source_code = self.file_source_code(file_id);
+        let line_start = self.line_index(file_id).line_start(line).0;
+
+        source_code[line_start..]
+            .split('\n')
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches('\r')
+    }
+
+    /// Position in the program's source code that the line of the file
+    /// starts at.
+    pub(crate) fn line_start(&self, file_id: FileId, line: Line) -> BytePos {
+        let file_start = self.file_span(file_id).start.0;
+
+        BytePos(file_start + self.line_index(file_id).line_start(line).0)
+    }
+
+    fn line_index(&self, file_id: FileId) -> &LineIndex {
+        let file = &self.files[file_id.0];
+
+        file.line_index
+            .get_or_init(|| LineIndex::new(self.file_source_code(file_id)))
+    }
+}
+
+/// Source code given as a whole, rather than read from files, is in a single
+/// file that has no name of its own.
+impl<S: AsRef<str>> From<S> for SourceMap {
+    fn from(source_code: S) -> SourceMap {
+        let mut source_map = SourceMap::new();
+        source_map.add_file("<input>", source_code.as_ref());
+
+        source_map
+    }
+}
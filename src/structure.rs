@@ -0,0 +1,522 @@
+use std::fmt::Write;
+
+use crate::ast::{
+    CompoundExpr, Const, Expr, ForExpr, ForIteration, IfExpr, LoopKeyword, MatchExpr, Program,
+    RangeKind,
+};
+use crate::compiler_context::CompilerContext;
+use crate::cst::{NodeKind, SyntaxElement, SyntaxNode, SyntaxTokenKind};
+use crate::interner::Symbol;
+use crate::scanner::{Delim, Keyword, Scanner, TokenKind};
+
+/// Renders the program as an indented tree of its AST, one node per line,
+/// leaving out spans, so that programs render the same exactly when they're
+/// the same parse, however their source code is laid out.
+///
+/// Parentheses are left out too, as the tree already tells how operands are
+/// grouped. Names are rendered as they're spelled, so programs parsed with
+/// different contexts can be compared.
+pub(crate) fn render_ast(ctx: &CompilerContext, program: Program) -> String {
+    let mut renderer = AstRenderer {
+        ctx,
+        text: String::new(),
+        depth: 0,
+    };
+
+    for decl in program.decls {
+        renderer.node(
+            format!("decl {}", renderer.name(decl.identifier)),
+            |renderer| renderer.render_expr(decl.value),
+        );
+    }
+
+    renderer.text
+}
+
+/// Renders the tokens of the source code, one per line, as the span, the kind
+/// and the text of each one.
+pub(crate) fn render_tokens(ctx: &CompilerContext) -> String {
+    let source_code = ctx.get_source_code();
+    let mut text = String::new();
+
+    for token in Scanner::new(ctx) {
+        writeln!(
+            text,
+            "{:<12} {:<20} {}",
+            format!("{}..{}", token.span.start.0, token.span.end.0),
+            token_kind_name(token.kind),
+            &source_code[token.span.start.0..token.span.end.0]
+        )
+        .unwrap();
+    }
+
+    text
+}
+
+/// Renders the concrete syntax tree as an indented tree, one node or token
+/// per line, as the kind and the span of each one, along with the text of the
+/// tokens, escaped so that whitespace shows.
+pub(crate) fn render_cst(node: &SyntaxNode) -> String {
+    let mut text = String::new();
+    render_cst_node(&mut text, node, 0);
+    text
+}
+
+fn render_cst_node(text: &mut String, node: &SyntaxNode, depth: usize) {
+    let kind = match node.kind {
+        NodeKind::File => "file",
+        NodeKind::Item => "item",
+        NodeKind::Delimited(Delim::Paren) => "parens",
+        NodeKind::Delimited(Delim::Curly) => "curlies",
+        NodeKind::Delimited(Delim::Bracket) => "brackets",
+        NodeKind::Error => "error",
+    };
+    let span = node.span().map_or(String::new(), |span| {
+        format!(" {}..{}", span.start.0, span.end.0)
+    });
+    writeln!(text, "{}{}{}", "  ".repeat(depth), kind, span).unwrap();
+
+    for child in &node.children {
+        match child {
+            SyntaxElement::Node(node) => render_cst_node(text, node, depth + 1),
+            SyntaxElement::Token(token) => {
+                let kind = match token.kind {
+                    SyntaxTokenKind::Token(kind) => token_kind_name(kind),
+                    SyntaxTokenKind::Whitespace => "whitespace",
+                    SyntaxTokenKind::Comment => "comment",
+                };
+                writeln!(
+                    text,
+                    "{}{} {}..{} {:?}",
+                    "  ".repeat(depth + 1),
+                    kind,
+                    token.span.start.0,
+                    token.span.end.0,
+                    token.text
+                )
+                .unwrap();
+            }
+        }
+    }
+}
+
+fn token_kind_name(kind: TokenKind) -> &'static str {
+    match kind {
+        TokenKind::UnitConstant => "unit_constant",
+        TokenKind::IntegerConstant => "integer_constant",
+        TokenKind::Identifier => "identifier",
+        TokenKind::Comma => "comma",
+        TokenKind::Excla => "excla",
+        TokenKind::Hash => "hash",
+        TokenKind::Star => "star",
+        TokenKind::Slash => "slash",
+        TokenKind::Plus => "plus",
+        TokenKind::Dash => "dash",
+        TokenKind::Percent => "percent",
+        TokenKind::Less => "less",
+        TokenKind::Greater => "greater",
+        TokenKind::LessLess => "less_less",
+        TokenKind::GreaterGreater => "greater_greater",
+        TokenKind::LessEqual => "less_equal",
+        TokenKind::GreaterEqual => "greater_equal",
+        TokenKind::Colon => "colon",
+        TokenKind::ColonColon => "colon_colon",
+        TokenKind::ColonEqual => "colon_equal",
+        TokenKind::Equal => "equal",
+        TokenKind::EqualGreater => "equal_greater",
+        TokenKind::Semi => "semi",
+        TokenKind::DashGreater => "dash_greater",
+        TokenKind::Period => "period",
+        TokenKind::PeriodPeriod => "period_period",
+        TokenKind::PeriodPeriodEqual => "period_period_equal",
+        TokenKind::Keyword(Keyword::I8) => "keyword_i8",
+        TokenKind::Keyword(Keyword::I16) => "keyword_i16",
+        TokenKind::Keyword(Keyword::I32) => "keyword_i32",
+        TokenKind::Keyword(Keyword::I64) => "keyword_i64",
+        TokenKind::Keyword(Keyword::U8) => "keyword_u8",
+        TokenKind::Keyword(Keyword::U16) => "keyword_u16",
+        TokenKind::Keyword(Keyword::U32) => "keyword_u32",
+        TokenKind::Keyword(Keyword::U64) => "keyword_u64",
+        TokenKind::Keyword(Keyword::Usize) => "keyword_usize",
+        TokenKind::Keyword(Keyword::If) => "keyword_if",
+        TokenKind::Keyword(Keyword::Else) => "keyword_else",
+        TokenKind::Keyword(Keyword::For) => "keyword_for",
+        TokenKind::Keyword(Keyword::Loop) => "keyword_loop",
+        TokenKind::Keyword(Keyword::Break) => "keyword_break",
+        TokenKind::Keyword(Keyword::Continue) => "keyword_continue",
+        TokenKind::Keyword(Keyword::Match) => "keyword_match",
+        TokenKind::Keyword(Keyword::Struct) => "keyword_struct",
+        TokenKind::Keyword(Keyword::Enum) => "keyword_enum",
+        TokenKind::Keyword(Keyword::Extern) => "keyword_extern",
+        TokenKind::Keyword(Keyword::As) => "keyword_as",
+        TokenKind::Keyword(Keyword::Step) => "keyword_step",
+        TokenKind::Keyword(Keyword::Rev) => "keyword_rev",
+        TokenKind::Open(Delim::Paren) => "open_paren",
+        TokenKind::Open(Delim::Curly) => "open_curly",
+        TokenKind::Open(Delim::Bracket) => "open_bracket",
+        TokenKind::Closed(Delim::Paren) => "closed_paren",
+        TokenKind::Closed(Delim::Curly) => "closed_curly",
+        TokenKind::Closed(Delim::Bracket) => "closed_bracket",
+        TokenKind::Unknown => "unknown",
+    }
+}
+
+/// Lines of the rendered structures, marked with `-` if only `expected` has
+/// them, with `+` if only `found` has them, or indented if both have them.
+/// It's `None` if the structures are the same.
+///
+/// Structures are rendered one node per line, e.g., as the program is emitted
+/// with [`Emit::Ast`](crate::Emit::Ast), or [`Emit::Cst`](crate::Emit::Cst).
+///
+/// ```
+/// let diff = sophia::diff_structures("a\nb\nc\n", "a\nc\nd\n");
+/// assert_eq!(diff.unwrap(), "  a\n- b\n  c\n+ d\n");
+/// ```
+pub fn diff_structures(expected: &str, found: &str) -> Option<String> {
+    if expected == found {
+        return None;
+    }
+
+    let expected: Vec<_> = expected.lines().collect();
+    let found: Vec<_> = found.lines().collect();
+
+    // Length of the longest common subsequence of each pair of suffixes.
+    let mut lcs_len = vec![vec![0; found.len() + 1]; expected.len() + 1];
+
+    for i in (0..expected.len()).rev() {
+        for j in (0..found.len()).rev() {
+            lcs_len[i][j] = if expected[i] == found[j] {
+                lcs_len[i + 1][j + 1] + 1
+            } else {
+                lcs_len[i + 1][j].max(lcs_len[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < expected.len() || j < found.len() {
+        if i < expected.len() && j < found.len() && expected[i] == found[j] {
+            writeln!(diff, "  {}", expected[i]).unwrap();
+            (i, j) = (i + 1, j + 1);
+        } else if j == found.len() || (i < expected.len() && lcs_len[i + 1][j] >= lcs_len[i][j + 1])
+        {
+            writeln!(diff, "- {}", expected[i]).unwrap();
+            i += 1;
+        } else {
+            writeln!(diff, "+ {}", found[j]).unwrap();
+            j += 1;
+        }
+    }
+
+    Some(diff)
+}
+
+struct AstRenderer<'a> {
+    ctx: &'a CompilerContext,
+    text: String,
+    depth: usize,
+}
+
+impl AstRenderer<'_> {
+    fn render_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Const(const_expr) => match const_expr.value {
+                Const::IntegerConstant { value } => self.leaf(format!("const {}", value)),
+                Const::SuffixedIntegerConstant { value, ty } => {
+                    self.leaf(format!("const {}{}", value, ty))
+                }
+            },
+            Expr::BindRef(bind_ref) => self.leaf(format!("ref {}", self.name(bind_ref.identifier))),
+            Expr::BindDef(bind_def) => self.node(
+                format!("bind {}", self.name(bind_def.identifier)),
+                |renderer| renderer.render_expr(bind_def.value),
+            ),
+            Expr::Function(function) => {
+                let params: Vec<_> = function
+                    .parameters
+                    .iter()
+                    .map(|param| format!("{}: {}", self.name(param.identifier), param.ty))
+                    .collect();
+                let header = format!(
+                    "function ({}) -> {}",
+                    params.join(", "),
+                    function.return_type
+                );
+
+                self.node(header, |renderer| {
+                    renderer.render_compound_expr(function.body)
+                });
+            }
+            Expr::Extern(extern_fn) => {
+                let params: Vec<_> = extern_fn
+                    .parameters
+                    .iter()
+                    .map(|param| format!("{}: {}", self.name(param.identifier), param.ty))
+                    .collect();
+
+                self.leaf(format!(
+                    "extern ({}) -> {}",
+                    params.join(", "),
+                    extern_fn.return_type
+                ));
+            }
+            Expr::Struct(struct_def) => self.node("struct".to_owned(), |renderer| {
+                for field in struct_def.fields {
+                    renderer.leaf(format!(
+                        "field {}: {}",
+                        renderer.name(field.identifier),
+                        field.ty
+                    ));
+                }
+            }),
+            Expr::StructLit(struct_lit_expr) => self.node(
+                format!("struct literal {}", self.name(struct_lit_expr.identifier)),
+                |renderer| {
+                    for field in struct_lit_expr.fields {
+                        renderer.node(
+                            format!("field {}", renderer.name(field.identifier)),
+                            |renderer| renderer.render_expr(field.value),
+                        );
+                    }
+                },
+            ),
+            Expr::Field(field_expr) => self.node(
+                format!("field {}", self.name(field_expr.identifier)),
+                |renderer| renderer.render_expr(field_expr.base),
+            ),
+            Expr::Enum(enum_def) => self.node("enum".to_owned(), |renderer| {
+                for variant in enum_def.variants {
+                    let name = renderer.name(variant.identifier);
+
+                    match variant.payload {
+                        Some(payload) => renderer.leaf(format!("variant {}({})", name, payload)),
+                        None => renderer.leaf(format!("variant {}", name)),
+                    }
+                }
+            }),
+            Expr::Variant(variant_expr) => self.node(
+                format!(
+                    "variant {}::{}",
+                    self.name(variant_expr.enum_identifier),
+                    self.name(variant_expr.variant_identifier)
+                ),
+                |renderer| {
+                    if let Some(payload) = variant_expr.payload {
+                        renderer.render_expr(payload);
+                    }
+                },
+            ),
+            Expr::Tuple(tuple_expr) => self.node("tuple".to_owned(), |renderer| {
+                for element in tuple_expr.elements {
+                    renderer.render_expr(element);
+                }
+            }),
+            Expr::TupleField(tuple_field_expr) => self
+                .node(format!("field {}", tuple_field_expr.index), |renderer| {
+                    renderer.render_expr(tuple_field_expr.base)
+                }),
+            Expr::TupleBindDef(tuple_bind_def) => {
+                let names: Vec<_> = tuple_bind_def
+                    .bindings
+                    .iter()
+                    .map(|binding| self.name(binding.identifier))
+                    .collect();
+
+                self.node(format!("bind {}", names.join(", ")), |renderer| {
+                    renderer.render_expr(tuple_bind_def.value)
+                });
+            }
+            Expr::Array(array_expr) => self.node("array".to_owned(), |renderer| {
+                for element in array_expr.elements {
+                    renderer.render_expr(element);
+                }
+            }),
+            Expr::Index(index_expr) => self.node("index".to_owned(), |renderer| {
+                renderer.render_expr(index_expr.base);
+                renderer.render_expr(index_expr.index);
+            }),
+            Expr::IndexAssign(index_assign_expr) => self.node("assign".to_owned(), |renderer| {
+                renderer.node("index".to_owned(), |renderer| {
+                    renderer.render_expr(index_assign_expr.target.base);
+                    renderer.render_expr(index_assign_expr.target.index);
+                });
+                renderer.render_expr(index_assign_expr.value);
+            }),
+            Expr::If(if_expr) => self.render_if_expr(*if_expr),
+            Expr::Match(match_expr) => self.render_match_expr(*match_expr),
+            Expr::For(for_expr) => self.render_for_expr(*for_expr),
+            Expr::Break(break_expr) => match break_expr.value {
+                Some(value) => {
+                    self.node("break".to_owned(), |renderer| renderer.render_expr(value))
+                }
+                None => self.leaf("break".to_owned()),
+            },
+            Expr::Continue(_) => self.leaf("continue".to_owned()),
+            Expr::Compound(compound_expr) => self.render_compound_expr(*compound_expr),
+            Expr::Semi(expr) => self.node("semi".to_owned(), |renderer| renderer.render_expr(expr)),
+            Expr::FnCall(fn_call_expr) => self.node(
+                format!("call {}", self.name(fn_call_expr.identifier)),
+                |renderer| {
+                    for arg in fn_call_expr.args {
+                        renderer.render_expr(arg);
+                    }
+                },
+            ),
+            Expr::Binary(binary_expr) => {
+                // A chain of operations is a single node, whose operators fold
+                // its operands from the left, so that long chains are indented
+                // no deeper than short ones.
+                let (operand, operations) = binary_expr.chain();
+                let ops: Vec<_> = operations
+                    .iter()
+                    .map(|binary_expr| binary_expr.op.to_string())
+                    .collect();
+
+                self.node(format!("binary {}", ops.join(" ")), |renderer| {
+                    renderer.render_expr(operand);
+
+                    for binary_expr in operations {
+                        renderer.render_expr(binary_expr.rhs);
+                    }
+                });
+            }
+            Expr::Cast(cast_expr) => self.node(format!("cast {}", cast_expr.ty), |renderer| {
+                renderer.render_expr(cast_expr.expr)
+            }),
+            Expr::Paren(paren_expr) => self.render_expr(paren_expr.expr),
+        }
+    }
+
+    fn render_if_expr(&mut self, if_expr: IfExpr) {
+        self.node("if".to_owned(), |renderer| {
+            renderer.render_expr(if_expr.cond_expr);
+            renderer.render_compound_expr(if_expr.true_branch);
+
+            for branch in if_expr.else_if_branches {
+                renderer.node("else if".to_owned(), |renderer| {
+                    renderer.render_expr(branch.cond_expr);
+                    renderer.render_compound_expr(branch.true_branch);
+                });
+            }
+
+            if let Some(final_branch) = if_expr.final_branch {
+                renderer.node("else".to_owned(), |renderer| {
+                    renderer.render_compound_expr(final_branch)
+                });
+            }
+        });
+    }
+
+    fn render_match_expr(&mut self, match_expr: MatchExpr) {
+        self.node("match".to_owned(), |renderer| {
+            renderer.render_expr(match_expr.scrutinee);
+
+            for arm in match_expr.arms {
+                renderer.node(format!("arm {}", arm.pattern.kind), |renderer| {
+                    renderer.render_expr(arm.expr)
+                });
+            }
+        });
+    }
+
+    fn render_for_expr(&mut self, for_expr: ForExpr) {
+        let mut header = match for_expr.iteration {
+            None => match for_expr.keyword {
+                LoopKeyword::For => "for".to_owned(),
+                LoopKeyword::Loop => "loop".to_owned(),
+            },
+            Some(ForIteration::Conditional { .. }) => "for conditional".to_owned(),
+            Some(ForIteration::Iterative {
+                identifier,
+                range_kind,
+                step_expr,
+                is_reversed,
+                ..
+            }) => {
+                let mut header = format!("for {}", self.name(identifier));
+
+                if is_reversed {
+                    header.push_str(" rev");
+                }
+
+                header.push_str(match range_kind {
+                    RangeKind::Exclusive => " ..",
+                    RangeKind::Inclusive => " ..=",
+                });
+
+                if step_expr.is_some() {
+                    header.push_str(" step");
+                }
+
+                header
+            }
+            Some(ForIteration::InCollection { identifier, .. }) => {
+                format!("for {} in", self.name(identifier))
+            }
+        };
+
+        if for_expr.is_main_loop {
+            header.push_str(" #[main_loop]");
+        }
+
+        self.node(header, |renderer| {
+            match for_expr.iteration {
+                Some(ForIteration::Conditional { cond_expr }) => renderer.render_expr(cond_expr),
+                Some(ForIteration::Iterative {
+                    start_expr,
+                    end_expr,
+                    step_expr,
+                    ..
+                }) => {
+                    renderer.render_expr(start_expr);
+                    renderer.render_expr(end_expr);
+
+                    if let Some(step_expr) = step_expr {
+                        renderer.render_expr(step_expr);
+                    }
+                }
+                Some(ForIteration::InCollection {
+                    collection_expr, ..
+                }) => renderer.render_expr(collection_expr),
+                None => {}
+            }
+
+            renderer.render_compound_expr(for_expr.body);
+
+            if let Some(else_branch) = for_expr.else_branch {
+                renderer.node("else".to_owned(), |renderer| {
+                    renderer.render_compound_expr(else_branch)
+                });
+            }
+        });
+    }
+
+    fn render_compound_expr(&mut self, compound_expr: CompoundExpr) {
+        self.node("compound".to_owned(), |renderer| {
+            for expr in compound_expr.exprs {
+                renderer.render_expr(expr);
+            }
+        });
+    }
+
+    /// Renders a node, and then its children one level deeper.
+    fn node(&mut self, line: String, render_children: impl FnOnce(&mut Self)) {
+        self.leaf(line);
+        self.depth += 1;
+        render_children(self);
+        self.depth -= 1;
+    }
+
+    fn leaf(&mut self, line: String) {
+        // Expressions may nest deeper than a formatting width can pad to.
+        self.text.push_str(&"  ".repeat(self.depth));
+        self.text.push_str(&line);
+        self.text.push('\n');
+    }
+
+    fn name(&self, symbol: Symbol) -> &'static str {
+        self.ctx.resolve_symbol(symbol)
+    }
+}
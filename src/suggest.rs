@@ -0,0 +1,73 @@
+/// Name among the candidates that the name is most likely a misspelling of,
+/// if any is spelled close enough to it. Of the closest ones, it's the first
+/// in alphabetical order, so that it doesn't depend on the candidates' order.
+///
+/// Names may be a third of their characters off, but not all of them, so that
+/// short names aren't taken for any other short name.
+pub(crate) fn find_similar_name<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let name_len = name.chars().count();
+    let max_distance = (name_len.max(3) / 3).min(name_len.saturating_sub(1));
+
+    candidates
+        .into_iter()
+        .filter(|&candidate| candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Whether the text is the word with a typo in it, i.e., with one character
+/// inserted, deleted or replaced, or with two adjacent characters swapped,
+/// e.g., `fro` for `for`. Texts of a single character aren't taken for typos,
+/// as they're one character off from too many words.
+pub(crate) fn is_typo_of(text: &str, word: &str) -> bool {
+    if text == word || text.chars().count() < 2 {
+        return false;
+    }
+
+    let text_chars: Vec<char> = text.chars().collect();
+    let word_chars: Vec<char> = word.chars().collect();
+    let is_swap = text_chars.len() == word_chars.len() && {
+        let diff_indices: Vec<usize> = (0..text_chars.len())
+            .filter(|&idx| text_chars[idx] != word_chars[idx])
+            .collect();
+
+        matches!(
+            diff_indices[..],
+            [first, second] if second == first + 1
+                && text_chars[first] == word_chars[second]
+                && text_chars[second] == word_chars[first]
+        )
+    };
+
+    is_swap || edit_distance(text, word) == 1
+}
+
+/// How many characters have to be inserted, deleted or replaced to spell one
+/// string as the other, i.e., their Levenshtein distance.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    // Distances between the prefixes of `a` scanned so far and every prefix
+    // of `b`.
+    let mut distances: Vec<usize> = (0..=b_chars.len()).collect();
+
+    for (a_idx, a_char) in a.chars().enumerate() {
+        let mut diagonal_distance = distances[0];
+        distances[0] = a_idx + 1;
+
+        for (b_idx, &b_char) in b_chars.iter().enumerate() {
+            let replace_distance = diagonal_distance + usize::from(a_char != b_char);
+            diagonal_distance = distances[b_idx + 1];
+
+            distances[b_idx + 1] = replace_distance
+                .min(distances[b_idx + 1] + 1)
+                .min(distances[b_idx] + 1);
+        }
+    }
+
+    distances[b_chars.len()]
+}
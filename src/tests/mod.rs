@@ -1,13 +1,156 @@
-use crate::driver;
+use std::cell::RefCell;
+use std::rc::Rc;
 
+use crate::diagnostics::{CompileError, CompileWarning};
+use crate::driver::{self, Options};
+use crate::interp::RuntimeError;
+use crate::profile::IntProfile;
+use crate::scanner::{BytePos, Span};
+
+mod test_api;
+mod test_array;
+mod test_ast_owned;
 mod test_basic_programs;
+mod test_binary_expr;
 mod test_binding;
+mod test_build_info;
+mod test_builtin;
+mod test_bytecode;
+mod test_c;
+mod test_cfg;
+mod test_compiler_context;
+mod test_cst;
+mod test_daemon;
+mod test_diagnostics;
+mod test_doctest;
+mod test_enum;
+mod test_error_codes;
+mod test_expand;
+mod test_extern;
+mod test_features;
+mod test_fixer;
 mod test_for_expr;
+mod test_formatter;
 mod test_function_call;
+mod test_function_value;
+mod test_host;
 mod test_if_else;
+mod test_incremental;
+mod test_int_types;
+mod test_interp;
+mod test_ir;
+#[cfg(feature = "jit")]
+mod test_jit;
+mod test_json;
+mod test_limits;
+mod test_line_index;
+mod test_linker;
+mod test_lint_config;
+mod test_llvm;
+mod test_loop_value;
+mod test_lsp;
+mod test_manifest;
+mod test_match_expr;
+mod test_object;
+mod test_printer;
+mod test_profile;
+mod test_profiler;
+mod test_query;
+mod test_remarks;
+mod test_render;
+mod test_repl;
+mod test_resolve;
+mod test_rewrite;
+mod test_runtime;
+mod test_script;
+mod test_source_map;
+mod test_struct;
+mod test_structure;
+mod test_suggest;
+mod test_tuple;
+mod test_typeck;
+mod test_vm;
+mod test_wasm;
+mod test_watch;
+
+fn span(start: usize, end: usize) -> Span {
+    Span {
+        start: BytePos(start),
+        end: BytePos(end),
+    }
+}
+
+fn run(source_code: &str) -> Result<i32, RuntimeError> {
+    run_with_options(source_code, &Options::default())
+}
+
+fn run_with_profile(source_code: &str, int_profile: IntProfile) -> Result<i32, RuntimeError> {
+    let options = Options {
+        int_profile,
+        ..Default::default()
+    };
+
+    run_with_options(source_code, &options)
+}
+
+/// Runs the program, returning its exit code and what it printed.
+fn run_with_output(source_code: &str) -> (Result<i32, RuntimeError>, String) {
+    let output = Rc::new(RefCell::new(vec![]));
+    let options = Options {
+        output: Some(output.clone()),
+        ..Default::default()
+    };
+
+    let exit_code = run_with_options(source_code, &options);
+
+    (exit_code, String::from_utf8(output.take()).unwrap())
+}
+
+fn run_with_options(source_code: &str, options: &Options) -> Result<i32, RuntimeError> {
+    match driver::run(strip_margin(source_code), options) {
+        Ok(execution) => execution.exit_code,
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+fn check_errors(source_code: &str) -> Vec<CompileError> {
+    driver::check(strip_margin(source_code), &Options::default()).errors
+}
 
 fn compile(source_code: &str) -> String {
-    driver::compile(&strip_margin(source_code))
+    compile_with_options(source_code, &Options::default())
+}
+
+fn compile_script(source_code: &str) -> String {
+    let options = Options {
+        script_mode: true,
+        ..Default::default()
+    };
+
+    compile_with_options(source_code, &options)
+}
+
+fn compile_with_options(source_code: &str, options: &Options) -> String {
+    match driver::compile(strip_margin(source_code), options) {
+        Ok(compiled_program) => remove_assembler_directives(&compiled_program.code),
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+fn compile_errors(source_code: &str) -> Vec<CompileError> {
+    match driver::compile(strip_margin(source_code), &Options::default()) {
+        Ok(compiled_program) => {
+            panic!("program compiled unexpectedly:\n{}", compiled_program.code)
+        }
+        Err(diagnostic) => diagnostic.errors,
+    }
+}
+
+fn compile_warnings(source_code: &str) -> Vec<CompileWarning> {
+    let diagnostic = driver::check(strip_margin(source_code), &Options::default());
+    assert!(diagnostic.errors.is_empty(), "{:?}", diagnostic.errors);
+
+    diagnostic.warnings
 }
 
 fn check<S: AsRef<str>>(program: S, expected_program: &str) {
@@ -45,3 +188,13 @@ fn remove_assembly_comments(text: String) -> String {
         .collect::<Vec<_>>()
         .join("\n")
 }
+
+/// Removes the directives that make the assembly a complete file, so that tests
+/// can focus on the generated code.
+fn remove_assembler_directives(assembly: &str) -> String {
+    assembly
+        .lines()
+        .filter(|line| !line.starts_with("    ."))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
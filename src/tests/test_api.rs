@@ -0,0 +1,431 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use pretty_assertions::assert_eq;
+
+use crate::api::{compile_str, ExecutionError, Location, Message, Session};
+use crate::driver::Emit;
+use crate::snapshot::{Snapshot, SnapshotError};
+use crate::timing::Pass;
+use crate::vm::{ExecutionLimits, HostCall, Progress};
+
+#[test]
+fn test_session_runs_program_split_across_files() {
+    let mut session = Session::new();
+    session.add_file("main.sph", "main :: () -> i32 { double(21) }");
+    session.add_file("double.sph", "double :: (x: i32) -> i32 { x * 2 }");
+
+    let execution = session.run().unwrap();
+
+    assert_eq!(execution.exit_code, Ok(42));
+    assert_eq!(execution.warnings, []);
+}
+
+#[test]
+fn test_session_check_locates_messages_in_their_file() {
+    let mut session = Session::new();
+    session.add_file("main.sph", "main :: () {\n    helper()\n}\n");
+    session.add_file("helper.sph", "helper :: () {\n    x\n}\n");
+
+    let diagnostics = session.check();
+
+    assert!(diagnostics.has_errors());
+    assert_eq!(
+        diagnostics.errors,
+        [Message {
+            text: "cannot find `x` in this scope".to_owned(),
+            location: Some(Location {
+                file: "helper.sph".to_owned(),
+                line: 2,
+                col: 5,
+                range: 19..20,
+            }),
+        }]
+    );
+    assert_eq!(
+        diagnostics.to_string(),
+        "error[E0002]: cannot find `x` in this scope\n --> helper.sph:2:5\n  |\n2 |     x\n  |     ^\n"
+    );
+}
+
+#[test]
+fn test_session_with_emit() {
+    let mut session = Session::new().with_emit(Emit::Source);
+    session.add_file("main.sph", "main :: () -> i32 { (1) + 2 * 3 }");
+
+    let compiled_program = session.compile().unwrap();
+
+    assert_eq!(
+        compiled_program.code,
+        "main :: () -> i32 {\n    (1) + 2 * 3\n}\n"
+    );
+}
+
+#[test]
+fn test_session_with_script_mode() {
+    let mut session = Session::new().with_script_mode(true);
+    session.add_file("script.sph", "x := 40\n_ = x + 2\n");
+
+    assert_eq!(session.run().unwrap().exit_code, Ok(0));
+}
+
+#[test]
+fn test_runtime_errors_are_located() {
+    let mut session = Session::new();
+    session.add_file("main.sph", "main :: () -> i32 {\n    1 / 0\n}\n");
+
+    let exit_code = session.run().unwrap().exit_code;
+
+    assert_eq!(
+        exit_code,
+        Err(ExecutionError::Failed(Message {
+            text: "attempt to divide by zero".to_owned(),
+            location: Some(Location {
+                file: "main.sph".to_owned(),
+                line: 2,
+                col: 5,
+                range: 24..29,
+            }),
+        }))
+    );
+}
+
+#[test]
+fn test_runtime_errors_about_program_have_no_location() {
+    let mut session = Session::new();
+    session.add_file("main.sph", "");
+
+    assert_eq!(
+        session.run().unwrap().exit_code,
+        Err(ExecutionError::Failed(Message {
+            text: "`main` function not found".to_owned(),
+            location: None,
+        }))
+    );
+}
+
+#[test]
+fn test_session_runs_program_calling_registered_functions() {
+    let mut session = Session::new();
+    session.add_file(
+        "main.sph",
+        "main :: () -> i32 {\n    record(is_even(4));\n    record(is_even(5));\n    roll() * 10\n}\n",
+    );
+
+    let recorded = Rc::new(RefCell::new(vec![]));
+    let recorded_by_host = Rc::clone(&recorded);
+    session.register_fn("record", move |even: bool| {
+        recorded_by_host.borrow_mut().push(even);
+    });
+    session.register_fn("is_even", |n: i32| n % 2 == 0);
+    session.register_fn("roll", || 1);
+    // Registering a function of the same name replaces the former.
+    session.register_fn("roll", || 4);
+
+    assert_eq!(session.run().unwrap().exit_code, Ok(40));
+    assert_eq!(*recorded.borrow(), [true, false]);
+}
+
+#[test]
+fn test_registered_functions_are_type_checked_against() {
+    let mut session = Session::new();
+    session.add_file("main.sph", "main :: () -> i32 {\n    roll(6)\n}\n");
+    session.register_fn("roll", || 4);
+
+    let diagnostics = session.run().unwrap_err();
+
+    assert_eq!(
+        diagnostics.errors,
+        [Message {
+            text: "arguments to this function are incorrect: expected `()`, found `(i32)`"
+                .to_owned(),
+            location: Some(Location {
+                file: "main.sph".to_owned(),
+                line: 2,
+                col: 5,
+                range: 24..31,
+            }),
+        }]
+    );
+}
+
+#[test]
+fn test_registered_functions_failing_stop_the_program() {
+    let mut session = Session::new();
+    session.add_file("main.sph", "main :: () -> i32 {\n    sqrt(0 - 4)\n}\n");
+    session.register_fn("sqrt", |n: i32| match n {
+        0.. => Ok(f64::from(n).sqrt() as i32),
+        _ => Err(format!("{} has no square root", n)),
+    });
+
+    assert_eq!(
+        session.run().unwrap().exit_code,
+        Err(ExecutionError::Failed(Message {
+            text: "host function `sqrt` failed: -4 has no square root".to_owned(),
+            location: None,
+        }))
+    );
+}
+
+#[test]
+fn test_session_stops_program_out_of_fuel() {
+    let mut session = Session::new().with_fuel(100);
+    session.add_file("main.sph", "main :: () {\n    for i : 0..1000 {}\n}\n");
+
+    assert_eq!(
+        session.run().unwrap().exit_code,
+        Err(ExecutionError::OutOfFuel)
+    );
+
+    let mut session = Session::new().with_fuel(100_000);
+    session.add_file("main.sph", "main :: () {\n    for i : 0..1000 {}\n}\n");
+
+    assert_eq!(session.run().unwrap().exit_code, Ok(0));
+}
+
+#[test]
+fn test_session_limits_nested_calls() {
+    let source = "main :: () -> i32 {\n    depth(50)\n}\n\
+                  depth :: (n: i32) -> i32 {\n    if n { 1 + depth(n - 1) } else { 0 }\n}\n";
+
+    let mut session = Session::new().with_max_call_depth(20);
+    session.add_file("main.sph", source);
+
+    assert_eq!(
+        session.run().unwrap().exit_code,
+        Err(ExecutionError::CallDepthExceeded)
+    );
+
+    // Host functions don't change how limits are reported.
+    let mut session = Session::new().with_max_call_depth(20);
+    session.add_file("main.sph", source);
+    session.register_fn("roll", || 4);
+
+    assert_eq!(
+        session.run().unwrap().exit_code,
+        Err(ExecutionError::CallDepthExceeded)
+    );
+}
+
+#[test]
+fn test_session_stops_program_running_out_of_time() {
+    let mut session = Session::new().with_timeout(Duration::from_millis(10));
+    session.add_file("main.sph", "main :: () {\n    for {}\n}\n");
+
+    assert_eq!(
+        session.run().unwrap().exit_code,
+        Err(ExecutionError::TimedOut)
+    );
+}
+
+#[test]
+fn test_session_times_passes() {
+    let mut session = Session::new().with_time_passes(true);
+    session.add_file("main.sph", "main :: () -> i32 { double(21) }");
+    session.add_file("double.sph", "double :: (x: i32) -> i32 { x * 2 }");
+
+    session.compile().unwrap();
+
+    let pass_timings = session.pass_timings();
+    let passes: Vec<_> = pass_timings.iter().map(|timing| timing.pass).collect();
+
+    assert_eq!(
+        passes,
+        [
+            Pass::Lex,
+            Pass::Parse,
+            Pass::Resolve,
+            Pass::Layout,
+            Pass::Typecheck,
+            Pass::Lint,
+            Pass::Optimize,
+            Pass::Codegen,
+        ]
+    );
+
+    // Nothing is allocated before the program is parsed, and nothing is freed
+    // until the compilation is.
+    assert_eq!(pass_timings[0].arena_bytes, 0);
+    assert!(pass_timings[1].arena_bytes > 0);
+    assert!(pass_timings
+        .windows(2)
+        .all(|pair| pair[0].arena_bytes <= pair[1].arena_bytes
+            && pair[0].interned_strings <= pair[1].interned_strings));
+
+    // Timings are taken once they're given.
+    assert_eq!(session.pass_timings(), []);
+}
+
+#[test]
+fn test_session_times_passes_until_errors() {
+    let mut session = Session::new().with_time_passes(true);
+    session.add_file("main.sph", "main :: () -> i32 { x }");
+
+    assert!(session.check().has_errors());
+
+    let passes: Vec<_> = session
+        .pass_timings()
+        .iter()
+        .map(|timing| timing.pass)
+        .collect();
+
+    assert_eq!(passes, [Pass::Lex, Pass::Parse, Pass::Resolve]);
+}
+
+#[test]
+fn test_session_without_time_passes_has_no_timings() {
+    let mut session = Session::new();
+    session.add_file("main.sph", "main :: () -> i32 { 42 }");
+
+    session.compile().unwrap();
+
+    assert_eq!(session.pass_timings(), []);
+}
+
+#[test]
+fn test_vm_calls_functions_with_registered_ones() {
+    let mut session = Session::new();
+    session.add_file(
+        "main.sph",
+        "scale :: (n: i32) -> i32 { n * factor() }\nmain :: () {}",
+    );
+    session.register_fn("factor", || 3);
+
+    let mut vm = session.load_vm().unwrap();
+
+    assert_eq!(vm.call("scale", &[5]), Ok(Some(15)));
+    assert_eq!(vm.call("main", &[]), Ok(None));
+    assert_eq!(vm.run_main(), Ok(0));
+}
+
+#[test]
+fn test_vm_locates_runtime_errors_in_their_file() {
+    let mut session = Session::new();
+    session.add_file("main.sph", "main :: () {}");
+    session.add_file(
+        "divide.sph",
+        "divide :: (a: i32, b: i32) -> i32 {\n    a / b\n}\n",
+    );
+
+    let mut vm = session.load_vm().unwrap();
+
+    assert_eq!(
+        vm.call("divide", &[1, 0]),
+        Err(ExecutionError::Failed(Message {
+            text: "attempt to divide by zero".to_owned(),
+            location: Some(Location {
+                file: "divide.sph".to_owned(),
+                line: 2,
+                col: 5,
+                range: 40..45,
+            }),
+        }))
+    );
+    assert_eq!(
+        vm.call("multiply", &[]),
+        Err(ExecutionError::Failed(Message {
+            text: "function `multiply` not found".to_owned(),
+            location: None,
+        }))
+    );
+}
+
+#[test]
+fn test_vm_pauses_and_resumes_executions_within_limits() {
+    let mut session = Session::new().with_fuel(1_000_000);
+    session.add_file(
+        "main.sph",
+        "sum :: (n: i32) -> i32 { if n { n + sum(n - 1) } else { 0 } }\nmain :: () {}",
+    );
+
+    let mut vm = session.load_vm().unwrap();
+    let mut snapshot = vm.start("sum", &[100]).unwrap();
+
+    assert_eq!(vm.resume(&mut snapshot, 10), Ok(Progress::Paused));
+    assert_eq!(
+        vm.resume(&mut snapshot, u64::MAX),
+        Ok(Progress::Finished(Some(5050)))
+    );
+
+    vm.set_limits(ExecutionLimits {
+        max_instructions: 10,
+        ..Default::default()
+    });
+
+    assert_eq!(vm.call("sum", &[100]), Err(ExecutionError::OutOfFuel));
+}
+
+#[test]
+fn test_saved_snapshot_resumes_in_another_vm() {
+    let mut session = Session::new();
+    session.add_file(
+        "main.sph",
+        "sum :: (n: i32) -> i32 { if n { n + sum(n - 1) } else { 0 } }\nmain :: () {}",
+    );
+
+    let mut vm = session.load_vm().unwrap();
+    let mut snapshot = vm.start("sum", &[10]).unwrap();
+    vm.resume(&mut snapshot, 20).unwrap();
+
+    let mut other_vm = session.load_vm().unwrap();
+    let mut loaded_snapshot = Snapshot::load(&snapshot.save()).unwrap();
+
+    assert_eq!(loaded_snapshot, snapshot);
+    assert_eq!(
+        other_vm.resume(&mut loaded_snapshot, u64::MAX),
+        Ok(Progress::Finished(Some(55)))
+    );
+    assert_eq!(Snapshot::load(b"SBC\0"), Err(SnapshotError::NotSnapshot));
+}
+
+#[test]
+fn test_deterministic_vm_records_host_calls_to_replay() {
+    let mut session = Session::new();
+    session.add_file("main.sph", "main :: () -> i32 { add(1, 2) * 10 }");
+    session.register_fn("add", |a: i32, b: i32| a + b);
+
+    let mut vm = session.load_vm().unwrap();
+    vm.set_deterministic(true);
+
+    assert_eq!(vm.run_main(), Ok(30));
+    assert_eq!(
+        vm.host_calls(),
+        [HostCall {
+            name: "add".to_owned(),
+            args: vec![1, 2],
+            result: Ok(3),
+        }]
+    );
+
+    let mut replaying_vm = session.load_vm().unwrap();
+    replaying_vm.replay_host_calls(vec![HostCall {
+        name: "add".to_owned(),
+        args: vec![1, 2],
+        result: Ok(4),
+    }]);
+
+    assert_eq!(replaying_vm.run_main(), Ok(40));
+    assert_eq!(
+        replaying_vm.executed_instructions(),
+        vm.executed_instructions()
+    );
+}
+
+#[test]
+fn test_compile_str() {
+    let compiled_program = compile_str("main :: () { x := 1 }").unwrap();
+
+    assert_eq!(
+        compiled_program.warnings,
+        [Message {
+            text: "binding `x` is never read; discard the value with `_ = ...` instead".to_owned(),
+            location: Some(Location {
+                file: "<input>".to_owned(),
+                line: 1,
+                col: 14,
+                range: 13..19,
+            }),
+        }]
+    );
+}
@@ -0,0 +1,306 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast_owned::Type;
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::interp::RuntimeError;
+use crate::layout::Layouts;
+use crate::profile::IntWidth;
+use crate::tests::{check_errors, compile_errors, run, span, strip_margin};
+
+#[test]
+fn test_run_array_indexing_and_assignment() {
+    let exit_code = run(r#"
+        |sum :: (values: [i32; 4]) -> i32 {
+        |    values[0] + values[1] + values[2] + values[3]
+        |}
+        |
+        |main :: () -> i32 {
+        |    values := [1, 2, 3, 4,];
+        |    copy := values;
+        |    copy[0] = 10;
+        |    grid := [[0, 0], [0, 0]];
+        |    i := 1;
+        |    grid[i][i - 1] = values[(2)];
+        |    sum(copy) * 100 + values[0] * 10 + grid[1][0]
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(1913));
+}
+
+#[test]
+fn test_index_past_the_end_at_run_time() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    values := [1, 2];
+        |    i := 2;
+        |    values[i]
+        |}
+        |"#);
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::IndexOutOfBounds {
+            index: 2,
+            len: 2,
+            span: span(58, 67),
+        })
+    );
+    assert_eq!(
+        exit_code.unwrap_err().to_string(),
+        "index out of bounds: the length is 2 but the index is 2"
+    );
+
+    let exit_code = run(r#"
+        |main :: () {
+        |    values := [[1], [2]];
+        |    i := 0 - 1;
+        |    values[1][i] = 3;
+        |}
+        |"#);
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::IndexOutOfBounds {
+            index: -1,
+            len: 1,
+            span: span(59, 71),
+        })
+    );
+}
+
+#[test]
+fn test_constant_index_past_the_end() {
+    let errors = check_errors(
+        r#"
+        |main :: () -> i32 {
+        |    values := [1, 2, 3];
+        |    values[2] = 0;
+        |    values[3]
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::IndexOutOfBounds {
+            index: 3,
+            len: 3,
+            span: span(75, 76),
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "index out of bounds: the length is 3 but the index is 3"
+    );
+}
+
+#[test]
+fn test_elements_and_indices_must_have_expected_types() {
+    let errors = check_errors(
+        r#"
+        |main :: () -> [i32; 2] {
+        |    values := [1, {}];
+        |    values[values] = 0;
+        |    values[0] = {};
+        |    [1, 2, 3]
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(43, 45),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Array {
+                    element: Box::new(Type::I32),
+                    len: 2,
+                },
+                span: span(59, 65),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(88, 90),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::Array {
+                    element: Box::new(Type::I32),
+                    len: 2,
+                },
+                found: Type::Array {
+                    element: Box::new(Type::I32),
+                    len: 3,
+                },
+                span: span(96, 105),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[3].to_string(),
+        "mismatched types: expected `[i32; 2]`, found `[i32; 3]`"
+    );
+}
+
+#[test]
+fn test_only_elements_of_bound_arrays_can_be_assigned_to() {
+    let errors = check_errors(
+        r#"
+        |values :: () -> [i32; 2] {
+        |    [1, 2]
+        |}
+        |
+        |main :: () {
+        |    x := 1;
+        |    x[0] = 2;
+        |    values()[0] = 3;
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::CannotIndex {
+                ty: Type::I32,
+                span: span(69, 73),
+            },
+            CompileError::InvalidAssignTarget { span: span(83, 94) },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "cannot index into a value of type `i32`"
+    );
+    assert_eq!(
+        errors[1].to_string(),
+        "only elements of arrays bound to names can be assigned to"
+    );
+}
+
+#[test]
+fn test_arrays_in_structs_are_laid_out_element_after_element() {
+    let context = CompilerContext::new(strip_margin(
+        r#"
+        |Grid :: struct { flag: (), cells: [[i32; 3]; 2], empty: [(); 4] }
+        |"#,
+    ));
+    let program = driver::parse(&context, &Options::default()).unwrap();
+
+    let layouts = Layouts::of_program(&context, program, IntWidth::Bits16).unwrap();
+    let layout = layouts.get_struct("Grid").unwrap();
+
+    assert_eq!(
+        layout
+            .fields
+            .iter()
+            .map(|field| field.offset)
+            .collect::<Vec<_>>(),
+        vec![0, 0, 12]
+    );
+    assert_eq!((layout.size, layout.align), (12, 2));
+}
+
+#[test]
+fn test_compiled_backends_reject_arrays() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    values := [1, 2];
+        |    values[0]
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::ArraysUnsupported { backend: "x86-64" }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "the x86-64 backend doesn't support arrays yet; run the program with the interpreter \
+         instead"
+    );
+
+    let errors = compile_errors(
+        r#"
+        |first :: (pairs: [(i32, i32); 2]) -> i32 { 0 }
+        |main :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::TuplesUnsupported { backend: "x86-64" }]
+    );
+}
+
+#[test]
+fn test_run_for_each_over_array() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    sum := [0];
+        |    for x : [1, 2, 3] {
+        |        sum[0] = sum[0] + x;
+        |    } else {
+        |        sum[0] = sum[0] * 10;
+        |    }
+        |    grid := [[1, 2], [3, 4]];
+        |    for row : grid {
+        |        if row[0] / 3 {
+        |            break;
+        |        }
+        |        for cell : row {
+        |            sum[0] = sum[0] + cell * 100;
+        |        }
+        |    } else {
+        |        sum[0] = 0;
+        |    }
+        |    sum[0]
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(360));
+}
+
+#[test]
+fn test_for_each_over_non_array() {
+    let errors = check_errors(
+        r#"
+        |main :: () -> i32 {
+        |    count := 3;
+        |    for x : count {
+        |        x + 1;
+        |    }
+        |    0
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::CannotIterate {
+                ty: Type::I32,
+                span: span(48, 53),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(64, 65),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "cannot iterate over a value of type `i32`"
+    );
+}
@@ -0,0 +1,220 @@
+use std::thread;
+
+use pretty_assertions::assert_eq;
+
+use crate::api::{parse_decl_fragment, parse_expr_fragment, Location, Message, Session};
+use crate::ast_owned::{
+    BinaryExpr, BinaryOp, BindDef, BindRef, CompoundExpr, Const, ConstExpr, Decl, Expr, FnCallExpr,
+    ForExpr, ForIteration, Function, Param, ParenExpr, RangeKind, Span, Type,
+};
+
+fn span(file: usize, start: usize, end: usize) -> Span {
+    Span {
+        file,
+        range: start..end,
+    }
+}
+
+fn int(value: i32, span: Span) -> Expr {
+    Expr::Const(ConstExpr {
+        value: Const::IntegerConstant { value },
+        span,
+    })
+}
+
+#[test]
+fn test_parse_into_owned_ast_with_spans_in_files() {
+    let mut session = Session::new();
+    session.add_file("main.sph", "main :: () -> i32 { (x) * 2 }");
+    session.add_file("x.sph", "x :: (a: i32) { b := a; }");
+
+    let program = session.parse().unwrap();
+
+    assert_eq!(
+        program.decls,
+        [
+            Decl {
+                identifier: "main".to_owned(),
+                identifier_span: span(0, 0, 4),
+                value: Expr::Function(Function {
+                    return_type: Type::I32,
+                    parameters: vec![],
+                    body: CompoundExpr {
+                        exprs: vec![Expr::Binary(BinaryExpr {
+                            op: BinaryOp::Mul,
+                            lhs: Box::new(Expr::Paren(ParenExpr {
+                                expr: Box::new(Expr::BindRef(BindRef {
+                                    identifier: "x".to_owned(),
+                                    span: span(0, 21, 22),
+                                })),
+                                span: span(0, 20, 23),
+                            })),
+                            rhs: Box::new(int(2, span(0, 26, 27))),
+                            span: span(0, 20, 27),
+                        })],
+                        span: span(0, 18, 29),
+                    },
+                    span: span(0, 8, 29),
+                }),
+            },
+            Decl {
+                identifier: "x".to_owned(),
+                identifier_span: span(1, 0, 1),
+                value: Expr::Function(Function {
+                    return_type: Type::Unit,
+                    parameters: vec![Param {
+                        identifier: "a".to_owned(),
+                        ty: Type::I32,
+                        span: span(1, 6, 12),
+                    }],
+                    body: CompoundExpr {
+                        exprs: vec![Expr::Semi(Box::new(Expr::BindDef(BindDef {
+                            identifier: "b".to_owned(),
+                            ty: None,
+                            value: Box::new(Expr::BindRef(BindRef {
+                                identifier: "a".to_owned(),
+                                span: span(1, 21, 22),
+                            })),
+                            span: span(1, 16, 22),
+                        })))],
+                        span: span(1, 14, 25),
+                    },
+                    span: span(1, 5, 25),
+                }),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_owned_ast_outlives_session_on_another_thread() {
+    let program = {
+        let mut session = Session::new();
+        session.add_file(
+            "main.sph",
+            "main :: () { for i : 0..=9 { f(i, 1); } }\nf :: (a: i32, b: i32) {}",
+        );
+
+        session.parse().unwrap()
+    };
+
+    let for_expr = thread::spawn(move || {
+        let Expr::Function(main) = &program.decls[0].value else {
+            panic!("`main` isn't a function");
+        };
+        let Expr::For(for_expr) = &main.body.exprs[0] else {
+            panic!("`main` doesn't start with a loop");
+        };
+
+        for_expr.clone()
+    })
+    .join()
+    .unwrap();
+
+    let ForExpr {
+        iteration:
+            Some(ForIteration::Iterative {
+                identifier,
+                range_kind: RangeKind::Inclusive,
+                ..
+            }),
+        body,
+        ..
+    } = for_expr
+    else {
+        panic!("loop doesn't iterate over an inclusive range");
+    };
+
+    assert_eq!(identifier, "i");
+    assert!(matches!(
+        &body.exprs[0],
+        Expr::Semi(call) if matches!(
+            &**call,
+            Expr::FnCall(FnCallExpr { identifier, args, .. })
+                if identifier == "f" && args.len() == 2
+        )
+    ));
+}
+
+#[test]
+fn test_parse_fails_only_on_code_that_cant_be_parsed() {
+    let mut session = Session::new();
+    session.add_file("main.sph", "main :: () -> i32 { x }");
+
+    // Unknown names are found by analyses, which parsing doesn't run.
+    assert!(session.parse().is_ok());
+
+    let mut session = Session::new();
+    session.add_file("main.sph", "main :: () -> i32 { 1");
+
+    let diagnostics = session.parse().unwrap_err();
+
+    assert_eq!(diagnostics.errors[0].text, "unclosed `{` opened here");
+}
+
+#[test]
+fn test_parse_expr_fragment() {
+    let expr = parse_expr_fragment("x := f(1);").unwrap();
+
+    assert_eq!(
+        expr,
+        Expr::Semi(Box::new(Expr::BindDef(BindDef {
+            identifier: "x".to_owned(),
+            ty: None,
+            value: Box::new(Expr::FnCall(FnCallExpr {
+                identifier: "f".to_owned(),
+                args: vec![int(1, span(0, 7, 8))],
+                span: span(0, 5, 9),
+            })),
+            span: span(0, 0, 9),
+        })))
+    );
+}
+
+#[test]
+fn test_parse_decl_fragment() {
+    let decl = parse_decl_fragment("answer :: () -> i32 { 42 }").unwrap();
+
+    assert_eq!(decl.identifier, "answer");
+    assert_eq!(decl.value.span(), &span(0, 10, 26));
+}
+
+#[test]
+fn test_fragment_is_all_of_the_code() {
+    let diagnostics = parse_expr_fragment("1 + 2 3").unwrap_err();
+
+    assert_eq!(
+        diagnostics.errors,
+        [Message {
+            text: "expected one of `.`, `[`, `as`, `+`, `-`, `*`, `/`, `%`, `;`, found integer"
+                .to_owned(),
+            location: Some(Location {
+                file: "<fragment>".to_owned(),
+                line: 1,
+                col: 7,
+                range: 6..7,
+            }),
+        }]
+    );
+
+    let diagnostics = parse_decl_fragment("a :: () {}\nb :: () {}").unwrap_err();
+
+    assert_eq!(
+        diagnostics.errors[0].text,
+        "expected one of `.`, `[`, `as`, `+`, `-`, `*`, `/`, `%`, found identifier"
+    );
+}
+
+#[test]
+fn test_empty_fragment_is_an_error() {
+    let diagnostics = parse_expr_fragment("").unwrap_err();
+
+    assert!(diagnostics.errors[0].text.ends_with("found end of file"));
+
+    let diagnostics = parse_decl_fragment("main").unwrap_err();
+
+    assert_eq!(
+        diagnostics.errors[0].text,
+        "expected `::`, found end of file"
+    );
+}
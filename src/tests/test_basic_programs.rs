@@ -1,4 +1,5 @@
-use crate::tests::{check, compile};
+use crate::driver::{self, Options};
+use crate::tests::{check, compile, strip_margin};
 
 #[test]
 fn test_main_empty_function_returns_0() {
@@ -88,3 +89,38 @@ fn test_main_function_explicitly_returns_42() {
         |"#,
     );
 }
+
+#[test]
+fn test_assembly_file_exports_top_level_functions() {
+    let compiled_program = driver::compile(
+        strip_margin(
+            r#"
+            |main :: () {}
+            |foo :: () {}
+            |"#,
+        ),
+        &Options::default(),
+    )
+    .unwrap_or_else(|diagnostic| panic!("program failed to compile: {:?}", diagnostic.errors));
+
+    check(
+        compiled_program.code,
+        r#"
+        |    .intel_syntax noprefix
+        |    .text
+        |    .globl main
+        |    .globl foo
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    pop rbp
+        |    ret
+        |foo:
+        |    push rbp
+        |    mov rbp, rsp
+        |    pop rbp
+        |    ret
+        |    .section .note.GNU-stack,"",@progbits
+        |"#,
+    );
+}
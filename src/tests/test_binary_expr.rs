@@ -0,0 +1,68 @@
+use crate::tests::{check, compile};
+
+#[test]
+fn test_additive_expr() {
+    let program = compile(
+        r#"
+        |main :: () -> i32 {
+        |    1 + 2 - 3
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    mov eax, 1
+        |    add eax, 2
+        |    sub eax, 3
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_multiplicative_exprs_bind_tighter() {
+    let program = compile(
+        r#"
+        |main :: () -> i32 {
+        |    x := 7;
+        |    x * 2 + x / (3 % 2)
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    sub rsp, 12
+        |    mov eax, 7
+        |    mov ecx, 2
+        |    imul eax, ecx
+        |    mov DWORD PTR [rbp-4], eax
+        |    mov eax, 3
+        |    mov ecx, 2
+        |    cdq
+        |    idiv ecx
+        |    mov eax, edx
+        |    mov DWORD PTR [rbp-8], eax
+        |    mov eax, 7
+        |    mov ecx, DWORD PTR [rbp-8]
+        |    cdq
+        |    idiv ecx
+        |    mov DWORD PTR [rbp-12], eax
+        |    mov eax, DWORD PTR [rbp-4]
+        |    add eax, DWORD PTR [rbp-12]
+        |    add rsp, 12
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
@@ -17,14 +17,7 @@ fn test_bind_to_primary_expr_and_return_from_function() {
         |main:
         |    push rbp
         |    mov rbp, rsp
-        |    sub rsp, 4
-        |
         |    mov eax, 42
-        |    mov DWORD PTR [rbp-4], eax
-        |
-        |    mov eax, DWORD PTR [rbp-4]
-        |
-        |    add rsp, 4
         |    pop rbp
         |    ret
         |"#,
@@ -32,7 +25,7 @@ fn test_bind_to_primary_expr_and_return_from_function() {
 }
 
 #[test]
-fn test_allocate_stack_according_to_how_many_bindings_there_are_and_ref_then_back() {
+fn test_bindings_of_constants_take_no_stack() {
     let program = compile(
         r#"
         |main :: () -> i32 {
@@ -52,23 +45,7 @@ fn test_allocate_stack_according_to_how_many_bindings_there_are_and_ref_then_bac
         |main:
         |    push rbp
         |    mov rbp, rsp
-        |    sub rsp, 16
-        |
-        |    mov eax, 42
-        |    mov DWORD PTR [rbp-4], eax
-        |
         |    mov eax, 314
-        |    mov DWORD PTR [rbp-8], eax
-        |
-        |    mov eax, 1
-        |    mov DWORD PTR [rbp-12], eax
-        |
-        |    mov eax, 0
-        |    mov DWORD PTR [rbp-16], eax
-        |
-        |    mov eax, DWORD PTR [rbp-8]
-        |
-        |    add rsp, 16
         |    pop rbp
         |    ret
         |"#,
@@ -79,19 +56,19 @@ fn test_allocate_stack_according_to_how_many_bindings_there_are_and_ref_then_bac
 fn test_allocate_stack_for_many_functions() {
     let program = compile(
         r#"
-        |func1 :: () -> i32 {
-        |    foo := 42;
-        |    quxx := 314;
+        |func1 :: (a: i32) -> i32 {
+        |    foo := a * 42;
+        |    quxx := foo + 314;
         |
-        |    foo
+        |    foo * quxx
         |}
         |
         |func2 :: () -> i32 {
-        |    quxx := 1;
-        |    baz := 2;
-        |    bar := 3;
+        |    quxx := func1(1);
+        |    baz := func1(2);
+        |    bar := func1(3);
         |
-        |    quxx
+        |    quxx + baz + bar
         |}
         |"#,
     );
@@ -102,27 +79,37 @@ fn test_allocate_stack_for_many_functions() {
         |func1:
         |    push rbp
         |    mov rbp, rsp
-        |    sub rsp, 8
-        |    mov eax, 42
-        |    mov DWORD PTR [rbp-4], eax
-        |    mov eax, 314
-        |    mov DWORD PTR [rbp-8], eax
+        |    sub rsp, 12
+        |    mov DWORD PTR [rbp-4], edi
         |    mov eax, DWORD PTR [rbp-4]
-        |    add rsp, 8
+        |    mov ecx, 42
+        |    imul eax, ecx
+        |    mov DWORD PTR [rbp-8], eax
+        |    mov eax, DWORD PTR [rbp-8]
+        |    add eax, 314
+        |    mov DWORD PTR [rbp-12], eax
+        |    mov eax, DWORD PTR [rbp-8]
+        |    mov ecx, DWORD PTR [rbp-12]
+        |    imul eax, ecx
+        |    add rsp, 12
         |    pop rbp
         |    ret
-        |
         |func2:
         |    push rbp
         |    mov rbp, rsp
         |    sub rsp, 12
-        |    mov eax, 1
+        |    mov edi, 1
+        |    call func1
         |    mov DWORD PTR [rbp-4], eax
-        |    mov eax, 2
+        |    mov edi, 2
+        |    call func1
         |    mov DWORD PTR [rbp-8], eax
-        |    mov eax, 3
+        |    mov edi, 3
+        |    call func1
         |    mov DWORD PTR [rbp-12], eax
         |    mov eax, DWORD PTR [rbp-4]
+        |    add eax, DWORD PTR [rbp-8]
+        |    add eax, DWORD PTR [rbp-12]
         |    add rsp, 12
         |    pop rbp
         |    ret
@@ -131,7 +118,7 @@ fn test_allocate_stack_for_many_functions() {
 }
 
 #[test]
-fn test_allocate_stack_for_inner_scopes() {
+fn test_inner_scopes_shadow_outer_bindings() {
     let program = compile(
         r#"
         |func :: () -> i32 {
@@ -146,8 +133,7 @@ fn test_allocate_stack_for_inner_scopes() {
         |
         |    quxx := 4;
         |
-        |    foo;
-        |    quxx
+        |    foo * quxx
         |}
         |"#,
     );
@@ -158,27 +144,9 @@ fn test_allocate_stack_for_inner_scopes() {
         |func:
         |    push rbp
         |    mov rbp, rsp
-        |    sub rsp, 16
-        |
         |    mov eax, 1
-        |    mov DWORD PTR [rbp-4], eax
-        |
-        |    mov eax, 2
-        |    mov DWORD PTR [rbp-8], eax
-        |
-        |    mov eax, 3
-        |    mov DWORD PTR [rbp-12], eax
-        |
-        |    mov eax, DWORD PTR [rbp-12]
-        |
-        |    mov eax, 4
-        |    mov DWORD PTR [rbp-16], eax
-        |
-        |    mov eax, DWORD PTR [rbp-4]
-        |
-        |    mov eax, DWORD PTR [rbp-16]
-        |
-        |    add rsp, 16
+        |    mov ecx, 4
+        |    imul eax, ecx
         |    pop rbp
         |    ret
         |"#,
@@ -189,7 +157,7 @@ fn test_allocate_stack_for_inner_scopes() {
 fn test_access_outer_scope_bindings() {
     let program = compile(
         r#"
-        |func :: () -> i32 {
+        |func :: () {
         |    foo := 1;
         |
         |    {
@@ -205,14 +173,65 @@ fn test_access_outer_scope_bindings() {
         |func:
         |    push rbp
         |    mov rbp, rsp
-        |    sub rsp, 4
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_discarded_values_are_evaluated_but_not_stored() {
+    let program = compile(
+        r#"
+        |main :: () {
+        |    _ := foo();
+        |    _ = 42;
+        |}
         |
+        |foo :: () -> i32 {
+        |    1
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    call foo
+        |    pop rbp
+        |    ret
+        |foo:
+        |    push rbp
+        |    mov rbp, rsp
         |    mov eax, 1
-        |    mov DWORD PTR [rbp-4], eax
-        |
-        |    mov eax, DWORD PTR [rbp-4]
-        |
-        |    add rsp, 4
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_bind_with_annotated_type() {
+    let program = compile(
+        r#"
+        |main :: () -> i32 {
+        |    foo : i32 = 42;
+        |    _ : i32 = foo;
+        |    foo
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    mov eax, 42
         |    pop rbp
         |    ret
         |"#,
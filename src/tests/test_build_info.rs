@@ -0,0 +1,32 @@
+use crate::build_info::build_info;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Emit, Options};
+
+#[test]
+fn test_compiled_backends_are_named_like_their_errors() {
+    let backends = build_info().backends;
+
+    for emit in [Emit::Assembly, Emit::LlvmIr, Emit::Wat, Emit::C] {
+        let options = Options {
+            emit,
+            ..Default::default()
+        };
+        let diagnostic = driver::compile("P :: struct {}\nmain :: () {}\n", &options)
+            .err()
+            .unwrap();
+
+        let [CompileError::StructsUnsupported { backend }] = diagnostic.errors[..] else {
+            panic!("unexpected errors: {:?}", diagnostic.errors);
+        };
+
+        assert!(backends.contains(&backend), "{} isn't listed", backend);
+    }
+}
+
+#[test]
+fn test_jit_backend_comes_with_its_feature() {
+    let info = build_info();
+
+    assert_eq!(info.backends.contains(&"jit"), cfg!(feature = "jit"));
+    assert_eq!(info.features.contains(&"jit"), cfg!(feature = "jit"));
+}
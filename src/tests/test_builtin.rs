@@ -0,0 +1,152 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use pretty_assertions::assert_eq;
+
+use crate::ast_owned::Type;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::tests::{check, compile, run_with_output, span, strip_margin};
+use crate::vm::Vm;
+
+#[test]
+fn test_run_prints_integers() {
+    let (exit_code, output) = run_with_output(
+        r#"
+        |main :: () {
+        |    for i : 1..=3 {
+        |        print(i * i);
+        |        print(0);
+        |    }
+        |    println(0 - 5);
+        |    println(200u8);
+        |    show := println;
+        |    show(7);
+        |}
+        |"#,
+    );
+
+    assert_eq!(exit_code, Ok(0));
+    assert_eq!(output, "104090-5\n200\n7\n");
+}
+
+#[test]
+fn test_declarations_shadow_builtins() {
+    let (exit_code, output) = run_with_output(
+        r#"
+        |main :: () {
+        |    println(2);
+        |}
+        |
+        |println :: (x: i32) {
+        |    print(x * 10);
+        |}
+        |"#,
+    );
+
+    assert_eq!(exit_code, Ok(0));
+    assert_eq!(output, "20");
+}
+
+#[test]
+fn test_builtins_print_a_single_integer() {
+    let errors = driver::check(
+        strip_margin(
+            r#"
+            |main :: () {
+            |    print();
+            |    println(1, 2);
+            |    print({});
+            |}
+            |"#,
+        ),
+        &Options::default(),
+    )
+    .errors;
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MismatchedCallArguments {
+                expected: vec![Type::I32],
+                found: vec![],
+                span: span(17, 24),
+            },
+            CompileError::MismatchedCallArguments {
+                expected: vec![Type::I32],
+                found: vec![Type::I32, Type::I32],
+                span: span(30, 43),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(55, 57),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_vm_prints_through_host_functions() {
+    let compiled_bytecode = driver::compile_bytecode(
+        strip_margin(
+            r#"
+            |main :: () {
+            |    println(1);
+            |    print(2);
+            |    println(3);
+            |}
+            |"#,
+        ),
+        &Options::default(),
+    )
+    .unwrap();
+    let imports: Vec<_> = compiled_bytecode
+        .module
+        .host_functions
+        .iter()
+        .map(|import| import.name.as_str())
+        .collect();
+
+    assert_eq!(imports, ["println", "print"]);
+
+    let mut vm = Vm::new(compiled_bytecode.module);
+    let printed = Rc::new(RefCell::new(String::new()));
+
+    for name in ["print", "println"] {
+        let printed = Rc::clone(&printed);
+        vm.register_host_function(name, move |args| {
+            printed
+                .borrow_mut()
+                .push_str(&format!("{}({}) ", name, args[0]));
+            Ok(0)
+        });
+    }
+
+    assert_eq!(vm.run_main(), Ok(0));
+    assert_eq!(*printed.borrow(), "println(1) print(2) println(3) ");
+}
+
+#[test]
+fn test_native_code_calls_the_runtime_library() {
+    let program = compile(
+        r#"
+        |main :: () {
+        |    println(42);
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    mov edi, 42
+        |    call sophia_println_i32
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
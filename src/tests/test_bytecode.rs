@@ -0,0 +1,312 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast::BinaryOp;
+use crate::bytecode::{LoadError, Module, FORMAT_VERSION, MAGIC};
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::interp::RuntimeError;
+use crate::profile::{IntProfile, Overflow};
+use crate::tests::{span, strip_margin};
+use crate::vm::Vm;
+
+fn compile_bytecode(source_code: &str) -> Module {
+    match driver::compile_bytecode(strip_margin(source_code), &Options::default()) {
+        Ok(compiled_bytecode) => compiled_bytecode.module,
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+/// Runs the program from its saved bytecode, checking that it does just what
+/// the interpreter does.
+fn run(source_code: &str) -> Result<i32, RuntimeError> {
+    let exit_code = Vm::load(&compile_bytecode(source_code).save())
+        .unwrap()
+        .run_main();
+
+    let interpreted = driver::run(strip_margin(source_code), &Options::default());
+    assert_eq!(exit_code, interpreted.ok().unwrap().exit_code);
+
+    exit_code
+}
+
+#[test]
+fn test_saved_module_loads_back_unchanged() {
+    let module = compile_bytecode(
+        r#"
+        |main :: () -> i32 {
+        |    square(7) / 2
+        |}
+        |
+        |square :: (n: i32) -> i32 {
+        |    n * n
+        |}
+        |"#,
+    );
+
+    assert_eq!(Module::load(&module.save()), Ok(module));
+}
+
+#[test]
+fn test_constants_are_pooled() {
+    let module = compile_bytecode(
+        r#"
+        |main :: () -> i32 {
+        |    7 + 7 * 7 - 3
+        |}
+        |"#,
+    );
+
+    assert_eq!(module.constants, vec![7, 3]);
+}
+
+#[test]
+fn test_recursive_calls() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    fib(10)
+        |}
+        |
+        |fib :: (n: i32) -> i32 {
+        |    if n - 1 {
+        |        if n {
+        |            fib(n - 1) + fib(n - 2)
+        |        } else {
+        |            0
+        |        }
+        |    } else {
+        |        1
+        |    }
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(55));
+}
+
+#[test]
+fn test_arguments_keep_their_order() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    digits(1, {}, 2, 3)
+        |}
+        |
+        |digits :: (a: i32, u: (), b: i32, c: i32) -> i32 {
+        |    x := u;
+        |    a * 100 + b * 10 + c
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(123));
+}
+
+#[test]
+fn test_break_skips_else_branch() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    for i : 0..10 {
+        |        if i - 5 {
+        |            continue
+        |        }
+        |        for {
+        |            break
+        |        }
+        |        break
+        |    } else {
+        |        _ = 1 / 0;
+        |    }
+        |    7
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(7));
+}
+
+#[test]
+fn test_completed_loop_runs_else_branch() {
+    let exit_code = run(r#"
+        |main :: () {
+        |    for 0 {
+        |    } else {
+        |        _ = 1 / 0;
+        |    }
+        |}
+        |"#);
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::DivisionByZero { span: span(50, 55) })
+    );
+}
+
+#[test]
+fn test_range_stops_before_stepping_past_the_largest_i32() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    for i : 0..=2147483647 step 1000000000 {}
+        |    for i : rev 0 - 2147483647 - 1..=2147483647 step 1000000000 {}
+        |    for i : 2147483640..=2147483647 step 2147483647 {} else {
+        |        _ = 10 / 0;
+        |    }
+        |    1
+        |}
+        |"#);
+
+    // Only a loop that runs to completion gets to its else-branch.
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::DivisionByZero {
+            span: span(207, 213)
+        })
+    );
+}
+
+#[test]
+fn test_if_without_else_discards_its_value() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    for i : 0..3 {
+        |        if i {
+        |            i * 2;
+        |        }
+        |    }
+        |    if 1 {
+        |        5;
+        |    }
+        |    9
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(9));
+}
+
+#[test]
+fn test_division_by_zero() {
+    let exit_code = run(r#"
+        |main :: () {
+        |    for i : 0..=3 {
+        |        _ = 10 / (i - 3);
+        |    }
+        |}
+        |"#);
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::DivisionByZero { span: span(45, 57) })
+    );
+}
+
+#[test]
+fn test_division_overflow() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    (0 - 2147483647 - 1) % (0 - 1)
+        |}
+        |"#);
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::Overflow {
+            op: BinaryOp::Rem,
+            span: span(24, 54),
+        })
+    );
+}
+
+#[test]
+fn test_missing_main() {
+    let exit_code = run(r#"
+        |foo :: () {}
+        |"#);
+
+    assert_eq!(exit_code, Err(RuntimeError::MissingMain));
+}
+
+#[test]
+fn test_main_taking_parameters() {
+    let exit_code = run(r#"
+        |main :: (x: i32) {}
+        |"#);
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::MainTakesParameters { span: span(9, 15) })
+    );
+}
+
+#[test]
+fn test_load_rejects_other_files() {
+    assert_eq!(Module::load(b"main :: () {}"), Err(LoadError::NotBytecode));
+}
+
+#[test]
+fn test_load_rejects_other_versions() {
+    let mut bytes = Module::default().save();
+    bytes[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+
+    assert_eq!(
+        Module::load(&bytes),
+        Err(LoadError::UnsupportedVersion {
+            version: FORMAT_VERSION + 1
+        })
+    );
+}
+
+#[test]
+fn test_load_rejects_truncated_files() {
+    let bytes = compile_bytecode(
+        r#"
+        |main :: () -> i32 {
+        |    42
+        |}
+        |"#,
+    )
+    .save();
+
+    assert_eq!(
+        Module::load(&bytes[..bytes.len() - 1]),
+        Err(LoadError::Malformed {
+            reason: "unexpected end of file"
+        })
+    );
+}
+
+#[test]
+fn test_load_rejects_operands_out_of_bounds() {
+    let mut module = compile_bytecode(
+        r#"
+        |main :: () -> i32 {
+        |    42
+        |}
+        |"#,
+    );
+    module.constants.clear();
+
+    assert_eq!(
+        Module::load(&module.save()),
+        Err(LoadError::Malformed {
+            reason: "instruction operand out of bounds"
+        })
+    );
+}
+
+#[test]
+fn test_other_int_profiles_are_unsupported() {
+    let options = Options {
+        int_profile: IntProfile {
+            overflow: Overflow::Trap,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let errors = match driver::compile_bytecode("main :: () {}", &options) {
+        Ok(_) => panic!("program compiled unexpectedly"),
+        Err(diagnostic) => diagnostic.errors,
+    };
+
+    assert_eq!(
+        errors,
+        vec![CompileError::IntProfileUnsupported {
+            backend: "bytecode"
+        }]
+    );
+}
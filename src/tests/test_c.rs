@@ -0,0 +1,234 @@
+use pretty_assertions::assert_eq;
+
+use crate::c::prelude;
+use crate::driver::{self, Emit, Options};
+use crate::profile::IntProfile;
+use crate::tests::strip_margin;
+
+/// C source file generated for the program, without the prelude.
+fn emit_c(source_code: &str) -> String {
+    let options = Options {
+        emit: Emit::C,
+        ..Default::default()
+    };
+
+    match driver::compile(strip_margin(source_code), &options) {
+        Ok(compiled_program) => {
+            let source_file = compiled_program
+                .code
+                .strip_prefix(&prelude(IntProfile::default()))
+                .expect("source file should start with the prelude");
+
+            // Blank lines are dropped, just like `strip_margin` drops them.
+            source_file
+                .lines()
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+#[test]
+fn test_unit_main_exits_with_zero() {
+    let c = emit_c(
+        r#"
+        |nothing :: (unit: ()) {}
+        |
+        |main :: () {
+        |    x := 3;
+        |    nothing({});
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        c,
+        strip_margin(
+            r#"
+            |void fn_nothing(void);
+            |void fn_main(void);
+            |void fn_nothing(void) {
+            |}
+            |void fn_main(void) {
+            |    fn_nothing();
+            |}
+            |int main(void) {
+            |    fn_main();
+            |    return 0;
+            |}
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_calls_and_loops() {
+    let c = emit_c(
+        r#"
+        |main :: () -> i32 {
+        |    x := add(1, 2);
+        |    for i : 0..=x {
+        |        if i {
+        |            continue
+        |        } else if i - 1 {
+        |            break
+        |        }
+        |    } else {
+        |        _ = if x { 1 } else { 2 };
+        |    }
+        |    x
+        |}
+        |
+        |add :: (a: i32, b: i32) -> i32 {
+        |    a + b
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        c,
+        strip_margin(
+            r#"
+            |sophia_int fn_main(void);
+            |sophia_int fn_add(sophia_int v0, sophia_int v1);
+            |sophia_int fn_main(void) {
+            |    sophia_int v2;
+            |    sophia_int v4;
+            |    sophia_int v7;
+            |    sophia_int v10;
+            |    v2 = fn_add(1, 2);
+            |    v4 = 0;
+            |block1:;
+            |    if (v4 > v2) goto block6;
+            |    if (v4 != 0) goto block4;
+            |    v7 = sophia_sub(v4, 1);
+            |    if (v7 != 0) goto block10;
+            |block4:;
+            |    if (sophia_add_overflows(v4, 1)) goto block6;
+            |    v10 = sophia_add(v4, 1);
+            |    v4 = v10;
+            |    goto block1;
+            |block6:;
+            |    if (v2 == 0) goto block8;
+            |    goto block9;
+            |block8:;
+            |block9:;
+            |block10:;
+            |    return v2;
+            |}
+            |sophia_int fn_add(sophia_int v0, sophia_int v1) {
+            |    sophia_int v2;
+            |    v2 = sophia_add(v0, v1);
+            |    return v2;
+            |}
+            |int main(void) {
+            |    return fn_main();
+            |}
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_builtins_call_the_runtime_library() {
+    let c = emit_c(
+        r#"
+        |main :: () {
+        |    print(1);
+        |    println(2);
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        c,
+        strip_margin(
+            r#"
+            |#include <stdio.h>
+            |static void sophia_print_i32(sophia_int value) {
+            |    printf("%lld", (long long) value);
+            |}
+            |static void sophia_println_i32(sophia_int value) {
+            |    printf("%lld\n", (long long) value);
+            |}
+            |void fn_main(void);
+            |void fn_main(void) {
+            |    sophia_print_i32(1);
+            |    sophia_println_i32(2);
+            |}
+            |int main(void) {
+            |    fn_main();
+            |    return 0;
+            |}
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_extern_functions_keep_their_names() {
+    let c = emit_c(
+        r#"
+        |extern putchar :: (c: i32) -> i32;
+        |extern exit :: (code: i32);
+        |main :: () {
+        |    _ = putchar(65);
+        |    exit(0);
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        c,
+        strip_margin(
+            r#"
+            |int putchar(int);
+            |void exit(int);
+            |void fn_main(void);
+            |void fn_main(void) {
+            |    putchar(65);
+            |    exit(0);
+            |}
+            |int main(void) {
+            |    fn_main();
+            |    return 0;
+            |}
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_steps_that_arent_positive_stop_the_program() {
+    let c = emit_c(
+        r#"
+        |count :: (n: i32) {
+        |    for i : 0..10 step n {}
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        c,
+        strip_margin(
+            r#"
+            |void fn_count(sophia_int v0);
+            |void fn_count(sophia_int v0) {
+            |    sophia_int v3;
+            |    sophia_int v6;
+            |    if (v0 <= 0) abort();
+            |    v3 = 0;
+            |block1:;
+            |    if (v3 >= 10) goto block4;
+            |    if (sophia_add_overflows(v3, v0)) goto block4;
+            |    v6 = sophia_add(v3, v0);
+            |    v3 = v6;
+            |    goto block1;
+            |block4:;
+            |}
+            |"#
+        )
+    );
+}
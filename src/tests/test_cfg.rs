@@ -0,0 +1,189 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast::BinaryOp;
+use crate::cfg::Cfg;
+use crate::driver::{self, Emit, Options};
+use crate::ir::{Block, BlockCall, BlockId, Function, Inst, InstKind, Terminator, Value};
+use crate::tests::{span, strip_margin};
+
+fn emit_cfg(source_code: &str) -> String {
+    let options = Options {
+        emit: Emit::Cfg,
+        ..Default::default()
+    };
+
+    match driver::compile(strip_margin(source_code), &options) {
+        Ok(compiled_program) => compiled_program.code,
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+fn jump(block: usize, args: Vec<Value>) -> Terminator {
+    Terminator::Jump(BlockCall {
+        block: BlockId(block),
+        args,
+    })
+}
+
+#[test]
+fn test_loop_header_has_entry_and_back_edge_as_predecessors() {
+    // The IR of `for i : 0..n { _ = i; }`.
+    let function = Function {
+        name: "count",
+        returns_value: false,
+        blocks: vec![
+            Block {
+                params: vec![Value(0)],
+                insts: vec![Inst {
+                    result: Some(Value(1)),
+                    kind: InstKind::Const(0),
+                }],
+                terminator: jump(1, vec![Value(1)]),
+            },
+            Block {
+                params: vec![Value(2)],
+                insts: vec![Inst {
+                    result: Some(Value(3)),
+                    kind: InstKind::Less {
+                        lhs: Value(2),
+                        rhs: Value(0),
+                    },
+                }],
+                terminator: Terminator::Branch {
+                    cond: Value(3),
+                    then_call: BlockCall {
+                        block: BlockId(2),
+                        args: vec![],
+                    },
+                    else_call: BlockCall {
+                        block: BlockId(3),
+                        args: vec![],
+                    },
+                },
+            },
+            Block {
+                params: vec![],
+                insts: vec![
+                    Inst {
+                        result: Some(Value(4)),
+                        kind: InstKind::Const(1),
+                    },
+                    Inst {
+                        result: Some(Value(5)),
+                        kind: InstKind::Binary {
+                            op: BinaryOp::Add,
+                            lhs: Value(2),
+                            rhs: Value(4),
+                            span: span(4, 5),
+                        },
+                    },
+                ],
+                terminator: jump(1, vec![Value(5)]),
+            },
+            Block {
+                params: vec![],
+                insts: vec![],
+                terminator: Terminator::Return(None),
+            },
+        ],
+    };
+
+    let cfg = Cfg::of_function(&function);
+
+    assert_eq!(cfg.block_count(), 4);
+    assert_eq!(cfg.successors(BlockId(1)), &[BlockId(2), BlockId(3)]);
+    assert_eq!(cfg.successors(BlockId(3)), &[]);
+    assert_eq!(cfg.predecessors(BlockId(0)), &[]);
+    assert_eq!(cfg.predecessors(BlockId(1)), &[BlockId(0), BlockId(2)]);
+    assert_eq!(cfg.predecessors(BlockId(3)), &[BlockId(1)]);
+}
+
+#[test]
+fn test_merge_block_is_immediately_dominated_by_the_branch_before_it() {
+    let branch = |cond: usize, then_block: usize, else_block: usize| Terminator::Branch {
+        cond: Value(cond),
+        then_call: BlockCall {
+            block: BlockId(then_block),
+            args: vec![],
+        },
+        else_call: BlockCall {
+            block: BlockId(else_block),
+            args: vec![],
+        },
+    };
+
+    let block = |terminator: Terminator| Block {
+        params: vec![],
+        insts: vec![],
+        terminator,
+    };
+
+    // Block 1 is jumped back to from block 3, which the branch of block 0
+    // reaches either way.
+    let function = Function {
+        name: "diamond",
+        returns_value: false,
+        blocks: vec![
+            Block {
+                params: vec![Value(0)],
+                insts: vec![],
+                terminator: branch(0, 1, 2),
+            },
+            block(jump(3, vec![])),
+            block(jump(3, vec![])),
+            block(branch(0, 1, 4)),
+            block(Terminator::Return(None)),
+        ],
+    };
+
+    let cfg = Cfg::of_function(&function);
+
+    assert_eq!(
+        cfg.immediate_dominators(),
+        vec![BlockId(0), BlockId(0), BlockId(0), BlockId(0), BlockId(3)]
+    );
+}
+
+#[test]
+fn test_dot_labels_blocks_with_their_code_and_branches_with_their_outcome() {
+    let dot = emit_cfg(
+        r#"
+        |main :: () -> i32 {
+        |    for i : 0..3 {
+        |        if i {
+        |            break
+        |        }
+        |    }
+        |    7
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        dot.trim_end(),
+        strip_margin(
+            r#"
+        |digraph cfg {
+        |    node [shape=box, fontname="monospace"];
+        |    subgraph "cluster_main" {
+        |        label="fn main() -> i32";
+        |        "main.block0" [label="block0:\l    v0 = const 0\l    v1 = const 3\l    jump block1(v0)\l"];
+        |        "main.block1" [label="block1(v2):\l    v3 = lt v2, v1\l    branch v3, block2, block5\l"];
+        |        "main.block2" [label="block2:\l    branch v2, block5, block3\l"];
+        |        "main.block3" [label="block3:\l    v4 = const 1\l    v5 = add_overflows v2, v4\l    branch v5, block5, block4\l"];
+        |        "main.block4" [label="block4:\l    v6 = add v2, v4\l    jump block1(v6)\l"];
+        |        "main.block5" [label="block5:\l    v7 = const 7\l    return v7\l"];
+        |        "main.block0" -> "main.block1";
+        |        "main.block1" -> "main.block2" [label="true"];
+        |        "main.block1" -> "main.block5" [label="false"];
+        |        "main.block2" -> "main.block5" [label="true"];
+        |        "main.block2" -> "main.block3" [label="false"];
+        |        "main.block3" -> "main.block5" [label="true"];
+        |        "main.block3" -> "main.block4" [label="false"];
+        |        "main.block4" -> "main.block1";
+        |    }
+        |}
+        |"#,
+        )
+    );
+}
@@ -0,0 +1,123 @@
+use std::thread;
+
+use pretty_assertions::assert_eq;
+
+use crate::compiler_context::{ArenaStats, CompilerContext};
+use crate::driver::{self, Options};
+use crate::tests::strip_margin;
+
+#[test]
+fn test_threads_intern_into_the_same_context() {
+    let ctx = CompilerContext::new(String::new());
+    let names: Vec<_> = (0..64).map(|idx| format!("name_{}", idx % 16)).collect();
+
+    let symbols: Vec<_> = thread::scope(|scope| {
+        let threads: Vec<_> = names
+            .chunks(8)
+            .map(|names| {
+                let ctx = &ctx;
+                scope.spawn(move || {
+                    names
+                        .iter()
+                        .map(|name| ctx.get_or_intern_str(name))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        threads
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap())
+            .collect()
+    });
+
+    for (name, symbol) in names.iter().zip(symbols) {
+        assert_eq!(ctx.get_or_intern_str(name), symbol);
+        assert_eq!(ctx.resolve_symbol(symbol), name);
+    }
+}
+
+#[test]
+fn test_threads_check_a_program_parsed_by_another() {
+    let source_code = strip_margin(
+        r#"
+        |square :: (n: i32) -> i32 { n * n }
+        |main :: () -> i32 {
+        |    unused := square(2);
+        |    square(3) + x
+        |}
+        |"#,
+    );
+    let options = Options::default();
+    let ctx = driver::new_context(source_code.as_str(), &options);
+    let program = driver::parse(&ctx, &options).unwrap();
+    let expected = driver::check_program(&ctx, program, &options);
+
+    let diagnostics: Vec<_> = thread::scope(|scope| {
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let ctx = &ctx;
+                scope.spawn(move || driver::check_program(ctx, program, &Options::default()))
+            })
+            .collect();
+
+        threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect()
+    });
+
+    assert!(expected.has_errors());
+
+    for diagnostic in diagnostics {
+        assert_eq!(diagnostic, expected);
+    }
+}
+
+#[test]
+fn test_arena_stats_count_bytes_by_node_kind() {
+    let ctx = CompilerContext::new("main :: () -> i32 { 1 + 2 }".to_owned());
+
+    assert_eq!(ctx.arena_stats(), ArenaStats::default());
+
+    driver::parse(&ctx, &Options::default()).unwrap();
+    let stats = ctx.arena_stats();
+
+    assert!(stats.exprs > 0);
+    assert!(stats.decls > 0);
+    assert_eq!(stats.match_arms, 0);
+    assert_eq!(stats.field_defs, 0);
+    assert_eq!(stats.total(), ctx.allocated_bytes());
+}
+
+#[test]
+fn test_reset_reuses_the_memory_of_the_syntax_tree() {
+    let source_code = "main :: () -> i32 { answer() }\nanswer :: () -> i32 { 42 }";
+    let mut ctx = CompilerContext::new(source_code.to_owned());
+    let answer = ctx.get_or_intern_str("answer");
+
+    driver::parse(&ctx, &Options::default()).unwrap();
+    let allocated_bytes = ctx.allocated_bytes();
+
+    assert_eq!(ctx.generation(), 0);
+
+    // The arenas keep the largest block of memory they allocated, which
+    // parsing the same code again fits in.
+    ctx.reset(source_code);
+    driver::parse(&ctx, &Options::default()).unwrap();
+
+    assert!(ctx.allocated_bytes() <= allocated_bytes);
+    assert_eq!(ctx.generation(), 1);
+
+    ctx.reset("main :: () -> i32 { 42 }");
+    let program = driver::parse(&ctx, &Options::default()).unwrap();
+
+    assert_eq!(ctx.generation(), 2);
+    assert_eq!(ctx.get_source_code(), "main :: () -> i32 { 42 }");
+    assert_eq!(ctx.resolve_symbol(answer), "answer");
+    assert_eq!(program.decls.len(), 1);
+    assert_eq!(
+        driver::check_program(&ctx, program, &Options::default()),
+        Default::default()
+    );
+}
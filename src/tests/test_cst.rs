@@ -0,0 +1,187 @@
+use pretty_assertions::assert_eq;
+
+use crate::cst::{parse_file, NodeKind, SyntaxElement, SyntaxNode, SyntaxTokenKind};
+use crate::driver::{self, Emit, Options};
+use crate::structure::render_cst;
+use crate::tests::strip_margin;
+
+#[test]
+fn test_cst_round_trips_source_code() {
+    let sources = [
+        "",
+        "  \n// only a comment",
+        "// header\n\n#[main_loop]\nmain :: () -> i32 {\n\tx := (1 + 2) // three\n   x * 2 }\n\n\n",
+        "extern putchar::(c:i32)->i32;main::(){_=putchar(65)}",
+        "x := 1;\ny := x + 1;\nprintln(y)",
+        "main :: () { ] ) (1, [2 }",
+    ];
+
+    for source in sources {
+        let tree = parse_file(source);
+
+        assert_eq!(tree.to_string(), source);
+        assert_eq!(
+            tree.tokens()
+                .iter()
+                .map(|token| token.text)
+                .collect::<String>(),
+            source
+        );
+    }
+}
+
+#[test]
+fn test_cst_groups_tokens_into_items_and_delimiters() {
+    let source_code = strip_margin(
+        r#"
+        |// Runs forever.
+        |#[main_loop]
+        |main :: () { f(1) }
+        |extern abs :: (n: i32) -> i32;
+        |"#,
+    );
+    let tree = parse_file(&source_code);
+
+    assert_eq!(
+        render_cst(&tree),
+        strip_margin(
+            r##"
+            |file 0..80
+            |  comment 0..16 "// Runs forever."
+            |  whitespace 16..17 "\n"
+            |  item 17..49
+            |    hash 17..18 "#"
+            |    brackets 18..29
+            |      open_bracket 18..19 "["
+            |      identifier 19..28 "main_loop"
+            |      closed_bracket 28..29 "]"
+            |    whitespace 29..30 "\n"
+            |    identifier 30..34 "main"
+            |    whitespace 34..35 " "
+            |    colon_colon 35..37 "::"
+            |    whitespace 37..38 " "
+            |    parens 38..40
+            |      open_paren 38..39 "("
+            |      closed_paren 39..40 ")"
+            |    whitespace 40..41 " "
+            |    curlies 41..49
+            |      open_curly 41..42 "{"
+            |      whitespace 42..43 " "
+            |      identifier 43..44 "f"
+            |      parens 44..47
+            |        open_paren 44..45 "("
+            |        integer_constant 45..46 "1"
+            |        closed_paren 46..47 ")"
+            |      whitespace 47..48 " "
+            |      closed_curly 48..49 "}"
+            |  whitespace 49..50 "\n"
+            |  item 50..80
+            |    keyword_extern 50..56 "extern"
+            |    whitespace 56..57 " "
+            |    identifier 57..60 "abs"
+            |    whitespace 60..61 " "
+            |    colon_colon 61..63 "::"
+            |    whitespace 63..64 " "
+            |    parens 64..72
+            |      open_paren 64..65 "("
+            |      identifier 65..66 "n"
+            |      colon 66..67 ":"
+            |      whitespace 67..68 " "
+            |      keyword_i32 68..71 "i32"
+            |      closed_paren 71..72 ")"
+            |    whitespace 72..73 " "
+            |    dash_greater 73..75 "->"
+            |    whitespace 75..76 " "
+            |    keyword_i32 76..79 "i32"
+            |    semi 79..80 ";"
+            |"##,
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_cst_keeps_code_that_doesnt_nest_in_error_nodes() {
+    let tree = parse_file("main :: () { ) }\nf :: (1, 2");
+    let errors: Vec<_> = error_nodes(&tree)
+        .iter()
+        .map(|node| node.to_string())
+        .collect();
+
+    assert_eq!(errors, [")", "(1, 2"]);
+}
+
+/// Nodes of kind `NodeKind::Error` in the tree, outermost first.
+fn error_nodes<'a, 'src>(node: &'a SyntaxNode<'src>) -> Vec<&'a SyntaxNode<'src>> {
+    let mut errors = vec![];
+
+    if node.kind == NodeKind::Error {
+        errors.push(node);
+    }
+
+    for child in &node.children {
+        if let SyntaxElement::Node(child) = child {
+            errors.extend(error_nodes(child));
+        }
+    }
+
+    errors
+}
+
+#[test]
+fn test_cst_splits_comments_from_whitespace() {
+    let tree = parse_file("x := 1; // one\n// two\ny := 2;");
+    let trivia: Vec<_> = tree
+        .children
+        .iter()
+        .filter_map(|child| match child {
+            SyntaxElement::Token(token) if token.is_trivia() => Some((token.kind, token.text)),
+            _ => None,
+        })
+        .collect();
+    let items = tree
+        .children
+        .iter()
+        .filter(|child| matches!(child, SyntaxElement::Node(node) if node.kind == NodeKind::Item))
+        .count();
+
+    assert_eq!(
+        trivia,
+        [
+            (SyntaxTokenKind::Whitespace, " "),
+            (SyntaxTokenKind::Comment, "// one"),
+            (SyntaxTokenKind::Whitespace, "\n"),
+            (SyntaxTokenKind::Comment, "// two"),
+            (SyntaxTokenKind::Whitespace, "\n"),
+        ]
+    );
+    assert_eq!(items, 2);
+}
+
+#[test]
+fn test_emit_cst_of_program_that_doesnt_parse() {
+    let options = Options {
+        emit: Emit::Cst,
+        ..Default::default()
+    };
+
+    let compiled_program = driver::compile("main :: ( {", &options).unwrap();
+
+    assert_eq!(
+        compiled_program.code,
+        strip_margin(
+            r#"
+            |file 0..11
+            |  item 0..11
+            |    identifier 0..4 "main"
+            |    whitespace 4..5 " "
+            |    colon_colon 5..7 "::"
+            |    whitespace 7..8 " "
+            |    error 8..11
+            |      open_paren 8..9 "("
+            |      whitespace 9..10 " "
+            |      error 10..11
+            |        open_curly 10..11 "{"
+            |"#,
+        ) + "\n"
+    );
+}
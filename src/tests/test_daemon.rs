@@ -0,0 +1,186 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::time::Duration;
+use std::{env, fs, process, thread};
+
+use pretty_assertions::assert_eq;
+
+use crate::daemon::Daemon;
+use crate::json::Json;
+
+fn request(daemon: &Daemon, message: &str) -> String {
+    daemon
+        .handle_message(message)
+        .expect("request must be responded")
+        .to_string()
+}
+
+#[test]
+fn test_check_request() {
+    let daemon = Daemon::new();
+
+    let response = request(
+        &daemon,
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "check", "params": {"source": "main :: () { x; }"}}"#,
+    );
+
+    assert_eq!(
+        response,
+        r#"{"jsonrpc":"2.0","id":1,"result":{"errors":[{"message":"cannot find `x` in this scope","span":{"start":13,"end":14,"line":1,"col":14}}],"warnings":[]}}"#
+    );
+}
+
+#[test]
+fn test_compile_request() {
+    let daemon = Daemon::new();
+
+    let response = request(
+        &daemon,
+        r#"{"jsonrpc": "2.0", "id": "a", "method": "compile", "params": {"source": "1;", "script": true}}"#,
+    );
+
+    assert_eq!(
+        response,
+        r#"{"jsonrpc":"2.0","id":"a","result":{"assembly":"    .intel_syntax noprefix\n    .text\n    .globl main\nmain:\n    push rbp\n    mov rbp, rsp\n    pop rbp\n    ret\n    .section .note.GNU-stack,\"\",@progbits\n","errors":[],"warnings":[{"message":"unused value of type `i32`; bind it with `x := ...` or discard it explicitly with `_ = ...`","span":{"start":0,"end":1,"line":1,"col":1}}]}}"#
+    );
+}
+
+#[test]
+fn test_compile_request_with_errors_has_no_assembly() {
+    let daemon = Daemon::new();
+
+    let response = request(
+        &daemon,
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "compile", "params": {"source": "main :: ("}}"#,
+    );
+
+    assert_eq!(
+        response,
+        r#"{"jsonrpc":"2.0","id":1,"result":{"assembly":null,"errors":[{"message":"unclosed `(` opened here","span":{"start":8,"end":9,"line":1,"col":9}}],"warnings":[]}}"#
+    );
+}
+
+#[test]
+fn test_invalid_requests() {
+    let daemon = Daemon::new();
+
+    assert_eq!(
+        request(
+            &daemon,
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "frobnicate"}"#
+        ),
+        r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"unknown method `frobnicate`"}}"#
+    );
+    assert_eq!(
+        request(&daemon, r#"{"jsonrpc": "2.0", "id": 2, "method": "check"}"#),
+        r#"{"jsonrpc":"2.0","id":2,"error":{"code":-32602,"message":"expected either `source` or `path`"}}"#
+    );
+    assert_eq!(
+        request(&daemon, r#"{"jsonrpc": "2.0", "id": 3"#),
+        r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"expected `,` or `}` at offset 26"}}"#
+    );
+}
+
+#[test]
+fn test_notifications_are_not_responded() {
+    let daemon = Daemon::new();
+
+    assert_eq!(
+        daemon.handle_message(r#"{"jsonrpc": "2.0", "method": "check", "params": {"source": ""}}"#),
+        None::<Json>
+    );
+}
+
+#[test]
+fn test_failed_connection_doesnt_stop_serving_the_others() {
+    let socket_path = env::temp_dir().join(format!("sophia-daemon-{}.sock", process::id()));
+    let _ = fs::remove_file(&socket_path);
+
+    let server = thread::spawn({
+        let socket_path = socket_path.clone();
+        move || Daemon::new().serve(&socket_path)
+    });
+
+    // Stays connected without sending anything, which mustn't hold up the
+    // other clients.
+    let _idle_client = connect(&socket_path);
+
+    let mut failing_client = connect(&socket_path);
+    failing_client.write_all(b"\xff\n").unwrap();
+    drop(failing_client);
+
+    let client = connect(&socket_path);
+    let mut responses = BufReader::new(client.try_clone().unwrap()).lines();
+    let mut send = |message: &str| {
+        writeln!(&client, "{}", message).unwrap();
+        responses.next().unwrap().unwrap()
+    };
+
+    assert_eq!(
+        send(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "check", "params": {"source": "main :: () {}"}}"#
+        ),
+        r#"{"jsonrpc":"2.0","id":1,"result":{"errors":[],"warnings":[]}}"#
+    );
+    assert_eq!(
+        send(r#"{"jsonrpc": "2.0", "id": 2, "method": "shutdown"}"#),
+        r#"{"jsonrpc":"2.0","id":2,"result":null}"#
+    );
+
+    server.join().unwrap().unwrap();
+    assert!(!socket_path.exists());
+}
+
+#[test]
+fn test_only_sockets_left_behind_are_replaced() {
+    let socket_path = env::temp_dir().join(format!("sophia-daemon-{}-taken.sock", process::id()));
+    fs::write(&socket_path, "not a socket").unwrap();
+
+    assert!(Daemon::new().serve(&socket_path).is_err());
+    assert_eq!(fs::read_to_string(&socket_path).unwrap(), "not a socket");
+    fs::remove_file(&socket_path).unwrap();
+
+    let server = thread::spawn({
+        let socket_path = socket_path.clone();
+        move || Daemon::new().serve(&socket_path)
+    });
+    let client = connect(&socket_path);
+
+    // The daemon that's serving is left alone.
+    let err = Daemon::new().serve(&socket_path).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+
+    writeln!(
+        &client,
+        r#"{{"jsonrpc": "2.0", "id": 1, "method": "shutdown"}}"#
+    )
+    .unwrap();
+    server.join().unwrap().unwrap();
+
+    // Whereas a socket whose daemon is gone is replaced.
+    UnixListener::bind(&socket_path).unwrap();
+
+    let server = thread::spawn({
+        let socket_path = socket_path.clone();
+        move || Daemon::new().serve(&socket_path)
+    });
+    let client = connect(&socket_path);
+
+    writeln!(
+        &client,
+        r#"{{"jsonrpc": "2.0", "id": 1, "method": "shutdown"}}"#
+    )
+    .unwrap();
+    server.join().unwrap().unwrap();
+}
+
+/// Connects to the daemon, waiting for it to start listening if it hasn't yet.
+fn connect(socket_path: &Path) -> UnixStream {
+    loop {
+        match UnixStream::connect(socket_path) {
+            Ok(stream) => return stream,
+            Err(_) => thread::sleep(Duration::from_millis(10)),
+        }
+    }
+}
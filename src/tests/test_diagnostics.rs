@@ -0,0 +1,1192 @@
+use crate::ast::RangeKind;
+use crate::ast_owned::Type;
+use crate::diagnostics::{CompileError, CompileWarning, Lint, LintLevels};
+use crate::driver::{self, Options};
+use crate::scanner::{Delim, Keyword, TokenKind};
+use crate::source_map::SourceMap;
+use crate::tests::{compile_errors, compile_warnings, span, strip_margin};
+
+#[test]
+fn test_unclosed_function_parameters_paren() {
+    let errors = compile_errors(
+        r#"
+        |main :: (
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::UnclosedDelimiter {
+            delim: Delim::Paren,
+            open_span: span(8, 9),
+            eof_span: span(9, 9),
+        }]
+    );
+}
+
+#[test]
+fn test_unclosed_function_body_curly() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    for {
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::UnclosedDelimiter {
+                delim: Delim::Curly,
+                open_span: span(11, 12),
+                eof_span: span(22, 22),
+            },
+            CompileError::UnclosedDelimiter {
+                delim: Delim::Curly,
+                open_span: span(21, 22),
+                eof_span: span(22, 22),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_unclosed_function_call_paren() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    foo(
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::UnclosedDelimiter {
+                delim: Delim::Curly,
+                open_span: span(11, 12),
+                eof_span: span(21, 21),
+            },
+            CompileError::UnclosedDelimiter {
+                delim: Delim::Paren,
+                open_span: span(20, 21),
+                eof_span: span(21, 21),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_unclosed_delimiter_message() {
+    let error = CompileError::UnclosedDelimiter {
+        delim: Delim::Curly,
+        open_span: span(0, 1),
+        eof_span: span(1, 1),
+    };
+
+    assert_eq!(error.to_string(), "unclosed `{` opened here");
+}
+
+#[test]
+fn test_expected_any_expression_start() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    1 +
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::ExpectedButFound {
+            expected: vec![
+                TokenKind::IntegerConstant,
+                TokenKind::Identifier,
+                TokenKind::Open(Delim::Paren),
+                TokenKind::Open(Delim::Bracket),
+                TokenKind::Open(Delim::Curly),
+                TokenKind::Keyword(Keyword::If),
+                TokenKind::Keyword(Keyword::Match),
+                TokenKind::Keyword(Keyword::For),
+                TokenKind::Keyword(Keyword::Loop),
+                TokenKind::Keyword(Keyword::Break),
+                TokenKind::Keyword(Keyword::Continue),
+                TokenKind::Hash,
+            ],
+            found: Some(TokenKind::Closed(Delim::Curly)),
+            span: span(28, 29),
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "expected one of integer, identifier, `(`, `[`, `{`, `if`, `match`, `for`, `loop`, \
+         `break`, `continue`, `#`, found `}`"
+    );
+}
+
+#[test]
+fn test_expected_tokens_include_every_one_checked_for() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    (1 }
+        |"#,
+    );
+
+    assert_eq!(
+        errors[0].to_string(),
+        "expected one of `.`, `[`, `as`, `+`, `-`, `*`, `/`, `%`, `,`, `)`, found `}`"
+    );
+}
+
+#[test]
+fn test_expected_single_token() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    for i : 0..3 (
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::ExpectedButFound {
+            expected: vec![
+                TokenKind::Period,
+                TokenKind::Open(Delim::Bracket),
+                TokenKind::Keyword(Keyword::As),
+                TokenKind::Plus,
+                TokenKind::Dash,
+                TokenKind::Star,
+                TokenKind::Slash,
+                TokenKind::Percent,
+                TokenKind::Semi,
+                TokenKind::Keyword(Keyword::Step),
+                TokenKind::Open(Delim::Curly),
+            ],
+            found: Some(TokenKind::Open(Delim::Paren)),
+            span: span(30, 31),
+        }]
+    );
+
+    let errors = driver::check("main :: () -> {}", &Options::default()).errors;
+
+    assert_eq!(
+        errors[0].to_string(),
+        "expected one of `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64`, `usize`, `(`, `[`, \
+         identifier, found `{`"
+    );
+
+    let errors = driver::check("main :: () { if 1 {} else 2 }", &Options::default()).errors;
+
+    assert_eq!(
+        errors[0].to_string(),
+        "expected one of `if`, `{`, found integer"
+    );
+
+    let errors = driver::check("main :: (a: i32 b: i32) {}", &Options::default()).errors;
+
+    assert_eq!(
+        errors[0].to_string(),
+        "expected one of `,`, `)`, found identifier"
+    );
+
+    // Only ranges can be iterated in reverse.
+    let errors = driver::check("main :: () { for i : rev xs {} }", &Options::default()).errors;
+
+    assert_eq!(
+        errors[0].to_string(),
+        "expected one of `:=`, `:`, `(`, `::`, `.`, `[`, `as`, `+`, `-`, `*`, `/`, `%`, `;`, `..`, \
+         `..=`, found `{`"
+    );
+}
+
+#[test]
+fn test_misspelled_keywords_are_parsed_as_keywords() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    fro i : 0..3 {}
+        |    iff 1 { 2 } esle { 3 }
+        |}
+        |Point :: strcut { x: i32 }
+        |Shape :: enmu { Circle(i32), Empty }
+        |f :: () { 1 + }
+        |"#,
+    );
+
+    let misspellings = [
+        ("fro", Keyword::For, span(24, 27)),
+        ("iff", Keyword::If, span(44, 47)),
+        ("esle", Keyword::Else, span(56, 60)),
+        ("strcut", Keyword::Struct, span(78, 84)),
+        ("enmu", Keyword::Enum, span(105, 109)),
+    ];
+
+    assert_eq!(
+        errors[..misspellings.len()],
+        misspellings.map(|(found, keyword, span)| CompileError::MisspelledKeyword {
+            found: found.to_owned(),
+            keyword,
+            span,
+        })
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "expected keyword, found `fro`; did you mean `for`?"
+    );
+    // The parser goes on after the misspellings, up to the code that doesn't
+    // parse.
+    assert!(
+        matches!(
+            errors[misspellings.len()..],
+            [CompileError::ExpectedButFound {
+                found: Some(TokenKind::Closed(Delim::Curly)),
+                ..
+            }]
+        ),
+        "{:?}",
+        errors
+    );
+}
+
+#[test]
+fn test_unknown_chars_are_reported_and_skipped() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    x := "1";
+        |    @x + café
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::UnknownChar {
+                ch: '"',
+                span: span(29, 30),
+            },
+            CompileError::UnknownChar {
+                ch: '"',
+                span: span(31, 32),
+            },
+            CompileError::UnknownChar {
+                ch: '@',
+                span: span(38, 39),
+            },
+            CompileError::UnknownChar {
+                ch: 'é',
+                span: span(46, 48),
+            },
+        ]
+    );
+    assert_eq!(errors[0].to_string(), "unknown character '\"'");
+    assert_eq!(errors[3].to_string(), "unknown character 'é'");
+}
+
+#[test]
+fn test_only_functions_structs_and_enums_are_declared() {
+    let errors = compile_errors(
+        r#"
+        |five :: 5
+        |pair :: (1, 2)
+        |main :: () -> i32 { five() }
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::InvalidDeclValue { span: span(8, 9) },
+            CompileError::InvalidDeclValue { span: span(18, 24) },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "expected function, struct or enum; only those can be declared at the top level"
+    );
+}
+
+#[test]
+fn test_names_like_keywords_are_names_where_they_parse() {
+    let source_code = strip_margin(
+        r#"
+        |Iff :: struct {}
+        |
+        |main :: () -> i32 {
+        |    fro := 1;
+        |    iff := fro + 1;
+        |    Iff {};
+        |    if iff { 0 } else { 1 }
+        |    esle := 2;
+        |    iff + esle
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        driver::check(source_code, &Options::default()).errors,
+        vec![]
+    );
+}
+
+#[test]
+fn test_expected_expression_at_end_of_file() {
+    let errors = driver::check("main ::", &Options::default()).errors;
+
+    assert_eq!(
+        errors,
+        vec![CompileError::ExpectedButFound {
+            expected: vec![
+                TokenKind::Keyword(Keyword::Struct),
+                TokenKind::Keyword(Keyword::Enum),
+                TokenKind::IntegerConstant,
+                TokenKind::Identifier,
+                TokenKind::Open(Delim::Paren),
+                TokenKind::Open(Delim::Bracket),
+                TokenKind::Open(Delim::Curly),
+                TokenKind::Keyword(Keyword::If),
+                TokenKind::Keyword(Keyword::Match),
+                TokenKind::Keyword(Keyword::For),
+                TokenKind::Keyword(Keyword::Loop),
+                TokenKind::Keyword(Keyword::Break),
+                TokenKind::Keyword(Keyword::Continue),
+                TokenKind::Hash,
+            ],
+            found: None,
+            span: span(7, 7),
+        }]
+    );
+    assert!(errors[0].to_string().ends_with(", found end of file"));
+}
+
+#[test]
+fn test_warn_infinite_loop_without_break() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    loop {
+        |        foo();
+        |    }
+        |}
+        |
+        |foo :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![CompileWarning::InfiniteLoop {
+            for_span: span(17, 21),
+        }]
+    );
+}
+
+#[test]
+fn test_infinite_loop_with_break_is_fine() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    loop {
+        |        if done() {
+        |            break;
+        |        }
+        |    }
+        |}
+        |
+        |done :: () -> i32 { 1 }
+        |"#,
+    );
+
+    assert_eq!(warnings, vec![]);
+}
+
+#[test]
+fn test_warn_infinite_loop_with_unreachable_break() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    loop {
+        |        continue;
+        |        break;
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![
+            CompileWarning::InfiniteLoop {
+                for_span: span(17, 21),
+            },
+            CompileWarning::UnreachableCode { span: span(50, 55) },
+        ]
+    );
+}
+
+#[test]
+fn test_warn_unreachable_code() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () -> i32 {
+        |    loop {
+        |        _x := {
+        |            break;
+        |            _ = 1;
+        |            _x := 2;
+        |            5
+        |        };
+        |    }
+        |    0
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![CompileWarning::UnreachableCode {
+            span: span(78, 119),
+        }]
+    );
+}
+
+#[test]
+fn test_warn_infinite_loop_whose_only_break_exits_inner_loop() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    loop {
+        |        loop {
+        |            break;
+        |        }
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![CompileWarning::InfiniteLoop {
+            for_span: span(17, 21),
+        }]
+    );
+}
+
+#[test]
+fn test_break_in_inner_loop_else_branch_exits_outer_loop() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    loop {
+        |        for more() {
+        |        } else {
+        |            break;
+        |        }
+        |    }
+        |}
+        |
+        |more :: () -> i32 { 0 }
+        |"#,
+    );
+
+    assert_eq!(warnings, vec![]);
+}
+
+#[test]
+fn test_warn_constant_if_conditions() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () -> i32 {
+        |    x := if (2 - 2) { 1 } else { 2 };
+        |    if 3 * 4 { x } else if x { 3 } else { 4 }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![
+            CompileWarning::ConstantCondition {
+                value: false,
+                is_loop: false,
+                span: span(32, 39),
+                dead_span: Some(span(40, 45)),
+            },
+            CompileWarning::ConstantCondition {
+                value: true,
+                is_loop: false,
+                span: span(65, 70),
+                dead_span: Some(span(85, 103)),
+            },
+        ]
+    );
+    assert_eq!(warnings[0].to_string(), "`if` condition is always false");
+    assert_eq!(
+        warnings[1].note(),
+        Some((span(85, 103), "this branch is never taken".to_owned()))
+    );
+}
+
+#[test]
+fn test_warn_constant_loop_conditions() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    for 0 {
+        |        foo();
+        |    }
+        |    for 1u8 {
+        |        break;
+        |    } else {
+        |        foo();
+        |    }
+        |}
+        |
+        |foo :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![
+            CompileWarning::ConstantCondition {
+                value: false,
+                is_loop: true,
+                span: span(21, 22),
+                dead_span: Some(span(23, 45)),
+            },
+            CompileWarning::ConstantCondition {
+                value: true,
+                is_loop: true,
+                span: span(54, 57),
+                dead_span: Some(span(86, 108)),
+            },
+        ]
+    );
+    assert_eq!(warnings[1].to_string(), "loop condition is always true");
+}
+
+#[test]
+fn test_conditions_that_may_overflow_are_not_constant() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () -> i32 {
+        |    if 256 * 256 { 1 } else { 2 }
+        |    + if 1 / 0 { 1 } else { 2 }
+        |    + if 255u8 as i8 { 1 } else { 2 }
+        |}
+        |"#,
+    );
+
+    assert_eq!(warnings, vec![]);
+}
+
+#[test]
+fn test_warn_reversed_range() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    for i : 3..1 {}
+        |    for j : (2 + 2)..=3i32 {}
+        |    for k : 1..1 {}
+        |    for l : 3..=3 {}
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![
+            CompileWarning::ReversedRange {
+                start: 3,
+                end: 1,
+                range_kind: RangeKind::Exclusive,
+                span: span(25, 29),
+            },
+            CompileWarning::ReversedRange {
+                start: 4,
+                end: 3,
+                range_kind: RangeKind::Inclusive,
+                span: span(45, 59),
+            },
+        ]
+    );
+    assert_eq!(
+        warnings[1].to_string(),
+        "range `4..=3` starts past its end, so the loop never runs"
+    );
+}
+
+#[test]
+fn test_main_loop_attribute_allows_infinite_loop() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    #[main_loop]
+        |    loop {
+        |        foo();
+        |    }
+        |}
+        |
+        |foo :: () {}
+        |"#,
+    );
+
+    assert_eq!(warnings, vec![]);
+}
+
+#[test]
+fn test_warn_for_loop_without_iteration() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    for {
+        |        break;
+        |    }
+        |    loop {
+        |        break;
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![CompileWarning::BareFor {
+            for_span: span(17, 20),
+        }]
+    );
+    assert_eq!(
+        warnings[0].to_string(),
+        "`for` without an iteration loops until a `break`; write `loop` if that's meant"
+    );
+}
+
+#[test]
+fn test_warn_for_loop_without_iteration_or_break_only_once() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    for {}
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![CompileWarning::InfiniteLoop {
+            for_span: span(17, 20),
+        }]
+    );
+}
+
+#[test]
+fn test_warn_unused_values_followed_by_semicolon() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    42;
+        |    foo();
+        |}
+        |
+        |foo :: () -> i32 {
+        |    1
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![
+            CompileWarning::UnusedValue {
+                span: span(17, 19),
+                ty: Type::I32,
+            },
+            CompileWarning::UnusedValue {
+                span: span(25, 30),
+                ty: Type::I32,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_warn_unused_value_in_non_final_position() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () -> i32 {
+        |    x := 1
+        |    x
+        |    0
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![CompileWarning::UnusedValue {
+            span: span(35, 36),
+            ty: Type::I32,
+        }]
+    );
+}
+
+#[test]
+fn test_final_value_of_compound_expr_is_used() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () -> i32 {
+        |    if flag() { 2 } else { 3 }
+        |}
+        |
+        |flag :: () -> i32 { 1 }
+        |"#,
+    );
+
+    assert_eq!(warnings, vec![]);
+}
+
+#[test]
+fn test_warn_unused_value_of_if_expr() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    if flag() { 2 } else { 3 };
+        |}
+        |
+        |flag :: () -> i32 { 1 }
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![CompileWarning::UnusedValue {
+            span: span(17, 43),
+            ty: Type::I32,
+        }]
+    );
+}
+
+#[test]
+fn test_warn_unused_value_at_end_of_for_loop_body() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    for i : 0..3 {
+        |        i
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![CompileWarning::UnusedValue {
+            span: span(40, 41),
+            ty: Type::I32,
+        }]
+    );
+}
+
+#[test]
+fn test_unused_value_message() {
+    let warning = CompileWarning::UnusedValue {
+        span: span(0, 1),
+        ty: Type::I32,
+    };
+
+    assert_eq!(
+        warning.to_string(),
+        "unused value of type `i32`; bind it with `x := ...` or discard it explicitly \
+         with `_ = ...`"
+    );
+}
+
+#[test]
+fn test_undefined_name_fails_compilation() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    x := y
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::UndefinedName {
+            name: "y".to_owned(),
+            span: span(22, 23),
+            suggestion: None,
+        }]
+    );
+}
+
+#[test]
+fn test_discarded_values_are_not_unused() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () {
+        |    _ = foo();
+        |    _ := 42;
+        |}
+        |
+        |foo :: () -> i32 {
+        |    1
+        |}
+        |"#,
+    );
+
+    assert_eq!(warnings, vec![]);
+}
+
+#[test]
+fn test_discard_cannot_be_used_as_value() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    _ := 42;
+        |    _
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::DiscardUsedAsValue { span: span(37, 38) }]
+    );
+}
+
+#[test]
+fn test_warn_unused_binding() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () -> i32 {
+        |    x := 1;
+        |    y := 2;
+        |    _z := 3;
+        |    y
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![CompileWarning::UnusedBinding {
+            name: "x".to_owned(),
+            span: span(24, 30),
+        }]
+    );
+}
+
+#[test]
+fn test_warn_binding_shadowing_parameter_in_nested_block() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () -> i32 { double(1) }
+        |double :: (n: i32) -> i32 {
+        |    if n {
+        |        n := n * 2;
+        |        n
+        |    } else {
+        |        0
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![CompileWarning::ShadowedBinding {
+            name: "n".to_owned(),
+            span: span(79, 89),
+            shadowed_span: span(43, 49),
+            shadows_param: true,
+        }]
+    );
+}
+
+#[test]
+fn test_warn_binding_shadowing_outer_binding_in_loop_body() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () -> i32 {
+        |    x := 1;
+        |    for i : 0..3 {
+        |        x := i;
+        |        _ = x;
+        |    }
+        |    x
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![CompileWarning::ShadowedBinding {
+            name: "x".to_owned(),
+            span: span(59, 65),
+            shadowed_span: span(24, 30),
+            shadows_param: false,
+        }]
+    );
+}
+
+#[test]
+fn test_rebinding_in_the_same_scope_is_not_shadowing() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () -> i32 {
+        |    x := 1;
+        |    x := x + 1;
+        |    if x {
+        |        _x := 2;
+        |        _x := _x + 1;
+        |        _ = _x;
+        |    }
+        |    x
+        |}
+        |"#,
+    );
+
+    assert_eq!(warnings, vec![]);
+}
+
+#[test]
+fn test_underscore_bindings_may_shadow() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () -> i32 {
+        |    _x := 1;
+        |    if _x {
+        |        _x := 2;
+        |        _ = _x;
+        |    }
+        |    _x
+        |}
+        |"#,
+    );
+
+    assert_eq!(warnings, vec![]);
+}
+
+#[test]
+fn test_warn_decl_unused_by_main() {
+    let warnings = compile_warnings(
+        r#"
+        |double :: (n: i32) -> i32 { n * 2 }
+        |quadruple :: (n: i32) -> i32 { double(double(n)) }
+        |unused :: () -> i32 { quadruple(1) }
+        |_kept :: () -> i32 { 1 }
+        |main :: () -> i32 { quadruple(2) }
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![CompileWarning::UnusedDecl {
+            name: "unused".to_owned(),
+            span: span(87, 93),
+        }]
+    );
+}
+
+#[test]
+fn test_allow_lints() {
+    let source_code = strip_margin(
+        r#"
+        |helper :: () -> i32 { 1 }
+        |main :: () -> i32 {
+        |    x := 1;
+        |    0
+        |}
+        |"#,
+    );
+    let mut options = Options::default();
+    options.lint_levels.allow(Lint::UnusedDecl);
+
+    let diagnostic = driver::check(&source_code, &options);
+
+    assert_eq!(
+        diagnostic.warnings,
+        vec![CompileWarning::UnusedBinding {
+            name: "x".to_owned(),
+            span: span(50, 56),
+        }]
+    );
+}
+
+#[test]
+fn test_denied_lints_fail_compilation() {
+    let source_code = strip_margin(
+        r#"
+        |main :: () -> i32 {
+        |    x := 1;
+        |    0
+        |}
+        |"#,
+    );
+    let mut options = Options::default();
+    options.lint_levels.deny(Lint::UnusedBinding);
+
+    let Err(diagnostic) = driver::compile(&source_code, &options) else {
+        panic!("denied warnings should fail the compilation");
+    };
+
+    assert!(!diagnostic.has_errors());
+    assert_eq!(
+        diagnostic.warnings,
+        vec![CompileWarning::UnusedBinding {
+            name: "x".to_owned(),
+            span: span(24, 30),
+        }]
+    );
+    assert!(options.lint_levels.denies(&diagnostic.warnings[0]));
+}
+
+#[test]
+fn test_warn_lint_undoes_deny() {
+    let mut options = Options::default();
+    options.lint_levels.deny(Lint::UnusedBinding);
+    options.lint_levels.warn(Lint::UnusedBinding);
+
+    let compiled = driver::compile("main :: () -> i32 {\n    x := 1;\n    0\n}\n", &options);
+
+    assert!(compiled.is_ok());
+}
+
+#[test]
+fn test_files_follow_their_own_lint_levels() {
+    let mut source_map = SourceMap::new();
+    source_map.add_file("main.sph", "main :: () -> i32 { 0 }\n");
+    let helpers = source_map.add_file("helpers.sph", "helper :: () -> i32 { 1 }\n");
+    let mut options = Options::default();
+    let mut helpers_lint_levels = LintLevels::default();
+    helpers_lint_levels.allow(Lint::UnusedDecl);
+    options
+        .file_lint_levels
+        .push((helpers, helpers_lint_levels));
+    options.lint_levels.deny(Lint::UnusedDecl);
+
+    let diagnostic = driver::check(source_map, &options);
+
+    assert_eq!(diagnostic.warnings, vec![]);
+}
+
+#[test]
+fn test_break_outside_loop() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    break;
+        |    for {
+        |        break;
+        |    } else {
+        |        continue;
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::BreakOutsideLoop {
+                keyword: Keyword::Break,
+                span: span(17, 22),
+            },
+            CompileError::BreakOutsideLoop {
+                keyword: Keyword::Continue,
+                span: span(70, 78),
+            },
+        ]
+    );
+}
+
+fn compile_executable(source_code: impl Into<SourceMap>) -> Vec<CompileError> {
+    let options = Options {
+        requires_main: true,
+        ..Options::default()
+    };
+
+    match driver::compile(source_code, &options) {
+        Ok(_) => vec![],
+        Err(diagnostic) => diagnostic.errors,
+    }
+}
+
+#[test]
+fn test_executables_require_main() {
+    let errors = compile_executable("helper :: () -> i32 { 1 }\nPoint :: struct {}\n");
+
+    assert_eq!(
+        errors,
+        vec![CompileError::MissingMain {
+            functions: vec!["helper".to_owned()],
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "program has no `main` function to start at; found only `helper`"
+    );
+    assert!(errors[0].is_about_program());
+}
+
+#[test]
+fn test_executables_require_one_main() {
+    let mut source_map = SourceMap::new();
+    source_map.add_file("main.sph", "main :: () -> i32 { 0 }\n");
+    source_map.add_file("other.sph", "main :: () {}\n");
+
+    assert_eq!(
+        compile_executable(source_map),
+        vec![CompileError::DuplicateMain {
+            first_span: span(0, 4),
+            span: span(25, 29),
+        }]
+    );
+}
+
+#[test]
+fn test_main_takes_nothing_and_returns_i32_or_unit() {
+    assert_eq!(compile_executable("main :: () -> i32 { 0 }"), vec![]);
+    assert_eq!(compile_executable("main :: () {}"), vec![]);
+
+    let errors = compile_executable("main :: (n: i32) -> i32 { n }");
+
+    assert_eq!(
+        errors,
+        vec![CompileError::InvalidMain {
+            found: "a function of type `(i32) -> i32`".to_owned(),
+            span: span(0, 4),
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "`main` must be a function of type `() -> i32` or `() -> ()`, found a function of type \
+         `(i32) -> i32`"
+    );
+    assert_eq!(
+        compile_executable("main :: enum { A }"),
+        vec![CompileError::InvalidMain {
+            found: "an enum".to_owned(),
+            span: span(0, 4),
+        }]
+    );
+}
+
+#[test]
+fn test_main_is_only_required_of_executables() {
+    let source_code = "helper :: (n: i32) -> i32 { n }\n";
+
+    assert!(driver::compile(source_code, &Options::default()).is_ok());
+    assert_eq!(
+        driver::check(source_code, &Options::default()).errors,
+        vec![]
+    );
+}
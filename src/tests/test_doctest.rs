@@ -0,0 +1,210 @@
+use pretty_assertions::assert_eq;
+
+use crate::diagnostics::CompileError;
+use crate::doctest::{
+    check_doc_example, extract_doc_examples, with_doc_example, DocExample, DocExampleFailure,
+};
+use crate::driver::Options;
+use crate::interp::RuntimeError;
+use crate::scanner::{BytePos, Span};
+use crate::source_map::SourceMap;
+use crate::tests::{compile, strip_margin};
+
+fn example(source_code: &str) -> DocExample {
+    DocExample {
+        line: 1,
+        source_code: strip_margin(source_code) + "\n",
+        no_run: false,
+    }
+}
+
+#[test]
+fn test_comments_are_skipped() {
+    let program = compile(
+        r#"
+        |// Answers everything.
+        |main :: () -> i32 { // Not a question.
+        |    /// Doc comments are comments too.
+        |    42
+        |}
+        |//"#,
+    );
+
+    assert_eq!(
+        program,
+        compile(
+            r#"
+            |main :: () -> i32 {
+            |    42
+            |}
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_extract_doc_examples() {
+    let source_code = strip_margin(
+        r#"
+        |/// Adds two numbers.
+        |///
+        |/// ```
+        |/// add(1, 2);
+        |/// ```
+        |///
+        |/// ```no_run
+        |///     main :: () {}
+        |/// ```
+        |add :: (a: i32, b: i32) -> i32 {
+        |    // ```
+        |    a + b
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        extract_doc_examples(&source_code),
+        vec![
+            DocExample {
+                line: 3,
+                source_code: "add(1, 2);\n".to_owned(),
+                no_run: false,
+            },
+            DocExample {
+                line: 7,
+                source_code: "    main :: () {}\n".to_owned(),
+                no_run: true,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_extract_doc_examples_leaves_out_ignored_and_other_languages() {
+    let source_code = strip_margin(
+        r#"
+        |/// ```ignore
+        |/// main :: (
+        |/// ```
+        |/// ```text
+        |/// Not code.
+        |/// ```
+        |//// ```
+        |//// Not a doc comment.
+        |//// ```
+        |main :: () {}
+        |"#,
+    );
+
+    assert_eq!(extract_doc_examples(&source_code), vec![]);
+}
+
+#[test]
+fn test_unclosed_doc_example_ends_with_its_doc_comment() {
+    let source_code = strip_margin(
+        r#"
+        |/// ```sophia
+        |/// 1 + 2;
+        |main :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        extract_doc_examples(&source_code),
+        vec![DocExample {
+            line: 1,
+            source_code: "1 + 2;\n".to_owned(),
+            no_run: false,
+        }]
+    );
+}
+
+/// Checks the example as if it were in a doc comment of the program.
+fn check_example_of(
+    program_source_code: &str,
+    example: &DocExample,
+) -> Result<(), DocExampleFailure> {
+    let source_map = SourceMap::from(strip_margin(program_source_code));
+
+    check_doc_example(
+        with_doc_example(&source_map, example, "example"),
+        example,
+        &Options::default(),
+    )
+}
+
+#[test]
+fn test_doc_example_uses_program_decls() {
+    let example = example(
+        r#"
+        |add(1, 2);"#,
+    );
+
+    let result = check_example_of(
+        r#"
+        |add :: (a: i32, b: i32) -> i32 {
+        |    a + b
+        |}
+        |main :: () -> i32 {
+        |    1 / 0
+        |}
+        |"#,
+        &example,
+    );
+
+    // The example's statements replace the program's `main`, which would fail.
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_doc_example_fails_to_compile() {
+    let example = example(
+        r#"
+        |add(x, 2);"#,
+    );
+
+    let Err(DocExampleFailure::Compile(diagnostic)) = check_example_of(
+        r#"
+        |add :: (a: i32, b: i32) -> i32 {
+        |    a + b
+        |}
+        |"#,
+        &example,
+    ) else {
+        panic!("example didn't fail to compile");
+    };
+
+    // The span is in the example, which comes after the program's 44 bytes
+    // and the newline that separates files.
+    assert_eq!(
+        diagnostic.errors,
+        vec![CompileError::UndefinedName {
+            name: "x".to_owned(),
+            span: Span {
+                start: BytePos(49),
+                end: BytePos(50),
+            },
+            suggestion: None,
+        }]
+    );
+}
+
+#[test]
+fn test_doc_example_fails_at_runtime_unless_not_run() {
+    let mut example = example(
+        r#"
+        |zero := 0;
+        |1 / zero;"#,
+    );
+
+    assert!(matches!(
+        check_example_of("", &example),
+        Err(DocExampleFailure::Runtime(
+            RuntimeError::DivisionByZero { .. }
+        ))
+    ));
+
+    example.no_run = true;
+
+    assert!(check_example_of("", &example).is_ok());
+}
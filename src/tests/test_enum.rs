@@ -0,0 +1,355 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast;
+use crate::ast_owned::Type;
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::layout::{EnumLayout, Layouts, VariantLayout};
+use crate::profile::IntWidth;
+use crate::tests::{check_errors, compile_errors, run, span, strip_margin};
+
+fn layouts(context: &CompilerContext, int_width: IntWidth) -> Result<Layouts, Vec<CompileError>> {
+    let program = driver::parse(context, &Options::default()).unwrap();
+
+    Layouts::of_program(context, program, int_width)
+}
+
+#[test]
+fn test_run_match_over_variants() {
+    let exit_code = run(r#"
+        |Shape :: enum { Circle(i32), Square(i32), Empty }
+        |
+        |area :: (shape: Shape) -> i32 {
+        |    match shape {
+        |        Shape::Circle(r) => 3 * r * r,
+        |        Shape::Square(side) => side * side,
+        |        Shape::Empty => 0,
+        |    }
+        |}
+        |
+        |main :: () -> i32 {
+        |    area(Shape::Circle(2)) + area(Shape::Square(10)) + area(Shape::Empty)
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(112));
+}
+
+#[test]
+fn test_run_variants_holding_structs_and_enums() {
+    let exit_code = run(r#"
+        |Point :: struct { x: i32, y: i32 }
+        |Shape :: enum { Circle(i32), Empty }
+        |Item :: enum { At(Point), Drawn(Shape), Nothing }
+        |
+        |value :: (item: Item) -> i32 {
+        |    match item {
+        |        Item::At(p) => p.x * 10 + p.y,
+        |        Item::Drawn(shape) => match shape {
+        |            Shape::Circle(_) => 100,
+        |            _ => 200,
+        |        },
+        |        _ => 0,
+        |    }
+        |}
+        |
+        |main :: () -> i32 {
+        |    value(Item::At(Point { x: 1, y: 2 })) + value(Item::Drawn(Shape::Empty))
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(212));
+}
+
+#[test]
+fn test_enum_layouts() {
+    let source_code = r#"
+        |Shape :: enum { Circle(i32), Square(i32), Empty }
+        |Unit :: enum { Only(i32) }
+        |Never :: enum {}
+        |Point :: struct { x: i32, flag: () }
+        |Item :: enum { At(Point), Nothing }
+        |"#;
+
+    let context = CompilerContext::new(strip_margin(source_code));
+    let enum_layouts = layouts(&context, IntWidth::Bits32).unwrap();
+
+    assert_eq!(
+        enum_layouts.get_enum("Shape"),
+        Some(&EnumLayout {
+            name: "Shape",
+            variants: vec![
+                VariantLayout {
+                    name: "Circle",
+                    payload: Some(ast::Type::I32),
+                    tag: 0,
+                },
+                VariantLayout {
+                    name: "Square",
+                    payload: Some(ast::Type::I32),
+                    tag: 1,
+                },
+                VariantLayout {
+                    name: "Empty",
+                    payload: None,
+                    tag: 2,
+                },
+            ],
+            tag_size: 1,
+            payload_offset: 4,
+            size: 8,
+            align: 4,
+        })
+    );
+    assert_eq!(
+        enum_layouts.get_enum("Unit").map(|layout| (
+            layout.tag_size,
+            layout.payload_offset,
+            layout.size
+        )),
+        Some((0, 0, 4))
+    );
+    assert_eq!(
+        enum_layouts
+            .get_enum("Never")
+            .map(|layout| (layout.size, layout.align)),
+        Some((0, 1))
+    );
+    assert_eq!(
+        enum_layouts.enum_of_decl(4).map(|layout| layout.size),
+        Some(8)
+    );
+    assert_eq!(enum_layouts.enum_of_decl(3), None);
+    assert_eq!(enum_layouts.get_struct("Shape"), None);
+
+    let context = CompilerContext::new(strip_margin(source_code));
+    let enum_layouts = layouts(&context, IntWidth::Bits16).unwrap();
+
+    assert_eq!(
+        enum_layouts.get_enum("Shape").map(|layout| (
+            layout.payload_offset,
+            layout.size,
+            layout.align
+        )),
+        Some((2, 4, 2))
+    );
+}
+
+#[test]
+fn test_tag_grows_with_the_number_of_variants() {
+    let variants: Vec<_> = (0..300).map(|idx| format!("V{}", idx)).collect();
+    let source_code = format!("|Big :: enum {{ {}(i32) }}\n", variants.join(", "));
+
+    let context = CompilerContext::new(strip_margin(&source_code));
+    let enum_layouts = layouts(&context, IntWidth::Bits32).unwrap();
+    let layout = enum_layouts.get_enum("Big").unwrap();
+
+    assert_eq!(
+        (layout.tag_size, layout.payload_offset, layout.size),
+        (2, 4, 8)
+    );
+    assert_eq!(layout.variant("V299").map(|variant| variant.tag), Some(299));
+}
+
+#[test]
+fn test_match_must_cover_every_variant() {
+    let errors = check_errors(
+        r#"
+        |Shape :: enum { Circle(i32), Square(i32), Empty }
+        |
+        |area :: (shape: Shape) -> i32 {
+        |    match shape {
+        |        Shape::Square(side) => side * side,
+        |    }
+        |}
+        |
+        |main :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::NonExhaustiveVariants {
+            uncovered: vec!["Shape::Circle(_)".to_owned(), "Shape::Empty".to_owned()],
+            span: span(92, 97),
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "non-exhaustive patterns: `Shape::Circle(_)` and `Shape::Empty` not covered"
+    );
+}
+
+#[test]
+fn test_variants_must_be_declared() {
+    let errors = check_errors(
+        r#"
+        |Shape :: enum { Circle(i32), Empty }
+        |
+        |main :: () -> i32 {
+        |    match Shape::Square(1) {
+        |        Shape::Triangle => 1,
+        |        _ => 0,
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::NoSuchVariant {
+                ty: Type::Named("Shape".to_owned()),
+                variant: "Square".to_owned(),
+                span: span(74, 80),
+            },
+            CompileError::NoSuchVariant {
+                ty: Type::Named("Shape".to_owned()),
+                variant: "Triangle".to_owned(),
+                span: span(101, 109),
+            },
+        ]
+    );
+    assert_eq!(errors[0].to_string(), "no variant `Square` on enum `Shape`");
+}
+
+#[test]
+fn test_variant_payloads_must_match_declaration() {
+    let errors = check_errors(
+        r#"
+        |Shape :: enum { Circle(i32), Empty }
+        |
+        |main :: () {
+        |    a := Shape::Circle;
+        |    b := Shape::Empty(1);
+        |    c := Shape::Circle({});
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MismatchedVariantPayload {
+                variant: "Shape::Circle".to_owned(),
+                expected: Some(Type::I32),
+                span: span(59, 72),
+            },
+            CompileError::MismatchedVariantPayload {
+                variant: "Shape::Empty".to_owned(),
+                expected: None,
+                span: span(83, 98),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(123, 125),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "variant `Shape::Circle` holds a value of type `i32`"
+    );
+    assert_eq!(
+        errors[1].to_string(),
+        "variant `Shape::Empty` holds no value"
+    );
+}
+
+#[test]
+fn test_enum_names_must_name_enums() {
+    let errors = check_errors(
+        r#"
+        |Point :: struct { x: i32 }
+        |
+        |main :: () {
+        |    a := Point::Origin;
+        |    b := Shap::Empty;
+        |}
+        |
+        |f :: (x: main) {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::NotAnEnum {
+                name: "Point".to_owned(),
+                span: span(49, 54),
+            },
+            CompileError::UndefinedName {
+                name: "Shap".to_owned(),
+                span: span(73, 77),
+                suggestion: None,
+            },
+            CompileError::NotAType {
+                name: "main".to_owned(),
+                span: span(94, 101),
+            },
+        ]
+    );
+    assert_eq!(errors[0].to_string(), "expected enum, found `Point`");
+    assert_eq!(errors[2].to_string(), "expected type, found `main`");
+}
+
+#[test]
+fn test_duplicate_and_recursive_variants() {
+    let errors = layouts(
+        &CompilerContext::new(strip_margin(
+            r#"
+        |Shape :: enum { Circle(i32), Circle }
+        |List :: enum { Cons(List), Nil }
+        |"#,
+        )),
+        IntWidth::Bits32,
+    )
+    .err()
+    .unwrap();
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::DuplicateVariant {
+                variant: "Circle".to_owned(),
+                span: span(29, 35),
+            },
+            CompileError::RecursiveType {
+                name: "List".to_owned(),
+                span: span(38, 42),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "variant `Circle` is already declared"
+    );
+}
+
+#[test]
+fn test_compiled_backends_reject_enums() {
+    let errors = compile_errors(
+        r#"
+        |Shape :: enum { Circle(i32), Empty }
+        |
+        |main :: () -> i32 {
+        |    match Shape::Circle(1) {
+        |        Shape::Circle(r) => r,
+        |        Shape::Empty => 0,
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::EnumsUnsupported { backend: "x86-64" }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "the x86-64 backend doesn't support enums yet; run the program with the interpreter \
+         instead"
+    );
+}
@@ -0,0 +1,232 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast_owned::Type;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::error_codes::{explain, EXPLANATIONS};
+use crate::profile::IntWidth;
+use crate::scanner::{BytePos, Delim, Keyword, Span, TokenKind};
+
+/// Code examples of erroneous programs that compile on their own, as opposed
+/// to ones compiled with flags, or that aren't code.
+fn erroneous_code_examples() -> Vec<(&'static str, String)> {
+    EXPLANATIONS
+        .iter()
+        .filter_map(|&(code, explanation)| {
+            let (_, example) = explanation.split_once("Erroneous code example:\n\n")?;
+            let example: Vec<_> = example
+                .lines()
+                .take_while(|line| !line.is_empty())
+                .map(|line| line.strip_prefix("    ").unwrap())
+                .collect();
+
+            Some((code, example.join("\n") + "\n"))
+        })
+        .collect()
+}
+
+#[test]
+fn test_every_error_has_an_explanation() {
+    let span = Span {
+        start: BytePos(0),
+        end: BytePos(0),
+    };
+    let errors = [
+        CompileError::UnclosedDelimiter {
+            delim: Delim::Paren,
+            open_span: span,
+            eof_span: span,
+        },
+        CompileError::UndefinedName {
+            name: "x".to_owned(),
+            span,
+            suggestion: None,
+        },
+        CompileError::DiscardUsedAsValue { span },
+        CompileError::MismatchedTypes {
+            expected: Type::I32,
+            found: Type::Unit,
+            span,
+        },
+        CompileError::MismatchedCallArguments {
+            expected: vec![],
+            found: vec![],
+            span,
+        },
+        CompileError::CallToNonFunction {
+            ty: Type::I32,
+            span,
+        },
+        CompileError::ScriptStatementsWithMain {
+            main_span: span,
+            stmt_span: span,
+        },
+        CompileError::SourceTooLarge { size: 2, limit: 1 },
+        CompileError::AstTooLarge { limit: 1, span },
+        CompileError::NestingTooDeep { limit: 1, span },
+        CompileError::IntegerConstantOutOfRange {
+            width: IntWidth::Bits16,
+            span,
+        },
+        CompileError::IntProfileUnsupported { backend: "x86-64" },
+        CompileError::HostFunctionsUnsupported { backend: "C" },
+        CompileError::BreakOutsideLoop {
+            keyword: Keyword::Break,
+            span,
+        },
+        CompileError::ExpectedButFound {
+            expected: vec![TokenKind::Semi],
+            found: None,
+            span,
+        },
+        CompileError::NonExhaustiveMatch {
+            uncovered: vec![(0, 0)],
+            width: IntWidth::Bits32,
+            span,
+        },
+        CompileError::NoSuchField {
+            ty: Type::I32,
+            field: "x".to_owned(),
+            span,
+        },
+        CompileError::MissingFields {
+            ty: Type::Named("Point".to_owned()),
+            fields: vec!["x".to_owned()],
+            span,
+        },
+        CompileError::DuplicateField {
+            field: "x".to_owned(),
+            span,
+        },
+        CompileError::RecursiveType {
+            name: "Node".to_owned(),
+            span,
+        },
+        CompileError::NotAStruct {
+            name: "main".to_owned(),
+            span,
+        },
+        CompileError::StructsUnsupported { backend: "C" },
+        CompileError::NoSuchVariant {
+            ty: Type::Named("Shape".to_owned()),
+            variant: "Square".to_owned(),
+            span,
+        },
+        CompileError::DuplicateVariant {
+            variant: "Circle".to_owned(),
+            span,
+        },
+        CompileError::MismatchedVariantPayload {
+            variant: "Shape::Circle".to_owned(),
+            expected: Some(Type::I32),
+            span,
+        },
+        CompileError::NotAnEnum {
+            name: "main".to_owned(),
+            span,
+        },
+        CompileError::NonExhaustiveVariants {
+            uncovered: vec!["Shape::Empty".to_owned()],
+            span,
+        },
+        CompileError::EnumsUnsupported { backend: "C" },
+        CompileError::NotAType {
+            name: "main".to_owned(),
+            span,
+        },
+        CompileError::MisspelledKeyword {
+            found: "fro".to_owned(),
+            keyword: Keyword::For,
+            span,
+        },
+        CompileError::MismatchedTupleBindings {
+            bindings: 2,
+            found: Type::I32,
+            span,
+        },
+        CompileError::TuplesUnsupported { backend: "C" },
+        CompileError::IndexOutOfBounds {
+            index: 4,
+            len: 4,
+            span,
+        },
+        CompileError::CannotIndex {
+            ty: Type::I32,
+            span,
+        },
+        CompileError::InvalidAssignTarget { span },
+        CompileError::ArraysUnsupported { backend: "C" },
+        CompileError::CannotIterate {
+            ty: Type::I32,
+            span,
+        },
+        CompileError::InvalidIntegerSuffix {
+            suffix: "f32".to_owned(),
+            span,
+        },
+        CompileError::IntegerConstantTooLarge { ty: Type::U8, span },
+        CompileError::IntTypesUnsupported { backend: "C" },
+        CompileError::InvalidCast {
+            from: Type::Unit,
+            to: Type::I32,
+            span,
+        },
+        CompileError::FunctionValuesUnsupported { backend: "C" },
+        CompileError::BreakValueInIterativeLoop { span },
+        CompileError::LoopValuesUnsupported { backend: "C" },
+        CompileError::MissingElseBranch {
+            ty: Type::I32,
+            span,
+        },
+        CompileError::InvalidExternType {
+            ty: Type::Unit,
+            span,
+        },
+        CompileError::MissingMain { functions: vec![] },
+        CompileError::DuplicateMain {
+            first_span: span,
+            span,
+        },
+        CompileError::InvalidMain {
+            found: "a struct".to_owned(),
+            span,
+        },
+        CompileError::InvalidCondition {
+            found: Type::Unit,
+            span,
+        },
+        CompileError::ZeroStep { span },
+        CompileError::UnknownChar { ch: '@', span },
+        CompileError::InvalidDeclValue { span },
+        CompileError::DuplicateDecl {
+            name: "f".to_owned(),
+            first_span: span,
+            span,
+        },
+    ];
+
+    let codes: Vec<_> = errors.iter().map(CompileError::code).collect();
+    let explained_codes: Vec<_> = EXPLANATIONS.iter().map(|&(code, _)| code).collect();
+
+    assert_eq!(codes, explained_codes);
+}
+
+#[test]
+fn test_erroneous_code_examples_fail_with_their_error() {
+    let examples = erroneous_code_examples();
+    assert!(!examples.is_empty());
+
+    for (code, example) in examples {
+        let diagnostic = driver::check(example.as_str(), &Options::default());
+        let codes: Vec<_> = diagnostic.errors.iter().map(CompileError::code).collect();
+
+        assert_eq!(codes, vec![code], "example of {}:\n{}", code, example);
+    }
+}
+
+#[test]
+fn test_explain_unknown_code() {
+    assert!(explain("E0002").unwrap().starts_with("A name was used"));
+    assert_eq!(explain("E9999"), None);
+    assert_eq!(explain("e0002"), None);
+}
@@ -0,0 +1,82 @@
+use pretty_assertions::assert_eq;
+
+use crate::driver::{self, Expansion, Options, Stage};
+use crate::tests::strip_margin;
+
+fn expand(source_code: &str) -> Vec<Expansion> {
+    match driver::expand(strip_margin(source_code), &Options::default()) {
+        Ok((expansions, _)) => expansions,
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+#[test]
+fn test_expand_prints_program_after_every_stage() {
+    let expansions = expand(
+        r#"
+        |main :: () -> i32 {
+        |    for { break; _ = 1; }
+        |    0
+        |}
+        |"#,
+    );
+
+    let stages: Vec<_> = expansions.iter().map(|expansion| expansion.stage).collect();
+    assert_eq!(stages, Stage::ALL);
+
+    assert_eq!(
+        expansions[0].source_code,
+        strip_margin(
+            r#"
+            |main :: () -> i32 {
+            |    for {
+            |        break;
+            |        _ = 1;
+            |    }
+            |    0
+            |}
+            |"#
+        ) + "\n"
+    );
+    assert_eq!(
+        expansions[1].source_code,
+        strip_margin(
+            r#"
+            |main :: () -> i32 {
+            |    for {
+            |        break;
+            |    }
+            |    0
+            |}
+            |"#
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_expansions_compile_as_they_are() {
+    let expansions = expand(
+        r#"
+        |main :: () -> i32 {
+        |    x := 0;
+        |    for i : 0..10 { if i { continue; x + 1; } }
+        |    (x)
+        |}
+        |"#,
+    );
+
+    for expansion in expansions {
+        let diagnostic = driver::check(expansion.source_code, &Options::default());
+
+        assert!(diagnostic.errors.is_empty(), "{:?}", diagnostic.errors);
+    }
+}
+
+#[test]
+fn test_stage_names_round_trip() {
+    for stage in Stage::ALL {
+        assert_eq!(Stage::from_name(stage.name()), Some(stage));
+    }
+
+    assert_eq!(Stage::from_name("macros"), None);
+}
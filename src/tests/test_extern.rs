@@ -0,0 +1,170 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast_owned::Type;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Emit, Options};
+use crate::interp::RuntimeError;
+use crate::tests::{
+    check, compile, compile_errors, compile_with_options, run_with_output, span, strip_margin,
+};
+use crate::vm::Vm;
+
+#[test]
+fn test_extern_declarations_are_printed_back() {
+    let options = Options {
+        emit: Emit::Source,
+        ..Default::default()
+    };
+
+    let source = compile_with_options(
+        r#"
+        |extern putchar::(c:i32)->i32;
+        |extern exit :: (code: i32,) ;
+        |main :: () {}
+        |"#,
+        &options,
+    );
+
+    assert_eq!(
+        source,
+        strip_margin(
+            r#"
+            |extern putchar :: (c: i32) -> i32;
+            |extern exit :: (code: i32);
+            |main :: () {}
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_interpreter_runs_functions_of_the_c_library() {
+    let (exit_code, output) = run_with_output(
+        r#"
+        |extern putchar :: (c: i32) -> i32;
+        |extern abs :: (n: i32) -> i32;
+        |
+        |main :: () -> i32 {
+        |    _ = putchar(72);
+        |    _ = putchar(105);
+        |    abs(0 - 7)
+        |}
+        |"#,
+    );
+
+    assert_eq!(exit_code, Ok(7));
+    assert_eq!(output, "Hi");
+}
+
+#[test]
+fn test_interpreter_cant_call_other_extern_functions() {
+    let (exit_code, _) = run_with_output(
+        r#"
+        |extern exit :: (code: i32);
+        |
+        |main :: () {
+        |    exit(3);
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::UnsupportedExternFn {
+            name: "exit".to_owned(),
+            span: span(45, 52),
+        })
+    );
+}
+
+#[test]
+fn test_vm_imports_extern_functions_as_host_functions() {
+    let compiled_bytecode = driver::compile_bytecode(
+        strip_margin(
+            r#"
+            |extern abs :: (n: i32) -> i32;
+            |
+            |main :: () -> i32 {
+            |    println(abs(0 - 2));
+            |    abs(0 - 5)
+            |}
+            |"#,
+        ),
+        &Options::default(),
+    )
+    .unwrap();
+    let imports: Vec<_> = compiled_bytecode
+        .module
+        .host_functions
+        .iter()
+        .map(|import| (import.name.as_str(), import.arity, import.returns_value))
+        .collect();
+
+    assert_eq!(imports, [("abs", 1, true), ("println", 1, false)]);
+
+    // The VM provides the C library's functions that it knows of.
+    let mut vm = Vm::new(compiled_bytecode.module);
+    vm.register_host_function("println", |_| Ok(0));
+
+    assert_eq!(vm.run_main(), Ok(5));
+}
+
+#[test]
+fn test_extern_functions_only_take_and_return_integers() {
+    let errors = compile_errors(
+        r#"
+        |extern first :: (pair: (i32, i32)) -> i32;
+        |extern unit :: (x: ()) -> (i32, i32);
+        |main :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::InvalidExternType {
+                ty: Type::Tuple(vec![Type::I32, Type::I32]),
+                span: span(17, 33),
+            },
+            CompileError::InvalidExternType {
+                ty: Type::Unit,
+                span: span(59, 64),
+            },
+            CompileError::InvalidExternType {
+                ty: Type::Tuple(vec![Type::I32, Type::I32]),
+                span: span(43, 80),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_native_code_aligns_the_stack_for_extern_functions() {
+    let program = compile(
+        r#"
+        |extern putchar :: (c: i32) -> i32;
+        |
+        |main :: () -> i32 {
+        |    putchar(65)
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    mov edi, 65
+        |    push rbx
+        |    mov rbx, rsp
+        |    and rsp, -16
+        |    call putchar
+        |    mov rsp, rbx
+        |    pop rbx
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
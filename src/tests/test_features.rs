@@ -0,0 +1,143 @@
+use crate::ast::{HostFnDecl, Type};
+use crate::driver::{self, Options};
+use crate::features::{Feature, FeatureReport};
+use crate::tests::strip_margin;
+
+fn report_features(source_code: &str, options: &Options) -> FeatureReport {
+    match driver::report_features(strip_margin(source_code), options) {
+        Ok((report, _)) => report,
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+fn features_by_decl(report: &FeatureReport) -> Vec<(&str, Vec<Feature>)> {
+    report
+        .decls
+        .iter()
+        .map(|decl| (decl.name.as_str(), decl.features.clone()))
+        .collect()
+}
+
+#[test]
+fn test_report_no_features() {
+    let report = report_features(
+        r#"
+        |main :: () -> i32 { 1 + 2 }
+        |"#,
+        &Options::default(),
+    );
+
+    assert_eq!(features_by_decl(&report), vec![("main", vec![])]);
+    assert_eq!(report.features(), vec![]);
+}
+
+#[test]
+fn test_report_direct_and_mutual_recursion() {
+    let report = report_features(
+        r#"
+        |fact :: (n: i32) -> i32 { if n { n * fact(n - 1) } else { 1 } }
+        |even :: (n: i32) -> i32 { if n { odd(n - 1) } else { 1 } }
+        |odd :: (n: i32) -> i32 { if n { even(n - 1) } else { 0 } }
+        |main :: () -> i32 { fact(3) + even(4) }
+        |"#,
+        &Options::default(),
+    );
+
+    assert_eq!(
+        features_by_decl(&report),
+        vec![
+            ("fact", vec![Feature::Recursion]),
+            ("even", vec![Feature::Recursion]),
+            ("odd", vec![Feature::Recursion]),
+            ("main", vec![]),
+        ]
+    );
+}
+
+#[test]
+fn test_report_loops_and_host_functions() {
+    let options = Options {
+        host_functions: vec![HostFnDecl {
+            name: "print".to_owned(),
+            param_types: vec![Type::I32],
+            return_type: Type::Unit,
+        }],
+        ..Default::default()
+    };
+
+    let report = report_features(
+        r#"
+        |count :: (n: i32) -> i32 {
+        |    for i : 0..n {
+        |        print(i);
+        |    }
+        |    if n { count(n - 1) } else { 0 }
+        |}
+        |main :: () -> i32 { count(3) }
+        |"#,
+        &options,
+    );
+
+    assert_eq!(
+        features_by_decl(&report),
+        vec![
+            (
+                "count",
+                vec![Feature::Loops, Feature::Recursion, Feature::HostFunctions]
+            ),
+            ("main", vec![]),
+        ]
+    );
+    assert_eq!(
+        report.to_json().to_string(),
+        r#"{"features":["loops","recursion","host-functions"],"decls":[{"name":"count","features":["loops","recursion","host-functions"]},{"name":"main","features":[]}]}"#
+    );
+}
+
+#[test]
+fn test_report_closures() {
+    let report = report_features(
+        r#"
+        |add :: (n: i32) -> i32 {
+        |    add_n := (x: i32) -> i32 { x + n };
+        |    add_n(1)
+        |}
+        |main :: () -> i32 { add(2) }
+        |"#,
+        &Options::default(),
+    );
+
+    assert_eq!(
+        features_by_decl(&report),
+        vec![("add", vec![Feature::Closures]), ("main", vec![])]
+    );
+}
+
+#[test]
+fn test_report_extern_functions() {
+    let report = report_features(
+        r#"
+        |extern putchar :: (c: i32) -> i32;
+        |greet :: () { _ = putchar(72); }
+        |main :: () -> i32 {
+        |    greet();
+        |    0
+        |}
+        |"#,
+        &Options::default(),
+    );
+
+    // Calling a function that calls an extern one isn't calling it.
+    assert_eq!(
+        features_by_decl(&report),
+        vec![
+            ("putchar", vec![Feature::ExternFunctions]),
+            ("greet", vec![Feature::ExternFunctions]),
+            ("main", vec![]),
+        ]
+    );
+    assert_eq!(
+        report.to_json().to_string(),
+        r#"{"features":["extern-functions"],"decls":[{"name":"putchar","features":["extern-functions"]},{"name":"greet","features":["extern-functions"]},{"name":"main","features":[]}]}"#
+    );
+}
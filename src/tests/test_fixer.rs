@@ -0,0 +1,100 @@
+use pretty_assertions::assert_eq;
+
+use crate::diagnostics::CompileError;
+use crate::driver::Options;
+use crate::fixer::fix_source;
+use crate::scanner::{BytePos, Span};
+use crate::tests::strip_margin;
+
+#[test]
+fn test_fixes_are_made_until_nothing_more_can_be() {
+    let source_code = strip_margin(
+        r#"
+        |triple :: (n: i32) -> i32 {
+        |    n * 3
+        |}
+        |
+        |main :: () -> i32 {
+        |    fro i : 0..3 {
+        |        _ = triple(i) @;
+        |    }
+        |    for {
+        |        break;
+        |    }
+        |    tripl(2)
+        |"#,
+    );
+
+    let fixed = fix_source(&source_code, &Options::default());
+
+    // The misspelled name is only found once the program parses, which takes
+    // closing `main`.
+    assert_eq!(
+        fixed.source_code,
+        strip_margin(
+            r#"
+            |triple :: (n: i32) -> i32 {
+            |    n * 3
+            |}
+            |
+            |main :: () -> i32 {
+            |    for i : 0..3 {
+            |        _ = triple(i) ;
+            |    }
+            |    loop {
+            |        break;
+            |    }
+            |    triple(2) }
+            |"#,
+        )
+    );
+    assert_eq!(fixed.fix_count, 5);
+    assert!(fixed.diagnostic.errors.is_empty());
+    assert!(fixed.diagnostic.warnings.is_empty());
+}
+
+#[test]
+fn test_fixes_that_leave_errors_arent_made() {
+    let source_code = strip_margin(
+        r#"
+        |count :: () -> i32 { 1 }
+        |main :: () -> i32 {
+        |    cont(1) @
+        |}
+        |"#,
+    );
+
+    let fixed = fix_source(&source_code, &Options::default());
+
+    // `count` takes no arguments, so the call would still be wrong with it.
+    assert_eq!(fixed.source_code, source_code.replace(" @", " "));
+    assert_eq!(fixed.fix_count, 1);
+    assert_eq!(
+        fixed.diagnostic.errors,
+        vec![CompileError::UndefinedName {
+            name: "cont".to_owned(),
+            span: Span {
+                start: BytePos(49),
+                end: BytePos(56),
+            },
+            suggestion: Some("count".to_owned()),
+        }]
+    );
+}
+
+#[test]
+fn test_programs_without_fixes_are_left_as_they_are() {
+    let source_code = "main :: () -> i32 {\n    y\n}\n";
+
+    let fixed = fix_source(source_code, &Options::default());
+
+    assert_eq!(fixed.source_code, source_code);
+    assert_eq!(fixed.fix_count, 0);
+    assert!(matches!(
+        fixed.diagnostic.errors[..],
+        [CompileError::UndefinedName {
+            suggestion: None,
+            ..
+        }]
+    ));
+}
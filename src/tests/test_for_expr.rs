@@ -19,9 +19,6 @@ fn test_empty_infinite_for_loop() {
         |    mov rbp, rsp
         |.L0:
         |    jmp .L0
-        |.L1:
-        |    pop rbp
-        |    ret
         |"#,
     );
 }
@@ -47,19 +44,9 @@ fn test_infinite_for_loop() {
         |main:
         |    push rbp
         |    mov rbp, rsp
-        |    sub rsp, 4
-        |
         |.L0:
-        |    mov eax, 42
-        |    mov DWORD PTR [rbp-4], eax
         |    call foo
         |    jmp .L0
-        |
-        |.L1:
-        |    add rsp, 4
-        |    pop rbp
-        |    ret
-        |
         |foo:
         |    push rbp
         |    mov rbp, rsp
@@ -89,9 +76,7 @@ fn test_conditional_empty_infinite_for_loop() {
         |.L0:
         |    mov eax, 1
         |    cmp eax, 0
-        |    je .L1
-        |    jmp .L0
-        |.L1:
+        |    jne .L0
         |    pop rbp
         |    ret
         |"#,
@@ -118,20 +103,15 @@ fn test_conditional_for_loop() {
         |main:
         |    push rbp
         |    mov rbp, rsp
-        |
-        |.L0:
+        |.L1:
         |    mov eax, 1
         |    cmp eax, 0
-        |    je .L1
-        |
+        |    je .L0
         |    call foo
-        |
-        |    jmp .L0
-        |
-        |.L1:
+        |    jmp .L1
+        |.L0:
         |    pop rbp
         |    ret
-        |
         |foo:
         |    push rbp
         |    mov rbp, rsp
@@ -160,23 +140,19 @@ fn test_iteration_for_loop_exclusive_range() {
         |    push rbp
         |    mov rbp, rsp
         |    sub rsp, 4
-        |
-        |    mov eax, 3
-        |    mov DWORD PTR [rbp-4], eax
-        |
-        |.L0:
+        |    mov DWORD PTR [rbp-4], 3
+        |.L1:
         |    mov eax, DWORD PTR [rbp-4]
         |    cmp eax, 10
-        |    jge .L1
-        |
+        |    jge .L0
         |    mov eax, DWORD PTR [rbp-4]
-        |
+        |    add eax, 1
+        |    jo .L0        ; stop if i would go past the largest i32
         |    mov eax, DWORD PTR [rbp-4]
         |    add eax, 1
         |    mov DWORD PTR [rbp-4], eax
-        |    jmp .L0
-        |
-        |.L1:
+        |    jmp .L1
+        |.L0:
         |    add rsp, 4
         |    pop rbp
         |    ret
@@ -203,23 +179,19 @@ fn test_iteration_for_loop_inclusive_range() {
         |    push rbp
         |    mov rbp, rsp
         |    sub rsp, 4
-        |
-        |    mov eax, 3
-        |    mov DWORD PTR [rbp-4], eax
-        |
-        |.L0:
+        |    mov DWORD PTR [rbp-4], 3
+        |.L1:
         |    mov eax, DWORD PTR [rbp-4]
         |    cmp eax, 10
-        |    jg .L1
-        |
+        |    jg .L0
         |    mov eax, DWORD PTR [rbp-4]
-        |
+        |    add eax, 1
+        |    jo .L0
         |    mov eax, DWORD PTR [rbp-4]
         |    add eax, 1
         |    mov DWORD PTR [rbp-4], eax
-        |    jmp .L0
-        |
-        |.L1:
+        |    jmp .L1
+        |.L0:
         |    add rsp, 4
         |    pop rbp
         |    ret
@@ -227,7 +199,6 @@ fn test_iteration_for_loop_inclusive_range() {
     );
 }
 
-
 #[test]
 fn test_break_infinite_for_loop() {
     let program = compile(
@@ -246,10 +217,6 @@ fn test_break_infinite_for_loop() {
         |main:
         |    push rbp
         |    mov rbp, rsp
-        |.L0:
-        |    jmp .L1
-        |    jmp .L0
-        |.L1:
         |    pop rbp
         |    ret
         |"#,
@@ -277,17 +244,6 @@ fn test_break_innermost_for_loop() {
         |main:
         |    push rbp
         |    mov rbp, rsp
-        |.L0:               ; start of outermost for-loop
-        |
-        |.L2:               ; start of innermost for-loop
-        |    jmp .L3        ; break out of innermost for-loop
-        |    jmp .L2
-        |.L3:               ; exit of innermost for-loop
-        |
-        |    jmp .L1        ; break out of outermost for-loop
-        |    jmp .L0
-        |
-        |.L1:               ; exit of outermost for-loop
         |    pop rbp
         |    ret
         |"#,
@@ -309,6 +265,54 @@ fn test_many_breaks_in_infinite_for_loop() {
         |"#,
     );
 
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_breaks_iterative_for_loop() {
+    let program = compile(
+        r#"
+        |main :: () {
+        |    for i : 0..10 {
+        |        break
+        |    }
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_continue_infinite_for_loop() {
+    let program = compile(
+        r#"
+        |main :: () {
+        |    for {
+        |        continue
+        |    }
+        |}
+        |"#,
+    );
+
     check(
         program,
         r#"
@@ -316,12 +320,53 @@ fn test_many_breaks_in_infinite_for_loop() {
         |    push rbp
         |    mov rbp, rsp
         |.L0:
-        |    jmp .L1
-        |    jmp .L1
-        |    jmp .L1
-        |    jmp .L1
         |    jmp .L0
+        |"#,
+    );
+}
+
+#[test]
+fn test_conditional_for_loop_with_else_branch() {
+    let program = compile(
+        r#"
+        |main :: () {
+        |    for 1 {
+        |        foo();
+        |    } else {
+        |        bar();
+        |    }
+        |}
+        |
+        |foo :: () {}
+        |
+        |bar :: () {}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
         |.L1:
+        |    mov eax, 1
+        |    cmp eax, 0
+        |    je .L0        ; loop completed, so run the else-branch
+        |    call foo
+        |    jmp .L1
+        |.L0:              ; else-branch
+        |    call bar
+        |    pop rbp
+        |    ret
+        |foo:
+        |    push rbp
+        |    mov rbp, rsp
+        |    pop rbp
+        |    ret
+        |bar:
+        |    push rbp
+        |    mov rbp, rsp
         |    pop rbp
         |    ret
         |"#,
@@ -329,14 +374,18 @@ fn test_many_breaks_in_infinite_for_loop() {
 }
 
 #[test]
-fn test_breaks_iterative_for_loop() {
+fn test_break_skips_else_branch_of_iterative_for_loop() {
     let program = compile(
         r#"
         |main :: () {
         |    for i : 0..10 {
         |        break
+        |    } else {
+        |        foo()
         |    }
         |}
+        |
+        |foo :: () {}
         |"#,
     );
 
@@ -347,33 +396,61 @@ fn test_breaks_iterative_for_loop() {
         |    push rbp
         |    mov rbp, rsp
         |    sub rsp, 4
-        |    mov eax, 0
-        |    mov DWORD PTR [rbp-4], eax
-        |.L0:
+        |    mov DWORD PTR [rbp-4], 0
         |    mov eax, DWORD PTR [rbp-4]
         |    cmp eax, 10
-        |    jge .L1
-        |
-        |    jmp .L1  ; break
-        |
-        |    mov eax, DWORD PTR [rbp-4]
-        |    add eax, 1
-        |    mov DWORD PTR [rbp-4], eax
-        |    jmp .L0
-        |.L1:
+        |    jl .L0        ; break skips the else-branch
+        |    call foo
+        |.L0:
         |    add rsp, 4
         |    pop rbp
         |    ret
+        |foo:
+        |    push rbp
+        |    mov rbp, rsp
+        |    pop rbp
+        |    ret
         |"#,
     );
 }
 
 #[test]
-fn test_continue_infinite_for_loop() {
+fn test_break_in_else_branch_exits_outer_for_loop() {
     let program = compile(
         r#"
         |main :: () {
         |    for {
+        |        for 1 {
+        |        } else {
+        |            break
+        |        }
+        |    }
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |.L0:
+        |    mov eax, 1
+        |    cmp eax, 0
+        |    jne .L0
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_continue_iterative_for_loop_steps_to_next_value() {
+    let program = compile(
+        r#"
+        |main :: () {
+        |    for i : 0..10 {
         |        continue
         |    }
         |}
@@ -386,10 +463,180 @@ fn test_continue_infinite_for_loop() {
         |main:
         |    push rbp
         |    mov rbp, rsp
+        |    sub rsp, 4
+        |    mov DWORD PTR [rbp-4], 0
+        |.L1:
+        |    mov eax, DWORD PTR [rbp-4]
+        |    cmp eax, 10
+        |    jge .L0
+        |    mov eax, DWORD PTR [rbp-4]     ; continue steps to the next value
+        |    add eax, 1
+        |    jo .L0
+        |    mov eax, DWORD PTR [rbp-4]
+        |    add eax, 1
+        |    mov DWORD PTR [rbp-4], eax
+        |    jmp .L1
         |.L0:
-        |    jmp .L0
-        |    jmp .L0
+        |    add rsp, 4
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_iterative_for_loop_with_non_constant_end() {
+    let program = compile(
+        r#"
+        |count :: (n: i32) {
+        |    for i : 0..n {
+        |    }
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |count:
+        |    push rbp
+        |    mov rbp, rsp
+        |    sub rsp, 8
+        |    mov DWORD PTR [rbp-4], edi
+        |    mov DWORD PTR [rbp-8], 0
+        |.L1:
+        |    mov eax, DWORD PTR [rbp-8]
+        |    cmp eax, DWORD PTR [rbp-4]
+        |    jge .L0
+        |    mov eax, DWORD PTR [rbp-8]
+        |    add eax, 1
+        |    jo .L0
+        |    mov eax, DWORD PTR [rbp-8]
+        |    add eax, 1
+        |    mov DWORD PTR [rbp-8], eax
+        |    jmp .L1
+        |.L0:
+        |    add rsp, 8
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_iterative_for_loop_with_step() {
+    let program = compile(
+        r#"
+        |count :: (n: i32) {
+        |    for i : 0..10 step n {
+        |    }
+        |    for i : 0..=10 step 2 {
+        |    }
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |count:
+        |    push rbp
+        |    mov rbp, rsp
+        |    sub rsp, 12
+        |    mov DWORD PTR [rbp-4], edi
+        |    mov eax, DWORD PTR [rbp-4]     ; step, evaluated once
+        |    cmp eax, 0                     ; which must be positive
+        |    jg .L0
+        |    call sophia_abort
+        |.L0:
+        |    mov DWORD PTR [rbp-8], 0
+        |.L2:
+        |    mov eax, DWORD PTR [rbp-8]
+        |    cmp eax, 10
+        |    jge .L1
+        |    mov eax, DWORD PTR [rbp-8]
+        |    add eax, DWORD PTR [rbp-4]
+        |    jo .L1
+        |    mov eax, DWORD PTR [rbp-8]
+        |    add eax, DWORD PTR [rbp-4]
+        |    mov DWORD PTR [rbp-8], eax
+        |    jmp .L2
+        |.L1:
+        |    mov DWORD PTR [rbp-12], 0
+        |.L4:
+        |    mov eax, DWORD PTR [rbp-12]
+        |    cmp eax, 10
+        |    jg .L3
+        |    mov eax, DWORD PTR [rbp-12]
+        |    add eax, 2
+        |    jo .L3
+        |    mov eax, DWORD PTR [rbp-12]
+        |    add eax, 2
+        |    mov DWORD PTR [rbp-12], eax
+        |    jmp .L4
+        |.L3:
+        |    add rsp, 12
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_reversed_iterative_for_loop() {
+    let program = compile(
+        r#"
+        |count :: (n: i32) {
+        |    for i : rev n..10 step 3 {
+        |    }
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |count:
+        |    push rbp
+        |    mov rbp, rsp
+        |    sub rsp, 24
+        |    mov DWORD PTR [rbp-4], edi
+        |    mov eax, DWORD PTR [rbp-4]
+        |    cmp eax, 10
+        |    mov eax, 1
+        |    jl .L0                         ; the range is empty unless n < 10
+        |    mov eax, 0
+        |.L0:
+        |    mov DWORD PTR [rbp-8], eax
+        |    mov eax, 10                    ; so i starts at 9
+        |    sub eax, 1
+        |    mov DWORD PTR [rbp-12], eax
+        |    mov eax, DWORD PTR [rbp-8]
+        |    mov DWORD PTR [rbp-16], eax
+        |.L3:
+        |    mov eax, DWORD PTR [rbp-16]
+        |    cmp eax, 0
+        |    je .L1
+        |    mov eax, DWORD PTR [rbp-12]
+        |    sub eax, 3
+        |    jo .L1                         ; stop if i would go past the smallest i32
+        |    mov eax, DWORD PTR [rbp-12]
+        |    sub eax, 3
+        |    mov DWORD PTR [rbp-20], eax
+        |    mov eax, DWORD PTR [rbp-4]
+        |    cmp eax, DWORD PTR [rbp-20]    ; or below n
+        |    mov eax, 1
+        |    jle .L2
+        |    mov eax, 0
+        |.L2:
+        |    mov DWORD PTR [rbp-24], eax
+        |    mov eax, DWORD PTR [rbp-20]
+        |    mov DWORD PTR [rbp-12], eax
+        |    mov eax, DWORD PTR [rbp-24]
+        |    mov DWORD PTR [rbp-16], eax
+        |    jmp .L3
         |.L1:
+        |    add rsp, 24
         |    pop rbp
         |    ret
         |"#,
@@ -0,0 +1,172 @@
+use pretty_assertions::assert_eq;
+
+use crate::cst::lossless_tokens;
+use crate::formatter::format_source;
+
+#[test]
+fn test_tokens_are_spaced_canonically() {
+    let source = "Point::struct{x:i32,y : i32}
+Shape :: enum{Dot,Square(i32)}
+square::(n:i32)->i32{n*n}
+extern putchar::(c:i32)->i32;
+main ::()->i32{
+x:=square( 3 )  ;
+y : i32=x+1
+p := Point{x:1,y:2}
+s:=Shape::Square(2)
+for i:0..=3 { _ = putchar(i) }
+#[main_loop] for {break}
+arr := [(1,),(2,3)]
+arr[0].0+p.x+y as i32
+}
+";
+
+    assert_eq!(
+        format_source(source).unwrap(),
+        "Point :: struct { x: i32, y: i32 }
+Shape :: enum { Dot, Square(i32) }
+square :: (n: i32) -> i32 { n * n }
+extern putchar :: (c: i32) -> i32;
+main :: () -> i32 {
+    x := square(3);
+    y : i32 = x + 1
+    p := Point { x: 1, y: 2 }
+    s := Shape::Square(2)
+    for i : 0..=3 { _ = putchar(i) }
+    #[main_loop] for { break }
+    arr := [(1,), (2, 3)]
+    arr[0].0 + p.x + y as i32
+}
+"
+    );
+}
+
+#[test]
+fn test_lines_are_indented_by_open_delimiters() {
+    let source = "main :: () {
+        total := sum(
+   1,
+  2 +
+3,
+         )
+  match total {
+      3 => println(total),
+  _ => {
+  println(0)
+  },
+        }
+}
+";
+
+    assert_eq!(
+        format_source(source).unwrap(),
+        "main :: () {
+    total := sum(
+        1,
+        2 +
+            3,
+    )
+    match total {
+        3 => println(total),
+        _ => {
+            println(0)
+        },
+    }
+}
+"
+    );
+}
+
+#[test]
+fn test_comments_and_blank_lines_are_kept() {
+    let source = "
+
+// Leading comment.
+/// Doc comment.
+f :: () {   // After code.\t
+
+
+    // In a block.
+
+    x := 1
+
+
+}
+
+
+
+// Trailing comment.";
+
+    assert_eq!(
+        format_source(source).unwrap(),
+        "// Leading comment.
+/// Doc comment.
+f :: () { // After code.
+    // In a block.
+
+    x := 1
+}
+
+// Trailing comment.
+"
+    );
+}
+
+#[test]
+fn test_formatting_keeps_tokens_and_is_idempotent() {
+    let source = "Pair::struct{a:i32,b:i32}
+main::()->i32{ p:=Pair{a:1,b:2} // comment
+   q := (n:i32)->i32{n*2}
+       q(p.a)+p.b }";
+    let formatted = format_source(source).unwrap();
+
+    let token_texts = |source_code| {
+        let (tokens, _) = lossless_tokens(source_code);
+
+        tokens.iter().map(|token| token.text).collect::<Vec<_>>()
+    };
+
+    assert_eq!(token_texts(&formatted), token_texts(source));
+    assert_eq!(format_source(&formatted).unwrap(), formatted);
+}
+
+#[test]
+fn test_lossless_tokens_make_up_the_source_code() {
+    let source = "  x := 1 // one\n\ny:=2\n// end\n";
+    let (tokens, trailing_trivia) = lossless_tokens(source);
+
+    let mut text: String = tokens
+        .iter()
+        .map(|token| format!("{}{}", token.leading_trivia, token.text))
+        .collect();
+    text.push_str(trailing_trivia);
+
+    assert_eq!(text, source);
+    assert_eq!(tokens[3].leading_trivia, " // one\n\n");
+    assert_eq!(trailing_trivia, "\n// end\n");
+    assert_eq!(format_source("").unwrap(), "");
+}
+
+#[test]
+fn test_code_that_doesnt_parse_isnt_formatted() {
+    let sources = [
+        "main :: () -> i32 { \"a b\" }",
+        "main :: () -> bool { 1 <= 2 }",
+        "main :: () -> i32 { /* block */ 0 }",
+        "main :: () -> i32 { (0 }",
+    ];
+
+    for source in sources {
+        let diagnostic = format_source(source).unwrap_err();
+
+        assert!(diagnostic.has_errors(), "{}", source);
+    }
+}
+
+#[test]
+fn test_scripts_are_formatted() {
+    assert_eq!(
+        format_source("x:=1\nprintln(x)").unwrap(),
+        "x := 1\nprintln(x)\n"
+    );
+}
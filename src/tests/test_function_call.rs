@@ -61,3 +61,41 @@ fn test_call_later_defined_function() {
         |"#,
     );
 }
+
+#[test]
+fn test_call_function_with_arguments() {
+    let program = compile(
+        r#"
+        |add :: (a: i32, b: i32) -> i32 {
+        |    b
+        |}
+        |
+        |main :: () -> i32 {
+        |    add(1, 2)
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |add:
+        |    push rbp
+        |    mov rbp, rsp
+        |    sub rsp, 4
+        |    mov DWORD PTR [rbp-4], esi   ; spill b, as a is never used
+        |    mov eax, DWORD PTR [rbp-4]
+        |    add rsp, 4
+        |    pop rbp
+        |    ret
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    mov edi, 1
+        |    mov esi, 2
+        |    call add
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
@@ -0,0 +1,207 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast_owned::Type;
+use crate::diagnostics::CompileError;
+use crate::tests::{check_errors, compile_errors, run, span};
+
+#[test]
+fn test_run_calls_through_bindings() {
+    let exit_code = run(r#"
+        |add :: (a: i32, b: i32) -> i32 {
+        |    a + b
+        |}
+        |
+        |main :: () -> i32 {
+        |    f := add;
+        |    g : (i32, i32) -> i32 = f;
+        |    f(1, 2) * 10 + g(3, 4)
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(37));
+}
+
+#[test]
+fn test_run_functions_passed_to_and_returned_from_functions() {
+    let exit_code = run(r#"
+        |double :: (x: i32) -> i32 { x * 2 }
+        |square :: (x: i32) -> i32 { x * x }
+        |
+        |twice :: (g: (i32) -> i32, x: i32) -> i32 {
+        |    g(g(x))
+        |}
+        |
+        |pick :: (first: i32) -> (i32) -> i32 {
+        |    if first { double } else { square }
+        |}
+        |
+        |main :: () -> i32 {
+        |    h := pick(0);
+        |    twice(double, 3) * 100 + twice(h, 2)
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(1216));
+}
+
+#[test]
+fn test_function_values_have_function_types() {
+    let errors = check_errors(
+        r#"
+        |add :: (a: i32, b: i32) -> i32 { a + b }
+        |main :: () -> i32 {
+        |    f := add;
+        |    x : i32 = f;
+        |    f(1);
+        |    n := 1;
+        |    n(2);
+        |    0
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Function {
+                    params: vec![Type::I32, Type::I32],
+                    return_type: Box::new(Type::I32),
+                },
+                span: span(89, 90),
+            },
+            CompileError::MismatchedCallArguments {
+                expected: vec![Type::I32, Type::I32],
+                found: vec![Type::I32],
+                span: span(96, 100),
+            },
+            CompileError::CallToNonFunction {
+                ty: Type::I32,
+                span: span(118, 122),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "mismatched types: expected `i32`, found `(i32, i32) -> i32`"
+    );
+}
+
+#[test]
+fn test_compiled_backends_reject_function_values() {
+    let errors = compile_errors(
+        r#"
+        |one :: () -> i32 { 1 }
+        |main :: () -> i32 {
+        |    f := one;
+        |    f()
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::FunctionValuesUnsupported { backend: "x86-64" }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "the x86-64 backend doesn't support function values yet; run the program with the \
+         interpreter instead"
+    );
+
+    let errors = compile_errors(
+        r#"
+        |apply :: (g: () -> i32) -> i32 { 0 }
+        |main :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::FunctionValuesUnsupported { backend: "x86-64" }]
+    );
+}
+
+#[test]
+fn test_run_closures_capturing_bindings_by_value() {
+    let exit_code = run(r#"
+        |make_adder :: (n: i32) -> (i32) -> i32 {
+        |    (x: i32) -> i32 { x + n }
+        |}
+        |
+        |main :: () -> i32 {
+        |    add_two := make_adder(2);
+        |    base := [10];
+        |    scaled := (x: i32) -> i32 { x * base[0] };
+        |    base[0] = 100;
+        |    twice := (x: i32) -> i32 { add_two(add_two(x)) };
+        |    k := 3;
+        |    nested := () -> () -> i32 {
+        |        inner := () -> i32 { k };
+        |        inner
+        |    };
+        |    get := nested();
+        |    scaled(twice(1)) * 10 + get()
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(503));
+}
+
+#[test]
+fn test_closures_are_type_checked_like_functions() {
+    let errors = check_errors(
+        r#"
+        |main :: () -> i32 {
+        |    n := 1;
+        |    f := (x: i32) -> i32 { {} };
+        |    g : () -> i32 = (x: i32) -> i32 { x + n };
+        |    f({})
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(59, 61),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::Function {
+                    params: vec![],
+                    return_type: Box::new(Type::I32),
+                },
+                found: Type::Function {
+                    params: vec![Type::I32],
+                    return_type: Box::new(Type::I32),
+                },
+                span: span(85, 110),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(118, 120),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_compiled_backends_reject_closures() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    f := () {};
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::FunctionValuesUnsupported { backend: "x86-64" }]
+    );
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use pretty_assertions::assert_eq;
+
+use crate::driver::{self, Options};
+use crate::host::{HermeticHost, Host};
+use crate::tests::strip_margin;
+
+#[test]
+fn test_hermetic_host_is_isolated_from_the_system() {
+    let host = HermeticHost { seed: 42 };
+
+    assert_eq!(host.random_u64(), 42);
+}
+
+#[test]
+fn test_output_does_not_depend_on_the_host() {
+    let source_code = strip_margin(
+        r#"
+        |add :: (a: i32, b: i32) -> i32 {
+        |    x := a + b;
+        |    x * 2
+        |}
+        |
+        |main :: () -> i32 {
+        |    for i : 0..add(1, 2) {
+        |        _ = add(i, i);
+        |    }
+        |    add(3, 4)
+        |}
+        |"#,
+    );
+
+    let compile_with_seed = |seed| {
+        let options = Options {
+            host: Some(Arc::new(HermeticHost { seed })),
+            ..Default::default()
+        };
+
+        match driver::compile(&source_code, &options) {
+            Ok(compiled_program) => compiled_program.code,
+            Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+        }
+    };
+
+    assert_eq!(compile_with_seed(0), compile_with_seed(0xdead_beef));
+    assert_eq!(
+        compile_with_seed(0),
+        driver::compile(&source_code, &Options::default())
+            .unwrap()
+            .code
+    );
+}
@@ -1,4 +1,10 @@
-use crate::tests::{check, compile};
+use pretty_assertions::assert_eq;
+
+use crate::ast_owned::Type;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::scanner::{BytePos, Span};
+use crate::tests::{check, compile, strip_margin};
 
 #[test]
 fn test_if_else_with_basic_expressions() {
@@ -20,16 +26,17 @@ fn test_if_else_with_basic_expressions() {
         |main:
         |    push rbp
         |    mov rbp, rsp
+        |    sub rsp, 4
         |    mov eax, 1
         |    cmp eax, 0
         |    je .L0
-        |    mov eax, 1
+        |    mov DWORD PTR [rbp-4], 1
         |    jmp .L1
-        |
         |.L0:
-        |    mov eax, 0
-        |
+        |    mov DWORD PTR [rbp-4], 0
         |.L1:
+        |    mov eax, DWORD PTR [rbp-4]
+        |    add rsp, 4
         |    pop rbp
         |    ret
         |"#,
@@ -56,21 +63,26 @@ fn test_if_else_inside_another() {
         |main:
         |    push rbp
         |    mov rbp, rsp
+        |    sub rsp, 8
         |    mov eax, 1
         |    cmp eax, 0
         |    je .L0
         |    mov eax, 2
         |    cmp eax, 0
         |    je .L1
-        |    mov eax, 3
+        |    mov DWORD PTR [rbp-4], 3
         |    jmp .L2
         |.L1:
-        |    mov eax, 4
+        |    mov DWORD PTR [rbp-4], 4
         |.L2:
+        |    mov eax, DWORD PTR [rbp-4]
+        |    mov DWORD PTR [rbp-8], eax
         |    jmp .L3
         |.L0:
-        |    mov eax, 0
+        |    mov DWORD PTR [rbp-8], 0
         |.L3:
+        |    mov eax, DWORD PTR [rbp-8]
+        |    add rsp, 8
         |    pop rbp
         |    ret
         |"#,
@@ -100,7 +112,6 @@ fn test_if_without_else() {
         |    mov eax, 1
         |    cmp eax, 0
         |    je .L0
-        |    mov eax, 1
         |.L0:
         |    mov eax, 0
         |    pop rbp
@@ -132,17 +143,17 @@ fn test_multi_exprs_inside_if_body() {
         |main:
         |    push rbp
         |    mov rbp, rsp
+        |    sub rsp, 4
         |    mov eax, 1
         |    cmp eax, 0
         |    je .L0
-        |    mov eax, 1
-        |    mov eax, 2
-        |    mov eax, 3
+        |    mov DWORD PTR [rbp-4], 3
         |    jmp .L1
         |.L0:
-        |    mov eax, 4
-        |    mov eax, 5
+        |    mov DWORD PTR [rbp-4], 5
         |.L1:
+        |    mov eax, DWORD PTR [rbp-4]
+        |    add rsp, 4
         |    pop rbp
         |    ret
         |"#,
@@ -173,29 +184,29 @@ fn test_chained_if_else() {
         |main:
         |    push rbp
         |    mov rbp, rsp
+        |    sub rsp, 4
         |    mov eax, 10
         |    cmp eax, 0
         |    je .L0
-        |    mov eax, 1
-        |    jmp .L3
-        |
+        |    mov DWORD PTR [rbp-4], 1
+        |    jmp .L1
         |.L0:
         |    mov eax, 20
         |    cmp eax, 0
-        |    je .L1
-        |    mov eax, 2
-        |    jmp .L3
-        |
-        |.L1:
-        |    mov eax, 30
-        |    cmp eax, 0
         |    je .L2
-        |    mov eax, 3
-        |    jmp .L3
-        |
+        |    mov DWORD PTR [rbp-4], 2
+        |    jmp .L1
         |.L2:
-        |    mov eax, 4
+        |    mov eax, 30
+        |    cmp eax, 0
+        |    je .L3
+        |    mov DWORD PTR [rbp-4], 3
+        |    jmp .L1
         |.L3:
+        |    mov DWORD PTR [rbp-4], 4
+        |.L1:
+        |    mov eax, DWORD PTR [rbp-4]
+        |    add rsp, 4
         |    pop rbp
         |    ret
         |"#,
@@ -206,14 +217,14 @@ fn test_chained_if_else() {
 fn test_chained_if_else_without_final_else() {
     let program = compile(
         r#"
-        |main :: () -> i32 {
+        |main :: () {
         |    if 10 {
         |        1
         |    } else if 20 {
         |        2
         |    } else if 30 {
         |        3
-        |    }
+        |    };
         |}
         |"#,
     );
@@ -227,26 +238,136 @@ fn test_chained_if_else_without_final_else() {
         |    mov eax, 10
         |    cmp eax, 0
         |    je .L0
-        |    mov eax, 1
-        |    jmp .L2
-        |
+        |    jmp .L1
         |.L0:
         |    mov eax, 20
         |    cmp eax, 0
+        |    je .L2
+        |    jmp .L1
+        |.L2:
+        |    mov eax, 30
+        |    cmp eax, 0
         |    je .L1
-        |    mov eax, 2
-        |    jmp .L2
-        |
         |.L1:
-        |    mov eax, 30
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_bind_value_of_if_else() {
+    let program = compile(
+        r#"
+        |main :: () -> i32 {
+        |    x := if 0 {
+        |        1
+        |    } else if 2 {
+        |        3
+        |    } else {
+        |        4
+        |    };
+        |    x + 5
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    sub rsp, 4
+        |    mov eax, 0
+        |    cmp eax, 0
+        |    je .L0
+        |    mov DWORD PTR [rbp-4], 1
+        |    jmp .L1
+        |.L0:
+        |    mov eax, 2
         |    cmp eax, 0
         |    je .L2
-        |    mov eax, 3
-        |    jmp .L2
-        |
+        |    mov DWORD PTR [rbp-4], 3
+        |    jmp .L1
         |.L2:
+        |    mov DWORD PTR [rbp-4], 4
+        |.L1:
+        |    mov eax, DWORD PTR [rbp-4]
+        |    add eax, 5
+        |    add rsp, 4
         |    pop rbp
         |    ret
         |"#,
     );
 }
+
+#[test]
+fn test_branches_that_leave_a_loop_agree_with_any_type() {
+    let execution = driver::run(
+        strip_margin(
+            r#"
+            |main :: () -> i32 {
+            |    sum := [0];
+            |    for x : 0..10 {
+            |        y := if x - 4 { x } else { break; };
+            |        z := if x - 1 {
+            |            if x - 3 { continue; } else { 100 }
+            |        } else if x {
+            |            y
+            |        } else {
+            |            continue;
+            |        };
+            |        sum[0] = sum[0] + y + z;
+            |    }
+            |    sum[0]
+            |}
+            |"#,
+        ),
+        &Options::default(),
+    )
+    .unwrap();
+
+    assert_eq!(execution.exit_code, Ok(105));
+}
+
+#[test]
+fn test_if_without_else_has_no_value() {
+    let errors = driver::check(
+        strip_margin(
+            r#"
+            |main :: () {
+            |    x := if 1 { 2 };
+            |    y := if 1 { {} } else if 2 { 3 };
+            |    if 1 { 4 };
+            |}
+            |"#,
+        ),
+        &Options::default(),
+    )
+    .errors;
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MissingElseBranch {
+                ty: Type::I32,
+                span: Span {
+                    start: BytePos(29),
+                    end: BytePos(30),
+                },
+            },
+            CompileError::MissingElseBranch {
+                ty: Type::I32,
+                span: Span {
+                    start: BytePos(67),
+                    end: BytePos(68),
+                },
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "`if` without an `else` branch can't have a value of type `i32`"
+    );
+}
@@ -0,0 +1,247 @@
+use pretty_assertions::assert_eq;
+
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::incremental::{decl_span, IncrementalParse, Reparse, TextEdit};
+use crate::scanner::{BytePos, Span};
+use crate::structure::render_ast;
+use crate::tests::{span, strip_margin};
+
+fn edit_of(source_code: &str, old_text: &str, new_text: &str) -> TextEdit {
+    let start = source_code
+        .find(old_text)
+        .unwrap_or_else(|| panic!("no `{}` to edit", old_text));
+
+    TextEdit {
+        range: Span {
+            start: BytePos(start),
+            end: BytePos(start + old_text.len()),
+        },
+        new_text: new_text.to_owned(),
+    }
+}
+
+/// Asserts that the incremental parse is the one that parsing its source code
+/// from scratch gives, spans included, which the spans of the diagnostics
+/// about it tell.
+fn assert_same_as_full_parse(ctx: &CompilerContext, parse: &IncrementalParse) {
+    let options = Options::default();
+    let full_ctx = CompilerContext::new(parse.source_code().to_owned());
+    let full_program = driver::parse(&full_ctx, &options).unwrap();
+    let program = parse.program().unwrap();
+
+    assert_eq!(
+        render_ast(ctx, program),
+        render_ast(&full_ctx, full_program)
+    );
+
+    let diagnostic = driver::check_program(ctx, program, &options);
+    let full_diagnostic = driver::check_program(&full_ctx, full_program, &options);
+
+    assert_eq!(diagnostic.errors, full_diagnostic.errors);
+    assert_eq!(diagnostic.warnings, full_diagnostic.warnings);
+}
+
+fn three_decls() -> String {
+    strip_margin(
+        r#"
+        |a :: () -> i32 {
+        |    1
+        |}
+        |b :: () -> i32 {
+        |    2
+        |}
+        |main :: () -> i32 {
+        |    a() + b() + x
+        |}
+        |"#,
+    )
+}
+
+#[test]
+fn test_edit_reparses_only_touched_decl() {
+    let source_code = three_decls();
+    let ctx = CompilerContext::new(source_code.clone());
+    let mut parse = IncrementalParse::new(&ctx, &Options::default());
+
+    let reparse = parse.apply_edit(&edit_of(&source_code, "    2", "    20 * 3"));
+
+    assert_eq!(
+        reparse,
+        Reparse::Incremental {
+            reparsed_decls: 1,
+            reused_decls: 1,
+            moved_decls: 1,
+        }
+    );
+    assert_same_as_full_parse(&ctx, &parse);
+
+    let diagnostic = driver::check_program(&ctx, parse.program().unwrap(), &Options::default());
+
+    // The error is in the declaration after the edit, which was moved rather
+    // than parsed again.
+    assert_eq!(
+        diagnostic.errors,
+        vec![CompileError::UndefinedName {
+            name: "x".to_owned(),
+            span: Span {
+                start: BytePos(91),
+                end: BytePos(92),
+            },
+            suggestion: None,
+        }]
+    );
+}
+
+#[test]
+fn test_edit_between_decls_parses_new_decl() {
+    let source_code = three_decls();
+    let ctx = CompilerContext::new(source_code.clone());
+    let mut parse = IncrementalParse::new(&ctx, &Options::default());
+
+    let reparse = parse.apply_edit(&edit_of(&source_code, "b ::", "x :: () -> i32 { 3 }\nb ::"));
+
+    assert_eq!(
+        reparse,
+        Reparse::Incremental {
+            reparsed_decls: 2,
+            reused_decls: 1,
+            moved_decls: 1,
+        }
+    );
+    assert_same_as_full_parse(&ctx, &parse);
+}
+
+#[test]
+fn test_successive_edits_stay_in_sync() {
+    let ctx = CompilerContext::new(three_decls());
+    let mut parse = IncrementalParse::new(&ctx, &Options::default());
+
+    for (old_text, new_text) in [
+        ("1", "100"),
+        ("b()", "b() * a()"),
+        ("+ x", "- 4"),
+        ("a ::", "aa ::"),
+    ] {
+        let edit = edit_of(parse.source_code(), old_text, new_text);
+
+        assert!(matches!(
+            parse.apply_edit(&edit),
+            Reparse::Incremental { .. }
+        ));
+        assert_same_as_full_parse(&ctx, &parse);
+    }
+}
+
+#[test]
+fn test_edit_that_breaks_decl_reparses_fully() {
+    let source_code = three_decls();
+    let ctx = CompilerContext::new(source_code.clone());
+    let mut parse = IncrementalParse::new(&ctx, &Options::default());
+
+    let reparse = parse.apply_edit(&edit_of(&source_code, "x\n}", "x\n"));
+
+    assert_eq!(reparse, Reparse::Full);
+
+    let Err(diagnostic) = parse.program() else {
+        panic!("program parsed unexpectedly");
+    };
+
+    assert!(matches!(
+        diagnostic.errors.as_slice(),
+        [CompileError::UnclosedDelimiter { .. }]
+    ));
+
+    // Once it's fixed, the program is parsed in full again, as there's no
+    // parse to reuse declarations from.
+    let edit = edit_of(parse.source_code(), "x\n", "x\n}");
+
+    assert_eq!(parse.apply_edit(&edit), Reparse::Full);
+    assert_same_as_full_parse(&ctx, &parse);
+}
+
+#[test]
+fn test_edit_between_texts() {
+    let edit = TextEdit::between("a :: () { 1 }", "a :: () { 12 }");
+
+    assert_eq!((edit.range, edit.new_text.as_str()), (span(11, 11), "2"));
+
+    // What the texts have alike can't end in the middle of a char.
+    let edit = TextEdit::between("// é\n", "// è\n");
+
+    assert_eq!((edit.range, edit.new_text.as_str()), (span(3, 5), "è"));
+
+    // Nor can what they start and end with alike overlap.
+    let edit = TextEdit::between("aaa", "aaaa");
+
+    assert_eq!((edit.range, edit.new_text.as_str()), (span(3, 3), "a"));
+
+    let edit = TextEdit::between("same", "same");
+
+    assert_eq!((edit.range, edit.new_text.as_str()), (span(4, 4), ""));
+}
+
+#[test]
+fn test_extern_decls_are_touched_by_edits_of_their_keyword() {
+    let source_code = strip_margin(
+        r#"
+        |extern puts :: (n: i32) -> i32;
+        |main :: () -> i32 { 0 }
+        |"#,
+    );
+    let ctx = CompilerContext::new(source_code.clone());
+    let mut parse = IncrementalParse::new(&ctx, &Options::default());
+
+    // Without its `extern`, the declaration has no body.
+    let reparse = parse.apply_edit(&edit_of(&source_code, "extern ", ""));
+
+    assert_eq!(reparse, Reparse::Full);
+    assert!(parse.program().is_err());
+}
+
+#[test]
+fn test_decls_after_edits_are_moved_once_the_program_is_asked_for() {
+    let ctx = CompilerContext::new(three_decls());
+    let mut parse = IncrementalParse::new(&ctx, &Options::default());
+
+    for (old_text, new_text) in [("    1", "    100"), ("100", "1000")] {
+        let edit = edit_of(parse.source_code(), old_text, new_text);
+
+        assert_eq!(
+            parse.apply_edit(&edit),
+            Reparse::Incremental {
+                reparsed_decls: 1,
+                reused_decls: 0,
+                moved_decls: 2,
+            }
+        );
+    }
+
+    // Where the declarations are is known before they're moved.
+    let decl_spans: Vec<_> = (parse.decls().unwrap().iter())
+        .map(|moved_decl| moved_decl.span())
+        .collect();
+
+    let full_ctx = CompilerContext::new(parse.source_code().to_owned());
+    let full_program = driver::parse(&full_ctx, &Options::default()).unwrap();
+
+    assert_eq!(
+        decl_spans,
+        (full_program.decls.iter())
+            .map(decl_span)
+            .collect::<Vec<_>>()
+    );
+
+    let allocated_bytes = ctx.allocated_bytes();
+    let program = parse.program().unwrap();
+
+    assert!(ctx.allocated_bytes() > allocated_bytes);
+
+    // Both edits moved them once, and only once.
+    let allocated_bytes = ctx.allocated_bytes();
+
+    assert!(std::ptr::eq(program.decls, parse.program().unwrap().decls));
+    assert_eq!(ctx.allocated_bytes(), allocated_bytes);
+    assert_same_as_full_parse(&ctx, &parse);
+}
@@ -0,0 +1,304 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast::BinaryOp;
+use crate::ast_owned::Type;
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::interp::RuntimeError;
+use crate::layout::Layouts;
+use crate::profile::{IntProfile, IntWidth, Overflow};
+use crate::tests::{check_errors, compile, compile_errors, run_with_profile, span, strip_margin};
+
+#[test]
+fn test_run_integers_of_other_types() {
+    let exit_code = run_with_profile(
+        r#"
+        |scale :: (x: i64) -> i64 {
+        |    x * 1000000
+        |}
+        |
+        |main :: () -> i32 {
+        |    big : i64 = 3000000;
+        |    trillions := scale(big) / 1000000000000i64;
+        |    byte := 200u8 + 55u8;
+        |    wide := byte + 1u16;
+        |    result : i32 = wide;
+        |    if trillions - 3 { 0 } else { result }
+        |}
+        |"#,
+        IntProfile::default(),
+    );
+
+    assert_eq!(exit_code, Ok(256));
+}
+
+#[test]
+fn test_integers_overflow_at_the_width_of_their_type() {
+    let source_code = r#"
+        |main :: () -> i32 {
+        |    byte := 200u8 + 100u8;
+        |    max := 18446744073709551615u64;
+        |    zero := max + 1u8;
+        |    result : i32 = byte;
+        |    if zero { 0 } else { result }
+        |}
+        |"#;
+
+    assert_eq!(run_with_profile(source_code, IntProfile::default()), Ok(44));
+
+    let trapping = IntProfile {
+        overflow: Overflow::Trap,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        run_with_profile(source_code, trapping),
+        Err(RuntimeError::Overflow {
+            op: BinaryOp::Add,
+            span: span(32, 45),
+        })
+    );
+}
+
+#[test]
+fn test_integers_only_widen_implicitly() {
+    let errors = check_errors(
+        r#"
+        |half :: (x: i32) -> i32 { x / 2 }
+        |main :: () {
+        |    a : u8 = 1;
+        |    b := 1u8 + 1i8;
+        |    c := half(1i64);
+        |    d : usize = 1u64;
+        |    e : i64 = 1u32;
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MismatchedTypes {
+                expected: Type::U8,
+                found: Type::I32,
+                span: span(60, 61),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::U8,
+                found: Type::I8,
+                span: span(78, 81),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::I64,
+                span: span(97, 101),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::Usize,
+                found: Type::U64,
+                span: span(120, 124),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "mismatched types: expected `u8`, found `i32`"
+    );
+}
+
+#[test]
+fn test_integer_constants_must_fit_their_suffix() {
+    let errors = check_errors(
+        r#"
+        |main :: () {
+        |    a := 42f32;
+        |    b := 256u8;
+        |    c := 255u8;
+        |    d := 99999999999;
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::InvalidIntegerSuffix {
+                suffix: "f32".to_owned(),
+                span: span(22, 27),
+            },
+            CompileError::IntegerConstantTooLarge {
+                ty: Type::U8,
+                span: span(38, 43),
+            },
+            CompileError::IntegerConstantTooLarge {
+                ty: Type::I32,
+                span: span(70, 81),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "invalid suffix `f32` for an integer constant"
+    );
+    assert_eq!(
+        errors[1].to_string(),
+        "integer constant doesn't fit in `u8`"
+    );
+}
+
+#[test]
+fn test_integers_in_structs_are_laid_out_by_their_width() {
+    let context = CompilerContext::new(strip_margin(
+        r#"
+        |Header :: struct { kind: u8, length: u64, flags: i16, count: u32 }
+        |"#,
+    ));
+    let program = driver::parse(&context, &Options::default()).unwrap();
+
+    let layouts = Layouts::of_program(&context, program, IntWidth::Bits16).unwrap();
+    let layout = layouts.get_struct("Header").unwrap();
+
+    assert_eq!(
+        layout
+            .fields
+            .iter()
+            .map(|field| field.offset)
+            .collect::<Vec<_>>(),
+        vec![0, 8, 16, 20]
+    );
+    assert_eq!((layout.size, layout.align), (24, 8));
+}
+
+#[test]
+fn test_compiled_backends_reject_other_integer_types() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    x := 1u8;
+        |    0
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::IntTypesUnsupported { backend: "x86-64" }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "the x86-64 backend only supports integers of type `i32` yet; run the program with the \
+         interpreter instead"
+    );
+
+    let errors = compile_errors(
+        r#"
+        |wide :: (x: i64) -> i32 { 0 }
+        |main :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::IntTypesUnsupported { backend: "x86-64" }]
+    );
+}
+
+#[test]
+fn test_run_casts_between_integer_types() {
+    let source_code = r#"
+        |main :: () -> i32 {
+        |    big := 300;
+        |    byte := big as u8;
+        |    all_ones := (0 - 1) as u16;
+        |    signed := 200u8 as i8;
+        |    wide := signed as i64 * 1000000000000i64;
+        |    product := (wide / 1000000000000i64) as i32;
+        |    byte as i32 + all_ones as i32 % 1000 + product
+        |}
+        |"#;
+
+    assert_eq!(
+        run_with_profile(source_code, IntProfile::default()),
+        Ok(523)
+    );
+
+    // Casts truncate rather than trap, whatever the profile says.
+    let trapping = IntProfile {
+        overflow: Overflow::Trap,
+        ..Default::default()
+    };
+
+    assert_eq!(run_with_profile(source_code, trapping), Ok(523));
+}
+
+#[test]
+fn test_only_integers_can_be_cast() {
+    let errors = check_errors(
+        r#"
+        |Point :: struct { x: i32 }
+        |main :: () -> i32 {
+        |    p := Point { x: 1 };
+        |    _ = p as i32;
+        |    _ = p.x as (i32, i32);
+        |    0
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::InvalidCast {
+                from: Type::Named("Point".to_owned()),
+                to: Type::I32,
+                span: span(80, 88),
+            },
+            CompileError::InvalidCast {
+                from: Type::I32,
+                to: Type::Tuple(vec![Type::I32, Type::I32]),
+                span: span(98, 115),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "cannot cast a value of type `Point` to `i32`"
+    );
+}
+
+#[test]
+fn test_casts_to_i32_of_i32_compile_to_nothing() {
+    assert_eq!(
+        compile(
+            r#"
+            |main :: () -> i32 {
+            |    x := 7;
+            |    x as i32 * 2
+            |}
+            |"#
+        ),
+        compile(
+            r#"
+            |main :: () -> i32 {
+            |    x := 7;
+            |    x * 2
+            |}
+            |"#
+        )
+    );
+
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    x := 7;
+        |    (x as u8) as i32
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::IntTypesUnsupported { backend: "x86-64" }]
+    );
+}
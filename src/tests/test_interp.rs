@@ -0,0 +1,363 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast::BinaryOp;
+use crate::interp::RuntimeError;
+use crate::tests::{run, span};
+use crate::vm::ExecutionLimit;
+
+#[test]
+fn test_main_returning_unit_exits_with_zero() {
+    let exit_code = run(r#"
+        |main :: () {}
+        |"#);
+
+    assert_eq!(exit_code, Ok(0));
+}
+
+#[test]
+fn test_main_return_value_is_exit_code() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    42
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(42));
+}
+
+#[test]
+fn test_arithmetic_respects_precedence_and_associativity() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    2 + 3 * 4 - 10 / 5 - 7 % 4
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(9));
+}
+
+#[test]
+fn test_bindings_and_inner_scopes() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    x := 1;
+        |    y := {
+        |        x := 10;
+        |        x + 1
+        |    };
+        |    x + y
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(12));
+}
+
+#[test]
+fn test_if_else_chain() {
+    let exit_code = run(r#"
+        |classify :: (a: i32, b: i32) -> i32 {
+        |    if a {
+        |        1
+        |    } else if b {
+        |        2
+        |    } else {
+        |        3
+        |    }
+        |}
+        |
+        |main :: () -> i32 {
+        |    classify(1, 0) * 100 + classify(0, 1) * 10 + classify(0, 0)
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(123));
+}
+
+#[test]
+fn test_exclusive_range_stops_before_end() {
+    let exit_code = run(r#"
+        |main :: () {
+        |    for i : 0..3 {
+        |        _ = 10 / (i - 3);
+        |    }
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(0));
+}
+
+#[test]
+fn test_range_steps_by_its_step() {
+    let exit_code = run(r#"
+        |five :: () -> i32 { 5 }
+        |main :: () -> i32 {
+        |    sums := [0, 0];
+        |    for i : 0..10 step 3 {
+        |        sums[0] = sums[0] + i;
+        |    }
+        |    for i : 1..=11 step five() {
+        |        sums[1] = sums[1] + i;
+        |    }
+        |    sums[0] * 100 + sums[1]
+        |}
+        |"#);
+
+    // 0 + 3 + 6 + 9 and 1 + 6 + 11.
+    assert_eq!(exit_code, Ok(1818));
+}
+
+#[test]
+fn test_range_stops_before_stepping_past_the_largest_i32() {
+    let exit_code = run(r#"
+        |largest :: () -> i32 { 2147483647 }
+        |main :: () -> i32 {
+        |    counts := [0, 0, 0];
+        |    for i : 0..=2147483647 step 1000000000 {
+        |        counts[0] = counts[0] + 1;
+        |    } else {
+        |        counts[0] = counts[0] + 10;
+        |    }
+        |    for i : 2147483640..=largest() step largest() {
+        |        counts[1] = counts[1] + 1;
+        |    }
+        |    for i : rev 0 - largest() - 1..=largest() step 1000000000 {
+        |        counts[2] = counts[2] + 1;
+        |    }
+        |    counts[0] * 100 + counts[1] * 10 + counts[2]
+        |}
+        |"#);
+
+    // The first loop runs three times before completing, the second once, and
+    // the third five times, down from the largest `i32`.
+    assert_eq!(exit_code, Ok(1315));
+}
+
+#[test]
+fn test_range_fails_to_step_by_zero() {
+    let exit_code = run(r#"
+        |zero :: () -> i32 { 0 }
+        |main :: () -> i32 {
+        |    for i : 0..10 step zero() {}
+        |    1
+        |}
+        |"#);
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::NonPositiveStep { span: span(67, 73) })
+    );
+}
+
+#[test]
+fn test_range_fails_to_step_by_negative_amount() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    for i : rev 0..10 step 0 - 1 {}
+        |    1
+        |}
+        |"#);
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::NonPositiveStep { span: span(47, 52) })
+    );
+}
+
+#[test]
+fn test_reversed_range_counts_down_to_its_start() {
+    let exit_code = run(r#"
+        |four :: () -> i32 { 4 }
+        |smallest :: () -> i32 { 0 - 2147483647 - 1 }
+        |main :: () -> i32 {
+        |    digits := [0, 0, 0];
+        |    for i : rev 1..4 {
+        |        digits[0] = digits[0] * 10 + i;
+        |    }
+        |    for i : rev 1..=9 step four() {
+        |        digits[1] = digits[1] * 10 + i;
+        |    }
+        |    for i : rev smallest()..=smallest() + 2 step 2 {
+        |        digits[2] = digits[2] * 10 + i - smallest() + 1;
+        |    }
+        |    for i : rev 3..3 {
+        |        digits[0] = 0;
+        |    }
+        |    digits[0] * 100000 + digits[1] * 100 + digits[2]
+        |}
+        |"#);
+
+    // 3, 2, 1, then 9, 5, 1, and the last loop stops at the smallest `i32`
+    // instead of going past it.
+    assert_eq!(exit_code, Ok(32195131));
+}
+
+#[test]
+fn test_step_and_rev_name_bindings_outside_ranges() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    step := 2;
+        |    rev := 1;
+        |    total := [0];
+        |    for i : rev..6 step step {
+        |        total[0] = total[0] + i;
+        |    }
+        |    for i : rev rev..=step {
+        |        total[0] = total[0] * 10 + i;
+        |    }
+        |    total[0]
+        |}
+        |"#);
+
+    // 1 + 3 + 5, then 2 and 1.
+    assert_eq!(exit_code, Ok(921));
+}
+
+#[test]
+fn test_inclusive_range_reaches_end() {
+    let exit_code = run(r#"
+        |main :: () {
+        |    for i : 0..=3 {
+        |        _ = 10 / (i - 3);
+        |    }
+        |}
+        |"#);
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::DivisionByZero { span: span(45, 57) })
+    );
+}
+
+#[test]
+fn test_break_skips_else_branch() {
+    let exit_code = run(r#"
+        |main :: () {
+        |    for i : 0..10 {
+        |        if i - 5 {
+        |            continue
+        |        }
+        |        break
+        |    } else {
+        |        _ = 1 / 0;
+        |    }
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(0));
+}
+
+#[test]
+fn test_completed_loop_runs_else_branch() {
+    let exit_code = run(r#"
+        |main :: () {
+        |    for 0 {
+        |    } else {
+        |        _ = 1 / 0;
+        |    }
+        |}
+        |"#);
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::DivisionByZero { span: span(50, 55) })
+    );
+}
+
+#[test]
+fn test_recursive_calls() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    fib(10)
+        |}
+        |
+        |fib :: (n: i32) -> i32 {
+        |    if n - 1 {
+        |        if n {
+        |            fib(n - 1) + fib(n - 2)
+        |        } else {
+        |            0
+        |        }
+        |    } else {
+        |        1
+        |    }
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(55));
+}
+
+#[test]
+fn test_mutually_recursive_calls_to_later_decls() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    is_even(10) * 10 + is_odd(7)
+        |}
+        |
+        |is_even :: (n: i32) -> i32 {
+        |    if n { is_odd(n - 1) } else { 1 }
+        |}
+        |
+        |is_odd :: (n: i32) -> i32 {
+        |    if n { is_even(n - 1) } else { 0 }
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(11));
+}
+
+#[test]
+fn test_recursion_nests_thousands_of_calls() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    depth(5000)
+        |}
+        |
+        |depth :: (n: i32) -> i32 {
+        |    if n { depth(n - 1) + 1 } else { 0 }
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(5000));
+}
+
+#[test]
+fn test_recursion_past_call_depth_limit() {
+    let exit_code = run(r#"
+        |main :: () {
+        |    main()
+        |}
+        |"#);
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::ExecutionLimitExceeded {
+            limit: ExecutionLimit::CallDepth(10_000),
+        })
+    );
+}
+
+#[test]
+fn test_division_overflow() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    (0 - 2147483647 - 1) / (0 - 1)
+        |}
+        |"#);
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::Overflow {
+            op: BinaryOp::Div,
+            span: span(24, 54),
+        })
+    );
+}
+
+#[test]
+fn test_missing_main() {
+    let exit_code = run(r#"
+        |foo :: () {}
+        |"#);
+
+    assert_eq!(exit_code, Err(RuntimeError::MissingMain));
+}
@@ -0,0 +1,184 @@
+use crate::driver::{self, Emit, Options};
+use crate::tests::{check, strip_margin};
+
+/// Lowers the program to the IR, leaving out the blank lines between
+/// functions.
+fn emit_ir(source_code: &str) -> String {
+    let options = Options {
+        emit: Emit::Ir,
+        ..Default::default()
+    };
+
+    match driver::compile(strip_margin(source_code), &options) {
+        Ok(compiled_program) => compiled_program
+            .code
+            .lines()
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+#[test]
+fn test_branches_pass_their_values_to_the_join_block() {
+    let ir = emit_ir(
+        r#"
+        |classify :: (a: i32, u: (), b: i32) -> i32 {
+        |    if a {
+        |        1
+        |    } else if b {
+        |        2
+        |    } else {
+        |        3
+        |    }
+        |}
+        |
+        |main :: () -> i32 {
+        |    x := classify(1, {}, 0);
+        |    if x {
+        |        _ = x * 2;
+        |    }
+        |    x + 1
+        |}
+        |"#,
+    );
+
+    check(
+        ir,
+        r#"
+        |fn classify(i32, i32) -> i32 {
+        |block0(v0, v1):
+        |    branch v0, block1, block2
+        |block1:
+        |    v2 = const 1
+        |    jump block5(v2)
+        |block2:
+        |    branch v1, block3, block4
+        |block3:
+        |    v3 = const 2
+        |    jump block5(v3)
+        |block4:
+        |    v4 = const 3
+        |    jump block5(v4)
+        |block5(v5):
+        |    return v5
+        |}
+        |fn main() -> i32 {
+        |block0:
+        |    v0 = const 1
+        |    v1 = const 0
+        |    v2 = call classify(v0, v1)
+        |    branch v2, block1, block2
+        |block1:
+        |    v3 = const 2
+        |    v4 = mul v2, v3
+        |    jump block2
+        |block2:
+        |    v5 = const 1
+        |    v6 = add v2, v5
+        |    return v6
+        |}
+        |"#,
+    );
+}
+
+#[test]
+fn test_loop_variable_is_a_header_parameter() {
+    let ir = emit_ir(
+        r#"
+        |main :: () {
+        |    n := 10;
+        |    for i : 0..n {
+        |        if i % 2 {
+        |            continue
+        |        }
+        |        _ = 100 / i;
+        |        break;
+        |        _ = 1;
+        |    } else {
+        |        _ = 0;
+        |    }
+        |}
+        |"#,
+    );
+
+    check(
+        ir,
+        r#"
+        |fn main() {
+        |block0:
+        |    v0 = const 10
+        |    v1 = const 0
+        |    jump block1(v1)
+        |block1(v2):
+        |    v3 = lt v2, v0
+        |    branch v3, block2, block6
+        |block2:
+        |    v4 = const 2
+        |    v5 = rem v2, v4
+        |    branch v5, block3, block5
+        |block3:
+        |    v6 = const 1
+        |    v7 = add_overflows v2, v6
+        |    branch v7, block6, block4
+        |block4:
+        |    v8 = add v2, v6
+        |    jump block1(v8)
+        |block5:
+        |    v9 = const 100
+        |    v10 = div v9, v2
+        |    jump block7
+        |block6:
+        |    v11 = const 0
+        |    jump block7
+        |block7:
+        |    return
+        |}
+        |"#,
+    );
+}
+
+#[test]
+fn test_code_after_infinite_loop_is_dropped() {
+    let ir = emit_ir(
+        r#"
+        |main :: () -> i32 {
+        |    for n : 0..=3 {
+        |        for {
+        |            continue
+        |        }
+        |        _ = n;
+        |    }
+        |    for 1 {
+        |        break
+        |    }
+        |    for {
+        |    }
+        |    42
+        |}
+        |"#,
+    );
+
+    check(
+        ir,
+        r#"
+        |fn main() -> i32 {
+        |block0:
+        |    v0 = const 0
+        |    v1 = const 3
+        |    jump block1(v0)
+        |block1(v2):
+        |    v3 = le v2, v1
+        |    branch v3, block2, block3
+        |block2:
+        |    jump block2
+        |block3:
+        |    v4 = const 1
+        |    branch v4, block4, block4
+        |block4:
+        |    jump block4
+        |}
+        |"#,
+    );
+}
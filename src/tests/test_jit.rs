@@ -0,0 +1,268 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast::BinaryOp;
+use crate::driver::{self, Options};
+use crate::interp::RuntimeError;
+use crate::tests::{span, strip_margin};
+
+/// Runs the program both compiled and interpreted, which must agree.
+fn run_jit(source_code: &str) -> Result<i32, RuntimeError> {
+    let source_code = strip_margin(source_code);
+
+    let exit_code = match driver::run_jit(&source_code, &Options::default()) {
+        Ok(execution) => execution.exit_code,
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    };
+
+    let interpreted_exit_code = driver::run(&source_code, &Options::default())
+        .unwrap()
+        .exit_code;
+    assert_eq!(exit_code, interpreted_exit_code);
+
+    exit_code
+}
+
+#[test]
+fn test_main_returning_unit_exits_with_zero() {
+    let exit_code = run_jit(
+        r#"
+        |main :: () {}
+        |"#,
+    );
+
+    assert_eq!(exit_code, Ok(0));
+}
+
+#[test]
+fn test_arithmetic_wraps_around() {
+    let exit_code = run_jit(
+        r#"
+        |main :: () -> i32 {
+        |    x := 2147483647 + 1;
+        |    x * 2 + 2 + 3 * 4 - 10 / 5 - 7 % 4
+        |}
+        |"#,
+    );
+
+    assert_eq!(exit_code, Ok(9));
+}
+
+#[test]
+fn test_bindings_of_unit_values() {
+    let exit_code = run_jit(
+        r#"
+        |nothing :: (unit: ()) {
+        |    unit
+        |}
+        |
+        |main :: () -> i32 {
+        |    x := nothing({});
+        |    y := {
+        |        nothing(x);
+        |        x := 10;
+        |        x + 1
+        |    };
+        |    y
+        |}
+        |"#,
+    );
+
+    assert_eq!(exit_code, Ok(11));
+}
+
+#[test]
+fn test_loops_with_break_and_continue() {
+    let exit_code = run_jit(
+        r#"
+        |main :: () -> i32 {
+        |    for i : 0..=10 {
+        |        if i % 2 {
+        |            continue
+        |        }
+        |        for j : i..i + 3 {
+        |            if j - i {
+        |                break
+        |            }
+        |            _ = 10 / (j - i - 2);
+        |        }
+        |        _ = 10 / (i - 7);
+        |    } else {
+        |        _ = 10 / 0;
+        |    }
+        |    1
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::DivisionByZero {
+            span: span(272, 278)
+        })
+    );
+}
+
+#[test]
+fn test_range_stops_before_stepping_past_the_largest_i32() {
+    let exit_code = run_jit(
+        r#"
+        |main :: () -> i32 {
+        |    for i : 0..=2147483647 step 1000000000 {}
+        |    for i : rev 0 - 2147483647 - 1..=2147483647 step 1000000000 {}
+        |    for i : 2147483640..=2147483647 step 2147483647 {} else {
+        |        _ = 10 / 0;
+        |    }
+        |    1
+        |}
+        |"#,
+    );
+
+    // Only a loop that runs to completion gets to its else-branch.
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::DivisionByZero {
+            span: span(207, 213)
+        })
+    );
+}
+
+#[test]
+fn test_range_fails_to_step_by_zero_or_less() {
+    let exit_code = run_jit(
+        r#"
+        |zero :: () -> i32 { 0 }
+        |main :: () -> i32 {
+        |    for i : 0..10 step zero() {}
+        |    1
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::NonPositiveStep { span: span(67, 73) })
+    );
+
+    let exit_code = run_jit(
+        r#"
+        |main :: () -> i32 {
+        |    for i : rev 0..10 step 0 - 1 {}
+        |    1
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::NonPositiveStep { span: span(47, 52) })
+    );
+}
+
+#[test]
+fn test_recursive_calls() {
+    let exit_code = run_jit(
+        r#"
+        |main :: () -> i32 {
+        |    fib(20)
+        |}
+        |
+        |fib :: (n: i32) -> i32 {
+        |    if n - 1 {
+        |        if n {
+        |            fib(n - 1) + fib(n - 2)
+        |        } else {
+        |            0
+        |        }
+        |    } else {
+        |        1
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(exit_code, Ok(6765));
+}
+
+#[test]
+fn test_calls_to_builtins() {
+    let exit_code = run_jit(
+        r#"
+        |main :: () -> i32 {
+        |    for i : 0..3 {
+        |        println(i);
+        |    }
+        |    print(7);
+        |    7
+        |}
+        |"#,
+    );
+
+    assert_eq!(exit_code, Ok(7));
+}
+
+#[test]
+fn test_error_in_callee_stops_the_program() {
+    let exit_code = run_jit(
+        r#"
+        |divide :: (a: i32, b: i32) -> i32 {
+        |    a / b
+        |}
+        |
+        |main :: () -> i32 {
+        |    for i : 0..=3 {
+        |        _ = divide(10, i - 3);
+        |    }
+        |    1
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::DivisionByZero { span: span(40, 45) })
+    );
+}
+
+#[test]
+fn test_division_overflow() {
+    let exit_code = run_jit(
+        r#"
+        |main :: () -> i32 {
+        |    (0 - 2147483647 - 1) % (0 - 1)
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::Overflow {
+            op: BinaryOp::Rem,
+            span: span(24, 54),
+        })
+    );
+}
+
+#[test]
+fn test_missing_main() {
+    let exit_code = run_jit(
+        r#"
+        |foo :: () {}
+        |"#,
+    );
+
+    assert_eq!(exit_code, Err(RuntimeError::MissingMain));
+}
+
+#[test]
+fn test_extern_functions_are_looked_up_in_the_process() {
+    let exit_code = run_jit(
+        r#"
+        |extern abs :: (n: i32) -> i32;
+        |main :: () -> i32 {
+        |    abs(0 - 7) + abs(3)
+        |}
+        |"#,
+    );
+
+    assert_eq!(exit_code, Ok(10));
+}
@@ -0,0 +1,79 @@
+use pretty_assertions::assert_eq;
+
+use crate::json::{Json, JsonError};
+
+#[test]
+fn test_parse_values() {
+    let json = Json::parse(r#" {"a": [1, -2, true, false, null], "b": {"c": "d"}} "#).unwrap();
+
+    assert_eq!(
+        json,
+        Json::object([
+            (
+                "a",
+                Json::Array(vec![
+                    Json::Number(1),
+                    Json::Number(-2),
+                    Json::Bool(true),
+                    Json::Bool(false),
+                    Json::Null,
+                ])
+            ),
+            ("b", Json::object([("c", Json::from("d"))])),
+        ])
+    );
+}
+
+#[test]
+fn test_parse_string_escapes() {
+    let json = Json::parse(r#""\"\\\/\n\té😀""#).unwrap();
+
+    assert_eq!(json, Json::from("\"\\/\n\té😀"));
+}
+
+#[test]
+fn test_display_round_trips() {
+    let json = Json::object([
+        ("message", Json::from("line\n\"quoted\"\u{1}")),
+        ("values", Json::from(vec![1i64, 2, 3])),
+        ("nothing", Json::Null),
+    ]);
+
+    assert_eq!(
+        json.to_string(),
+        r#"{"message":"line\n\"quoted\"\u0001","values":[1,2,3],"nothing":null}"#
+    );
+    assert_eq!(Json::parse(&json.to_string()), Ok(json));
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(
+        Json::parse(r#"{"a" 1}"#),
+        Err(JsonError {
+            message: "expected `:`".to_owned(),
+            offset: 5,
+        })
+    );
+    assert_eq!(
+        Json::parse("[1, 2"),
+        Err(JsonError {
+            message: "expected `,` or `]`".to_owned(),
+            offset: 5,
+        })
+    );
+    assert_eq!(
+        Json::parse("1.5"),
+        Err(JsonError {
+            message: "only integer numbers are supported".to_owned(),
+            offset: 1,
+        })
+    );
+    assert_eq!(
+        Json::parse("1 2"),
+        Err(JsonError {
+            message: "trailing characters".to_owned(),
+            offset: 2,
+        })
+    );
+}
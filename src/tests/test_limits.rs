@@ -0,0 +1,275 @@
+use crate::diagnostics::{CompileError, CompileWarning};
+use crate::driver::{self, Emit, Options};
+use crate::limits::Limits;
+use crate::tests::{span, strip_margin};
+
+fn compile_errors_with_limits(source_code: &str, limits: Limits) -> Vec<CompileError> {
+    let options = Options {
+        limits,
+        ..Default::default()
+    };
+
+    match driver::compile(source_code, &options) {
+        Ok(compiled_program) => {
+            panic!("program compiled unexpectedly:\n{}", compiled_program.code)
+        }
+        Err(diagnostic) => diagnostic.errors,
+    }
+}
+
+#[test]
+fn test_source_code_over_limit() {
+    let limits = Limits {
+        max_source_bytes: 8,
+        ..Default::default()
+    };
+
+    let errors = compile_errors_with_limits("main :: () {}", limits);
+
+    assert_eq!(
+        errors,
+        vec![CompileError::SourceTooLarge { size: 13, limit: 8 }]
+    );
+}
+
+#[test]
+fn test_parens_nested_past_limit() {
+    let limits = Limits {
+        max_nesting_depth: 3,
+        ..Default::default()
+    };
+
+    let errors = compile_errors_with_limits(
+        &strip_margin(
+            r#"
+            |main :: () -> i32 {
+            |    (((1)))
+            |}
+            |"#,
+        ),
+        limits,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::NestingTooDeep {
+            limit: 3,
+            span: span(26, 27),
+        }]
+    );
+}
+
+#[test]
+fn test_binary_expr_chain_doesnt_nest() {
+    let options = Options {
+        limits: Limits {
+            max_nesting_depth: 4,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let compiled_program = driver::compile(
+        strip_margin(
+            r#"
+            |main :: () -> i32 {
+            |    1 + 2 + 3 + 4 + 5
+            |}
+            |"#,
+        ),
+        &options,
+    );
+
+    assert!(compiled_program.is_ok());
+}
+
+#[test]
+fn test_deeply_nested_parens_fail_without_overflowing_stack() {
+    on_main_thread_stack(|| {
+        let depth = 100_000;
+        let source_code = format!(
+            "main :: () -> i32 {{ {}1{} }}",
+            "(".repeat(depth),
+            ")".repeat(depth)
+        );
+
+        let errors = compile_errors_with_limits(&source_code, Limits::default());
+
+        assert!(
+            matches!(
+                errors.as_slice(),
+                [CompileError::NestingTooDeep { limit: 256, .. }]
+            ),
+            "{:?}",
+            errors
+        );
+    });
+}
+
+#[test]
+fn test_deeply_nested_blocks_fail_without_overflowing_stack() {
+    on_main_thread_stack(|| {
+        let depth = 50_000;
+        let source_code = format!(
+            "main :: () -> i32 {{ {}1{} }}",
+            "{ ".repeat(depth),
+            " }".repeat(depth)
+        );
+
+        let errors = compile_errors_with_limits(&source_code, Limits::default());
+
+        assert!(
+            matches!(
+                errors.as_slice(),
+                [CompileError::NestingTooDeep { limit: 256, .. }]
+            ),
+            "{:?}",
+            errors
+        );
+    });
+}
+
+#[test]
+fn test_expressions_nested_up_to_limit_compile_on_main_thread_stack() {
+    on_main_thread_stack(|| {
+        // Leaves room for the levels of `main` and of the outermost
+        // statement.
+        let depth = Limits::default().max_nesting_depth - 3;
+        let source_codes = [
+            format!(
+                "main :: () -> i32 {{ {}1{} }}",
+                "{ ".repeat(depth),
+                " }".repeat(depth)
+            ),
+            format!(
+                "main :: () -> i32 {{ {}1{} }}",
+                "if 1 { ".repeat(depth),
+                " } else { 2 }".repeat(depth)
+            ),
+            format!(
+                "main :: () -> i32 {{ {}_ = 1{} 1 }}",
+                "for i: 0..1 { ".repeat(depth),
+                " }".repeat(depth)
+            ),
+            format!(
+                "f :: (x: i32) -> i32 {{ x }}\nmain :: () -> i32 {{ {}1{} }}",
+                "f(".repeat(depth),
+                ")".repeat(depth)
+            ),
+            format!(
+                "main :: () -> i32 {{ {}1{} }}",
+                "1 + (".repeat(depth),
+                ")".repeat(depth)
+            ),
+        ];
+
+        for source_code in &source_codes {
+            assert_compiles_with_every_backend(source_code);
+        }
+    });
+}
+
+#[test]
+fn test_long_binary_expr_chain_compiles_on_default_thread_stack() {
+    let source_code = format!("main :: () -> i32 {{ 0{} }}", " + 1".repeat(10_000));
+
+    assert_compiles_with_every_backend(&source_code);
+}
+
+/// Runs the test on a thread with as much stack as the main thread usually
+/// gets, which compilations are expected to run on, rather than the 2 MiB that
+/// Rust gives spawned threads.
+fn on_main_thread_stack(test: impl FnOnce() + Send + 'static) {
+    std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(test)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+fn assert_compiles_with_every_backend(source_code: &str) {
+    for emit in [
+        Emit::Assembly,
+        Emit::LlvmIr,
+        Emit::Wat,
+        Emit::C,
+        Emit::Ir,
+        Emit::Cfg,
+        Emit::Source,
+    ] {
+        let options = Options {
+            emit,
+            remarks: true,
+            ..Default::default()
+        };
+
+        assert!(driver::compile(source_code, &options).is_ok());
+    }
+
+    let options = Options::default();
+
+    assert!(driver::compile_bytecode(source_code, &options).is_ok());
+    assert!(driver::expand(source_code, &options).is_ok());
+    assert!(driver::run(source_code, &options).is_ok_and(|execution| execution.exit_code.is_ok()));
+}
+
+#[test]
+fn test_ast_over_limit() {
+    let limits = Limits {
+        max_ast_bytes: 1,
+        ..Default::default()
+    };
+
+    let errors = compile_errors_with_limits(
+        &strip_margin(
+            r#"
+            |main :: () -> i32 {
+            |    0
+            |}
+            |"#,
+        ),
+        limits,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::AstTooLarge {
+            limit: 1,
+            span: span(0, 4),
+        }]
+    );
+}
+
+#[test]
+fn test_lints_skipped_past_declaration_limit() {
+    let options = Options {
+        limits: Limits {
+            max_decls_to_lint: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let diagnostic = driver::check(
+        strip_margin(
+            r#"
+            |spin :: () {
+            |    for {}
+            |}
+            |main :: () {}
+            |"#,
+        ),
+        &options,
+    );
+
+    assert_eq!(diagnostic.errors, vec![]);
+    assert_eq!(
+        diagnostic.warnings,
+        vec![CompileWarning::LintsSkipped {
+            decl_count: 2,
+            limit: 1,
+            span: span(26, 30),
+        }]
+    );
+}
@@ -0,0 +1,34 @@
+use crate::compiler_context::CompilerContext;
+use crate::line_index::{Col, Line, LineIndex};
+use crate::scanner::BytePos;
+
+#[test]
+fn test_lookup_line_col() {
+    let context = CompilerContext::new("main :: () {\n    x\n}\n".to_owned());
+
+    assert_eq!(context.lookup_line_col(BytePos(0)), (Line(1), Col(1)));
+    assert_eq!(context.lookup_line_col(BytePos(12)), (Line(1), Col(13)));
+    assert_eq!(context.lookup_line_col(BytePos(13)), (Line(2), Col(1)));
+    assert_eq!(context.lookup_line_col(BytePos(17)), (Line(2), Col(5)));
+    assert_eq!(context.lookup_line_col(BytePos(19)), (Line(3), Col(1)));
+}
+
+#[test]
+fn test_lookup_line_col_past_end_is_at_end() {
+    let context = CompilerContext::new("a\nbc".to_owned());
+
+    assert_eq!(context.lookup_line_col(BytePos(4)), (Line(2), Col(3)));
+    assert_eq!(context.lookup_line_col(BytePos(100)), (Line(2), Col(3)));
+}
+
+#[test]
+fn test_columns_count_characters() {
+    let source_code = "é :: 1\n";
+    let line_index = LineIndex::new(source_code);
+
+    assert_eq!(
+        line_index.line_col(source_code, BytePos(3)),
+        (Line(1), Col(3))
+    );
+    assert_eq!(line_index.line_start(Line(2)), BytePos(8));
+}
@@ -0,0 +1,80 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::linker::{compiler_args, linker_input, LinkOutput};
+use crate::runtime;
+
+fn args(args: &[&str]) -> Vec<OsString> {
+    args.iter().map(OsString::from).collect()
+}
+
+#[test]
+fn test_executables_are_linked_without_the_c_runtime() {
+    assert_eq!(
+        compiler_args(LinkOutput::Executable, false, Path::new("out/hello")),
+        args(&[
+            "-nostdlib",
+            "-static",
+            "-o",
+            "out/hello",
+            "-x",
+            "assembler",
+            "-"
+        ])
+    );
+}
+
+#[test]
+fn test_objects_are_only_assembled() {
+    assert_eq!(
+        compiler_args(LinkOutput::Object, false, Path::new("hello.o")),
+        args(&["-c", "-o", "hello.o", "-x", "assembler", "-"])
+    );
+}
+
+#[test]
+fn test_executables_calling_extern_functions_are_linked_with_the_c_library() {
+    assert_eq!(
+        compiler_args(LinkOutput::Executable, true, Path::new("hello")),
+        args(&["-o", "hello", "-x", "assembler", "-"])
+    );
+}
+
+#[test]
+fn test_runtime_library_follows_the_program() {
+    let input = linker_input("    .globl main\nmain:\n    ret\n", false);
+
+    assert!(input.starts_with("    .globl main\n"));
+    assert!(input.contains(runtime::ENTRY_POINT));
+    assert!(input.ends_with(runtime::ASSEMBLY));
+}
+
+#[test]
+fn test_c_runtime_provides_the_entry_point_of_programs_calling_extern_functions() {
+    let input = linker_input("    .globl main\nmain:\n    ret\n", true);
+
+    assert!(!input.contains(runtime::ENTRY_POINT));
+    assert!(input.ends_with(runtime::ASSEMBLY));
+}
+
+#[test]
+fn test_outputs_are_named_after_the_input_file() {
+    let input_path = Path::new("examples/hello.sph");
+
+    assert_eq!(
+        LinkOutput::Executable.default_path(input_path),
+        PathBuf::from("hello")
+    );
+    assert_eq!(
+        LinkOutput::Object.default_path(input_path),
+        PathBuf::from("hello.o")
+    );
+}
+
+#[test]
+fn test_inputs_without_an_extension_are_not_overwritten() {
+    assert_eq!(
+        LinkOutput::Executable.default_path(Path::new("hello")),
+        PathBuf::from("hello.out")
+    );
+}
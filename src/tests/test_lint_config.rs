@@ -0,0 +1,204 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use pretty_assertions::assert_eq;
+
+use crate::diagnostics::{Lint, LintLevel, LintLevels};
+use crate::lint_config::{self, LintConfig, LintConfigError, LINT_CONFIG_FILE_NAME};
+
+/// Directory of its own for a test to write files to, so that tests running
+/// at the same time don't see each other's.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("sophia-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+#[test]
+fn test_parse_lint_config() {
+    let config = LintConfig::parse(
+        r#"
+# Lints of the whole workspace.
+root = true
+
+[lints]
+infinite-loop = "deny"  # Programs must end.
+"unused-decl" = "allow"
+"#,
+    );
+
+    assert_eq!(
+        config,
+        Ok(LintConfig {
+            root: true,
+            levels: vec![
+                (Lint::InfiniteLoop, LintLevel::Deny),
+                (Lint::UnusedDecl, LintLevel::Allow),
+            ],
+        })
+    );
+}
+
+#[test]
+fn test_lint_config_all_lints() {
+    let config = LintConfig::parse("[lints]\nall = \"warn\"\n").unwrap();
+
+    assert_eq!(
+        config.levels,
+        Lint::ALL.map(|lint| (lint, LintLevel::Warn)).to_vec()
+    );
+}
+
+#[test]
+fn test_lint_config_lint_groups() {
+    let config =
+        LintConfig::parse("[lints]\nwarnings = \"deny\"\ndead_code = \"allow\"\n").unwrap();
+
+    let mut expected_levels = Lint::ALL.map(|lint| (lint, LintLevel::Deny)).to_vec();
+    expected_levels.extend([
+        (Lint::UnreachableCode, LintLevel::Allow),
+        (Lint::UnusedDecl, LintLevel::Allow),
+    ]);
+
+    assert_eq!(config.levels, expected_levels);
+}
+
+#[test]
+fn test_lints_named() {
+    assert_eq!(
+        Lint::lints_named("unused"),
+        Some(&[Lint::UnusedValue, Lint::UnusedBinding, Lint::UnusedDecl][..])
+    );
+    assert_eq!(Lint::lints_named("warnings"), Some(&Lint::ALL[..]));
+    assert_eq!(
+        Lint::lints_named("unused_binding"),
+        Some(&[Lint::UnusedBinding][..])
+    );
+    assert_eq!(Lint::lints_named("unused-all"), None);
+}
+
+#[test]
+fn test_lint_config_errors() {
+    let error = |message: &str, line| {
+        Err(LintConfigError {
+            message: message.to_owned(),
+            line,
+        })
+    };
+
+    assert_eq!(
+        LintConfig::parse("[lints]\nunused-thing = \"allow\"\n"),
+        error("unknown lint `unused-thing`", 2)
+    );
+    assert_eq!(
+        LintConfig::parse("[lints]\nunused-decl = \"forbid\"\n"),
+        error(
+            "expected `\"allow\"`, `\"warn\"` or `\"deny\"` as the level of `unused-decl`",
+            2
+        )
+    );
+    assert_eq!(
+        LintConfig::parse("root = yes\n"),
+        error("expected `true` or `false` as the value of `root`", 1)
+    );
+    assert_eq!(
+        LintConfig::parse("unused-decl = \"allow\"\n"),
+        error("unknown key `unused-decl`", 1)
+    );
+    assert_eq!(
+        LintConfig::parse("\n[lint]\n"),
+        error("unknown table `lint`", 2)
+    );
+    assert_eq!(
+        LintConfig::parse("[lints]\nunused-decl\n")
+            .unwrap_err()
+            .to_string(),
+        "expected `<key> = <value>` on line 2"
+    );
+}
+
+#[test]
+fn test_nearer_lint_configs_and_overrides_win() {
+    let workspace_config = LintConfig {
+        root: true,
+        levels: vec![
+            (Lint::UnusedDecl, LintLevel::Deny),
+            (Lint::UnusedValue, LintLevel::Deny),
+        ],
+    };
+    let subdir_config = LintConfig {
+        root: false,
+        levels: vec![(Lint::UnusedDecl, LintLevel::Allow)],
+    };
+
+    let lint_levels = lint_config::lint_levels(
+        &[workspace_config, subdir_config],
+        &[(Lint::UnusedValue, LintLevel::Warn)],
+    );
+
+    let mut expected = LintLevels::default();
+    expected.allow(Lint::UnusedDecl);
+    assert_eq!(lint_levels, expected);
+}
+
+#[test]
+fn test_discover_lint_configs_up_to_root() {
+    let dir = test_dir("discover-lint-configs");
+    let subdir = dir.join("workspace/team");
+    fs::create_dir_all(&subdir).unwrap();
+    // Outside of the workspace, so it doesn't apply.
+    fs::write(dir.join(LINT_CONFIG_FILE_NAME), "[lints]\nall = \"deny\"\n").unwrap();
+    fs::write(
+        dir.join("workspace").join(LINT_CONFIG_FILE_NAME),
+        "root = true\n[lints]\nunused-decl = \"deny\"\n",
+    )
+    .unwrap();
+    fs::write(
+        subdir.join(LINT_CONFIG_FILE_NAME),
+        "[lints]\nunused-decl = \"allow\"\n",
+    )
+    .unwrap();
+
+    let configs = LintConfig::discover(&subdir.join("main.sph"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        configs.unwrap(),
+        vec![
+            LintConfig {
+                root: true,
+                levels: vec![(Lint::UnusedDecl, LintLevel::Deny)],
+            },
+            LintConfig {
+                root: false,
+                levels: vec![(Lint::UnusedDecl, LintLevel::Allow)],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_discover_reports_invalid_lint_config() {
+    let dir = test_dir("discover-invalid-lint-config");
+    let config_path = dir.join(LINT_CONFIG_FILE_NAME);
+    fs::write(
+        &config_path,
+        "root = true\n[lints]\nunused-all = \"allow\"\n",
+    )
+    .unwrap();
+
+    let configs = LintConfig::discover(&dir.join("main.sph"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        configs.unwrap_err().to_string(),
+        format!(
+            "invalid lint configuration `{}`: unknown lint `unused-all` on line 3",
+            config_path.display()
+        )
+    );
+}
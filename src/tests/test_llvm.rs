@@ -0,0 +1,279 @@
+use pretty_assertions::assert_eq;
+
+use crate::driver::{self, Emit, Options};
+use crate::tests::strip_margin;
+
+fn emit_llvm_ir(source_code: &str) -> String {
+    let options = Options {
+        emit: Emit::LlvmIr,
+        ..Default::default()
+    };
+
+    match driver::compile(strip_margin(source_code), &options) {
+        // Blank lines are dropped, just like `strip_margin` drops them.
+        Ok(compiled_program) => compiled_program
+            .code
+            .lines()
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+#[test]
+fn test_unit_main_returns_zero() {
+    let ir = emit_llvm_ir(
+        r#"
+        |main :: () {
+        |    x := 3;
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        ir,
+        strip_margin(
+            r#"
+            |; ModuleID = 'sophia'
+            |source_filename = "sophia"
+            |define i32 @main() {
+            |block0:
+            |  ret i32 0
+            |}
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_calls_and_loops() {
+    let ir = emit_llvm_ir(
+        r#"
+        |add :: (a: i32, b: i32) -> i32 {
+        |    a + b
+        |}
+        |
+        |main :: () -> i32 {
+        |    x := add(1, 2);
+        |    for i : 0..=x {
+        |        if i {
+        |            continue
+        |        }
+        |    }
+        |    x
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        ir,
+        strip_margin(
+            r#"
+            |; ModuleID = 'sophia'
+            |source_filename = "sophia"
+            |define i32 @add(i32 %v0, i32 %v1) {
+            |block0:
+            |  %v2 = add i32 %v0, %v1
+            |  ret i32 %v2
+            |}
+            |define i32 @main() {
+            |block0:
+            |  %v2 = call i32 @add(i32 1, i32 2)
+            |  br label %block1
+            |block1:
+            |  %v4 = phi i32 [ 0, %block0 ], [ %v8, %block4 ]
+            |  %v5 = icmp sle i32 %v4, %v2
+            |  br i1 %v5, label %block2, label %block5
+            |block2:
+            |  br label %block3
+            |block3:
+            |  %v7.with_overflow = call { i32, i1 } @llvm.sadd.with.overflow.i32(i32 %v4, i32 1)
+            |  %v7 = extractvalue { i32, i1 } %v7.with_overflow, 1
+            |  br i1 %v7, label %block5, label %block4
+            |block4:
+            |  %v8 = add i32 %v4, 1
+            |  br label %block1
+            |block5:
+            |  ret i32 %v2
+            |}
+            |declare { i32, i1 } @llvm.sadd.with.overflow.i32(i32, i32)
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_branches_to_the_same_block_select_its_arguments() {
+    let ir = emit_llvm_ir(
+        r#"
+        |pick :: (c: i32, a: i32, b: i32) -> i32 {
+        |    if c { a } else { b }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        ir,
+        strip_margin(
+            r#"
+            |; ModuleID = 'sophia'
+            |source_filename = "sophia"
+            |define i32 @pick(i32 %v0, i32 %v1, i32 %v2) {
+            |block0:
+            |  %block0.cond = icmp ne i32 %v0, 0
+            |  %block0.v3 = select i1 %block0.cond, i32 %v1, i32 %v2
+            |  br label %block1
+            |block1:
+            |  %v3 = phi i32 [ %block0.v3, %block0 ]
+            |  ret i32 %v3
+            |}
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_division_traps_instead_of_being_undefined() {
+    let ir = emit_llvm_ir(
+        r#"
+        |main :: () {
+        |    _ = 7 / 2;
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        ir,
+        strip_margin(
+            r#"
+            |; ModuleID = 'sophia'
+            |source_filename = "sophia"
+            |define i32 @main() {
+            |block0:
+            |  %block0.is_zero = icmp eq i32 2, 0
+            |  %block0.is_min = icmp eq i32 7, -2147483648
+            |  %block0.is_minus_one = icmp eq i32 2, -1
+            |  %block0.overflows = and i1 %block0.is_min, %block0.is_minus_one
+            |  %block0.traps = or i1 %block0.is_zero, %block0.overflows
+            |  br i1 %block0.traps, label %trap, label %block0.1
+            |block0.1:
+            |  %v2 = sdiv i32 7, 2
+            |  ret i32 0
+            |trap:
+            |  call void @llvm.trap()
+            |  unreachable
+            |}
+            |declare void @llvm.trap()
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_builtins_call_the_runtime_library() {
+    let ir = emit_llvm_ir(
+        r#"
+        |main :: () {
+        |    print(1);
+        |    println(2);
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        ir,
+        strip_margin(
+            r#"
+            |; ModuleID = 'sophia'
+            |source_filename = "sophia"
+            |define i32 @main() {
+            |block0:
+            |  call void @sophia_print_i32(i32 1)
+            |  call void @sophia_println_i32(i32 2)
+            |  ret i32 0
+            |}
+            |declare void @sophia_print_i32(i32)
+            |declare void @sophia_println_i32(i32)
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_extern_functions_are_declared() {
+    let ir = emit_llvm_ir(
+        r#"
+        |extern putchar :: (c: i32) -> i32;
+        |extern exit :: (code: i32);
+        |main :: () {
+        |    _ = putchar(65);
+        |    exit(0);
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        ir,
+        strip_margin(
+            r#"
+            |; ModuleID = 'sophia'
+            |source_filename = "sophia"
+            |define i32 @main() {
+            |block0:
+            |  %v1 = call i32 @putchar(i32 65)
+            |  call void @exit(i32 0)
+            |  ret i32 0
+            |}
+            |declare i32 @putchar(i32)
+            |declare void @exit(i32)
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_steps_that_arent_positive_stop_the_program() {
+    let ir = emit_llvm_ir(
+        r#"
+        |count :: (n: i32) {
+        |    for i : 0..10 step n {}
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        ir,
+        strip_margin(
+            r#"
+            |; ModuleID = 'sophia'
+            |source_filename = "sophia"
+            |define void @count(i32 %v0) {
+            |block0:
+            |  %block0.is_not_positive = icmp sle i32 %v0, 0
+            |  br i1 %block0.is_not_positive, label %trap, label %block0.1
+            |block0.1:
+            |  br label %block1
+            |block1:
+            |  %v3 = phi i32 [ 0, %block0.1 ], [ %v6, %block3 ]
+            |  %v4 = icmp slt i32 %v3, 10
+            |  br i1 %v4, label %block2, label %block4
+            |block2:
+            |  %v5.with_overflow = call { i32, i1 } @llvm.sadd.with.overflow.i32(i32 %v3, i32 %v0)
+            |  %v5 = extractvalue { i32, i1 } %v5.with_overflow, 1
+            |  br i1 %v5, label %block4, label %block3
+            |block3:
+            |  %v6 = add i32 %v3, %v0
+            |  br label %block1
+            |block4:
+            |  ret void
+            |trap:
+            |  call void @llvm.trap()
+            |  unreachable
+            |}
+            |declare void @llvm.trap()
+            |declare { i32, i1 } @llvm.sadd.with.overflow.i32(i32, i32)
+            |"#
+        )
+    );
+}
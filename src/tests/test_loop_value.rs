@@ -0,0 +1,103 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast_owned::Type;
+use crate::diagnostics::CompileError;
+use crate::tests::{check_errors, compile_errors, run, span};
+
+#[test]
+fn test_run_loops_with_values() {
+    let exit_code = run(r#"
+        |main :: () -> i32 {
+        |    values := [3, 0, 5];
+        |    i := [0];
+        |    first_zero := for {
+        |        if values[i[0]] {
+        |            i[0] = i[0] + 1;
+        |        } else {
+        |            break i[0];
+        |        }
+        |    };
+        |    inner_break := for {
+        |        for x : 0..3 {
+        |            if x { break; }
+        |        } else {
+        |            break 10;
+        |        }
+        |        break 20;
+        |    };
+        |    else_break := for {
+        |        for x : [1, 2] {} else {
+        |            break 7;
+        |        }
+        |    };
+        |    first_zero * 100 + inner_break + else_break
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(127));
+}
+
+#[test]
+fn test_breaks_out_of_a_loop_have_values_of_the_same_type() {
+    let errors = check_errors(
+        r#"
+        |main :: () -> i32 {
+        |    a := for {
+        |        break 1;
+        |        break {};
+        |    };
+        |    for x : 0..3 {
+        |        break x;
+        |    }
+        |    b := for { break; };
+        |    b
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(66, 68),
+            },
+            CompileError::BreakValueInIterativeLoop {
+                span: span(110, 111)
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(148, 149),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[1].to_string(),
+        "`break` with a value out of a loop that can complete without one"
+    );
+}
+
+#[test]
+fn test_compiled_backends_reject_loop_values() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    for {
+        |        break 1;
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::LoopValuesUnsupported { backend: "x86-64" }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "the x86-64 backend doesn't support breaking out of loops with values yet; run the \
+         program with the interpreter instead"
+    );
+}
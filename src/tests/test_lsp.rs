@@ -0,0 +1,232 @@
+use pretty_assertions::assert_eq;
+
+use crate::driver::Options;
+use crate::lsp::LanguageServer;
+
+const SOURCE: &str = r#"square :: (n: i32) -> i32 { n * n }\nmain :: () -> i32 {\n    x := square(3);\n    x + y\n}\n"#;
+
+/// Sends the messages to the server, returning what it sent back to each one.
+fn send(server: &mut LanguageServer, messages: &[&str]) -> Vec<String> {
+    messages
+        .iter()
+        .map(|message| {
+            server
+                .handle_message(message)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect()
+}
+
+fn open_document(server: &mut LanguageServer) -> String {
+    let did_open = format!(
+        r#"{{"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {{"textDocument": {{"uri": "file:///a.sph", "text": "{}"}}}}}}"#,
+        SOURCE
+    );
+
+    send(server, &[&did_open]).remove(0)
+}
+
+#[test]
+fn test_initialize_reports_capabilities() {
+    let mut server = LanguageServer::new(Options::default());
+
+    let responses = send(
+        &mut server,
+        &[
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}"#,
+            r#"{"jsonrpc": "2.0", "method": "initialized", "params": {}}"#,
+        ],
+    );
+
+    assert_eq!(
+        responses,
+        [
+            format!(
+                r#"{{"jsonrpc":"2.0","id":1,"result":{{"capabilities":{{"textDocumentSync":{{"openClose":true,"change":2}},"definitionProvider":true,"hoverProvider":true,"documentSymbolProvider":true}},"serverInfo":{{"name":"sophia","version":"{}"}}}}}}"#,
+                env!("CARGO_PKG_VERSION")
+            ),
+            String::new(),
+        ]
+    );
+}
+
+#[test]
+fn test_diagnostics_are_published_on_change() {
+    let mut server = LanguageServer::new(Options::default());
+
+    assert_eq!(
+        open_document(&mut server),
+        r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///a.sph","diagnostics":[{"range":{"start":{"line":3,"character":8},"end":{"line":3,"character":9}},"severity":1,"code":"E0002","source":"sophia","message":"cannot find `y` in this scope"}]}}"#
+    );
+
+    // The edit replaces the `y` with `1`.
+    let responses = send(
+        &mut server,
+        &[
+            r#"{"jsonrpc": "2.0", "method": "textDocument/didChange", "params": {"textDocument": {"uri": "file:///a.sph", "version": 2}, "contentChanges": [{"range": {"start": {"line": 3, "character": 8}, "end": {"line": 3, "character": 9}}, "text": "1"}]}}"#,
+            r#"{"jsonrpc": "2.0", "method": "textDocument/didClose", "params": {"textDocument": {"uri": "file:///a.sph"}}}"#,
+        ],
+    );
+
+    assert_eq!(
+        responses,
+        [
+            r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///a.sph","diagnostics":[]}}"#,
+            r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///a.sph","diagnostics":[]}}"#,
+        ]
+    );
+}
+
+#[test]
+fn test_edits_are_checked_the_same_as_the_whole_document() {
+    let mut server = LanguageServer::new(Options::default());
+    open_document(&mut server);
+
+    // The edits type in a declaration of `y` a char at a time, at the end of
+    // the document, so that every version is parsed from the last one.
+    let typed_in = "y :: () -> i32 { 2 }";
+    let mut responses = vec![];
+
+    for (col, ch) in typed_in.chars().enumerate() {
+        let did_change = format!(
+            r#"{{"jsonrpc": "2.0", "method": "textDocument/didChange", "params": {{"textDocument": {{"uri": "file:///a.sph"}}, "contentChanges": [{{"range": {{"start": {{"line": 5, "character": {col}}}, "end": {{"line": 5, "character": {col}}}}}, "text": "{ch}"}}]}}}}"#,
+        );
+
+        responses = send(&mut server, &[&did_change]);
+    }
+
+    // `y` is a function now, so `x + y` adds a function to an integer.
+    let mut fresh_server = LanguageServer::new(Options::default());
+    let did_open = format!(
+        r#"{{"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {{"textDocument": {{"uri": "file:///a.sph", "text": "{}{}"}}}}}}"#,
+        SOURCE, typed_in
+    );
+
+    assert_eq!(responses, send(&mut fresh_server, &[&did_open]));
+    assert!(responses[0].contains("E0004"), "{}", responses[0]);
+}
+
+#[test]
+fn test_unknown_chars_typed_in_are_published() {
+    let mut server = LanguageServer::new(Options::default());
+    open_document(&mut server);
+
+    // The edit types an `@` in front of the `y`.
+    let responses = send(
+        &mut server,
+        &[
+            r#"{"jsonrpc": "2.0", "method": "textDocument/didChange", "params": {"textDocument": {"uri": "file:///a.sph", "version": 2}, "contentChanges": [{"range": {"start": {"line": 3, "character": 8}, "end": {"line": 3, "character": 8}}, "text": "@"}]}}"#,
+        ],
+    );
+
+    assert_eq!(
+        responses,
+        [
+            r#"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///a.sph","diagnostics":[{"range":{"start":{"line":3,"character":8},"end":{"line":3,"character":9}},"severity":1,"code":"E0052","source":"sophia","message":"unknown character '@'"}]}}"#,
+        ]
+    );
+}
+
+#[test]
+fn test_go_to_definition_and_hover() {
+    let mut server = LanguageServer::new(Options::default());
+    open_document(&mut server);
+
+    let responses = send(
+        &mut server,
+        &[
+            // `y` is replaced with `x`, so that the names resolve.
+            r#"{"jsonrpc": "2.0", "method": "textDocument/didChange", "params": {"textDocument": {"uri": "file:///a.sph"}, "contentChanges": [{"range": {"start": {"line": 3, "character": 8}, "end": {"line": 3, "character": 9}}, "text": "x"}]}}"#,
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "textDocument/definition", "params": {"textDocument": {"uri": "file:///a.sph"}, "position": {"line": 2, "character": 11}}}"#,
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {"textDocument": {"uri": "file:///a.sph"}, "position": {"line": 2, "character": 11}}}"#,
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "textDocument/definition", "params": {"textDocument": {"uri": "file:///a.sph"}, "position": {"line": 3, "character": 8}}}"#,
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "textDocument/hover", "params": {"textDocument": {"uri": "file:///a.sph"}, "position": {"line": 3, "character": 8}}}"#,
+            r#"{"jsonrpc": "2.0", "id": 5, "method": "textDocument/hover", "params": {"textDocument": {"uri": "file:///a.sph"}, "position": {"line": 3, "character": 6}}}"#,
+        ],
+    );
+
+    assert_eq!(
+        responses[1..],
+        [
+            r#"{"jsonrpc":"2.0","id":1,"result":{"uri":"file:///a.sph","range":{"start":{"line":0,"character":0},"end":{"line":0,"character":6}}}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"result":{"contents":{"kind":"markdown","value":"```sophia\nsquare :: (i32) -> i32\n```"},"range":{"start":{"line":2,"character":9},"end":{"line":2,"character":15}}}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"result":{"uri":"file:///a.sph","range":{"start":{"line":2,"character":4},"end":{"line":2,"character":5}}}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"result":{"contents":{"kind":"markdown","value":"```sophia\nx : i32\n```"},"range":{"start":{"line":3,"character":8},"end":{"line":3,"character":9}}}}"#,
+            r#"{"jsonrpc":"2.0","id":5,"result":null}"#,
+        ]
+    );
+}
+
+#[test]
+fn test_names_that_resolve_are_known_along_with_ones_that_dont() {
+    let mut server = LanguageServer::new(Options::default());
+    open_document(&mut server);
+
+    // `y` isn't defined, so nothing of `main` is type checked, but its names
+    // that resolve are still known.
+    let responses = send(
+        &mut server,
+        &[
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "textDocument/definition", "params": {"textDocument": {"uri": "file:///a.sph"}, "position": {"line": 2, "character": 11}}}"#,
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {"textDocument": {"uri": "file:///a.sph"}, "position": {"line": 2, "character": 11}}}"#,
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "textDocument/definition", "params": {"textDocument": {"uri": "file:///a.sph"}, "position": {"line": 3, "character": 4}}}"#,
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "textDocument/hover", "params": {"textDocument": {"uri": "file:///a.sph"}, "position": {"line": 3, "character": 4}}}"#,
+            r#"{"jsonrpc": "2.0", "id": 5, "method": "textDocument/definition", "params": {"textDocument": {"uri": "file:///a.sph"}, "position": {"line": 3, "character": 8}}}"#,
+        ],
+    );
+
+    assert_eq!(
+        responses,
+        [
+            r#"{"jsonrpc":"2.0","id":1,"result":{"uri":"file:///a.sph","range":{"start":{"line":0,"character":0},"end":{"line":0,"character":6}}}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"result":{"contents":{"kind":"markdown","value":"```sophia\nsquare :: (i32) -> i32\n```"},"range":{"start":{"line":2,"character":9},"end":{"line":2,"character":15}}}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"result":{"uri":"file:///a.sph","range":{"start":{"line":2,"character":4},"end":{"line":2,"character":5}}}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"result":null}"#,
+            r#"{"jsonrpc":"2.0","id":5,"result":null}"#,
+        ]
+    );
+}
+
+#[test]
+fn test_document_symbols() {
+    let mut server = LanguageServer::new(Options::default());
+    open_document(&mut server);
+
+    let responses = send(
+        &mut server,
+        &[
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "textDocument/documentSymbol", "params": {"textDocument": {"uri": "file:///a.sph"}}}"#,
+        ],
+    );
+
+    assert_eq!(
+        responses,
+        [
+            r#"{"jsonrpc":"2.0","id":1,"result":[{"name":"square","kind":12,"range":{"start":{"line":0,"character":0},"end":{"line":0,"character":35}},"selectionRange":{"start":{"line":0,"character":0},"end":{"line":0,"character":6}}},{"name":"main","kind":12,"range":{"start":{"line":1,"character":0},"end":{"line":4,"character":1}},"selectionRange":{"start":{"line":1,"character":0},"end":{"line":1,"character":4}}}]}"#,
+        ]
+    );
+}
+
+#[test]
+fn test_requests_about_unopened_documents_fail() {
+    let mut server = LanguageServer::new(Options::default());
+
+    let responses = send(
+        &mut server,
+        &[
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "textDocument/hover", "params": {"textDocument": {"uri": "file:///b.sph"}, "position": {"line": 0, "character": 0}}}"#,
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "textDocument/rename", "params": {}}"#,
+        ],
+    );
+
+    assert_eq!(
+        responses,
+        [
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32602,"message":"`file:///b.sph` isn't open"}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"error":{"code":-32601,"message":"unknown method `textDocument/rename`"}}"#,
+        ]
+    );
+}
@@ -0,0 +1,31 @@
+use std::path::Path;
+
+use pretty_assertions::assert_eq;
+
+use crate::manifest::{ArtifactKind, ContentHash, Manifest};
+
+#[test]
+fn test_content_hash_is_fnv1a() {
+    assert_eq!(ContentHash::of(b""), ContentHash(0xcbf29ce484222325));
+    assert_eq!(ContentHash::of(b"a"), ContentHash(0xaf63dc4c8601ec8c));
+    assert_eq!(
+        ContentHash::of(b"foobar").to_string(),
+        "fnv1a64:85944171f73967e8"
+    );
+}
+
+#[test]
+fn test_manifest_lists_sources_and_artifacts() {
+    let mut manifest = Manifest::default();
+    manifest.add_source(Path::new("src/main.sof"), b"a");
+    manifest.add_artifact(ArtifactKind::Assembly, Path::new("out/main.s"), b"foobar");
+
+    assert_eq!(
+        manifest.to_json().to_string(),
+        concat!(
+            r#"{"version":1,"#,
+            r#""sources":[{"path":"src/main.sof","size":1,"hash":"fnv1a64:af63dc4c8601ec8c"}],"#,
+            r#""artifacts":[{"kind":"assembly","path":"out/main.s","size":6,"hash":"fnv1a64:85944171f73967e8"}]}"#,
+        )
+    );
+}
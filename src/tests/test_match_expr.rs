@@ -0,0 +1,313 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast_owned::Type;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::profile::IntWidth;
+use crate::tests::{check, compile, compile_errors, compile_warnings, span, strip_margin};
+use crate::vm::Vm;
+
+/// Program whose exit code tells which arm `classify` picked for each of a
+/// few integers, as a digit of its own.
+fn classify_program() -> String {
+    strip_margin(
+        r#"
+        |classify :: (x: i32) -> i32 {
+        |    match x {
+        |        0 => 1,
+        |        1..=9 => { 2 }
+        |        -5..0 => 3,
+        |        _ => 4,
+        |    }
+        |}
+        |
+        |main :: () -> i32 {
+        |    classify(0) + classify(9) * 10 + classify(0 - 5) * 100 + classify(10) * 1000
+        |}
+        |"#,
+    )
+}
+
+#[test]
+fn test_match_expr_tests_arms_in_order() {
+    let program = compile(
+        r#"
+        |main :: () -> i32 {
+        |    x := 3;
+        |    match x {
+        |        0 => 1,
+        |        1..=9 => 2,
+        |        _ => 3,
+        |    }
+        |}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    sub rsp, 4
+        |    mov eax, 0
+        |    cmp eax, 3
+        |    jg .L0
+        |    mov eax, 3
+        |    cmp eax, 0
+        |    jg .L0
+        |    mov DWORD PTR [rbp-4], 1
+        |    jmp .L1
+        |.L0:
+        |    mov eax, 1
+        |    cmp eax, 3
+        |    jg .L2
+        |    mov eax, 3
+        |    cmp eax, 9
+        |    jg .L2
+        |    mov DWORD PTR [rbp-4], 2
+        |    jmp .L1
+        |.L2:
+        |    mov DWORD PTR [rbp-4], 3
+        |.L1:
+        |    mov eax, DWORD PTR [rbp-4]
+        |    add rsp, 4
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_match_expr_picks_first_matching_arm() {
+    let execution = driver::run(classify_program(), &Options::default()).unwrap();
+
+    assert_eq!(execution.exit_code, Ok(4321));
+}
+
+#[test]
+fn test_match_expr_in_bytecode() {
+    let compiled_bytecode =
+        driver::compile_bytecode(classify_program(), &Options::default()).unwrap();
+    let mut vm = Vm::load(&compiled_bytecode.module.save()).unwrap();
+
+    assert_eq!(vm.run_main(), Ok(4321));
+}
+
+#[test]
+fn test_ranges_covering_every_integer_are_exhaustive() {
+    let warnings = compile_warnings(
+        r#"
+        |main :: () -> i32 {
+        |    match 7 {
+        |        -2147483648..0 => 1,
+        |        0 => 2,
+        |        1..=2147483647 => 3,
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(warnings, vec![]);
+}
+
+#[test]
+fn test_match_expr_must_cover_every_integer() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    x := 3;
+        |    match x {
+        |        0 => 1,
+        |        5..10 => 2,
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::NonExhaustiveMatch {
+            uncovered: vec![
+                (i128::from(i32::MIN), -1),
+                (1, 4),
+                (10, i128::from(i32::MAX))
+            ],
+            width: IntWidth::Bits32,
+            span: span(42, 43),
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "non-exhaustive patterns: `i32::MIN..=-1`, `1..=4` and `10..=i32::MAX` not covered"
+    );
+}
+
+#[test]
+fn test_non_exhaustive_match_lists_first_uncovered_ranges() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    match 3 {
+        |        0 => 1,
+        |        2 => 2,
+        |        4 => 3,
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors[0].to_string(),
+        "non-exhaustive patterns: `i32::MIN..=-1`, `1`, `3` and 1 more not covered"
+    );
+}
+
+#[test]
+fn test_match_expr_without_arms_is_not_exhaustive() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    match 1 {}
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors[0].to_string(),
+        "non-exhaustive patterns: `i32::MIN..=i32::MAX` not covered"
+    );
+}
+
+#[test]
+fn test_match_arms_must_have_same_type() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    match 1 {
+        |        0 => 1,
+        |        _ => {},
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::MismatchedTypes {
+            expected: Type::I32,
+            found: Type::Unit,
+            span: span(63, 65),
+        }]
+    );
+}
+
+#[test]
+fn test_match_scrutinee_must_be_integer() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    match {} {
+        |        _ => 1,
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::MismatchedTypes {
+            expected: Type::I32,
+            found: Type::Unit,
+            span: span(30, 32),
+        }]
+    );
+}
+
+#[test]
+fn test_pattern_must_fit_in_integer() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    match 1 {
+        |        0..=2147483648 => 1,
+        |        _ => 2,
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::IntegerConstantOutOfRange {
+            width: IntWidth::Bits32,
+            span: span(42, 56),
+        }]
+    );
+}
+
+#[test]
+fn test_pattern_of_largest_u64_doesnt_fit_in_integer() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    match 1 {
+        |        0..=18446744073709551615 => 1,
+        |        _ => 2,
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::IntegerConstantOutOfRange {
+            width: IntWidth::Bits32,
+            span: span(42, 66),
+        }]
+    );
+}
+
+#[test]
+fn test_pattern_integer_larger_than_u64() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    match 1 {
+        |        18446744073709551616 => 1,
+        |        _ => 2,
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::IntegerConstantTooLarge {
+            ty: Type::U64,
+            span: span(42, 62),
+        }]
+    );
+}
+
+#[test]
+fn test_pattern_integer_takes_no_suffix() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    match 1 {
+        |        1u8 => 1,
+        |        _ => 2,
+        |    }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::InvalidIntegerSuffix {
+            suffix: "u8".to_owned(),
+            span: span(42, 45),
+        }]
+    );
+}
@@ -0,0 +1,198 @@
+use pretty_assertions::assert_eq;
+
+use crate::driver::{self, Options};
+use crate::object::{FunctionSymbol, ObjectFile, Relocation};
+use crate::tests::strip_margin;
+
+fn compile_object(source_code: &str) -> ObjectFile {
+    match driver::compile_object(strip_margin(source_code), &Options::default()) {
+        Ok(compiled_object) => compiled_object.object,
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[test]
+fn test_encode_function() {
+    let object = compile_object(
+        r#"
+        |main :: () -> i32 {
+        |    3
+        |}
+        "#,
+    );
+
+    assert_eq!(
+        object,
+        ObjectFile {
+            text: vec![
+                0x55, // push rbp
+                0x48, 0x89, 0xe5, // mov rbp, rsp
+                0xb8, 0x03, 0x00, 0x00, 0x00, // mov eax, 3
+                0x5d, // pop rbp
+                0xc3, // ret
+            ],
+            functions: vec![FunctionSymbol {
+                name: "main".to_owned(),
+                offset: 0,
+                size: 11,
+            }],
+            relocations: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_encode_frame_slots_and_arithmetic() {
+    let object = compile_object(
+        r#"
+        |f :: (a: i32) -> i32 {
+        |    a * 2
+        |}
+        "#,
+    );
+
+    assert_eq!(
+        object.text,
+        vec![
+            0x55, // push rbp
+            0x48, 0x89, 0xe5, // mov rbp, rsp
+            0x48, 0x81, 0xec, 0x04, 0x00, 0x00, 0x00, // sub rsp, 4
+            0x89, 0x7d, 0xfc, // mov DWORD PTR [rbp-4], edi
+            0x8b, 0x45, 0xfc, // mov eax, DWORD PTR [rbp-4]
+            0xb9, 0x02, 0x00, 0x00, 0x00, // mov ecx, 2
+            0x0f, 0xaf, 0xc1, // imul eax, ecx
+            0x48, 0x81, 0xc4, 0x04, 0x00, 0x00, 0x00, // add rsp, 4
+            0x5d, // pop rbp
+            0xc3, // ret
+        ]
+    );
+}
+
+#[test]
+fn test_calls_between_functions_are_resolved() {
+    let object = compile_object(
+        r#"
+        |one :: () -> i32 {
+        |    1
+        |}
+        |
+        |main :: () -> i32 {
+        |    one()
+        |}
+        "#,
+    );
+
+    assert_eq!(
+        object.functions,
+        vec![
+            FunctionSymbol {
+                name: "one".to_owned(),
+                offset: 0,
+                size: 11,
+            },
+            FunctionSymbol {
+                name: "main".to_owned(),
+                offset: 11,
+                size: 11,
+            },
+        ]
+    );
+
+    // The call goes back to the start of `one` from the end of the call.
+    assert_eq!(object.text[15..20], [0xe8, 0xec, 0xff, 0xff, 0xff]);
+    assert_eq!(object.relocations, vec![]);
+}
+
+#[test]
+fn test_calls_to_the_runtime_library_are_relocated() {
+    let object = compile_object(
+        r#"
+        |main :: () {
+        |    println(7);
+        |}
+        "#,
+    );
+
+    assert_eq!(
+        object.relocations,
+        vec![Relocation {
+            offset: 10,
+            symbol: "sophia_println_i32".to_owned(),
+        }]
+    );
+    assert_eq!(object.text[9..14], [0xe8, 0x00, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn test_jumps_take_32_bit_displacements() {
+    let object = compile_object(
+        r#"
+        |main :: () -> i32 {
+        |    if 1 { 2 } else { 3 }
+        |}
+        "#,
+    );
+
+    assert_eq!(
+        object.text[16..],
+        [
+            0x81, 0xf8, 0x00, 0x00, 0x00, 0x00, // cmp eax, 0
+            0x0f, 0x84, 0x0c, 0x00, 0x00, 0x00, // je .L0
+            0xc7, 0x45, 0xfc, 0x02, 0x00, 0x00, 0x00, // mov DWORD PTR [rbp-4], 2
+            0xe9, 0x07, 0x00, 0x00, 0x00, // jmp .L1
+            0xc7, 0x45, 0xfc, 0x03, 0x00, 0x00, 0x00, // mov DWORD PTR [rbp-4], 3
+            0x8b, 0x45, 0xfc, // mov eax, DWORD PTR [rbp-4]
+            0x48, 0x81, 0xc4, 0x04, 0x00, 0x00, 0x00, // add rsp, 4
+            0x5d, // pop rbp
+            0xc3, // ret
+        ]
+    );
+}
+
+#[test]
+fn test_elf_header() {
+    let elf = compile_object(
+        r#"
+        |main :: () -> i32 {
+        |    0
+        |}
+        "#,
+    )
+    .to_elf();
+
+    assert_eq!(elf[..8], [0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+    // Relocatable file for x86-64.
+    assert_eq!(read_u16(&elf, 16), 1);
+    assert_eq!(read_u16(&elf, 18), 62);
+    // Section headers, which are the last thing in the file.
+    let section_count = read_u16(&elf, 60) as usize;
+    assert_eq!(section_count, 7);
+    assert_eq!(read_u64(&elf, 40) as usize + section_count * 64, elf.len());
+}
+
+#[test]
+fn test_elf_symbol_table() {
+    let elf = compile_object(
+        r#"
+        |main :: () {
+        |    print(1);
+        |}
+        "#,
+    )
+    .to_elf();
+
+    let contains = |name: &[u8]| elf.windows(name.len()).any(|window| window == name);
+
+    assert!(contains(b"\0main\0sophia_print_i32\0"));
+    assert!(contains(
+        b"\0.text\0.rela.text\0.note.GNU-stack\0.symtab\0.strtab\0.shstrtab\0"
+    ));
+}
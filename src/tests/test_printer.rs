@@ -0,0 +1,460 @@
+use crate::ast::{
+    BinaryExpr, BinaryOp, CompoundExpr, Const, ConstExpr, Decl, Expr, Function, Program, Type,
+};
+use crate::compiler_context::CompilerContext;
+use crate::diff_parses;
+use crate::driver::{self, Emit, Options};
+use crate::printer::SourcePrinter;
+use crate::scanner::{BytePos, Span};
+use crate::tests::{compile_with_options, strip_margin};
+
+fn print(source_code: &str) -> String {
+    let context = CompilerContext::new(strip_margin(source_code));
+    let program = driver::parse(&context, &Options::default()).unwrap();
+
+    SourcePrinter::new(&context).print_program(program)
+}
+
+#[test]
+fn test_print_program() {
+    let source_code = r#"
+        |add :: (a: i32, b: i32) -> i32 { a + b }
+        |main :: () -> i32 {
+        |    x := add(1, 2) * (3 - 1);
+        |    for i : 0..=x { if i { continue; } else if i - 1 { _ = 1; } else { break; } } else {}
+        |    for j : 0..x step  x-1 {}
+        |    for k : rev  0..=x {}
+        |    #[main_loop]
+        |    for x { break; }
+        |    for ({ 1 }) { break; }
+        |    loop { break; }
+        |    x
+        |}
+        |"#;
+
+    assert_eq!(
+        print(source_code),
+        strip_margin(
+            r#"
+        |add :: (a: i32, b: i32) -> i32 {
+        |    a + b
+        |}
+        |main :: () -> i32 {
+        |    x := add(1, 2) * (3 - 1);
+        |    for i : 0..=x {
+        |        if i {
+        |            continue;
+        |        } else if i - 1 {
+        |            _ = 1;
+        |        } else {
+        |            break;
+        |        }
+        |    } else {}
+        |    for j : 0..x step x - 1 {}
+        |    for k : rev 0..=x {}
+        |    #[main_loop] for x {
+        |        break;
+        |    }
+        |    for ({
+        |        1
+        |    }) {
+        |        break;
+        |    }
+        |    loop {
+        |        break;
+        |    }
+        |    x
+        |}
+        |"#
+        ) + "\n"
+    );
+
+    assert_eq!(
+        diff_parses(&strip_margin(source_code), &print(source_code)).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_print_break_values() {
+    let source_code = r#"
+        |main :: () -> i32 {
+        |    for {  break  (1 +2)*3 ; }
+        |}
+        |"#;
+
+    assert_eq!(
+        print(source_code),
+        strip_margin(
+            r#"
+        |main :: () -> i32 {
+        |    for {
+        |        break (1 + 2) * 3;
+        |    }
+        |}
+        |"#
+        ) + "\n"
+    );
+    assert_eq!(
+        diff_parses(&strip_margin(source_code), &print(source_code)).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_print_comments_where_they_are() {
+    let source_code = r#"
+        |// Triples numbers.
+        |triple :: (n: i32) -> i32 {
+        |    // It's n added thrice.
+        |    n * 3 // or n + n + n
+        |}
+        |
+        |/// Entry point.
+        |main :: () -> i32 {
+        |    x := triple(2);   // six
+        |    if x {
+        |        // nothing yet
+        |    }
+        |    y := (x + // within a statement
+        |        1);
+        |    y
+        |    // end of main
+        |} // after main
+        |
+        |// end of file
+        |"#;
+
+    assert_eq!(
+        print(source_code),
+        strip_margin(
+            r#"
+        |// Triples numbers.
+        |triple :: (n: i32) -> i32 {
+        |    // It's n added thrice.
+        |    n * 3 // or n + n + n
+        |}
+        |/// Entry point.
+        |main :: () -> i32 {
+        |    x := triple(2); // six
+        |    if x {
+        |        // nothing yet
+        |    }
+        |    y := (x + 1);
+        |    // within a statement
+        |    y
+        |    // end of main
+        |} // after main
+        |// end of file
+        |"#
+        ) + "\n"
+    );
+    assert_eq!(
+        diff_parses(&strip_margin(source_code), &print(source_code)).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_print_match_expr() {
+    let source_code = r#"
+        |main :: () -> i32 {
+        |    match ({ 3 }) { -1 => 0, 0..10 => { 1 } 10..=99 => {} _ => 2 }
+        |}
+        |"#;
+
+    assert_eq!(
+        print(source_code),
+        strip_margin(
+            r#"
+        |main :: () -> i32 {
+        |    match ({
+        |        3
+        |    }) {
+        |        -1 => 0,
+        |        0..10 => {
+        |            1
+        |        }
+        |        10..=99 => {}
+        |        _ => 2,
+        |    }
+        |}
+        |"#
+        ) + "\n"
+    );
+    assert_eq!(
+        diff_parses(&strip_margin(source_code), &print(source_code)).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_print_structs() {
+    let source_code = r#"
+        |Empty :: struct {}
+        |Point :: struct { x: i32, y: i32 }
+        |main :: () -> i32 {
+        |    p := Point { y: 2, x: (1 + 2) };
+        |    if (Point { x: 1, y: p.y }).x { (p.x + 1) * 2 } else { Empty {}; 0 }
+        |}
+        |"#;
+
+    assert_eq!(
+        print(source_code),
+        strip_margin(
+            r#"
+        |Empty :: struct {}
+        |Point :: struct { x: i32, y: i32 }
+        |main :: () -> i32 {
+        |    p := Point { y: 2, x: (1 + 2) };
+        |    if (Point { x: 1, y: p.y }).x {
+        |        (p.x + 1) * 2
+        |    } else {
+        |        Empty {};
+        |        0
+        |    }
+        |}
+        |"#
+        ) + "\n"
+    );
+    assert_eq!(
+        diff_parses(&strip_margin(source_code), &print(source_code)).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_print_enums() {
+    let source_code = r#"
+        |Never :: enum {}
+        |Shape :: enum { Circle(i32), Empty, }
+        |main :: () -> i32 {
+        |    match Shape::Circle(1 + 2) { Shape::Circle(r) => r, Shape::Empty => 0 }
+        |}
+        |"#;
+
+    assert_eq!(
+        print(source_code),
+        strip_margin(
+            r#"
+        |Never :: enum {}
+        |Shape :: enum { Circle(i32), Empty }
+        |main :: () -> i32 {
+        |    match Shape::Circle(1 + 2) {
+        |        Shape::Circle(r) => r,
+        |        Shape::Empty => 0,
+        |    }
+        |}
+        |"#
+        ) + "\n"
+    );
+    assert_eq!(
+        diff_parses(&strip_margin(source_code), &print(source_code)).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_print_tuples() {
+    let source_code = r#"
+        |swap :: (pair: (i32, (i32,))) -> ((i32,), i32) {
+        |    a, _ := (pair.1, (pair.0 + 1)) ; (a, pair.1.0)
+        |}
+        |"#;
+
+    assert_eq!(
+        print(source_code),
+        strip_margin(
+            r#"
+        |swap :: (pair: (i32, (i32,))) -> ((i32,), i32) {
+        |    a, _ := (pair.1, (pair.0 + 1));
+        |    (a, pair.1.0)
+        |}
+        |"#
+        ) + "\n"
+    );
+    assert_eq!(
+        diff_parses(&strip_margin(source_code), &print(source_code)).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_print_arrays() {
+    let source_code = r#"
+        |first :: (grid: [[i32; 2]; 3]) -> [i32; 2] {
+        |    copy := grid ; copy[0][1 + 0] = [4, 5,][1] ;
+        |    for row : grid { copy[1] = row ; }
+        |    copy[0]
+        |}
+        |"#;
+
+    assert_eq!(
+        print(source_code),
+        strip_margin(
+            r#"
+        |first :: (grid: [[i32; 2]; 3]) -> [i32; 2] {
+        |    copy := grid;
+        |    copy[0][1 + 0] = [4, 5][1];
+        |    for row : grid {
+        |        copy[1] = row;
+        |    }
+        |    copy[0]
+        |}
+        |"#
+        ) + "\n"
+    );
+    assert_eq!(
+        diff_parses(&strip_margin(source_code), &print(source_code)).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_print_built_ast_parenthesizes_by_precedence() {
+    let context = CompilerContext::new(String::new());
+    let span = Span {
+        start: BytePos(0),
+        end: BytePos(0),
+    };
+    let constant = |value| {
+        context.alloc_expr(Expr::Const(ConstExpr {
+            value: Const::IntegerConstant { value },
+            span,
+        }))
+    };
+    let binary = |op, lhs, rhs| context.alloc_expr(Expr::Binary(BinaryExpr { op, lhs, rhs, span }));
+
+    // (1 - (2 - 3)) * 4, and then - 5 * 6, left as is.
+    let value = binary(
+        BinaryOp::Sub,
+        binary(
+            BinaryOp::Mul,
+            binary(
+                BinaryOp::Sub,
+                constant(1),
+                binary(BinaryOp::Sub, constant(2), constant(3)),
+            ),
+            constant(4),
+        ),
+        binary(BinaryOp::Mul, constant(5), constant(6)),
+    );
+    let function = Expr::Function(Function {
+        return_type: Type::I32,
+        parameters: context.alloc_slice_of_param(&[]),
+        body: CompoundExpr {
+            exprs: context.alloc_slice_of_expr(&[*value]),
+            span,
+        },
+        span,
+    });
+    let program = Program {
+        decls: context.alloc_slice_of_decl(&[Decl {
+            identifier: context.get_or_intern_str("main"),
+            identifier_span: span,
+            value: context.alloc_expr(function),
+        }]),
+    };
+
+    assert_eq!(
+        SourcePrinter::new(&context).print_program(program),
+        strip_margin(
+            r#"
+        |main :: () -> i32 {
+        |    (1 - (2 - 3)) * 4 - 5 * 6
+        |}
+        |"#
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_emit_source_after_dead_code_elimination() {
+    let options = Options {
+        emit: Emit::Source,
+        ..Default::default()
+    };
+
+    let source_code = compile_with_options(
+        r#"
+        |main :: () -> i32 {
+        |    for {
+        |        break;
+        |        _ = 1;
+        |    }
+        |    0
+        |}
+        |"#,
+        &options,
+    );
+
+    assert_eq!(
+        source_code,
+        strip_margin(
+            r#"
+        |main :: () -> i32 {
+        |    for {
+        |        break;
+        |    }
+        |    0
+        |}
+        |"#
+        )
+    );
+}
+
+#[test]
+fn test_print_typed_bindings() {
+    let source_code = r#"
+        |main :: () -> i32 {
+        |    x:i32=1 ; _ :(i32, ())= (x, {}) ; _:u64=255u8+1usize; _:( )->i32 =main; x
+        |}
+        |"#;
+
+    assert_eq!(
+        print(source_code),
+        strip_margin(
+            r#"
+        |main :: () -> i32 {
+        |    x : i32 = 1;
+        |    _ : (i32, ()) = (x, {});
+        |    _ : u64 = 255u8 + 1usize;
+        |    _ : () -> i32 = main;
+        |    x
+        |}
+        |"#
+        ) + "\n"
+    );
+    assert_eq!(
+        diff_parses(&strip_margin(source_code), &print(source_code)).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_print_casts() {
+    let source_code = r#"
+        |main :: () -> i32 {
+        |    x:=(1+2)as u8 as i64*3 as i64;
+        |    pair := (x, 4);
+        |    (pair.0 as i32) + (pair as(i64,i32)).1
+        |}
+        |"#;
+
+    assert_eq!(
+        print(source_code),
+        strip_margin(
+            r#"
+        |main :: () -> i32 {
+        |    x := (1 + 2) as u8 as i64 * 3 as i64;
+        |    pair := (x, 4);
+        |    (pair.0 as i32) + (pair as (i64, i32)).1
+        |}
+        |"#
+        ) + "\n"
+    );
+    assert_eq!(
+        diff_parses(&strip_margin(source_code), &print(source_code)).unwrap(),
+        None
+    );
+}
@@ -0,0 +1,179 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast::BinaryOp;
+use crate::c::prelude;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Emit, Options};
+use crate::interp::RuntimeError;
+use crate::profile::{IntProfile, IntWidth, Overflow};
+use crate::tests::{run_with_profile, span, strip_margin};
+
+fn options(int_profile: IntProfile) -> Options {
+    Options {
+        int_profile,
+        ..Default::default()
+    }
+}
+
+const BITS_16: IntProfile = IntProfile {
+    width: IntWidth::Bits16,
+    overflow: Overflow::Wrap,
+    has_hardware_division: true,
+};
+
+#[test]
+fn test_profile_evaluates_operations() {
+    let trapping_16 = IntProfile {
+        overflow: Overflow::Trap,
+        ..BITS_16
+    };
+
+    assert_eq!(BITS_16.eval(BinaryOp::Add, 32767, 1), Some(-32768));
+    assert_eq!(BITS_16.eval(BinaryOp::Mul, 300, 300), Some(24464));
+    assert_eq!(trapping_16.eval(BinaryOp::Add, 32767, 1), None);
+    assert_eq!(trapping_16.eval(BinaryOp::Sub, -32767, 1), Some(-32768));
+    assert_eq!(BITS_16.eval(BinaryOp::Div, -32768, -1), None);
+    assert_eq!(BITS_16.eval(BinaryOp::Rem, -32768, -1), None);
+    assert_eq!(BITS_16.eval(BinaryOp::Rem, -7, 2), Some(-1));
+    assert_eq!(IntProfile::default().eval(BinaryOp::Div, 7, 0), None);
+    assert_eq!(
+        IntProfile::default().eval(BinaryOp::Add, i32::MAX, 1),
+        Some(i32::MIN)
+    );
+}
+
+#[test]
+fn test_interpreter_wraps_around_16_bit_integers() {
+    let exit_code = run_with_profile(
+        r#"
+        |main :: () -> i32 {
+        |    x := 32767 + 2;
+        |    x / 256
+        |}
+        |"#,
+        BITS_16,
+    );
+
+    assert_eq!(exit_code, Ok(-127));
+}
+
+#[test]
+fn test_interpreter_traps_on_overflow() {
+    let profile = IntProfile {
+        overflow: Overflow::Trap,
+        ..Default::default()
+    };
+
+    let exit_code = run_with_profile(
+        r#"
+        |main :: () -> i32 {
+        |    2147483647 + 1
+        |}
+        |"#,
+        profile,
+    );
+
+    assert_eq!(
+        exit_code,
+        Err(RuntimeError::Overflow {
+            op: BinaryOp::Add,
+            span: span(24, 38),
+        })
+    );
+}
+
+#[test]
+fn test_loop_variable_stops_before_overflowing() {
+    let profile = IntProfile {
+        overflow: Overflow::Trap,
+        ..BITS_16
+    };
+
+    let exit_code = run_with_profile(
+        r#"
+        |main :: () -> i32 {
+        |    counts := [0, 0];
+        |    for i : 32760..=32767 step 3 {
+        |        counts[0] = counts[0] + 1;
+        |    }
+        |    for i : rev 0 - 32767 - 1..=0 - 32760 step 5 {
+        |        counts[1] = counts[1] + 1;
+        |    }
+        |    counts[0] * 10 + counts[1]
+        |}
+        |"#,
+        profile,
+    );
+
+    // 32760, 32763 and 32766, then -32760 and -32765.
+    assert_eq!(exit_code, Ok(32));
+}
+
+#[test]
+fn test_integer_constant_out_of_16_bit_range() {
+    let diagnostic = driver::check(
+        strip_margin(
+            r#"
+            |main :: () -> i32 {
+            |    32767 + 32768
+            |}
+            |"#,
+        ),
+        &options(BITS_16),
+    );
+
+    assert_eq!(
+        diagnostic.errors,
+        vec![CompileError::IntegerConstantOutOfRange {
+            width: IntWidth::Bits16,
+            span: span(32, 37),
+        }]
+    );
+}
+
+#[test]
+fn test_only_c_backend_supports_other_profiles() {
+    let source_code = "main :: () {}";
+
+    for (emit, backend) in [
+        (Emit::Assembly, "x86-64"),
+        (Emit::LlvmIr, "LLVM IR"),
+        (Emit::Wat, "WebAssembly"),
+    ] {
+        let options = Options {
+            emit,
+            ..options(BITS_16)
+        };
+
+        let errors = match driver::compile(source_code, &options) {
+            Ok(_) => panic!("{:?} backend accepted a 16-bit profile", emit),
+            Err(diagnostic) => diagnostic.errors,
+        };
+
+        assert_eq!(
+            errors,
+            vec![CompileError::IntProfileUnsupported { backend }]
+        );
+    }
+
+    let options = Options {
+        emit: Emit::C,
+        ..options(BITS_16)
+    };
+
+    assert!(driver::compile(source_code, &options).is_ok());
+}
+
+#[test]
+fn test_c_prelude_follows_profile() {
+    let prelude = prelude(IntProfile {
+        overflow: Overflow::Trap,
+        has_hardware_division: false,
+        ..BITS_16
+    });
+
+    assert!(prelude.contains("typedef int16_t sophia_int;"));
+    assert!(prelude.contains("static inline sophia_int sophia_checked(long long value) {"));
+    assert!(prelude.contains("static inline sophia_int sophia_divmod("));
+    assert!(!prelude.contains("lhs / rhs"));
+}
@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use pretty_assertions::assert_eq;
+
+use crate::driver::{self, Options};
+use crate::profiler::{ExecutionProfile, FunctionProfile};
+use crate::tests::strip_margin;
+
+fn profile(source_code: &str) -> ExecutionProfile {
+    let options = Options {
+        profiling: true,
+        ..Default::default()
+    };
+
+    match driver::run(strip_margin(source_code), &options) {
+        Ok(execution) => execution.profile.expect("the program was profiled"),
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+fn function<'a>(profile: &'a ExecutionProfile, name: &str) -> &'a FunctionProfile {
+    profile
+        .functions
+        .iter()
+        .find(|function| function.name == name)
+        .unwrap_or_else(|| panic!("`{}` wasn't profiled", name))
+}
+
+#[test]
+fn test_profile_counts_calls_and_allocations() {
+    let profile = profile(
+        r#"
+        |double :: (x: i32) -> i32 { x * 2 }
+        |main :: () -> i32 {
+        |    a := double(1)
+        |    double(a)
+        |}
+        |"#,
+    );
+
+    let counts: Vec<_> = profile
+        .functions
+        .iter()
+        .map(|function| (function.name.as_str(), function.calls, function.allocations))
+        .collect();
+
+    // Every call enters the scope of its parameters and then its body's, and
+    // `main` allocates the argument lists of its calls.
+    assert_eq!(counts, [("main", 1, 4), ("double", 2, 4)]);
+}
+
+#[test]
+fn test_profile_stacks_start_from_main() {
+    let profile = profile(
+        r#"
+        |leaf :: () {}
+        |middle :: () { leaf() }
+        |main :: () {
+        |    middle()
+        |    leaf()
+        |}
+        |"#,
+    );
+
+    let stacks: Vec<_> = profile
+        .stacks
+        .iter()
+        .map(|(stack, _)| stack.join(";"))
+        .collect();
+    assert_eq!(
+        stacks,
+        ["main", "main;leaf", "main;middle", "main;middle;leaf"]
+    );
+
+    let folded_stacks = profile.to_folded_stacks();
+    let folded_stacks: Vec<_> = folded_stacks
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect();
+    assert_eq!(folded_stacks, stacks);
+}
+
+#[test]
+fn test_profile_counts_time_of_recursive_calls_once() {
+    let profile = profile(
+        r#"
+        |count :: (n: i32) -> i32 {
+        |    if n { count(n - 1) + 1 } else { 0 }
+        |}
+        |main :: () -> i32 { count(20) }
+        |"#,
+    );
+
+    let main = function(&profile, "main");
+    let count = function(&profile, "count");
+
+    assert_eq!(count.calls, 21);
+    assert!(count.total_time <= main.total_time);
+    assert!(count.self_time <= count.total_time);
+}
+
+#[test]
+fn test_flat_profile_lists_functions_by_self_time() {
+    let function = |name: &str, self_micros| FunctionProfile {
+        name: name.to_owned(),
+        calls: 1,
+        self_time: Duration::from_micros(self_micros),
+        total_time: Duration::from_micros(self_micros),
+        allocations: 2,
+    };
+    let profile = ExecutionProfile {
+        functions: vec![function("main", 1500), function("helper", 2250)],
+        stacks: vec![],
+    };
+
+    assert_eq!(
+        profile.to_flat_profile(),
+        strip_margin(
+            r#"
+            |        self        total      calls     allocs  function
+            |     2.250ms      2.250ms          1          2  helper
+            |     1.500ms      1.500ms          1          2  main
+            |"#
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_run_without_profiling_has_no_profile() {
+    let execution = driver::run("main :: () {}", &Options::default()).unwrap();
+
+    assert_eq!(execution.profile, None);
+}
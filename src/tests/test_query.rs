@@ -0,0 +1,205 @@
+use pretty_assertions::assert_eq;
+
+use crate::driver::{self, Options};
+use crate::query::{QueryDatabase, QueryStats};
+use crate::tests::strip_margin;
+
+/// Checks the version with the queries, asserting that it's checked the same
+/// as from scratch, and returning how many queries were run for it.
+fn check_version(queries: &mut QueryDatabase, source_code: &str) -> QueryStats {
+    let options = Options::default();
+    let stats_before = queries.stats();
+
+    let context = driver::new_context(source_code, &options);
+    let full_diagnostic = match driver::parse(&context, &options) {
+        Ok(program) => driver::check_program(&context, program, &options),
+        Err(diagnostic) => diagnostic,
+    };
+
+    assert_eq!(queries.check(source_code, &options), full_diagnostic);
+
+    let stats_after = queries.stats();
+
+    QueryStats {
+        parsed_files: stats_after.parsed_files - stats_before.parsed_files,
+        parsed_decls: stats_after.parsed_decls - stats_before.parsed_decls,
+        resolved_decls: stats_after.resolved_decls - stats_before.resolved_decls,
+        checked_decls: stats_after.checked_decls - stats_before.checked_decls,
+    }
+}
+
+fn three_decls(a_body: &str, b_signature: &str) -> String {
+    strip_margin(&format!(
+        r#"
+        |a :: () -> i32 {{
+        |    {}
+        |}}
+        |b :: {} {{
+        |    unused := 2;
+        |    2
+        |}}
+        |main :: () -> i32 {{
+        |    a() + b()
+        |}}
+        |"#,
+        a_body, b_signature
+    ))
+}
+
+#[test]
+fn test_versions_are_checked_the_same_as_from_scratch() {
+    let mut queries = QueryDatabase::default();
+
+    let versions = [
+        // Warnings, of the linter and of unused bindings and declarations.
+        strip_margin(
+            r#"
+            |Point :: struct { x: i32, y: i32 }
+            |helper :: () -> i32 { 0 }
+            |main :: () -> i32 {
+            |    p := Point { x: 1, y: 2 };
+            |    unused := 3;
+            |    p.y;
+            |    p.x
+            |}
+            |"#,
+        ),
+        // Type errors, in several declarations.
+        strip_margin(
+            r#"
+            |Point :: struct { x: i32, y: i32 }
+            |helper :: () -> i32 {
+            |    Point { x: 1, y: 2 }
+            |}
+            |main :: () -> i32 {
+            |    p := Point { x: 1, y: 2 };
+            |    p.z + helper(1)
+            |}
+            |"#,
+        ),
+        // Names that don't resolve.
+        strip_margin(
+            r#"
+            |Point :: struct { x: i32, y: i32 }
+            |helper :: () -> i32 { y }
+            |main :: () -> i32 {
+            |    p := Point { x: 1, y: 2 };
+            |    p.x + z
+            |}
+            |"#,
+        ),
+        // Types that can't be laid out.
+        strip_margin(
+            r#"
+            |Point :: struct { x: i32, next: Point }
+            |helper :: () -> i32 { 0 }
+            |main :: () -> i32 {
+            |    helper()
+            |}
+            |"#,
+        ),
+        // Syntax errors.
+        strip_margin(
+            r#"
+            |helper :: () -> i32 { 0
+            |main :: () -> i32 {
+            |    helper()
+            |}
+            |"#,
+        ),
+        three_decls("1", "() -> i32"),
+    ];
+
+    for version in &versions {
+        check_version(&mut queries, version);
+    }
+}
+
+#[test]
+fn test_only_edited_decls_are_checked_again() {
+    let mut queries = QueryDatabase::default();
+
+    assert_eq!(
+        check_version(&mut queries, &three_decls("1", "() -> i32")),
+        QueryStats {
+            parsed_files: 1,
+            parsed_decls: 3,
+            resolved_decls: 3,
+            checked_decls: 3,
+        }
+    );
+
+    // The other declarations move, and their warnings with them.
+    assert_eq!(
+        check_version(
+            &mut queries,
+            &three_decls("x := 1;\n|    x + 1", "() -> i32")
+        ),
+        QueryStats {
+            parsed_files: 1,
+            parsed_decls: 1,
+            resolved_decls: 1,
+            checked_decls: 1,
+        }
+    );
+
+    assert_eq!(
+        check_version(
+            &mut queries,
+            &three_decls("x := 1;\n|    x + y", "() -> i32")
+        ),
+        QueryStats {
+            parsed_files: 1,
+            parsed_decls: 1,
+            resolved_decls: 1,
+            checked_decls: 0,
+        }
+    );
+
+    // Nothing changed since the last version.
+    assert_eq!(
+        check_version(
+            &mut queries,
+            &three_decls("x := 1;\n|    x + y", "() -> i32")
+        ),
+        QueryStats::default()
+    );
+}
+
+#[test]
+fn test_changed_signatures_check_every_decl_again() {
+    let mut queries = QueryDatabase::default();
+
+    check_version(&mut queries, &three_decls("1", "() -> i32"));
+
+    // `main` calls `b` with the wrong arguments now, even though only `b`
+    // changed.
+    assert_eq!(
+        check_version(&mut queries, &three_decls("1", "(n: i32) -> i32")),
+        QueryStats {
+            parsed_files: 1,
+            parsed_decls: 1,
+            resolved_decls: 1,
+            checked_decls: 3,
+        }
+    );
+}
+
+#[test]
+fn test_changed_names_resolve_every_decl_again() {
+    let mut queries = QueryDatabase::default();
+
+    check_version(&mut queries, &three_decls("1", "() -> i32"));
+
+    let renamed = three_decls("1", "() -> i32").replace("b ::", "c ::");
+
+    assert_eq!(
+        check_version(&mut queries, &renamed),
+        QueryStats {
+            parsed_files: 1,
+            parsed_decls: 1,
+            resolved_decls: 3,
+            checked_decls: 0,
+        }
+    );
+}
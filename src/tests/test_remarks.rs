@@ -0,0 +1,101 @@
+use pretty_assertions::assert_eq;
+
+use crate::diagnostics::Remark;
+use crate::driver::{self, Options};
+use crate::render::DiagnosticRenderer;
+use crate::source_map::SourceMap;
+use crate::tests::{span, strip_margin};
+
+fn remarks(source_code: &str) -> Vec<Remark> {
+    let options = Options {
+        remarks: true,
+        ..Default::default()
+    };
+
+    match driver::compile(strip_margin(source_code), &options) {
+        Ok(compiled_program) => compiled_program.remarks,
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+#[test]
+fn test_remark_removed_unreachable_code() {
+    let remarks = remarks(
+        r#"
+        |main :: () {
+        |    for { break; _ = 1; _ = 2; }
+        |    for { continue; _ = 3; }
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        remarks,
+        [
+            Remark::RemovedUnreachableCode {
+                count: 2,
+                span: span(30, 42),
+            },
+            Remark::RemovedUnreachableCode {
+                count: 1,
+                span: span(66, 71),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_remark_kept_unreachable_value() {
+    let remarks = remarks(
+        r#"
+        |main :: () -> i32 {
+        |    for { break; _ = 1; 2 }
+        |    0
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        remarks,
+        [
+            Remark::RemovedUnreachableCode {
+                count: 1,
+                span: span(37, 42),
+            },
+            Remark::KeptUnreachableValue { span: span(44, 45) },
+        ]
+    );
+}
+
+#[test]
+fn test_no_remarks_unless_asked_for() {
+    let compiled_program =
+        driver::compile("main :: () { for { break; _ = 1; } }", &Options::default()).unwrap();
+
+    assert_eq!(compiled_program.remarks, []);
+}
+
+#[test]
+fn test_render_remark() {
+    let source_code = "main :: () { for { break; _ = 1; } }";
+    let mut source_map = SourceMap::new();
+    source_map.add_file("main.sph", source_code);
+
+    let remark = Remark::RemovedUnreachableCode {
+        count: 1,
+        span: span(26, 32),
+    };
+
+    assert_eq!(
+        DiagnosticRenderer::new(&source_map).render_remark(&remark),
+        strip_margin(
+            r#"
+            |remark[dce]: removed an unreachable expression
+            | --> main.sph:1:27
+            |  |
+            |1 | main :: () { for { break; _ = 1; } }
+            |  |                           ^^^^^^
+            |"#
+        ) + "\n"
+    );
+}
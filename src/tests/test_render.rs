@@ -0,0 +1,269 @@
+use crate::diagnostics::{CompileError, CompileWarning, Diagnostic};
+use crate::driver::{self, Options};
+use crate::json::Json;
+use crate::render::{DiagnosticCounts, DiagnosticRenderer, ErrorFormat};
+use crate::source_map::SourceMap;
+use crate::tests::{span, strip_margin};
+
+fn main_source_map(source_code: &str) -> SourceMap {
+    let mut source_map = SourceMap::new();
+    source_map.add_file("main.sph", source_code);
+
+    source_map
+}
+
+#[test]
+fn test_render_error_with_source_line() {
+    let source_code = strip_margin(
+        r#"
+        |main :: () -> i32 {
+        |    x + 1
+        |}
+        |"#,
+    );
+    let diagnostic = driver::check(&source_code, &Options::default());
+
+    assert_eq!(
+        DiagnosticRenderer::new(&main_source_map(&source_code)).render(&diagnostic),
+        strip_margin(
+            r#"
+            |error[E0002]: cannot find `x` in this scope
+            | --> main.sph:2:5
+            |  |
+            |2 |     x + 1
+            |  |     ^
+            |"#
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_render_warnings_before_errors() {
+    let source_code = "a :: () {}\nmain :: () { b() }\n";
+    let diagnostic = Diagnostic {
+        errors: vec![CompileError::UndefinedName {
+            name: "b".to_owned(),
+            span: span(24, 27),
+            suggestion: None,
+        }],
+        warnings: vec![CompileWarning::UnusedDecl {
+            name: "a".to_owned(),
+            span: span(0, 1),
+        }],
+    };
+
+    assert_eq!(
+        DiagnosticRenderer::new(&main_source_map(source_code)).render(&diagnostic),
+        strip_margin(
+            r#"
+            |warning: `a` is never used by `main`
+            | --> main.sph:1:1
+            |  |
+            |1 | a :: () {}
+            |  | ^
+            |error[E0002]: cannot find `b` in this scope
+            | --> main.sph:2:14
+            |  |
+            |2 | main :: () { b() }
+            |  |              ^^^
+            |"#
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_render_span_over_many_lines_underlines_first_line() {
+    let source_code = format!("{}main :: () {{\n    for {{\n    }}\n}}\n", "\n".repeat(9));
+    let rendered = DiagnosticRenderer::new(&main_source_map(&source_code))
+        .render_warning(&CompileWarning::UnreachableCode { span: span(26, 38) });
+
+    assert_eq!(
+        rendered,
+        strip_margin(
+            r#"
+            |warning: unreachable code after `break` or `continue`
+            |  --> main.sph:11:5
+            |   |
+            |11 |     for {
+            |   |     ^^^^^
+            |"#
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_render_error_about_whole_program_without_source_line() {
+    let rendered = DiagnosticRenderer::new(&main_source_map("main :: () {}"))
+        .render_error(&CompileError::HostFunctionsUnsupported { backend: "C" });
+
+    assert_eq!(
+        rendered,
+        "error[E0013]: the C backend can't call host functions; compile to bytecode to run them in \
+         the VM\n"
+    );
+}
+
+#[test]
+fn test_render_with_colors() {
+    let rendered = DiagnosticRenderer::new(&main_source_map("main :: () { x }"))
+        .with_colors(true)
+        .render_error(&CompileError::UndefinedName {
+            name: "x".to_owned(),
+            span: span(13, 14),
+            suggestion: None,
+        });
+
+    assert_eq!(
+        rendered,
+        "\x1b[1;31merror[E0002]\x1b[0m\x1b[1m: cannot find `x` in this scope\x1b[0m\n \
+         \x1b[1;34m-->\x1b[0m main.sph:1:14\n  \x1b[1;34m|\x1b[0m\n\x1b[1;34m1\x1b[0m \
+         \x1b[1;34m|\x1b[0m main :: () { x }\n  \x1b[1;34m|\x1b[0m              \
+         \x1b[1;31m^\x1b[0m\n"
+    );
+}
+
+#[test]
+fn test_render_json_line_per_diagnostic() {
+    let source_code = strip_margin(
+        r#"
+        |unused :: () {}
+        |main :: () -> i32 {
+        |    x + 1
+        |}
+        |"#,
+    );
+    let mut diagnostic = driver::check(&source_code, &Options::default());
+    // Lints don't run on programs with errors.
+    diagnostic.warnings.push(CompileWarning::UnusedDecl {
+        name: "unused".to_owned(),
+        span: span(0, 6),
+    });
+
+    assert_eq!(
+        DiagnosticRenderer::new(&main_source_map(&source_code))
+            .with_colors(true)
+            .with_format(ErrorFormat::Json)
+            .render(&diagnostic),
+        strip_margin(
+            r#"
+            |{"code":"unused-decl","severity":"warning","message":"`unused` is never used by `main`","spans":[{"file":"main.sph","start":0,"end":6,"line":1,"col":1}]}
+            |{"code":"E0002","severity":"error","message":"cannot find `x` in this scope","spans":[{"file":"main.sph","start":40,"end":41,"line":3,"col":5}]}
+            |"#
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_render_json_error_about_whole_program_without_spans() {
+    let rendered = DiagnosticRenderer::new(&main_source_map("main :: () {}"))
+        .with_format(ErrorFormat::Json)
+        .render_error(&CompileError::HostFunctionsUnsupported { backend: "C" });
+    let json = Json::parse(rendered.trim_end()).unwrap();
+
+    assert_eq!(json.get("code"), Some(&Json::from("E0013")));
+    assert_eq!(json.get("severity"), Some(&Json::from("error")));
+    assert_eq!(json.get("spans"), Some(&Json::Array(vec![])));
+}
+
+#[test]
+fn test_render_denied_warning_as_error_with_lint_name() {
+    let rendered = DiagnosticRenderer::new(&main_source_map("a :: () {}\n")).render_denied_warning(
+        &CompileWarning::UnusedDecl {
+            name: "a".to_owned(),
+            span: span(0, 1),
+        },
+    );
+
+    assert_eq!(
+        rendered,
+        strip_margin(
+            r#"
+            |error[unused-decl]: `a` is never used by `main`
+            | --> main.sph:1:1
+            |  |
+            |1 | a :: () {}
+            |  | ^
+            |"#
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_render_shadowed_binding_with_note() {
+    let source_code = strip_margin(
+        r#"
+        |main :: () -> i32 {
+        |    x := 1;
+        |    if x {
+        |        x := 2;
+        |        _ = x;
+        |    }
+        |    x
+        |}
+        |"#,
+    );
+    let diagnostic = driver::check(&source_code, &Options::default());
+
+    assert_eq!(
+        DiagnosticRenderer::new(&main_source_map(&source_code)).render(&diagnostic),
+        strip_margin(
+            r#"
+            |warning: binding `x` shadows an outer binding of the same name
+            | --> main.sph:4:9
+            |  |
+            |4 |         x := 2;
+            |  |         ^^^^^^
+            |note: shadowed binding `x` is defined here
+            | --> main.sph:2:5
+            |  |
+            |2 |     x := 1;
+            |  |     ------
+            |"#
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_render_duplicate_decl_with_note() {
+    let source_code = "main :: () -> i32 { 0 }\nmain :: () {}\n";
+    let diagnostic = driver::check(source_code, &Options::default());
+
+    assert_eq!(
+        DiagnosticRenderer::new(&main_source_map(source_code)).render(&diagnostic),
+        strip_margin(
+            r#"
+            |error[E0054]: `main` is declared more than once
+            | --> main.sph:2:1
+            |  |
+            |2 | main :: () {}
+            |  | ^^^^
+            |note: `main` is first declared here
+            | --> main.sph:1:1
+            |  |
+            |1 | main :: () -> i32 { 0 }
+            |  | ----
+            |"#
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_render_summary() {
+    let source_map = main_source_map("");
+    let counts = DiagnosticCounts {
+        errors: 1,
+        warnings: 2,
+        remarks: 0,
+    };
+
+    assert_eq!(
+        DiagnosticRenderer::new(&source_map).render_summary(counts),
+        "summary: 1 error, 2 warnings, 0 remarks\n"
+    );
+    assert_eq!(
+        DiagnosticRenderer::new(&source_map)
+            .with_format(ErrorFormat::Json)
+            .render_summary(counts),
+        "{\"errors\":1,\"warnings\":2,\"remarks\":0}\n"
+    );
+}
@@ -0,0 +1,155 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use pretty_assertions::assert_eq;
+
+use crate::diagnostics::CompileError;
+use crate::driver::Options;
+use crate::repl::{self, Repl};
+
+/// Evaluates the inputs in order, returning what each one printed followed by
+/// its value, or what made it fail.
+fn eval_inputs(inputs: &[&str]) -> Vec<String> {
+    let output = Rc::new(RefCell::new(vec![]));
+    let mut repl = Repl::new(Options {
+        output: Some(output.clone()),
+        ..Default::default()
+    });
+
+    inputs
+        .iter()
+        .map(|input| {
+            let outcome = repl.eval(input);
+            let mut text = String::from_utf8(output.take()).unwrap();
+
+            match outcome.evaluation {
+                Ok(evaluation) => match evaluation.value {
+                    Ok(Some(value)) => text.push_str(&value),
+                    Ok(None) => {}
+                    Err(error) => text.push_str(&format!("runtime error: {}", error)),
+                },
+                Err(diagnostic) => text.push_str(&format!("{} errors", diagnostic.errors.len())),
+            }
+
+            text
+        })
+        .collect()
+}
+
+#[test]
+fn test_inputs_use_earlier_declarations_and_bindings() {
+    let outputs = eval_inputs(&[
+        "x := 40",
+        "x + 2",
+        "square :: (n: i32) -> i32 {\n    n * n\n}",
+        "y := square(x / 10)",
+        "(y, square)",
+    ]);
+
+    assert_eq!(outputs, ["", "42", "", "", "(16, square)"]);
+}
+
+#[test]
+fn test_earlier_statements_print_only_once() {
+    let outputs = eval_inputs(&["x := 7", "println(x)", "print(x);\nx * 2", "print(x)"]);
+
+    assert_eq!(outputs, ["", "7\n", "714", "7"]);
+}
+
+#[test]
+fn test_bindings_ended_with_semicolon_are_kept() {
+    let outputs = eval_inputs(&["x := 5;", "x + 1", "a, b := (x, 2);\nc := a * b;", "c"]);
+
+    assert_eq!(outputs, ["", "6", "", "10"]);
+}
+
+#[test]
+fn test_earlier_statements_dont_run_again() {
+    let outputs = eval_inputs(&[
+        "counts := [0];",
+        "counts[0] = counts[0] + 1;",
+        "counts[0] = counts[0] + 10;",
+        "counts",
+    ]);
+
+    assert_eq!(outputs, ["", "", "", "[11]"]);
+}
+
+#[test]
+fn test_closures_of_earlier_inputs_can_be_called() {
+    let outputs = eval_inputs(&[
+        "adder :: (n: i32) -> (i32) -> i32 {\n    (x: i32) -> i32 { x + n }\n}",
+        "add_two := adder(2);",
+        "double := (x: i32) -> i32 { x * 2 }",
+        "double(add_two(3))",
+    ]);
+
+    assert_eq!(outputs, ["", "", "", "10"]);
+}
+
+#[test]
+fn test_failed_inputs_are_forgotten() {
+    let outputs = eval_inputs(&["x := 1 / 0", "x", "x := 3", "y", "x"]);
+
+    assert_eq!(
+        outputs,
+        [
+            "runtime error: attempt to divide by zero",
+            "1 errors",
+            "",
+            "1 errors",
+            "3"
+        ]
+    );
+}
+
+#[test]
+fn test_values_are_rendered_like_source_code() {
+    let outputs = eval_inputs(&[
+        "Point :: struct { x: i32, y: i32 }",
+        "Shape :: enum { Dot, Square(i32) }",
+        "Point { x: 1, y: 0 - 2 }",
+        "shape := Shape::Square(3)",
+        "shape",
+        "(5i64, (Shape::Dot,))",
+        "(n: i32) -> i32 { n }",
+    ]);
+
+    assert_eq!(
+        outputs,
+        [
+            "",
+            "",
+            "{ x: 1, y: -2 }",
+            "",
+            "Square(3)",
+            "(5, (Dot,))",
+            "<closure>"
+        ]
+    );
+}
+
+#[test]
+fn test_statements_cant_come_with_main() {
+    let mut repl = Repl::new(Options::default());
+    repl.eval("x := 1");
+
+    let Err(diagnostic) = repl.eval("main :: () {}").evaluation else {
+        panic!("`main` was declared along with statements");
+    };
+
+    assert!(matches!(
+        diagnostic.errors[..],
+        [CompileError::ScriptStatementsWithMain { .. }]
+    ));
+}
+
+#[test]
+fn test_inputs_with_open_delimiters_are_incomplete() {
+    assert!(repl::is_incomplete("f :: () {\n"));
+    assert!(repl::is_incomplete("g((1, 2\n"));
+    assert!(!repl::is_incomplete("f :: () {}\n"));
+    assert!(!repl::is_incomplete("x := 1 + 2\n"));
+    // Stray closing delimiters are left for the parser to report.
+    assert!(!repl::is_incomplete(")\n"));
+}
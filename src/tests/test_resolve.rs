@@ -0,0 +1,305 @@
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::resolve::{Capture, Resolution, Resolutions, Resolver};
+use crate::tests::{span, strip_margin};
+
+fn resolve(source_code: &str) -> Result<Resolutions, Vec<CompileError>> {
+    let context = CompilerContext::new(strip_margin(source_code));
+    let program = driver::parse(&context, &Options::default()).unwrap();
+
+    Resolver::new(&context, &[]).resolve_program(program)
+}
+
+#[test]
+fn test_resolve_local_binding() {
+    let resolutions = resolve(
+        r#"
+        |main :: () {
+        |    x := 1
+        |    x
+        |}
+        |"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        resolutions.get(span(28, 29)),
+        Some(Resolution::Local(span(17, 23)))
+    );
+}
+
+#[test]
+fn test_resolve_function_call_to_later_decl() {
+    let resolutions = resolve(
+        r#"
+        |main :: () {
+        |    foo()
+        |}
+        |foo :: () {}
+        |"#,
+    )
+    .unwrap();
+
+    assert_eq!(resolutions.get(span(17, 22)), Some(Resolution::Decl(1)));
+}
+
+#[test]
+fn test_resolve_innermost_binding() {
+    let resolutions = resolve(
+        r#"
+        |main :: () {
+        |    x := 1
+        |    {
+        |        x := x
+        |        x
+        |    }
+        |}
+        |"#,
+    )
+    .unwrap();
+
+    // The value of the inner `x` refers to the outer `x`.
+    assert_eq!(
+        resolutions.get(span(43, 44)),
+        Some(Resolution::Local(span(17, 23)))
+    );
+    assert_eq!(
+        resolutions.get(span(53, 54)),
+        Some(Resolution::Local(span(38, 44)))
+    );
+}
+
+#[test]
+fn test_closures_capture_bindings_of_enclosing_functions() {
+    let context = CompilerContext::new(strip_margin(
+        r#"
+        |main :: () {
+        |    x := 1;
+        |    y := 2;
+        |    f := () {
+        |        z := x;
+        |        g := () { y; z; x };
+        |    };
+        |}
+        |"#,
+    ));
+    let program = driver::parse(&context, &Options::default()).unwrap();
+    let resolutions = Resolver::new(&context, &[])
+        .resolve_program(program)
+        .unwrap();
+
+    let capture = |name, def_span| Capture {
+        identifier: context.get_or_intern_str(name),
+        def_span,
+    };
+    let (x, y, z) = (
+        capture("x", span(17, 23)),
+        capture("y", span(29, 35)),
+        capture("z", span(59, 65)),
+    );
+
+    // `f` captures `y` too, so that it can make `g`.
+    assert_eq!(resolutions.captures(span(46, 101)), [x, y]);
+    assert_eq!(resolutions.captures(span(80, 94)), [y, z, x]);
+    assert_eq!(resolutions.captures(span(8, 104)), []);
+}
+
+#[test]
+fn test_undefined_binding() {
+    let errors = resolve(
+        r#"
+        |main :: () {
+        |    x
+        |}
+        |"#,
+    )
+    .err()
+    .unwrap();
+
+    assert_eq!(
+        errors,
+        vec![CompileError::UndefinedName {
+            name: "x".to_owned(),
+            span: span(17, 18),
+            suggestion: None,
+        }]
+    );
+}
+
+#[test]
+fn test_duplicate_decls() {
+    let errors = resolve(
+        r#"
+        |foo :: () -> i32 { 1 }
+        |main :: () -> i32 { foo() }
+        |foo :: () -> i32 { 2 }
+        |Point :: struct {}
+        |foo :: struct {}
+        |"#,
+    )
+    .err()
+    .unwrap();
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::DuplicateDecl {
+                name: "foo".to_owned(),
+                first_span: span(0, 3),
+                span: span(51, 54),
+            },
+            CompileError::DuplicateDecl {
+                name: "foo".to_owned(),
+                first_span: span(0, 3),
+                span: span(93, 96),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_undefined_function() {
+    let errors = resolve(
+        r#"
+        |main :: () {
+        |    foo()
+        |}
+        |"#,
+    )
+    .err()
+    .unwrap();
+
+    assert_eq!(
+        errors,
+        vec![CompileError::UndefinedName {
+            name: "foo".to_owned(),
+            span: span(17, 22),
+            suggestion: Some("for".to_owned()),
+        }]
+    );
+}
+
+#[test]
+fn test_binding_is_out_of_scope_after_its_compound_expr() {
+    let errors = resolve(
+        r#"
+        |main :: () {
+        |    { x := 1 }
+        |    x
+        |}
+        |"#,
+    )
+    .err()
+    .unwrap();
+
+    assert_eq!(
+        errors,
+        vec![CompileError::UndefinedName {
+            name: "x".to_owned(),
+            span: span(32, 33),
+            suggestion: None,
+        }]
+    );
+}
+
+#[test]
+fn test_bindings_of_a_branch_are_out_of_scope_after_it() {
+    let errors = resolve(
+        r#"
+        |main :: (c: i32) -> i32 {
+        |    if c { x := 1 } else {}
+        |    match c { 0 => y := 2, _ => {} }
+        |    z := z;
+        |    x + y
+        |}
+        |"#,
+    )
+    .err()
+    .unwrap();
+
+    let names: Vec<_> = errors
+        .iter()
+        .map(|error| match error {
+            CompileError::UndefinedName { name, .. } => name.as_str(),
+            _ => panic!("unexpected error: {:?}", error),
+        })
+        .collect();
+
+    assert_eq!(names, ["z", "x", "y"]);
+}
+
+#[test]
+fn test_loop_variable_is_out_of_scope_in_else_branch() {
+    let errors = resolve(
+        r#"
+        |main :: () {
+        |    for i : 0..3 {
+        |    } else {
+        |        i
+        |    }
+        |}
+        |"#,
+    )
+    .err()
+    .unwrap();
+
+    assert_eq!(
+        errors,
+        vec![CompileError::UndefinedName {
+            name: "i".to_owned(),
+            span: span(53, 54),
+            suggestion: None,
+        }]
+    );
+}
+
+#[test]
+fn test_undefined_name_suggests_binding_in_scope() {
+    let errors = resolve(
+        r#"
+        |main :: () -> i32 {
+        |    counter := 1;
+        |    countr
+        |}
+        |"#,
+    )
+    .err()
+    .unwrap();
+
+    assert_eq!(
+        errors,
+        vec![CompileError::UndefinedName {
+            name: "countr".to_owned(),
+            span: span(42, 48),
+            suggestion: Some("counter".to_owned()),
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "cannot find `countr` in this scope; did you mean `counter`?"
+    );
+}
+
+#[test]
+fn test_undefined_name_suggests_nothing_out_of_scope() {
+    let errors = resolve(
+        r#"
+        |main :: () -> i32 {
+        |    { counter := 1 }
+        |    countr
+        |}
+        |"#,
+    )
+    .err()
+    .unwrap();
+
+    assert_eq!(
+        errors,
+        vec![CompileError::UndefinedName {
+            name: "countr".to_owned(),
+            span: span(45, 51),
+            suggestion: None,
+        }]
+    );
+}
@@ -0,0 +1,134 @@
+use pretty_assertions::assert_eq;
+
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::rewrite::TokenRewriter;
+use crate::tests::{span, strip_margin};
+
+fn find_token(ctx: &CompilerContext, rewriter: &TokenRewriter, text: &str) -> usize {
+    let source_code = ctx.get_source_code();
+
+    (rewriter.tokens().iter())
+        .position(|token| &source_code[token.span.start.0..token.span.end.0] == text)
+        .unwrap_or_else(|| panic!("no token `{}`", text))
+}
+
+#[test]
+fn test_replace_token() {
+    let ctx = CompilerContext::new(strip_margin(
+        r#"
+        |main :: () -> i32 {
+        |    x
+        |}
+        |"#,
+    ));
+    let mut rewriter = TokenRewriter::new(&ctx);
+    rewriter.replace(find_token(&ctx, &rewriter, "x"), "42");
+
+    let rewrite = rewriter.finish();
+
+    assert_eq!(
+        rewrite.source_code,
+        strip_margin(
+            r#"
+            |main :: () -> i32 {
+            |    42
+            |}
+            |"#,
+        )
+    );
+
+    let execution = driver::run(rewrite.source_code.as_str(), &Options::default()).unwrap();
+
+    assert_eq!(execution.exit_code, Ok(42));
+}
+
+#[test]
+fn test_insert_and_delete_tokens() {
+    let ctx = CompilerContext::new(strip_margin(
+        r#"
+        |main :: () -> i32 {
+        |    1 + 2;
+        |}
+        |"#,
+    ));
+    let mut rewriter = TokenRewriter::new(&ctx);
+    rewriter.insert_after(find_token(&ctx, &rewriter, "{"), "0 +");
+    rewriter.insert_after(find_token(&ctx, &rewriter, "2"), "*");
+    rewriter.insert_after(find_token(&ctx, &rewriter, "2"), "3");
+    rewriter.delete(find_token(&ctx, &rewriter, ";"));
+
+    let rewrite = rewriter.finish();
+
+    assert_eq!(
+        rewrite.source_code,
+        strip_margin(
+            r#"
+            |main :: () -> i32 { 0 +
+            |    1 + 2 * 3
+            |}
+            |"#,
+        )
+    );
+
+    let execution = driver::run(rewrite.source_code.as_str(), &Options::default()).unwrap();
+
+    assert_eq!(execution.exit_code, Ok(7));
+}
+
+#[test]
+fn test_errors_in_rewritten_program_map_to_original_code() {
+    let ctx = CompilerContext::new(strip_margin(
+        r#"
+        |main :: () -> i32 {
+        |    answer
+        |}
+        |"#,
+    ));
+    let mut rewriter = TokenRewriter::new(&ctx);
+    rewriter.insert_after(find_token(&ctx, &rewriter, "{"), "x +");
+
+    let rewrite = rewriter.finish();
+    let diagnostic = driver::check(rewrite.source_code.as_str(), &Options::default());
+
+    assert_eq!(
+        diagnostic.errors,
+        vec![
+            CompileError::UndefinedName {
+                name: "x".to_owned(),
+                span: span(20, 21),
+                suggestion: None,
+            },
+            CompileError::UndefinedName {
+                name: "answer".to_owned(),
+                span: span(28, 34),
+                suggestion: None,
+            },
+        ]
+    );
+
+    let original_spans: Vec<_> = diagnostic
+        .errors
+        .iter()
+        .map(|error| rewrite.original_span(error.span()))
+        .collect();
+
+    // The inserted `x` comes from where it was inserted, and `answer` from
+    // where it was before the insertion.
+    assert_eq!(original_spans, vec![span(19, 19), span(24, 30)]);
+}
+
+#[test]
+fn test_replaced_token_maps_to_original_token() {
+    let ctx = CompilerContext::new("main :: () -> i32 { 1 }".to_owned());
+    let mut rewriter = TokenRewriter::new(&ctx);
+    rewriter.replace(find_token(&ctx, &rewriter, "1"), "yes");
+
+    let rewrite = rewriter.finish();
+
+    assert_eq!(rewrite.source_code, "main :: () -> i32 { yes }");
+    assert_eq!(rewrite.original_span(span(20, 23)), span(20, 21));
+    assert_eq!(rewrite.original_span(span(21, 22)), span(20, 21));
+    assert_eq!(rewrite.original_span(span(24, 25)), span(22, 23));
+}
@@ -0,0 +1,15 @@
+use crate::builtin::Builtin;
+use crate::runtime::ASSEMBLY;
+
+#[test]
+fn test_runtime_defines_the_functions_of_every_builtin() {
+    for builtin in Builtin::ALL {
+        let symbol = builtin.runtime_symbol();
+
+        assert!(
+            ASSEMBLY.contains(&format!("    .globl {}\n{}:\n", symbol, symbol)),
+            "`{}` isn't defined",
+            symbol
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use pretty_assertions::assert_eq;
+
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::tests::{check, compile_script, span, strip_margin};
+
+fn compile_script_errors(source_code: &str) -> Vec<CompileError> {
+    let options = Options {
+        script_mode: true,
+        ..Default::default()
+    };
+
+    match driver::compile(strip_margin(source_code), &options) {
+        Ok(compiled_program) => {
+            panic!("script compiled unexpectedly:\n{}", compiled_program.code)
+        }
+        Err(diagnostic) => diagnostic.errors,
+    }
+}
+
+#[test]
+fn test_top_level_statements_make_up_implicit_main() {
+    let program = compile_script(
+        r#"
+        |x := 42;
+        |println(x + 1);
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    mov eax, 42
+        |    add eax, 1
+        |    mov edi, eax
+        |    call sophia_println_i32
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_statements_interleaved_with_declarations() {
+    let program = compile_script(
+        r#"
+        |foo :: () {}
+        |foo();
+        |bar :: () {}
+        |bar();
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |foo:
+        |    push rbp
+        |    mov rbp, rsp
+        |    pop rbp
+        |    ret
+        |
+        |bar:
+        |    push rbp
+        |    mov rbp, rsp
+        |    pop rbp
+        |    ret
+        |
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    call foo
+        |    call bar
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_script_without_statements_keeps_declarations() {
+    let program = compile_script(
+        r#"
+        |main :: () {}
+        |"#,
+    );
+
+    check(
+        program,
+        r#"
+        |main:
+        |    push rbp
+        |    mov rbp, rsp
+        |    pop rbp
+        |    ret
+        |"#,
+    );
+}
+
+#[test]
+fn test_script_statements_conflict_with_explicit_main() {
+    let errors = compile_script_errors(
+        r#"
+        |main :: () {}
+        |foo();
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::ScriptStatementsWithMain {
+            main_span: span(0, 4),
+            stmt_span: span(14, 19),
+        }]
+    );
+}
@@ -0,0 +1,103 @@
+use pretty_assertions::assert_eq;
+
+use crate::driver::{self, Options};
+use crate::line_index::{Col, Line};
+use crate::render::DiagnosticRenderer;
+use crate::scanner::{BytePos, Span};
+use crate::source_map::{FileId, SourceMap};
+use crate::tests::strip_margin;
+
+fn two_file_source_map() -> SourceMap {
+    let mut source_map = SourceMap::new();
+    source_map.add_file("main.sph", "main :: () -> i32 {\n    answer()\n}");
+    source_map.add_file("answer.sph", "answer :: () -> i32 {\n    42\n}\n");
+
+    source_map
+}
+
+#[test]
+fn test_files_are_laid_out_one_after_another() {
+    let source_map = two_file_source_map();
+
+    assert_eq!(
+        source_map.file_span(FileId(0)),
+        Span {
+            start: BytePos(0),
+            end: BytePos(34),
+        }
+    );
+    assert_eq!(
+        source_map.file_span(FileId(1)),
+        Span {
+            start: BytePos(35),
+            end: BytePos(66),
+        }
+    );
+    assert_eq!(source_map.file_name(FileId(1)), "answer.sph");
+    assert_eq!(
+        source_map.file_source_code(FileId(1)),
+        "answer :: () -> i32 {\n    42\n}\n"
+    );
+}
+
+#[test]
+fn test_lookup_file() {
+    let source_map = two_file_source_map();
+
+    assert_eq!(source_map.lookup_file(BytePos(0)), FileId(0));
+    assert_eq!(source_map.lookup_file(BytePos(33)), FileId(0));
+    assert_eq!(source_map.lookup_file(BytePos(34)), FileId(0));
+    assert_eq!(source_map.lookup_file(BytePos(35)), FileId(1));
+    assert_eq!(source_map.lookup_file(BytePos(100)), FileId(1));
+}
+
+#[test]
+fn test_lines_count_from_the_start_of_each_file() {
+    let source_map = two_file_source_map();
+
+    assert_eq!(
+        source_map.lookup_line_col(BytePos(24)),
+        (FileId(0), Line(2), Col(5))
+    );
+    assert_eq!(
+        source_map.lookup_line_col(BytePos(35)),
+        (FileId(1), Line(1), Col(1))
+    );
+    assert_eq!(
+        source_map.lookup_line_col(BytePos(61)),
+        (FileId(1), Line(2), Col(5))
+    );
+    assert_eq!(source_map.line_text(FileId(1), Line(2)), "    42");
+}
+
+#[test]
+fn test_run_program_split_across_files() {
+    let execution = driver::run(two_file_source_map(), &Options::default());
+
+    assert_eq!(
+        execution.ok().map(|execution| execution.exit_code),
+        Some(Ok(42))
+    );
+}
+
+#[test]
+fn test_diagnostics_point_at_the_file_of_the_span() {
+    let mut source_map = SourceMap::new();
+    source_map.add_file("main.sph", "main :: () -> i32 {\n    answer()\n}\n");
+    source_map.add_file("answer.sph", "answer :: () -> i32 {\n    x\n}\n");
+
+    let diagnostic = driver::check(source_map.clone(), &Options::default());
+
+    assert_eq!(
+        DiagnosticRenderer::new(&source_map).render(&diagnostic),
+        strip_margin(
+            r#"
+            |error[E0002]: cannot find `x` in this scope
+            | --> answer.sph:2:5
+            |  |
+            |2 |     x
+            |  |     ^
+            |"#
+        ) + "\n"
+    );
+}
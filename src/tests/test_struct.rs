@@ -0,0 +1,413 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast;
+use crate::ast_owned::Type;
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::{CompileError, CompileWarning};
+use crate::driver::{self, Emit, Options};
+use crate::layout::{FieldLayout, Layouts, StructLayout};
+use crate::profile::IntWidth;
+use crate::tests::{check_errors, compile_errors, compile_warnings, run, span, strip_margin};
+
+fn layouts(context: &CompilerContext, int_width: IntWidth) -> Result<Layouts, Vec<CompileError>> {
+    let program = driver::parse(context, &Options::default()).unwrap();
+
+    Layouts::of_program(context, program, int_width)
+}
+
+#[test]
+fn test_run_struct_fields() {
+    let exit_code = run(r#"
+        |Point :: struct { x: i32, y: i32 }
+        |Line :: struct { start: Point, end: Point }
+        |
+        |length_squared :: (line: Line) -> i32 {
+        |    dx := line.end.x - line.start.x;
+        |    dy := line.end.y - line.start.y;
+        |    dx * dx + dy * dy
+        |}
+        |
+        |main :: () -> i32 {
+        |    start := Point { x: 1, y: 2 };
+        |    line := Line { end: Point { y: 6, x: 4 }, start: start };
+        |    length_squared(line) * 10 + line.start.y
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(252));
+}
+
+#[test]
+fn test_struct_literal_in_condition_needs_parentheses() {
+    let exit_code = run(r#"
+        |Flag :: struct { on: i32 }
+        |
+        |main :: () -> i32 {
+        |    flag := Flag { on: 1 };
+        |    if flag.on { (Flag { on: 7 }).on } else { 0 }
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(7));
+
+    let errors = check_errors(
+        r#"
+        |Flag :: struct { on: i32 }
+        |
+        |main :: () -> i32 {
+        |    if Flag { on: 1 }.on { 1 } else { 0 }
+        |}
+        |"#,
+    );
+
+    assert!(
+        matches!(errors[..], [CompileError::ExpectedButFound { .. }]),
+        "{:?}",
+        errors
+    );
+}
+
+#[test]
+fn test_struct_layouts() {
+    let source_code = r#"
+        |Inner :: struct { flag: (), value: i32 }
+        |Outer :: struct { first: i32, unit: (), inner: Inner }
+        |Empty :: struct {}
+        |"#;
+
+    let context = CompilerContext::new(strip_margin(source_code));
+    let struct_layouts = layouts(&context, IntWidth::Bits32).unwrap();
+
+    assert_eq!(
+        struct_layouts.get_struct("Outer"),
+        Some(&StructLayout {
+            name: "Outer",
+            fields: vec![
+                FieldLayout {
+                    name: "first",
+                    ty: ast::Type::I32,
+                    offset: 0,
+                },
+                FieldLayout {
+                    name: "unit",
+                    ty: ast::Type::Unit,
+                    offset: 4,
+                },
+                FieldLayout {
+                    name: "inner",
+                    ty: ast::Type::Named("Inner"),
+                    offset: 4,
+                },
+            ],
+            size: 8,
+            align: 4,
+        })
+    );
+    assert_eq!(
+        struct_layouts.struct_of_decl(0).map(|layout| layout.size),
+        Some(4)
+    );
+    assert_eq!(
+        struct_layouts
+            .get_struct("Empty")
+            .map(|layout| (layout.size, layout.align)),
+        Some((0, 1))
+    );
+
+    let context = CompilerContext::new(strip_margin(source_code));
+    let struct_layouts = layouts(&context, IntWidth::Bits16).unwrap();
+
+    assert_eq!(
+        struct_layouts
+            .get_struct("Outer")
+            .map(|layout| (layout.size, layout.align)),
+        Some((4, 2))
+    );
+}
+
+#[test]
+fn test_recursive_structs_are_reported_once_per_cycle() {
+    let errors = layouts(
+        &CompilerContext::new(strip_margin(
+            r#"
+        |List :: struct { head: i32, tail: List }
+        |Even :: struct { odd: Odd }
+        |Odd :: struct { even: Even }
+        |Wrapper :: struct { even: Even }
+        |"#,
+        )),
+        IntWidth::Bits32,
+    )
+    .err()
+    .unwrap();
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::RecursiveType {
+                name: "List".to_owned(),
+                span: span(0, 4),
+            },
+            CompileError::RecursiveType {
+                name: "Even".to_owned(),
+                span: span(41, 45),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "type `List` contains itself, so it has infinite size"
+    );
+}
+
+#[test]
+fn test_duplicate_fields() {
+    let errors = check_errors(
+        r#"
+        |Point :: struct { x: i32, x: i32 }
+        |
+        |main :: () {
+        |    Point { x: 1, x: 2 };
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::DuplicateField {
+            field: "x".to_owned(),
+            span: span(26, 32),
+        }]
+    );
+    assert_eq!(errors[0].to_string(), "field `x` is already declared");
+
+    let errors = check_errors(
+        r#"
+        |Point :: struct { x: i32 }
+        |
+        |main :: () {
+        |    Point { x: 1, x: 2 };
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::DuplicateField {
+            field: "x".to_owned(),
+            span: span(58, 59),
+        }]
+    );
+}
+
+#[test]
+fn test_struct_literal_must_initialize_declared_fields() {
+    let errors = check_errors(
+        r#"
+        |Point :: struct { x: i32, y: i32, z: i32 }
+        |
+        |main :: () {
+        |    Point { x: 1, w: 2 };
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::NoSuchField {
+                ty: Type::Named("Point".to_owned()),
+                field: "w".to_owned(),
+                span: span(74, 75),
+            },
+            CompileError::MissingFields {
+                ty: Type::Named("Point".to_owned()),
+                fields: vec!["y".to_owned(), "z".to_owned()],
+                span: span(60, 65),
+            },
+        ]
+    );
+    assert_eq!(errors[0].to_string(), "no field `w` on type `Point`");
+    assert_eq!(
+        errors[1].to_string(),
+        "missing fields `y` and `z` in initializer of `Point`"
+    );
+}
+
+#[test]
+fn test_field_values_must_have_field_types() {
+    let errors = check_errors(
+        r#"
+        |Point :: struct { x: i32 }
+        |Line :: struct { start: Point }
+        |
+        |main :: () {
+        |    Line { start: 1 };
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::MismatchedTypes {
+            expected: Type::Named("Point".to_owned()),
+            found: Type::I32,
+            span: span(90, 91),
+        }]
+    );
+}
+
+#[test]
+fn test_access_to_missing_field() {
+    let errors = check_errors(
+        r#"
+        |Point :: struct { x: i32 }
+        |
+        |main :: () -> i32 {
+        |    p := Point { x: 1 };
+        |    x := 2;
+        |    p.y + x.y
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::NoSuchField {
+                ty: Type::Named("Point".to_owned()),
+                field: "y".to_owned(),
+                span: span(90, 91),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(88, 91),
+            },
+            CompileError::NoSuchField {
+                ty: Type::I32,
+                field: "y".to_owned(),
+                span: span(96, 97),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(94, 97),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_struct_names_must_name_structs() {
+    let errors = check_errors(
+        r#"
+        |Point :: struct { x: i32 }
+        |
+        |origin :: (p: Pointt) -> i32 {
+        |    main { x: 0 };
+        |    p.x
+        |}
+        |
+        |main :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::UndefinedName {
+                name: "Pointt".to_owned(),
+                span: span(38, 47),
+                suggestion: Some("Point".to_owned()),
+            },
+            CompileError::NotAStruct {
+                name: "main".to_owned(),
+                span: span(62, 66),
+            },
+        ]
+    );
+    assert_eq!(errors[1].to_string(), "expected struct, found `main`");
+
+    let errors = check_errors(
+        r#"
+        |Point :: struct { x: i32 }
+        |
+        |main :: () -> Point {
+        |    Point(1)
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::CallToNonFunction {
+                ty: Type::Named("Point".to_owned()),
+                span: span(53, 61),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::Named("Point".to_owned()),
+                found: Type::Unit,
+                span: span(53, 61),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_structs_used_as_types_are_used() {
+    let warnings = compile_warnings(
+        r#"
+        |Point :: struct { x: i32 }
+        |Line :: struct { start: Point }
+        |Unused :: struct {}
+        |
+        |length :: (line: Line) -> i32 {
+        |    line.start.x
+        |}
+        |
+        |main :: () -> i32 {
+        |    length(Line { start: Point { x: 3 } })
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        warnings,
+        vec![CompileWarning::UnusedDecl {
+            name: "Unused".to_owned(),
+            span: span(59, 65),
+        }]
+    );
+}
+
+#[test]
+fn test_compiled_backends_reject_structs() {
+    let errors = compile_errors(
+        r#"
+        |Point :: struct { x: i32 }
+        |
+        |main :: () -> i32 {
+        |    Point { x: 1 }.x
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::StructsUnsupported { backend: "x86-64" }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "the x86-64 backend doesn't support structs yet; run the program with the interpreter \
+         instead"
+    );
+
+    let options = Options {
+        emit: Emit::Source,
+        ..Default::default()
+    };
+
+    assert!(driver::compile("P :: struct { x: i32 }\nmain :: () {}\n", &options).is_ok());
+}
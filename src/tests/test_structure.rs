@@ -0,0 +1,277 @@
+use crate::ast::BinaryOp;
+use crate::compiler_context::CompilerContext;
+use crate::diff_parses;
+use crate::driver::{self, Emit, Options};
+use crate::ir::{Block, Function, Inst, InstKind, Module, Terminator, Value};
+use crate::scanner::{BytePos, Span};
+use crate::structure::{diff_structures, render_ast};
+use crate::tests::strip_margin;
+
+/// Panics with a diff of the rendered structures if they differ.
+#[track_caller]
+fn assert_same_structure(expected: &str, found: &str) {
+    if let Some(diff) = diff_structures(expected, found) {
+        panic!("structures differ (-expected +found):\n{}", diff);
+    }
+}
+
+fn render(source_code: &str) -> String {
+    let context = CompilerContext::new(strip_margin(source_code));
+    let program = driver::parse(&context, &Options::default()).unwrap();
+
+    render_ast(&context, program)
+}
+
+#[test]
+fn test_render_ast() {
+    let tree = render(
+        r#"
+        |main :: (n: i32) -> i32 {
+        |    for i : 0..=n {
+        |        if i { break; } else { continue; }
+        |    } else {
+        |        x := f(n);
+        |    }
+        |    (n + 1) * 2
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        tree.trim_end(),
+        strip_margin(
+            r#"
+            |decl main
+            |  function (n: i32) -> i32
+            |    compound
+            |      for i ..=
+            |        const 0
+            |        ref n
+            |        compound
+            |          if
+            |            ref i
+            |            compound
+            |              semi
+            |                break
+            |            else
+            |              compound
+            |                semi
+            |                  continue
+            |        else
+            |          compound
+            |            semi
+            |              bind x
+            |                call f
+            |                  ref n
+            |      binary *
+            |        binary +
+            |          ref n
+            |          const 1
+            |        const 2
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_render_chains_of_operations_as_one_node() {
+    let tree = render(
+        r#"
+        |main :: (a: i32, b: i32, c: i32) -> i32 {
+        |    a - b + c * a - 1
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        tree.trim_end(),
+        strip_margin(
+            r#"
+            |decl main
+            |  function (a: i32, b: i32, c: i32) -> i32
+            |    compound
+            |      binary - + -
+            |        ref a
+            |        ref b
+            |        binary *
+            |          ref c
+            |          ref a
+            |        const 1
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_emit_tokens() {
+    let options = Options {
+        emit: Emit::Tokens,
+        ..Default::default()
+    };
+
+    let compiled_program = driver::compile("main :: () -> i32 { 1 }", &options).unwrap();
+
+    assert_eq!(
+        compiled_program.code,
+        strip_margin(
+            r#"
+            |0..4         identifier           main
+            |5..7         colon_colon          ::
+            |8..9         open_paren           (
+            |9..10        closed_paren         )
+            |11..13       dash_greater         ->
+            |14..17       keyword_i32          i32
+            |18..19       open_curly           {
+            |20..21       integer_constant     1
+            |22..23       closed_curly         }
+            |"#,
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_emit_tokens_after_comments_of_multibyte_chars() {
+    let options = Options {
+        emit: Emit::Tokens,
+        ..Default::default()
+    };
+
+    let compiled_program = driver::compile("// café ☕\nmain :: ()", &options).unwrap();
+
+    // Spans are in bytes, which the comment takes 12 of.
+    assert_eq!(
+        compiled_program.code,
+        strip_margin(
+            r#"
+            |13..17       identifier           main
+            |18..20       colon_colon          ::
+            |21..22       open_paren           (
+            |22..23       closed_paren         )
+            |"#,
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_emit_ast_of_program_that_analyses_reject() {
+    let options = Options {
+        emit: Emit::Ast,
+        ..Default::default()
+    };
+
+    let compiled_program = driver::compile("main :: () -> i32 { x + 1 }", &options).unwrap();
+
+    assert_eq!(
+        compiled_program.code,
+        strip_margin(
+            r#"
+            |decl main
+            |  function () -> i32
+            |    compound
+            |      binary +
+            |        ref x
+            |        const 1
+            |"#,
+        ) + "\n"
+    );
+}
+
+#[test]
+fn test_same_parse_ignores_layout_and_parentheses() {
+    let diff = diff_parses(
+        "main :: () -> i32 { (1 + 2) * 3 }",
+        "main::()->i32{\n    ((1+2)) * (3)\n}\n",
+    );
+
+    assert_eq!(diff.unwrap(), None);
+}
+
+#[test]
+fn test_different_parses_are_diffed() {
+    let diff = diff_parses(
+        "main :: () -> i32 { 1 + 2 * 3 }",
+        "main :: () -> i32 { (1 + 2) * 3 }",
+    );
+
+    assert_eq!(
+        diff.unwrap().unwrap().trim_end(),
+        strip_margin(
+            r#"
+            |  decl main
+            |    function () -> i32
+            |      compound
+            |-       binary +
+            |-         const 1
+            |-         binary *
+            |+       binary *
+            |+         binary +
+            |+           const 1
+            |            const 2
+            |-           const 3
+            |+         const 3
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_diff_of_same_structures_is_none() {
+    assert_eq!(diff_structures("a\nb\n", "a\nb\n"), None);
+    assert_eq!(
+        diff_structures("a\nb\nc\n", "a\nc\nd\n"),
+        Some("  a\n- b\n  c\n+ d\n".to_owned())
+    );
+}
+
+#[test]
+fn test_ir_structures_ignore_spans() {
+    let module_with_span = |start, end| Module {
+        functions: vec![Function {
+            name: "main",
+            returns_value: true,
+            blocks: vec![Block {
+                params: vec![],
+                insts: vec![
+                    Inst {
+                        result: Some(Value(0)),
+                        kind: InstKind::Const(1),
+                    },
+                    Inst {
+                        result: Some(Value(1)),
+                        kind: InstKind::Binary {
+                            op: BinaryOp::Add,
+                            lhs: Value(0),
+                            rhs: Value(0),
+                            span: Span {
+                                start: BytePos(start),
+                                end: BytePos(end),
+                            },
+                        },
+                    },
+                ],
+                terminator: Terminator::Return(Some(Value(1))),
+            }],
+        }],
+    };
+
+    let module = module_with_span(0, 5);
+    let moved_module = module_with_span(10, 15);
+
+    assert_ne!(module, moved_module);
+    assert_same_structure(&module.to_string(), &moved_module.to_string());
+}
+
+#[test]
+#[should_panic(expected = "structures differ")]
+fn test_assert_same_structure_panics_on_difference() {
+    assert_same_structure(&render("|main :: () {}"), &render("|main :: () { 1; }"));
+}
+
+#[test]
+fn test_programs_that_dont_parse_arent_diffed() {
+    let diagnostics =
+        diff_parses("main :: () -> i32 { 1 }", "main :: () -> i32 { 1 +").unwrap_err();
+
+    let location = diagnostics.errors[0].location.as_ref().unwrap();
+    assert_eq!(location.file, "<found>");
+}
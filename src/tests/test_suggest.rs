@@ -0,0 +1,45 @@
+use crate::suggest::{edit_distance, find_similar_name, is_typo_of};
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(edit_distance("", ""), 0);
+    assert_eq!(edit_distance("abc", ""), 3);
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(edit_distance("counter", "counter"), 0);
+    assert_eq!(edit_distance("countr", "counter"), 1);
+    assert_eq!(edit_distance("cuonter", "counter"), 2);
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+}
+
+#[test]
+fn test_find_similar_name_picks_closest() {
+    assert_eq!(
+        find_similar_name("countr", ["counters", "counter", "center"]),
+        Some("counter")
+    );
+}
+
+#[test]
+fn test_find_similar_name_breaks_ties_alphabetically() {
+    assert_eq!(find_similar_name("ab", ["ac", "aa"]), Some("aa"));
+    assert_eq!(find_similar_name("abcd", ["abce", "abca"]), Some("abca"));
+}
+
+#[test]
+fn test_find_similar_name_leaves_out_distant_names() {
+    assert_eq!(find_similar_name("counter", ["total", "cnt"]), None);
+    assert_eq!(find_similar_name("x", ["y"]), None);
+    assert_eq!(find_similar_name("x", ["x"]), None);
+}
+
+#[test]
+fn test_is_typo_of() {
+    assert!(is_typo_of("fro", "for"));
+    assert!(is_typo_of("esle", "else"));
+    assert!(is_typo_of("matc", "match"));
+    assert!(is_typo_of("iff", "if"));
+    assert!(!is_typo_of("for", "for"));
+    assert!(!is_typo_of("f", "if"));
+    assert!(!is_typo_of("rof", "for"));
+    assert!(!is_typo_of("elif", "else"));
+}
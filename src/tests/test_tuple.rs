@@ -0,0 +1,215 @@
+use pretty_assertions::assert_eq;
+
+use crate::ast_owned::Type;
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::layout::Layouts;
+use crate::profile::IntWidth;
+use crate::tests::{check_errors, compile_errors, run, span, strip_margin};
+
+#[test]
+fn test_run_tuple_values() {
+    let exit_code = run(r#"
+        |div_rem :: (a: i32, b: i32) -> (i32, i32) {
+        |    (a / b, a % b)
+        |}
+        |
+        |sum :: (triple: (i32, (i32, i32))) -> i32 {
+        |    triple.0 + triple.1.0 + triple.1.1
+        |}
+        |
+        |main :: () -> i32 {
+        |    q, r := div_rem(47, 10);
+        |    _, one := (q, (1,));
+        |    sum((q * 100, (r * 10, one.0)))
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(471));
+}
+
+#[test]
+fn test_tuple_types_need_a_comma_for_one_element() {
+    let exit_code = run(r#"
+        |unwrap :: (single: (i32,), plain: (i32)) -> i32 {
+        |    single.0 + plain
+        |}
+        |
+        |main :: () -> i32 {
+        |    unwrap((1,), (2))
+        |}
+        |"#);
+
+    assert_eq!(exit_code, Ok(3));
+
+    let errors = check_errors(
+        r#"
+        |main :: () -> (i32,) {
+        |    (1)
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::MismatchedTypes {
+            expected: Type::Tuple(vec![Type::I32]),
+            found: Type::I32,
+            span: span(27, 30),
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "mismatched types: expected `(i32,)`, found `i32`"
+    );
+}
+
+#[test]
+fn test_errors_keep_their_types_after_the_context_is_dropped() {
+    let errors = check_errors(
+        r#"
+        |Point :: struct { x: i32 }
+        |
+        |main :: () -> i32 {
+        |    _ : (Point, (i32, i32)) = 1;
+        |    0
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        vec!["mismatched types: expected `(Point, (i32, i32))`, found `i32`"]
+    );
+}
+
+#[test]
+fn test_access_to_missing_element() {
+    let errors = check_errors(
+        r#"
+        |main :: () -> i32 {
+        |    pair := (1, 2);
+        |    x := 3;
+        |    pair.2 + x.0
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::NoSuchField {
+                ty: Type::Tuple(vec![Type::I32, Type::I32]),
+                field: "2".to_owned(),
+                span: span(61, 62),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(56, 62),
+            },
+            CompileError::NoSuchField {
+                ty: Type::I32,
+                field: "0".to_owned(),
+                span: span(67, 68),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(65, 68),
+            },
+        ]
+    );
+    assert_eq!(errors[0].to_string(), "no field `2` on type `(i32, i32)`");
+}
+
+#[test]
+fn test_destructuring_needs_a_binding_per_element() {
+    let errors = check_errors(
+        r#"
+        |main :: () -> i32 {
+        |    a, b := (1, 2, 3);
+        |    c, d := 4;
+        |    0
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MismatchedTupleBindings {
+                bindings: 2,
+                found: Type::Tuple(vec![Type::I32, Type::I32, Type::I32]),
+                span: span(32, 41),
+            },
+            CompileError::MismatchedTupleBindings {
+                bindings: 2,
+                found: Type::I32,
+                span: span(55, 56),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "expected a tuple of 2 elements, found `(i32, i32, i32)`"
+    );
+}
+
+#[test]
+fn test_tuples_in_structs_are_laid_out_like_structs() {
+    let context = CompilerContext::new(strip_margin(
+        r#"
+        |Entry :: struct { key: (i32, ()), value: (i32,) }
+        |"#,
+    ));
+    let program = driver::parse(&context, &Options::default()).unwrap();
+
+    let layouts = Layouts::of_program(&context, program, IntWidth::Bits16).unwrap();
+    let layout = layouts.get_struct("Entry").unwrap();
+
+    assert_eq!(
+        layout
+            .fields
+            .iter()
+            .map(|field| field.offset)
+            .collect::<Vec<_>>(),
+        vec![0, 2]
+    );
+    assert_eq!((layout.size, layout.align), (4, 2));
+}
+
+#[test]
+fn test_compiled_backends_reject_tuples() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    a, b := (1, 2);
+        |    a + b
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::TuplesUnsupported { backend: "x86-64" }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "the x86-64 backend doesn't support tuples yet; run the program with the interpreter \
+         instead"
+    );
+
+    let errors = compile_errors(
+        r#"
+        |first :: (pair: (i32, i32)) -> i32 { pair.0 }
+        |main :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::TuplesUnsupported { backend: "x86-64" }]
+    );
+}
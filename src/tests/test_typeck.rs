@@ -0,0 +1,363 @@
+use crate::ast_owned::Type;
+use crate::diagnostics::CompileError;
+use crate::tests::{compile_errors, span};
+
+#[test]
+fn test_function_body_must_match_return_type() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::MismatchedTypes {
+            expected: Type::I32,
+            found: Type::Unit,
+            span: span(18, 21),
+        }]
+    );
+}
+
+#[test]
+fn test_unit_function_cannot_return_value() {
+    let errors = compile_errors(
+        r#"
+        |foo :: () {
+        |    1
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::MismatchedTypes {
+            expected: Type::Unit,
+            found: Type::I32,
+            span: span(16, 17),
+        }]
+    );
+}
+
+#[test]
+fn test_if_condition_must_be_integer() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    if foo() {
+        |    }
+        |}
+        |foo :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::InvalidCondition {
+            found: Type::Unit,
+            span: span(20, 25),
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "expected integer condition, found `()`; conditions hold when they aren't zero"
+    );
+}
+
+#[test]
+fn test_loop_condition_must_be_integer() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    for foo {
+        |    }
+        |}
+        |foo :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::InvalidCondition {
+            found: Type::Function {
+                params: vec![],
+                return_type: Box::new(Type::Unit),
+            },
+            span: span(21, 24),
+        }]
+    );
+}
+
+#[test]
+fn test_range_bounds_must_be_integers() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    for i : foo()..3 {}
+        |    for j : 0..=foo {}
+        |}
+        |foo :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(25, 30),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Function {
+                    params: vec![],
+                    return_type: Box::new(Type::Unit),
+                },
+                span: span(53, 56),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_range_bounds_and_steps_must_have_the_same_type() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    for i : 0u8..10i64 {}
+        |    for j : 0..10 step 2u8 {}
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MismatchedTypes {
+                expected: Type::U8,
+                found: Type::I64,
+                span: span(30, 35),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::U8,
+                span: span(62, 65),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_range_steps_must_be_non_zero_integers() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    for i : 0..3 step foo() {}
+        |    for j : 0..=3 step 0 {}
+        |}
+        |foo :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(35, 40),
+            },
+            CompileError::ZeroStep { span: span(67, 68) },
+        ]
+    );
+}
+
+#[test]
+fn test_arithmetic_operands_must_be_integers() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    1 + foo()
+        |}
+        |foo :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::MismatchedTypes {
+            expected: Type::I32,
+            found: Type::Unit,
+            span: span(28, 33),
+        }]
+    );
+}
+
+#[test]
+fn test_if_branches_must_have_same_type() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    if 1 {
+        |        2
+        |    } else {
+        |        foo()
+        |    }
+        |}
+        |foo :: () {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::MismatchedTypes {
+            expected: Type::I32,
+            found: Type::Unit,
+            span: span(62, 67),
+        }]
+    );
+}
+
+#[test]
+fn test_call_arguments_must_match_parameters() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    foo(1)
+        |}
+        |foo :: (a: i32, b: i32) {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::MismatchedCallArguments {
+            expected: vec![Type::I32, Type::I32],
+            found: vec![Type::I32],
+            span: span(17, 23),
+        }]
+    );
+}
+
+#[test]
+fn test_each_call_argument_of_the_wrong_type_is_reported() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    foo({}, 1u8, {})
+        |}
+        |foo :: (a: i32, b: i32, c: ()) {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::MismatchedTypes {
+            expected: Type::I32,
+            found: Type::Unit,
+            span: span(21, 23),
+        }]
+    );
+
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    foo(1, {}, 2)
+        |}
+        |foo :: (a: i32, b: i32, c: ()) {}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(24, 26),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::Unit,
+                found: Type::I32,
+                span: span(28, 29),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_cannot_call_binding() {
+    let errors = compile_errors(
+        r#"
+        |main :: () {
+        |    x := 1;
+        |    x()
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![CompileError::CallToNonFunction {
+            ty: Type::I32,
+            span: span(29, 32),
+        }]
+    );
+}
+
+#[test]
+fn test_type_error_messages() {
+    let mismatched_types = CompileError::MismatchedTypes {
+        expected: Type::I32,
+        found: Type::Unit,
+        span: span(0, 1),
+    };
+
+    assert_eq!(
+        mismatched_types.to_string(),
+        "mismatched types: expected `i32`, found `()`"
+    );
+
+    let mismatched_call_arguments = CompileError::MismatchedCallArguments {
+        expected: vec![Type::I32, Type::I32],
+        found: vec![Type::Unit],
+        span: span(0, 1),
+    };
+
+    assert_eq!(
+        mismatched_call_arguments.to_string(),
+        "arguments to this function are incorrect: expected `(i32, i32)`, found `(())`"
+    );
+}
+
+#[test]
+fn test_bindings_must_match_their_annotated_types() {
+    let errors = compile_errors(
+        r#"
+        |main :: () -> i32 {
+        |    x : i32 = {};
+        |    y : () = x;
+        |    x
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        errors,
+        vec![
+            CompileError::MismatchedTypes {
+                expected: Type::I32,
+                found: Type::Unit,
+                span: span(34, 36),
+            },
+            CompileError::MismatchedTypes {
+                expected: Type::Unit,
+                found: Type::I32,
+                span: span(51, 52),
+            },
+        ]
+    );
+}
@@ -0,0 +1,647 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use pretty_assertions::assert_eq;
+
+use crate::ast::{HostFnDecl, Type};
+use crate::diagnostics::CompileError;
+use crate::driver::{self, Options};
+use crate::host_binding::{self, BindError};
+use crate::interp::RuntimeError;
+use crate::snapshot::{Snapshot, SnapshotError};
+use crate::tests::{span, strip_margin};
+use crate::vm::{ExecutionLimit, ExecutionLimits, HostCall, Progress, Vm};
+
+fn host_fn(name: &str, param_types: &[Type], return_type: Type) -> HostFnDecl {
+    HostFnDecl {
+        name: name.to_owned(),
+        param_types: param_types.to_vec(),
+        return_type,
+    }
+}
+
+fn options_with(host_functions: Vec<HostFnDecl>) -> Options {
+    Options {
+        host_functions,
+        ..Default::default()
+    }
+}
+
+fn load(source_code: &str, options: &Options) -> Vm {
+    match driver::compile_bytecode(strip_margin(source_code), options) {
+        Ok(compiled_bytecode) => Vm::load(&compiled_bytecode.module.save()).unwrap(),
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+#[test]
+fn test_host_function_returning_value() {
+    let options = options_with(vec![host_fn("max", &[Type::I32, Type::I32], Type::I32)]);
+    let mut vm = load(
+        r#"
+        |main :: () -> i32 {
+        |    max(3, 7) - max(5, 2)
+        |}
+        |"#,
+        &options,
+    );
+    vm.register_host_function("max", |args| Ok(args[0].max(args[1])));
+
+    assert_eq!(vm.run_main(), Ok(2));
+}
+
+#[test]
+fn test_host_function_returning_unit() {
+    let options = options_with(vec![host_fn("print", &[Type::I32], Type::Unit)]);
+    let mut vm = load(
+        r#"
+        |main :: () {
+        |    for i : 1..=3 {
+        |        print(i * i);
+        |    }
+        |}
+        |"#,
+        &options,
+    );
+
+    let printed = Rc::new(RefCell::new(vec![]));
+    let printed_by_host = Rc::clone(&printed);
+    vm.register_host_function("print", move |args| {
+        printed_by_host.borrow_mut().push(args[0]);
+        Ok(0)
+    });
+
+    assert_eq!(vm.run_main(), Ok(0));
+    assert_eq!(*printed.borrow(), vec![1, 4, 9]);
+}
+
+#[test]
+fn test_declarations_shadow_host_functions() {
+    let options = options_with(vec![host_fn("answer", &[], Type::I32)]);
+    let mut vm = load(
+        r#"
+        |main :: () -> i32 {
+        |    answer()
+        |}
+        |
+        |answer :: () -> i32 {
+        |    42
+        |}
+        |"#,
+        &options,
+    );
+    vm.register_host_function("answer", |_| Ok(0));
+
+    assert_eq!(vm.run_main(), Ok(42));
+}
+
+#[test]
+fn test_host_function_not_registered() {
+    let options = options_with(vec![host_fn("answer", &[], Type::I32)]);
+    let mut vm = load(
+        r#"
+        |main :: () -> i32 {
+        |    answer()
+        |}
+        |"#,
+        &options,
+    );
+
+    assert_eq!(
+        vm.run_main(),
+        Err(RuntimeError::UndefinedHostFunction {
+            name: "answer".to_owned()
+        })
+    );
+}
+
+#[test]
+fn test_host_function_failure_stops_program() {
+    let options = options_with(vec![host_fn("check", &[Type::I32], Type::Unit)]);
+    let mut vm = load(
+        r#"
+        |main :: () {
+        |    check(1);
+        |    check(0);
+        |    _ = 1 / 0;
+        |}
+        |"#,
+        &options,
+    );
+    vm.register_host_function("check", |args| match args[0] {
+        0 => Err("check failed".to_owned()),
+        _ => Ok(0),
+    });
+
+    assert_eq!(
+        vm.run_main(),
+        Err(RuntimeError::HostFunctionFailed {
+            name: "check".to_owned(),
+            message: "check failed".to_owned()
+        })
+    );
+}
+
+#[test]
+fn test_host_function_arguments_are_type_checked() {
+    let options = options_with(vec![host_fn("print", &[Type::I32], Type::Unit)]);
+
+    let errors = match driver::compile_bytecode("main :: () { print({}); }", &options) {
+        Ok(_) => panic!("program compiled unexpectedly"),
+        Err(diagnostic) => diagnostic.errors,
+    };
+
+    assert_eq!(
+        errors,
+        vec![CompileError::MismatchedTypes {
+            expected: Type::I32.into(),
+            found: Type::Unit.into(),
+            span: span(19, 21),
+        }]
+    );
+}
+
+#[test]
+fn test_host_functions_are_unsupported_by_other_backends() {
+    let options = options_with(vec![host_fn("answer", &[], Type::I32)]);
+
+    let errors = match driver::run("main :: () {}", &options) {
+        Ok(_) => panic!("program ran unexpectedly"),
+        Err(diagnostic) => diagnostic.errors,
+    };
+
+    assert_eq!(
+        errors,
+        vec![CompileError::HostFunctionsUnsupported {
+            backend: "interpreter"
+        }]
+    );
+}
+
+#[test]
+fn test_call_entry_function() {
+    let mut vm = load(
+        r#"
+        |square :: (n: i32) -> i32 {
+        |    n * n
+        |}
+        |
+        |nothing :: (n: i32, u: ()) {}
+        |"#,
+        &Options::default(),
+    );
+
+    assert_eq!(vm.call("square", &[7]), Ok(Some(49)));
+    assert_eq!(vm.call("nothing", &[7]), Ok(None));
+    assert_eq!(
+        vm.call("cube", &[7]),
+        Err(RuntimeError::UndefinedFunction {
+            name: "cube".to_owned()
+        })
+    );
+    assert_eq!(
+        vm.call("square", &[]),
+        Err(RuntimeError::MismatchedArgumentCount {
+            name: "square".to_owned(),
+            expected: 1,
+            found: 0
+        })
+    );
+}
+
+#[test]
+fn test_call_mutually_recursive_functions() {
+    let mut vm = load(
+        r#"
+        |is_even :: (n: i32) -> i32 {
+        |    if n { is_odd(n - 1) } else { 1 }
+        |}
+        |
+        |is_odd :: (n: i32) -> i32 {
+        |    if n { is_even(n - 1) } else { 0 }
+        |}
+        |"#,
+        &Options::default(),
+    );
+
+    assert_eq!(vm.call("is_even", &[10]), Ok(Some(1)));
+    assert_eq!(vm.call("is_odd", &[10]), Ok(Some(0)));
+}
+
+#[test]
+fn test_instruction_limit() {
+    let mut vm = load(
+        r#"
+        |main :: () {
+        |    for {}
+        |}
+        |"#,
+        &Options::default(),
+    );
+    vm.set_limits(ExecutionLimits {
+        max_instructions: 1000,
+        ..Default::default()
+    });
+
+    assert_eq!(
+        vm.run_main(),
+        Err(RuntimeError::ExecutionLimitExceeded {
+            limit: ExecutionLimit::Instructions(1000)
+        })
+    );
+}
+
+#[test]
+fn test_timeout() {
+    let mut vm = load(
+        r#"
+        |main :: () {
+        |    for {}
+        |}
+        |"#,
+        &Options::default(),
+    );
+    vm.set_limits(ExecutionLimits {
+        timeout: Some(Duration::from_millis(10)),
+        ..Default::default()
+    });
+
+    assert_eq!(
+        vm.run_main(),
+        Err(RuntimeError::ExecutionLimitExceeded {
+            limit: ExecutionLimit::Timeout(Duration::from_millis(10))
+        })
+    );
+}
+
+#[test]
+fn test_call_depth_and_stack_size_limits() {
+    let mut vm = load(
+        r#"
+        |recurse :: (n: i32) -> i32 {
+        |    recurse(n + 1)
+        |}
+        |"#,
+        &Options::default(),
+    );
+
+    vm.set_limits(ExecutionLimits {
+        max_call_depth: 100,
+        ..Default::default()
+    });
+    assert_eq!(
+        vm.call("recurse", &[0]),
+        Err(RuntimeError::ExecutionLimitExceeded {
+            limit: ExecutionLimit::CallDepth(100)
+        })
+    );
+
+    vm.set_limits(ExecutionLimits {
+        max_stack_size: 100,
+        ..Default::default()
+    });
+    assert_eq!(
+        vm.call("recurse", &[0]),
+        Err(RuntimeError::ExecutionLimitExceeded {
+            limit: ExecutionLimit::StackSize(100)
+        })
+    );
+}
+
+#[test]
+fn test_bound_functions_convert_arguments_and_values() {
+    let max = |a: i32, b: i32| a.max(b);
+    let is_even = |n: i32| n % 2 == 0;
+    let pick = |cond: bool, a: i32, b: i32| if cond { a } else { b };
+    let options = options_with(vec![
+        host_binding::decl_of("max", &max),
+        host_binding::decl_of("is_even", &is_even),
+        host_binding::decl_of("pick", &pick),
+    ]);
+    let mut vm = load(
+        r#"
+        |main :: () -> i32 {
+        |    pick(is_even(4), max(3, 7), 0) + pick(is_even(5), 0, 100)
+        |}
+        |"#,
+        &options,
+    );
+
+    vm.bind_host_function("max", max).unwrap();
+    vm.bind_host_function("is_even", is_even).unwrap();
+    vm.bind_host_function("pick", pick).unwrap();
+
+    assert_eq!(vm.run_main(), Ok(107));
+}
+
+#[test]
+fn test_bound_functions_returning_errors() {
+    let options = options_with(vec![host_fn("sqrt", &[Type::I32], Type::I32)]);
+    let mut vm = load(
+        r#"
+        |main :: () -> i32 {
+        |    sqrt(49) + sqrt(0 - 1)
+        |}
+        |"#,
+        &options,
+    );
+
+    vm.bind_host_function("sqrt", |n: i32| match n {
+        0.. => Ok(f64::from(n).sqrt() as i32),
+        _ => Err(format!("{} has no square root", n)),
+    })
+    .unwrap();
+
+    assert_eq!(
+        vm.run_main(),
+        Err(RuntimeError::HostFunctionFailed {
+            name: "sqrt".to_owned(),
+            message: "-1 has no square root".to_owned()
+        })
+    );
+}
+
+#[test]
+fn test_binding_checks_signature() {
+    let options = options_with(vec![host_fn("log", &[Type::I32], Type::Unit)]);
+    let mut vm = load(
+        r#"
+        |main :: () {
+        |    log(1);
+        |}
+        |"#,
+        &options,
+    );
+
+    assert_eq!(
+        vm.bind_host_function("log", |a: i32, b: i32| a + b),
+        Err(BindError::MismatchedArity {
+            name: "log".to_owned(),
+            expected: 1,
+            found: 2
+        })
+    );
+    assert_eq!(
+        vm.bind_host_function("log", |a: i32| a),
+        Err(BindError::MismatchedReturnType {
+            name: "log".to_owned(),
+            expected: Type::Unit,
+            found: Type::I32
+        })
+    );
+    assert_eq!(vm.bind_host_function("log", |_: i32| {}), Ok(()));
+}
+
+const SUM: &str = r#"
+    |sum :: (n: i32) -> i32 {
+    |    if n {
+    |        n + sum(n - 1)
+    |    } else {
+    |        0
+    |    }
+    |}
+    |"#;
+
+#[test]
+fn test_paused_execution_resumes_where_it_stopped() {
+    let mut vm = load(SUM, &Options::default());
+    let mut snapshot = vm.start("sum", &[100]).unwrap();
+    let mut pauses = 0;
+
+    let value = loop {
+        match vm.resume(&mut snapshot, 50) {
+            Ok(Progress::Paused) => pauses += 1,
+            Ok(Progress::Finished(value)) => break value,
+            Err(error) => panic!("execution failed: {:?}", error),
+        }
+    };
+
+    assert_eq!(value, Some(5050));
+    assert!(pauses > 10, "paused only {} times", pauses);
+    assert_eq!(
+        vm.resume(&mut snapshot, 50),
+        Ok(Progress::Finished(Some(5050)))
+    );
+}
+
+#[test]
+fn test_saved_snapshot_resumes_in_another_vm() {
+    let mut vm = load(SUM, &Options::default());
+    let mut snapshot = vm.start("sum", &[10]).unwrap();
+    assert_eq!(vm.resume(&mut snapshot, 40), Ok(Progress::Paused));
+
+    let saved = snapshot.save();
+    assert_eq!(Snapshot::load(&saved), Ok(snapshot.clone()));
+
+    // Resuming the same snapshot twice goes the same way, which is how
+    // execution can go back in time.
+    let mut other_vm = load(SUM, &Options::default());
+    let mut loaded = Snapshot::load(&saved).unwrap();
+    assert_eq!(
+        other_vm.resume(&mut loaded, u64::MAX),
+        Ok(Progress::Finished(Some(55)))
+    );
+    assert_eq!(
+        vm.resume(&mut snapshot, u64::MAX),
+        Ok(Progress::Finished(Some(55)))
+    );
+}
+
+#[test]
+fn test_snapshot_of_another_module_is_rejected() {
+    let mut vm = load(SUM, &Options::default());
+    let mut snapshot = vm.start("sum", &[10]).unwrap();
+
+    let mut other_vm = load(
+        r#"
+        |sum :: (n: i32) -> i32 {
+        |    n
+        |}
+        |"#,
+        &Options::default(),
+    );
+
+    assert_eq!(
+        other_vm.resume(&mut snapshot, u64::MAX),
+        Err(RuntimeError::InvalidSnapshot {
+            reason: "it was taken of another module"
+        })
+    );
+
+    snapshot.frames[0].pc = 1000;
+
+    assert_eq!(
+        vm.resume(&mut snapshot, u64::MAX),
+        Err(RuntimeError::InvalidSnapshot {
+            reason: "a frame's instruction is out of bounds"
+        })
+    );
+}
+
+#[test]
+fn test_snapshot_load_rejects_other_files() {
+    let mut vm = load(SUM, &Options::default());
+    let saved = vm.start("sum", &[10]).unwrap().save();
+
+    assert_eq!(Snapshot::load(b"SBC\0"), Err(SnapshotError::NotSnapshot));
+    assert_eq!(
+        Snapshot::load(&saved[..saved.len() - 1]),
+        Err(SnapshotError::Malformed {
+            reason: "unexpected end of file"
+        })
+    );
+}
+
+#[test]
+fn test_executed_instructions_are_counted() {
+    let mut vm = load(SUM, &Options::default());
+    assert_eq!(vm.call("sum", &[10]), Ok(Some(55)));
+    let executed = vm.executed_instructions();
+
+    let mut other_vm = load(SUM, &Options::default());
+    assert_eq!(other_vm.call("sum", &[10]), Ok(Some(55)));
+
+    assert!(executed > 0);
+    assert_eq!(other_vm.executed_instructions(), executed);
+
+    assert_eq!(vm.call("sum", &[10]), Ok(Some(55)));
+    assert_eq!(vm.executed_instructions(), 2 * executed);
+}
+
+const ROLL: &str = r#"
+    |main :: () -> i32 {
+    |    roll(6) * 10 + roll(6)
+    |}
+    |"#;
+
+#[test]
+fn test_deterministic_run_replays_host_calls() {
+    let options = options_with(vec![host_fn("roll", &[Type::I32], Type::I32)]);
+
+    let mut vm = load(ROLL, &options);
+    vm.set_deterministic(true);
+    let mut rolls = vec![3, 5].into_iter();
+    vm.register_host_function("roll", move |_| Ok(rolls.next().unwrap()));
+
+    assert_eq!(vm.run_main(), Ok(35));
+    assert_eq!(
+        vm.host_calls(),
+        [
+            HostCall {
+                name: "roll".to_owned(),
+                args: vec![6],
+                result: Ok(3)
+            },
+            HostCall {
+                name: "roll".to_owned(),
+                args: vec![6],
+                result: Ok(5)
+            },
+        ]
+    );
+
+    // The replay doesn't need the host function, only what it returned.
+    let mut replay_vm = load(ROLL, &options);
+    replay_vm.replay_host_calls(vm.host_calls().to_vec());
+
+    assert_eq!(replay_vm.run_main(), Ok(35));
+    assert_eq!(
+        replay_vm.executed_instructions(),
+        vm.executed_instructions()
+    );
+}
+
+#[test]
+fn test_replay_diverging_from_recorded_run() {
+    let options = options_with(vec![host_fn("roll", &[Type::I32], Type::I32)]);
+    let mut vm = load(ROLL, &options);
+
+    vm.replay_host_calls(vec![HostCall {
+        name: "roll".to_owned(),
+        args: vec![20],
+        result: Ok(1),
+    }]);
+
+    assert_eq!(
+        vm.run_main(),
+        Err(RuntimeError::ReplayDiverged {
+            name: "roll".to_owned()
+        })
+    );
+}
+
+#[test]
+fn test_run_vm_agrees_with_the_interpreter() {
+    let source_code = strip_margin(
+        r#"
+        |extern putchar :: (c: i32) -> i32;
+        |
+        |triangle :: (n: i32) -> i32 {
+        |    if n { n + triangle(n - 1) } else { 0 }
+        |}
+        |
+        |main :: () -> i32 {
+        |    for i : 1..4 {
+        |        print(triangle(i));
+        |    }
+        |    _ = putchar(10);
+        |    triangle(10) % 7
+        |}
+        |"#,
+    );
+
+    let run = |run: fn(String, &Options) -> Result<driver::Execution, _>| {
+        let output = Rc::new(RefCell::new(vec![]));
+        let options = Options {
+            output: Some(output.clone()),
+            ..Default::default()
+        };
+        let execution = run(source_code.clone(), &options).unwrap();
+
+        (
+            execution.exit_code,
+            String::from_utf8(output.take()).unwrap(),
+        )
+    };
+
+    let interpreted = run(driver::run);
+
+    assert_eq!(interpreted, (Ok(6), "136\n".to_owned()));
+    assert_eq!(run(driver::run_vm), interpreted);
+}
+
+#[test]
+fn test_range_fails_to_step_by_zero_or_less() {
+    let options = Options::default();
+    let mut vm = load(
+        r#"
+        |zero :: () -> i32 { 0 }
+        |main :: () -> i32 {
+        |    for i : 0..10 step zero() {}
+        |    1
+        |}
+        |"#,
+        &options,
+    );
+
+    assert_eq!(
+        vm.run_main(),
+        Err(RuntimeError::NonPositiveStep { span: span(67, 73) })
+    );
+
+    let mut vm = load(
+        r#"
+        |main :: () -> i32 {
+        |    for i : rev 0..10 step 0 - 1 {}
+        |    1
+        |}
+        |"#,
+        &options,
+    );
+
+    assert_eq!(
+        vm.run_main(),
+        Err(RuntimeError::NonPositiveStep { span: span(47, 52) })
+    );
+}
@@ -0,0 +1,310 @@
+use pretty_assertions::assert_eq;
+
+use crate::driver::{self, Emit, Options};
+use crate::tests::strip_margin;
+
+fn emit_wat(source_code: &str) -> String {
+    let options = Options {
+        emit: Emit::Wat,
+        ..Default::default()
+    };
+
+    match driver::compile(strip_margin(source_code), &options) {
+        Ok(compiled_program) => compiled_program.code.trim_end().to_owned(),
+        Err(diagnostic) => panic!("program failed to compile: {:?}", diagnostic.errors),
+    }
+}
+
+#[test]
+fn test_functions_are_exported_and_unit_main_returns_zero() {
+    let wat = emit_wat(
+        r#"
+        |nothing :: (unit: ()) {}
+        |
+        |main :: () {
+        |    x := 3;
+        |    nothing({});
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        wat,
+        strip_margin(
+            r#"
+            |(module
+            |  (func $nothing (export "nothing")
+            |  )
+            |  (func $main (export "main") (result i32)
+            |    call $nothing
+            |    i32.const 0
+            |  )
+            |)
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_calls_and_loops() {
+    let wat = emit_wat(
+        r#"
+        |add :: (a: i32, b: i32) -> i32 {
+        |    a + b
+        |}
+        |
+        |main :: () -> i32 {
+        |    x := add(1, 2);
+        |    for i : 0..=x {
+        |        if i - 2 {
+        |            continue
+        |        }
+        |        println(i);
+        |        break
+        |    } else {
+        |        println(if x { 1 } else { 2 });
+        |    }
+        |    x
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        wat,
+        strip_margin(
+            r#"
+            |(module
+            |  (import "env" "sophia_println_i32" (func $sophia_println_i32 (param i32)))
+            |  (func $add (export "add") (param $v0 i32) (param $v1 i32) (result i32)
+            |    local.get $v0
+            |    local.get $v1
+            |    i32.add
+            |  )
+            |  (func $main (export "main") (result i32)
+            |    (local $v2 i32)
+            |    (local $v4 i32)
+            |    (local $v13 i32)
+            |    (local $step i32)
+            |    i32.const 1
+            |    i32.const 2
+            |    call $add
+            |    local.set $v2
+            |    i32.const 0
+            |    local.set $v4
+            |    loop $loop1
+            |      block $block10
+            |        block $block6
+            |          local.get $v4
+            |          local.get $v2
+            |          i32.le_s
+            |          i32.eqz
+            |          br_if $block6
+            |          local.get $v4
+            |          i32.const 2
+            |          i32.sub
+            |          if
+            |            local.get $v4
+            |            i32.const 1
+            |            local.set $step
+            |            i64.extend_i32_s
+            |            local.get $step
+            |            i64.extend_i32_s
+            |            i64.add
+            |            i64.const 2147483648
+            |            i64.add
+            |            i64.const 4294967296
+            |            i64.ge_u
+            |            br_if $block6
+            |            local.get $v4
+            |            i32.const 1
+            |            i32.add
+            |            local.set $v4
+            |            br $loop1
+            |          else
+            |            local.get $v4
+            |            call $sophia_println_i32
+            |            br $block10
+            |          end
+            |        end
+            |        local.get $v2
+            |        if
+            |          i32.const 1
+            |          local.set $v13
+            |        else
+            |          i32.const 2
+            |          local.set $v13
+            |        end
+            |        local.get $v13
+            |        call $sophia_println_i32
+            |      end
+            |      local.get $v2
+            |      return
+            |    end
+            |    unreachable
+            |  )
+            |)
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_remainder_traps_on_overflow_like_native_code() {
+    let wat = emit_wat(
+        r#"
+        |main :: () -> i32 {
+        |    7 % 2
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        wat,
+        strip_margin(
+            r#"
+            |(module
+            |  (func $main (export "main") (result i32)
+            |    (local $rem.lhs i32)
+            |    (local $rem.rhs i32)
+            |    i32.const 7
+            |    i32.const 2
+            |    local.set $rem.rhs
+            |    local.set $rem.lhs
+            |    local.get $rem.lhs
+            |    local.get $rem.rhs
+            |    i32.div_s
+            |    drop
+            |    local.get $rem.lhs
+            |    local.get $rem.rhs
+            |    i32.rem_s
+            |  )
+            |)
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_builtins_call_the_runtime_library() {
+    let wat = emit_wat(
+        r#"
+        |main :: () {
+        |    print(1);
+        |    println(2);
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        wat,
+        strip_margin(
+            r#"
+            |(module
+            |  (import "env" "sophia_print_i32" (func $sophia_print_i32 (param i32)))
+            |  (import "env" "sophia_println_i32" (func $sophia_println_i32 (param i32)))
+            |  (func $main (export "main") (result i32)
+            |    i32.const 1
+            |    call $sophia_print_i32
+            |    i32.const 2
+            |    call $sophia_println_i32
+            |    i32.const 0
+            |  )
+            |)
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_extern_functions_are_imported() {
+    let wat = emit_wat(
+        r#"
+        |extern putchar :: (c: i32) -> i32;
+        |extern exit :: (code: i32);
+        |main :: () {
+        |    _ = putchar(65);
+        |    exit(0);
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        wat,
+        strip_margin(
+            r#"
+            |(module
+            |  (import "env" "putchar" (func $putchar (param i32) (result i32)))
+            |  (import "env" "exit" (func $exit (param i32)))
+            |  (func $main (export "main") (result i32)
+            |    i32.const 65
+            |    call $putchar
+            |    drop
+            |    i32.const 0
+            |    call $exit
+            |    i32.const 0
+            |  )
+            |)
+            |"#
+        )
+    );
+}
+
+#[test]
+fn test_steps_that_arent_positive_stop_the_program() {
+    let wat = emit_wat(
+        r#"
+        |count :: (n: i32) {
+        |    for i : 0..10 step n {}
+        |}
+        |"#,
+    );
+
+    assert_eq!(
+        wat,
+        strip_margin(
+            r#"
+            |(module
+            |  (func $count (export "count") (param $v0 i32)
+            |    (local $v3 i32)
+            |    (local $step i32)
+            |    local.get $v0
+            |    i32.const 1
+            |    i32.lt_s
+            |    if
+            |      unreachable
+            |    end
+            |    i32.const 0
+            |    local.set $v3
+            |    loop $loop1
+            |      block $block4
+            |        local.get $v3
+            |        i32.const 10
+            |        i32.lt_s
+            |        i32.eqz
+            |        br_if $block4
+            |        local.get $v3
+            |        local.get $v0
+            |        local.set $step
+            |        i64.extend_i32_s
+            |        local.get $step
+            |        i64.extend_i32_s
+            |        i64.add
+            |        i64.const 2147483648
+            |        i64.add
+            |        i64.const 4294967296
+            |        i64.ge_u
+            |        br_if $block4
+            |        local.get $v3
+            |        local.get $v0
+            |        i32.add
+            |        local.set $v3
+            |        br $loop1
+            |      end
+            |      return
+            |    end
+            |  )
+            |)
+            |"#
+        )
+    );
+}
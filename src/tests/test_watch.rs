@@ -0,0 +1,64 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use pretty_assertions::assert_eq;
+
+use crate::watch::FileWatcher;
+
+/// Contents of the files if the watcher saw any of them change, with the
+/// ones that can't be read as `None`.
+fn poll(watcher: &mut FileWatcher) -> Option<Vec<Option<String>>> {
+    watcher
+        .poll()
+        .map(|contents| contents.into_iter().map(Result::ok).collect())
+}
+
+#[test]
+fn test_watcher_reports_changed_files() {
+    let dir = env::temp_dir().join(format!("sophia-watch-{}", process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let a_path = dir.join("a.sph");
+    let b_path = dir.join("b.sph");
+    fs::write(&a_path, "a :: () -> i32 { 1 }").unwrap();
+    fs::write(&b_path, "main :: () -> i32 { a() }").unwrap();
+
+    let mut watcher = FileWatcher::new([&a_path, &b_path]);
+
+    // The files are new to the first poll.
+    assert_eq!(
+        poll(&mut watcher),
+        Some(vec![
+            Some("a :: () -> i32 { 1 }".to_owned()),
+            Some("main :: () -> i32 { a() }".to_owned()),
+        ])
+    );
+    assert_eq!(poll(&mut watcher), None);
+
+    // Writing the same contents again isn't a change.
+    fs::write(&a_path, "a :: () -> i32 { 1 }").unwrap();
+    assert_eq!(poll(&mut watcher), None);
+
+    fs::write(&a_path, "a :: () -> i32 { 2 }").unwrap();
+    assert_eq!(
+        poll(&mut watcher),
+        Some(vec![
+            Some("a :: () -> i32 { 2 }".to_owned()),
+            Some("main :: () -> i32 { a() }".to_owned()),
+        ])
+    );
+
+    fs::remove_file(&b_path).unwrap();
+    assert_eq!(
+        poll(&mut watcher),
+        Some(vec![Some("a :: () -> i32 { 2 }".to_owned()), None])
+    );
+    assert_eq!(poll(&mut watcher), None);
+
+    fs::write(&b_path, "main :: () -> i32 { a() }").unwrap();
+    assert!(poll(&mut watcher).is_some());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
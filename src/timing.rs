@@ -0,0 +1,107 @@
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::compiler_context::CompilerContext;
+
+/// Pass of the compiler that `-Ztime-passes` times.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Pass {
+    /// Scanning the tokens of the source code on their own, which parsing
+    /// does all over again, as it pulls the tokens from the scanner as it
+    /// goes.
+    Lex,
+    Parse,
+    Resolve,
+    /// Laying out the types of the program in memory.
+    Layout,
+    Typecheck,
+    /// Linting the program, and looking for what it never uses.
+    Lint,
+    /// Eliminating dead code.
+    Optimize,
+    Codegen,
+}
+
+/// How long a pass took, and how much memory the compilation had taken up
+/// once it was done. Memory is never freed until the compilation is, so that
+/// is the most it took up during the pass.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PassTiming {
+    pub pass: Pass,
+    /// Wall time of the pass.
+    pub duration: Duration,
+    /// Bytes allocated in the arenas of the syntax tree.
+    pub arena_bytes: usize,
+    /// How many strings were interned.
+    pub interned_strings: usize,
+    /// Bytes taken up by the interned strings.
+    pub interned_bytes: usize,
+}
+
+impl Pass {
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::Lex => "lex",
+            Pass::Parse => "parse",
+            Pass::Resolve => "resolve",
+            Pass::Layout => "layout",
+            Pass::Typecheck => "typecheck",
+            Pass::Lint => "lint",
+            Pass::Optimize => "optimize",
+            Pass::Codegen => "codegen",
+        }
+    }
+}
+
+/// Runs the pass, adding how long it took to the timings, if there are any to
+/// add it to.
+pub(crate) fn time_pass<T>(
+    timings: Option<&RefCell<Vec<PassTiming>>>,
+    context: &CompilerContext,
+    pass: Pass,
+    run: impl FnOnce() -> T,
+) -> T {
+    let Some(timings) = timings else {
+        return run();
+    };
+
+    let start = Instant::now();
+    let result = run();
+    let duration = start.elapsed();
+    let (interned_strings, interned_bytes) = context.interner_size();
+
+    timings.borrow_mut().push(PassTiming {
+        pass,
+        duration,
+        arena_bytes: context.allocated_bytes(),
+        interned_strings,
+        interned_bytes,
+    });
+
+    result
+}
+
+/// Renders the timings as a table, one pass per line, with the time in
+/// milliseconds.
+pub(crate) fn render_pass_timings(timings: &[PassTiming]) -> String {
+    let mut text = format!(
+        "{:<10} {:>10} {:>12} {:>10} {:>14}\n",
+        "pass", "time (ms)", "arena bytes", "interned", "interned bytes"
+    );
+
+    for timing in timings {
+        writeln!(
+            text,
+            "{:<10} {:>10.3} {:>12} {:>10} {:>14}",
+            timing.pass.name(),
+            timing.duration.as_secs_f64() * 1000.0,
+            timing.arena_bytes,
+            timing.interned_strings,
+            timing.interned_bytes
+        )
+        .unwrap();
+    }
+
+    text
+}
@@ -0,0 +1,1230 @@
+use crate::ast::{
+    ArrayExpr, BinaryExpr, BindRef, BreakExpr, CastExpr, CompoundExpr, Const, Expr, ExternFn,
+    FieldExpr, FnCallExpr, ForExpr, ForIteration, Function, HostFnDecl, IfExpr, IndexAssignExpr,
+    IndexExpr, MatchArm, MatchExpr, Pattern, PatternKind, Program, StructLitExpr, TupleBindDef,
+    TupleFieldExpr, Type, VariantExpr, VariantPattern,
+};
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::{CompileError, CompileWarning, Diagnostic};
+use crate::host::HashMap;
+use crate::layout::{EnumLayout, Layouts};
+use crate::lower::constant_value;
+use crate::profile::IntWidth;
+use crate::resolve::{Resolution, Resolutions};
+use crate::scanner::Span;
+
+/// Type of builtins as values, which only print `i32`s.
+const BUILTIN_FN_TYPE: Type = Type::Function {
+    params: &[Type::I32],
+    return_type: &Type::Unit,
+};
+
+/// Types that type checking inferred for what a program's names refer to, for
+/// editors to show.
+pub(crate) struct InferredTypes {
+    local_types: HashMap<Span, Type>,
+    fn_types: HashMap<usize, Type>,
+    host_fn_types: Vec<Type>,
+}
+
+impl InferredTypes {
+    /// Type of what the name resolves to, which only values have, so not
+    /// structs and enums.
+    pub(crate) fn of(&self, resolution: Resolution) -> Option<Type> {
+        match resolution {
+            Resolution::Local(def_span) => self.local_types.get(&def_span).copied(),
+            Resolution::Decl(decl_idx) => self.fn_types.get(&decl_idx).copied(),
+            Resolution::Host(host_fn_idx) => self.host_fn_types.get(host_fn_idx).copied(),
+            Resolution::Builtin(_) => Some(BUILTIN_FN_TYPE),
+        }
+    }
+
+    /// Local bindings along with their types, by the spans they're defined
+    /// at, in no particular order.
+    pub(crate) fn locals(&self) -> impl Iterator<Item = (Span, Type)> + '_ {
+        self.local_types.iter().map(|(&span, &ty)| (span, ty))
+    }
+}
+
+pub(crate) struct TypeChecker<'res> {
+    ctx: &'res CompilerContext,
+    resolutions: &'res Resolutions,
+    layouts: &'res Layouts,
+    int_width: IntWidth,
+    fn_signatures: HashMap<usize, FnSignature>,
+    host_fn_signatures: Vec<FnSignature>,
+    local_types: HashMap<Span, Type>,
+    /// Loops that the current expression is in the body of, within its
+    /// function, innermost last.
+    loops: Vec<EnclosingLoop>,
+    errors: Vec<CompileError>,
+    warnings: Vec<CompileWarning>,
+}
+
+enum EnclosingLoop {
+    /// Loop that completes when its iteration ends, so it has no value.
+    Iterative,
+    /// Loop that only a `break` ends, whose value has the type of the value
+    /// that the first `break` out of it carries, if one was checked yet.
+    Infinite(Option<Type>),
+}
+
+struct FnSignature {
+    param_types: Vec<Type>,
+    return_type: Type,
+}
+
+impl FnSignature {
+    fn of(function: Function) -> FnSignature {
+        FnSignature {
+            param_types: function.parameters.iter().map(|param| param.ty).collect(),
+            return_type: function.return_type,
+        }
+    }
+
+    fn of_extern(extern_fn: ExternFn) -> FnSignature {
+        FnSignature {
+            param_types: extern_fn.parameters.iter().map(|param| param.ty).collect(),
+            return_type: extern_fn.return_type,
+        }
+    }
+
+    /// Type of the function's values, as when it's bound to a name.
+    fn ty(&self, ctx: &CompilerContext) -> Type {
+        Type::Function {
+            params: ctx.alloc_type_list(&self.param_types),
+            return_type: &ctx.alloc_type_list(&[self.return_type])[0],
+        }
+    }
+}
+
+impl<'res> TypeChecker<'res> {
+    pub(crate) fn new(
+        ctx: &'res CompilerContext,
+        resolutions: &'res Resolutions,
+        layouts: &'res Layouts,
+        int_width: IntWidth,
+        host_fns: &[HostFnDecl],
+    ) -> TypeChecker<'res> {
+        let host_fn_signatures = host_fns
+            .iter()
+            .map(|host_fn| FnSignature {
+                param_types: host_fn.param_types.clone(),
+                return_type: host_fn.return_type,
+            })
+            .collect();
+
+        TypeChecker {
+            ctx,
+            resolutions,
+            layouts,
+            int_width,
+            fn_signatures: Default::default(),
+            host_fn_signatures,
+            local_types: Default::default(),
+            loops: vec![],
+            errors: vec![],
+            warnings: vec![],
+        }
+    }
+
+    pub(crate) fn check_program(self, program: Program) -> Diagnostic {
+        self.infer_types(program).0
+    }
+
+    /// Checks the program like `check_program`, also returning the types it
+    /// inferred, which are there even for programs with type errors.
+    pub(crate) fn infer_types(self, program: Program) -> (Diagnostic, InferredTypes) {
+        let decl_indices: Vec<_> = (0..program.decls.len()).collect();
+
+        self.infer_decl_types(program, &decl_indices)
+    }
+
+    /// Checks the declarations at the indices, which can refer to any
+    /// declaration of the program, whose names are expected to be resolved.
+    pub(crate) fn check_decls(self, program: Program, decl_indices: &[usize]) -> Diagnostic {
+        self.infer_decl_types(program, decl_indices).0
+    }
+
+    /// Checks the declarations at the indices like `check_decls`, also
+    /// returning the types it inferred, like `infer_types`.
+    pub(crate) fn infer_decl_types(
+        mut self,
+        program: Program,
+        decl_indices: &[usize],
+    ) -> (Diagnostic, InferredTypes) {
+        for (decl_idx, decl) in program.decls.iter().enumerate() {
+            match decl.value {
+                Expr::Function(function) => {
+                    self.fn_signatures
+                        .insert(decl_idx, FnSignature::of(*function));
+                }
+                Expr::Extern(extern_fn) => {
+                    self.fn_signatures
+                        .insert(decl_idx, FnSignature::of_extern(*extern_fn));
+                }
+                _ => {}
+            }
+        }
+
+        for &decl_idx in decl_indices {
+            self.check_expr(program.decls[decl_idx].value);
+        }
+
+        let inferred_types = InferredTypes {
+            fn_types: self
+                .fn_signatures
+                .iter()
+                .map(|(&decl_idx, signature)| (decl_idx, signature.ty(self.ctx)))
+                .collect(),
+            host_fn_types: self
+                .host_fn_signatures
+                .iter()
+                .map(|signature| signature.ty(self.ctx))
+                .collect(),
+            local_types: self.local_types,
+        };
+
+        (
+            Diagnostic {
+                errors: self.errors,
+                warnings: self.warnings,
+            },
+            inferred_types,
+        )
+    }
+
+    fn check_expr(&mut self, expr: &Expr) -> Type {
+        match expr {
+            Expr::Const(const_expr) => match const_expr.value {
+                Const::IntegerConstant { value } => {
+                    if !self.int_width.contains(value.into()) {
+                        self.errors.push(CompileError::IntegerConstantOutOfRange {
+                            width: self.int_width,
+                            span: const_expr.span,
+                        });
+                    }
+
+                    Type::I32
+                }
+                // Its value was checked to fit in the type when it was parsed.
+                Const::SuffixedIntegerConstant { ty, .. } => ty,
+            },
+            Expr::BindRef(bind_ref) => self.type_of_bind_ref(*bind_ref),
+            Expr::BindDef(bind_def) => {
+                let value_ty = self.check_expr(bind_def.value);
+
+                // The binding has the type it's annotated with even if the
+                // value doesn't, so that its uses are checked against it.
+                let ty = match bind_def.ty {
+                    Some(ty) => {
+                        self.expect_widening(ty, value_ty, bind_def.value.span());
+                        ty
+                    }
+                    None => value_ty,
+                };
+                self.local_types.insert(bind_def.span, ty);
+
+                Type::Unit
+            }
+            Expr::Function(function) => self.check_function(*function),
+            Expr::Extern(extern_fn) => {
+                self.check_extern_fn(*extern_fn);
+
+                Type::Unit
+            }
+            // Structs and enums were checked when they were laid out.
+            Expr::Struct(_) | Expr::Enum(_) => Type::Unit,
+            Expr::StructLit(struct_lit_expr) => self.check_struct_lit_expr(*struct_lit_expr),
+            Expr::Field(field_expr) => self.check_field_expr(*field_expr),
+            Expr::Variant(variant_expr) => self.check_variant_expr(*variant_expr),
+            Expr::Tuple(tuple_expr) => {
+                let element_types: Vec<_> = tuple_expr
+                    .elements
+                    .iter()
+                    .map(|element| self.check_expr(element))
+                    .collect();
+
+                Type::Tuple(self.ctx.alloc_type_list(&element_types))
+            }
+            Expr::TupleField(tuple_field_expr) => self.check_tuple_field_expr(*tuple_field_expr),
+            Expr::TupleBindDef(tuple_bind_def) => {
+                self.check_tuple_bind_def(*tuple_bind_def);
+
+                Type::Unit
+            }
+            Expr::Array(array_expr) => self.check_array_expr(*array_expr),
+            Expr::Index(index_expr) => self.check_index_expr(*index_expr).unwrap_or(Type::Unit),
+            Expr::IndexAssign(index_assign_expr) => {
+                self.check_index_assign_expr(*index_assign_expr);
+
+                Type::Unit
+            }
+            Expr::If(if_expr) => self.check_if_expr(*if_expr, true),
+            Expr::Match(match_expr) => self.check_match_expr(*match_expr),
+            Expr::For(for_expr) => self.check_for_expr(*for_expr),
+            Expr::Break(break_expr) => {
+                self.check_break_expr(*break_expr);
+
+                Type::Unit
+            }
+            Expr::Continue(_) => Type::Unit,
+            Expr::Compound(compound_expr) => self.check_compound_expr(*compound_expr),
+            Expr::Semi(expr) => {
+                self.check_expr(expr);
+
+                Type::Unit
+            }
+            Expr::FnCall(fn_call_expr) => self.check_fn_call_expr(*fn_call_expr),
+            Expr::Binary(binary_expr) => self.check_binary_expr(*binary_expr),
+            Expr::Cast(cast_expr) => self.check_cast_expr(*cast_expr),
+            Expr::Paren(paren_expr) => self.check_expr(paren_expr.expr),
+        }
+    }
+
+    fn check_function(&mut self, function: Function) -> Type {
+        for param in function.parameters {
+            self.local_types.insert(param.span, param.ty);
+        }
+
+        // Loops can't be broken out of from the closures they contain.
+        let loops = std::mem::take(&mut self.loops);
+        let body_ty = self.check_compound_expr(function.body);
+        self.loops = loops;
+
+        self.expect_widening(function.return_type, body_ty, value_span(function.body));
+
+        // It's only a value when it's a closure, as top-level declarations
+        // aren't evaluated.
+        FnSignature::of(function).ty(self.ctx)
+    }
+
+    fn check_extern_fn(&mut self, extern_fn: ExternFn) {
+        for param in extern_fn.parameters {
+            if !param.ty.is_int() {
+                self.errors.push(CompileError::InvalidExternType {
+                    ty: param.ty.into(),
+                    span: param.span,
+                });
+            }
+        }
+
+        // Returning nothing is returning `void`.
+        let return_type = extern_fn.return_type;
+
+        if return_type != Type::Unit && !return_type.is_int() {
+            self.errors.push(CompileError::InvalidExternType {
+                ty: return_type.into(),
+                span: extern_fn.span,
+            });
+        }
+    }
+
+    fn check_struct_lit_expr(&mut self, struct_lit_expr: StructLitExpr) -> Type {
+        let Some(Resolution::Decl(decl_idx)) =
+            self.resolutions.get(struct_lit_expr.identifier_span)
+        else {
+            unreachable!("all struct names are resolved before type checking");
+        };
+        let layout = self
+            .layouts
+            .struct_of_decl(decl_idx)
+            .expect("struct names resolve to structs");
+        let ty = Type::Named(layout.name);
+
+        let mut initialized_fields = vec![];
+
+        for field in struct_lit_expr.fields {
+            let value_ty = self.check_expr(field.value);
+            let name = self.ctx.resolve_symbol(field.identifier);
+
+            if initialized_fields.contains(&name) {
+                self.errors.push(CompileError::DuplicateField {
+                    field: name.to_owned(),
+                    span: field.identifier_span,
+                });
+
+                continue;
+            }
+
+            match layout.field(name) {
+                Some(field_layout) => {
+                    initialized_fields.push(name);
+                    self.expect_type(field_layout.ty, value_ty, field.value.span());
+                }
+                None => self.errors.push(CompileError::NoSuchField {
+                    ty: ty.into(),
+                    field: name.to_owned(),
+                    span: field.identifier_span,
+                }),
+            }
+        }
+
+        let missing_fields: Vec<_> = layout
+            .fields
+            .iter()
+            .filter(|field_layout| !initialized_fields.contains(&field_layout.name))
+            .map(|field_layout| field_layout.name.to_owned())
+            .collect();
+
+        if !missing_fields.is_empty() {
+            self.errors.push(CompileError::MissingFields {
+                ty: ty.into(),
+                fields: missing_fields,
+                span: struct_lit_expr.identifier_span,
+            });
+        }
+
+        ty
+    }
+
+    fn check_field_expr(&mut self, field_expr: FieldExpr) -> Type {
+        let base_ty = self.check_expr(field_expr.base);
+        let name = self.ctx.resolve_symbol(field_expr.identifier);
+
+        let field_ty = match base_ty {
+            Type::Named(type_name) => self
+                .layouts
+                .get_struct(type_name)
+                .and_then(|layout| layout.field(name))
+                .map(|field_layout| field_layout.ty),
+            Type::Unit
+            | Type::I8
+            | Type::I16
+            | Type::I32
+            | Type::I64
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::Usize
+            | Type::Tuple(_)
+            | Type::Array { .. }
+            | Type::Function { .. } => None,
+        };
+
+        match field_ty {
+            Some(field_ty) => field_ty,
+            None => {
+                self.errors.push(CompileError::NoSuchField {
+                    ty: base_ty.into(),
+                    field: name.to_owned(),
+                    span: field_expr.identifier_span,
+                });
+
+                Type::Unit
+            }
+        }
+    }
+
+    fn check_tuple_field_expr(&mut self, tuple_field_expr: TupleFieldExpr) -> Type {
+        let base_ty = self.check_expr(tuple_field_expr.base);
+
+        let element_ty = match base_ty {
+            Type::Tuple(element_types) => element_types.get(tuple_field_expr.index).copied(),
+            Type::Unit
+            | Type::I8
+            | Type::I16
+            | Type::I32
+            | Type::I64
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::Usize
+            | Type::Named(_)
+            | Type::Array { .. }
+            | Type::Function { .. } => None,
+        };
+
+        match element_ty {
+            Some(element_ty) => element_ty,
+            None => {
+                self.errors.push(CompileError::NoSuchField {
+                    ty: base_ty.into(),
+                    field: tuple_field_expr.index.to_string(),
+                    span: tuple_field_expr.index_span,
+                });
+
+                Type::Unit
+            }
+        }
+    }
+
+    /// Gives each binding the type of the element at its position, or `()` if
+    /// the value isn't a tuple of as many elements, so that the code after it
+    /// can still be checked.
+    fn check_tuple_bind_def(&mut self, tuple_bind_def: TupleBindDef) {
+        let value_ty = self.check_expr(tuple_bind_def.value);
+
+        let element_types = match value_ty {
+            Type::Tuple(element_types) if element_types.len() == tuple_bind_def.bindings.len() => {
+                element_types
+            }
+            _ => {
+                self.errors.push(CompileError::MismatchedTupleBindings {
+                    bindings: tuple_bind_def.bindings.len(),
+                    found: value_ty.into(),
+                    span: tuple_bind_def.value.span(),
+                });
+
+                &[]
+            }
+        };
+
+        for (idx, binding) in tuple_bind_def.bindings.iter().enumerate() {
+            let ty = element_types.get(idx).copied().unwrap_or(Type::Unit);
+            self.local_types.insert(binding.span, ty);
+        }
+    }
+
+    /// Types the array after its first element, which the others must have
+    /// the type of.
+    fn check_array_expr(&mut self, array_expr: ArrayExpr) -> Type {
+        let (first, rest) = array_expr
+            .elements
+            .split_first()
+            .expect("array literals have at least one element");
+        let element_ty = self.check_expr(first);
+
+        for element in rest {
+            let ty = self.check_expr(element);
+            self.expect_type(element_ty, ty, element.span());
+        }
+
+        Type::Array {
+            element: &self.ctx.alloc_type_list(&[element_ty])[0],
+            len: array_expr.elements.len(),
+        }
+    }
+
+    /// Type of the indexed element, or `None` if the indexed value isn't an
+    /// array. Constant indices are checked against the array's length, the
+    /// others are checked when the program runs.
+    fn check_index_expr(&mut self, index_expr: IndexExpr) -> Option<Type> {
+        let base_ty = self.check_expr(index_expr.base);
+        let index_ty = self.check_expr(index_expr.index);
+        self.expect_widening(Type::I32, index_ty, index_expr.index.span());
+
+        let Type::Array { element, len } = base_ty else {
+            self.errors.push(CompileError::CannotIndex {
+                ty: base_ty.into(),
+                span: index_expr.span,
+            });
+
+            return None;
+        };
+
+        if let Some(index) = constant_value(index_expr.index) {
+            if !usize::try_from(index).is_ok_and(|index| index < len) {
+                self.errors.push(CompileError::IndexOutOfBounds {
+                    index,
+                    len,
+                    span: index_expr.index.span(),
+                });
+            }
+        }
+
+        Some(*element)
+    }
+
+    fn check_index_assign_expr(&mut self, index_assign_expr: IndexAssignExpr) {
+        let element_ty = self.check_index_expr(index_assign_expr.target);
+        let value_ty = self.check_expr(index_assign_expr.value);
+
+        if !self.is_local_binding_root(index_assign_expr.target.base) {
+            self.errors.push(CompileError::InvalidAssignTarget {
+                span: index_assign_expr.target.span,
+            });
+        } else if let Some(element_ty) = element_ty {
+            self.expect_type(element_ty, value_ty, index_assign_expr.value.span());
+        }
+    }
+
+    /// Whether the indexed value is an array that a local binding holds, even
+    /// if nested in other arrays, as only those can be changed.
+    fn is_local_binding_root(&self, base: &Expr) -> bool {
+        match base {
+            Expr::BindRef(bind_ref) => matches!(
+                self.resolutions.get(bind_ref.span),
+                Some(Resolution::Local(_))
+            ),
+            Expr::Index(index_expr) => self.is_local_binding_root(index_expr.base),
+            Expr::Paren(paren_expr) => self.is_local_binding_root(paren_expr.expr),
+            _ => false,
+        }
+    }
+
+    fn check_variant_expr(&mut self, variant_expr: VariantExpr) -> Type {
+        let payload_ty = variant_expr
+            .payload
+            .map(|payload| (self.check_expr(payload), payload.span()));
+
+        let Some(Resolution::Decl(decl_idx)) =
+            self.resolutions.get(variant_expr.enum_identifier_span)
+        else {
+            unreachable!("all enum names are resolved before type checking");
+        };
+        let layout = self
+            .layouts
+            .enum_of_decl(decl_idx)
+            .expect("enum names resolve to enums");
+        let ty = Type::Named(layout.name);
+        let name = self.ctx.resolve_symbol(variant_expr.variant_identifier);
+
+        let Some(variant_layout) = layout.variant(name) else {
+            self.errors.push(CompileError::NoSuchVariant {
+                ty: ty.into(),
+                variant: name.to_owned(),
+                span: variant_expr.variant_identifier_span,
+            });
+
+            return ty;
+        };
+
+        match (variant_layout.payload, payload_ty) {
+            (Some(expected), Some((found, payload_span))) => {
+                self.expect_type(expected, found, payload_span)
+            }
+            (None, None) => {}
+            (expected, _) => self.errors.push(CompileError::MismatchedVariantPayload {
+                variant: format!("{}::{}", layout.name, name),
+                expected: expected.map(Into::into),
+                span: variant_expr.span,
+            }),
+        }
+
+        ty
+    }
+
+    /// Checks an if-expression, whose branches must agree on its type. Without
+    /// an `else` branch, it's of type `()`, so if its value is used, none of
+    /// its branches may have another. Branches that break or continue out of
+    /// a loop have no value, so they agree with any type.
+    fn check_if_expr(&mut self, if_expr: IfExpr, is_value_used: bool) -> Type {
+        self.check_cond_expr(if_expr.cond_expr);
+        let true_branch_ty = self.check_compound_expr(if_expr.true_branch);
+
+        let mut branch_types = vec![(true_branch_ty, if_expr.true_branch)];
+
+        for branch in if_expr.else_if_branches {
+            self.check_cond_expr(branch.cond_expr);
+
+            let branch_ty = self.check_compound_expr(branch.true_branch);
+            branch_types.push((branch_ty, branch.true_branch));
+        }
+
+        if let Some(final_branch) = if_expr.final_branch {
+            let final_branch_ty = self.check_compound_expr(final_branch);
+            branch_types.push((final_branch_ty, final_branch));
+        }
+
+        let mut value_branches = branch_types
+            .into_iter()
+            .filter(|(_, branch)| !branch.exprs.iter().any(crate::dce::diverges));
+
+        match if_expr.final_branch {
+            Some(_) => {
+                let Some((if_ty, _)) = value_branches.next() else {
+                    return Type::Unit;
+                };
+
+                // Every branch must agree with the first one on the if-expr's type.
+                for (branch_ty, branch) in value_branches {
+                    self.expect_type(if_ty, branch_ty, value_span(branch));
+                }
+
+                if_ty
+            }
+            None => {
+                if is_value_used {
+                    for (branch_ty, branch) in value_branches {
+                        if branch_ty != Type::Unit {
+                            self.errors.push(CompileError::MissingElseBranch {
+                                ty: branch_ty.into(),
+                                span: value_span(branch),
+                            });
+                        }
+                    }
+                }
+
+                Type::Unit
+            }
+        }
+    }
+
+    fn check_match_expr(&mut self, match_expr: MatchExpr) -> Type {
+        let scrutinee_ty = self.check_expr(match_expr.scrutinee);
+
+        // Enums are matched by their variants, and everything else by the
+        // integers it may be.
+        let enum_layout = match scrutinee_ty {
+            Type::Named(type_name) => self.layouts.get_enum(type_name),
+            Type::Unit
+            | Type::I8
+            | Type::I16
+            | Type::I32
+            | Type::I64
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::Usize
+            | Type::Tuple(_)
+            | Type::Array { .. }
+            | Type::Function { .. } => None,
+        };
+
+        if enum_layout.is_none() {
+            self.expect_widening(Type::I32, scrutinee_ty, match_expr.scrutinee.span());
+        }
+
+        let mut match_ty = None;
+
+        for arm in match_expr.arms {
+            match enum_layout {
+                Some(enum_layout) => self.check_variant_pattern(arm.pattern, enum_layout),
+                None => self.check_integer_pattern(arm.pattern),
+            }
+
+            let arm_ty = self.check_expr(arm.expr);
+
+            // Every arm must agree with the first one on the match-expr's type.
+            match match_ty {
+                Some(match_ty) => self.expect_type(match_ty, arm_ty, arm.expr.span()),
+                None => match_ty = Some(arm_ty),
+            }
+        }
+
+        match enum_layout {
+            Some(enum_layout) => {
+                let uncovered = uncovered_variants(match_expr.arms, enum_layout);
+
+                if !uncovered.is_empty() {
+                    self.errors.push(CompileError::NonExhaustiveVariants {
+                        uncovered,
+                        span: match_expr.scrutinee.span(),
+                    });
+                }
+            }
+            None => {
+                let uncovered = uncovered_ranges(match_expr.arms, self.int_width);
+
+                if !uncovered.is_empty() {
+                    self.errors.push(CompileError::NonExhaustiveMatch {
+                        uncovered,
+                        width: self.int_width,
+                        span: match_expr.scrutinee.span(),
+                    });
+                }
+            }
+        }
+
+        match_ty.unwrap_or(Type::Unit)
+    }
+
+    fn check_integer_pattern(&mut self, pattern: Pattern) {
+        let (start, end) = match pattern.kind {
+            PatternKind::Wildcard => return,
+            PatternKind::Variant(variant_pattern) => {
+                self.bind_variant_pattern(variant_pattern, Type::Unit);
+                self.errors.push(CompileError::MismatchedTypes {
+                    expected: Type::I32.into(),
+                    found: Type::Named(variant_pattern.enum_name).into(),
+                    span: pattern.span,
+                });
+
+                return;
+            }
+            PatternKind::Integer(_) | PatternKind::Range { .. } => {
+                pattern.kind.bounds().expect("integer patterns have bounds")
+            }
+        };
+
+        let fits = |value| i64::try_from(value).is_ok_and(|value| self.int_width.contains(value));
+
+        if !fits(start) || !fits(end) {
+            self.errors.push(CompileError::IntegerConstantOutOfRange {
+                width: self.int_width,
+                span: pattern.span,
+            });
+        }
+    }
+
+    fn check_variant_pattern(&mut self, pattern: Pattern, enum_layout: &EnumLayout) {
+        let ty = Type::Named(enum_layout.name);
+
+        let variant_pattern = match pattern.kind {
+            PatternKind::Wildcard => return,
+            PatternKind::Integer(_) | PatternKind::Range { .. } => {
+                self.errors.push(CompileError::MismatchedTypes {
+                    expected: ty.into(),
+                    found: Type::I32.into(),
+                    span: pattern.span,
+                });
+
+                return;
+            }
+            PatternKind::Variant(variant_pattern) => variant_pattern,
+        };
+
+        if variant_pattern.enum_name != enum_layout.name {
+            self.bind_variant_pattern(variant_pattern, Type::Unit);
+            self.errors.push(CompileError::MismatchedTypes {
+                expected: ty.into(),
+                found: Type::Named(variant_pattern.enum_name).into(),
+                span: pattern.span,
+            });
+
+            return;
+        }
+
+        let Some(variant_layout) = enum_layout.variant(variant_pattern.variant_name) else {
+            self.bind_variant_pattern(variant_pattern, Type::Unit);
+            self.errors.push(CompileError::NoSuchVariant {
+                ty: ty.into(),
+                variant: variant_pattern.variant_name.to_owned(),
+                span: variant_pattern.variant_name_span,
+            });
+
+            return;
+        };
+
+        match (variant_layout.payload, variant_pattern.binding) {
+            (Some(payload_ty), Some(_)) => self.bind_variant_pattern(variant_pattern, payload_ty),
+            (None, None) => {}
+            (expected, _) => {
+                self.bind_variant_pattern(variant_pattern, Type::Unit);
+                self.errors.push(CompileError::MismatchedVariantPayload {
+                    variant: format!("{}::{}", enum_layout.name, variant_layout.name),
+                    expected: expected.map(Into::into),
+                    span: pattern.span,
+                });
+            }
+        }
+    }
+
+    /// Gives the binding of the pattern, if it has one, the type of the value
+    /// it's bound to, which is `()` if the pattern is wrong, so that the arm
+    /// can still be checked.
+    fn bind_variant_pattern(&mut self, variant_pattern: VariantPattern, ty: Type) {
+        if let Some((_, span)) = variant_pattern.binding {
+            self.local_types.insert(span, ty);
+        }
+    }
+
+    fn check_for_expr(&mut self, for_expr: ForExpr) -> Type {
+        match for_expr.iteration {
+            Some(ForIteration::Conditional { cond_expr }) => self.check_cond_expr(cond_expr),
+            Some(ForIteration::Iterative {
+                identifier_span,
+                start_expr,
+                end_expr,
+                step_expr,
+                ..
+            }) => {
+                // The bounds and the step are compared and added together
+                // as they are, so they're of the same type.
+                let ty = self.check_range_bound(start_expr);
+                let end_ty = self.check_range_bound(end_expr);
+                self.expect_type(ty, end_ty, end_expr.span());
+
+                if let Some(step_expr) = step_expr {
+                    let step_ty = self.check_range_bound(step_expr);
+                    self.expect_type(ty, step_ty, step_expr.span());
+
+                    if constant_value(step_expr) == Some(0) {
+                        self.errors.push(CompileError::ZeroStep {
+                            span: step_expr.span(),
+                        });
+                    }
+                }
+
+                self.local_types.insert(identifier_span, ty);
+            }
+            Some(ForIteration::InCollection {
+                identifier_span,
+                collection_expr,
+                ..
+            }) => {
+                let ty = self.check_collection_expr(collection_expr);
+                self.local_types.insert(identifier_span, ty);
+            }
+            None => {}
+        }
+
+        self.loops.push(match for_expr.iteration {
+            Some(_) => EnclosingLoop::Iterative,
+            None => EnclosingLoop::Infinite(None),
+        });
+
+        // The body's value is discarded at the end of every iteration, so there's no
+        // final expression to give the loop a value, but its `break`s can.
+        self.check_statements(for_expr.body.exprs);
+
+        let loop_ty = match self.loops.pop() {
+            Some(EnclosingLoop::Infinite(Some(ty))) => ty,
+            _ => Type::Unit,
+        };
+
+        // A `break` in the else-branch exits an enclosing loop instead.
+        if let Some(else_branch) = for_expr.else_branch {
+            self.check_statements(else_branch.exprs);
+        }
+
+        loop_ty
+    }
+
+    /// Bounds of a range are integers, of any type, and so is its step, and the
+    /// loop's variable has the type of its start. Bounds of other types are
+    /// taken for `i32`s, so that the loop can still be checked.
+    fn check_range_bound(&mut self, bound_expr: &Expr) -> Type {
+        let ty = self.check_expr(bound_expr);
+
+        if ty.is_int() {
+            ty
+        } else {
+            self.expect_type(Type::I32, ty, bound_expr.span());
+            Type::I32
+        }
+    }
+
+    /// The values of all the `break`s out of a loop have the same type, which
+    /// is `()` for those that carry none.
+    fn check_break_expr(&mut self, break_expr: BreakExpr) {
+        let value_ty = match break_expr.value {
+            Some(value) => self.check_expr(value),
+            None => Type::Unit,
+        };
+        let value_span = break_expr.value.map_or(break_expr.span, Expr::span);
+
+        match self.loops.last_mut() {
+            Some(EnclosingLoop::Infinite(loop_ty @ None)) => *loop_ty = Some(value_ty),
+            Some(EnclosingLoop::Infinite(Some(loop_ty))) => {
+                let loop_ty = *loop_ty;
+                self.expect_type(loop_ty, value_ty, value_span);
+            }
+            Some(EnclosingLoop::Iterative) => {
+                if break_expr.value.is_some() {
+                    self.errors
+                        .push(CompileError::BreakValueInIterativeLoop { span: value_span });
+                }
+            }
+            None => unreachable!("`break`s outside of loops are rejected before type checking"),
+        }
+    }
+
+    /// Type of the collection's elements, which is `()` if the collection
+    /// isn't an array, so that the loop's body can still be checked.
+    fn check_collection_expr(&mut self, collection_expr: &Expr) -> Type {
+        match self.check_expr(collection_expr) {
+            Type::Array { element, .. } => *element,
+            ty => {
+                self.errors.push(CompileError::CannotIterate {
+                    ty: ty.into(),
+                    span: collection_expr.span(),
+                });
+
+                Type::Unit
+            }
+        }
+    }
+
+    /// Conditions hold if they're non-zero integers, of any type.
+    fn check_cond_expr(&mut self, cond_expr: &Expr) {
+        let cond_ty = self.check_expr(cond_expr);
+
+        if !cond_ty.is_int() {
+            self.errors.push(CompileError::InvalidCondition {
+                found: cond_ty.into(),
+                span: cond_expr.span(),
+            });
+        }
+    }
+
+    fn check_compound_expr(&mut self, compound_expr: CompoundExpr) -> Type {
+        match compound_expr.exprs.split_last() {
+            Some((last_expr, exprs)) => {
+                self.check_statements(exprs);
+
+                match last_expr {
+                    Expr::Semi(_) => {
+                        self.check_statement(last_expr);
+
+                        Type::Unit
+                    }
+                    _ => self.check_expr(last_expr),
+                }
+            }
+            None => Type::Unit,
+        }
+    }
+
+    fn check_statements(&mut self, exprs: &[Expr]) {
+        for expr in exprs {
+            self.check_statement(expr);
+        }
+    }
+
+    /// Checks an expression whose value is discarded, warning if it isn't `()`.
+    fn check_statement(&mut self, expr: &Expr) {
+        let value_expr = match expr {
+            Expr::Semi(expr) => expr,
+            _ => expr,
+        };
+
+        let ty = match value_expr {
+            Expr::If(if_expr) => self.check_if_expr(*if_expr, false),
+            _ => self.check_expr(value_expr),
+        };
+
+        if ty != Type::Unit {
+            self.warnings.push(CompileWarning::UnusedValue {
+                span: value_expr.span(),
+                ty: ty.into(),
+            });
+        }
+    }
+
+    fn check_fn_call_expr(&mut self, fn_call_expr: FnCallExpr) -> Type {
+        let arg_types: Vec<Type> = fn_call_expr
+            .args
+            .iter()
+            .map(|arg| self.check_expr(arg))
+            .collect();
+
+        let (param_types, return_type) = match self.resolutions.get(fn_call_expr.span) {
+            Some(Resolution::Decl(decl_idx)) => match self.fn_signatures.get(&decl_idx) {
+                Some(signature) => (&signature.param_types[..], signature.return_type),
+                None => {
+                    let type_name = match self.layouts.struct_of_decl(decl_idx) {
+                        Some(layout) => layout.name,
+                        None => match self.layouts.enum_of_decl(decl_idx) {
+                            Some(layout) => layout.name,
+                            None => unreachable!(
+                                "only functions, structs and enums are declared at the top level"
+                            ),
+                        },
+                    };
+
+                    self.errors.push(CompileError::CallToNonFunction {
+                        ty: Type::Named(type_name).into(),
+                        span: fn_call_expr.span,
+                    });
+
+                    return Type::Unit;
+                }
+            },
+            Some(Resolution::Host(host_fn_idx)) => {
+                let signature = &self.host_fn_signatures[host_fn_idx];
+
+                (&signature.param_types[..], signature.return_type)
+            }
+            // Builtins print an integer of any type.
+            Some(Resolution::Builtin(_)) => match arg_types[..] {
+                [arg_ty] if arg_ty.is_int() => return Type::Unit,
+                _ => (&[Type::I32][..], Type::Unit),
+            },
+            Some(Resolution::Local(def_span)) => match self.local_types[&def_span] {
+                Type::Function {
+                    params,
+                    return_type,
+                } => (params, *return_type),
+                ty => {
+                    self.errors.push(CompileError::CallToNonFunction {
+                        ty: ty.into(),
+                        span: fn_call_expr.span,
+                    });
+
+                    return Type::Unit;
+                }
+            },
+            None => unreachable!("all names are resolved before type checking"),
+        };
+
+        // Arguments are only told apart from each other when there's one for
+        // each parameter, so each one of the wrong type is reported on its own.
+        if arg_types.len() == param_types.len() {
+            let param_types = param_types.to_vec();
+
+            for ((arg, arg_ty), param_ty) in
+                fn_call_expr.args.iter().zip(arg_types).zip(param_types)
+            {
+                self.expect_widening(param_ty, arg_ty, arg.span());
+            }
+        } else {
+            self.errors.push(CompileError::MismatchedCallArguments {
+                expected: param_types.iter().copied().map(Into::into).collect(),
+                found: arg_types.into_iter().map(Into::into).collect(),
+                span: fn_call_expr.span,
+            });
+        }
+
+        return_type
+    }
+
+    /// Arithmetic is only defined on integers, and the operand of the narrower
+    /// type is widened into the type of the other, which is the result's.
+    fn check_binary_expr(&mut self, binary_expr: BinaryExpr) -> Type {
+        let (operand, operations) = binary_expr.chain();
+        let mut lhs_ty = self.check_operand(operand);
+
+        for binary_expr in operations {
+            let rhs_ty = self.check_operand(binary_expr.rhs);
+
+            if lhs_ty.widens_to(rhs_ty) {
+                lhs_ty = rhs_ty;
+            } else {
+                self.expect_widening(lhs_ty, rhs_ty, binary_expr.rhs.span());
+            }
+        }
+
+        lhs_ty
+    }
+
+    /// Type of an operand of a binary operation, which is taken to be `i32`
+    /// if it isn't an integer.
+    fn check_operand(&mut self, operand: &Expr) -> Type {
+        let ty = self.check_expr(operand);
+
+        if ty.is_int() {
+            ty
+        } else {
+            self.expect_type(Type::I32, ty, operand.span());
+            Type::I32
+        }
+    }
+
+    fn check_cast_expr(&mut self, cast_expr: CastExpr) -> Type {
+        let ty = self.check_expr(cast_expr.expr);
+
+        if !ty.is_int() || !cast_expr.ty.is_int() {
+            self.errors.push(CompileError::InvalidCast {
+                from: ty.into(),
+                to: cast_expr.ty.into(),
+                span: cast_expr.span,
+            });
+        }
+
+        cast_expr.ty
+    }
+
+    fn type_of_bind_ref(&self, bind_ref: BindRef) -> Type {
+        match self.resolutions.get(bind_ref.span) {
+            Some(Resolution::Local(def_span)) => self.local_types[&def_span],
+            Some(Resolution::Decl(decl_idx)) => match self.fn_signatures.get(&decl_idx) {
+                Some(signature) => signature.ty(self.ctx),
+                // Structs and enums aren't values.
+                None => Type::Unit,
+            },
+            Some(Resolution::Host(host_fn_idx)) => {
+                self.host_fn_signatures[host_fn_idx].ty(self.ctx)
+            }
+            Some(Resolution::Builtin(_)) => BUILTIN_FN_TYPE,
+            None => unreachable!("all names are resolved before type checking"),
+        }
+    }
+
+    fn expect_type(&mut self, expected: Type, found: Type, span: Span) {
+        if expected != found {
+            self.errors.push(CompileError::MismatchedTypes {
+                expected: expected.into(),
+                found: found.into(),
+                span,
+            });
+        }
+    }
+
+    /// Like `expect_type`, but integers of narrower types are expected too.
+    /// It's only where values are given a type, be it by binding them to a
+    /// name whose type is spelled out, passing them to a function, returning
+    /// them from one, or computing with them, that they're widened, so that
+    /// every other value has exactly the type it's checked to have.
+    fn expect_widening(&mut self, expected: Type, found: Type, span: Span) {
+        if !found.widens_to(expected) {
+            self.expect_type(expected, found, span);
+        }
+    }
+}
+
+/// Variants of the enum that none of the arms' patterns match, as patterns
+/// that'd match them, in the order they're declared.
+fn uncovered_variants(arms: &[MatchArm], enum_layout: &EnumLayout) -> Vec<String> {
+    let mut covered_variants = vec![];
+
+    for arm in arms {
+        match arm.pattern.kind {
+            PatternKind::Wildcard => return vec![],
+            PatternKind::Variant(variant_pattern) => {
+                covered_variants.push(variant_pattern.variant_name);
+            }
+            // They're reported as mismatched types.
+            PatternKind::Integer(_) | PatternKind::Range { .. } => {}
+        }
+    }
+
+    enum_layout
+        .variants
+        .iter()
+        .filter(|variant_layout| !covered_variants.contains(&variant_layout.name))
+        .map(|variant_layout| match variant_layout.payload {
+            Some(_) => format!("{}::{}(_)", enum_layout.name, variant_layout.name),
+            None => format!("{}::{}", enum_layout.name, variant_layout.name),
+        })
+        .collect()
+}
+
+/// Integers of the width that none of the arms' patterns match, as the
+/// fewest inclusive ranges, in order.
+fn uncovered_ranges(arms: &[MatchArm], int_width: IntWidth) -> Vec<(i128, i128)> {
+    let mut covered_ranges = vec![];
+
+    for arm in arms {
+        let bounds = match arm.pattern.kind {
+            // They're reported as mismatched types.
+            PatternKind::Variant(_) => continue,
+            kind => kind.bounds(),
+        };
+
+        match bounds {
+            Some((start, end)) if start <= end => covered_ranges.push((start, end)),
+            // Empty ranges cover nothing.
+            Some(_) => {}
+            None => return vec![],
+        }
+    }
+
+    covered_ranges.sort_unstable();
+
+    let (min, max) = (i128::from(int_width.min()), i128::from(int_width.max()));
+    let mut uncovered_ranges = vec![];
+    // Least integer that isn't known to be covered yet.
+    let mut next_uncovered = min;
+
+    for (start, end) in covered_ranges {
+        if next_uncovered > max {
+            break;
+        }
+
+        if start > next_uncovered {
+            uncovered_ranges.push((next_uncovered, (start - 1).min(max)));
+        }
+
+        next_uncovered = next_uncovered.max(end.saturating_add(1));
+    }
+
+    if next_uncovered <= max {
+        uncovered_ranges.push((next_uncovered, max));
+    }
+
+    uncovered_ranges
+}
+
+/// Span of the expression that gives `compound_expr` its value, or of the
+/// whole compound expression if there is no such expression.
+fn value_span(compound_expr: CompoundExpr) -> Span {
+    match compound_expr.exprs.last() {
+        Some(last_expr) => last_expr.span(),
+        None => compound_expr.span,
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::HashSet;
+
+use crate::ast::{
+    CompoundExpr, Expr, ForExpr, ForIteration, IfExpr, MatchExpr, ParenExpr, PatternKind, Program,
+    Type, DISCARD_IDENTIFIER,
+};
+use crate::compiler_context::CompilerContext;
+use crate::diagnostics::CompileWarning;
+use crate::host::HashMap;
+use crate::resolve::{Resolution, Resolutions};
+use crate::scanner::Span;
+
+/// Finds bindings that are never read, and top-level declarations that `main`
+/// never refers to, not even through other declarations.
+///
+/// Names starting with `_` are never reported, so that they can be kept around
+/// on purpose.
+pub(crate) struct UnusedChecker<'a> {
+    ctx: &'a CompilerContext,
+    resolutions: &'a Resolutions,
+    /// Definition spans of the local bindings that are read somewhere.
+    read_locals: HashSet<Span>,
+    /// Bindings defined with `x := ...`, along with their names.
+    bind_defs: Vec<(&'static str, Span)>,
+    /// Indices of the declarations each declaration refers to.
+    decl_refs: Vec<Vec<usize>>,
+    /// Indices of the structs' and enums' declarations, as types and patterns
+    /// refer to them by name.
+    type_decl_idx_by_name: HashMap<&'static str, usize>,
+}
+
+impl<'a> UnusedChecker<'a> {
+    pub(crate) fn new(ctx: &'a CompilerContext, resolutions: &'a Resolutions) -> UnusedChecker<'a> {
+        UnusedChecker {
+            ctx,
+            resolutions,
+            read_locals: Default::default(),
+            bind_defs: vec![],
+            decl_refs: vec![],
+            type_decl_idx_by_name: Default::default(),
+        }
+    }
+
+    pub(crate) fn check_program(self, program: Program) -> Vec<CompileWarning> {
+        let ctx = self.ctx;
+        let decl_indices: Vec<_> = (0..program.decls.len()).collect();
+        let (mut warnings, decl_refs) = self.check_decls(program, &decl_indices);
+
+        let decl_names: Vec<_> = program
+            .decls
+            .iter()
+            .map(|decl| (ctx.resolve_symbol(decl.identifier), decl.identifier_span))
+            .collect();
+        warnings.extend(unused_decls(&decl_names, &decl_refs));
+
+        warnings.sort_by_key(|warning| warning.span().start.0);
+
+        warnings
+    }
+
+    /// Finds the bindings that are never read in the declarations at the
+    /// indices, returning them along with the indices of the declarations that
+    /// each of those declarations refers to.
+    pub(crate) fn check_decls(
+        mut self,
+        program: Program,
+        decl_indices: &[usize],
+    ) -> (Vec<CompileWarning>, Vec<Vec<usize>>) {
+        for (decl_idx, decl) in program.decls.iter().enumerate() {
+            if let Expr::Struct(_) | Expr::Enum(_) = decl.value {
+                self.type_decl_idx_by_name
+                    .insert(self.ctx.resolve_symbol(decl.identifier), decl_idx);
+            }
+        }
+
+        for &decl_idx in decl_indices {
+            self.decl_refs.push(vec![]);
+            self.check_expr(program.decls[decl_idx].value);
+        }
+
+        let warnings = self
+            .bind_defs
+            .iter()
+            .filter(|(_, span)| !self.read_locals.contains(span))
+            .map(|&(name, span)| CompileWarning::UnusedBinding {
+                name: name.to_owned(),
+                span,
+            })
+            .collect();
+
+        (warnings, self.decl_refs)
+    }
+
+    fn check_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Const(_) | Expr::Continue(_) => {}
+            Expr::Break(break_expr) => {
+                if let Some(value) = break_expr.value {
+                    self.check_expr(value);
+                }
+            }
+            Expr::BindRef(bind_ref) => self.check_ref(bind_ref.span),
+            Expr::BindDef(bind_def) => {
+                if let Some(ty) = bind_def.ty {
+                    self.check_type(ty);
+                }
+
+                self.check_expr(bind_def.value);
+
+                let name = self.ctx.resolve_symbol(bind_def.identifier);
+
+                if name != DISCARD_IDENTIFIER && !name.starts_with('_') {
+                    self.bind_defs.push((name, bind_def.span));
+                }
+            }
+            Expr::Function(function) => {
+                for param in function.parameters {
+                    self.check_type(param.ty);
+                }
+
+                self.check_type(function.return_type);
+                self.check_compound_expr(function.body);
+            }
+            Expr::Extern(extern_fn) => {
+                for param in extern_fn.parameters {
+                    self.check_type(param.ty);
+                }
+
+                self.check_type(extern_fn.return_type);
+            }
+            Expr::Struct(struct_def) => {
+                for field in struct_def.fields {
+                    self.check_type(field.ty);
+                }
+            }
+            Expr::StructLit(struct_lit_expr) => {
+                for field in struct_lit_expr.fields {
+                    self.check_expr(field.value);
+                }
+
+                self.check_ref(struct_lit_expr.identifier_span);
+            }
+            Expr::Field(field_expr) => self.check_expr(field_expr.base),
+            Expr::Enum(enum_def) => {
+                for variant in enum_def.variants {
+                    if let Some(payload) = variant.payload {
+                        self.check_type(payload);
+                    }
+                }
+            }
+            Expr::Variant(variant_expr) => {
+                if let Some(payload) = variant_expr.payload {
+                    self.check_expr(payload);
+                }
+
+                self.check_ref(variant_expr.enum_identifier_span);
+            }
+            Expr::Tuple(tuple_expr) => {
+                for element in tuple_expr.elements {
+                    self.check_expr(element);
+                }
+            }
+            Expr::TupleField(tuple_field_expr) => self.check_expr(tuple_field_expr.base),
+            Expr::TupleBindDef(tuple_bind_def) => {
+                self.check_expr(tuple_bind_def.value);
+
+                for binding in tuple_bind_def.bindings {
+                    let name = self.ctx.resolve_symbol(binding.identifier);
+
+                    if name != DISCARD_IDENTIFIER && !name.starts_with('_') {
+                        self.bind_defs.push((name, binding.span));
+                    }
+                }
+            }
+            Expr::Array(array_expr) => {
+                for element in array_expr.elements {
+                    self.check_expr(element);
+                }
+            }
+            Expr::Index(index_expr) => {
+                self.check_expr(index_expr.base);
+                self.check_expr(index_expr.index);
+            }
+            Expr::IndexAssign(index_assign_expr) => {
+                self.check_expr(index_assign_expr.target.base);
+                self.check_expr(index_assign_expr.target.index);
+                self.check_expr(index_assign_expr.value);
+            }
+            Expr::If(if_expr) => self.check_if_expr(*if_expr),
+            Expr::Match(match_expr) => self.check_match_expr(*match_expr),
+            Expr::For(for_expr) => self.check_for_expr(*for_expr),
+            Expr::Compound(compound_expr) => self.check_compound_expr(*compound_expr),
+            Expr::Cast(cast_expr) => {
+                self.check_expr(cast_expr.expr);
+                self.check_type(cast_expr.ty);
+            }
+            Expr::Semi(expr) | Expr::Paren(ParenExpr { expr, .. }) => self.check_expr(expr),
+            Expr::FnCall(fn_call_expr) => {
+                for arg in fn_call_expr.args {
+                    self.check_expr(arg);
+                }
+
+                self.check_ref(fn_call_expr.span);
+            }
+            Expr::Binary(binary_expr) => {
+                let (operand, operations) = binary_expr.chain();
+                self.check_expr(operand);
+
+                for binary_expr in operations {
+                    self.check_expr(binary_expr.rhs);
+                }
+            }
+        }
+    }
+
+    fn check_if_expr(&mut self, if_expr: IfExpr) {
+        self.check_expr(if_expr.cond_expr);
+        self.check_compound_expr(if_expr.true_branch);
+
+        for branch in if_expr.else_if_branches {
+            self.check_expr(branch.cond_expr);
+            self.check_compound_expr(branch.true_branch);
+        }
+
+        if let Some(final_branch) = if_expr.final_branch {
+            self.check_compound_expr(final_branch);
+        }
+    }
+
+    fn check_match_expr(&mut self, match_expr: MatchExpr) {
+        self.check_expr(match_expr.scrutinee);
+
+        for arm in match_expr.arms {
+            if let PatternKind::Variant(variant_pattern) = arm.pattern.kind {
+                self.check_ref(variant_pattern.enum_name_span);
+            }
+
+            self.check_expr(arm.expr);
+        }
+    }
+
+    fn check_for_expr(&mut self, for_expr: ForExpr) {
+        match for_expr.iteration {
+            Some(ForIteration::Conditional { cond_expr }) => self.check_expr(cond_expr),
+            Some(ForIteration::Iterative {
+                start_expr,
+                end_expr,
+                step_expr,
+                ..
+            }) => {
+                self.check_expr(start_expr);
+                self.check_expr(end_expr);
+
+                if let Some(step_expr) = step_expr {
+                    self.check_expr(step_expr);
+                }
+            }
+            Some(ForIteration::InCollection {
+                collection_expr, ..
+            }) => self.check_expr(collection_expr),
+            None => {}
+        }
+
+        self.check_compound_expr(for_expr.body);
+
+        if let Some(else_branch) = for_expr.else_branch {
+            self.check_compound_expr(else_branch);
+        }
+    }
+
+    fn check_compound_expr(&mut self, compound_expr: CompoundExpr) {
+        for expr in compound_expr.exprs {
+            self.check_expr(expr);
+        }
+    }
+
+    /// Declarations that types name are used by whatever has the type.
+    fn check_type(&mut self, ty: Type) {
+        match ty {
+            Type::Unit
+            | Type::I8
+            | Type::I16
+            | Type::I32
+            | Type::I64
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::Usize => {}
+            Type::Named(name) => {
+                let decl_idx = self.type_decl_idx_by_name[name];
+                self.decl_refs.last_mut().unwrap().push(decl_idx);
+            }
+            Type::Tuple(element_types) => {
+                for &element_ty in element_types {
+                    self.check_type(element_ty);
+                }
+            }
+            Type::Array { element, .. } => self.check_type(*element),
+            Type::Function {
+                params,
+                return_type,
+            } => {
+                for &param_ty in params {
+                    self.check_type(param_ty);
+                }
+
+                self.check_type(*return_type);
+            }
+        }
+    }
+
+    fn check_ref(&mut self, ref_span: Span) {
+        match self.resolutions.get(ref_span) {
+            Some(Resolution::Local(def_span)) => {
+                self.read_locals.insert(def_span);
+            }
+            Some(Resolution::Decl(decl_idx)) => self.decl_refs.last_mut().unwrap().push(decl_idx),
+            Some(Resolution::Host(_) | Resolution::Builtin(_)) | None => {}
+        }
+    }
+}
+
+/// Declarations, given by their names and spans, that `main` never refers to,
+/// not even through other declarations, given the indices of the
+/// declarations that each one refers to.
+pub(crate) fn unused_decls(
+    decls: &[(&'static str, Span)],
+    decl_refs: &[Vec<usize>],
+) -> Vec<CompileWarning> {
+    // Without `main`, nothing is known to be used, so nothing is reported.
+    let Some(main_idx) = decls.iter().position(|&(name, _)| name == "main") else {
+        return vec![];
+    };
+
+    let mut is_used = vec![false; decls.len()];
+    let mut worklist = vec![main_idx];
+    is_used[main_idx] = true;
+
+    while let Some(decl_idx) = worklist.pop() {
+        for &ref_idx in &decl_refs[decl_idx] {
+            if !is_used[ref_idx] {
+                is_used[ref_idx] = true;
+                worklist.push(ref_idx);
+            }
+        }
+    }
+
+    decls
+        .iter()
+        .zip(is_used)
+        .filter(|&(&(name, _), is_used)| !is_used && !name.starts_with('_'))
+        .map(|(&(name, span), _)| CompileWarning::UnusedDecl {
+            name: name.to_owned(),
+            span,
+        })
+        .collect()
+}
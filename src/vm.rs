@@ -0,0 +1,738 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::ast::{BinaryOp, Type};
+use crate::builtin::Builtin;
+use crate::bytecode::{FunctionCode, Instr, LoadError, Module};
+use crate::host_binding::{BindError, IntoHostFunction};
+use crate::interp::RuntimeError;
+use crate::libc::LibcFn;
+use crate::manifest::ContentHash;
+use crate::snapshot::{Frame, Snapshot};
+
+/// Runs bytecode modules, with the same semantics as the native code.
+///
+/// Applications embed Sophia through it, by way of `api::Vm`: they load a
+/// module compiled with the host functions of `Options::host_functions`,
+/// register a closure for each of them, then call into the module's functions.
+pub(crate) struct Vm {
+    module: Module,
+    /// Hash of the saved module, which snapshots of its executions record.
+    module_hash: ContentHash,
+    /// Host functions registered by the embedder, along with their names.
+    host_functions: Vec<(String, HostFunction)>,
+    limits: ExecutionLimits,
+    /// Instructions executed by every run so far.
+    executed_instructions: u64,
+    deterministic: bool,
+    /// Calls to host functions that runs in deterministic mode made.
+    host_calls: Vec<HostCall>,
+    /// Calls to answer calls to host functions with, in order, instead of
+    /// calling them.
+    replayed_host_calls: Option<VecDeque<HostCall>>,
+}
+
+/// Function of the embedder that programs can call. It takes the arguments of
+/// type `i32`, and its value is ignored if it's declared to return `()`. An
+/// error stops the program.
+pub(crate) type HostFunction = Box<dyn FnMut(&[i32]) -> Result<i32, String>>;
+
+/// Call to a host function that a deterministic VM recorded, along with what
+/// the host function returned.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HostCall {
+    pub name: String,
+    pub args: Vec<i32>,
+    pub result: Result<i32, String>,
+}
+
+/// Bounds on the resources of a single call into the VM, or of a run of the
+/// interpreter, so that embedders can run programs they don't trust. Unless
+/// told otherwise, only calls nest at most 10000 deep, and the VM's stack
+/// holds at most 2^20 values.
+///
+/// ```
+/// let mut session = sophia::Session::new();
+/// session.add_file("main.sph", "spin :: () { loop { } }\nmain :: () {}");
+///
+/// let mut vm = session.load_vm().unwrap();
+/// vm.set_limits(sophia::ExecutionLimits {
+///     max_instructions: 1000,
+///     ..Default::default()
+/// });
+///
+/// assert_eq!(vm.call("spin", &[]), Err(sophia::ExecutionError::OutOfFuel));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ExecutionLimits {
+    /// How many instructions may run, which for the interpreter is how many
+    /// expressions it may evaluate and loop iterations it may run.
+    pub max_instructions: u64,
+    pub max_call_depth: usize,
+    /// How many values the operands and the slots of every frame can take up
+    /// together, which only the VM keeps on a stack of its own.
+    pub max_stack_size: usize,
+    /// How long may pass, if that's limited. Unlike the other limits, it
+    /// depends on the machine, so runs that hit it aren't reproducible.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for ExecutionLimits {
+    fn default() -> ExecutionLimits {
+        ExecutionLimits {
+            max_instructions: u64::MAX,
+            max_call_depth: 10_000,
+            max_stack_size: 1 << 20,
+            timeout: None,
+        }
+    }
+}
+
+impl ExecutionLimits {
+    /// When a run that starts now goes over the timeout, if there's one.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.timeout.map(|timeout| Instant::now() + timeout)
+    }
+}
+
+/// How many instructions run between checks of the clock, which is much
+/// slower to read than running an instruction.
+pub(crate) const INSTRUCTIONS_PER_CLOCK_CHECK: u64 = 1024;
+
+/// How far a resumed execution got.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Progress {
+    /// It ran as many instructions as it was allowed to, and can be resumed.
+    Paused,
+    /// The entry function returned, along with its value, if it returns one.
+    Finished(Option<i32>),
+}
+
+/// Limit of `ExecutionLimits` that a run went over.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum ExecutionLimit {
+    Instructions(u64),
+    CallDepth(usize),
+    StackSize(usize),
+    /// Bytes of the stack of the interpreter's thread, which it recurses on.
+    NativeStack(usize),
+    Timeout(Duration),
+}
+
+impl fmt::Display for ExecutionLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutionLimit::Instructions(limit) => write!(f, "{} instructions", limit),
+            ExecutionLimit::CallDepth(limit) => write!(f, "{} nested calls", limit),
+            ExecutionLimit::StackSize(limit) => write!(f, "{} values on the stack", limit),
+            ExecutionLimit::NativeStack(limit) => write!(f, "{} bytes of stack", limit),
+            ExecutionLimit::Timeout(limit) => write!(f, "{:?} of running time", limit),
+        }
+    }
+}
+
+impl Vm {
+    pub(crate) fn new(module: Module) -> Vm {
+        let mut vm = Vm {
+            module_hash: ContentHash::of(&module.save()),
+            module,
+            host_functions: vec![],
+            limits: ExecutionLimits::default(),
+            executed_instructions: 0,
+            deterministic: false,
+            host_calls: vec![],
+            replayed_host_calls: None,
+        };
+
+        vm.print_to(Rc::new(RefCell::new(io::stdout())));
+
+        vm
+    }
+
+    /// Makes the host functions that print write to the output, which is the
+    /// standard output unless told otherwise.
+    ///
+    /// Modules call builtins as host functions, which the embedder may
+    /// register others in place of. Extern functions of the C library that the
+    /// VM knows of are host functions too, as it can't call native code.
+    pub(crate) fn print_to(&mut self, output: Rc<RefCell<dyn io::Write>>) {
+        for builtin in Builtin::ALL {
+            let output = Rc::clone(&output);
+
+            self.register_host_function(builtin.name(), move |args| {
+                let [value] = args else {
+                    return Err("expected 1 argument".to_owned());
+                };
+
+                output
+                    .borrow_mut()
+                    .write_all(builtin.format((*value).into()).as_bytes())
+                    .map_err(|err| err.to_string())?;
+
+                Ok(0)
+            });
+        }
+
+        for libc_fn in LibcFn::ALL {
+            let output = Rc::clone(&output);
+
+            self.register_host_function(libc_fn.name(), move |args| {
+                if args.len() != libc_fn.arity() {
+                    return Err("wrong number of arguments".to_owned());
+                }
+
+                libc_fn
+                    .call(args, &mut *output.borrow_mut())
+                    .map_err(|err| err.to_string())
+            });
+        }
+    }
+
+    /// Loads a module saved by `Module::save`.
+    pub(crate) fn load(bytes: &[u8]) -> Result<Vm, LoadError> {
+        Ok(Vm::new(Module::load(bytes)?))
+    }
+
+    /// Makes the closure the host function of the name, replacing the one
+    /// registered before, if any.
+    pub(crate) fn register_host_function(
+        &mut self,
+        name: impl Into<String>,
+        function: impl FnMut(&[i32]) -> Result<i32, String> + 'static,
+    ) {
+        let name = name.into();
+        self.host_functions
+            .retain(|(other_name, _)| *other_name != name);
+        self.host_functions.push((name, Box::new(function)));
+    }
+
+    /// Registers the Rust function as the host function of the name,
+    /// converting its arguments and value, after checking that it takes the
+    /// arguments the module calls it with.
+    // Only embedders bind host functions, not the CLI.
+    #[allow(dead_code)]
+    pub(crate) fn bind_host_function<Params>(
+        &mut self,
+        name: &str,
+        function: impl IntoHostFunction<Params>,
+    ) -> Result<(), BindError> {
+        let binding = function.bind(name);
+        let decl = &binding.decl;
+
+        if let Some(import) = self
+            .module
+            .host_functions
+            .iter()
+            .find(|import| import.name == name)
+        {
+            let arity = decl
+                .param_types
+                .iter()
+                .filter(|&&ty| ty == Type::I32)
+                .count() as u32;
+
+            if arity != import.arity {
+                return Err(BindError::MismatchedArity {
+                    name: name.to_owned(),
+                    expected: import.arity,
+                    found: arity,
+                });
+            }
+
+            let return_type = if import.returns_value {
+                Type::I32
+            } else {
+                Type::Unit
+            };
+
+            if decl.return_type != return_type {
+                return Err(BindError::MismatchedReturnType {
+                    name: name.to_owned(),
+                    expected: return_type,
+                    found: decl.return_type,
+                });
+            }
+        }
+
+        self.register_host_function(name, binding.function);
+
+        Ok(())
+    }
+
+    pub(crate) fn set_limits(&mut self, limits: ExecutionLimits) {
+        self.limits = limits;
+    }
+
+    /// Makes runs deterministic, or not.
+    ///
+    /// Programs only ever observe the world through host functions, so in
+    /// deterministic mode, the VM records every call to them, along with what
+    /// they returned. Replaying those calls with `replay_host_calls` runs the
+    /// program exactly the same way again, down to the number of instructions
+    /// it executes, which is how long it took regardless of the machine.
+    pub(crate) fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    /// Instructions executed by every run so far, in any mode.
+    pub(crate) fn executed_instructions(&self) -> u64 {
+        self.executed_instructions
+    }
+
+    /// Calls to host functions recorded in deterministic mode, in the order
+    /// they were made.
+    pub(crate) fn host_calls(&self) -> &[HostCall] {
+        &self.host_calls
+    }
+
+    /// Answers calls to host functions with the recorded ones instead of
+    /// calling them, which then don't have to be registered. A call that
+    /// doesn't match the next recorded one stops the program.
+    pub(crate) fn replay_host_calls(&mut self, host_calls: Vec<HostCall>) {
+        self.replayed_host_calls = Some(host_calls.into());
+    }
+
+    /// Runs the module's `main` function, returning its value as an exit code,
+    /// or zero if it returns `()`.
+    pub(crate) fn run_main(&mut self) -> Result<i32, RuntimeError> {
+        let Some(main_idx) = self.function_idx("main") else {
+            return Err(RuntimeError::MissingMain);
+        };
+
+        if let Some(&span) = self.module.functions[main_idx].debug.param_spans.first() {
+            return Err(RuntimeError::MainTakesParameters { span });
+        }
+
+        let exit_code = self.call("main", &[])?;
+
+        Ok(exit_code.unwrap_or(0))
+    }
+
+    /// Runs the function of the name, passing it the arguments for its
+    /// parameters of type `i32`. Returns the function's value, or `None` if it
+    /// returns `()`.
+    pub(crate) fn call(&mut self, name: &str, args: &[i32]) -> Result<Option<i32>, RuntimeError> {
+        let mut snapshot = self.start(name, args)?;
+
+        match self.resume(&mut snapshot, u64::MAX)? {
+            Progress::Finished(value) => Ok(value),
+            Progress::Paused => unreachable!("the execution runs until it finishes"),
+        }
+    }
+
+    /// Prepares to run the function of the name, just like `call` does, but
+    /// without running any of it until the execution is resumed.
+    pub(crate) fn start(&mut self, name: &str, args: &[i32]) -> Result<Snapshot, RuntimeError> {
+        let Some(function_idx) = self.function_idx(name) else {
+            return Err(RuntimeError::UndefinedFunction {
+                name: name.to_owned(),
+            });
+        };
+
+        let function = &self.module.functions[function_idx];
+
+        if args.len() != function.arity as usize {
+            return Err(RuntimeError::MismatchedArgumentCount {
+                name: name.to_owned(),
+                expected: function.arity as usize,
+                found: args.len(),
+            });
+        }
+
+        let mut snapshot = Snapshot {
+            module_hash: self.module_hash,
+            entry_function_idx: function_idx as u32,
+            executed_instructions: 0,
+            stack: args.to_vec(),
+            slots: vec![],
+            frames: vec![],
+        };
+
+        let mut machine = self.machine(&mut snapshot, 0)?;
+        machine.call(function_idx)?;
+        machine.save_into(&mut snapshot);
+
+        Ok(snapshot)
+    }
+
+    /// Runs the paused execution for at most the given number of instructions,
+    /// updating the snapshot to where it stopped, even if it stopped on an
+    /// error.
+    pub(crate) fn resume(
+        &mut self,
+        snapshot: &mut Snapshot,
+        max_instructions: u64,
+    ) -> Result<Progress, RuntimeError> {
+        self.validate_snapshot(snapshot)?;
+
+        let executed_before = snapshot.executed_instructions;
+
+        let mut machine = self.machine(snapshot, max_instructions)?;
+        let result = machine.run();
+        machine.save_into(snapshot);
+
+        self.executed_instructions += snapshot.executed_instructions - executed_before;
+        result?;
+
+        if !snapshot.frames.is_empty() {
+            return Ok(Progress::Paused);
+        }
+
+        let entry_function = &self.module.functions[snapshot.entry_function_idx as usize];
+
+        // The value stays on the stack, so that resuming a finished execution
+        // returns it again.
+        if entry_function.returns_value {
+            let value = snapshot
+                .stack
+                .last()
+                .ok_or(invalid_bytecode("pop from an empty stack"))?;
+
+            Ok(Progress::Finished(Some(*value)))
+        } else {
+            Ok(Progress::Finished(None))
+        }
+    }
+
+    /// Takes the state out of the snapshot to run it, until `save_into` puts
+    /// it back.
+    fn machine(
+        &mut self,
+        snapshot: &mut Snapshot,
+        max_instructions: u64,
+    ) -> Result<Machine<'_>, RuntimeError> {
+        let host_function_indices = match self.replayed_host_calls {
+            Some(_) => vec![],
+            None => self.link_host_functions()?,
+        };
+
+        Ok(Machine {
+            module: &self.module,
+            host_functions: &mut self.host_functions,
+            host_function_indices,
+            recorded_host_calls: self.deterministic.then_some(&mut self.host_calls),
+            replayed_host_calls: self.replayed_host_calls.as_mut(),
+            limits: self.limits,
+            deadline: self.limits.deadline(),
+            pause_at: snapshot
+                .executed_instructions
+                .saturating_add(max_instructions),
+            stack: mem::take(&mut snapshot.stack),
+            slots: mem::take(&mut snapshot.slots),
+            frames: mem::take(&mut snapshot.frames),
+            executed_instructions: snapshot.executed_instructions,
+        })
+    }
+
+    /// Checks that the snapshot was taken of this module, and that its frames
+    /// fit the module's functions, so that resuming it can't go out of bounds.
+    fn validate_snapshot(&self, snapshot: &Snapshot) -> Result<(), RuntimeError> {
+        let invalid = |reason| Err(RuntimeError::InvalidSnapshot { reason });
+
+        if snapshot.module_hash != self.module_hash {
+            return invalid("it was taken of another module");
+        }
+
+        if snapshot.entry_function_idx as usize >= self.module.functions.len() {
+            return invalid("its entry function is out of bounds");
+        }
+
+        let mut slots_end = 0;
+
+        for frame in &snapshot.frames {
+            let Some(function) = self.module.functions.get(frame.function_idx as usize) else {
+                return invalid("a frame's function is out of bounds");
+            };
+
+            if frame.pc as usize >= function.code.len() {
+                return invalid("a frame's instruction is out of bounds");
+            }
+
+            if frame.slots_base != slots_end {
+                return invalid("a frame's slots don't follow its caller's");
+            }
+
+            slots_end += function.slot_count;
+        }
+
+        if slots_end as usize != snapshot.slots.len() {
+            return invalid("its slots don't match its frames");
+        }
+
+        Ok(())
+    }
+
+    fn function_idx(&self, name: &str) -> Option<usize> {
+        self.module
+            .functions
+            .iter()
+            .position(|function| function.name == name)
+    }
+
+    /// Finds the registered host function for each one the module imports,
+    /// before running anything, returning their indices in `host_functions`.
+    fn link_host_functions(&self) -> Result<Vec<usize>, RuntimeError> {
+        self.module
+            .host_functions
+            .iter()
+            .map(|import| {
+                self.host_functions
+                    .iter()
+                    .position(|(name, _)| *name == import.name)
+                    .ok_or_else(|| RuntimeError::UndefinedHostFunction {
+                        name: import.name.clone(),
+                    })
+            })
+            .collect()
+    }
+}
+
+/// State of a single call into the VM.
+struct Machine<'vm> {
+    module: &'vm Module,
+    host_functions: &'vm mut [(String, HostFunction)],
+    /// Index in `host_functions` of each host function the module imports.
+    host_function_indices: Vec<usize>,
+    recorded_host_calls: Option<&'vm mut Vec<HostCall>>,
+    replayed_host_calls: Option<&'vm mut VecDeque<HostCall>>,
+    limits: ExecutionLimits,
+    /// When the execution goes over the timeout, which is counted from when
+    /// it started or was resumed.
+    deadline: Option<Instant>,
+    /// How many instructions the execution runs before it's paused.
+    pause_at: u64,
+    stack: Vec<i32>,
+    slots: Vec<i32>,
+    frames: Vec<Frame>,
+    executed_instructions: u64,
+}
+
+impl Machine<'_> {
+    fn save_into(self, snapshot: &mut Snapshot) {
+        snapshot.stack = self.stack;
+        snapshot.slots = self.slots;
+        snapshot.frames = self.frames;
+        snapshot.executed_instructions = self.executed_instructions;
+    }
+
+    fn run(&mut self) -> Result<(), RuntimeError> {
+        let module = self.module;
+
+        while let Some(frame) = self.frames.last() {
+            if self.executed_instructions == self.limits.max_instructions {
+                return Err(RuntimeError::ExecutionLimitExceeded {
+                    limit: ExecutionLimit::Instructions(self.limits.max_instructions),
+                });
+            }
+
+            if self.executed_instructions == self.pause_at {
+                return Ok(());
+            }
+
+            if self
+                .executed_instructions
+                .is_multiple_of(INSTRUCTIONS_PER_CLOCK_CHECK)
+                && self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return Err(RuntimeError::ExecutionLimitExceeded {
+                    limit: ExecutionLimit::Timeout(self.limits.timeout.unwrap()),
+                });
+            }
+
+            let function = &module.functions[frame.function_idx as usize];
+            let pc = frame.pc as usize;
+            let slots_base = frame.slots_base as usize;
+            self.frames.last_mut().unwrap().pc += 1;
+            self.executed_instructions += 1;
+
+            match function.code[pc] {
+                Instr::Const(idx) => self.stack.push(module.constants[idx as usize]),
+                Instr::Load(slot) => self.stack.push(self.slots[slots_base + slot as usize]),
+                Instr::Store(slot) => self.slots[slots_base + slot as usize] = self.pop()?,
+                Instr::Pop => {
+                    self.pop()?;
+                }
+                Instr::Add => self.binary(i32::wrapping_add)?,
+                Instr::Sub => self.binary(i32::wrapping_sub)?,
+                Instr::Mul => self.binary(i32::wrapping_mul)?,
+                Instr::Div => self.division(function, pc, BinaryOp::Div)?,
+                Instr::Rem => self.division(function, pc, BinaryOp::Rem)?,
+                Instr::Less => self.binary(|lhs, rhs| (lhs < rhs).into())?,
+                Instr::LessEqual => self.binary(|lhs, rhs| (lhs <= rhs).into())?,
+                Instr::AddOverflows => {
+                    self.binary(|lhs, rhs| lhs.checked_add(rhs).is_none().into())?
+                }
+                Instr::SubOverflows => {
+                    self.binary(|lhs, rhs| lhs.checked_sub(rhs).is_none().into())?
+                }
+                Instr::CheckStep => {
+                    if self.pop()? <= 0 {
+                        let span = function
+                            .debug
+                            .span_of(pc)
+                            .ok_or(invalid_bytecode("step check without a span"))?;
+
+                        return Err(RuntimeError::NonPositiveStep { span });
+                    }
+                }
+                Instr::Jump(target) => self.frames.last_mut().unwrap().pc = target,
+                Instr::JumpIfZero(target) => {
+                    if self.pop()? == 0 {
+                        self.frames.last_mut().unwrap().pc = target;
+                    }
+                }
+                Instr::Call(idx) => self.call(idx as usize)?,
+                Instr::CallHost(idx) => self.call_host(idx as usize)?,
+                Instr::Return => {
+                    let frame = self.frames.pop().unwrap();
+                    self.slots.truncate(frame.slots_base as usize);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pushes a frame for the function, whose prologue pops the arguments off
+    /// the stack into its slots.
+    fn call(&mut self, function_idx: usize) -> Result<(), RuntimeError> {
+        let function = &self.module.functions[function_idx];
+
+        if function.arity as usize > self.stack.len() {
+            return Err(invalid_bytecode("not enough arguments on the stack"));
+        }
+
+        if self.frames.len() == self.limits.max_call_depth {
+            return Err(RuntimeError::ExecutionLimitExceeded {
+                limit: ExecutionLimit::CallDepth(self.limits.max_call_depth),
+            });
+        }
+
+        let slots_base = self.slots.len();
+        let slots_end = slots_base + function.slot_count as usize;
+
+        if self.stack.len() + slots_end > self.limits.max_stack_size {
+            return Err(RuntimeError::ExecutionLimitExceeded {
+                limit: ExecutionLimit::StackSize(self.limits.max_stack_size),
+            });
+        }
+
+        self.slots.resize(slots_end, 0);
+
+        self.frames.push(Frame {
+            function_idx: function_idx as u32,
+            pc: 0,
+            slots_base: slots_base as u32,
+        });
+
+        Ok(())
+    }
+
+    fn call_host(&mut self, import_idx: usize) -> Result<(), RuntimeError> {
+        let import = &self.module.host_functions[import_idx];
+        let arity = import.arity as usize;
+
+        if arity > self.stack.len() {
+            return Err(invalid_bytecode("not enough arguments on the stack"));
+        }
+
+        let args = self.stack.split_off(self.stack.len() - arity);
+
+        let result = match &mut self.replayed_host_calls {
+            Some(replayed_host_calls) => match replayed_host_calls.pop_front() {
+                Some(call) if call.name == import.name && call.args == args => call.result,
+                _ => {
+                    return Err(RuntimeError::ReplayDiverged {
+                        name: import.name.clone(),
+                    })
+                }
+            },
+            None => {
+                let (_, host_function) =
+                    &mut self.host_functions[self.host_function_indices[import_idx]];
+
+                host_function(&args)
+            }
+        };
+
+        if let Some(recorded_host_calls) = &mut self.recorded_host_calls {
+            recorded_host_calls.push(HostCall {
+                name: import.name.clone(),
+                args,
+                result: result.clone(),
+            });
+        }
+
+        let value = result.map_err(|message| RuntimeError::HostFunctionFailed {
+            name: import.name.clone(),
+            message,
+        })?;
+
+        if import.returns_value {
+            self.stack.push(value);
+        }
+
+        Ok(())
+    }
+
+    fn binary(&mut self, op: impl Fn(i32, i32) -> i32) -> Result<(), RuntimeError> {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
+        self.stack.push(op(lhs, rhs));
+
+        Ok(())
+    }
+
+    fn division(
+        &mut self,
+        function: &FunctionCode,
+        pc: usize,
+        op: BinaryOp,
+    ) -> Result<(), RuntimeError> {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
+
+        let value = match op {
+            BinaryOp::Div => lhs.checked_div(rhs),
+            _ => lhs.checked_rem(rhs),
+        };
+
+        match value {
+            Some(value) => {
+                self.stack.push(value);
+
+                Ok(())
+            }
+            None => {
+                let span = function
+                    .debug
+                    .span_of(pc)
+                    .ok_or(invalid_bytecode("division without a span"))?;
+
+                // The hardware traps on overflow too, e.g., on `MIN / -1`.
+                if rhs == 0 {
+                    Err(RuntimeError::DivisionByZero { span })
+                } else {
+                    Err(RuntimeError::Overflow { op, span })
+                }
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Result<i32, RuntimeError> {
+        self.stack
+            .pop()
+            .ok_or(invalid_bytecode("pop from an empty stack"))
+    }
+}
+
+fn invalid_bytecode(reason: &'static str) -> RuntimeError {
+    RuntimeError::InvalidBytecode { reason }
+}
@@ -0,0 +1,572 @@
+use std::fmt::Write;
+
+use crate::ast::{BinaryOp, Expr, Program, Type};
+use crate::cfg::Cfg;
+use crate::compiler_context::CompilerContext;
+use crate::ir::{self, BlockCall, BlockId, Callee, Inst, InstKind, Terminator, Value};
+use crate::ir_lower::IrLowering;
+use crate::resolve::Resolutions;
+
+/// Lowers a program to a WebAssembly module in the text format, which
+/// `wat2wasm` assembles into a `.wasm` file for browsers and wasm runtimes.
+///
+/// Every top-level function is exported under its own name, and `i32` maps to
+/// wasm's `i32`, which wraps around just like the native code does. Values of
+/// the IR that aren't used right away become locals.
+pub(crate) struct WasmCodeGen<'ctx> {
+    ctx: &'ctx CompilerContext,
+    resolutions: &'ctx Resolutions,
+}
+
+impl<'ctx> WasmCodeGen<'ctx> {
+    pub(crate) fn new(
+        ctx: &'ctx CompilerContext,
+        resolutions: &'ctx Resolutions,
+    ) -> WasmCodeGen<'ctx> {
+        WasmCodeGen { ctx, resolutions }
+    }
+
+    pub(crate) fn gen_module(self, program: Program<'ctx>) -> String {
+        let mut module = String::new();
+        writeln!(module, "(module").unwrap();
+
+        // Builtins are functions of the runtime library, which the embedder
+        // provides like C toolchains do, and imports come before functions.
+        for builtin in self.resolutions.builtins() {
+            let symbol = builtin.runtime_symbol();
+            writeln!(
+                module,
+                "  (import \"env\" \"{}\" (func ${} (param i32)))",
+                symbol, symbol
+            )
+            .unwrap();
+        }
+
+        // Extern functions are imported from the embedder too, under their own
+        // names.
+        for decl in program.decls {
+            if let Expr::Extern(extern_fn) = decl.value {
+                let name = self.ctx.resolve_symbol(decl.identifier);
+                let mut signature = format!("func ${}", name);
+
+                for _ in extern_fn.parameters {
+                    signature.push_str(" (param i32)");
+                }
+
+                if extern_fn.return_type != Type::Unit {
+                    signature.push_str(" (result i32)");
+                }
+
+                writeln!(module, "  (import \"env\" \"{}\" ({}))", name, signature).unwrap();
+            }
+        }
+
+        let ir_module = IrLowering::new(self.ctx, self.resolutions, &[]).lower_program(program);
+
+        for function in &ir_module.functions {
+            module.push_str(&FunctionGen::new(&ir_module, function).gen_function());
+        }
+
+        writeln!(module, ")").unwrap();
+
+        module
+    }
+}
+
+/// Generates a function's blocks as wasm's structured control flow, following
+/// "Beyond Relooper" by Norman Ramsey.
+///
+/// Every block is generated within the one that immediately dominates it. A
+/// block that's jumped to from more than one place is a merge block, which
+/// follows a wasm `block` that the jumps branch out of. A block that's jumped
+/// back to is a loop header, which is wrapped in a wasm `loop` that the jumps
+/// branch to the start of. Any other block is generated right where it's
+/// jumped to.
+///
+/// Values that are used right away, by the next instruction that pops them,
+/// are left on the stack. Constants are pushed wherever they're used, and the
+/// other values are kept in locals.
+struct FunctionGen<'gen> {
+    module: &'gen ir::Module,
+    function: &'gen ir::Function,
+    use_counts: Vec<usize>,
+    is_stacked: Vec<bool>,
+    /// Value of every constant, by its number.
+    constants: Vec<Option<i32>>,
+    /// Blocks that each block immediately dominates and that are merge
+    /// blocks, in increasing order.
+    merge_children: Vec<Vec<BlockId>>,
+    is_merge_block: Vec<bool>,
+    is_loop_header: Vec<bool>,
+    /// Declarations of the function's locals, which are kept apart from the
+    /// rest of the code, as all of them have to come first.
+    locals: String,
+    body: String,
+    /// How many blocks the next instruction is nested in.
+    depth: usize,
+    /// Whether the locals that hold the operands of a remainder have been
+    /// declared yet.
+    has_rem_operands: bool,
+    /// Whether the local that holds the step of a range being checked for
+    /// overflows has been declared yet.
+    has_step_operand: bool,
+}
+
+impl<'gen> FunctionGen<'gen> {
+    fn new(module: &'gen ir::Module, function: &'gen ir::Function) -> FunctionGen<'gen> {
+        let block_count = function.blocks.len();
+        let cfg = Cfg::of_function(function);
+        let idoms = cfg.immediate_dominators();
+
+        // Blocks are numbered in reverse postorder, so jumps go forward unless
+        // they go back to a loop header.
+        let mut forward_jump_counts = vec![0; block_count];
+        let mut is_loop_header = vec![false; block_count];
+
+        for (idx, block) in function.blocks.iter().enumerate() {
+            let mut calls = block.terminator.calls();
+
+            // A branch that goes the same way either way is a single jump.
+            calls.dedup();
+
+            for call in calls {
+                if call.block.0 > idx {
+                    forward_jump_counts[call.block.0] += 1;
+                } else {
+                    is_loop_header[call.block.0] = true;
+                }
+            }
+        }
+
+        let is_merge_block: Vec<_> = forward_jump_counts.iter().map(|&count| count > 1).collect();
+        let mut merge_children = vec![vec![]; block_count];
+
+        for idx in 1..block_count {
+            if is_merge_block[idx] {
+                merge_children[idoms[idx].0].push(BlockId(idx));
+            }
+        }
+
+        let use_counts = function.use_counts();
+        let mut constants = vec![None; use_counts.len()];
+
+        for inst in function.blocks.iter().flat_map(|block| &block.insts) {
+            if let (Some(result), InstKind::Const(value)) = (inst.result, &inst.kind) {
+                constants[result.0] = Some(*value);
+            }
+        }
+
+        FunctionGen {
+            module,
+            function,
+            use_counts,
+            is_stacked: function.stacked_values(),
+            constants,
+            merge_children,
+            is_merge_block,
+            is_loop_header,
+            locals: String::new(),
+            body: String::new(),
+            depth: 0,
+            has_rem_operands: false,
+            has_step_operand: false,
+        }
+    }
+
+    fn gen_function(mut self) -> String {
+        let name = self.function.name;
+        let mut signature = format!("(func ${} (export \"{}\")", name, name);
+
+        for param in self.function.params() {
+            write!(signature, " (param ${} i32)", param).unwrap();
+        }
+
+        if returns_value(self.function) {
+            signature.push_str(" (result i32)");
+        }
+
+        for value in 0..self.use_counts.len() {
+            if self.is_local(Value(value)) && !self.function.params().contains(&Value(value)) {
+                writeln!(self.locals, "    (local ${} i32)", Value(value)).unwrap();
+            }
+        }
+
+        self.gen_tree(BlockId(0), None);
+
+        // The code after the last block's end is never reached, e.g., as every
+        // branch of an if returns, but the function still has to return a
+        // value as far as wasm can tell.
+        if returns_value(self.function) && self.body.ends_with("    end\n") {
+            self.emit("unreachable");
+        }
+
+        format!("  {}\n{}{}  )\n", signature, self.locals, self.body)
+    }
+
+    /// Generates the block and every block it immediately dominates, within a
+    /// loop if it's a loop header. Falling off the end of the code goes on to
+    /// the block that follows, if any.
+    fn gen_tree(&mut self, block: BlockId, follower: Option<BlockId>) {
+        let merge_children = self.merge_children[block.0].clone();
+
+        if self.is_loop_header[block.0] {
+            self.open_block(format!("loop $loop{}", block.0));
+            self.gen_within(block, &merge_children, follower);
+            self.close_block();
+        } else {
+            self.gen_within(block, &merge_children, follower);
+        }
+    }
+
+    /// Generates the block within wasm blocks for its merge children, each of
+    /// which follows its wasm block, the last one outermost.
+    fn gen_within(
+        &mut self,
+        block: BlockId,
+        merge_children: &[BlockId],
+        follower: Option<BlockId>,
+    ) {
+        match merge_children.split_last() {
+            Some((&merge_child, merge_children)) => {
+                let start = self.body.len();
+                let label = format!("$block{}", merge_child.0);
+
+                self.open_block(format!("block {}", label));
+                self.gen_within(block, merge_children, Some(merge_child));
+                self.close_block();
+
+                self.unwrap_unused_block(start, &label);
+
+                self.gen_tree(merge_child, follower);
+            }
+            None => {
+                let function = self.function;
+
+                for inst in &function.blocks[block.0].insts {
+                    self.gen_inst(inst);
+                }
+
+                self.gen_terminator(block, &function.blocks[block.0].terminator, follower);
+            }
+        }
+    }
+
+    fn gen_inst(&mut self, inst: &Inst) {
+        if let InstKind::Const(value) = inst.kind {
+            // Constants that aren't left on the stack are pushed by their uses.
+            if inst.result.is_some_and(|result| self.is_stacked[result.0]) {
+                self.emit(format!("i32.const {}", value));
+            }
+
+            return;
+        }
+
+        self.gen_operands(&inst.kind.operands());
+
+        match &inst.kind {
+            InstKind::Const(_) => unreachable!("constants are pushed by their uses"),
+            InstKind::Binary { op, .. } => self.gen_binary_op(*op),
+            InstKind::Less { .. } => self.emit("i32.lt_s"),
+            InstKind::LessEqual { .. } => self.emit("i32.le_s"),
+            InstKind::Overflows { op, .. } => self.gen_overflow_check(*op),
+            InstKind::CheckStep { .. } => {
+                self.emit("i32.const 1");
+                self.emit("i32.lt_s");
+                self.open_block("if");
+                self.emit("unreachable");
+                self.close_block();
+            }
+            InstKind::Call { callee, .. } => match *callee {
+                Callee::Function(name) => {
+                    self.emit(format!("call ${}", name));
+
+                    // `main` returns an exit code even if it's of type `()`.
+                    let callee_function = self.module.functions.iter().find(|f| f.name == name);
+
+                    if callee_function.is_some_and(|f| returns_value(f) && !f.returns_value) {
+                        self.emit("drop");
+                    }
+                }
+                Callee::Builtin(builtin) => {
+                    self.emit(format!("call ${}", builtin.runtime_symbol()));
+                }
+                Callee::Extern(name) => self.emit(format!("call ${}", name)),
+                Callee::Host(_) => {
+                    unreachable!(
+                        "programs that call host functions are rejected before code generation"
+                    )
+                }
+            },
+        }
+
+        if let Some(result) = inst.result {
+            self.gen_result(result);
+        }
+    }
+
+    fn gen_binary_op(&mut self, op: BinaryOp) {
+        match op {
+            BinaryOp::Add => self.emit("i32.add"),
+            BinaryOp::Sub => self.emit("i32.sub"),
+            BinaryOp::Mul => self.emit("i32.mul"),
+            BinaryOp::Div => self.emit("i32.div_s"),
+            // The remainder of the minimum integer by minus one is zero in wasm,
+            // whereas it traps in the native code, so dividing them first makes
+            // it trap here too.
+            BinaryOp::Rem => {
+                if !self.has_rem_operands {
+                    writeln!(self.locals, "    (local $rem.lhs i32)").unwrap();
+                    writeln!(self.locals, "    (local $rem.rhs i32)").unwrap();
+                    self.has_rem_operands = true;
+                }
+
+                self.emit("local.set $rem.rhs");
+                self.emit("local.set $rem.lhs");
+                self.emit("local.get $rem.lhs");
+                self.emit("local.get $rem.rhs");
+                self.emit("i32.div_s");
+                self.emit("drop");
+                self.emit("local.get $rem.lhs");
+                self.emit("local.get $rem.rhs");
+                self.emit("i32.rem_s");
+            }
+        }
+    }
+
+    /// Steps the range on 64-bit integers, which hold any result, and checks
+    /// whether the result is within the range of `i32` by offsetting it to
+    /// start at zero.
+    fn gen_overflow_check(&mut self, op: BinaryOp) {
+        if !self.has_step_operand {
+            writeln!(self.locals, "    (local $step i32)").unwrap();
+            self.has_step_operand = true;
+        }
+
+        self.emit("local.set $step");
+        self.emit("i64.extend_i32_s");
+        self.emit("local.get $step");
+        self.emit("i64.extend_i32_s");
+        match op {
+            BinaryOp::Add => self.emit("i64.add"),
+            BinaryOp::Sub => self.emit("i64.sub"),
+            _ => unreachable!("only steps of ranges are checked for overflows"),
+        }
+        self.emit("i64.const 2147483648");
+        self.emit("i64.add");
+        self.emit("i64.const 4294967296");
+        self.emit("i64.ge_u");
+    }
+
+    fn gen_terminator(
+        &mut self,
+        block: BlockId,
+        terminator: &Terminator,
+        follower: Option<BlockId>,
+    ) {
+        match terminator {
+            Terminator::Jump(call) => self.gen_block_call(block, call, follower),
+            Terminator::Branch {
+                cond,
+                then_call,
+                else_call,
+            } => {
+                if then_call == else_call {
+                    if self.is_stacked[cond.0] {
+                        self.emit("drop");
+                    }
+
+                    self.gen_block_call(block, then_call, follower);
+
+                    return;
+                }
+
+                let start = self.body.len();
+                self.gen_operands(&[*cond]);
+
+                // Jumps without arguments branch right away. Wasm's `br_if` takes
+                // any non-zero integer as true, just like sophia.
+                let then_label = self
+                    .label(block, then_call)
+                    .filter(|_| then_call.args.is_empty());
+                let else_label = self
+                    .label(block, else_call)
+                    .filter(|_| else_call.args.is_empty());
+
+                if let Some(label) = then_label {
+                    self.emit(format!("br_if {}", label));
+                    self.gen_block_call(block, else_call, follower);
+                } else if let Some(label) = else_label {
+                    self.emit("i32.eqz");
+                    self.emit(format!("br_if {}", label));
+                    self.gen_block_call(block, then_call, follower);
+                } else {
+                    let if_start = self.body.len();
+
+                    self.open_block("if");
+                    self.gen_block_call(block, then_call, follower);
+                    self.emit_else();
+                    self.gen_block_call(block, else_call, follower);
+                    self.close_block();
+
+                    // Both ways fall through to the block that follows, e.g., as
+                    // neither passes arguments that are used.
+                    if self.body[if_start..].lines().count() == 3 {
+                        self.body.truncate(start);
+
+                        if self.is_stacked[cond.0] {
+                            self.emit("drop");
+                        }
+                    }
+                }
+            }
+            Terminator::Return(value) => {
+                match value {
+                    Some(value) => self.gen_operands(&[*value]),
+                    // `main` returns an exit code even if it's of type `()`.
+                    None if !self.function.returns_value && returns_value(self.function) => {
+                        self.emit("i32.const 0");
+                    }
+                    None if self.function.returns_value => {
+                        // The function never returns, e.g., it ends in an
+                        // infinite loop.
+                        self.emit("unreachable");
+
+                        return;
+                    }
+                    None => {}
+                }
+
+                // The function's value is whatever is left on the stack at its
+                // end.
+                if follower.is_some() || self.depth > 0 {
+                    self.emit("return");
+                }
+            }
+        }
+    }
+
+    /// Passes the arguments to the block's parameters that are used, then goes
+    /// to it, by branching to it unless it's the block that follows, or by
+    /// generating it right here if it's only jumped to from here.
+    fn gen_block_call(&mut self, from: BlockId, call: &BlockCall, follower: Option<BlockId>) {
+        let function = self.function;
+        let (params, args): (Vec<_>, Vec<_>) = function.used_args(call, &self.use_counts).unzip();
+
+        self.gen_operands(&args);
+
+        for param in params.iter().rev() {
+            self.emit(format!("local.set ${}", param));
+        }
+
+        match self.label(from, call) {
+            Some(_) if !self.is_back_jump(from, call) && Some(call.block) == follower => {}
+            Some(label) => self.emit(format!("br {}", label)),
+            None => self.gen_tree(call.block, follower),
+        }
+    }
+
+    /// Label to branch to for the jump, unless the block is generated right
+    /// where it's jumped to.
+    fn label(&self, from: BlockId, call: &BlockCall) -> Option<String> {
+        if self.is_back_jump(from, call) {
+            Some(format!("$loop{}", call.block.0))
+        } else if self.is_merge_block[call.block.0] {
+            Some(format!("$block{}", call.block.0))
+        } else {
+            None
+        }
+    }
+
+    fn is_back_jump(&self, from: BlockId, call: &BlockCall) -> bool {
+        call.block.0 <= from.0
+    }
+
+    /// Pushes the operands that aren't on the stack already, which come after
+    /// the ones that are.
+    fn gen_operands(&mut self, operands: &[Value]) {
+        for &operand in operands {
+            if self.is_stacked[operand.0] {
+                continue;
+            }
+
+            match self.constants[operand.0] {
+                Some(value) => self.emit(format!("i32.const {}", value)),
+                None => self.emit(format!("local.get ${}", operand)),
+            }
+        }
+    }
+
+    fn gen_result(&mut self, result: Value) {
+        if self.is_local(result) {
+            self.emit(format!("local.set ${}", result));
+        } else if !self.is_stacked[result.0] {
+            self.emit("drop");
+        }
+    }
+
+    /// Whether the value is kept in a local, which is a parameter for the
+    /// function's parameters.
+    fn is_local(&self, value: Value) -> bool {
+        self.function.params().contains(&value)
+            || (self.use_counts[value.0] > 0
+                && !self.is_stacked[value.0]
+                && self.constants[value.0].is_none())
+    }
+
+    /// Removes the wasm block that starts at the offset of the body if nothing
+    /// branches out of it, e.g., as every jump to the merge block falls through
+    /// to it.
+    fn unwrap_unused_block(&mut self, start: usize, label: &str) {
+        let block = self.body.split_off(start);
+        let lines: Vec<_> = block.lines().collect();
+        let inner_lines = &lines[1..lines.len() - 1];
+
+        if inner_lines
+            .iter()
+            .any(|line| line.ends_with(&format!(" {}", label)))
+        {
+            self.body.push_str(&block);
+
+            return;
+        }
+
+        for line in inner_lines {
+            writeln!(self.body, "{}", &line[2..]).unwrap();
+        }
+    }
+
+    fn open_block(&mut self, instruction: impl AsRef<str>) {
+        self.emit(instruction);
+        self.depth += 1;
+    }
+
+    fn emit_else(&mut self) {
+        self.depth -= 1;
+        self.emit("else");
+        self.depth += 1;
+    }
+
+    fn close_block(&mut self) {
+        self.depth -= 1;
+        self.emit("end");
+    }
+
+    fn emit(&mut self, instruction: impl AsRef<str>) {
+        let indentation = 4 + 2 * self.depth;
+
+        writeln!(
+            self.body,
+            "{:indentation$}{}",
+            "",
+            instruction.as_ref(),
+            indentation = indentation
+        )
+        .unwrap();
+    }
+}
+
+/// Whether the function returns an `i32`. `main` returns an exit code to its
+/// host, which is zero if it returns `()`.
+fn returns_value(function: &ir::Function) -> bool {
+    function.returns_value || function.name == "main"
+}
@@ -0,0 +1,63 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::manifest::ContentHash;
+
+/// Watches files for changes, for `sophia --check --watch`, by reading them
+/// whenever it's polled and comparing the hashes of their contents with the
+/// ones it read last time. Polling works the same on every platform, and
+/// source files are small enough to read several times a second.
+pub(crate) struct FileWatcher {
+    files: Vec<WatchedFile>,
+    has_polled: bool,
+}
+
+struct WatchedFile {
+    path: PathBuf,
+    /// Hash of the contents read last time, or `None` if they couldn't be
+    /// read.
+    hash: Option<ContentHash>,
+}
+
+impl FileWatcher {
+    pub(crate) fn new(paths: impl IntoIterator<Item = impl Into<PathBuf>>) -> FileWatcher {
+        FileWatcher {
+            files: paths
+                .into_iter()
+                .map(|path| WatchedFile {
+                    path: path.into(),
+                    hash: None,
+                })
+                .collect(),
+            has_polled: false,
+        }
+    }
+
+    /// Reads the files, returning what each one has if any of them changed
+    /// since the last poll, which the first poll counts as. Files that can't
+    /// be read, e.g., as they're being replaced, change again once they can.
+    pub(crate) fn poll(&mut self) -> Option<Vec<io::Result<String>>> {
+        let contents: Vec<_> = self
+            .files
+            .iter()
+            .map(|file| fs::read_to_string(&file.path))
+            .collect();
+        let mut has_changed = !self.has_polled;
+        self.has_polled = true;
+
+        for (file, contents) in self.files.iter_mut().zip(&contents) {
+            let hash = contents
+                .as_ref()
+                .ok()
+                .map(|contents| ContentHash::of(contents.as_bytes()));
+
+            if hash != file.hash {
+                file.hash = hash;
+                has_changed = true;
+            }
+        }
+
+        has_changed.then_some(contents)
+    }
+}